@@ -0,0 +1,9 @@
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Use the system protoc when there is one, the vendored binary otherwise
+    if std::env::var_os("PROTOC").is_none() {
+        std::env::set_var("PROTOC", protoc_bin_vendored::protoc_bin_path()?);
+        std::env::set_var("PROTOC_INCLUDE", protoc_bin_vendored::include_path()?);
+    }
+    tonic_build::compile_protos("proto/scheduler.proto")?;
+    Ok(())
+}
@@ -0,0 +1,184 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use serde_json::{json, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+use super::CoordinationStore;
+
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Talks to etcd v3 through its JSON gateway. Every lease and membership is a
+// key attached to an etcd lease, so it goes away with the lease when the
+// holder stops renewing
+pub struct EtcdStore {
+    client: reqwest::Client,
+    endpoints: Vec<String>,
+    // The etcd lease behind each key this instance holds
+    leases: Mutex<HashMap<String, i64>>,
+}
+
+impl EtcdStore {
+    pub fn new(endpoints: Vec<String>) -> Result<Self> {
+        if endpoints.is_empty() {
+            anyhow::bail!("The etcd coordination backend needs at least one endpoint");
+        }
+        
+        Ok(Self {
+            client: reqwest::Client::builder().timeout(REQUEST_TIMEOUT).build()?,
+            endpoints: endpoints.into_iter().map(|e| e.trim_end_matches('/').to_string()).collect(),
+            leases: Mutex::new(HashMap::new()),
+        })
+    }
+    
+    // Tries each endpoint in turn until one answers
+    async fn call(&self, path: &str, body: Value) -> Result<Value> {
+        let mut last_error = None;
+        for endpoint in &self.endpoints {
+            let response = self.client
+                .post(format!("{}{}", endpoint, path))
+                .json(&body)
+                .send()
+                .await;
+            match response {
+                Ok(response) => return Ok(response.error_for_status()?.json().await?),
+                Err(e) => {
+                    warn!("etcd endpoint {} failed: {}", endpoint, e);
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.map(anyhow::Error::from)
+            .unwrap_or_else(|| anyhow::anyhow!("No etcd endpoints configured")))
+    }
+    
+    async fn grant(&self, ttl: Duration) -> Result<i64> {
+        let response = self.call("/v3/lease/grant", json!({ "TTL": ttl.as_secs().max(1) })).await?;
+        int_field(&response["ID"]).ok_or_else(|| anyhow::anyhow!("etcd granted no lease"))
+    }
+    
+    // False once the lease has expired
+    async fn keep_alive(&self, lease: i64) -> Result<bool> {
+        let response = self.call("/v3/lease/keepalive", json!({ "ID": lease.to_string() })).await?;
+        Ok(int_field(&response["result"]["TTL"]).is_some_and(|ttl| ttl > 0))
+    }
+    
+    async fn revoke(&self, lease: i64) {
+        if let Err(e) = self.call("/v3/lease/revoke", json!({ "ID": lease.to_string() })).await {
+            warn!("Failed to revoke etcd lease {}: {}", lease, e);
+        }
+    }
+    
+    // Renews the lease we hold on key, if any; forgets it once it expired
+    async fn renew(&self, key: &str) -> Result<bool> {
+        let Some(lease) = self.leases.lock().unwrap().remove(key) else {
+            return Ok(false);
+        };
+        if self.keep_alive(lease).await? {
+            self.leases.lock().unwrap().insert(key.to_string(), lease);
+            return Ok(true);
+        }
+        Ok(false)
+    }
+}
+
+#[async_trait]
+impl CoordinationStore for EtcdStore {
+    async fn acquire(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        if self.renew(key).await? {
+            return Ok(true);
+        }
+        
+        // Only creates the key when nobody holds it
+        let lease = self.grant(ttl).await?;
+        let response = self.call("/v3/kv/txn", json!({
+            "compare": [{ "key": encode(key), "target": "CREATE", "create_revision": "0" }],
+            "success": [{ "request_put": { "key": encode(key), "value": encode(holder), "lease": lease.to_string() } }],
+        })).await;
+        match response {
+            Ok(response) if response["succeeded"].as_bool() == Some(true) => {
+                self.leases.lock().unwrap().insert(key.to_string(), lease);
+                Ok(true)
+            }
+            result => {
+                self.revoke(lease).await;
+                result.map(|_| false)
+            }
+        }
+    }
+    
+    async fn release(&self, key: &str, holder: &str) -> Result<()> {
+        let lease = self.leases.lock().unwrap().remove(key);
+        self.call("/v3/kv/txn", json!({
+            "compare": [{ "key": encode(key), "target": "VALUE", "value": encode(holder) }],
+            "success": [{ "request_delete_range": { "key": encode(key) } }],
+        })).await?;
+        if let Some(lease) = lease {
+            self.revoke(lease).await;
+        }
+        Ok(())
+    }
+    
+    async fn join(&self, group: &str, member: &str, ttl: Duration) -> Result<()> {
+        let key = member_key(group, member);
+        if self.renew(&key).await? {
+            return Ok(());
+        }
+        
+        let lease = self.grant(ttl).await?;
+        let put = self.call("/v3/kv/put", json!({
+            "key": encode(&key),
+            "value": encode(member),
+            "lease": lease.to_string(),
+        })).await;
+        if let Err(e) = put {
+            self.revoke(lease).await;
+            return Err(e);
+        }
+        self.leases.lock().unwrap().insert(key, lease);
+        Ok(())
+    }
+    
+    async fn leave(&self, group: &str, member: &str) -> Result<()> {
+        let key = member_key(group, member);
+        let lease = self.leases.lock().unwrap().remove(&key);
+        self.call("/v3/kv/deleterange", json!({ "key": encode(&key) })).await?;
+        if let Some(lease) = lease {
+            self.revoke(lease).await;
+        }
+        Ok(())
+    }
+    
+    async fn members(&self, group: &str) -> Result<Vec<String>> {
+        // Every key under "group/", which ends just before "group0"
+        let response = self.call("/v3/kv/range", json!({
+            "key": encode(&format!("{}/", group)),
+            "range_end": encode(&format!("{}0", group)),
+        })).await?;
+        
+        let mut members = Vec::new();
+        for kv in response["kvs"].as_array().into_iter().flatten() {
+            let value = kv["value"].as_str().unwrap_or_default();
+            members.push(String::from_utf8(STANDARD.decode(value)?)?);
+        }
+        Ok(members)
+    }
+}
+
+fn member_key(group: &str, member: &str) -> String {
+    format!("{}/{}", group, member)
+}
+
+// The gateway takes and returns keys and values as base64
+fn encode(value: &str) -> String {
+    STANDARD.encode(value)
+}
+
+// int64 fields come back as JSON strings
+fn int_field(value: &Value) -> Option<i64> {
+    value.as_str().and_then(|s| s.parse().ok()).or_else(|| value.as_i64())
+}
@@ -0,0 +1,63 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::CoordinationStore;
+
+// Leases and memberships in process memory. Nothing is shared, so every
+// instance sees only itself; fine for a single instance and the CLI
+#[derive(Default)]
+pub struct LocalStore {
+    leases: Mutex<HashMap<String, (String, Instant)>>,
+    groups: Mutex<HashMap<String, HashMap<String, Instant>>>,
+}
+
+#[async_trait]
+impl CoordinationStore for LocalStore {
+    async fn acquire(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let now = Instant::now();
+        let mut leases = self.leases.lock().unwrap();
+        match leases.get(key) {
+            Some((current, expires)) if current != holder && *expires > now => Ok(false),
+            _ => {
+                leases.insert(key.to_string(), (holder.to_string(), now + ttl));
+                Ok(true)
+            }
+        }
+    }
+    
+    async fn release(&self, key: &str, holder: &str) -> Result<()> {
+        let mut leases = self.leases.lock().unwrap();
+        if leases.get(key).is_some_and(|(current, _)| current == holder) {
+            leases.remove(key);
+        }
+        Ok(())
+    }
+    
+    async fn join(&self, group: &str, member: &str, ttl: Duration) -> Result<()> {
+        self.groups.lock().unwrap()
+            .entry(group.to_string())
+            .or_default()
+            .insert(member.to_string(), Instant::now() + ttl);
+        Ok(())
+    }
+    
+    async fn leave(&self, group: &str, member: &str) -> Result<()> {
+        if let Some(members) = self.groups.lock().unwrap().get_mut(group) {
+            members.remove(member);
+        }
+        Ok(())
+    }
+    
+    async fn members(&self, group: &str) -> Result<Vec<String>> {
+        let now = Instant::now();
+        let mut groups = self.groups.lock().unwrap();
+        let Some(members) = groups.get_mut(group) else {
+            return Ok(Vec::new());
+        };
+        members.retain(|_, expires| *expires > now);
+        Ok(members.keys().cloned().collect())
+    }
+}
@@ -0,0 +1,130 @@
+pub mod etcd;
+pub mod local;
+pub mod redis;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::{CoordinationBackend, CoordinationConfig};
+use self::etcd::EtcdStore;
+use self::local::LocalStore;
+use self::redis::RedisStore;
+
+// Leases and group memberships that expire unless renewed, kept where every
+// instance can see them
+#[async_trait]
+pub trait CoordinationStore: Send + Sync {
+    // Takes the lease for holder, or extends it if holder already has it;
+    // false while someone else holds it
+    async fn acquire(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool>;
+    // Does nothing unless holder has the lease
+    async fn release(&self, key: &str, holder: &str) -> Result<()>;
+    // Lists member in group until ttl passes without another join
+    async fn join(&self, group: &str, member: &str, ttl: Duration) -> Result<()>;
+    async fn leave(&self, group: &str, member: &str) -> Result<()>;
+    async fn members(&self, group: &str) -> Result<Vec<String>>;
+}
+
+// This instance's handle on the configured backend, shared by leader
+// election, sharding and model retraining
+#[derive(Clone)]
+pub struct Coordinator {
+    backend: Arc<dyn CoordinationStore>,
+    instance_id: String,
+    key_prefix: String,
+}
+
+// Held until released or its TTL runs out, which bounds how long a crashed
+// holder can block everyone else
+pub struct LockGuard {
+    coordinator: Coordinator,
+    name: String,
+}
+
+impl Coordinator {
+    pub fn from_config(config: &CoordinationConfig) -> Result<Self> {
+        let backend: Arc<dyn CoordinationStore> = match config.backend {
+            CoordinationBackend::Local => Arc::new(LocalStore::default()),
+            CoordinationBackend::Redis => {
+                let url = config.endpoints.first()
+                    .ok_or_else(|| anyhow::anyhow!("The redis coordination backend needs an endpoint"))?;
+                Arc::new(RedisStore::new(url)?)
+            }
+            CoordinationBackend::Etcd => Arc::new(EtcdStore::new(config.endpoints.clone())?),
+        };
+        let instance_id = config.instance_id.clone().unwrap_or_else(|| {
+            let host = std::env::var("HOSTNAME").unwrap_or_else(|_| "instance".to_string());
+            format!("{}-{}", host, &Uuid::new_v4().to_string()[..8])
+        });
+        
+        Ok(Self {
+            backend,
+            instance_id,
+            key_prefix: config.key_prefix.clone(),
+        })
+    }
+    
+    pub fn instance_id(&self) -> &str {
+        &self.instance_id
+    }
+    
+    pub async fn acquire_lease(&self, name: &str, ttl: Duration) -> Result<bool> {
+        self.backend.acquire(&self.key(name), &self.instance_id, ttl).await
+    }
+    
+    pub async fn release_lease(&self, name: &str) -> Result<()> {
+        self.backend.release(&self.key(name), &self.instance_id).await
+    }
+    
+    // None while another instance holds the lock. Re-entrant within this
+    // instance, as the lock is held per instance rather than per task
+    pub async fn try_lock(&self, name: &str, ttl: Duration) -> Result<Option<LockGuard>> {
+        let acquired = self.acquire_lease(name, ttl).await?;
+        Ok(acquired.then(|| LockGuard {
+            coordinator: self.clone(),
+            name: name.to_string(),
+        }))
+    }
+    
+    pub async fn join(&self, group: &str, ttl: Duration) -> Result<()> {
+        self.backend.join(&self.group_key(group), &self.instance_id, ttl).await
+    }
+    
+    pub async fn leave(&self, group: &str) -> Result<()> {
+        self.backend.leave(&self.group_key(group), &self.instance_id).await
+    }
+    
+    pub async fn members(&self, group: &str) -> Result<Vec<String>> {
+        self.backend.members(&self.group_key(group)).await
+    }
+    
+    fn key(&self, name: &str) -> String {
+        if self.key_prefix.is_empty() {
+            name.to_string()
+        } else {
+            format!("{}/{}", self.key_prefix, name)
+        }
+    }
+    
+    fn group_key(&self, group: &str) -> String {
+        self.key(&format!("members/{}", group))
+    }
+}
+
+impl LockGuard {
+    // Extends the lock by ttl; false once it lapsed and someone else took it
+    pub async fn renew(&self, ttl: Duration) -> Result<bool> {
+        self.coordinator.acquire_lease(&self.name, ttl).await
+    }
+    
+    // A failed release leaves the lock to expire with its TTL
+    pub async fn release(self) {
+        if let Err(e) = self.coordinator.release_lease(&self.name).await {
+            warn!("Failed to release lock {}: {}", self.name, e);
+        }
+    }
+}
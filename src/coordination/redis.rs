@@ -0,0 +1,141 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use redis::aio::MultiplexedConnection;
+use redis::{RedisResult, Script};
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use super::CoordinationStore;
+
+// Extends the lease if the holder has it, takes it if nobody does
+const ACQUIRE_SCRIPT: &str = r#"
+local current = redis.call('GET', KEYS[1])
+if current == ARGV[1] then
+    return redis.call('PEXPIRE', KEYS[1], ARGV[2])
+end
+if not current then
+    redis.call('SET', KEYS[1], ARGV[1], 'PX', ARGV[2])
+    return 1
+end
+return 0
+"#;
+
+const RELEASE_SCRIPT: &str = r#"
+if redis.call('GET', KEYS[1]) == ARGV[1] then
+    return redis.call('DEL', KEYS[1])
+end
+return 0
+"#;
+
+// Groups are sorted sets scored by each member's expiry, on the Redis
+// server's clock so members' clocks don't have to agree
+const JOIN_SCRIPT: &str = r#"
+local now = redis.call('TIME')
+local now_ms = tonumber(now[1]) * 1000 + math.floor(tonumber(now[2]) / 1000)
+return redis.call('ZADD', KEYS[1], now_ms + tonumber(ARGV[2]), ARGV[1])
+"#;
+
+const MEMBERS_SCRIPT: &str = r#"
+local now = redis.call('TIME')
+local now_ms = tonumber(now[1]) * 1000 + math.floor(tonumber(now[2]) / 1000)
+redis.call('ZREMRANGEBYSCORE', KEYS[1], '-inf', now_ms)
+return redis.call('ZRANGE', KEYS[1], 0, -1)
+"#;
+
+pub struct RedisStore {
+    client: redis::Client,
+    connection: Mutex<Option<MultiplexedConnection>>,
+    acquire: Script,
+    release: Script,
+    join: Script,
+    members: Script,
+}
+
+impl RedisStore {
+    pub fn new(url: &str) -> Result<Self> {
+        Ok(Self {
+            client: redis::Client::open(url)?,
+            connection: Mutex::new(None),
+            acquire: Script::new(ACQUIRE_SCRIPT),
+            release: Script::new(RELEASE_SCRIPT),
+            join: Script::new(JOIN_SCRIPT),
+            members: Script::new(MEMBERS_SCRIPT),
+        })
+    }
+    
+    async fn connection(&self) -> Result<MultiplexedConnection> {
+        let mut connection = self.connection.lock().await;
+        if let Some(conn) = connection.as_ref() {
+            return Ok(conn.clone());
+        }
+        
+        let conn = self.client.get_multiplexed_async_connection().await?;
+        *connection = Some(conn.clone());
+        Ok(conn)
+    }
+    
+    // Reconnects on the next call after a failure
+    async fn checked<T>(&self, result: RedisResult<T>) -> Result<T> {
+        if result.is_err() {
+            *self.connection.lock().await = None;
+        }
+        Ok(result?)
+    }
+}
+
+#[async_trait]
+impl CoordinationStore for RedisStore {
+    async fn acquire(&self, key: &str, holder: &str, ttl: Duration) -> Result<bool> {
+        let mut conn = self.connection().await?;
+        let result = self.acquire
+            .key(key)
+            .arg(holder)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async::<_, i64>(&mut conn)
+            .await;
+        Ok(self.checked(result).await? == 1)
+    }
+    
+    async fn release(&self, key: &str, holder: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+        let result = self.release
+            .key(key)
+            .arg(holder)
+            .invoke_async::<_, i64>(&mut conn)
+            .await;
+        self.checked(result).await?;
+        Ok(())
+    }
+    
+    async fn join(&self, group: &str, member: &str, ttl: Duration) -> Result<()> {
+        let mut conn = self.connection().await?;
+        let result = self.join
+            .key(group)
+            .arg(member)
+            .arg(ttl.as_millis() as u64)
+            .invoke_async::<_, i64>(&mut conn)
+            .await;
+        self.checked(result).await?;
+        Ok(())
+    }
+    
+    async fn leave(&self, group: &str, member: &str) -> Result<()> {
+        let mut conn = self.connection().await?;
+        let result = redis::cmd("ZREM")
+            .arg(group)
+            .arg(member)
+            .query_async::<_, i64>(&mut conn)
+            .await;
+        self.checked(result).await?;
+        Ok(())
+    }
+    
+    async fn members(&self, group: &str) -> Result<Vec<String>> {
+        let mut conn = self.connection().await?;
+        let result = self.members
+            .key(group)
+            .invoke_async::<_, Vec<String>>(&mut conn)
+            .await;
+        self.checked(result).await
+    }
+}
@@ -0,0 +1,92 @@
+use anyhow::{Context, Result};
+use pprof::protos::Message;
+use serde::Deserialize;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::Mutex;
+
+use crate::config::DiagnosticsConfig;
+
+// jemalloc samples an allocation roughly every 512 KiB, cheap enough to
+// leave on so a heap profile can be dumped whenever memory grows
+#[global_allocator]
+static ALLOCATOR: tikv_jemallocator::Jemalloc = tikv_jemallocator::Jemalloc;
+
+#[allow(non_upper_case_globals)]
+#[export_name = "malloc_conf"]
+pub static malloc_conf: &[u8] = b"prof:true,prof_active:true,lg_prof_sample:19\0";
+
+// The sampler is process-wide, so one CPU profile at a time
+static CPU_PROFILE: Mutex<()> = Mutex::const_new(());
+
+// Samples per second; off a round number so it doesn't beat with timers
+const CPU_FREQUENCY: i32 = 99;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileFormat {
+    // Protobuf for `go tool pprof`
+    #[default]
+    Pprof,
+    Flamegraph,
+}
+
+impl ProfileFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Pprof => "application/octet-stream",
+            Self::Flamegraph => "image/svg+xml",
+        }
+    }
+    
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Pprof => "pb",
+            Self::Flamegraph => "svg",
+        }
+    }
+}
+
+// Feeds tokio-console; the layer serves it from a thread of its own
+pub fn console_layer(config: &DiagnosticsConfig) -> Result<console_subscriber::ConsoleLayer> {
+    let addr: SocketAddr = config.console_addr.parse()
+        .with_context(|| format!("Invalid diagnostics.console_addr {}", config.console_addr))?;
+    Ok(console_subscriber::ConsoleLayer::builder().server_addr(addr).spawn())
+}
+
+// Samples every thread's stack for duration. None while another profile
+// is being taken
+pub async fn cpu_profile(duration: Duration, format: ProfileFormat) -> Result<Option<Vec<u8>>> {
+    let Ok(_running) = CPU_PROFILE.try_lock() else {
+        return Ok(None);
+    };
+    
+    // The guard has to stay on one thread while it samples
+    let profile = tokio::task::spawn_blocking(move || -> Result<Vec<u8>> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(CPU_FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()?;
+        std::thread::sleep(duration);
+        
+        let report = guard.report().build()?;
+        match format {
+            ProfileFormat::Pprof => Ok(report.pprof()?.encode_to_vec()),
+            ProfileFormat::Flamegraph => {
+                let mut svg = Vec::new();
+                report.flamegraph(&mut svg)?;
+                Ok(svg)
+            }
+        }
+    }).await??;
+    Ok(Some(profile))
+}
+
+// Live allocations from jemalloc's samples, as pprof protobuf
+pub async fn heap_profile() -> Result<Vec<u8>> {
+    let ctl = jemalloc_pprof::PROF_CTL.as_ref()
+        .context("jemalloc heap profiling is unavailable")?;
+    let mut ctl = ctl.lock().await;
+    anyhow::ensure!(ctl.activated(), "jemalloc heap profiling is not active");
+    ctl.dump_pprof()
+}
@@ -1,4 +1,11 @@
+use rand::Rng;
+use rdkafka::error::{KafkaError, RDKafkaErrorCode};
+use reqwest::header::{HeaderMap, RETRY_AFTER};
+use std::fmt::Display;
+use std::future::Future;
+use std::time::{Duration, Instant};
 use thiserror::Error;
+use tracing::warn;
 
 #[derive(Error, Debug)]
 pub enum OpenStackError {
@@ -9,6 +16,8 @@ pub enum OpenStackError {
     ApiError {
         status: u16,
         message: String,
+        // From the Retry-After header
+        retry_after: Option<Duration>,
     },
     
     #[error("Service unavailable: {0}")]
@@ -16,6 +25,10 @@ pub enum OpenStackError {
     
     #[error("Invalid configuration: {0}")]
     ConfigError(String),
+    
+    // The breaker for this API is open after repeated server errors
+    #[error("Circuit breaker open for the {0} API")]
+    CircuitOpen(String),
 }
 
 #[derive(Error, Debug)]
@@ -24,7 +37,7 @@ pub enum MetricsError {
     CollectionError(String),
     
     #[error("Kafka producer error: {0}")]
-    KafkaError(String),
+    KafkaError(#[from] KafkaError),
     
     #[error("Processing error: {0}")]
     ProcessingError(String),
@@ -50,6 +63,277 @@ pub enum SchedulerError {
     #[error("Resource placement failed: {0}")]
     PlacementError(String),
     
+    #[error("Migration failed: {0}")]
+    MigrationFailed(String),
+    
     #[error("SLA violation: {0}")]
     SLAViolation(String),
+    
+    #[error("Scheduling policy error: {0}")]
+    PolicyError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum StorageError {
+    #[error("Invalid storage key: {0}")]
+    InvalidKey(String),
+    
+    #[error("Storage backend error: {0}")]
+    BackendError(String),
+}
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("Cannot read config file {path}: {message}")]
+    Unreadable { path: String, message: String },
+    
+    #[error("Invalid configuration:\n  {}", .0.join("\n  "))]
+    Invalid(Vec<String>),
+}
+
+// Handing a notification to a webhook, chat or mail service
+#[derive(Error, Debug)]
+pub enum DeliveryError {
+    // Retrying would get the same answer
+    #[error("{0}")]
+    Permanent(String),
+    
+    #[error("{message}")]
+    Transient {
+        message: String,
+        retry_after: Option<Duration>,
+    },
+}
+
+// Whether an operation that failed this way is worth trying again
+pub trait Retryable {
+    fn is_retryable(&self) -> bool;
+    
+    // How long the other side asked us to wait, if it did
+    fn retry_after(&self) -> Option<Duration> {
+        None
+    }
+}
+
+impl OpenStackError {
+    pub async fn from_response(response: reqwest::Response) -> Self {
+        let status = response.status().as_u16();
+        let retry_after = retry_after_header(response.headers());
+        Self::ApiError {
+            status,
+            message: response.text().await.unwrap_or_default(),
+            retry_after,
+        }
+    }
+}
+
+impl Retryable for OpenStackError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::ApiError { status, .. } => is_retryable_status(*status),
+            Self::ServiceUnavailable(_) => true,
+            Self::AuthError(_) | Self::ConfigError(_) | Self::CircuitOpen(_) => false,
+        }
+    }
+    
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::ApiError { retry_after, .. } => *retry_after,
+            _ => None,
+        }
+    }
+}
+
+impl Retryable for MetricsError {
+    fn is_retryable(&self) -> bool {
+        match self {
+            Self::KafkaError(e) => e.is_retryable(),
+            Self::CollectionError(_) | Self::ProcessingError(_) => false,
+        }
+    }
+}
+
+impl DeliveryError {
+    // Client errors other than timeouts and throttling won't succeed on retry
+    pub fn from_status(status: u16, headers: &HeaderMap) -> Self {
+        if is_retryable_status(status) {
+            Self::Transient {
+                message: format!("status {}", status),
+                retry_after: retry_after_header(headers),
+            }
+        } else {
+            Self::Permanent(format!("rejected with status {}", status))
+        }
+    }
+}
+
+impl From<reqwest::Error> for DeliveryError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_retryable() {
+            Self::Transient { message: e.to_string(), retry_after: None }
+        } else {
+            Self::Permanent(e.to_string())
+        }
+    }
+}
+
+impl Retryable for DeliveryError {
+    fn is_retryable(&self) -> bool {
+        matches!(self, Self::Transient { .. })
+    }
+    
+    fn retry_after(&self) -> Option<Duration> {
+        match self {
+            Self::Transient { retry_after, .. } => *retry_after,
+            Self::Permanent(_) => None,
+        }
+    }
+}
+
+// Broker failover and a full local queue clear up on their own; anything
+// else, like an oversized message or an unknown topic, won't
+impl Retryable for KafkaError {
+    fn is_retryable(&self) -> bool {
+        matches!(
+            self.rdkafka_error_code(),
+            Some(
+                RDKafkaErrorCode::QueueFull
+                    | RDKafkaErrorCode::MessageTimedOut
+                    | RDKafkaErrorCode::RequestTimedOut
+                    | RDKafkaErrorCode::BrokerTransportFailure
+                    | RDKafkaErrorCode::AllBrokersDown
+                    | RDKafkaErrorCode::NetworkException
+                    | RDKafkaErrorCode::LeaderNotAvailable
+                    | RDKafkaErrorCode::NotLeaderForPartition
+                    | RDKafkaErrorCode::NotEnoughReplicas
+                    | RDKafkaErrorCode::NotEnoughReplicasAfterAppend
+            )
+        )
+    }
+}
+
+// Requests that never got an answer, or got one worth retrying
+impl Retryable for reqwest::Error {
+    fn is_retryable(&self) -> bool {
+        self.is_timeout()
+            || self.is_connect()
+            || self.is_request()
+            || self.status().is_some_and(|status| is_retryable_status(status.as_u16()))
+    }
+}
+
+// Classifies by the error underneath; anything unrecognized is permanent
+impl Retryable for anyhow::Error {
+    fn is_retryable(&self) -> bool {
+        classify(self).is_some_and(|e| e.is_retryable())
+    }
+    
+    fn retry_after(&self) -> Option<Duration> {
+        classify(self).and_then(|e| e.retry_after())
+    }
+}
+
+fn classify(error: &anyhow::Error) -> Option<&dyn Retryable> {
+    if let Some(e) = error.downcast_ref::<OpenStackError>() {
+        return Some(e);
+    }
+    if let Some(e) = error.downcast_ref::<MetricsError>() {
+        return Some(e);
+    }
+    if let Some(e) = error.downcast_ref::<DeliveryError>() {
+        return Some(e);
+    }
+    if let Some(e) = error.downcast_ref::<KafkaError>() {
+        return Some(e);
+    }
+    error.downcast_ref::<reqwest::Error>().map(|e| e as &dyn Retryable)
+}
+
+pub fn is_retryable_status(status: u16) -> bool {
+    status == 408 || status == 429 || status >= 500
+}
+
+// Only the delay-seconds form; the services we call don't send HTTP dates
+pub fn retry_after_header(headers: &HeaderMap) -> Option<Duration> {
+    let seconds = headers.get(RETRY_AFTER)?.to_str().ok()?.trim().parse().ok()?;
+    Some(Duration::from_secs(seconds))
+}
+
+// Exponential backoff with jitter, shared by everything that talks to
+// another service so they all back off the same way
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    // Including the first
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    // Gives up rather than sleep past this much time since the first attempt
+    pub deadline: Option<Duration>,
+}
+
+impl RetryPolicy {
+    pub fn new(max_attempts: u32, initial_backoff: Duration) -> Self {
+        Self {
+            max_attempts: max_attempts.max(1),
+            initial_backoff,
+            max_backoff: Duration::from_secs(60),
+            deadline: None,
+        }
+    }
+    
+    // A single attempt, for operations that aren't safe to repeat
+    pub fn once() -> Self {
+        Self::new(1, Duration::ZERO)
+    }
+    
+    pub fn with_max_backoff(mut self, max_backoff: Duration) -> Self {
+        self.max_backoff = max_backoff;
+        self
+    }
+    
+    pub fn with_deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+    
+    // Runs op until it succeeds, fails with an error that isn't retryable, or
+    // runs out of attempts or time, returning the last error. A Retry-After
+    // from the other side takes the place of the backoff.
+    pub async fn run<T, E, F, Fut>(&self, what: &str, mut op: F) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+        E: Retryable + Display,
+    {
+        let started = Instant::now();
+        let mut attempt = 1;
+        
+        loop {
+            let error = match op().await {
+                Ok(value) => return Ok(value),
+                Err(e) => e,
+            };
+            if !error.is_retryable() || attempt >= self.max_attempts {
+                return Err(error);
+            }
+            
+            let delay = error.retry_after().unwrap_or_else(|| self.backoff(attempt));
+            if self.deadline.is_some_and(|deadline| started.elapsed() + delay > deadline) {
+                return Err(error);
+            }
+            
+            warn!("{} failed ({}), retrying in {:?}", what, error, delay);
+            tokio::time::sleep(delay).await;
+            attempt += 1;
+        }
+    }
+    
+    // Up to half of each delay is taken off at random, so clients that
+    // failed together don't all retry together
+    fn backoff(&self, attempt: u32) -> Duration {
+        let exponential = self.initial_backoff
+            .saturating_mul(2u32.saturating_pow(attempt - 1))
+            .min(self.max_backoff);
+        exponential.mul_f64(1.0 - rand::thread_rng().gen_range(0.0..0.5))
+    }
 }
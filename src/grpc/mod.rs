@@ -0,0 +1,7 @@
+pub mod service;
+
+pub mod proto {
+    tonic::include_proto!("openstack.scheduler.v1");
+}
+
+pub use service::SchedulerGrpcService;
@@ -0,0 +1,419 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use futures_util::{stream, Stream};
+use std::collections::HashSet;
+use std::pin::Pin;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tonic::{Code, Request, Response, Status};
+use tracing::info;
+
+use crate::config::{ApiRole, GrpcConfig};
+use crate::metrics::agent::{AgentReport, BalloonStats, DiskStats, DomainStats};
+use crate::metrics::MetricsCollector;
+use crate::ml::MLEngine;
+use crate::openstack::services::{InterfaceTraffic, ServerMetrics};
+use crate::scheduler::explain::DecisionExplanation;
+use crate::scheduler::sla_manager::{PenaltyModel, PenaltyTier, SLAPolicy, SLAPriority};
+use crate::scheduler::ResourceScheduler;
+use crate::web::audit::{AuditEntry, AuditInterface, AuditLog, AuditOutcome};
+use crate::web::auth::{Authenticator, Principal};
+use super::proto::{
+    self,
+    manage_sla_policy_request::Operation,
+    scheduler_server::{Scheduler, SchedulerServer},
+};
+
+const DEFAULT_DECISION_LIMIT: usize = 50;
+// How far back per-resource decision lookups search the journal
+const RESOURCE_DECISION_SCAN: usize = 500;
+
+// gRPC face of the scheduler for other services, alongside the JSON API
+#[derive(Clone)]
+pub struct SchedulerGrpcService {
+    ml_engine: Arc<MLEngine>,
+    metrics_collector: Arc<MetricsCollector>,
+    scheduler: Arc<ResourceScheduler>,
+    auth: Arc<Authenticator>,
+    audit: Arc<AuditLog>,
+}
+
+impl SchedulerGrpcService {
+    pub fn new(
+        ml_engine: Arc<MLEngine>,
+        metrics_collector: Arc<MetricsCollector>,
+        scheduler: Arc<ResourceScheduler>,
+        auth: Arc<Authenticator>,
+        audit: Arc<AuditLog>,
+    ) -> Self {
+        Self {
+            ml_engine,
+            metrics_collector,
+            scheduler,
+            auth,
+            audit,
+        }
+    }
+    
+    pub async fn serve(self, config: &GrpcConfig, shutdown: CancellationToken) -> Result<()> {
+        let address = ([0, 0, 0, 0], config.port).into();
+        info!("gRPC API listening on {}", address);
+        
+        tonic::transport::Server::builder()
+            .add_service(SchedulerServer::new(self))
+            .serve_with_shutdown(address, shutdown.cancelled_owned())
+            .await?;
+        info!("gRPC API stopped");
+        Ok(())
+    }
+    
+    fn principal<T>(&self, request: &Request<T>) -> Option<Principal> {
+        let metadata = request.metadata();
+        match metadata.get("authorization").and_then(|v| v.to_str().ok()) {
+            Some(value) => value.strip_prefix("Bearer ").and_then(|token| self.auth.authenticate_bearer(token)),
+            None => metadata.get("x-api-key")
+                .and_then(|v| v.to_str().ok())
+                .and_then(|key| self.auth.api_key(key.trim())),
+        }
+    }
+    
+    // Same rules as the HTTP middleware: with auth on, reads need any
+    // principal and changes need an operator; with it off, changes still do
+    fn authorize<T>(&self, request: &Request<T>, write: bool) -> Result<(), Denied> {
+        match self.principal(request) {
+            Some(principal) if write && principal.role != ApiRole::Operator => Err(Denied::NotOperator),
+            Some(_) => Ok(()),
+            None if write || self.auth.is_enabled() => Err(Denied::Unauthenticated),
+            None => Ok(()),
+        }
+    }
+    
+    async fn apply_sla_operation(&self, operation: Option<Operation>) -> Result<Vec<SLAPolicy>, Status> {
+        let policies = match operation {
+            Some(Operation::Get(get)) => match self.scheduler.get_sla_policy(&get.resource_id).await {
+                Some(policy) => vec![policy],
+                None => return Err(Status::not_found(format!("No SLA policy for {}", get.resource_id))),
+            },
+            Some(Operation::List(_)) => self.scheduler.list_sla_policies().await,
+            Some(Operation::Put(policy)) => {
+                let policy = self.scheduler.put_sla_policy(sla_policy_from_proto(policy)).await
+                    .map_err(|e| Status::invalid_argument(e.to_string()))?;
+                vec![policy]
+            }
+            Some(Operation::Delete(delete)) => {
+                match self.scheduler.delete_sla_policy(&delete.resource_id).await {
+                    Ok(true) => Vec::new(),
+                    Ok(false) => return Err(Status::not_found(format!("No SLA policy for {}", delete.resource_id))),
+                    Err(e) => return Err(Status::internal(e.to_string())),
+                }
+            }
+            None => return Err(Status::invalid_argument("operation is required")),
+        };
+        Ok(policies)
+    }
+}
+
+enum Denied {
+    Unauthenticated,
+    NotOperator,
+}
+
+impl From<Denied> for Status {
+    fn from(denied: Denied) -> Self {
+        match denied {
+            Denied::Unauthenticated => Status::unauthenticated("Authentication required"),
+            Denied::NotOperator => Status::permission_denied("Operator role required"),
+        }
+    }
+}
+
+type MetricStream = Pin<Box<dyn Stream<Item = Result<proto::MetricSample, Status>> + Send>>;
+
+#[tonic::async_trait]
+impl Scheduler for SchedulerGrpcService {
+    async fn get_prediction(
+        &self,
+        request: Request<proto::GetPredictionRequest>,
+    ) -> Result<Response<proto::Prediction>, Status> {
+        self.authorize(&request, false)?;
+        let resource_id = request.into_inner().resource_id;
+        
+        let predicted_load = self.ml_engine.get_resource_prediction(&resource_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let confidence = self.ml_engine.get_prediction_confidence(&resource_id).await;
+        let forecast = self.ml_engine.get_resource_forecast(&resource_id).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        
+        Ok(Response::new(proto::Prediction {
+            resource_id,
+            predicted_load,
+            confidence,
+            forecast_step_minutes: forecast.as_ref().map(|f| f.step_minutes).unwrap_or(0),
+            forecast: forecast.map(|f| f.values).unwrap_or_default(),
+        }))
+    }
+    
+    type StreamMetricsStream = MetricStream;
+    
+    async fn stream_metrics(
+        &self,
+        request: Request<proto::StreamMetricsRequest>,
+    ) -> Result<Response<Self::StreamMetricsStream>, Status> {
+        self.authorize(&request, false)?;
+        let wanted: HashSet<String> = request.into_inner().resource_ids.into_iter().collect();
+        let receiver = self.metrics_collector.subscribe_samples();
+        
+        // Slow consumers skip the samples they missed rather than failing
+        let samples = stream::unfold((receiver, wanted), |(mut receiver, wanted)| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(metrics) if wanted.is_empty() || wanted.contains(&metrics.server_id) => {
+                        return Some((Ok(metric_sample(metrics)), (receiver, wanted)));
+                    }
+                    Ok(_) | Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        });
+        Ok(Response::new(Box::pin(samples)))
+    }
+    
+    async fn list_decisions(
+        &self,
+        request: Request<proto::ListDecisionsRequest>,
+    ) -> Result<Response<proto::ListDecisionsResponse>, Status> {
+        self.authorize(&request, false)?;
+        let request = request.into_inner();
+        let limit = match request.limit {
+            0 => DEFAULT_DECISION_LIMIT,
+            limit => limit as usize,
+        };
+        
+        let scan = if request.resource_id.is_some() { RESOURCE_DECISION_SCAN.max(limit) } else { limit };
+        let decisions = self.scheduler.recent_decisions(scan).await
+            .map_err(|e| Status::internal(e.to_string()))?;
+        let decisions = decisions.into_iter()
+            .filter(|d| request.resource_id.as_ref().is_none_or(|id| *id == d.resource_id))
+            .take(limit)
+            .map(decision)
+            .collect();
+        
+        Ok(Response::new(proto::ListDecisionsResponse { decisions }))
+    }
+    
+    async fn manage_sla_policy(
+        &self,
+        request: Request<proto::ManageSlaPolicyRequest>,
+    ) -> Result<Response<proto::ManageSlaPolicyResponse>, Status> {
+        let write = matches!(request.get_ref().operation, Some(Operation::Put(_)) | Some(Operation::Delete(_)));
+        let authorized = self.authorize(&request, write);
+        let principal = self.principal(&request);
+        let client_address = request.remote_addr();
+        let operation = request.into_inner().operation;
+        
+        // Changes are audited whether or not they were allowed
+        let audited = match &operation {
+            Some(Operation::Put(policy)) => Some((
+                "ManageSlaPolicy.Put",
+                policy.resource_id.clone(),
+                serde_json::to_value(sla_policy_from_proto(policy.clone())).ok(),
+            )),
+            Some(Operation::Delete(delete)) => Some(("ManageSlaPolicy.Delete", delete.resource_id.clone(), None)),
+            _ => None,
+        };
+        
+        let result = match authorized {
+            Ok(()) => self.apply_sla_operation(operation).await,
+            Err(denied) => Err(denied.into()),
+        };
+        
+        if let Some((action, target, payload)) = audited {
+            let mut entry = AuditEntry::new(AuditInterface::Grpc, action.to_string(), target, principal.as_ref());
+            entry.client_address = client_address.map(|address| address.ip().to_string());
+            entry.request = payload;
+            let code = result.as_ref().err().map(Status::code).unwrap_or(Code::Ok);
+            entry.status = format!("{:?}", code);
+            entry.outcome = match code {
+                Code::Ok => AuditOutcome::Success,
+                Code::Unauthenticated | Code::PermissionDenied => AuditOutcome::Denied,
+                _ => AuditOutcome::Failed,
+            };
+            entry.error = result.as_ref().err().map(|status| status.message().to_string());
+            self.audit.record(entry).await;
+        }
+        
+        Ok(Response::new(proto::ManageSlaPolicyResponse {
+            policies: result?.into_iter().map(sla_policy_to_proto).collect(),
+        }))
+    }
+    
+    // Not audited, as over HTTP
+    async fn ingest_agent_report(
+        &self,
+        request: Request<proto::AgentReport>,
+    ) -> Result<Response<proto::IngestAgentReportResponse>, Status> {
+        self.authorize(&request, true)?;
+        if !self.metrics_collector.accepts_agent_reports() {
+            return Err(Status::failed_precondition("Agent ingestion is disabled"));
+        }
+        let report = agent_report_from_proto(request.into_inner())?;
+        let result = self.metrics_collector.ingest_agent_report(report).await
+            .map_err(|e| Status::invalid_argument(e.to_string()))?;
+        
+        Ok(Response::new(proto::IngestAgentReportResponse {
+            recorded: result.recorded as u32,
+            baseline: result.baseline as u32,
+            stale: result.stale as u32,
+            not_owned: result.not_owned as u32,
+        }))
+    }
+}
+
+fn timestamp(at: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: at.timestamp(),
+        nanos: at.timestamp_subsec_nanos() as i32,
+    }
+}
+
+fn metric_sample(metrics: ServerMetrics) -> proto::MetricSample {
+    proto::MetricSample {
+        resource_id: metrics.server_id,
+        cpu_utilization: metrics.cpu_utilization,
+        memory_usage: metrics.memory_usage,
+        memory_total: metrics.memory_total,
+        disk_read_bytes: metrics.disk_read_bytes,
+        disk_write_bytes: metrics.disk_write_bytes,
+        network_rx_bytes: metrics.network_rx_bytes,
+        network_tx_bytes: metrics.network_tx_bytes,
+        timestamp: Some(timestamp(metrics.timestamp)),
+    }
+}
+
+fn decision(explanation: DecisionExplanation) -> proto::Decision {
+    proto::Decision {
+        id: explanation.decision_id.clone(),
+        resource_id: explanation.resource_id.clone(),
+        action: explanation.action.clone(),
+        summary: explanation.rationale.summary.clone(),
+        status: explanation.outcome.status().to_string(),
+        requested_by: explanation.rationale.requested_by.clone(),
+        explanation_json: serde_json::to_string(&explanation).unwrap_or_default(),
+        created_at: Some(timestamp(explanation.created_at)),
+        updated_at: Some(timestamp(explanation.updated_at)),
+    }
+}
+
+fn sla_policy_to_proto(policy: SLAPolicy) -> proto::SlaPolicy {
+    let priority = match policy.priority {
+        SLAPriority::Critical => proto::SlaPriority::Critical,
+        SLAPriority::High => proto::SlaPriority::High,
+        SLAPriority::Medium => proto::SlaPriority::Medium,
+        SLAPriority::Low => proto::SlaPriority::Low,
+    };
+    
+    proto::SlaPolicy {
+        resource_id: policy.resource_id,
+        max_cpu_utilization: policy.max_cpu_utilization,
+        max_memory_utilization: policy.max_memory_utilization,
+        max_response_time_ms: policy.max_response_time_ms,
+        min_availability_percent: policy.min_availability_percent,
+        priority: priority as i32,
+        deadline_minutes: policy.deadline_minutes,
+        penalty_tiers: policy.penalty
+            .map(|penalty| {
+                penalty.tiers.into_iter()
+                    .map(|tier| proto::PenaltyTier {
+                        min_severity: tier.min_severity,
+                        cost_per_minute: tier.cost_per_minute,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default(),
+        collection_interval_seconds: policy.collection_interval_seconds,
+    }
+}
+
+// Unset priorities default to medium; validation happens in put_sla_policy
+fn sla_policy_from_proto(policy: proto::SlaPolicy) -> SLAPolicy {
+    let priority = match proto::SlaPriority::try_from(policy.priority) {
+        Ok(proto::SlaPriority::Critical) => SLAPriority::Critical,
+        Ok(proto::SlaPriority::High) => SLAPriority::High,
+        Ok(proto::SlaPriority::Low) => SLAPriority::Low,
+        _ => SLAPriority::Medium,
+    };
+    
+    SLAPolicy {
+        resource_id: policy.resource_id,
+        max_cpu_utilization: policy.max_cpu_utilization,
+        max_memory_utilization: policy.max_memory_utilization,
+        max_response_time_ms: policy.max_response_time_ms,
+        min_availability_percent: policy.min_availability_percent,
+        priority,
+        deadline_minutes: policy.deadline_minutes,
+        penalty: (!policy.penalty_tiers.is_empty()).then(|| PenaltyModel {
+            tiers: policy.penalty_tiers.into_iter()
+                .map(|tier| PenaltyTier {
+                    min_severity: tier.min_severity,
+                    cost_per_minute: tier.cost_per_minute,
+                })
+                .collect(),
+        }),
+        collection_interval_seconds: policy.collection_interval_seconds,
+    }
+}
+
+fn agent_report_from_proto(report: proto::AgentReport) -> Result<AgentReport, Status> {
+    let timestamp = report.timestamp
+        .and_then(|at| DateTime::from_timestamp(at.seconds, at.nanos.max(0) as u32))
+        .ok_or_else(|| Status::invalid_argument("timestamp is required"))?;
+    
+    Ok(AgentReport {
+        hypervisor: report.hypervisor,
+        timestamp,
+        domains: report.domains.into_iter()
+            .map(|domain| {
+                let balloon = domain.balloon.unwrap_or_default();
+                DomainStats {
+                    server_id: domain.server_id,
+                    vcpus: domain.vcpus,
+                    cpu_time_ns: domain.cpu_time_ns,
+                    cpu_steal_ns: domain.cpu_steal_ns,
+                    balloon: BalloonStats {
+                        current_kib: balloon.current_kib,
+                        maximum_kib: balloon.maximum_kib,
+                        available_kib: balloon.available_kib,
+                        unused_kib: balloon.unused_kib,
+                        rss_kib: balloon.rss_kib,
+                        swap_in_kib: balloon.swap_in_kib,
+                        swap_out_kib: balloon.swap_out_kib,
+                        major_faults: balloon.major_faults,
+                    },
+                    disks: domain.disks.into_iter()
+                        .map(|disk| DiskStats {
+                            device: disk.device,
+                            read_bytes: disk.read_bytes,
+                            write_bytes: disk.write_bytes,
+                            read_requests: disk.read_requests,
+                            write_requests: disk.write_requests,
+                            read_time_ns: disk.read_time_ns,
+                            write_time_ns: disk.write_time_ns,
+                        })
+                        .collect(),
+                    interfaces: domain.interfaces.into_iter()
+                        .map(|interface| InterfaceTraffic {
+                            mac_address: interface.mac_address,
+                            rx_bytes: interface.rx_bytes,
+                            tx_bytes: interface.tx_bytes,
+                            rx_packets: interface.rx_packets,
+                            tx_packets: interface.tx_packets,
+                            rx_dropped: interface.rx_dropped,
+                            tx_dropped: interface.tx_dropped,
+                        })
+                        .collect(),
+                }
+            })
+            .collect(),
+    })
+}
@@ -1,7 +1,12 @@
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
+use chrono::{Duration as ChronoDuration, Utc};
+use metrics_exporter_prometheus::PrometheusBuilder;
+use serde::Serialize;
 use std::sync::Arc;
-use tokio::signal;
+use std::time::Duration;
+use tokio::signal::unix::{signal, SignalKind};
+use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
 mod openstack;
@@ -9,54 +14,165 @@ mod metrics;
 mod ml;
 mod scheduler;
 mod config;
+mod coordination;
+#[cfg(feature = "diagnostics")]
+mod diagnostics;
 mod error;
+mod storage;
+mod grpc;
+mod plugins;
+mod reload;
+mod systemd;
+mod telemetry;
 mod web; // Add web module
 
 use crate::config::Config;
+use crate::coordination::Coordinator;
+use crate::grpc::SchedulerGrpcService;
+use crate::metrics::history::MetricHistory;
 use crate::metrics::MetricsCollector;
 use crate::ml::MLEngine;
+use crate::reload::ConfigReloader;
 use crate::scheduler::ResourceScheduler;
+use crate::storage::Storage;
+use crate::systemd::PidFile;
 use crate::web::DashboardServer; // Add dashboard import
 
 #[derive(Parser)]
 #[command(name = "openstack-metrics-service")]
 #[command(about = "High-performance OpenStack metrics collection and ML-based resource scheduling")]
 struct Cli {
-    #[arg(short, long, default_value = "config.toml")]
+    #[arg(short, long, default_value = "config.toml", global = true)]
     config: String,
     
     #[arg(long, default_value = "8080")]
     dashboard_port: u16,
+    
+    // Runs the service when omitted
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+// One-shot commands print their result as JSON on stdout; logs go to stderr
+#[derive(Subcommand, Clone)]
+enum Command {
+    // Run every service until interrupted
+    Run,
+    // Discover resources and collect their metrics once
+    CollectOnce {
+        // Also send the samples to the sinks and the history store
+        #[arg(long)]
+        publish: bool,
+    },
+    // Forecast a resource's load from its stored metric history
+    Predict {
+        resource_id: String,
+        #[arg(long, default_value = "24")]
+        history_hours: i64,
+    },
+    // Plan a scheduling cycle against the current cluster state without
+    // executing anything
+    Simulate {
+        #[arg(long, default_value = "24")]
+        history_hours: i64,
+        // Also send the samples collected first to the sinks and the
+        // history store
+        #[arg(long)]
+        publish: bool,
+    },
+    // Retrain the load model
+    Train,
+    // Check the config, report every problem found and exit; non-zero when
+    // it has errors
+    ValidateConfig,
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    // Initialize tracing
-    tracing_subscriber::fmt::init();
-    
     let cli = Cli::parse();
-    let config = Config::from_file(&cli.config)?;
+    let command = cli.command.clone().unwrap_or(Command::Run);
+    if let Command::ValidateConfig = command {
+        validate_config(&cli.config);
+    }
     
+    // Logging is set up from the config, so its warnings wait until then
+    let (config, warnings) = Config::load(&cli.config)?;
+    let telemetry = telemetry::init(&config.telemetry, &config.diagnostics)?;
+    for warning in &warnings {
+        warn!("Config: {}", warning);
+    }
+    
+    let result = match command {
+        Command::Run => run(&cli, config).await,
+        Command::CollectOnce { publish } => collect_once(&config, publish).await,
+        Command::Predict { resource_id, history_hours } => predict(&config, &resource_id, history_hours).await,
+        Command::Simulate { history_hours, publish } => simulate(&config, history_hours, publish).await,
+        Command::Train => train(&config).await,
+        Command::ValidateConfig => unreachable!("handled before logging is set up"),
+    };
+    telemetry.shutdown().await;
+    result
+}
+
+async fn run(cli: &Cli, config: Config) -> Result<()> {
     info!("Starting OpenStack Metrics Service with ML Dashboard");
+    let _pid_file = config.systemd.pid_file.as_deref().map(PidFile::create).transpose()?;
     
-    // Initialize core components
-    let openstack_client = Arc::new(
-        openstack::Client::new(&config.openstack).await?
-    );
+    // Recorder for the service's own metrics, scraped via the dashboard's /metrics
+    let prometheus = PrometheusBuilder::new().install_recorder()?;
+    
+    // Initialize core components. systemd only hears READY once Keystone,
+    // the sinks and the model are all up
+    systemd::notify("STATUS=Authenticating with Keystone");
+    let clouds = openstack::Clouds::connect(&config.openstack).await?;
+    let openstack_client = clouds.primary();
+    
+    let storage = Storage::from_config(&config.storage).await?;
+    
+    let coordinator = Coordinator::from_config(&config.coordination)?;
+    info!("Coordinating with other instances as {}", coordinator.instance_id());
     
     let metrics_collector = Arc::new(
-        MetricsCollector::new(&config.metrics, openstack_client.clone()).await?
+        MetricsCollector::new(
+            &config.metrics,
+            clouds.clone(),
+            storage.clone(),
+            coordinator.clone(),
+        ).await?
     );
+    systemd::notify("STATUS=Connecting to metric sinks");
+    metrics_collector.check_sinks().await?;
     
+    systemd::notify("STATUS=Loading the forecast model");
     let ml_engine = Arc::new(
-        MLEngine::new(&config.ml).await?
+        MLEngine::new(&config.ml, coordinator.clone()).await?
     );
+    let mut seeded = false;
+    if openstack_client.telemetry.is_enabled() {
+        let since = Utc::now() - ChronoDuration::hours(config.openstack.gnocchi.history_hours);
+        match ml_engine.seed_from_telemetry(&openstack_client.telemetry, since).await {
+            Ok(_) => seeded = true,
+            Err(e) => warn!("Failed to seed load history from Gnocchi: {}", e),
+        }
+    }
+    // Without Gnocchi, forecasts pick up from the metric history kept before
+    // a restart
+    if !seeded {
+        let since = Utc::now() - ChronoDuration::hours(config.metrics.history.memory_hours);
+        let resources = ml_engine.seed_from_history(&metrics_collector.history(), since).await;
+        info!("Seeded load history for {} resources from metric history", resources);
+    }
     
     let scheduler = Arc::new(
         ResourceScheduler::new(
             &config.scheduler,
-            openstack_client.clone(),
-            ml_engine.clone()
+            clouds.clone(),
+            ml_engine.clone(),
+            storage.clone(),
+            metrics_collector.latest_metrics(),
+            metrics_collector.shards(),
+            coordinator,
+            &config.metrics.kafka_config,
         ).await?
     );
     
@@ -65,13 +181,32 @@ async fn main() -> Result<()> {
         ml_engine.clone(),
         metrics_collector.clone(),
         scheduler.clone(),
+        storage.clone(),
+        prometheus,
+        config.api.clone(),
+        config.alerting.clone(),
+        config.diagnostics.clone(),
+    ).await?;
+    
+    let grpc_service = SchedulerGrpcService::new(
+        ml_engine.clone(),
+        metrics_collector.clone(),
+        scheduler.clone(),
+        dashboard_server.authenticator(),
+        dashboard_server.audit_log(),
     );
     
+    let reloader = ConfigReloader::new(&cli.config, config.clone(), scheduler.clone(), dashboard_server.clone());
+    
+    // Every loop watches this and winds down once it is cancelled
+    let shutdown = CancellationToken::new();
+    
     // Start services
     let metrics_handle = tokio::spawn({
         let collector = metrics_collector.clone();
+        let shutdown = shutdown.clone();
         async move {
-            if let Err(e) = collector.start_collection().await {
+            if let Err(e) = collector.start_collection(shutdown).await {
                 warn!("Metrics collection error: {}", e);
             }
         }
@@ -79,8 +214,10 @@ async fn main() -> Result<()> {
     
     let ml_handle = tokio::spawn({
         let engine = ml_engine.clone();
+        let samples = metrics_collector.subscribe_samples();
+        let shutdown = shutdown.clone();
         async move {
-            if let Err(e) = engine.start_inference_loop().await {
+            if let Err(e) = engine.start_inference_loop(samples, shutdown).await {
                 warn!("ML engine error: {}", e);
             }
         }
@@ -88,35 +225,171 @@ async fn main() -> Result<()> {
     
     let scheduler_handle = tokio::spawn({
         let sched = scheduler.clone();
+        let shutdown = shutdown.clone();
         async move {
-            if let Err(e) = sched.start_scheduling_loop().await {
+            if let Err(e) = sched.start_scheduling_loop(shutdown).await {
                 warn!("Scheduler error: {}", e);
             }
         }
     });
     
     // Start dashboard server
+    let dashboard_port = cli.dashboard_port;
     let dashboard_handle = tokio::spawn({
         let server = dashboard_server;
+        let shutdown = shutdown.clone();
         async move {
-            if let Err(e) = server.start(cli.dashboard_port).await {
+            if let Err(e) = server.start(dashboard_port, shutdown).await {
                 warn!("Dashboard server error: {}", e);
             }
         }
     });
     
+    let token_handle = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move { clouds.renew_tokens(shutdown).await }
+    });
+    
+    let reload_handle = tokio::spawn({
+        let shutdown = shutdown.clone();
+        async move {
+            if let Err(e) = reloader.run(shutdown).await {
+                warn!("Config reloader error: {}", e);
+            }
+        }
+    });
+    
+    let mut handles = vec![
+        ("metrics collection", metrics_handle),
+        ("ML inference", ml_handle),
+        ("scheduler", scheduler_handle),
+        ("dashboard", dashboard_handle),
+        ("config reload", reload_handle),
+        ("token renewal", token_handle),
+    ];
+    if config.grpc.enabled {
+        let grpc_config = config.grpc.clone();
+        let shutdown = shutdown.clone();
+        handles.push(("gRPC", tokio::spawn(async move {
+            if let Err(e) = grpc_service.serve(&grpc_config, shutdown).await {
+                warn!("gRPC server error: {}", e);
+            }
+        })));
+    }
+    
+    let watched = handles.iter().map(|(name, handle)| (*name, handle.abort_handle())).collect();
+    handles.push(("watchdog", tokio::spawn(systemd::run_watchdog(watched, shutdown.clone()))));
+    
+    systemd::notify("READY=1\nSTATUS=Collecting and scheduling");
     info!("All services started successfully");
     info!("Dashboard available at http://localhost:{}", cli.dashboard_port);
     
     // Wait for shutdown signal
-    signal::ctrl_c().await?;
+    let mut terminate = signal(SignalKind::terminate())?;
+    tokio::select! {
+        result = tokio::signal::ctrl_c() => result?,
+        _ = terminate.recv() => {}
+    }
     info!("Shutdown signal received, stopping services...");
+    systemd::notify("STOPPING=1\nSTATUS=Shutting down");
     
-    // Graceful shutdown
-    metrics_handle.abort();
-    ml_handle.abort();
-    scheduler_handle.abort();
-    dashboard_handle.abort();
+    // Let the loops finish what they are doing and flush; abort whatever is
+    // still running once the timeout is up
+    shutdown.cancel();
+    let timeout = Duration::from_secs(config.shutdown.timeout_seconds);
+    let aborts: Vec<_> = handles.iter().map(|(_, handle)| handle.abort_handle()).collect();
+    let handles = handles.into_iter().map(|(_, handle)| handle);
+    if tokio::time::timeout(timeout, futures_util::future::join_all(handles)).await.is_err() {
+        warn!("Services still running after {}s, aborting them", timeout.as_secs());
+        for abort in aborts {
+            abort.abort();
+        }
+    } else {
+        info!("All services stopped");
+    }
     
     Ok(())
 }
+
+async fn collect_once(config: &Config, publish: bool) -> Result<()> {
+    let coordinator = Coordinator::from_config(&config.coordination)?;
+    let (_, _, collector) = build_collector(config, coordinator).await?;
+    print_json(&collector.collect_once(publish).await?)
+}
+
+async fn predict(config: &Config, resource_id: &str, history_hours: i64) -> Result<()> {
+    let storage = Storage::from_config(&config.storage).await?;
+    let history = MetricHistory::load(config.metrics.history.clone(), storage).await?;
+    let ml_engine = MLEngine::new(&config.ml, Coordinator::from_config(&config.coordination)?).await?;
+    ml_engine.seed_from_history(&history, Utc::now() - ChronoDuration::hours(history_hours)).await;
+    
+    let forecast = ml_engine.get_resource_forecast(resource_id).await?
+        .ok_or_else(|| anyhow::anyhow!("Not enough metric history to forecast {}", resource_id))?;
+    print_json(&serde_json::json!({
+        "resource_id": resource_id,
+        "model_version": ml_engine.model_version().await,
+        "forecast": forecast,
+    }))
+}
+
+// Collects first so the plan sees current metrics rather than whatever was
+// last stored
+async fn simulate(config: &Config, history_hours: i64, publish: bool) -> Result<()> {
+    let coordinator = Coordinator::from_config(&config.coordination)?;
+    let (clouds, storage, collector) = build_collector(config, coordinator.clone()).await?;
+    collector.collect_once(publish).await?;
+    
+    let ml_engine = Arc::new(MLEngine::new(&config.ml, coordinator.clone()).await?);
+    ml_engine.seed_from_history(&collector.history(), Utc::now() - ChronoDuration::hours(history_hours)).await;
+    
+    let scheduler = ResourceScheduler::new(
+        &config.scheduler,
+        clouds,
+        ml_engine,
+        storage,
+        collector.latest_metrics(),
+        collector.shards(),
+        coordinator,
+        &config.metrics.kafka_config,
+    ).await?;
+    print_json(&scheduler.dry_run().await?)
+}
+
+async fn train(config: &Config) -> Result<()> {
+    let ml_engine = MLEngine::new(&config.ml, Coordinator::from_config(&config.coordination)?).await?;
+    if !ml_engine.retrain_model().await? {
+        anyhow::bail!("Another instance is retraining the model");
+    }
+    print_json(&serde_json::json!({ "model_version": ml_engine.model_version().await }))
+}
+
+async fn build_collector(
+    config: &Config,
+    coordinator: Coordinator,
+) -> Result<(openstack::Clouds, Storage, Arc<MetricsCollector>)> {
+    let clouds = openstack::Clouds::connect(&config.openstack).await?;
+    let storage = Storage::from_config(&config.storage).await?;
+    let collector = MetricsCollector::new(&config.metrics, clouds.clone(), storage.clone(), coordinator).await?;
+    Ok((clouds, storage, Arc::new(collector)))
+}
+
+fn print_json<T: Serialize>(value: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+fn validate_config(path: &str) -> ! {
+    match Config::load(path) {
+        Ok((_, warnings)) => {
+            for warning in &warnings {
+                println!("warning: {}", warning);
+            }
+            println!("{} is valid", path);
+            std::process::exit(0);
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Mutex;
+
+use crate::config::AgentIngestConfig;
+use crate::openstack::services::{InterfaceTraffic, ServerMetrics};
+
+// How far ahead of ours an agent's clock may be
+const MAX_CLOCK_SKEW_SECONDS: i64 = 60;
+
+// What a per-hypervisor agent pushes: the libvirt stats of every domain on
+// its host, with counters as virConnectGetAllDomainStats reports them
+#[derive(Debug, Clone, Deserialize)]
+pub struct AgentReport {
+    pub hypervisor: String,
+    pub timestamp: DateTime<Utc>,
+    pub domains: Vec<DomainStats>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DomainStats {
+    // The domain's UUID, which is the Nova server's id
+    pub server_id: String,
+    pub vcpus: u32,
+    // cpu.time
+    pub cpu_time_ns: u64,
+    // The sum of vcpu.N.delay, the time vCPUs were ready to run but the
+    // host ran something else
+    #[serde(default)]
+    pub cpu_steal_ns: Option<u64>,
+    #[serde(default)]
+    pub balloon: BalloonStats,
+    #[serde(default)]
+    pub disks: Vec<DiskStats>,
+    #[serde(default)]
+    pub interfaces: Vec<InterfaceTraffic>,
+}
+
+// balloon.*, in KiB as libvirt has them; unset where the guest's balloon
+// driver doesn't report them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct BalloonStats {
+    pub current_kib: Option<u64>,
+    pub maximum_kib: Option<u64>,
+    pub available_kib: Option<u64>,
+    pub unused_kib: Option<u64>,
+    pub rss_kib: Option<u64>,
+    pub swap_in_kib: Option<u64>,
+    pub swap_out_kib: Option<u64>,
+    pub major_faults: Option<u64>,
+}
+
+// block.N.*, by target device such as vda
+#[derive(Debug, Clone, Deserialize)]
+pub struct DiskStats {
+    pub device: String,
+    pub read_bytes: u64,
+    pub write_bytes: u64,
+    pub read_requests: u64,
+    pub write_requests: u64,
+    pub read_time_ns: u64,
+    pub write_time_ns: u64,
+}
+
+// What became of a report's domains
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IngestResult {
+    pub recorded: usize,
+    // A domain's first report, or its first after a reboot, only sets the
+    // baseline
+    pub baseline: usize,
+    pub stale: usize,
+    // In another member's shard
+    pub not_owned: usize,
+}
+
+// Turns agent reports into server samples. Rates come from the difference
+// to the domain's previous report, so that is kept per server
+pub struct AgentIngest {
+    config: AgentIngestConfig,
+    previous: Mutex<HashMap<String, Previous>>,
+}
+
+struct Previous {
+    timestamp: DateTime<Utc>,
+    cpu_time_ns: u64,
+    cpu_steal_ns: Option<u64>,
+    disks: HashMap<String, DiskStats>,
+}
+
+impl AgentIngest {
+    pub fn new(config: &AgentIngestConfig) -> Self {
+        Self {
+            config: config.clone(),
+            previous: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    pub fn check(&self, report: &AgentReport) -> Result<()> {
+        if report.hypervisor.trim().is_empty() {
+            anyhow::bail!("hypervisor is required");
+        }
+        // It would make every later report of its domains look overtaken
+        if report.timestamp > Utc::now() + ChronoDuration::seconds(MAX_CLOCK_SKEW_SECONDS) {
+            anyhow::bail!("timestamp {} is in the future", report.timestamp);
+        }
+        if report.domains.len() > self.config.max_domains_per_report {
+            anyhow::bail!("{} domains, at most {} are taken per report", report.domains.len(), self.config.max_domains_per_report);
+        }
+        if let Some(domain) = report.domains.iter().find(|domain| domain.vcpus == 0) {
+            anyhow::bail!("domain {} has no vCPUs", domain.server_id);
+        }
+        Ok(())
+    }
+    
+    // Domains of a report older than max_age_seconds are all stale
+    pub fn samples(&self, report: AgentReport, result: &mut IngestResult) -> Vec<ServerMetrics> {
+        let oldest = Utc::now() - ChronoDuration::seconds(self.config.max_age_seconds as i64);
+        if report.timestamp < oldest {
+            result.stale += report.domains.len();
+            return Vec::new();
+        }
+        
+        let mut previous = self.previous.lock().unwrap();
+        // Servers that stopped being reported, e.g. deleted or migrated away
+        previous.retain(|_, domain| domain.timestamp >= oldest);
+        let mut samples = Vec::new();
+        for domain in report.domains {
+            let current = Previous {
+                timestamp: report.timestamp,
+                cpu_time_ns: domain.cpu_time_ns,
+                cpu_steal_ns: domain.cpu_steal_ns,
+                disks: domain.disks.iter().map(|disk| (disk.device.clone(), disk.clone())).collect(),
+            };
+            let server_id = domain.server_id.clone();
+            match previous.get(&server_id) {
+                // Overtaken by a later report
+                Some(before) if before.timestamp >= report.timestamp => {
+                    result.stale += 1;
+                    continue;
+                }
+                Some(before) if before.cpu_time_ns <= domain.cpu_time_ns => {
+                    samples.push(sample(domain, report.timestamp, before));
+                }
+                // Not seen yet, or a lower counter after a reboot
+                _ => result.baseline += 1,
+            }
+            previous.insert(server_id, current);
+        }
+        result.recorded += samples.len();
+        samples
+    }
+}
+
+fn sample(domain: DomainStats, timestamp: DateTime<Utc>, before: &Previous) -> ServerMetrics {
+    let elapsed_ns = (timestamp - before.timestamp).num_nanoseconds().unwrap_or(i64::MAX) as f64;
+    let cpus = domain.vcpus.max(1) as f64;
+    let share = |delta: u64| (delta as f64 / elapsed_ns / cpus * 100.0).clamp(0.0, 100.0);
+    
+    let mut extra = BTreeMap::new();
+    extra.insert("num_cpus".to_string(), cpus);
+    if let (Some(steal), Some(steal_before)) = (domain.cpu_steal_ns, before.cpu_steal_ns) {
+        if steal >= steal_before {
+            extra.insert("cpu_steal".to_string(), share(steal - steal_before));
+        }
+    }
+    
+    let balloon = &domain.balloon;
+    let bytes = |kib: Option<u64>| kib.map(|kib| kib.saturating_mul(1024));
+    for (name, value) in [
+        ("memory_balloon_bytes", bytes(balloon.current_kib)),
+        ("memory_balloon_max_bytes", bytes(balloon.maximum_kib)),
+        // How much the balloon took back from the guest
+        ("memory_ballooned_bytes", bytes(balloon.maximum_kib.zip(balloon.current_kib).map(|(max, current)| max.saturating_sub(current)))),
+        ("memory_rss_bytes", bytes(balloon.rss_kib)),
+        ("memory_swap_in_bytes", bytes(balloon.swap_in_kib)),
+        ("memory_swap_out_bytes", bytes(balloon.swap_out_kib)),
+        ("memory_major_faults", balloon.major_faults),
+    ] {
+        if let Some(value) = value {
+            extra.insert(name.to_string(), value as f64);
+        }
+    }
+    // In MiB like Nova's. As the guest sees it, or failing that what the
+    // host gave it
+    let memory_usage = match (balloon.available_kib, balloon.unused_kib) {
+        (Some(available), Some(unused)) => Some(available.saturating_sub(unused)),
+        _ => balloon.rss_kib,
+    };
+    
+    // Mean time per request since the previous report, per disk and over
+    // all of them
+    let mut reads = (0, 0);
+    let mut writes = (0, 0);
+    for disk in &domain.disks {
+        let Some(disk_before) = before.disks.get(&disk.device) else {
+            continue;
+        };
+        // Device names become field names in the sinks
+        let named = disk.device.chars().all(|c| c.is_ascii_alphanumeric());
+        let deltas = [
+            ("read", &mut reads, disk.read_requests.saturating_sub(disk_before.read_requests), disk.read_time_ns.saturating_sub(disk_before.read_time_ns)),
+            ("write", &mut writes, disk.write_requests.saturating_sub(disk_before.write_requests), disk.write_time_ns.saturating_sub(disk_before.write_time_ns)),
+        ];
+        for (operation, total, requests, time_ns) in deltas {
+            total.0 += requests;
+            total.1 += time_ns;
+            if named && requests > 0 {
+                extra.insert(format!("disk_{}_{}_latency_ms", disk.device, operation), time_ns as f64 / requests as f64 / 1e6);
+            }
+        }
+    }
+    for (operation, (requests, time_ns)) in [("read", reads), ("write", writes)] {
+        if requests > 0 {
+            extra.insert(format!("disk_{}_latency_ms", operation), time_ns as f64 / requests as f64 / 1e6);
+        }
+    }
+    
+    ServerMetrics {
+        cpu_utilization: share(domain.cpu_time_ns - before.cpu_time_ns),
+        memory_usage: memory_usage.unwrap_or(0) / 1024,
+        memory_total: balloon.current_kib.unwrap_or(0) / 1024,
+        disk_read_bytes: domain.disks.iter().map(|disk| disk.read_bytes).sum(),
+        disk_write_bytes: domain.disks.iter().map(|disk| disk.write_bytes).sum(),
+        network_rx_bytes: domain.interfaces.iter().map(|interface| interface.rx_bytes).sum(),
+        network_tx_bytes: domain.interfaces.iter().map(|interface| interface.tx_bytes).sum(),
+        server_id: domain.server_id,
+        interfaces: domain.interfaces,
+        workload_class: None,
+        cloud: None,
+        region: None,
+        extra,
+        timestamp,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ingest() -> AgentIngest {
+        AgentIngest::new(&AgentIngestConfig {
+            enabled: true,
+            ..AgentIngestConfig::default()
+        })
+    }
+    
+    fn domain(cpu_time_ns: u64, cpu_steal_ns: u64, read_requests: u64, read_time_ns: u64) -> DomainStats {
+        DomainStats {
+            server_id: "vm-1".to_string(),
+            vcpus: 2,
+            cpu_time_ns,
+            cpu_steal_ns: Some(cpu_steal_ns),
+            balloon: BalloonStats {
+                current_kib: Some(3 * 1024 * 1024),
+                maximum_kib: Some(4 * 1024 * 1024),
+                available_kib: Some(3 * 1024 * 1024),
+                unused_kib: Some(1024 * 1024),
+                ..BalloonStats::default()
+            },
+            disks: vec![DiskStats {
+                device: "vda".to_string(),
+                read_bytes: read_requests * 4096,
+                write_bytes: 0,
+                read_requests,
+                write_requests: 0,
+                read_time_ns,
+                write_time_ns: 0,
+            }],
+            interfaces: Vec::new(),
+        }
+    }
+    
+    fn report(seconds_ago: i64, domain: DomainStats) -> AgentReport {
+        AgentReport {
+            hypervisor: "compute-1".to_string(),
+            timestamp: Utc::now() - ChronoDuration::seconds(seconds_ago),
+            domains: vec![domain],
+        }
+    }
+    
+    #[test]
+    fn first_report_only_sets_the_baseline() {
+        let ingest = ingest();
+        let mut result = IngestResult::default();
+        assert!(ingest.samples(report(20, domain(0, 0, 0, 0)), &mut result).is_empty());
+        assert_eq!((result.baseline, result.recorded), (1, 0));
+    }
+    
+    #[test]
+    fn rates_come_from_counter_deltas() {
+        let ingest = ingest();
+        let mut result = IngestResult::default();
+        let before = report(20, domain(5_000_000_000, 100, 10, 1_000_000));
+        let after = AgentReport {
+            timestamp: before.timestamp + ChronoDuration::seconds(10),
+            ..report(0, domain(15_000_000_000, 1_000_000_100, 110, 201_000_000))
+        };
+        ingest.samples(before, &mut result);
+        let samples = ingest.samples(after, &mut result);
+        assert_eq!(result.recorded, 1);
+        
+        let sample = &samples[0];
+        // 10s of CPU time over 10s on 2 vCPUs
+        assert!((sample.cpu_utilization - 50.0).abs() < 1e-9);
+        assert!((sample.extra["cpu_steal"] - 5.0).abs() < 1e-9);
+        // 200ms over 100 reads
+        assert!((sample.extra["disk_vda_read_latency_ms"] - 2.0).abs() < 1e-9);
+        assert!((sample.extra["disk_read_latency_ms"] - 2.0).abs() < 1e-9);
+        assert!(!sample.extra.contains_key("disk_write_latency_ms"));
+        assert_eq!(sample.extra["memory_ballooned_bytes"], 1024.0 * 1024.0 * 1024.0);
+        assert_eq!((sample.memory_usage, sample.memory_total), (2048, 3072));
+    }
+    
+    #[test]
+    fn lower_counters_after_a_reboot_reset_the_baseline() {
+        let ingest = ingest();
+        let mut result = IngestResult::default();
+        ingest.samples(report(20, domain(50_000_000_000, 0, 0, 0)), &mut result);
+        assert!(ingest.samples(report(10, domain(1_000_000_000, 0, 0, 0)), &mut result).is_empty());
+        assert_eq!(result.baseline, 2);
+        
+        // Deltas resume from the rebooted counters
+        let samples = ingest.samples(report(0, domain(11_000_000_000, 0, 0, 0)), &mut result);
+        assert_eq!(samples.len(), 1);
+        assert!(samples[0].cpu_utilization > 0.0);
+    }
+    
+    #[test]
+    fn skips_overtaken_and_old_reports() {
+        let ingest = ingest();
+        let mut result = IngestResult::default();
+        ingest.samples(report(10, domain(0, 0, 0, 0)), &mut result);
+        assert!(ingest.samples(report(20, domain(1_000_000_000, 0, 0, 0)), &mut result).is_empty());
+        assert!(ingest.samples(report(600, domain(2_000_000_000, 0, 0, 0)), &mut result).is_empty());
+        assert_eq!(result.stale, 2);
+    }
+    
+    #[test]
+    fn checks_reports() {
+        let ingest = ingest();
+        assert!(ingest.check(&report(0, domain(0, 0, 0, 0))).is_ok());
+        assert!(ingest.check(&report(-3600, domain(0, 0, 0, 0))).is_err());
+        assert!(ingest.check(&AgentReport { hypervisor: " ".to_string(), ..report(0, domain(0, 0, 0, 0)) }).is_err());
+        assert!(ingest.check(&report(0, DomainStats { vcpus: 0, ..domain(0, 0, 0, 0) })).is_err());
+    }
+}
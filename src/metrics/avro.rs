@@ -0,0 +1,508 @@
+use anyhow::{Context, Result};
+use apache_avro::types::Value;
+use apache_avro::Schema;
+use chrono::{DateTime, Utc};
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use std::time::Duration;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::config::SchemaRegistryConfig;
+use crate::openstack::services::{InterfaceTraffic, NetworkMetrics, ServerMetrics, StorageMetrics};
+
+// Confluent's wire format: this byte, the schema id as a big-endian u32,
+// then the Avro datum
+const MAGIC_BYTE: u8 = 0;
+
+const SERVER_METRICS_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "ServerMetrics",
+    "namespace": "openstack.metrics",
+    "fields": [
+        {"name": "server_id", "type": "string"},
+        {"name": "cpu_utilization", "type": "double"},
+        {"name": "memory_usage", "type": "long"},
+        {"name": "memory_total", "type": "long"},
+        {"name": "disk_read_bytes", "type": "long"},
+        {"name": "disk_write_bytes", "type": "long"},
+        {"name": "network_rx_bytes", "type": "long"},
+        {"name": "network_tx_bytes", "type": "long"},
+        {"name": "interfaces", "type": {"type": "array", "items": {
+            "type": "record",
+            "name": "InterfaceTraffic",
+            "fields": [
+                {"name": "mac_address", "type": "string"},
+                {"name": "rx_bytes", "type": "long"},
+                {"name": "tx_bytes", "type": "long"},
+                {"name": "rx_packets", "type": "long"},
+                {"name": "tx_packets", "type": "long"},
+                {"name": "rx_dropped", "type": "long"},
+                {"name": "tx_dropped", "type": "long"}
+            ]
+        }}, "default": []},
+        {"name": "workload_class", "type": ["null", "string"], "default": null},
+        {"name": "cloud", "type": ["null", "string"], "default": null},
+        {"name": "region", "type": ["null", "string"], "default": null},
+        {"name": "extra", "type": {"type": "map", "values": "double"}, "default": {}},
+        {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+    ]
+}"#;
+
+const NETWORK_METRICS_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "NetworkMetrics",
+    "namespace": "openstack.metrics",
+    "fields": [
+        {"name": "network_id", "type": "string"},
+        {"name": "network_name", "type": ["null", "string"], "default": null},
+        {"name": "router_id", "type": ["null", "string"], "default": null},
+        {"name": "port_id", "type": "string"},
+        {"name": "server_id", "type": "string"},
+        {"name": "fixed_ips", "type": {"type": "array", "items": "string"}},
+        {"name": "floating_ips", "type": {"type": "array", "items": "string"}},
+        {"name": "rx_bytes", "type": "long"},
+        {"name": "tx_bytes", "type": "long"},
+        {"name": "rx_packets", "type": "long"},
+        {"name": "tx_packets", "type": "long"},
+        {"name": "packet_loss", "type": "double"},
+        {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+    ]
+}"#;
+
+const STORAGE_METRICS_SCHEMA: &str = r#"{
+    "type": "record",
+    "name": "StorageMetrics",
+    "namespace": "openstack.metrics",
+    "fields": [
+        {"name": "volume_id", "type": "string"},
+        {"name": "name", "type": ["null", "string"], "default": null},
+        {"name": "server_id", "type": "string"},
+        {"name": "device", "type": ["null", "string"], "default": null},
+        {"name": "size_gb", "type": "long"},
+        {"name": "status", "type": "string"},
+        {"name": "volume_type", "type": ["null", "string"], "default": null},
+        {"name": "backend", "type": ["null", "string"], "default": null},
+        {"name": "bootable", "type": "boolean"},
+        {"name": "timestamp", "type": {"type": "long", "logicalType": "timestamp-micros"}}
+    ]
+}"#;
+
+// A payload type with a fixed Avro schema
+pub trait AvroRecord {
+    fn schema() -> &'static AvroSchema;
+    
+    fn to_avro(&self) -> Value;
+}
+
+pub struct AvroSchema {
+    json: &'static str,
+    parsed: OnceLock<Schema>,
+}
+
+impl AvroSchema {
+    const fn new(json: &'static str) -> Self {
+        Self { json, parsed: OnceLock::new() }
+    }
+    
+    // The schemas are constants, so failing to parse one is a build mistake
+    fn parsed(&self) -> &Schema {
+        self.parsed.get_or_init(|| Schema::parse_str(self.json).expect("invalid built-in Avro schema"))
+    }
+}
+
+static SERVER_METRICS: AvroSchema = AvroSchema::new(SERVER_METRICS_SCHEMA);
+static NETWORK_METRICS: AvroSchema = AvroSchema::new(NETWORK_METRICS_SCHEMA);
+static STORAGE_METRICS: AvroSchema = AvroSchema::new(STORAGE_METRICS_SCHEMA);
+
+impl AvroRecord for ServerMetrics {
+    fn schema() -> &'static AvroSchema {
+        &SERVER_METRICS
+    }
+    
+    fn to_avro(&self) -> Value {
+        let interfaces = self.interfaces.iter()
+            .map(|interface| Value::Record(vec![
+                ("mac_address".to_string(), Value::String(interface.mac_address.clone())),
+                ("rx_bytes".to_string(), long(interface.rx_bytes)),
+                ("tx_bytes".to_string(), long(interface.tx_bytes)),
+                ("rx_packets".to_string(), long(interface.rx_packets)),
+                ("tx_packets".to_string(), long(interface.tx_packets)),
+                ("rx_dropped".to_string(), long(interface.rx_dropped)),
+                ("tx_dropped".to_string(), long(interface.tx_dropped)),
+            ]))
+            .collect();
+        let extra = self.extra.iter()
+            .map(|(name, value)| (name.clone(), Value::Double(*value)))
+            .collect();
+        Value::Record(vec![
+            ("server_id".to_string(), Value::String(self.server_id.clone())),
+            ("cpu_utilization".to_string(), Value::Double(self.cpu_utilization)),
+            ("memory_usage".to_string(), long(self.memory_usage)),
+            ("memory_total".to_string(), long(self.memory_total)),
+            ("disk_read_bytes".to_string(), long(self.disk_read_bytes)),
+            ("disk_write_bytes".to_string(), long(self.disk_write_bytes)),
+            ("network_rx_bytes".to_string(), long(self.network_rx_bytes)),
+            ("network_tx_bytes".to_string(), long(self.network_tx_bytes)),
+            ("interfaces".to_string(), Value::Array(interfaces)),
+            ("workload_class".to_string(), optional(&self.workload_class)),
+            ("cloud".to_string(), optional(&self.cloud)),
+            ("region".to_string(), optional(&self.region)),
+            ("extra".to_string(), Value::Map(extra)),
+            ("timestamp".to_string(), Value::TimestampMicros(self.timestamp.timestamp_micros())),
+        ])
+    }
+}
+
+impl AvroRecord for NetworkMetrics {
+    fn schema() -> &'static AvroSchema {
+        &NETWORK_METRICS
+    }
+    
+    fn to_avro(&self) -> Value {
+        let strings = |values: &[String]| Value::Array(values.iter().cloned().map(Value::String).collect());
+        Value::Record(vec![
+            ("network_id".to_string(), Value::String(self.network_id.clone())),
+            ("network_name".to_string(), optional(&self.network_name)),
+            ("router_id".to_string(), optional(&self.router_id)),
+            ("port_id".to_string(), Value::String(self.port_id.clone())),
+            ("server_id".to_string(), Value::String(self.server_id.clone())),
+            ("fixed_ips".to_string(), strings(&self.fixed_ips)),
+            ("floating_ips".to_string(), strings(&self.floating_ips)),
+            ("rx_bytes".to_string(), long(self.rx_bytes)),
+            ("tx_bytes".to_string(), long(self.tx_bytes)),
+            ("rx_packets".to_string(), long(self.rx_packets)),
+            ("tx_packets".to_string(), long(self.tx_packets)),
+            ("packet_loss".to_string(), Value::Double(self.packet_loss)),
+            ("timestamp".to_string(), Value::TimestampMicros(self.timestamp.timestamp_micros())),
+        ])
+    }
+}
+
+impl AvroRecord for StorageMetrics {
+    fn schema() -> &'static AvroSchema {
+        &STORAGE_METRICS
+    }
+    
+    fn to_avro(&self) -> Value {
+        Value::Record(vec![
+            ("volume_id".to_string(), Value::String(self.volume_id.clone())),
+            ("name".to_string(), optional(&self.name)),
+            ("server_id".to_string(), Value::String(self.server_id.clone())),
+            ("device".to_string(), optional(&self.device)),
+            ("size_gb".to_string(), long(self.size_gb)),
+            ("status".to_string(), Value::String(self.status.clone())),
+            ("volume_type".to_string(), optional(&self.volume_type)),
+            ("backend".to_string(), optional(&self.backend)),
+            ("bootable".to_string(), Value::Boolean(self.bootable)),
+            ("timestamp".to_string(), Value::TimestampMicros(self.timestamp.timestamp_micros())),
+        ])
+    }
+}
+
+// Counters past i64::MAX don't happen in practice; they saturate
+fn long(value: u64) -> Value {
+    Value::Long(value.min(i64::MAX as u64) as i64)
+}
+
+// The ["null", "string"] unions above
+fn optional(value: &Option<String>) -> Value {
+    match value {
+        Some(value) => Value::Union(1, Box::new(Value::String(value.clone()))),
+        None => Value::Union(0, Box::new(Value::Null)),
+    }
+}
+
+// A sample off the compute topic, in whichever serialization it was
+// published with. Avro payloads were written by us with our own schema, so
+// the id they carry isn't looked up
+pub fn parse_server_metrics(payload: &[u8]) -> Result<ServerMetrics> {
+    if payload.first() != Some(&MAGIC_BYTE) {
+        return Ok(serde_json::from_slice(payload)?);
+    }
+    let mut datum = payload.get(5..).context("Truncated Avro payload")?;
+    let value = apache_avro::from_avro_datum(SERVER_METRICS.parsed(), &mut datum, None)?;
+    
+    let mut fields = Fields::of(value)?;
+    let interfaces = fields.array("interfaces")?.into_iter()
+        .map(|interface| {
+            let mut fields = Fields::of(interface)?;
+            Ok(InterfaceTraffic {
+                mac_address: fields.string("mac_address")?,
+                rx_bytes: fields.long("rx_bytes")?,
+                tx_bytes: fields.long("tx_bytes")?,
+                rx_packets: fields.long("rx_packets")?,
+                tx_packets: fields.long("tx_packets")?,
+                rx_dropped: fields.long("rx_dropped")?,
+                tx_dropped: fields.long("tx_dropped")?,
+            })
+        })
+        .collect::<Result<_>>()?;
+    let extra = match fields.take("extra")? {
+        Value::Map(extra) => extra.into_iter()
+            .filter_map(|(name, value)| match value {
+                Value::Double(value) => Some((name, value)),
+                _ => None,
+            })
+            .collect(),
+        other => anyhow::bail!("extra is {:?}, not a map", other),
+    };
+    Ok(ServerMetrics {
+        server_id: fields.string("server_id")?,
+        cpu_utilization: fields.double("cpu_utilization")?,
+        memory_usage: fields.long("memory_usage")?,
+        memory_total: fields.long("memory_total")?,
+        disk_read_bytes: fields.long("disk_read_bytes")?,
+        disk_write_bytes: fields.long("disk_write_bytes")?,
+        network_rx_bytes: fields.long("network_rx_bytes")?,
+        network_tx_bytes: fields.long("network_tx_bytes")?,
+        interfaces,
+        workload_class: fields.optional_string("workload_class")?,
+        cloud: fields.optional_string("cloud")?,
+        region: fields.optional_string("region")?,
+        extra,
+        timestamp: fields.timestamp("timestamp")?,
+    })
+}
+
+// A decoded record's fields by name, unions unwrapped
+struct Fields(HashMap<String, Value>);
+
+impl Fields {
+    fn of(value: Value) -> Result<Self> {
+        match value {
+            Value::Record(fields) => Ok(Self(fields.into_iter().collect())),
+            other => anyhow::bail!("expected an Avro record, got {:?}", other),
+        }
+    }
+    
+    fn take(&mut self, name: &str) -> Result<Value> {
+        match self.0.remove(name) {
+            Some(Value::Union(_, value)) => Ok(*value),
+            Some(value) => Ok(value),
+            None => anyhow::bail!("the Avro record has no {}", name),
+        }
+    }
+    
+    fn string(&mut self, name: &str) -> Result<String> {
+        match self.take(name)? {
+            Value::String(value) => Ok(value),
+            other => anyhow::bail!("{} is {:?}, not a string", name, other),
+        }
+    }
+    
+    fn optional_string(&mut self, name: &str) -> Result<Option<String>> {
+        match self.take(name)? {
+            Value::Null => Ok(None),
+            Value::String(value) => Ok(Some(value)),
+            other => anyhow::bail!("{} is {:?}, not a string", name, other),
+        }
+    }
+    
+    fn long(&mut self, name: &str) -> Result<u64> {
+        match self.take(name)? {
+            Value::Long(value) => Ok(value.max(0) as u64),
+            other => anyhow::bail!("{} is {:?}, not a long", name, other),
+        }
+    }
+    
+    fn double(&mut self, name: &str) -> Result<f64> {
+        match self.take(name)? {
+            Value::Double(value) => Ok(value),
+            other => anyhow::bail!("{} is {:?}, not a double", name, other),
+        }
+    }
+    
+    fn array(&mut self, name: &str) -> Result<Vec<Value>> {
+        match self.take(name)? {
+            Value::Array(values) => Ok(values),
+            other => anyhow::bail!("{} is {:?}, not an array", name, other),
+        }
+    }
+    
+    fn timestamp(&mut self, name: &str) -> Result<DateTime<Utc>> {
+        match self.take(name)? {
+            Value::TimestampMicros(micros) | Value::Long(micros) => {
+                DateTime::from_timestamp_micros(micros).with_context(|| format!("{} is out of range", name))
+            }
+            other => anyhow::bail!("{} is {:?}, not a timestamp", name, other),
+        }
+    }
+}
+
+fn frame<T: AvroRecord>(id: u32, record: &T) -> Result<Vec<u8>> {
+    let datum = apache_avro::to_avro_datum(T::schema().parsed(), record.to_avro())
+        .context("Cannot encode the payload as Avro")?;
+    
+    let mut payload = Vec::with_capacity(5 + datum.len());
+    payload.push(MAGIC_BYTE);
+    payload.extend_from_slice(&id.to_be_bytes());
+    payload.extend_from_slice(&datum);
+    Ok(payload)
+}
+
+// Registers each payload schema under its topic's subject on first use and
+// frames payloads with the id it's given
+pub struct SchemaRegistry {
+    url: String,
+    username: Option<String>,
+    password: Option<String>,
+    http_client: HttpClient,
+    // Schema id by subject
+    ids: RwLock<HashMap<String, u32>>,
+}
+
+#[derive(Deserialize)]
+struct RegisteredSchema {
+    id: u32,
+}
+
+impl SchemaRegistry {
+    pub fn new(config: &SchemaRegistryConfig) -> Result<Self> {
+        Ok(Self {
+            url: config.url.trim_end_matches('/').to_string(),
+            username: config.username.clone(),
+            password: config.password.clone(),
+            http_client: HttpClient::builder()
+                .timeout(Duration::from_secs(config.timeout_seconds))
+                .build()?,
+            ids: RwLock::new(HashMap::new()),
+        })
+    }
+    
+    // Fails unless the registry takes the record's schema for the topic
+    pub async fn register<T: AvroRecord>(&self, topic: &str) -> Result<()> {
+        self.schema_id(&format!("{}-value", topic), T::schema()).await?;
+        Ok(())
+    }
+    
+    // The record in Confluent's wire format for the topic, under the
+    // TopicNameStrategy subject "<topic>-value"
+    pub async fn encode<T: AvroRecord>(&self, topic: &str, record: &T) -> Result<Vec<u8>> {
+        let id = self.schema_id(&format!("{}-value", topic), T::schema()).await?;
+        frame(id, record)
+    }
+    
+    // Registering a schema the subject already has returns its existing id
+    async fn schema_id(&self, subject: &str, schema: &AvroSchema) -> Result<u32> {
+        if let Some(id) = self.ids.read().await.get(subject) {
+            return Ok(*id);
+        }
+        
+        let mut request = self.http_client
+            .post(format!("{}/subjects/{}/versions", self.url, subject))
+            .header("Content-Type", "application/vnd.schemaregistry.v1+json")
+            .json(&json!({ "schema": schema.json }));
+        if let Some(username) = &self.username {
+            request = request.basic_auth(username, self.password.as_ref());
+        }
+        let registered: RegisteredSchema = request.send().await?
+            .error_for_status()
+            .with_context(|| format!("Schema Registry refused the schema for {}", subject))?
+            .json()
+            .await?;
+        
+        info!("Using schema {} for {}", registered.id, subject);
+        self.ids.write().await.insert(subject.to_string(), registered.id);
+        Ok(registered.id)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::collections::BTreeMap;
+    
+    fn server_metrics() -> ServerMetrics {
+        ServerMetrics {
+            server_id: "vm-1".to_string(),
+            cpu_utilization: 42.5,
+            memory_usage: 1024,
+            memory_total: 4096,
+            disk_read_bytes: 10,
+            disk_write_bytes: 20,
+            network_rx_bytes: 30,
+            network_tx_bytes: u64::MAX,
+            interfaces: vec![InterfaceTraffic {
+                mac_address: "fa:16:3e:00:00:01".to_string(),
+                rx_bytes: 1,
+                tx_bytes: 2,
+                rx_packets: 3,
+                tx_packets: 4,
+                rx_dropped: 5,
+                tx_dropped: 6,
+            }],
+            workload_class: Some("web".to_string()),
+            cloud: None,
+            region: Some("RegionOne".to_string()),
+            extra: BTreeMap::from([("cpu_steal".to_string(), 1.5)]),
+            timestamp: Utc.timestamp_opt(1_700_000_000, 123_456_000).unwrap(),
+        }
+    }
+    
+    #[test]
+    fn round_trips_server_metrics() {
+        let metrics = server_metrics();
+        let payload = frame(7, &metrics).unwrap();
+        assert_eq!(payload[0], MAGIC_BYTE);
+        assert_eq!(payload[1..5], 7u32.to_be_bytes());
+        
+        let parsed = parse_server_metrics(&payload).unwrap();
+        // Counters saturate at i64::MAX
+        let expected = ServerMetrics { network_tx_bytes: i64::MAX as u64, ..metrics };
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&expected).unwrap());
+    }
+    
+    #[test]
+    fn parses_json_payloads() {
+        let metrics = server_metrics();
+        let payload = serde_json::to_vec(&metrics).unwrap();
+        let parsed = parse_server_metrics(&payload).unwrap();
+        assert_eq!(serde_json::to_value(&parsed).unwrap(), serde_json::to_value(&metrics).unwrap());
+    }
+    
+    #[test]
+    fn rejects_truncated_avro_payloads() {
+        let payload = frame(7, &server_metrics()).unwrap();
+        assert!(parse_server_metrics(&payload[..3]).is_err());
+        assert!(parse_server_metrics(&payload[..payload.len() / 2]).is_err());
+    }
+    
+    #[test]
+    fn encodes_network_and_storage_metrics() {
+        let timestamp = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let network = NetworkMetrics {
+            network_id: "net-1".to_string(),
+            network_name: None,
+            router_id: Some("router-1".to_string()),
+            port_id: "port-1".to_string(),
+            server_id: "vm-1".to_string(),
+            fixed_ips: vec!["10.0.0.5".to_string()],
+            floating_ips: Vec::new(),
+            rx_bytes: 1,
+            tx_bytes: 2,
+            rx_packets: 3,
+            tx_packets: 4,
+            packet_loss: 0.01,
+            timestamp,
+        };
+        let storage = StorageMetrics {
+            volume_id: "vol-1".to_string(),
+            name: Some("data".to_string()),
+            server_id: "vm-1".to_string(),
+            device: Some("/dev/vdb".to_string()),
+            size_gb: 100,
+            status: "in-use".to_string(),
+            volume_type: None,
+            backend: None,
+            bootable: false,
+            timestamp,
+        };
+        assert!(frame(1, &network).is_ok());
+        assert!(frame(2, &storage).is_ok());
+    }
+}
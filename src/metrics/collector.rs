@@ -1,81 +1,369 @@
 use anyhow::Result;
 use dashmap::DashMap;
-use std::sync::Arc;
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
 use std::time::Duration;
+use tokio::sync::{broadcast, mpsc};
 use tokio::time::interval;
-use tracing::{debug, error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
 
 use crate::config::MetricsConfig;
-use crate::openstack::Client;
-use super::kafka_producer::KafkaProducer;
+use crate::coordination::Coordinator;
+use crate::openstack::services::{Server, ServerMetrics};
+use crate::openstack::{Client, Clouds};
+use crate::plugins::{self, MetricSink, MetricSource};
+use crate::scheduler::resource_scheduler::SLA_POLICY_COLLECTION;
+use crate::scheduler::sla_manager::SLAPolicy;
+use crate::storage::Storage;
+use crate::telemetry;
+use super::agent::{AgentIngest, AgentReport, IngestResult};
+use super::edf::{CollectionJob, CollectionQueue, CollectionQueueStats, Push};
+use super::history::MetricHistory;
+use super::intervals::CollectionIntervals;
+use super::kafka_consumer::ExternalMetricsConsumer;
+use super::latest::LatestMetrics;
+use super::lifecycle::{Changelog, LifecycleEvent, LifecycleKind};
+use super::notifications::{NotificationListener, ResourceEvent};
+use super::processor::MetricsProcessor;
+use super::sharding::ShardCoordinator;
+
+// Longest wait for sinks to send buffered samples on shutdown
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Stands in for the Swift account on the shard ring
+const OBJECT_STORAGE_SHARD_KEY: &str = "swift-account";
 
 pub struct MetricsCollector {
     config: MetricsConfig,
-    openstack_client: Arc<Client>,
-    kafka_producer: KafkaProducer,
+    clouds: Clouds,
+    sources: Arc<Vec<Arc<dyn MetricSource>>>,
+    sinks: Arc<Vec<Arc<dyn MetricSink>>>,
+    processor: Arc<MetricsProcessor>,
     active_resources: Arc<DashMap<String, ResourceInfo>>,
+    latest_metrics: Arc<LatestMetrics>,
+    history: Arc<MetricHistory>,
+    shards: Arc<ShardCoordinator>,
+    intervals: Arc<CollectionIntervals>,
+    queue: Arc<CollectionQueue>,
+    changelog: Arc<Changelog>,
+    agents: Arc<AgentIngest>,
+    // When the last full discovery started; servers created before it that
+    // turn up now were another member's, or are seen again after a restart
+    last_discovery: Arc<Mutex<Option<chrono::DateTime<chrono::Utc>>>>,
+    // For the SLA policies' collection intervals
+    storage: Storage,
+    // Every collected server sample, for streaming consumers
+    samples: broadcast::Sender<ServerMetrics>,
 }
 
 #[derive(Debug, Clone)]
 pub struct ResourceInfo {
     pub resource_type: String,
+    pub project_id: Option<String>,
+    // None for the main credentials
+    pub cloud: Option<String>,
+    pub region: Option<String>,
+    // From the server's image, when it has one
+    pub workload_class: Option<String>,
     pub last_collected: chrono::DateTime<chrono::Utc>,
     pub collection_interval: Duration,
+    // The interval plus jitter after the last collection
+    pub next_collection: chrono::DateTime<chrono::Utc>,
+    // Ports and volumes are read along with the server once their own
+    // intervals have passed
+    pub ports_collected: Option<chrono::DateTime<chrono::Utc>>,
+    pub volumes_collected: Option<chrono::DateTime<chrono::Utc>>,
+    // As last listed
+    pub status: String,
+    pub first_seen: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    // Successful listings of its region in a row it was missing from
+    pub missed_discoveries: u32,
 }
 
 impl MetricsCollector {
     pub async fn new(
         config: &MetricsConfig,
-        openstack_client: Arc<Client>,
+        clouds: Clouds,
+        storage: Storage,
+        coordinator: Coordinator,
     ) -> Result<Self> {
-        let kafka_producer = KafkaProducer::new(&config.kafka_config).await?;
+        let registry = plugins::registry();
+        let mut sources = Vec::new();
+        for source in &config.sources {
+            sources.push(registry.source(source, config).await?);
+        }
+        let mut sinks = Vec::new();
+        for sink in &config.sinks {
+            sinks.push(registry.sink(sink, config).await?);
+        }
+        let processor = MetricsProcessor::new(&config.transforms)?;
+        let history = MetricHistory::load(config.history.clone(), storage.clone()).await?;
+        let shards = ShardCoordinator::new(config.sharding.clone(), coordinator);
         
         Ok(Self {
             config: config.clone(),
-            openstack_client,
-            kafka_producer,
+            clouds,
+            sources: Arc::new(sources),
+            sinks: Arc::new(sinks),
+            processor: Arc::new(processor),
             active_resources: Arc::new(DashMap::new()),
+            latest_metrics: Arc::new(LatestMetrics::new(config.stale_after_seconds)),
+            history: Arc::new(history),
+            shards: Arc::new(shards),
+            intervals: Arc::new(CollectionIntervals::new(config)),
+            queue: Arc::new(CollectionQueue::new(&config.collection)),
+            changelog: Arc::new(Changelog::new(config.lifecycle.changelog_size)),
+            agents: Arc::new(AgentIngest::new(&config.agent_ingest)),
+            last_discovery: Arc::default(),
+            storage,
+            samples: broadcast::channel(1024).0,
         })
     }
     
-    pub async fn start_collection(&self) -> Result<()> {
+    pub fn latest_metrics(&self) -> Arc<LatestMetrics> {
+        self.latest_metrics.clone()
+    }
+    
+    pub fn history(&self) -> Arc<MetricHistory> {
+        self.history.clone()
+    }
+    
+    pub fn shards(&self) -> Arc<ShardCoordinator> {
+        self.shards.clone()
+    }
+    
+    pub fn clouds(&self) -> &Clouds {
+        &self.clouds
+    }
+    
+    // Every discovered resource
+    pub fn resources(&self) -> Vec<(String, ResourceInfo)> {
+        self.active_resources.iter()
+            .map(|entry| (entry.key().clone(), entry.value().clone()))
+            .collect()
+    }
+    
+    // Fails unless every sink can take samples
+    pub async fn check_sinks(&self) -> Result<()> {
+        for sink in self.sinks.iter() {
+            sink.check().await.map_err(|e| e.context(format!("metric sink {} isn't ready", sink.name())))?;
+        }
+        Ok(())
+    }
+    
+    pub fn changelog(&self) -> Arc<Changelog> {
+        self.changelog.clone()
+    }
+    
+    pub fn collection_stats(&self) -> CollectionQueueStats {
+        self.queue.stats()
+    }
+    
+    pub fn subscribe_samples(&self) -> broadcast::Receiver<ServerMetrics> {
+        self.samples.subscribe()
+    }
+    
+    pub fn accepts_agent_reports(&self) -> bool {
+        self.agents.is_enabled()
+    }
+    
+    // The samples of servers in this member's shard go through the same
+    // pipeline as polled ones. Fails only for a malformed report
+    pub async fn ingest_agent_report(&self, mut report: AgentReport) -> Result<IngestResult> {
+        self.agents.check(&report)?;
+        let mut result = IngestResult::default();
+        let reported = report.domains.len();
+        report.domains.retain(|domain| self.shards.owns(&domain.server_id));
+        result.not_owned = reported - report.domains.len();
+        
+        let span = info_span!("metrics.collect", source = "agent", hypervisor = %report.hypervisor);
+        for metrics in self.agents.samples(report, &mut result) {
+            self.record_sample(metrics, "agent").instrument(span.clone()).await;
+        }
+        for (outcome, count) in [
+            ("recorded", result.recorded),
+            ("baseline", result.baseline),
+            ("stale", result.stale),
+            ("not_owned", result.not_owned),
+        ] {
+            ::metrics::counter!("agent_domains_total", "outcome" => outcome).increment(count as u64);
+        }
+        Ok(result)
+    }
+    
+    // Runs until shutdown is cancelled, then persists history and flushes
+    // samples the sinks still hold
+    pub async fn start_collection(&self, shutdown: CancellationToken) -> Result<()> {
         info!("Starting metrics collection service");
         
+        // Join the ring before the first discovery so it only picks up our share
+        if self.shards.is_enabled() {
+            self.shards.heartbeat().await?;
+        }
+        let shard_handle = tokio::spawn({
+            let shards = self.shards.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                shards.run(shutdown).await;
+            }
+        });
+        
         // Start resource discovery
         let discovery_handle = tokio::spawn({
             let collector = self.clone();
+            let shutdown = shutdown.clone();
             async move {
-                collector.resource_discovery_loop().await;
+                collector.resource_discovery_loop(shutdown).await;
+            }
+        });
+        
+        // Discovery between full listings, from the services' notifications
+        let notification_handle = tokio::spawn({
+            let collector = self.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                collector.notification_loop(shutdown).await;
             }
         });
         
         // Start metrics collection
         let collection_handle = tokio::spawn({
             let collector = self.clone();
+            let shutdown = shutdown.clone();
             async move {
-                collector.metrics_collection_loop().await;
+                collector.metrics_collection_loop(shutdown).await;
             }
         });
         
         // Start EDF scheduler for critical metrics
         let edf_handle = tokio::spawn({
             let collector = self.clone();
+            let shutdown = shutdown.clone();
             async move {
-                collector.edf_scheduling_loop().await;
+                collector.edf_scheduling_loop(shutdown).await;
             }
         });
         
+        // Persist metric history for dashboard charts
+        let history_handle = tokio::spawn({
+            let collector = self.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                collector.history_checkpoint_loop(shutdown).await;
+            }
+        });
+        
+        // Swift account usage, for capacity planning
+        let object_storage_handle = tokio::spawn({
+            let collector = self.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                collector.object_storage_loop(shutdown).await;
+            }
+        });
+        
+        // Octavia listener traffic and member health
+        let load_balancer_handle = tokio::spawn({
+            let collector = self.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                collector.load_balancer_loop(shutdown).await;
+            }
+        });
+        
+        // Samples external agents publish to Kafka
+        let external_handle = tokio::spawn({
+            let collector = self.clone();
+            let shutdown = shutdown.clone();
+            async move {
+                collector.external_metrics_loop(shutdown).await;
+            }
+        });
+        
+        let source_handles: Vec<_> = self.sources.iter()
+            .map(|source| tokio::spawn({
+                let collector = self.clone();
+                let source = source.clone();
+                let shutdown = shutdown.clone();
+                async move {
+                    collector.source_loop(source, shutdown).await;
+                }
+            }))
+            .collect();
+        
         // Wait for all tasks
-        tokio::try_join!(discovery_handle, collection_handle, edf_handle)?;
+        tokio::try_join!(discovery_handle, notification_handle, collection_handle, edf_handle, history_handle, object_storage_handle, load_balancer_handle, shard_handle, external_handle)?;
+        for handle in source_handles {
+            handle.await?;
+        }
+        
+        self.history.checkpoint().await;
+        for sink in self.sinks.iter() {
+            if let Err(e) = sink.flush(FLUSH_TIMEOUT).await {
+                warn!("Failed to flush metric sink {}: {}", sink.name(), e);
+            }
+        }
+        info!("Metrics collection stopped");
         
         Ok(())
     }
     
-    async fn resource_discovery_loop(&self) {
-        let mut interval = interval(Duration::from_secs(self.config.discovery_interval_seconds));
+    // One discovery and one pass over every compute resource regardless of
+    // its interval. The samples reach the caches; only with publish set do
+    // they also go to the sinks and the history store, like a regular
+    // collection's
+    pub async fn collect_once(&self, publish: bool) -> Result<Vec<ServerMetrics>> {
+        self.discover_resources().await?;
+        
+        let mut collection_tasks = Vec::new();
+        for entry in self.active_resources.iter().filter(|entry| entry.value().resource_type == "compute") {
+            let resource_id = entry.key().clone();
+            let client = self.client_for(entry.value());
+            let span = info_span!("metrics.collect", resource_id = %resource_id, resource_type = "compute");
+            collection_tasks.push((span.clone(), tokio::spawn(async move {
+                client.nova.get_server_metrics(&resource_id).await
+            }.instrument(span))));
+        }
+        
+        let mut collected = Vec::new();
+        for (span, task) in collection_tasks {
+            match task.await? {
+                Ok(mut metrics) => {
+                    self.tag_sample(&mut metrics);
+                    self.processor.process(&mut metrics, "nova");
+                    self.latest_metrics.record_server_metrics(&metrics);
+                    self.history.record_server_metrics(&metrics);
+                    if publish {
+                        self.publish_server_metrics(&metrics).instrument(span).await?;
+                    }
+                    collected.push(metrics);
+                }
+                Err(e) => error!("Metrics collection failed: {}", e),
+            }
+        }
+        if publish {
+            self.history.checkpoint().await;
+        }
+        
+        info!("Collected metrics for {} of {} compute resources", collected.len(), self.active_resources.len());
+        Ok(collected)
+    }
+    
+    // With notifications followed, a full listing is only a resync
+    async fn resource_discovery_loop(&self, shutdown: CancellationToken) {
+        let seconds = match self.config.notifications.enabled {
+            true => self.config.notifications.resync_interval_seconds,
+            false => self.config.discovery_interval_seconds,
+        };
+        let mut interval = interval(Duration::from_secs(seconds));
         
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
             
             if let Err(e) = self.discover_resources().await {
                 error!("Resource discovery failed: {}", e);
@@ -83,112 +371,546 @@ impl MetricsCollector {
         }
     }
     
+    // Every region is discovered even when one fails; the last failure is
+    // returned
     async fn discover_resources(&self) -> Result<()> {
         debug!("Discovering OpenStack resources");
+        let started = chrono::Utc::now();
         
-        // Discover compute instances
-        let servers = self.openstack_client.nova.list_servers().await?;
-        for server in servers {
-            self.active_resources.insert(
-                server.id.clone(),
-                ResourceInfo {
-                    resource_type: "compute".to_string(),
-                    last_collected: chrono::Utc::now(),
-                    collection_interval: Duration::from_secs(self.config.compute_interval_seconds),
-                }
-            );
+        // Another member collects them now
+        let moved: Vec<String> = self.active_resources.iter()
+            .filter(|entry| !self.shards.owns(entry.key()))
+            .map(|entry| entry.key().clone())
+            .collect();
+        for resource_id in moved {
+            self.remove_resource(&resource_id, LifecycleKind::Released, "rebalanced").await;
+        }
+        // Keeps the intervals it had when the policies can't be read
+        match self.storage.list::<SLAPolicy>(SLA_POLICY_COLLECTION).await {
+            Ok(policies) => self.intervals.set_sla_policies(&policies),
+            Err(e) => warn!("Failed to read SLA policies for collection intervals: {}", e),
+        }
+        let mut result = Ok(());
+        for client in self.clouds.all() {
+            if let Err(e) = self.discover_region(client).await {
+                result = Err(e.context(match client.cloud() {
+                    Some(cloud) => format!("region {} of cloud {}", client.region(), cloud),
+                    None => format!("region {}", client.region()),
+                }));
+            }
         }
         
+        *self.last_discovery.lock().unwrap() = Some(started);
         debug!("Discovered {} compute resources", self.active_resources.len());
+        result
+    }
+    
+    // Compute instances in the client's region, keeping only this member's
+    // shard. Those of the region missing from the listing too many times in
+    // a row are dropped
+    async fn discover_region(&self, client: &Client) -> Result<()> {
+        let mut servers = client.nova.list_servers().await?;
+        if let Err(e) = client.glance.enrich(&mut servers).await {
+            warn!("Failed to read image metadata from Glance in {}: {}", client.region(), e);
+        }
+        let mut seen = HashSet::new();
+        for server in servers.into_iter().filter(|server| self.shards.owns(&server.id)) {
+            seen.insert(server.id.clone());
+            self.track_server(&server).await;
+        }
+        
+        let mut missing = Vec::new();
+        for mut entry in self.active_resources.iter_mut() {
+            if seen.contains(entry.key()) {
+                continue;
+            }
+            let resource_id = entry.key().clone();
+            let info = entry.value_mut();
+            if info.cloud.as_deref() != client.cloud() || info.region.as_deref() != Some(client.region()) {
+                continue;
+            }
+            info.missed_discoveries += 1;
+            if info.missed_discoveries >= self.config.lifecycle.evict_after_missed_discoveries {
+                missing.push(resource_id);
+            }
+        }
+        for resource_id in missing {
+            self.remove_resource(&resource_id, LifecycleKind::Removed, "not_seen").await;
+        }
         Ok(())
     }
     
-    async fn metrics_collection_loop(&self) {
+    // A server seen again keeps its schedule unless its interval changed
+    async fn track_server(&self, server: &Server) {
+        self.latest_metrics.record_status(&server.id, &server.status);
+        if matches!(server.status.as_str(), "DELETED" | "SOFT_DELETED") {
+            self.remove_resource(&server.id, LifecycleKind::Removed, "deleted").await;
+            return;
+        }
+        
+        let workload_class = server.image_metadata.as_ref().and_then(|image| image.workload_class.clone());
+        let interval = self.intervals.compute(&server.id, &server.metadata, workload_class.as_deref());
+        let now = chrono::Utc::now();
+        let previous = self.active_resources.get(&server.id).map(|info| info.clone());
+        let (last_collected, next_collection, ports_collected, volumes_collected) = match &previous {
+            Some(info) if info.collection_interval == interval => {
+                (info.last_collected, info.next_collection, info.ports_collected, info.volumes_collected)
+            }
+            Some(info) => (info.last_collected, self.intervals.first(now, interval), info.ports_collected, info.volumes_collected),
+            None => (now, self.intervals.first(now, interval), None, None),
+        };
+        let info = ResourceInfo {
+            resource_type: "compute".to_string(),
+            project_id: server.tenant_id.clone(),
+            cloud: server.cloud.clone(),
+            region: server.region.clone(),
+            workload_class,
+            last_collected,
+            collection_interval: interval,
+            next_collection,
+            ports_collected,
+            volumes_collected,
+            status: server.status.clone(),
+            first_seen: previous.as_ref().map_or(now, |info| info.first_seen),
+            last_seen: now,
+            missed_discoveries: 0,
+        };
+        self.active_resources.insert(server.id.clone(), info.clone());
+        
+        let change = match &previous {
+            None => Some(self.arrival(server)),
+            Some(previous) if server.status == "ERROR" && previous.status != "ERROR" => {
+                Some((LifecycleKind::Errored, server.fault.as_ref().map(|fault| fault.message.clone())))
+            }
+            Some(previous) if previous.status == "ERROR" && server.status != "ERROR" => Some((LifecycleKind::Recovered, None)),
+            Some(_) => None,
+        };
+        if let Some((kind, reason)) = change {
+            self.record_lifecycle(LifecycleEvent::new(&server.id, &info, kind, reason)).await;
+        }
+    }
+    
+    // Added for a server created since the last discovery, Acquired for one
+    // that existed then but wasn't ours
+    fn arrival(&self, server: &Server) -> (LifecycleKind, Option<String>) {
+        let created = chrono::DateTime::parse_from_rfc3339(&server.created).ok();
+        match *self.last_discovery.lock().unwrap() {
+            None => (LifecycleKind::Acquired, Some("started".to_string())),
+            Some(last) if created.is_some_and(|created| created < last) => (LifecycleKind::Acquired, Some("rebalanced".to_string())),
+            Some(_) => (LifecycleKind::Added, None),
+        }
+    }
+    
+    async fn remove_resource(&self, resource_id: &str, kind: LifecycleKind, reason: &str) {
+        if let Some((_, info)) = self.active_resources.remove(resource_id) {
+            debug!("No longer collecting {} ({})", resource_id, reason);
+            self.record_lifecycle(LifecycleEvent::new(resource_id, &info, kind, Some(reason.to_string()))).await;
+        }
+    }
+    
+    // Into the changelog and, unless only ownership changed, every sink; a
+    // sink failing is only logged
+    async fn record_lifecycle(&self, event: LifecycleEvent) {
+        ::metrics::counter!("resource_lifecycle_events_total", "kind" => event.kind.as_str()).increment(1);
+        for sink in self.sinks.iter().filter(|_| !event.kind.is_ownership()) {
+            if let Err(e) = sink.publish_lifecycle(&event).await {
+                warn!("Failed to publish the lifecycle event of {} to {}: {}", event.resource_id, sink.name(), e);
+            }
+        }
+        self.changelog.record(event);
+    }
+    
+    async fn notification_loop(&self, shutdown: CancellationToken) {
+        if !self.config.notifications.enabled {
+            return;
+        }
+        
+        let (sender, mut events) = mpsc::channel(1024);
+        let listener = NotificationListener::new(self.config.notifications.clone());
+        let listener_handle = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { listener.run(sender, shutdown).await }
+        });
+        
+        while let Some(event) = events.recv().await {
+            if let Err(e) = self.apply_event(event).await {
+                warn!("Failed to apply OpenStack notification: {}", e);
+            }
+        }
+        let _ = listener_handle.await;
+    }
+    
+    // Only this member's shard is tracked, as in a full discovery
+    async fn apply_event(&self, event: ResourceEvent) -> Result<()> {
+        match event {
+            ResourceEvent::ServerDeleted(server_id) => {
+                self.remove_resource(&server_id, LifecycleKind::Removed, "deleted").await;
+            }
+            ResourceEvent::ServerChanged(server_id) if self.shards.owns(&server_id) => {
+                // Keeps the cloud it was discovered under; new servers are
+                // looked up in the broker's region
+                let client = match self.active_resources.get(&server_id) {
+                    Some(info) => self.client_for(&info),
+                    None => self.config.notifications.region.as_deref()
+                        .and_then(|region| self.clouds.get(None, region))
+                        .cloned()
+                        .unwrap_or_else(|| self.clouds.primary()),
+                };
+                let mut servers = vec![client.nova.get_server(&server_id).await?];
+                if let Err(e) = client.glance.enrich(&mut servers).await {
+                    warn!("Failed to read image metadata from Glance for {}: {}", server_id, e);
+                }
+                if !self.active_resources.contains_key(&server_id) {
+                    debug!("Server {} appeared, collecting it", server_id);
+                }
+                self.track_server(&servers[0]).await;
+            }
+            ResourceEvent::ServerChanged(_) => {}
+        }
+        Ok(())
+    }
+    
+    // The client of the cloud and region the resource was discovered in
+    fn client_for(&self, info: &ResourceInfo) -> Arc<Client> {
+        info.region.as_deref()
+            .and_then(|region| self.clouds.get(info.cloud.as_deref(), region))
+            .cloned()
+            .unwrap_or_else(|| self.clouds.primary())
+    }
+    
+    // Queues what's due; edf_scheduling_loop runs it
+    async fn metrics_collection_loop(&self, shutdown: CancellationToken) {
         let mut interval = interval(Duration::from_millis(100)); // High frequency for real-time
         
         loop {
-            interval.tick().await;
-            
-            if let Err(e) = self.collect_all_metrics().await {
-                error!("Metrics collection failed: {}", e);
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
             }
+            
+            self.release_due_collections();
         }
     }
     
-    async fn collect_all_metrics(&self) -> Result<()> {
+    // Rescheduled when queued rather than when done, so a slow call to Nova
+    // isn't queued again on the next tick. A collection's deadline is when
+    // the next one is due. Once the queue is full the rest stay due, and are
+    // released as it drains
+    fn release_due_collections(&self) {
         let now = chrono::Utc::now();
-        let mut collection_tasks = Vec::new();
+        // Longest overdue first, so a full queue holds back the ones that
+        // came due last rather than whichever the map yields last
+        let mut due: Vec<(chrono::DateTime<chrono::Utc>, String)> = self.active_resources.iter()
+            .filter(|entry| entry.next_collection <= now)
+            .map(|entry| (entry.next_collection, entry.key().clone()))
+            .collect();
+        due.sort();
         
-        // Collect metrics for resources that need updating
-        for entry in self.active_resources.iter() {
-            let resource_id = entry.key().clone();
-            let resource_info = entry.value().clone();
+        for (_, resource_id) in due {
+            let Some(mut info) = self.active_resources.get_mut(&resource_id) else {
+                continue;
+            };
+            let next_collection = self.intervals.next(now, info.collection_interval);
+            let collect_ports = is_due(info.ports_collected, now, self.intervals.network());
+            let collect_volumes = is_due(info.volumes_collected, now, self.intervals.storage());
+            let priority = self.intervals.priority(&resource_id);
+            let job = CollectionJob::new(resource_id.clone(), info.clone(), collect_ports, collect_volumes, next_collection, priority);
             
-            if now.signed_duration_since(resource_info.last_collected).num_seconds() 
-                >= resource_info.collection_interval.as_secs() as i64 {
-                
-                let client = self.openstack_client.clone();
-                let producer = self.kafka_producer.clone();
+            match self.queue.push(job) {
+                Push::Queued => {
+                    info.last_collected = now;
+                    if collect_ports {
+                        info.ports_collected = Some(now);
+                    }
+                    if collect_volumes {
+                        info.volumes_collected = Some(now);
+                    }
+                }
+                Push::InFlight => debug!("Collection of {} is still queued or running, skipping this period", resource_id),
+                Push::Full => {
+                    debug!("Collection queue is full, holding back due resources");
+                    return;
+                }
+            }
+            info.next_collection = next_collection;
+        }
+    }
+    
+    // Reads the resource's samples, then publishes them in a task of its
+    // own, so a preempted collection loses only its calls to OpenStack and
+    // never leaves a sample half published
+    async fn collect_resource(&self, job: &CollectionJob) {
+        match job.info.resource_type.as_str() {
+            "compute" => {
+                let client = self.client_for(&job.info);
+                let Ok(metrics) = client.nova.get_server_metrics(&job.resource_id).await else {
+                    return;
+                };
+                // Per-port traffic, tied to the server through its vNICs
+                let ports = match job.collect_ports {
+                    true => client.neutron.port_traffic(&metrics).await.unwrap_or_else(|e| {
+                        debug!("Failed to match the ports of {}: {}", job.resource_id, e);
+                        Vec::new()
+                    }),
+                    false => Vec::new(),
+                };
+                let volumes = match job.collect_volumes {
+                    true => client.cinder.storage_metrics(&job.resource_id).await.unwrap_or_else(|e| {
+                        debug!("Failed to list the volumes of {}: {}", job.resource_id, e);
+                        Vec::new()
+                    }),
+                    false => Vec::new(),
+                };
                 
-                let task = tokio::spawn(async move {
-                    match resource_info.resource_type.as_str() {
-                        "compute" => {
-                            if let Ok(metrics) = client.nova.get_server_metrics(&resource_id).await {
-                                let _ = producer.send_server_metrics(&metrics).await;
-                            }
-                        },
-                        "network" => {
-                            if let Ok(metrics) = client.neutron.get_network_metrics().await {
-                                for metric in metrics {
-                                    let _ = producer.send_network_metrics(&metric).await;
-                                }
-                            }
-                        },
-                        "storage" => {
-                            if let Ok(metrics) = client.cinder.get_storage_metrics().await {
-                                for metric in metrics {
-                                    let _ = producer.send_storage_metrics(&metric).await;
-                                }
+                let collector = self.clone();
+                tokio::spawn(async move {
+                    for port in ports {
+                        for sink in collector.sinks.iter() {
+                            let _ = sink.publish_network(&port).await;
+                        }
+                    }
+                    for volume in volumes {
+                        for sink in collector.sinks.iter() {
+                            let _ = sink.publish_storage(&volume).await;
+                        }
+                    }
+                    collector.record_sample(metrics, "nova").await;
+                }.instrument(Span::current()));
+            },
+            _ => {}
+        }
+    }
+    
+    // Publishes the Swift account's usage on its own, slower interval. With
+    // sharding only the member owning the account collects it
+    async fn object_storage_loop(&self, shutdown: CancellationToken) {
+        let client = self.clouds.primary();
+        let swift = &client.swift;
+        if !swift.is_available() {
+            debug!("No object-store endpoint in the catalog, not collecting Swift metrics");
+            return;
+        }
+        let mut interval = interval(Duration::from_secs(self.config.object_storage_interval_seconds));
+        
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            if !self.shards.owns(OBJECT_STORAGE_SHARD_KEY) {
+                continue;
+            }
+            
+            match swift.object_storage_metrics().await {
+                Ok(metrics) => {
+                    debug!("Swift account {} holds {} bytes in {} containers", metrics.account, metrics.bytes_used, metrics.container_count);
+                    for sink in self.sinks.iter() {
+                        if let Err(e) = sink.publish_object_storage(&metrics).await {
+                            warn!("Failed to publish Swift metrics to {}: {}", sink.name(), e);
+                        }
+                    }
+                }
+                Err(e) => warn!("Swift metrics collection failed: {}", e),
+            }
+        }
+    }
+    
+    // Publishes each load balancer's traffic and member health, and records
+    // its availability so SLA policies on load balancers can be checked. With
+    // sharding each member covers the load balancers it owns
+    async fn load_balancer_loop(&self, shutdown: CancellationToken) {
+        let client = self.clouds.primary();
+        let octavia = &client.octavia;
+        if !octavia.is_available() {
+            debug!("No load-balancer endpoint in the catalog, not collecting Octavia metrics");
+            return;
+        }
+        let mut interval = interval(Duration::from_secs(self.config.loadbalancer_interval_seconds));
+        
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            
+            let load_balancers = match octavia.list_load_balancers().await {
+                Ok(load_balancers) => load_balancers,
+                Err(e) => {
+                    warn!("Failed to list Octavia load balancers: {}", e);
+                    continue;
+                }
+            };
+            for load_balancer in load_balancers.iter().filter(|lb| self.shards.owns(&lb.id)) {
+                let span = info_span!("metrics.collect", resource_id = %load_balancer.id, resource_type = "loadbalancer");
+                match octavia.load_balancer_metrics(load_balancer).instrument(span.clone()).await {
+                    Ok(metrics) => {
+                        if let Some(availability_percent) = metrics.availability_percent {
+                            self.latest_metrics.record_availability(&metrics.loadbalancer_id, availability_percent);
+                        }
+                        for sink in self.sinks.iter() {
+                            if let Err(e) = sink.publish_load_balancer(&metrics).instrument(span.clone()).await {
+                                warn!("Failed to publish load balancer metrics to {}: {}", sink.name(), e);
                             }
-                        },
-                        _ => {}
+                        }
                     }
-                });
-                
-                collection_tasks.push(task);
+                    Err(e) => warn!("Metrics collection for load balancer {} failed: {}", load_balancer.id, e),
+                }
             }
         }
+    }
+    
+    // Polls an extra source on the compute interval, keeping only samples for
+    // this member's shard
+    async fn source_loop(&self, source: Arc<dyn MetricSource>, shutdown: CancellationToken) {
+        let mut interval = interval(Duration::from_secs(self.config.compute_interval_seconds));
         
-        // Wait for all collection tasks to complete
-        for task in collection_tasks {
-            let _ = task.await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            
+            let span = info_span!("metrics.collect", source = source.name());
+            match source.collect().instrument(span.clone()).await {
+                Ok(samples) => {
+                    for metrics in samples.into_iter().filter(|m| self.shards.owns(&m.server_id)) {
+                        self.record_sample(metrics, source.name()).instrument(span.clone()).await;
+                    }
+                }
+                Err(e) => error!("Metric source {} failed: {}", source.name(), e),
+            }
+        }
+    }
+    
+    async fn external_metrics_loop(&self, shutdown: CancellationToken) {
+        let config = &self.config.kafka_consumer;
+        if !config.enabled {
+            return;
         }
         
-        Ok(())
+        let group_id = if self.shards.is_enabled() {
+            format!("{}-{}", config.group_id, self.shards.instance_id())
+        } else {
+            config.group_id.clone()
+        };
+        let consumer = match ExternalMetricsConsumer::new(config, &self.config.kafka_config, &group_id) {
+            Ok(consumer) => consumer,
+            Err(e) => {
+                error!("Cannot read external metrics from Kafka: {}", e);
+                return;
+            }
+        };
+        let (sender, mut samples) = mpsc::channel(1024);
+        let consumer_handle = tokio::spawn({
+            let shutdown = shutdown.clone();
+            async move { consumer.run(sender, shutdown).await }
+        });
+        
+        while let Some((metrics, carrier)) = samples.recv().await {
+            if !self.shards.owns(&metrics.server_id) {
+                continue;
+            }
+            // Carries on the agent's trace when it sent one
+            let span = info_span!("metrics.collect", source = "kafka", resource_id = %metrics.server_id);
+            telemetry::set_remote_parent(&span, &carrier);
+            self.record_sample(metrics, "kafka").instrument(span).await;
+        }
+        let _ = consumer_handle.await;
+    }
+    
+    // Everywhere a server sample goes, once transformed: the caches, every
+    // sink and streaming consumers
+    async fn record_sample(&self, mut metrics: ServerMetrics, source: &str) {
+        self.tag_sample(&mut metrics);
+        self.processor.process(&mut metrics, source);
+        self.latest_metrics.record_server_metrics(&metrics);
+        self.history.record_server_metrics(&metrics);
+        let _ = self.publish_server_metrics(&metrics).await;
+        // Fails only when nobody is listening
+        let _ = self.samples.send(metrics);
+    }
+    
+    // Sources other than Nova know neither the workload class, which
+    // discovery took from Glance, nor the cloud and region
+    fn tag_sample(&self, metrics: &mut ServerMetrics) {
+        let Some(info) = self.active_resources.get(&metrics.server_id) else {
+            return;
+        };
+        if metrics.workload_class.is_none() {
+            metrics.workload_class = info.workload_class.clone();
+        }
+        if metrics.region.is_none() {
+            metrics.cloud = info.cloud.clone();
+            metrics.region = info.region.clone();
+        }
     }
     
-    async fn edf_scheduling_loop(&self) {
+    // Every sink gets the sample even when one fails; the last failure is
+    // returned
+    async fn publish_server_metrics(&self, metrics: &ServerMetrics) -> Result<()> {
+        let mut result = Ok(());
+        for sink in self.sinks.iter() {
+            if let Err(e) = sink.publish_server(metrics).await {
+                result = Err(e.context(format!("metric sink {}", sink.name())));
+            }
+        }
+        result
+    }
+    
+    async fn history_checkpoint_loop(&self, shutdown: CancellationToken) {
+        let mut interval = interval(Duration::from_secs(self.config.history.checkpoint_interval_seconds));
+        
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            self.history.checkpoint().await;
+        }
+    }
+    
+    async fn edf_scheduling_loop(&self, shutdown: CancellationToken) {
         let mut interval = interval(Duration::from_millis(10)); // EDF requires high frequency
         
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => {
+                    self.queue.abort_all();
+                    return;
+                }
+            }
             
-            // Implement Earliest Deadline First scheduling for critical metrics
-            self.process_edf_queue().await;
+            self.process_edf_queue();
         }
     }
     
-    async fn process_edf_queue(&self) {
-        // Priority queue implementation for EDF scheduling
-        // This would prioritize metrics collection based on SLA requirements
-        // and deadline constraints
-        
-        // Mock implementation - in reality this would:
-        // 1. Sort resources by deadline urgency
-        // 2. Process critical SLA-bound resources first
-        // 3. Ensure real-time constraints are met
-        
-        debug!("Processing EDF scheduling queue");
+    // Starts what the queue has slots for, earliest deadline first
+    fn process_edf_queue(&self) {
+        for job in self.queue.dispatch(chrono::Utc::now()) {
+            let collector = self.clone();
+            // Roots the trace that inference and scheduling decisions for
+            // the resource join later
+            let span = info_span!(
+                "metrics.collect",
+                resource_id = %job.resource_id,
+                resource_type = %job.info.resource_type,
+            );
+            let running = job.clone();
+            let task = tokio::spawn(async move {
+                collector.collect_resource(&running).await;
+                collector.queue.finish(&running, chrono::Utc::now());
+            }.instrument(span));
+            self.queue.started(&job, task.abort_handle());
+        }
+    }
+}
+
+// Never collected counts as due
+fn is_due(collected: Option<chrono::DateTime<chrono::Utc>>, now: chrono::DateTime<chrono::Utc>, interval: Duration) -> bool {
+    match collected {
+        Some(at) => now.signed_duration_since(at).to_std().unwrap_or_default() >= interval,
+        None => true,
     }
 }
 
@@ -196,9 +918,21 @@ impl Clone for MetricsCollector {
     fn clone(&self) -> Self {
         Self {
             config: self.config.clone(),
-            openstack_client: self.openstack_client.clone(),
-            kafka_producer: self.kafka_producer.clone(),
+            clouds: self.clouds.clone(),
+            sources: self.sources.clone(),
+            sinks: self.sinks.clone(),
+            processor: self.processor.clone(),
             active_resources: self.active_resources.clone(),
+            latest_metrics: self.latest_metrics.clone(),
+            history: self.history.clone(),
+            shards: self.shards.clone(),
+            intervals: self.intervals.clone(),
+            queue: self.queue.clone(),
+            changelog: self.changelog.clone(),
+            agents: self.agents.clone(),
+            last_discovery: self.last_discovery.clone(),
+            storage: self.storage.clone(),
+            samples: self.samples.clone(),
         }
     }
 }
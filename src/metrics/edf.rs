@@ -0,0 +1,406 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use std::sync::Mutex;
+use tokio::task::AbortHandle;
+use tracing::debug;
+
+use crate::config::CollectionConfig;
+use crate::scheduler::sla_manager::SLAPriority;
+use super::collector::ResourceInfo;
+
+// A resource's collection for one period: released when it came due, and
+// late once the next one is due
+#[derive(Debug, Clone)]
+pub struct CollectionJob {
+    pub resource_id: String,
+    pub info: ResourceInfo,
+    pub collect_ports: bool,
+    pub collect_volumes: bool,
+    pub deadline: DateTime<Utc>,
+    // From the resource's SLA policy
+    pub priority: Option<SLAPriority>,
+    // Tells a preempted run from the one that replaced it
+    run: u64,
+}
+
+impl CollectionJob {
+    pub fn new(
+        resource_id: String,
+        info: ResourceInfo,
+        collect_ports: bool,
+        collect_volumes: bool,
+        deadline: DateTime<Utc>,
+        priority: Option<SLAPriority>,
+    ) -> Self {
+        Self { resource_id, info, collect_ports, collect_volumes, deadline, priority, run: 0 }
+    }
+    
+    // Earliest deadline first; at equal deadlines resources with an SLA
+    // policy, most urgent first
+    fn key(&self) -> (DateTime<Utc>, bool, Option<SLAPriority>) {
+        (self.deadline, self.priority.is_none(), self.priority)
+    }
+    
+    fn priority_label(&self) -> &'static str {
+        match self.priority {
+            Some(SLAPriority::Critical) => "critical",
+            Some(SLAPriority::High) => "high",
+            Some(SLAPriority::Medium) => "medium",
+            Some(SLAPriority::Low) => "low",
+            None => "none",
+        }
+    }
+}
+
+// Reversed, as BinaryHeap pops its greatest
+struct Queued(CollectionJob);
+
+impl Ord for Queued {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.key().cmp(&self.0.key())
+    }
+}
+
+impl PartialOrd for Queued {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Queued {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Queued {}
+
+struct Running {
+    job: CollectionJob,
+    // Set once its task is spawned
+    abort: Option<AbortHandle>,
+}
+
+// Due collections, earliest deadline first, with at most max_concurrent
+// running and max_queued waiting. A resource is queued or running at most
+// once, so one that falls behind isn't doubled up
+pub struct CollectionQueue {
+    max_running: usize,
+    capacity: usize,
+    preemption: bool,
+    state: Mutex<State>,
+}
+
+#[derive(Default)]
+struct State {
+    queued: BinaryHeap<Queued>,
+    waiting: HashSet<String>,
+    running: HashMap<String, Running>,
+    runs: u64,
+    stats: CollectionQueueStats,
+}
+
+pub enum Push {
+    Queued,
+    // Still queued or running from an earlier period
+    InFlight,
+    // Nothing more is taken until collections finish
+    Full,
+}
+
+// Counters are since start
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CollectionQueueStats {
+    pub queued: usize,
+    pub running: usize,
+    pub capacity: usize,
+    pub skipped_in_flight: u64,
+    pub rejected_full: u64,
+    pub preemptions: u64,
+    pub deadline_misses: u64,
+}
+
+impl CollectionQueue {
+    pub fn new(config: &CollectionConfig) -> Self {
+        Self {
+            max_running: config.max_concurrent.max(1),
+            capacity: config.max_queued.max(1),
+            preemption: config.preemption,
+            state: Mutex::new(State::default()),
+        }
+    }
+    
+    pub fn push(&self, job: CollectionJob) -> Push {
+        let mut state = self.state.lock().unwrap();
+        if state.running.contains_key(&job.resource_id) || state.waiting.contains(&job.resource_id) {
+            state.stats.skipped_in_flight += 1;
+            ::metrics::counter!("collection_skipped_total", "reason" => "in_flight").increment(1);
+            return Push::InFlight;
+        }
+        if state.queued.len() >= self.capacity {
+            state.stats.rejected_full += 1;
+            ::metrics::counter!("collection_skipped_total", "reason" => "queue_full").increment(1);
+            return Push::Full;
+        }
+        state.waiting.insert(job.resource_id.clone());
+        state.queued.push(Queued(job));
+        ::metrics::gauge!("collection_queue_depth").set(state.queued.len() as f64);
+        Push::Queued
+    }
+    
+    pub fn stats(&self) -> CollectionQueueStats {
+        let state = self.state.lock().unwrap();
+        CollectionQueueStats {
+            queued: state.queued.len(),
+            running: state.running.len(),
+            capacity: self.capacity,
+            ..state.stats.clone()
+        }
+    }
+    
+    // The jobs to start now, counted as running from here on. Those whose
+    // deadline passed while they waited are dropped as misses, as the next
+    // period's job is due
+    pub fn dispatch(&self, now: DateTime<Utc>) -> Vec<CollectionJob> {
+        let mut state = self.state.lock().unwrap();
+        let mut dispatched = Vec::new();
+        while let Some(head) = state.queued.peek() {
+            let expired = head.0.deadline <= now;
+            let priority = head.0.priority;
+            let mut preempted = None;
+            if !expired && state.running.len() >= self.max_running {
+                if self.preemption {
+                    preempted = state.preempt(priority);
+                }
+                if preempted.is_none() {
+                    break;
+                }
+            }
+            let Some(Queued(mut job)) = state.queued.pop() else {
+                break;
+            };
+            state.waiting.remove(&job.resource_id);
+            // Queued again only now, so it doesn't come out ahead of the job
+            // it made way for
+            if let Some(preempted) = preempted {
+                state.waiting.insert(preempted.resource_id.clone());
+                state.queued.push(Queued(preempted));
+            }
+            if expired {
+                state.stats.deadline_misses += 1;
+                record_miss(&job, "expired", now);
+                continue;
+            }
+            
+            state.runs += 1;
+            job.run = state.runs;
+            state.running.insert(job.resource_id.clone(), Running { job: job.clone(), abort: None });
+            dispatched.push(job);
+        }
+        ::metrics::gauge!("collection_queue_depth").set(state.queued.len() as f64);
+        ::metrics::gauge!("collection_running").set(state.running.len() as f64);
+        dispatched
+    }
+    
+    // Makes a dispatched job preemptible. It may have finished already
+    pub fn started(&self, job: &CollectionJob, abort: AbortHandle) {
+        let mut state = self.state.lock().unwrap();
+        if let Some(running) = state.running.get_mut(&job.resource_id).filter(|running| running.job.run == job.run) {
+            running.abort = Some(abort);
+        }
+    }
+    
+    // Counts a miss when the job ended past its deadline
+    pub fn finish(&self, job: &CollectionJob, now: DateTime<Utc>) {
+        let mut state = self.state.lock().unwrap();
+        if state.running.get(&job.resource_id).is_some_and(|running| running.job.run == job.run) {
+            state.running.remove(&job.resource_id);
+        }
+        ::metrics::gauge!("collection_running").set(state.running.len() as f64);
+        if now > job.deadline {
+            state.stats.deadline_misses += 1;
+            record_miss(job, "late", now);
+        }
+    }
+    
+    // Collections still running are abandoned on shutdown
+    pub fn abort_all(&self) {
+        let mut state = self.state.lock().unwrap();
+        for (_, running) in state.running.drain() {
+            if let Some(abort) = running.abort {
+                abort.abort();
+            }
+        }
+    }
+}
+
+impl State {
+    // Aborts the least urgent running job with a lower priority than the
+    // one waiting, for the caller to queue again. Resources without an SLA
+    // policy never preempt
+    fn preempt(&mut self, priority: Option<SLAPriority>) -> Option<CollectionJob> {
+        let priority = priority?;
+        let victim = self.running.values()
+            .filter(|running| running.abort.is_some())
+            .filter(|running| match running.job.priority {
+                Some(running_priority) => running_priority > priority,
+                None => true,
+            })
+            .max_by_key(|running| (running.job.priority.is_none(), running.job.priority, running.job.deadline))
+            .map(|running| running.job.resource_id.clone());
+        let running = self.running.remove(&victim?)?;
+        
+        if let Some(abort) = &running.abort {
+            abort.abort();
+        }
+        debug!("Preempted the collection of {} for a {:?} priority one", running.job.resource_id, priority);
+        ::metrics::counter!("collection_preemptions_total", "priority" => running.job.priority_label()).increment(1);
+        self.stats.preemptions += 1;
+        Some(running.job)
+    }
+}
+
+fn record_miss(job: &CollectionJob, reason: &'static str, now: DateTime<Utc>) {
+    let lateness = (now - job.deadline).num_milliseconds().max(0) as f64 / 1000.0;
+    debug!("Collection of {} missed its deadline by {:.1}s ({})", job.resource_id, lateness, reason);
+    ::metrics::counter!("collection_deadline_misses_total", "priority" => job.priority_label(), "reason" => reason).increment(1);
+    ::metrics::histogram!("collection_lateness_seconds", "priority" => job.priority_label()).record(lateness);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use std::time::Duration;
+    
+    fn queue(max_concurrent: usize, preemption: bool) -> CollectionQueue {
+        CollectionQueue::new(&CollectionConfig {
+            max_concurrent,
+            max_queued: 10,
+            preemption,
+            ..CollectionConfig::default()
+        })
+    }
+    
+    fn job(resource_id: &str, deadline_seconds: i64, priority: Option<SLAPriority>) -> CollectionJob {
+        let now = Utc::now();
+        let info = ResourceInfo {
+            resource_type: "server".to_string(),
+            project_id: None,
+            cloud: None,
+            region: None,
+            workload_class: None,
+            last_collected: now,
+            collection_interval: Duration::from_secs(60),
+            next_collection: now,
+            ports_collected: None,
+            volumes_collected: None,
+            status: "ACTIVE".to_string(),
+            first_seen: now,
+            last_seen: now,
+            missed_discoveries: 0,
+        };
+        CollectionJob::new(resource_id.to_string(), info, false, false, now + ChronoDuration::seconds(deadline_seconds), priority)
+    }
+    
+    fn ids(jobs: &[CollectionJob]) -> Vec<&str> {
+        jobs.iter().map(|job| job.resource_id.as_str()).collect()
+    }
+    
+    // Stands in for a collection's task until it is aborted
+    fn start(queue: &CollectionQueue, job: &CollectionJob) -> AbortHandle {
+        let abort = tokio::spawn(std::future::pending::<()>()).abort_handle();
+        queue.started(job, abort.clone());
+        abort
+    }
+    
+    #[test]
+    fn dispatches_earliest_deadline_then_priority() {
+        let queue = queue(10, false);
+        queue.push(job("late", 120, Some(SLAPriority::Critical)));
+        queue.push(job("unprioritized", 60, None));
+        queue.push(job("low", 60, Some(SLAPriority::Low)));
+        queue.push(job("high", 60, Some(SLAPriority::High)));
+        
+        let dispatched = queue.dispatch(Utc::now());
+        assert_eq!(ids(&dispatched), ["high", "low", "unprioritized", "late"]);
+    }
+    
+    #[test]
+    fn skips_resources_already_in_flight() {
+        let queue = queue(1, false);
+        assert!(matches!(queue.push(job("a", 60, None)), Push::Queued));
+        assert!(matches!(queue.push(job("a", 60, None)), Push::InFlight));
+        queue.dispatch(Utc::now());
+        assert!(matches!(queue.push(job("a", 60, None)), Push::InFlight));
+        assert_eq!(queue.stats().skipped_in_flight, 2);
+    }
+    
+    #[test]
+    fn drops_expired_jobs_as_misses() {
+        let queue = queue(10, false);
+        queue.push(job("expired", -1, None));
+        queue.push(job("due", 60, None));
+        
+        let dispatched = queue.dispatch(Utc::now());
+        assert_eq!(ids(&dispatched), ["due"]);
+        assert_eq!(queue.stats().deadline_misses, 1);
+    }
+    
+    #[tokio::test]
+    async fn preempts_least_urgent_running_job() {
+        let queue = queue(2, true);
+        queue.push(job("low", 30, Some(SLAPriority::Low)));
+        queue.push(job("medium", 40, Some(SLAPriority::Medium)));
+        let running = queue.dispatch(Utc::now());
+        let aborts: Vec<_> = running.iter().map(|job| start(&queue, job)).collect();
+        
+        queue.push(job("critical", 60, Some(SLAPriority::Critical)));
+        let dispatched = queue.dispatch(Utc::now());
+        assert_eq!(ids(&dispatched), ["critical"]);
+        tokio::task::yield_now().await;
+        assert!(aborts[0].is_finished());
+        assert!(!aborts[1].is_finished());
+        
+        let stats = queue.stats();
+        assert_eq!(stats.preemptions, 1);
+        assert_eq!((stats.running, stats.queued), (2, 1));
+    }
+    
+    #[tokio::test]
+    async fn requeues_preempted_job_behind_the_one_it_made_way_for() {
+        let queue = queue(1, true);
+        queue.push(job("low", 30, Some(SLAPriority::Low)));
+        let low = queue.dispatch(Utc::now());
+        start(&queue, &low[0]);
+        
+        // Both outrank the running job, but only one slot frees up
+        queue.push(job("critical", 60, Some(SLAPriority::Critical)));
+        queue.push(job("high", 60, Some(SLAPriority::High)));
+        let dispatched = queue.dispatch(Utc::now());
+        assert_eq!(ids(&dispatched), ["critical"]);
+        
+        // The preempted job keeps its earlier deadline and goes first once
+        // the slot is free again
+        queue.finish(&dispatched[0], Utc::now());
+        let dispatched = queue.dispatch(Utc::now());
+        assert_eq!(ids(&dispatched), ["low"]);
+        assert_eq!(queue.stats().queued, 1);
+    }
+    
+    #[test]
+    fn never_preempts_for_unprioritized_jobs_or_unstarted_ones() {
+        let queue = queue(1, true);
+        queue.push(job("low", 30, Some(SLAPriority::Low)));
+        queue.dispatch(Utc::now());
+        
+        queue.push(job("unprioritized", 10, None));
+        assert!(queue.dispatch(Utc::now()).is_empty());
+        // Not started yet, so there is nothing to abort
+        queue.push(job("critical", 10, Some(SLAPriority::Critical)));
+        assert!(queue.dispatch(Utc::now()).is_empty());
+        assert_eq!(queue.stats().preemptions, 0);
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+
+use crate::config::HistoryConfig;
+use crate::openstack::services::ServerMetrics;
+use crate::storage::Storage;
+use super::history_store::{self, HistoryStore, StoredBucket};
+
+// Per-resource metric history at a fixed resolution. The most recent
+// memory_hours are held in memory and checkpointed to the history store,
+// which answers for older ranges so charts and forecasts survive a restart
+pub struct MetricHistory {
+    config: HistoryConfig,
+    store: Arc<dyn HistoryStore>,
+    resources: DashMap<String, HashMap<HistoryMetric, VecDeque<Bucket>>>,
+    // Resources with buckets not yet written to the store, and the start of
+    // the earliest such bucket
+    dirty: DashMap<String, DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HistoryMetric {
+    CpuUtilization,
+    MemoryUtilization,
+    DiskReadBytes,
+    DiskWriteBytes,
+    NetworkRxBytes,
+    NetworkTxBytes,
+    // Recorded by the dashboard from the ML engine rather than collected
+    PredictedLoad,
+    PredictionConfidence,
+}
+
+impl HistoryMetric {
+    const ALL: [HistoryMetric; 8] = [
+        HistoryMetric::CpuUtilization,
+        HistoryMetric::MemoryUtilization,
+        HistoryMetric::DiskReadBytes,
+        HistoryMetric::DiskWriteBytes,
+        HistoryMetric::NetworkRxBytes,
+        HistoryMetric::NetworkTxBytes,
+        HistoryMetric::PredictedLoad,
+        HistoryMetric::PredictionConfidence,
+    ];
+    
+    // As serialized
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            HistoryMetric::CpuUtilization => "cpu_utilization",
+            HistoryMetric::MemoryUtilization => "memory_utilization",
+            HistoryMetric::DiskReadBytes => "disk_read_bytes",
+            HistoryMetric::DiskWriteBytes => "disk_write_bytes",
+            HistoryMetric::NetworkRxBytes => "network_rx_bytes",
+            HistoryMetric::NetworkTxBytes => "network_tx_bytes",
+            HistoryMetric::PredictedLoad => "predicted_load",
+            HistoryMetric::PredictionConfidence => "prediction_confidence",
+        }
+    }
+    
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|metric| metric.as_str() == name)
+    }
+}
+
+// Aggregate of the samples falling into one resolution interval
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Bucket {
+    pub start: DateTime<Utc>,
+    pub sum: f64,
+    pub count: u64,
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistoryPoint {
+    pub timestamp: DateTime<Utc>,
+    pub avg: f64,
+    pub min: f64,
+    pub max: f64,
+    pub samples: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HistorySeries {
+    pub resource_id: String,
+    pub metric: HistoryMetric,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    // May be wider than requested to stay within max_points
+    pub step_seconds: i64,
+    pub points: Vec<HistoryPoint>,
+}
+
+impl MetricHistory {
+    pub async fn load(config: HistoryConfig, storage: Storage) -> Result<Self> {
+        let store = history_store::from_config(&config, storage).await?;
+        let history = Self {
+            config,
+            store,
+            resources: DashMap::new(),
+            dirty: DashMap::new(),
+        };
+        
+        let stored = history.store.load(history.memory_cutoff(Utc::now())).await?;
+        for StoredBucket { resource_id, metric, bucket } in stored {
+            history.resources.entry(resource_id).or_default().entry(metric).or_default().push_back(bucket);
+        }
+        for mut resource in history.resources.iter_mut() {
+            for buckets in resource.values_mut() {
+                buckets.make_contiguous().sort_by_key(|b| b.start);
+            }
+        }
+        info!("Restored recent metric history for {} resources from the {:?} history store", history.resources.len(), history.config.backend);
+        Ok(history)
+    }
+    
+    pub fn resolution_seconds(&self) -> i64 {
+        self.config.resolution_seconds.max(1)
+    }
+    
+    // Resources with history in memory or in the store
+    pub async fn resource_ids(&self) -> Vec<String> {
+        let mut ids: HashSet<String> = self.resources.iter().map(|r| r.key().clone()).collect();
+        match self.store.resource_ids().await {
+            Ok(stored) => ids.extend(stored),
+            Err(e) => warn!("Failed to list resources in the metric history store: {}", e),
+        }
+        let mut ids: Vec<String> = ids.into_iter().collect();
+        ids.sort();
+        ids
+    }
+    
+    pub fn record_server_metrics(&self, metrics: &ServerMetrics) {
+        let at = metrics.timestamp;
+        self.record(&metrics.server_id, HistoryMetric::CpuUtilization, metrics.cpu_utilization, at);
+        if metrics.memory_total > 0 {
+            let memory = metrics.memory_usage as f64 / metrics.memory_total as f64 * 100.0;
+            self.record(&metrics.server_id, HistoryMetric::MemoryUtilization, memory, at);
+        }
+        self.record(&metrics.server_id, HistoryMetric::DiskReadBytes, metrics.disk_read_bytes as f64, at);
+        self.record(&metrics.server_id, HistoryMetric::DiskWriteBytes, metrics.disk_write_bytes as f64, at);
+        self.record(&metrics.server_id, HistoryMetric::NetworkRxBytes, metrics.network_rx_bytes as f64, at);
+        self.record(&metrics.server_id, HistoryMetric::NetworkTxBytes, metrics.network_tx_bytes as f64, at);
+    }
+    
+    pub fn record(&self, resource_id: &str, metric: HistoryMetric, value: f64, at: DateTime<Utc>) {
+        let start = align(at, self.resolution_seconds());
+        let mut resource = self.resources.entry(resource_id.to_string()).or_default();
+        let buckets = resource.entry(metric).or_default();
+        
+        match buckets.back_mut() {
+            Some(bucket) if bucket.start == start => {
+                bucket.sum += value;
+                bucket.count += 1;
+                bucket.min = bucket.min.min(value);
+                bucket.max = bucket.max.max(value);
+            }
+            // Late samples for an earlier interval are dropped rather than
+            // reordering the series
+            Some(bucket) if bucket.start > start => return,
+            _ => buckets.push_back(Bucket { start, sum: value, count: 1, min: value, max: value }),
+        }
+        drop(resource);
+        self.mark_dirty(resource_id, start);
+    }
+    
+    // Buckets in [from, to) merged into points `step` apart, aligned to the
+    // step so repeated queries line up
+    pub async fn query(
+        &self,
+        resource_id: &str,
+        metric: HistoryMetric,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step_seconds: Option<i64>,
+    ) -> HistorySeries {
+        let resolution = self.resolution_seconds();
+        let span = (to - from).num_seconds().max(1);
+        let max_points = self.config.max_points.max(1) as i64;
+        let widest_needed = (span + max_points - 1) / max_points;
+        // No wider than everything that's kept, and whole multiples of the
+        // resolution only
+        let retention = (self.config.retention_hours * 3600).max(resolution);
+        let step = step_seconds.unwrap_or(0).max(widest_needed).clamp(resolution, retention);
+        let step = (step + resolution - 1) / resolution * resolution;
+        
+        let buckets = self.buckets(resource_id, metric, from, to).await;
+        HistorySeries {
+            resource_id: resource_id.to_string(),
+            metric,
+            from,
+            to,
+            step_seconds: step,
+            points: merge(&buckets, step),
+        }
+    }
+    
+    // Every bucket in [from, to) at the stored resolution, for exports that
+    // must not be coarsened
+    pub async fn points(&self, resource_id: &str, metric: HistoryMetric, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<HistoryPoint> {
+        let buckets = self.buckets(resource_id, metric, from, to).await;
+        merge(&buckets, self.resolution_seconds())
+    }
+    
+    // Buckets in [from, to): those before the memory cutoff from the store,
+    // the rest from memory
+    async fn buckets(&self, resource_id: &str, metric: HistoryMetric, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Bucket> {
+        let cutoff = self.memory_cutoff(Utc::now());
+        let mut buckets = Vec::new();
+        if from < cutoff {
+            match self.store.range(resource_id, metric, from, to.min(cutoff)).await {
+                Ok(stored) => buckets = stored,
+                Err(e) => warn!("Failed to read metric history of {} from the store: {}", resource_id, e),
+            }
+        }
+        if let Some(resource) = self.resources.get(resource_id) {
+            if let Some(recent) = resource.get(&metric) {
+                let from = from.max(cutoff);
+                buckets.extend(recent.iter().filter(|b| b.start >= from && b.start < to).cloned());
+            }
+        }
+        buckets
+    }
+    
+    // Writes buckets that changed since the last checkpoint, then lets go of
+    // those older than memory_hours and drops expired ones from the store
+    pub async fn checkpoint(&self) {
+        let now = Utc::now();
+        
+        let dirty: Vec<(String, DateTime<Utc>)> = self.dirty.iter().map(|d| (d.key().clone(), *d.value())).collect();
+        let mut changed = Vec::new();
+        for (resource_id, since) in &dirty {
+            self.dirty.remove(resource_id);
+            if let Some(resource) = self.resources.get(resource_id) {
+                for (metric, buckets) in resource.iter() {
+                    changed.extend(buckets.iter().filter(|b| b.start >= *since).map(|bucket| StoredBucket {
+                        resource_id: resource_id.clone(),
+                        metric: *metric,
+                        bucket: bucket.clone(),
+                    }));
+                }
+            }
+        }
+        if !changed.is_empty() {
+            if let Err(e) = self.store.write(changed).await {
+                warn!("Failed to persist metric history: {}", e);
+                for (resource_id, since) in dirty {
+                    self.mark_dirty(&resource_id, since);
+                }
+            }
+        }
+        
+        self.prune_memory(self.memory_cutoff(now));
+        let retention = now - Duration::hours(self.config.retention_hours);
+        if let Err(e) = self.store.prune(retention).await {
+            warn!("Failed to drop expired metric history: {}", e);
+        }
+        debug!("Metric history holds {} resources in memory", self.resources.len());
+    }
+    
+    fn mark_dirty(&self, resource_id: &str, start: DateTime<Utc>) {
+        self.dirty.entry(resource_id.to_string())
+            .and_modify(|since| *since = (*since).min(start))
+            .or_insert(start);
+    }
+    
+    fn memory_cutoff(&self, now: DateTime<Utc>) -> DateTime<Utc> {
+        now - Duration::hours(self.config.memory_hours.min(self.config.retention_hours))
+    }
+    
+    fn prune_memory(&self, cutoff: DateTime<Utc>) {
+        for mut resource in self.resources.iter_mut() {
+            for buckets in resource.values_mut() {
+                while buckets.front().is_some_and(|b| b.start < cutoff) {
+                    buckets.pop_front();
+                }
+            }
+            resource.retain(|_, buckets| !buckets.is_empty());
+        }
+        self.resources.retain(|_, resource| !resource.is_empty());
+    }
+}
+
+fn merge(buckets: &[Bucket], step: i64) -> Vec<HistoryPoint> {
+    let mut points: Vec<HistoryPoint> = Vec::new();
+    for bucket in buckets {
+        let timestamp = align(bucket.start, step);
+        match points.last_mut() {
+            Some(point) if point.timestamp == timestamp => {
+                // avg holds the running sum until the end
+                point.avg += bucket.sum;
+                point.samples += bucket.count;
+                point.min = point.min.min(bucket.min);
+                point.max = point.max.max(bucket.max);
+            }
+            _ => points.push(HistoryPoint {
+                timestamp,
+                avg: bucket.sum,
+                min: bucket.min,
+                max: bucket.max,
+                samples: bucket.count,
+            }),
+        }
+    }
+    for point in &mut points {
+        point.avg /= point.samples.max(1) as f64;
+    }
+    points
+}
+
+fn align(at: DateTime<Utc>, step_seconds: i64) -> DateTime<Utc> {
+    let seconds = at.timestamp();
+    Utc.timestamp_opt(seconds - seconds.rem_euclid(step_seconds), 0).unwrap()
+}
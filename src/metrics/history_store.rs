@@ -0,0 +1,256 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, TimeZone, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::sqlite::SqlitePoolOptions;
+use sqlx::{Row, SqlitePool};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::Arc;
+use tracing::info;
+
+use crate::config::{HistoryBackend, HistoryConfig};
+use crate::error::StorageError;
+use crate::storage::Storage;
+use super::history::{Bucket, HistoryMetric};
+
+const HISTORY_COLLECTION: &str = "metric_history";
+
+// Where metric history persists. MetricHistory holds the recent buckets in
+// memory and comes here for older ranges
+#[async_trait]
+pub trait HistoryStore: Send + Sync {
+    // Every bucket starting at or after since
+    async fn load(&self, since: DateTime<Utc>) -> Result<Vec<StoredBucket>>;
+    
+    // The resource's buckets starting in [from, to), oldest first
+    async fn range(&self, resource_id: &str, metric: HistoryMetric, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Bucket>>;
+    
+    async fn resource_ids(&self) -> Result<Vec<String>>;
+    
+    // Replaces stored buckets with the same start
+    async fn write(&self, buckets: Vec<StoredBucket>) -> Result<()>;
+    
+    // Drops buckets starting before the cutoff
+    async fn prune(&self, before: DateTime<Utc>) -> Result<()>;
+}
+
+#[derive(Debug, Clone)]
+pub struct StoredBucket {
+    pub resource_id: String,
+    pub metric: HistoryMetric,
+    pub bucket: Bucket,
+}
+
+pub async fn from_config(config: &HistoryConfig, storage: Storage) -> Result<Arc<dyn HistoryStore>> {
+    Ok(match config.backend {
+        HistoryBackend::Storage => Arc::new(DocumentHistoryStore { storage }),
+        HistoryBackend::Sqlite => Arc::new(SqliteHistoryStore::connect(&config.url).await?),
+    })
+}
+
+// A document per resource in the shared storage, as history was always kept
+struct DocumentHistoryStore {
+    storage: Storage,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StoredHistory {
+    resource_id: String,
+    series: HashMap<HistoryMetric, VecDeque<Bucket>>,
+}
+
+#[async_trait]
+impl HistoryStore for DocumentHistoryStore {
+    async fn load(&self, since: DateTime<Utc>) -> Result<Vec<StoredBucket>> {
+        let stored: Vec<StoredHistory> = self.storage.list(HISTORY_COLLECTION).await?;
+        Ok(stored.into_iter()
+            .flat_map(|history| {
+                let resource_id = history.resource_id;
+                history.series.into_iter().flat_map(move |(metric, buckets)| {
+                    let resource_id = resource_id.clone();
+                    buckets.into_iter()
+                        .filter(move |bucket| bucket.start >= since)
+                        .map(move |bucket| StoredBucket { resource_id: resource_id.clone(), metric, bucket })
+                })
+            })
+            .collect())
+    }
+    
+    async fn range(&self, resource_id: &str, metric: HistoryMetric, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Bucket>> {
+        let stored: Option<StoredHistory> = self.storage.get(HISTORY_COLLECTION, resource_id).await?;
+        Ok(stored
+            .and_then(|mut history| history.series.remove(&metric))
+            .unwrap_or_default()
+            .into_iter()
+            .filter(|bucket| bucket.start >= from && bucket.start < to)
+            .collect())
+    }
+    
+    async fn resource_ids(&self) -> Result<Vec<String>> {
+        let stored: Vec<StoredHistory> = self.storage.list(HISTORY_COLLECTION).await?;
+        Ok(stored.into_iter().map(|history| history.resource_id).collect())
+    }
+    
+    async fn write(&self, buckets: Vec<StoredBucket>) -> Result<()> {
+        let mut by_resource: BTreeMap<String, Vec<StoredBucket>> = BTreeMap::new();
+        for stored in buckets {
+            by_resource.entry(stored.resource_id.clone()).or_default().push(stored);
+        }
+        
+        for (resource_id, buckets) in by_resource {
+            let mut history: StoredHistory = self.storage.get(HISTORY_COLLECTION, &resource_id).await?
+                .unwrap_or_else(|| StoredHistory { resource_id: resource_id.clone(), ..Default::default() });
+            for StoredBucket { metric, bucket, .. } in buckets {
+                let series = history.series.entry(metric).or_default();
+                match series.binary_search_by_key(&bucket.start, |b| b.start) {
+                    Ok(i) => series[i] = bucket,
+                    Err(i) => series.insert(i, bucket),
+                }
+            }
+            self.storage.put(HISTORY_COLLECTION, &resource_id, &history).await?;
+        }
+        Ok(())
+    }
+    
+    async fn prune(&self, before: DateTime<Utc>) -> Result<()> {
+        let stored: Vec<StoredHistory> = self.storage.list(HISTORY_COLLECTION).await?;
+        for mut history in stored {
+            let mut changed = false;
+            for series in history.series.values_mut() {
+                while series.front().is_some_and(|bucket| bucket.start < before) {
+                    series.pop_front();
+                    changed = true;
+                }
+            }
+            history.series.retain(|_, series| !series.is_empty());
+            if history.series.is_empty() {
+                self.storage.delete(HISTORY_COLLECTION, &history.resource_id).await?;
+            } else if changed {
+                self.storage.put(HISTORY_COLLECTION, &history.resource_id, &history).await?;
+            }
+        }
+        Ok(())
+    }
+}
+
+// A row per bucket in an embedded sqlite database of its own, a ring buffer
+// per resource and metric trimmed to the retention on every checkpoint
+struct SqliteHistoryStore {
+    pool: SqlitePool,
+}
+
+impl SqliteHistoryStore {
+    async fn connect(url: &str) -> Result<Self> {
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect(url)
+            .await
+            .map_err(|e| StorageError::BackendError(format!("Cannot open the metric history database: {}", e)))?;
+        sqlx::query(
+            "CREATE TABLE IF NOT EXISTS history_buckets (
+                resource_id TEXT NOT NULL,
+                metric TEXT NOT NULL,
+                start BIGINT NOT NULL,
+                sum DOUBLE PRECISION NOT NULL,
+                count BIGINT NOT NULL,
+                min DOUBLE PRECISION NOT NULL,
+                max DOUBLE PRECISION NOT NULL,
+                PRIMARY KEY (resource_id, metric, start)
+            )",
+        )
+        .execute(&pool)
+        .await?;
+        sqlx::query("CREATE INDEX IF NOT EXISTS history_buckets_start ON history_buckets (start)")
+            .execute(&pool)
+            .await?;
+        
+        info!("Metric history database opened");
+        Ok(Self { pool })
+    }
+    
+    fn bucket(row: &sqlx::sqlite::SqliteRow) -> Result<Bucket> {
+        Ok(Bucket {
+            start: Utc.timestamp_opt(row.try_get("start")?, 0).single()
+                .ok_or_else(|| StorageError::BackendError("Invalid bucket start in the metric history database".to_string()))?,
+            sum: row.try_get("sum")?,
+            count: row.try_get::<i64, _>("count")? as u64,
+            min: row.try_get("min")?,
+            max: row.try_get("max")?,
+        })
+    }
+}
+
+#[async_trait]
+impl HistoryStore for SqliteHistoryStore {
+    async fn load(&self, since: DateTime<Utc>) -> Result<Vec<StoredBucket>> {
+        let rows = sqlx::query("SELECT * FROM history_buckets WHERE start >= $1 ORDER BY resource_id, metric, start")
+            .bind(since.timestamp())
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter()
+            .filter_map(|row| {
+                // Metrics this build no longer knows are left alone
+                let metric = HistoryMetric::parse(row.try_get::<&str, _>("metric").ok()?)?;
+                Some((row, metric))
+            })
+            .map(|(row, metric)| Ok(StoredBucket {
+                resource_id: row.try_get("resource_id")?,
+                metric,
+                bucket: Self::bucket(row)?,
+            }))
+            .collect()
+    }
+    
+    async fn range(&self, resource_id: &str, metric: HistoryMetric, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<Bucket>> {
+        let rows = sqlx::query(
+            "SELECT * FROM history_buckets
+             WHERE resource_id = $1 AND metric = $2 AND start >= $3 AND start < $4
+             ORDER BY start",
+        )
+        .bind(resource_id)
+        .bind(metric.as_str())
+        .bind(from.timestamp())
+        .bind(to.timestamp())
+        .fetch_all(&self.pool)
+        .await?;
+        rows.iter().map(Self::bucket).collect()
+    }
+    
+    async fn resource_ids(&self) -> Result<Vec<String>> {
+        let rows = sqlx::query("SELECT DISTINCT resource_id FROM history_buckets ORDER BY resource_id")
+            .fetch_all(&self.pool)
+            .await?;
+        rows.iter().map(|row| Ok(row.try_get("resource_id")?)).collect()
+    }
+    
+    async fn write(&self, buckets: Vec<StoredBucket>) -> Result<()> {
+        let mut tx = self.pool.begin().await?;
+        for StoredBucket { resource_id, metric, bucket } in buckets {
+            sqlx::query(
+                "INSERT INTO history_buckets (resource_id, metric, start, sum, count, min, max)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7)
+                 ON CONFLICT (resource_id, metric, start) DO UPDATE SET
+                     sum = excluded.sum, count = excluded.count, min = excluded.min, max = excluded.max",
+            )
+            .bind(resource_id)
+            .bind(metric.as_str())
+            .bind(bucket.start.timestamp())
+            .bind(bucket.sum)
+            .bind(bucket.count as i64)
+            .bind(bucket.min)
+            .bind(bucket.max)
+            .execute(&mut *tx)
+            .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    async fn prune(&self, before: DateTime<Utc>) -> Result<()> {
+        sqlx::query("DELETE FROM history_buckets WHERE start < $1")
+            .bind(before.timestamp())
+            .execute(&self.pool)
+            .await?;
+        Ok(())
+    }
+}
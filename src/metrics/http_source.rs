@@ -0,0 +1,55 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{MetricsConfig, PluginConfig};
+use crate::openstack::services::ServerMetrics;
+use crate::plugins::{MetricSource, Registry};
+
+// Polls an endpoint that returns a JSON array of server samples, for
+// exporters covering guests or hypervisors Nova doesn't report on
+pub struct HttpSource {
+    url: String,
+    http_client: HttpClient,
+}
+
+#[derive(Deserialize)]
+struct HttpSourceOptions {
+    url: String,
+    #[serde(default = "default_timeout_seconds")]
+    timeout_seconds: u64,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.add_source("http", http_source);
+}
+
+fn http_source<'a>(plugin: &'a PluginConfig, _config: &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSource>>> {
+    Box::pin(async move {
+        let options: HttpSourceOptions = plugin.options()?;
+        let http_client = HttpClient::builder()
+            .timeout(Duration::from_secs(options.timeout_seconds))
+            .build()?;
+        Ok(Arc::new(HttpSource { url: options.url, http_client }) as Arc<dyn MetricSource>)
+    })
+}
+
+#[async_trait]
+impl MetricSource for HttpSource {
+    fn name(&self) -> &str {
+        "http"
+    }
+    
+    async fn collect(&self) -> Result<Vec<ServerMetrics>> {
+        let response = self.http_client.get(&self.url).send().await?.error_for_status()?;
+        Ok(response.json().await?)
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use reqwest::Client as HttpClient;
+use serde::Deserialize;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::time::Duration;
+
+use crate::config::{MetricsConfig, PluginConfig};
+use crate::plugins::{MetricSink, Registry};
+use super::point_sink::{Point, PointSink, PointWriter};
+
+// Writes samples as line protocol to InfluxDB's v2 write API, which 1.8 and
+// later also serve (the bucket is then "database/retention policy")
+pub struct InfluxDbWriter {
+    write_url: String,
+    ping_url: String,
+    token: Option<String>,
+    http_client: HttpClient,
+}
+
+#[derive(Deserialize)]
+struct InfluxDbOptions {
+    url: String,
+    #[serde(default)]
+    org: String,
+    bucket: String,
+    #[serde(default)]
+    token: Option<String>,
+    #[serde(default = "default_timeout_seconds")]
+    timeout_seconds: u64,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+    #[serde(default = "default_flush_interval_seconds")]
+    flush_interval_seconds: u64,
+}
+
+fn default_timeout_seconds() -> u64 {
+    10
+}
+
+fn default_batch_size() -> usize {
+    5000
+}
+
+fn default_flush_interval_seconds() -> u64 {
+    10
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.add_sink("influxdb", influxdb_sink);
+}
+
+fn influxdb_sink<'a>(plugin: &'a PluginConfig, _config: &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSink>>> {
+    Box::pin(async move {
+        let options: InfluxDbOptions = plugin.options()?;
+        let base = options.url.trim_end_matches('/');
+        let mut write_url = reqwest::Url::parse(&format!("{}/api/v2/write", base))?;
+        write_url.query_pairs_mut()
+            .append_pair("org", &options.org)
+            .append_pair("bucket", &options.bucket)
+            .append_pair("precision", "ns");
+        let writer = InfluxDbWriter {
+            write_url: write_url.to_string(),
+            ping_url: format!("{}/ping", base),
+            token: options.token,
+            http_client: HttpClient::builder()
+                .timeout(Duration::from_secs(options.timeout_seconds))
+                .build()?,
+        };
+        let sink = PointSink::start(writer, options.batch_size, Duration::from_secs(options.flush_interval_seconds.max(1)));
+        Ok(sink as Arc<dyn MetricSink>)
+    })
+}
+
+#[async_trait]
+impl PointWriter for InfluxDbWriter {
+    fn name(&self) -> &str {
+        "influxdb"
+    }
+    
+    async fn write(&self, points: Vec<Point>) -> Result<()> {
+        let mut body = String::new();
+        for point in &points {
+            line(&mut body, point);
+        }
+        let mut request = self.http_client.post(&self.write_url).body(body);
+        if let Some(token) = &self.token {
+            request = request.header("Authorization", format!("Token {}", token));
+        }
+        request.send().await?.error_for_status()?;
+        Ok(())
+    }
+    
+    async fn check(&self) -> Result<()> {
+        self.http_client.get(&self.ping_url).send().await?.error_for_status()?;
+        Ok(())
+    }
+}
+
+// measurement,tag=value field=value timestamp
+fn line(body: &mut String, point: &Point) {
+    if point.fields.is_empty() {
+        return;
+    }
+    body.push_str(&escape(point.measurement, false));
+    for (key, value) in &point.tags {
+        let _ = write!(body, ",{}={}", escape(key, true), escape(value, true));
+    }
+    for (i, (key, value)) in point.fields.iter().enumerate() {
+        let _ = write!(body, "{}{}={}", if i == 0 { ' ' } else { ',' }, escape(key, true), value);
+    }
+    let timestamp = point.timestamp.timestamp_nanos_opt().unwrap_or_default();
+    let _ = writeln!(body, " {}", timestamp);
+}
+
+// Measurements escape commas and spaces; tag and field keys and tag values
+// equals signs too
+fn escape(value: &str, equals: bool) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            ',' | ' ' => escaped.push('\\'),
+            '=' if equals => escaped.push('\\'),
+            // Line protocol can't escape line breaks, so they become spaces
+            '\n' | '\r' => {
+                escaped.push_str("\\ ");
+                continue;
+            }
+            _ => {}
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    
+    #[test]
+    fn escapes_commas_and_spaces() {
+        assert_eq!(escape("server metrics", false), "server\\ metrics");
+        assert_eq!(escape("a,b", false), "a\\,b");
+        assert_eq!(escape("plain_name", true), "plain_name");
+    }
+    
+    #[test]
+    fn escapes_equals_only_in_keys_and_tag_values() {
+        assert_eq!(escape("a=b", false), "a=b");
+        assert_eq!(escape("a=b", true), "a\\=b");
+        assert_eq!(escape("k=v, w", true), "k\\=v\\,\\ w");
+    }
+    
+    #[test]
+    fn turns_line_breaks_into_escaped_spaces() {
+        assert_eq!(escape("first\nsecond", true), "first\\ second");
+        assert_eq!(escape("first\r\nsecond", false), "first\\ \\ second");
+    }
+    
+    #[test]
+    fn writes_a_line_per_point() {
+        let point = Point {
+            measurement: "server metrics",
+            tags: vec![("server_id", "vm 1".to_string()), ("project_id", "a=b".to_string())],
+            fields: vec![("cpu_utilization".to_string(), 42.5), ("memory usage".to_string(), 512.0)],
+            timestamp: Utc.timestamp_opt(1_700_000_000, 0).unwrap(),
+        };
+        let mut body = String::new();
+        line(&mut body, &point);
+        assert_eq!(
+            body,
+            "server\\ metrics,server_id=vm\\ 1,project_id=a\\=b cpu_utilization=42.5,memory\\ usage=512 1700000000000000000\n"
+        );
+        
+        let empty = Point { fields: Vec::new(), ..point };
+        let mut body = String::new();
+        line(&mut body, &empty);
+        assert!(body.is_empty());
+    }
+}
@@ -0,0 +1,109 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use dashmap::DashMap;
+use rand::Rng;
+use std::collections::HashMap;
+use std::time::Duration;
+use tracing::debug;
+
+use crate::config::{CollectionConfig, MetricsConfig};
+use crate::scheduler::sla_manager::{SLAPolicy, SLAPriority};
+
+// How often each resource is collected, when it's next due, and how urgent
+// its collections are
+pub struct CollectionIntervals {
+    config: CollectionConfig,
+    compute: Duration,
+    network: Duration,
+    storage: Duration,
+    // From SLA policies, by resource id
+    sla: DashMap<String, SlaCollection>,
+}
+
+struct SlaCollection {
+    interval: Option<Duration>,
+    priority: SLAPriority,
+}
+
+impl CollectionIntervals {
+    pub fn new(config: &MetricsConfig) -> Self {
+        Self {
+            config: config.collection.clone(),
+            compute: Duration::from_secs(config.compute_interval_seconds),
+            network: Duration::from_secs(config.network_interval_seconds),
+            storage: Duration::from_secs(config.storage_interval_seconds),
+            sla: DashMap::new(),
+        }
+    }
+    
+    // Replaces what earlier policies set
+    pub fn set_sla_policies(&self, policies: &[SLAPolicy]) {
+        self.sla.clear();
+        for policy in policies {
+            self.sla.insert(policy.resource_id.clone(), SlaCollection {
+                interval: policy.collection_interval_seconds.map(Duration::from_secs),
+                priority: policy.priority,
+            });
+        }
+    }
+    
+    // None for resources without an SLA policy, which come after any that
+    // have one
+    pub fn priority(&self, resource_id: &str) -> Option<SLAPriority> {
+        self.sla.get(resource_id).map(|sla| sla.priority)
+    }
+    
+    // SLA policy, then server metadata, then workload class, then the
+    // compute interval
+    pub fn compute(&self, resource_id: &str, metadata: &HashMap<String, String>, workload_class: Option<&str>) -> Duration {
+        if let Some(interval) = self.sla.get(resource_id).and_then(|sla| sla.interval) {
+            return self.at_least_min(interval);
+        }
+        let from_metadata = self.config.metadata_key.as_ref()
+            .and_then(|key| metadata.get(key))
+            .and_then(|value| match value.trim().parse::<u64>() {
+                Ok(seconds) if seconds > 0 => Some(seconds),
+                _ => {
+                    debug!("Ignoring collection interval {:?} in the metadata of {}", value, resource_id);
+                    None
+                }
+            });
+        if let Some(seconds) = from_metadata {
+            return self.at_least_min(Duration::from_secs(seconds));
+        }
+        match workload_class.and_then(|class| self.config.workload_class_intervals.get(class)) {
+            Some(seconds) => self.at_least_min(Duration::from_secs(*seconds)),
+            None => self.compute,
+        }
+    }
+    
+    pub fn network(&self) -> Duration {
+        self.network
+    }
+    
+    pub fn storage(&self) -> Duration {
+        self.storage
+    }
+    
+    // Somewhere within the first interval, so resources discovered together
+    // don't all come due together
+    pub fn first(&self, now: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+        now + random_up_to(interval)
+    }
+    
+    // The interval plus up to jitter_ratio of it
+    pub fn next(&self, now: DateTime<Utc>, interval: Duration) -> DateTime<Utc> {
+        now + ChronoDuration::from_std(interval).unwrap_or_default() + random_up_to(interval.mul_f64(self.config.jitter_ratio))
+    }
+    
+    fn at_least_min(&self, interval: Duration) -> Duration {
+        interval.max(Duration::from_secs(self.config.min_interval_seconds))
+    }
+}
+
+fn random_up_to(max: Duration) -> ChronoDuration {
+    let millis = max.as_millis() as u64;
+    if millis == 0 {
+        return ChronoDuration::zero();
+    }
+    ChronoDuration::milliseconds(rand::thread_rng().gen_range(0..millis) as i64)
+}
@@ -0,0 +1,93 @@
+use anyhow::Result;
+use chrono::{Duration as ChronoDuration, Utc};
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::Message;
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::{KafkaConfig, KafkaConsumerConfig};
+use crate::openstack::services::ServerMetrics;
+use super::kafka_producer::trace_carrier;
+
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// A sample read off Kafka, with the trace context its producer sent along
+pub type ExternalSample = (ServerMetrics, HashMap<String, String>);
+
+// Reads server samples that agents outside OpenStack publish, for the
+// collector to record like its own
+pub struct ExternalMetricsConsumer {
+    consumer: StreamConsumer,
+    max_age: ChronoDuration,
+}
+
+// One sample per message, or a batch of them
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Payload {
+    One(ServerMetrics),
+    Many(Vec<ServerMetrics>),
+}
+
+impl ExternalMetricsConsumer {
+    pub fn new(config: &KafkaConsumerConfig, kafka_config: &KafkaConfig, group_id: &str) -> Result<Self> {
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", config.brokers.as_deref().unwrap_or(&kafka_config.brokers))
+            .set("group.id", group_id)
+            .set("auto.offset.reset", &config.auto_offset_reset)
+            .create()?;
+        let topics: Vec<&str> = config.topics.iter().map(String::as_str).collect();
+        consumer.subscribe(&topics)?;
+        info!("Reading external metrics from {} as {}", config.topics.join(", "), group_id);
+        
+        Ok(Self {
+            consumer,
+            max_age: ChronoDuration::seconds(config.max_age_seconds as i64),
+        })
+    }
+    
+    // Until shutdown or until nobody takes the samples any more
+    pub async fn run(&self, samples: mpsc::Sender<ExternalSample>, shutdown: CancellationToken) {
+        loop {
+            let received = tokio::select! {
+                received = self.consumer.recv() => received,
+                _ = shutdown.cancelled() => return,
+            };
+            // The message itself can't be held across an await
+            let (batch, carrier) = match received {
+                Ok(message) => match message.payload().map(serde_json::from_slice::<Payload>) {
+                    Some(Ok(Payload::One(metrics))) => (vec![metrics], trace_carrier(&message)),
+                    Some(Ok(Payload::Many(batch))) => (batch, trace_carrier(&message)),
+                    Some(Err(e)) => {
+                        debug!("Skipping unreadable sample from {}: {}", message.topic(), e);
+                        ::metrics::counter!("external_metrics_total", "outcome" => "unreadable").increment(1);
+                        continue;
+                    }
+                    None => continue,
+                },
+                Err(e) => {
+                    warn!("External metrics receive failed: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            
+            let oldest = Utc::now() - self.max_age;
+            for metrics in batch {
+                if metrics.timestamp < oldest {
+                    ::metrics::counter!("external_metrics_total", "outcome" => "stale").increment(1);
+                    continue;
+                }
+                ::metrics::counter!("external_metrics_total", "outcome" => "received").increment(1);
+                if samples.send((metrics, carrier.clone())).await.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+}
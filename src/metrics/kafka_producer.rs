@@ -1,89 +1,557 @@
 use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use futures_util::future::{join_all, BoxFuture};
 use rdkafka::config::ClientConfig;
-use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::message::{Header, Headers, OwnedHeaders};
+use rdkafka::producer::{FutureProducer, FutureRecord, Producer};
+use rdkafka::Message;
+use serde::Serialize;
 use serde_json;
-use std::time::Duration;
-use tracing::{debug, error};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc, oneshot};
+use tracing::{debug, error, info, instrument, warn};
 
-use crate::config::KafkaConfig;
-use crate::openstack::services::{ServerMetrics, NetworkMetrics, StorageMetrics};
+use crate::config::{DeliveryGuarantee, KafkaBatchingConfig, KafkaConfig, KafkaDeliveryConfig, KafkaSerialization, MetricsConfig, PluginConfig};
+use crate::error::{MetricsError, RetryPolicy, Retryable};
+use crate::openstack::services::{ServerMetrics, NetworkMetrics, LoadBalancerMetrics, ObjectStorageMetrics, StorageMetrics};
+use crate::plugins::{MetricSink, Registry};
+use crate::telemetry;
+use super::avro::{AvroRecord, SchemaRegistry};
+use super::kafka_spool::{Spool, SpooledMessage};
+use super::lifecycle::LifecycleEvent;
 
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+// Metric messages are queued for the batcher, which sends them a batch at a
+// time and waits for the whole batch to be delivered. What still fails after
+// the retry policy is spooled or dead-lettered as [delivery] says. Scheduler
+// events are sent and awaited one by one, as their callers record the outcome
 #[derive(Clone)]
 pub struct KafkaProducer {
     producer: FutureProducer,
     config: KafkaConfig,
+    retry: RetryPolicy,
+    queue: mpsc::Sender<Queued>,
+    // Set for avro serialization
+    schema_registry: Option<Arc<SchemaRegistry>>,
+}
+
+enum Queued {
+    Message(OutgoingMessage),
+    // Sends what's been batched so far, then answers
+    Flush(oneshot::Sender<()>),
+}
+
+struct OutgoingMessage {
+    topic: String,
+    key: String,
+    payload: Vec<u8>,
+    // Trace context of the span that queued it
+    trace: HashMap<String, String>,
 }
 
 impl KafkaProducer {
     pub async fn new(config: &KafkaConfig) -> Result<Self> {
-        let producer: FutureProducer = ClientConfig::new()
+        let batching = &config.batching;
+        let mut client_config = ClientConfig::new();
+        client_config
             .set("bootstrap.servers", &config.brokers)
             .set("message.timeout.ms", "5000")
             .set("queue.buffering.max.messages", "100000")
-            .set("queue.buffering.max.ms", "10")
-            .set("batch.num.messages", "1000")
-            .create()?;
+            .set("queue.buffering.max.ms", batching.linger_ms.to_string())
+            .set("batch.num.messages", batching.batch_size.to_string())
+            .set("compression.type", batching.compression.as_str());
+        // Internal retries then neither duplicate nor reorder messages
+        let spool = match config.delivery.guarantee {
+            DeliveryGuarantee::AtMostOnce => None,
+            DeliveryGuarantee::AtLeastOnce => {
+                client_config
+                    .set("enable.idempotence", "true")
+                    .set("acks", "all");
+                Some(Spool::open(&config.delivery.spool_dir, config.delivery.max_spool_files).await?)
+            }
+        };
+        let producer: FutureProducer = client_config.create()?;
+        
+        let retry = config.retry.policy();
+        let (queue, queued) = mpsc::channel(batching.queue_capacity.max(1));
+        let batcher = Batcher {
+            producer: producer.clone(),
+            retry: retry.clone(),
+            config: batching.clone(),
+            delivery: config.delivery.clone(),
+            spool,
+            stats: BTreeMap::new(),
+            stats_since: Instant::now(),
+        };
+        // Ends once every clone of the producer is dropped
+        tokio::spawn(batcher.run(queued));
         
+        let schema_registry = match config.serialization {
+            KafkaSerialization::Json => None,
+            KafkaSerialization::Avro => Some(Arc::new(SchemaRegistry::new(&config.schema_registry)?)),
+        };
         Ok(Self {
             producer,
             config: config.clone(),
+            retry,
+            queue,
+            schema_registry,
         })
     }
     
-    pub async fn send_server_metrics(&self, metrics: &ServerMetrics) -> Result<()> {
-        let payload = serde_json::to_string(metrics)?;
+    // Sends what's queued and waits for it to be delivered, up to the timeout
+    pub async fn flush(&self, timeout: Duration) -> Result<()> {
+        let started = Instant::now();
+        let (ack, acked) = oneshot::channel();
+        let queued = async {
+            if self.queue.send(Queued::Flush(ack)).await.is_ok() {
+                let _ = acked.await;
+            }
+        };
+        if tokio::time::timeout(timeout, queued).await.is_err() {
+            anyhow::bail!("queued Kafka messages weren't sent within {:?}", timeout);
+        }
         
-        let record = FutureRecord::to(&self.config.compute_topic)
-            .key(&metrics.server_id)
-            .payload(&payload);
+        let producer = self.producer.clone();
+        let remaining = timeout.saturating_sub(started.elapsed());
+        tokio::task::spawn_blocking(move || producer.flush(remaining)).await??;
+        Ok(())
+    }
+    
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = %self.config.compute_topic, key = %metrics.server_id))]
+    pub async fn send_server_metrics(&self, metrics: &ServerMetrics) -> Result<()> {
+        let payload = self.encode(&self.config.compute_topic, metrics).await?;
+        self.enqueue(&self.config.compute_topic, &metrics.server_id, payload).await
+    }
+    
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = %self.config.network_topic, key = %metrics.network_id))]
+    pub async fn send_network_metrics(&self, metrics: &NetworkMetrics) -> Result<()> {
+        let payload = self.encode(&self.config.network_topic, metrics).await?;
+        self.enqueue(&self.config.network_topic, &metrics.network_id, payload).await
+    }
+    
+    // No-op without an events topic
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = ?self.config.events_topic, key = %key))]
+    pub async fn send_event<T: Serialize>(&self, key: &str, event: &T) -> Result<()> {
+        let topic = match &self.config.events_topic {
+            Some(topic) => topic,
+            None => return Ok(()),
+        };
+        let payload = serde_json::to_string(event)?;
+        let headers = trace_headers(&telemetry::inject_context());
         
-        match self.producer.send(record, Duration::from_secs(1)).await {
-            Ok(_) => {
-                debug!("Sent server metrics for {}", metrics.server_id);
+        match deliver(&self.producer, &self.retry, topic, key, payload.as_bytes(), &headers).await {
+            Ok(()) => {
+                debug!("Sent scheduler event for {}", key);
                 Ok(())
             },
-            Err((e, _)) => {
-                error!("Failed to send server metrics: {}", e);
+            Err(e) => {
+                error!("Failed to send scheduler event: {}", e);
                 Err(e.into())
             }
         }
     }
     
-    pub async fn send_network_metrics(&self, metrics: &NetworkMetrics) -> Result<()> {
-        let payload = serde_json::to_string(metrics)?;
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = %self.config.storage_topic, key = %metrics.volume_id))]
+    pub async fn send_storage_metrics(&self, metrics: &StorageMetrics) -> Result<()> {
+        let payload = self.encode(&self.config.storage_topic, metrics).await?;
+        self.enqueue(&self.config.storage_topic, &metrics.volume_id, payload).await
+    }
+    
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = %self.config.object_storage_topic, key = %metrics.account))]
+    pub async fn send_object_storage_metrics(&self, metrics: &ObjectStorageMetrics) -> Result<()> {
+        self.enqueue(&self.config.object_storage_topic, &metrics.account, serde_json::to_vec(metrics)?).await
+    }
+    
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = %self.config.loadbalancer_topic, key = %metrics.loadbalancer_id))]
+    pub async fn send_load_balancer_metrics(&self, metrics: &LoadBalancerMetrics) -> Result<()> {
+        self.enqueue(&self.config.loadbalancer_topic, &metrics.loadbalancer_id, serde_json::to_vec(metrics)?).await
+    }
+    
+    // No-op without a lifecycle topic
+    #[instrument(name = "kafka.publish", skip_all, fields(topic = ?self.config.lifecycle_topic, key = %event.resource_id))]
+    pub async fn send_lifecycle_event(&self, event: &LifecycleEvent) -> Result<()> {
+        match &self.config.lifecycle_topic {
+            Some(topic) => self.enqueue(topic, &event.resource_id, serde_json::to_vec(event)?).await,
+            None => Ok(()),
+        }
+    }
+    
+    // As configured for the topics that have a schema
+    async fn encode<T: AvroRecord + Serialize>(&self, topic: &str, record: &T) -> Result<Vec<u8>> {
+        match &self.schema_registry {
+            Some(registry) => registry.encode(topic, record).await,
+            None => Ok(serde_json::to_vec(record)?),
+        }
+    }
+    
+    // Waits only while the queue is full; delivery failures are handled by
+    // the batcher
+    async fn enqueue(&self, topic: &str, key: &str, payload: Vec<u8>) -> Result<()> {
+        let message = OutgoingMessage {
+            topic: topic.to_string(),
+            key: key.to_string(),
+            payload,
+            trace: telemetry::inject_context(),
+        };
+        self.queue.send(Queued::Message(message)).await
+            .map_err(|_| MetricsError::ProcessingError("the Kafka batcher has stopped".to_string()))?;
+        Ok(())
+    }
+}
+
+struct Batcher {
+    producer: FutureProducer,
+    retry: RetryPolicy,
+    config: KafkaBatchingConfig,
+    delivery: KafkaDeliveryConfig,
+    // Set for at-least-once delivery
+    spool: Option<Spool>,
+    // Since the last report, by topic
+    stats: BTreeMap<String, TopicStats>,
+    stats_since: Instant,
+}
+
+#[derive(Default)]
+struct TopicStats {
+    delivered: u64,
+    failed: u64,
+    bytes: u64,
+}
+
+impl Batcher {
+    async fn run(mut self, mut queued: mpsc::Receiver<Queued>) {
+        let linger = Duration::from_millis(self.config.linger_ms);
+        // Also picks up what an earlier run left in the spool
+        let mut spool_retry = tokio::time::interval(Duration::from_secs(self.delivery.spool_retry_seconds.max(1)));
+        loop {
+            let first = tokio::select! {
+                first = queued.recv() => first,
+                _ = spool_retry.tick(), if self.spool.is_some() => {
+                    self.retry_spool().await;
+                    continue;
+                }
+            };
+            // The spool is left for the next run
+            let Some(first) = first else {
+                return;
+            };
+            
+            let mut batch = Vec::new();
+            let mut flushes = Vec::new();
+            match first {
+                Queued::Message(message) => batch.push(message),
+                Queued::Flush(ack) => flushes.push(ack),
+            }
+            
+            let deadline = tokio::time::Instant::now() + linger;
+            while flushes.is_empty() && batch.len() < self.config.batch_size {
+                match tokio::time::timeout_at(deadline, queued.recv()).await {
+                    Ok(Some(Queued::Message(message))) => batch.push(message),
+                    Ok(Some(Queued::Flush(ack))) => flushes.push(ack),
+                    Ok(None) | Err(_) => break,
+                }
+            }
+            
+            self.send(batch).await;
+            for ack in flushes {
+                let _ = ack.send(());
+            }
+            self.report();
+        }
+    }
+    
+    async fn send(&mut self, batch: Vec<OutgoingMessage>) {
+        if batch.is_empty() {
+            return;
+        }
+        ::metrics::histogram!("kafka_batch_messages").record(batch.len() as f64);
         
-        let record = FutureRecord::to(&self.config.network_topic)
-            .key(&metrics.network_id)
-            .payload(&payload);
+        let results = join_all(batch.iter().map(|message| {
+            let headers = trace_headers(&message.trace);
+            async move {
+                deliver(&self.producer, &self.retry, &message.topic, &message.key, &message.payload, &headers).await
+            }
+        })).await;
         
-        match self.producer.send(record, Duration::from_secs(1)).await {
-            Ok(_) => {
-                debug!("Sent network metrics for {}", metrics.network_id);
-                Ok(())
-            },
-            Err((e, _)) => {
-                error!("Failed to send network metrics: {}", e);
-                Err(e.into())
+        let mut to_spool = Vec::new();
+        for (message, result) in batch.into_iter().zip(results) {
+            let e = match result {
+                Ok(()) => {
+                    delivered(&mut self.stats, &message.topic, message.payload.len());
+                    continue;
+                }
+                Err(e) => e,
+            };
+            self.stats.entry(message.topic.clone()).or_default().failed += 1;
+            warn!("Failed to send {} to Kafka topic {}: {}", message.key, message.topic, e);
+            
+            let failed = SpooledMessage {
+                topic: message.topic,
+                key: message.key,
+                payload: message.payload,
+                trace: message.trace,
+                attempts: 1,
+                first_failed_at: Utc::now(),
+                last_error: e.to_string(),
+            };
+            if self.spool.is_some() && e.is_retryable() {
+                to_spool.push(failed);
+            } else if e.is_retryable() {
+                count(&failed.topic, "dropped");
+            } else {
+                self.dead_letter(&failed).await;
+            }
+        }
+        
+        let Some(spool) = &self.spool else {
+            return;
+        };
+        if to_spool.is_empty() {
+            return;
+        }
+        match spool.write(&to_spool).await {
+            Ok(()) => {
+                for message in &to_spool {
+                    count(&message.topic, "spooled");
+                }
+            }
+            Err(e) => {
+                error!("Cannot spool {} failed Kafka messages: {:#}", to_spool.len(), e);
+                for message in &to_spool {
+                    self.dead_letter(message).await;
+                }
             }
         }
     }
     
-    pub async fn send_storage_metrics(&self, metrics: &StorageMetrics) -> Result<()> {
-        let payload = serde_json::to_string(metrics)?;
+    // Oldest batch first, stopping at the first one that gets nowhere as
+    // Kafka is likely still unreachable
+    async fn retry_spool(&mut self) {
+        let Some(spool) = &self.spool else {
+            return;
+        };
+        let files = match spool.files().await {
+            Ok(files) => files,
+            Err(e) => {
+                error!("Cannot list the Kafka spool: {}", e);
+                return;
+            }
+        };
         
-        let record = FutureRecord::to(&self.config.storage_topic)
-            .key(&metrics.volume_id)
-            .payload(&payload);
+        for path in files {
+            let messages = match spool.read(&path).await {
+                Ok(messages) => messages,
+                Err(e) => {
+                    error!("{:#}, setting it aside", e);
+                    if let Err(e) = spool.set_aside(&path).await {
+                        error!("Cannot set aside {}: {}", path.display(), e);
+                    }
+                    continue;
+                }
+            };
+            
+            let results = join_all(messages.iter().map(|message| {
+                let headers = trace_headers(&message.trace);
+                async move {
+                    deliver(&self.producer, &self.retry, &message.topic, &message.key, &message.payload, &headers).await
+                }
+            })).await;
+            
+            let spooled = messages.len();
+            let mut remaining = Vec::new();
+            for (mut message, result) in messages.into_iter().zip(results) {
+                match result {
+                    Ok(()) => {
+                        delivered(&mut self.stats, &message.topic, message.payload.len());
+                    }
+                    Err(e) => {
+                        message.attempts += 1;
+                        message.last_error = e.to_string();
+                        if !e.is_retryable() || message.attempts >= self.delivery.max_spool_attempts {
+                            self.dead_letter(&message).await;
+                        } else {
+                            remaining.push(message);
+                        }
+                    }
+                }
+            }
+            
+            let stalled = remaining.len() == spooled;
+            if let Err(e) = spool.replace(&path, &remaining).await {
+                error!("Cannot update Kafka spool file {}: {}", path.display(), e);
+            }
+            if stalled {
+                debug!("Kafka still refuses spooled messages, {} left in {}", spooled, path.display());
+                return;
+            }
+        }
+    }
+    
+    // Sent as is, with headers saying where it was headed and why it didn't
+    // get there
+    async fn dead_letter(&self, message: &SpooledMessage) {
+        let Some(topic) = &self.delivery.dead_letter_topic else {
+            error!("Dropping message {} for Kafka topic {}: {}", message.key, message.topic, message.last_error);
+            count(&message.topic, "dropped");
+            return;
+        };
+        let attempts = message.attempts.to_string();
+        let first_failed_at = message.first_failed_at.to_rfc3339();
+        let headers = trace_headers(&message.trace)
+            .insert(Header { key: "dlt.original_topic", value: Some(message.topic.as_str()) })
+            .insert(Header { key: "dlt.error", value: Some(message.last_error.as_str()) })
+            .insert(Header { key: "dlt.attempts", value: Some(attempts.as_str()) })
+            .insert(Header { key: "dlt.first_failed_at", value: Some(first_failed_at.as_str()) });
         
-        match self.producer.send(record, Duration::from_secs(1)).await {
-            Ok(_) => {
-                debug!("Sent storage metrics for {}", metrics.volume_id);
-                Ok(())
-            },
-            Err((e, _)) => {
-                error!("Failed to send storage metrics: {}", e);
-                Err(e.into())
+        match deliver(&self.producer, &self.retry, topic, &message.key, &message.payload, &headers).await {
+            Ok(()) => {
+                warn!("Sent message {} for Kafka topic {} to dead-letter topic {}: {}", message.key, message.topic, topic, message.last_error);
+                count(&message.topic, "dead_lettered");
+            }
+            Err(e) => {
+                error!("Dropping message {} for Kafka topic {}, the dead-letter topic refused it too: {}", message.key, message.topic, e);
+                count(&message.topic, "dropped");
             }
         }
     }
+    
+    // Logs each topic's throughput once the interval has passed
+    fn report(&mut self) {
+        let interval = self.config.stats_interval_seconds;
+        let elapsed = self.stats_since.elapsed();
+        if interval == 0 || elapsed < Duration::from_secs(interval) {
+            return;
+        }
+        let seconds = elapsed.as_secs_f64();
+        for (topic, stats) in std::mem::take(&mut self.stats) {
+            info!(
+                "Kafka topic {}: {:.1} messages/s, {:.1} KiB/s, {} failed over the last {:.0}s",
+                topic,
+                stats.delivered as f64 / seconds,
+                stats.bytes as f64 / 1024.0 / seconds,
+                stats.failed,
+                seconds,
+            );
+        }
+        self.stats_since = Instant::now();
+    }
+}
+
+// Takes the stats rather than the batcher, which is borrowed for its spool
+fn delivered(stats: &mut BTreeMap<String, TopicStats>, topic: &str, bytes: usize) {
+    let stats = stats.entry(topic.to_string()).or_default();
+    stats.delivered += 1;
+    stats.bytes += bytes as u64;
+    count(topic, "delivered");
+    ::metrics::counter!("kafka_message_bytes_total", "topic" => topic.to_string()).increment(bytes as u64);
+}
+
+fn count(topic: &str, outcome: &'static str) {
+    ::metrics::counter!("kafka_messages_total", "topic" => topic.to_string(), "outcome" => outcome).increment(1);
+}
+
+// Retries what Kafka reports as transient. The record is rebuilt for each
+// attempt, as a failed send consumes it
+async fn deliver(
+    producer: &FutureProducer,
+    retry: &RetryPolicy,
+    topic: &str,
+    key: &str,
+    payload: &[u8],
+    headers: &OwnedHeaders,
+) -> Result<(), MetricsError> {
+    retry.run("Kafka publish", || async {
+        let record = FutureRecord::to(topic)
+            .key(key)
+            .payload(payload)
+            .headers(headers.clone());
+        
+        producer.send(record, Duration::from_secs(1)).await
+            .map(|_| ())
+            .map_err(|(e, _)| MetricsError::from(e))
+    }).await
+}
+
+#[async_trait]
+impl MetricSink for KafkaProducer {
+    fn name(&self) -> &str {
+        "kafka"
+    }
+    
+    async fn publish_server(&self, metrics: &ServerMetrics) -> Result<()> {
+        self.send_server_metrics(metrics).await
+    }
+    
+    async fn publish_network(&self, metrics: &NetworkMetrics) -> Result<()> {
+        self.send_network_metrics(metrics).await
+    }
+    
+    async fn publish_storage(&self, metrics: &StorageMetrics) -> Result<()> {
+        self.send_storage_metrics(metrics).await
+    }
+    
+    async fn publish_object_storage(&self, metrics: &ObjectStorageMetrics) -> Result<()> {
+        self.send_object_storage_metrics(metrics).await
+    }
+    
+    async fn publish_load_balancer(&self, metrics: &LoadBalancerMetrics) -> Result<()> {
+        self.send_load_balancer_metrics(metrics).await
+    }
+    
+    async fn publish_lifecycle(&self, event: &LifecycleEvent) -> Result<()> {
+        self.send_lifecycle_event(event).await
+    }
+    
+    // Asks the brokers for cluster metadata, which needs a live connection,
+    // and has the registry take the schemas up front
+    async fn check(&self) -> Result<()> {
+        let producer = self.producer.clone();
+        tokio::task::spawn_blocking(move || producer.client().fetch_metadata(None, METADATA_TIMEOUT)).await??;
+        if let Some(registry) = &self.schema_registry {
+            registry.register::<ServerMetrics>(&self.config.compute_topic).await?;
+            registry.register::<NetworkMetrics>(&self.config.network_topic).await?;
+            registry.register::<StorageMetrics>(&self.config.storage_topic).await?;
+        }
+        Ok(())
+    }
+    
+    async fn flush(&self, timeout: Duration) -> Result<()> {
+        KafkaProducer::flush(self, timeout).await
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.add_sink("kafka", kafka_sink);
+}
+
+// Publishes to the topics in [metrics.kafka_config]
+fn kafka_sink<'a>(_plugin: &'a PluginConfig, config: &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSink>>> {
+    Box::pin(async move {
+        let producer = KafkaProducer::new(&config.kafka_config).await?;
+        Ok(Arc::new(producer) as Arc<dyn MetricSink>)
+    })
+}
+
+// The trace a message was sent in, so consumers can continue it
+fn trace_headers(carrier: &HashMap<String, String>) -> OwnedHeaders {
+    carrier.iter().fold(OwnedHeaders::new(), |headers, (key, value)| {
+        headers.insert(Header { key, value: Some(value.as_str()) })
+    })
+}
+
+// Trace context from a message's headers, for telemetry::set_remote_parent
+pub fn trace_carrier<M: Message>(message: &M) -> HashMap<String, String> {
+    message.headers()
+        .into_iter()
+        .flat_map(|headers| headers.iter())
+        .filter_map(|header| {
+            let value = std::str::from_utf8(header.value?).ok()?;
+            Some((header.key.to_string(), value.to_string()))
+        })
+        .collect()
 }
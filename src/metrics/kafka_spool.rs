@@ -0,0 +1,95 @@
+use anyhow::{Context, Result};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use uuid::Uuid;
+
+// Kafka messages that couldn't be delivered, kept in files on local disk
+// until they can be. Each file holds one failed batch and is rewritten with
+// what's left after every retry
+pub struct Spool {
+    dir: PathBuf,
+    max_files: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SpooledMessage {
+    pub topic: String,
+    pub key: String,
+    #[serde(serialize_with = "encode_payload", deserialize_with = "decode_payload")]
+    pub payload: Vec<u8>,
+    pub trace: HashMap<String, String>,
+    // Delivery attempts so far, each a full run of the retry policy
+    pub attempts: u32,
+    pub first_failed_at: DateTime<Utc>,
+    pub last_error: String,
+}
+
+impl Spool {
+    pub async fn open(dir: &str, max_files: usize) -> Result<Self> {
+        tokio::fs::create_dir_all(dir).await
+            .with_context(|| format!("Cannot create the Kafka spool directory {}", dir))?;
+        Ok(Self {
+            dir: PathBuf::from(dir),
+            max_files,
+        })
+    }
+    
+    // Fails once the spool holds max_files, so an outage can't fill the disk
+    pub async fn write(&self, messages: &[SpooledMessage]) -> Result<()> {
+        if self.files().await?.len() >= self.max_files {
+            anyhow::bail!("the Kafka spool is full ({} batches)", self.max_files);
+        }
+        let name = format!("{}-{}.json", Utc::now().format("%Y%m%dT%H%M%S%.6f"), Uuid::new_v4());
+        self.replace(&self.dir.join(name), messages).await
+    }
+    
+    // Oldest first
+    pub async fn files(&self) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        let mut entries = tokio::fs::read_dir(&self.dir).await?;
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().is_some_and(|extension| extension == "json") {
+                files.push(path);
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+    
+    pub async fn read(&self, path: &PathBuf) -> Result<Vec<SpooledMessage>> {
+        let contents = tokio::fs::read(path).await?;
+        serde_json::from_slice(&contents).with_context(|| format!("Unreadable Kafka spool file {}", path.display()))
+    }
+    
+    // Out of the way of retries, but still there for someone to look at
+    pub async fn set_aside(&self, path: &PathBuf) -> Result<()> {
+        tokio::fs::rename(path, path.with_extension("unreadable")).await?;
+        Ok(())
+    }
+    
+    // Removes the file when nothing is left in it. Written next to it first
+    // so a crash never leaves half a file
+    pub async fn replace(&self, path: &PathBuf, messages: &[SpooledMessage]) -> Result<()> {
+        if messages.is_empty() {
+            return Ok(tokio::fs::remove_file(path).await?);
+        }
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, serde_json::to_vec(messages)?).await?;
+        tokio::fs::rename(&partial, path).await?;
+        Ok(())
+    }
+}
+
+fn encode_payload<S: Serializer>(payload: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&STANDARD.encode(payload))
+}
+
+fn decode_payload<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+    let encoded = String::deserialize(deserializer)?;
+    STANDARD.decode(encoded).map_err(serde::de::Error::custom)
+}
@@ -0,0 +1,119 @@
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+
+use crate::openstack::services::ServerMetrics;
+use crate::telemetry;
+
+// Most recent observation of each metric per resource, fed by the collector
+// and read by SLA compliance checks
+pub struct LatestMetrics {
+    samples: DashMap<String, LatestSample>,
+    stale_after: Duration,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Observed<T> {
+    value: T,
+    observed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Default)]
+struct LatestSample {
+    cpu_utilization: Option<Observed<f64>>,
+    memory_utilization: Option<Observed<f64>>,
+    response_time_ms: Option<Observed<u64>>,
+    availability_percent: Option<Observed<f64>>,
+    // Trace of the last server sample, which later inference and decisions
+    // for the resource join
+    trace: Option<opentelemetry::Context>,
+}
+
+// Fresh values only; stale or never-seen metrics are None
+#[derive(Debug, Clone, Default)]
+pub struct MetricsView {
+    pub cpu_utilization: Option<f64>,
+    pub memory_utilization: Option<f64>,
+    pub response_time_ms: Option<u64>,
+    pub availability_percent: Option<f64>,
+}
+
+impl MetricsView {
+    pub fn is_empty(&self) -> bool {
+        self.cpu_utilization.is_none()
+            && self.memory_utilization.is_none()
+            && self.response_time_ms.is_none()
+            && self.availability_percent.is_none()
+    }
+}
+
+impl LatestMetrics {
+    pub fn new(stale_after_seconds: u64) -> Self {
+        Self {
+            samples: DashMap::new(),
+            stale_after: Duration::seconds(stale_after_seconds as i64),
+        }
+    }
+    
+    // Called within the span that collected or received the sample
+    pub fn record_server_metrics(&self, metrics: &ServerMetrics) {
+        let mut sample = self.samples.entry(metrics.server_id.clone()).or_default();
+        sample.trace = Some(telemetry::current_context());
+        sample.cpu_utilization = Some(Observed {
+            value: metrics.cpu_utilization,
+            observed_at: metrics.timestamp,
+        });
+        if metrics.memory_total > 0 {
+            sample.memory_utilization = Some(Observed {
+                value: metrics.memory_usage as f64 / metrics.memory_total as f64 * 100.0,
+                observed_at: metrics.timestamp,
+            });
+        }
+        // Nova doesn't measure it; sources that do, such as an agent probing
+        // the service, send it among the extras
+        if let Some(response_time_ms) = metrics.extra.get("response_time_ms") {
+            sample.response_time_ms = Some(Observed {
+                value: response_time_ms.max(0.0).round() as u64,
+                observed_at: metrics.timestamp,
+            });
+        }
+    }
+    
+    pub fn record_status(&self, resource_id: &str, status: &str) {
+        let available = status.eq_ignore_ascii_case("ACTIVE");
+        self.record_availability(resource_id, if available { 100.0 } else { 0.0 });
+    }
+    
+    // For resources that are partly up, like a load balancer with some
+    // pools down
+    pub fn record_availability(&self, resource_id: &str, availability_percent: f64) {
+        let mut sample = self.samples.entry(resource_id.to_string()).or_default();
+        sample.availability_percent = Some(Observed {
+            value: availability_percent,
+            observed_at: Utc::now(),
+        });
+    }
+    
+    pub fn trace_context(&self, resource_id: &str) -> Option<opentelemetry::Context> {
+        self.samples.get(resource_id).and_then(|sample| sample.trace.clone())
+    }
+    
+    pub fn view(&self, resource_id: &str, now: DateTime<Utc>) -> MetricsView {
+        let sample = match self.samples.get(resource_id) {
+            Some(sample) => sample.clone(),
+            None => return MetricsView::default(),
+        };
+        
+        MetricsView {
+            cpu_utilization: self.fresh(sample.cpu_utilization, now),
+            memory_utilization: self.fresh(sample.memory_utilization, now),
+            response_time_ms: self.fresh(sample.response_time_ms, now),
+            availability_percent: self.fresh(sample.availability_percent, now),
+        }
+    }
+    
+    fn fresh<T>(&self, observed: Option<Observed<T>>, now: DateTime<Utc>) -> Option<T> {
+        observed
+            .filter(|o| now - o.observed_at <= self.stale_after)
+            .map(|o| o.value)
+    }
+}
@@ -0,0 +1,114 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+use super::collector::ResourceInfo;
+
+// A resource starting or stopping being collected, or going into or out of
+// ERROR. Acquired and Released are this member taking over or handing off
+// collection, not changes to the resource, and stay in the changelog
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LifecycleEvent {
+    pub id: String,
+    pub resource_id: String,
+    pub resource_type: String,
+    pub kind: LifecycleKind,
+    // Why it was removed, or the fault it went into ERROR with
+    pub reason: Option<String>,
+    pub project_id: Option<String>,
+    pub cloud: Option<String>,
+    pub region: Option<String>,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LifecycleKind {
+    Added,
+    Removed,
+    Errored,
+    Recovered,
+    Acquired,
+    Released,
+}
+
+impl LifecycleKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Added => "added",
+            Self::Removed => "removed",
+            Self::Errored => "errored",
+            Self::Recovered => "recovered",
+            Self::Acquired => "acquired",
+            Self::Released => "released",
+        }
+    }
+    
+    pub fn is_ownership(&self) -> bool {
+        matches!(self, Self::Acquired | Self::Released)
+    }
+}
+
+impl LifecycleEvent {
+    pub fn new(resource_id: &str, info: &ResourceInfo, kind: LifecycleKind, reason: Option<String>) -> Self {
+        let timestamp = Utc::now();
+        Self {
+            // Sorts by time, and is unique within the same microsecond
+            id: format!("{}-{}", timestamp.format("%Y%m%dT%H%M%S%.6fZ"), &Uuid::new_v4().simple().to_string()[..8]),
+            resource_id: resource_id.to_string(),
+            resource_type: info.resource_type.clone(),
+            kind,
+            reason,
+            project_id: info.project_id.clone(),
+            cloud: info.cloud.clone(),
+            region: info.region.clone(),
+            timestamp,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChangelogQuery {
+    since: Option<DateTime<Utc>>,
+    resource_id: Option<String>,
+    kind: Option<LifecycleKind>,
+    limit: Option<usize>,
+}
+
+// The most recent lifecycle events, in memory only; with sharding each
+// member has those of its own shard
+pub struct Changelog {
+    max_entries: usize,
+    // Oldest first
+    entries: Mutex<VecDeque<LifecycleEvent>>,
+}
+
+impl Changelog {
+    pub fn new(max_entries: usize) -> Self {
+        Self {
+            max_entries: max_entries.max(1),
+            entries: Mutex::new(VecDeque::new()),
+        }
+    }
+    
+    pub fn record(&self, event: LifecycleEvent) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.push_back(event);
+        while entries.len() > self.max_entries {
+            entries.pop_front();
+        }
+    }
+    
+    // Newest first
+    pub fn query(&self, query: &ChangelogQuery) -> Vec<LifecycleEvent> {
+        self.entries.lock().unwrap().iter().rev()
+            .filter(|e| query.since.is_none_or(|since| e.timestamp >= since))
+            .filter(|e| query.resource_id.as_ref().is_none_or(|id| &e.resource_id == id))
+            .filter(|e| query.kind.is_none_or(|kind| e.kind == kind))
+            .take(query.limit.unwrap_or(self.max_entries))
+            .cloned()
+            .collect()
+    }
+}
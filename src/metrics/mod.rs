@@ -1,4 +1,22 @@
+pub mod agent;
+pub mod avro;
 pub mod collector;
+pub mod edf;
+pub mod history;
+pub mod history_store;
+pub mod http_source;
+pub mod influxdb;
+pub mod intervals;
+pub mod kafka_consumer;
 pub mod kafka_producer;
+pub mod kafka_spool;
+pub mod latest;
+pub mod lifecycle;
+pub mod notifications;
+pub mod point_sink;
+pub mod processor;
+pub mod sharding;
+pub mod timescale;
 
 pub use collector::MetricsCollector;
+pub use latest::LatestMetrics;
@@ -0,0 +1,154 @@
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use lapin::options::{BasicConsumeOptions, ExchangeDeclareOptions, QueueBindOptions, QueueDeclareOptions};
+use lapin::types::FieldTable;
+use lapin::{Connection, ConnectionProperties, ExchangeKind};
+use serde_json::Value;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::NotificationsConfig;
+
+// What a notification means for discovery
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResourceEvent {
+    // Created, moved, resized or otherwise changed; read it again from Nova
+    ServerChanged(String),
+    ServerDeleted(String),
+}
+
+// Follows the notifications Nova, Neutron and Cinder publish through
+// oslo.messaging. Every instance binds a queue of its own, so with sharding
+// each sees every event and keeps those for its share
+pub struct NotificationListener {
+    config: NotificationsConfig,
+}
+
+impl NotificationListener {
+    pub fn new(config: NotificationsConfig) -> Self {
+        Self { config }
+    }
+    
+    // Reconnects after losing the broker until shutdown or until nobody
+    // takes the events any more
+    pub async fn run(&self, events: mpsc::Sender<ResourceEvent>, shutdown: CancellationToken) {
+        let reconnect = Duration::from_secs(self.config.reconnect_seconds);
+        loop {
+            match self.consume(&events, &shutdown).await {
+                Ok(()) => return,
+                Err(e) => warn!("OpenStack notification listener failed: {:#}", e),
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(reconnect) => {}
+                _ = shutdown.cancelled() => return,
+            }
+        }
+    }
+    
+    async fn consume(&self, events: &mpsc::Sender<ResourceEvent>, shutdown: &CancellationToken) -> Result<()> {
+        let connection = Connection::connect(&self.config.url, ConnectionProperties::default()).await
+            .context("Cannot connect to the notification broker")?;
+        let channel = connection.create_channel().await?;
+        
+        // Named by the broker and gone once we disconnect
+        let queue = channel.queue_declare(
+            "",
+            QueueDeclareOptions { exclusive: true, auto_delete: true, ..Default::default() },
+            FieldTable::default(),
+        ).await?;
+        for exchange in &self.config.exchanges {
+            // The services own their exchanges and declare them with their
+            // own settings, so only check it's there; until the service has
+            // declared it this fails and the listener tries again later
+            channel.exchange_declare(exchange, ExchangeKind::Topic, ExchangeDeclareOptions { passive: true, ..Default::default() }, FieldTable::default()).await
+                .with_context(|| format!("Exchange {} doesn't exist", exchange))?;
+            for topic in &self.config.topics {
+                let routing_key = format!("{}.info", topic);
+                channel.queue_bind(queue.name().as_str(), exchange, &routing_key, QueueBindOptions::default(), FieldTable::default()).await
+                    .with_context(|| format!("Cannot bind to {} on exchange {}", routing_key, exchange))?;
+            }
+        }
+        
+        let mut consumer = channel.basic_consume(
+            queue.name().as_str(),
+            "",
+            BasicConsumeOptions { no_ack: true, ..Default::default() },
+            FieldTable::default(),
+        ).await?;
+        info!("Listening for OpenStack notifications on {}", self.config.exchanges.join(", "));
+        
+        loop {
+            let delivery = tokio::select! {
+                delivery = consumer.next() => delivery,
+                _ = shutdown.cancelled() => {
+                    let _ = connection.close(0, "shutting down").await;
+                    return Ok(());
+                }
+            };
+            let delivery = delivery.context("The broker closed the consumer")??;
+            
+            match parse(&delivery.data) {
+                Some(event) => {
+                    debug!("Notification for discovery: {:?}", event);
+                    ::metrics::counter!("openstack_notifications_total", "outcome" => "applied").increment(1);
+                    if events.send(event).await.is_err() {
+                        return Ok(());
+                    }
+                }
+                None => ::metrics::counter!("openstack_notifications_total", "outcome" => "ignored").increment(1),
+            }
+        }
+    }
+}
+
+// The event a notification carries, if discovery cares about it. Handles
+// Nova's legacy and versioned notifications, Neutron port changes on a
+// server and Cinder volumes attached to or detached from one
+fn parse(body: &[u8]) -> Option<ResourceEvent> {
+    let message: Value = serde_json::from_slice(body).ok()?;
+    // oslo.messaging wraps the notification in an envelope as a string
+    let message = match message.get("oslo.message").and_then(Value::as_str) {
+        Some(inner) => serde_json::from_str(inner).ok()?,
+        None => message,
+    };
+    let event_type = message.get("event_type")?.as_str()?;
+    let payload = message.get("payload")?;
+    
+    if let Some(action) = event_type.strip_prefix("compute.instance.") {
+        let server_id = payload.get("instance_id")?.as_str()?.to_string();
+        return server_event(action, server_id);
+    }
+    if let Some(action) = event_type.strip_prefix("instance.") {
+        let server_id = payload.get("nova_object.data")?.get("uuid")?.as_str()?.to_string();
+        return server_event(action, server_id);
+    }
+    if matches!(event_type, "port.create.end" | "port.update.end") {
+        let port = payload.get("port")?;
+        if !port.get("device_owner")?.as_str()?.starts_with("compute:") {
+            return None;
+        }
+        let server_id = port.get("device_id")?.as_str()?;
+        return (!server_id.is_empty()).then(|| ResourceEvent::ServerChanged(server_id.to_string()));
+    }
+    if matches!(event_type, "volume.attach.end" | "volume.detach.end") {
+        let server_id = payload.get("instance_uuid").and_then(Value::as_str)
+            .or_else(|| {
+                payload.get("volume_attachment")?.as_array()?.iter()
+                    .find_map(|attachment| attachment.get("instance_uuid")?.as_str())
+            })?;
+        return Some(ResourceEvent::ServerChanged(server_id.to_string()));
+    }
+    None
+}
+
+// Only finished operations, plus Nova's catch-all state updates
+fn server_event(action: &str, server_id: String) -> Option<ResourceEvent> {
+    match action {
+        "delete.end" => Some(ResourceEvent::ServerDeleted(server_id)),
+        "update" => Some(ResourceEvent::ServerChanged(server_id)),
+        action if action.ends_with(".end") => Some(ResourceEvent::ServerChanged(server_id)),
+        _ => None,
+    }
+}
@@ -0,0 +1,241 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
+use tokio::sync::Notify;
+use tracing::warn;
+
+use crate::openstack::services::{LoadBalancerMetrics, NetworkMetrics, ObjectStorageMetrics, ServerMetrics, StorageMetrics};
+use crate::plugins::MetricSink;
+
+// Full batches that may wait for the writer before the oldest points are
+// dropped
+const MAX_PENDING_BATCHES: usize = 10;
+
+// A sample flattened for time-series databases: what was measured, the
+// labels to filter it by, and its numeric values
+#[derive(Debug, Clone)]
+pub struct Point {
+    pub measurement: &'static str,
+    pub tags: Vec<(&'static str, String)>,
+    pub fields: Vec<(String, f64)>,
+    pub timestamp: DateTime<Utc>,
+}
+
+impl Point {
+    fn new(measurement: &'static str, timestamp: DateTime<Utc>) -> Self {
+        Self {
+            measurement,
+            tags: Vec::new(),
+            fields: Vec::new(),
+            timestamp,
+        }
+    }
+    
+    fn tag(mut self, key: &'static str, value: Option<&str>) -> Self {
+        // Empty tag values aren't allowed in line protocol
+        if let Some(value) = value.filter(|value| !value.is_empty()) {
+            self.tags.push((key, value.to_string()));
+        }
+        self
+    }
+    
+    fn field(mut self, key: &str, value: f64) -> Self {
+        if value.is_finite() {
+            self.fields.push((key.to_string(), value));
+        }
+        self
+    }
+    
+    pub fn server(metrics: &ServerMetrics) -> Self {
+        let mut point = Self::new("openstack_server", metrics.timestamp)
+            .tag("server_id", Some(&metrics.server_id))
+            .tag("cloud", metrics.cloud.as_deref())
+            .tag("region", metrics.region.as_deref())
+            .tag("workload_class", metrics.workload_class.as_deref())
+            .field("cpu_utilization", metrics.cpu_utilization)
+            .field("memory_usage", metrics.memory_usage as f64)
+            .field("memory_total", metrics.memory_total as f64)
+            .field("disk_read_bytes", metrics.disk_read_bytes as f64)
+            .field("disk_write_bytes", metrics.disk_write_bytes as f64)
+            .field("network_rx_bytes", metrics.network_rx_bytes as f64)
+            .field("network_tx_bytes", metrics.network_tx_bytes as f64);
+        for (name, value) in &metrics.extra {
+            point = point.field(name, *value);
+        }
+        point
+    }
+    
+    pub fn network(metrics: &NetworkMetrics) -> Self {
+        Self::new("openstack_port", metrics.timestamp)
+            .tag("network_id", Some(&metrics.network_id))
+            .tag("port_id", Some(&metrics.port_id))
+            .tag("server_id", Some(&metrics.server_id))
+            .tag("router_id", metrics.router_id.as_deref())
+            .field("rx_bytes", metrics.rx_bytes as f64)
+            .field("tx_bytes", metrics.tx_bytes as f64)
+            .field("rx_packets", metrics.rx_packets as f64)
+            .field("tx_packets", metrics.tx_packets as f64)
+            .field("packet_loss", metrics.packet_loss)
+    }
+    
+    pub fn storage(metrics: &StorageMetrics) -> Self {
+        Self::new("openstack_volume", metrics.timestamp)
+            .tag("volume_id", Some(&metrics.volume_id))
+            .tag("server_id", Some(&metrics.server_id))
+            .tag("status", Some(&metrics.status))
+            .tag("volume_type", metrics.volume_type.as_deref())
+            .tag("backend", metrics.backend.as_deref())
+            .field("size_gb", metrics.size_gb as f64)
+    }
+    
+    pub fn object_storage(metrics: &ObjectStorageMetrics) -> Self {
+        let mut point = Self::new("openstack_object_storage", metrics.timestamp)
+            .tag("account", Some(&metrics.account))
+            .field("bytes_used", metrics.bytes_used as f64)
+            .field("object_count", metrics.object_count as f64)
+            .field("container_count", metrics.container_count as f64);
+        if let Some(quota) = metrics.quota_bytes {
+            point = point.field("quota_bytes", quota as f64);
+        }
+        point
+    }
+    
+    pub fn load_balancer(metrics: &LoadBalancerMetrics) -> Self {
+        let mut point = Self::new("openstack_load_balancer", metrics.timestamp)
+            .tag("loadbalancer_id", Some(&metrics.loadbalancer_id))
+            .tag("project_id", metrics.project_id.as_deref())
+            .tag("operating_status", Some(&metrics.operating_status))
+            .field("active_connections", metrics.stats.active_connections as f64)
+            .field("total_connections", metrics.stats.total_connections as f64)
+            .field("bytes_in", metrics.stats.bytes_in as f64)
+            .field("bytes_out", metrics.stats.bytes_out as f64)
+            .field("request_errors", metrics.stats.request_errors as f64);
+        if let Some(availability) = metrics.availability_percent {
+            point = point.field("availability_percent", availability);
+        }
+        point
+    }
+}
+
+// Where a PointSink's batches go
+#[async_trait]
+pub trait PointWriter: Send + Sync + 'static {
+    fn name(&self) -> &str;
+    
+    async fn write(&self, points: Vec<Point>) -> Result<()>;
+    
+    async fn check(&self) -> Result<()>;
+}
+
+// A metric sink for databases that take points in batches. Samples are
+// buffered until batch_size of them have come in or flush_interval has
+// passed, then written in the background; a batch that fails to write is
+// dropped rather than retried, so a database that is down or slow doesn't
+// hold up collection
+pub struct PointSink<W> {
+    writer: W,
+    batch_size: usize,
+    pending: Mutex<Vec<Point>>,
+    // Wakes the flusher as soon as a batch is full
+    batch_full: Arc<Notify>,
+}
+
+impl<W: PointWriter> PointSink<W> {
+    pub fn start(writer: W, batch_size: usize, flush_interval: Duration) -> Arc<Self> {
+        let batch_full = Arc::new(Notify::new());
+        let sink = Arc::new(Self {
+            writer,
+            batch_size: batch_size.max(1),
+            pending: Mutex::new(Vec::new()),
+            batch_full: batch_full.clone(),
+        });
+        
+        // Stops with the sink
+        let weak: Weak<Self> = Arc::downgrade(&sink);
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(flush_interval);
+            interval.tick().await;
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => {}
+                    _ = batch_full.notified() => {}
+                }
+                let Some(sink) = weak.upgrade() else {
+                    return;
+                };
+                if let Err(e) = sink.write_pending().await {
+                    warn!("Failed to write to metric sink {}: {}", sink.writer.name(), e);
+                }
+            }
+        });
+        sink
+    }
+    
+    fn push(&self, point: Point) -> Result<()> {
+        let mut pending = self.pending.lock().unwrap();
+        pending.push(point);
+        if pending.len() >= self.batch_size {
+            self.batch_full.notify_one();
+        }
+        let overflow = pending.len().saturating_sub(self.batch_size * MAX_PENDING_BATCHES);
+        if overflow > 0 {
+            pending.drain(..overflow);
+            ::metrics::counter!("metric_sink_points_total", "sink" => self.writer.name().to_string(), "outcome" => "dropped").increment(overflow as u64);
+        }
+        Ok(())
+    }
+    
+    async fn write_pending(&self) -> Result<()> {
+        let batch = std::mem::take(&mut *self.pending.lock().unwrap());
+        if batch.is_empty() {
+            return Ok(());
+        }
+        self.write(batch).await
+    }
+    
+    async fn write(&self, batch: Vec<Point>) -> Result<()> {
+        let count = batch.len() as u64;
+        let result = self.writer.write(batch).await;
+        let outcome = if result.is_ok() { "written" } else { "dropped" };
+        ::metrics::counter!("metric_sink_points_total", "sink" => self.writer.name().to_string(), "outcome" => outcome).increment(count);
+        result
+    }
+}
+
+#[async_trait]
+impl<W: PointWriter> MetricSink for PointSink<W> {
+    fn name(&self) -> &str {
+        self.writer.name()
+    }
+    
+    async fn publish_server(&self, metrics: &ServerMetrics) -> Result<()> {
+        self.push(Point::server(metrics))
+    }
+    
+    async fn publish_network(&self, metrics: &NetworkMetrics) -> Result<()> {
+        self.push(Point::network(metrics))
+    }
+    
+    async fn publish_storage(&self, metrics: &StorageMetrics) -> Result<()> {
+        self.push(Point::storage(metrics))
+    }
+    
+    async fn publish_object_storage(&self, metrics: &ObjectStorageMetrics) -> Result<()> {
+        self.push(Point::object_storage(metrics))
+    }
+    
+    async fn publish_load_balancer(&self, metrics: &LoadBalancerMetrics) -> Result<()> {
+        self.push(Point::load_balancer(metrics))
+    }
+    
+    async fn check(&self) -> Result<()> {
+        self.writer.check().await
+    }
+    
+    async fn flush(&self, timeout: Duration) -> Result<()> {
+        tokio::time::timeout(timeout, self.write_pending()).await
+            .map_err(|_| anyhow::anyhow!("timed out after {:?}", timeout))?
+    }
+}
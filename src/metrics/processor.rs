@@ -0,0 +1,253 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use crate::config::PluginConfig;
+use crate::openstack::services::ServerMetrics;
+use crate::plugins::{self, MetricTransform, Registry};
+
+// The configured transforms, run over every server sample in order. One
+// with sources set only sees the samples of those: "nova", "kafka",
+// "agent" or an extra source by name
+pub struct MetricsProcessor {
+    transforms: Vec<(Option<Vec<String>>, Box<dyn MetricTransform>)>,
+}
+
+impl MetricsProcessor {
+    pub fn new(transforms: &[PluginConfig]) -> Result<Self> {
+        let registry = plugins::registry();
+        let transforms = transforms.iter()
+            .map(|transform| {
+                let mut transform = transform.clone();
+                let sources = transform.options.remove("sources")
+                    .map(|sources| sources.try_into::<Vec<String>>())
+                    .transpose()
+                    .map_err(|_| anyhow::anyhow!("Invalid settings for {}: sources must be a list of source names", transform.name))?;
+                // Every source reports in units of its own, Nova's being
+                // the ones the rest expects
+                if transform.name == "unit_conversion" && sources.is_none() {
+                    anyhow::bail!("Invalid settings for unit_conversion: sources is required");
+                }
+                Ok((sources, registry.transform(&transform)?))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { transforms })
+    }
+    
+    pub fn process(&self, metrics: &mut ServerMetrics, source: &str) {
+        for (sources, transform) in &self.transforms {
+            if sources.as_ref().is_none_or(|sources| sources.iter().any(|name| name == source)) {
+                transform.apply(metrics);
+            }
+        }
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry
+        .add_transform("normalize", |_| Ok(Box::new(Normalize)))
+        .add_transform("unit_conversion", |plugin| Ok(Box::new(UnitConversion::new(plugin.options()?)?)))
+        .add_transform("smoothing", |plugin| Ok(Box::new(Smoothing::new(plugin.options()?)?)))
+        .add_transform("derived", |plugin| Ok(Box::new(Derived::new(plugin.options()?))));
+}
+
+// Keeps readings in range: CPU within 0-100%, memory used within the total,
+// and no NaN or infinite values
+struct Normalize;
+
+impl MetricTransform for Normalize {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+    
+    fn apply(&self, metrics: &mut ServerMetrics) {
+        metrics.cpu_utilization = if metrics.cpu_utilization.is_finite() {
+            metrics.cpu_utilization.clamp(0.0, 100.0)
+        } else {
+            0.0
+        };
+        if metrics.memory_total > 0 {
+            metrics.memory_usage = metrics.memory_usage.min(metrics.memory_total);
+        }
+        metrics.extra.retain(|_, value| value.is_finite());
+    }
+}
+
+// For sources that report in other units than Nova: memory in MiB and CPU
+// in percent
+struct UnitConversion {
+    // Bytes in the source's memory unit, over bytes in a MiB
+    memory_factor: f64,
+    cpu_fraction: bool,
+}
+
+#[derive(Deserialize)]
+struct UnitConversionOptions {
+    // B, KiB, MiB or GiB
+    #[serde(default = "default_memory_unit")]
+    memory_unit: String,
+    // CPU reported as 0-1 rather than 0-100
+    #[serde(default)]
+    cpu_fraction: bool,
+}
+
+fn default_memory_unit() -> String {
+    "MiB".to_string()
+}
+
+impl UnitConversion {
+    fn new(options: UnitConversionOptions) -> Result<Self> {
+        let memory_factor = match options.memory_unit.as_str() {
+            "B" => 1.0 / (1024.0 * 1024.0),
+            "KiB" => 1.0 / 1024.0,
+            "MiB" => 1.0,
+            "GiB" => 1024.0,
+            other => anyhow::bail!("Invalid settings for unit_conversion: unknown memory unit {}", other),
+        };
+        Ok(Self {
+            memory_factor,
+            cpu_fraction: options.cpu_fraction,
+        })
+    }
+}
+
+impl MetricTransform for UnitConversion {
+    fn name(&self) -> &str {
+        "unit_conversion"
+    }
+    
+    fn apply(&self, metrics: &mut ServerMetrics) {
+        if self.memory_factor != 1.0 {
+            metrics.memory_usage = (metrics.memory_usage as f64 * self.memory_factor).round() as u64;
+            metrics.memory_total = (metrics.memory_total as f64 * self.memory_factor).round() as u64;
+        }
+        if self.cpu_fraction {
+            metrics.cpu_utilization *= 100.0;
+        }
+    }
+}
+
+// Exponentially weighted moving average of each server's CPU, so a single
+// spiky reading doesn't trigger a migration. A server not seen for
+// max_gap_seconds starts over from its next reading
+struct Smoothing {
+    alpha: f64,
+    max_gap_seconds: i64,
+    // Smoothed CPU and when it was last updated, by server
+    state: Mutex<HashMap<String, (f64, DateTime<Utc>)>>,
+}
+
+#[derive(Deserialize)]
+struct SmoothingOptions {
+    // Weight of the newest reading, in (0, 1]
+    #[serde(default = "default_alpha")]
+    alpha: f64,
+    #[serde(default = "default_max_gap_seconds")]
+    max_gap_seconds: i64,
+}
+
+fn default_alpha() -> f64 {
+    0.3
+}
+
+fn default_max_gap_seconds() -> i64 {
+    300
+}
+
+impl Smoothing {
+    fn new(options: SmoothingOptions) -> Result<Self> {
+        if !(options.alpha > 0.0 && options.alpha <= 1.0) {
+            anyhow::bail!("Invalid settings for smoothing: alpha must be in (0, 1], not {}", options.alpha);
+        }
+        Ok(Self {
+            alpha: options.alpha,
+            max_gap_seconds: options.max_gap_seconds,
+            state: Mutex::new(HashMap::new()),
+        })
+    }
+}
+
+impl MetricTransform for Smoothing {
+    fn name(&self) -> &str {
+        "smoothing"
+    }
+    
+    fn apply(&self, metrics: &mut ServerMetrics) {
+        let mut state = self.state.lock().unwrap();
+        let fresh = |at: &DateTime<Utc>| (metrics.timestamp - *at).num_seconds() <= self.max_gap_seconds;
+        let smoothed = match state.get(&metrics.server_id) {
+            Some((previous, at)) if fresh(at) => self.alpha * metrics.cpu_utilization + (1.0 - self.alpha) * previous,
+            Some(_) => metrics.cpu_utilization,
+            None => {
+                // Servers that are gone only linger until the next new one
+                state.retain(|_, (_, at)| fresh(at));
+                metrics.cpu_utilization
+            }
+        };
+        state.insert(metrics.server_id.clone(), (smoothed, metrics.timestamp));
+        metrics.cpu_utilization = smoothed;
+    }
+}
+
+// Adds metrics computed from the others to the sample's extras:
+// memory_pressure, how far memory use is past the threshold towards full
+// in percent, and cpu_steal, the share of the server's CPU time the
+// hypervisor gave to others, from a cpu_steal_ns counter (with num_cpus)
+// that a source reports
+struct Derived {
+    memory_pressure_threshold: f64,
+    // Last steal counter reading and when, by server
+    steal: Mutex<HashMap<String, (f64, DateTime<Utc>)>>,
+}
+
+#[derive(Deserialize)]
+struct DerivedOptions {
+    #[serde(default = "default_memory_pressure_threshold")]
+    memory_pressure_threshold: f64,
+}
+
+fn default_memory_pressure_threshold() -> f64 {
+    80.0
+}
+
+impl Derived {
+    fn new(options: DerivedOptions) -> Self {
+        Self {
+            memory_pressure_threshold: options.memory_pressure_threshold.clamp(0.0, 99.0),
+            steal: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    fn cpu_steal(&self, metrics: &ServerMetrics) -> Option<f64> {
+        let counter = *metrics.extra.get("cpu_steal_ns")?;
+        let cpus = metrics.extra.get("num_cpus").copied().unwrap_or(1.0).max(1.0);
+        let mut steal = self.steal.lock().unwrap();
+        let previous = steal.insert(metrics.server_id.clone(), (counter, metrics.timestamp));
+        let (previous_counter, at) = previous?;
+        let elapsed_ns = (metrics.timestamp - at).num_nanoseconds()? as f64;
+        // A lower reading means the server rebooted
+        if elapsed_ns <= 0.0 || counter < previous_counter {
+            return None;
+        }
+        Some(((counter - previous_counter) / elapsed_ns / cpus * 100.0).clamp(0.0, 100.0))
+    }
+}
+
+impl MetricTransform for Derived {
+    fn name(&self) -> &str {
+        "derived"
+    }
+    
+    fn apply(&self, metrics: &mut ServerMetrics) {
+        if metrics.memory_total > 0 {
+            let used = metrics.memory_usage as f64 / metrics.memory_total as f64 * 100.0;
+            let pressure = (used - self.memory_pressure_threshold) / (100.0 - self.memory_pressure_threshold) * 100.0;
+            metrics.extra.insert("memory_pressure".to_string(), pressure.clamp(0.0, 100.0));
+        }
+        if let Some(steal) = self.cpu_steal(metrics) {
+            metrics.extra.insert("cpu_steal".to_string(), steal);
+        }
+    }
+}
@@ -0,0 +1,121 @@
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::interval;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::ShardingConfig;
+use crate::coordination::Coordinator;
+
+const MEMBER_GROUP: &str = "shards";
+
+// Decides which resources this instance collects. Members join a group in
+// the coordination backend and every member builds the same consistent-hash
+// ring from the live ones, so a member joining or leaving only moves its share
+pub struct ShardCoordinator {
+    config: ShardingConfig,
+    coordinator: Coordinator,
+    ring: ArcSwap<HashRing>,
+}
+
+#[derive(Debug, Default)]
+struct HashRing {
+    members: Vec<String>,
+    points: BTreeMap<u64, String>,
+}
+
+impl ShardCoordinator {
+    pub fn new(config: ShardingConfig, coordinator: Coordinator) -> Self {
+        Self {
+            config,
+            coordinator,
+            ring: ArcSwap::from_pointee(HashRing::default()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    pub fn instance_id(&self) -> &str {
+        self.coordinator.instance_id()
+    }
+    
+    // Everything is ours without sharding or before the first heartbeat
+    pub fn owns(&self, resource_id: &str) -> bool {
+        if !self.config.enabled {
+            return true;
+        }
+        self.ring.load().owner(resource_id).is_none_or(|owner| owner == self.coordinator.instance_id())
+    }
+    
+    pub async fn run(&self, shutdown: CancellationToken) {
+        if !self.config.enabled {
+            return;
+        }
+        
+        let mut ticker = interval(Duration::from_secs(self.config.heartbeat_interval_seconds));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => break,
+            }
+            if let Err(e) = self.heartbeat().await {
+                warn!("Shard heartbeat failed: {}", e);
+            }
+        }
+        
+        // Leave right away rather than holding resources until the TTL runs out
+        if let Err(e) = self.coordinator.leave(MEMBER_GROUP).await {
+            warn!("Failed to leave the shard ring: {}", e);
+        }
+    }
+    
+    // Renews this member and rebuilds the ring from the live ones
+    pub async fn heartbeat(&self) -> Result<()> {
+        let ttl = Duration::from_secs(self.config.member_ttl_seconds);
+        self.coordinator.join(MEMBER_GROUP, ttl).await?;
+        
+        let mut members = self.coordinator.members(MEMBER_GROUP).await?;
+        members.sort();
+        
+        if members != self.ring.load().members {
+            info!("Shard ring now has {} members: {}", members.len(), members.join(", "));
+            ::metrics::gauge!("shard_members").set(members.len() as f64);
+            self.ring.store(Arc::new(HashRing::new(members, self.config.virtual_nodes)));
+        }
+        Ok(())
+    }
+}
+
+impl HashRing {
+    fn new(members: Vec<String>, virtual_nodes: usize) -> Self {
+        let points = members.iter()
+            .flat_map(|member| {
+                (0..virtual_nodes).map(move |i| (ring_hash(&format!("{}#{}", member, i)), member.clone()))
+            })
+            .collect();
+        Self { members, points }
+    }
+    
+    // The first point clockwise from the resource's hash
+    fn owner(&self, resource_id: &str) -> Option<&str> {
+        let hash = ring_hash(resource_id);
+        self.points.range(hash..)
+            .next()
+            .or_else(|| self.points.iter().next())
+            .map(|(_, member)| member.as_str())
+    }
+}
+
+// Must agree between members, which may run different builds, so no std hasher
+fn ring_hash(value: &str) -> u64 {
+    let digest = Sha256::digest(value.as_bytes());
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    u64::from_be_bytes(bytes)
+}
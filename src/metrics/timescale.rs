@@ -0,0 +1,159 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use serde::Deserialize;
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::config::{MetricsConfig, PluginConfig};
+use crate::error::StorageError;
+use crate::plugins::{MetricSink, Registry};
+use super::point_sink::{Point, PointSink, PointWriter};
+
+// Bind parameters per row, and Postgres' limit on them per statement
+const COLUMNS: usize = 5;
+const MAX_PARAMETERS: usize = 65535;
+
+// Writes samples to a TimescaleDB hypertable, a row per field:
+// (time, measurement, field, value, tags), the tags as JSONB. The table is
+// created on start when missing
+pub struct TimescaleWriter {
+    pool: PgPool,
+    table: String,
+}
+
+#[derive(Deserialize)]
+struct TimescaleOptions {
+    url: String,
+    #[serde(default = "default_table")]
+    table: String,
+    #[serde(default = "default_max_connections")]
+    max_connections: u32,
+    #[serde(default = "default_batch_size")]
+    batch_size: usize,
+    #[serde(default = "default_flush_interval_seconds")]
+    flush_interval_seconds: u64,
+}
+
+fn default_table() -> String {
+    "openstack_metrics".to_string()
+}
+
+fn default_max_connections() -> u32 {
+    4
+}
+
+fn default_batch_size() -> usize {
+    1000
+}
+
+fn default_flush_interval_seconds() -> u64 {
+    10
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.add_sink("timescaledb", timescale_sink);
+}
+
+fn timescale_sink<'a>(plugin: &'a PluginConfig, _config: &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSink>>> {
+    Box::pin(async move {
+        let options: TimescaleOptions = plugin.options()?;
+        // Spliced into statements, so only a plain identifier will do
+        let valid_table = options.table.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+            && options.table.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+        if !valid_table {
+            anyhow::bail!("Invalid settings for timescaledb: table {:?} isn't a plain identifier", options.table);
+        }
+        
+        let pool = PgPoolOptions::new()
+            .max_connections(options.max_connections.max(1))
+            .connect(&options.url)
+            .await
+            .map_err(|e| StorageError::BackendError(format!("Cannot connect to TimescaleDB: {}", e)))?;
+        let writer = TimescaleWriter { pool, table: options.table };
+        writer.create_table().await?;
+        
+        let sink = PointSink::start(writer, options.batch_size, Duration::from_secs(options.flush_interval_seconds.max(1)));
+        Ok(sink as Arc<dyn MetricSink>)
+    })
+}
+
+impl TimescaleWriter {
+    async fn create_table(&self) -> Result<()> {
+        sqlx::query(&format!(
+            "CREATE TABLE IF NOT EXISTS {} (
+                time TIMESTAMPTZ NOT NULL,
+                measurement TEXT NOT NULL,
+                field TEXT NOT NULL,
+                value DOUBLE PRECISION NOT NULL,
+                tags JSONB NOT NULL
+            )",
+            self.table,
+        ))
+        .execute(&self.pool)
+        .await?;
+        sqlx::query(&format!(
+            "CREATE INDEX IF NOT EXISTS {table}_measurement_field_time ON {table} (measurement, field, time DESC)",
+            table = self.table,
+        ))
+        .execute(&self.pool)
+        .await?;
+        
+        // Plain Postgres still takes the rows, just without chunking
+        match sqlx::query("SELECT create_hypertable($1, 'time', if_not_exists => TRUE)")
+            .bind(&self.table)
+            .execute(&self.pool)
+            .await
+        {
+            Ok(_) => info!("Writing metrics to TimescaleDB hypertable {}", self.table),
+            Err(e) => warn!("Cannot make {} a hypertable, is the timescaledb extension installed? {}", self.table, e),
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl PointWriter for TimescaleWriter {
+    fn name(&self) -> &str {
+        "timescaledb"
+    }
+    
+    async fn write(&self, points: Vec<Point>) -> Result<()> {
+        let mut rows = Vec::new();
+        for point in &points {
+            let tags: BTreeMap<&str, &str> = point.tags.iter().map(|(key, value)| (*key, value.as_str())).collect();
+            let tags = serde_json::to_string(&tags)?;
+            for (field, value) in &point.fields {
+                rows.push((point.timestamp, point.measurement, field.as_str(), *value, tags.clone()));
+            }
+        }
+        
+        let mut tx = self.pool.begin().await?;
+        for chunk in rows.chunks(MAX_PARAMETERS / COLUMNS) {
+            let mut insert: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+                "INSERT INTO {} (time, measurement, field, value, tags) ",
+                self.table,
+            ));
+            insert.push_values(chunk, |mut row, (time, measurement, field, value, tags)| {
+                row.push_bind(*time)
+                    .push_bind(*measurement)
+                    .push_bind(*field)
+                    .push_bind(*value)
+                    .push_bind(tags.as_str())
+                    .push_unseparated("::jsonb");
+            });
+            insert.build().execute(&mut *tx).await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+    
+    async fn check(&self) -> Result<()> {
+        sqlx::query("SELECT 1").execute(&self.pool).await?;
+        Ok(())
+    }
+}
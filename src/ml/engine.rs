@@ -1,51 +1,130 @@
 use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
 use tokio::sync::RwLock;
 use tokio::time::interval;
-use tracing::{debug, error, info};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 
 use crate::config::MLConfig;
-use super::models::LSTMModel;
-use super::predictor::LoadPredictor;
+use crate::coordination::Coordinator;
+use crate::metrics::history::{HistoryMetric, MetricHistory};
+use crate::openstack::services::{ServerMetrics, TelemetryService};
+use crate::plugins;
+use super::predictor::{LoadForecast, LoadPredictor, SharedModel};
+
+const RETRAIN_LOCK: &str = "ml-retrain";
+// Renewed while training, so this only bounds how long an instance that
+// dies mid-way blocks the others
+const RETRAIN_LOCK_TTL: Duration = Duration::from_secs(30 * 60);
 
 pub struct MLEngine {
     config: MLConfig,
-    lstm_model: Arc<RwLock<LSTMModel>>,
+    model: SharedModel,
+    workload_models: HashMap<String, SharedModel>,
     load_predictor: Arc<LoadPredictor>,
+    coordinator: Coordinator,
 }
 
 impl MLEngine {
-    pub async fn new(config: &MLConfig) -> Result<Self> {
-        let lstm_model = Arc::new(RwLock::new(
-            LSTMModel::load_from_file(&config.model_path).await?
+    pub async fn new(config: &MLConfig, coordinator: Coordinator) -> Result<Self> {
+        let model = Arc::new(RwLock::new(
+            plugins::registry().model(&config.model, config).await?
         ));
+        let mut workload_models = HashMap::new();
+        for (class, plugin) in &config.workload_models {
+            let workload_model: SharedModel = Arc::new(RwLock::new(plugins::registry().model(plugin, config).await?));
+            info!("Forecasting {} workloads with their own {} model", class, plugin.name);
+            workload_models.insert(class.clone(), workload_model);
+        }
         
         let load_predictor = Arc::new(
-            LoadPredictor::new(lstm_model.clone())
+            LoadPredictor::new(model.clone(), workload_models.clone())
         );
         
-        info!("ML Engine initialized successfully");
+        info!("ML Engine initialized with the {} model", config.model.name);
         
         Ok(Self {
             config: config.clone(),
-            lstm_model,
+            model,
+            workload_models,
             load_predictor,
+            coordinator,
         })
     }
     
-    pub async fn start_inference_loop(&self) -> Result<()> {
+    // Collected samples feed the predictor's history between inference cycles
+    pub async fn start_inference_loop(
+        &self,
+        mut samples: broadcast::Receiver<ServerMetrics>,
+        shutdown: CancellationToken,
+    ) -> Result<()> {
         info!("Starting ML inference loop");
         
         let mut interval = interval(Duration::from_secs(self.config.inference_interval_seconds));
         
         loop {
-            interval.tick().await;
-            
-            if let Err(e) = self.run_inference_cycle().await {
-                error!("ML inference cycle failed: {}", e);
+            tokio::select! {
+                _ = interval.tick() => {
+                    if let Err(e) = self.run_inference_cycle().await {
+                        error!("ML inference cycle failed: {}", e);
+                    }
+                }
+                sample = samples.recv() => match sample {
+                    Ok(metrics) => self.record_sample(metrics).await,
+                    Err(RecvError::Lagged(skipped)) => warn!("ML engine skipped {} metric samples", skipped),
+                    Err(RecvError::Closed) => anyhow::bail!("metric sample stream closed"),
+                },
+                _ = shutdown.cancelled() => {
+                    info!("ML inference loop stopped");
+                    return Ok(());
+                }
+            }
+        }
+    }
+    
+    pub async fn record_sample(&self, metrics: ServerMetrics) {
+        self.load_predictor
+            .set_workload_class(&metrics.server_id, metrics.workload_class.as_deref())
+            .await;
+        self.load_predictor
+            .update_historical_data(metrics.server_id, metrics.timestamp, metrics.cpu_utilization)
+            .await;
+    }
+    
+    // Fills the predictor from stored metric history, on start and for
+    // one-shot runs that have no live sample stream; returns the number of
+    // resources seeded
+    pub async fn seed_from_history(&self, history: &MetricHistory, since: DateTime<Utc>) -> usize {
+        let now = Utc::now();
+        let mut seeded = 0;
+        for resource_id in history.resource_ids().await {
+            let points = history.points(&resource_id, HistoryMetric::CpuUtilization, since, now).await;
+            if points.is_empty() {
+                continue;
+            }
+            for point in points {
+                self.load_predictor.update_historical_data(resource_id.clone(), point.timestamp, point.avg).await;
+            }
+            seeded += 1;
+        }
+        seeded
+    }
+    
+    // Fills the predictor from Gnocchi's CPU history, before any live
+    // samples arrive; returns the number of resources seeded
+    pub async fn seed_from_telemetry(&self, telemetry: &TelemetryService, since: DateTime<Utc>) -> Result<usize> {
+        let histories = telemetry.cpu_history(since).await?;
+        for (resource_id, points) in &histories {
+            for (timestamp, value) in points {
+                self.load_predictor.update_historical_data(resource_id.clone(), *timestamp, *value).await;
             }
         }
+        info!("Seeded load history for {} resources from Gnocchi", histories.len());
+        Ok(histories.len())
     }
     
     async fn run_inference_cycle(&self) -> Result<()> {
@@ -72,20 +151,61 @@ impl MLEngine {
         false
     }
     
-    async fn retrain_model(&self) -> Result<()> {
+    // Only one instance retrains at a time; false when another one already is
+    pub async fn retrain_model(&self) -> Result<bool> {
+        let Some(lock) = self.coordinator.try_lock(RETRAIN_LOCK, RETRAIN_LOCK_TTL).await? else {
+            info!("Another instance is retraining the model, skipping");
+            return Ok(false);
+        };
         info!("Retraining ML model");
         
-        // Hot-swap model without downtime
-        let new_model = LSTMModel::retrain(&self.config.model_path).await?;
+        let training = async {
+            let models = std::iter::once(&self.model).chain(self.workload_models.values());
+            for model in models {
+                let retrained = model.read().await.retrain(&self.config).await?;
+                // Hot-swap model without downtime
+                *model.write().await = retrained;
+            }
+            Ok(())
+        };
+        tokio::pin!(training);
         
-        let mut model_lock = self.lstm_model.write().await;
-        *model_lock = new_model;
+        // Training stops once another instance could have taken the lock
+        let mut renewal = interval(RETRAIN_LOCK_TTL / 3);
+        renewal.tick().await;
+        let result: Result<()> = loop {
+            tokio::select! {
+                result = &mut training => break result,
+                _ = renewal.tick() => match lock.renew(RETRAIN_LOCK_TTL).await {
+                    Ok(true) => {}
+                    Ok(false) => break Err(anyhow::anyhow!("Lost the retrain lock, abandoning the retrain")),
+                    Err(e) => warn!("Failed to renew the retrain lock: {}", e),
+                },
+            }
+        };
+        lock.release().await;
+        result?;
         
         info!("Model retrained and swapped successfully");
-        Ok(())
+        Ok(true)
     }
     
     pub async fn get_resource_prediction(&self, resource_id: &str) -> Result<f64> {
         self.load_predictor.predict_resource_load(resource_id).await
     }
+    
+    pub async fn get_prediction_confidence(&self, resource_id: &str) -> Option<f64> {
+        self.load_predictor.prediction_confidence(resource_id).await
+    }
+    
+    pub async fn model_version(&self) -> String {
+        self.model.read().await.version().to_string()
+    }
+    
+    // None until enough history has been collected for the resource
+    pub async fn get_resource_forecast(&self, resource_id: &str) -> Result<Option<LoadForecast>> {
+        self.load_predictor
+            .forecast_resource_load(resource_id, self.config.forecast_step_minutes)
+            .await
+    }
 }
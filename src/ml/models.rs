@@ -1,9 +1,14 @@
 use anyhow::Result;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
 use nalgebra::{DMatrix, DVector};
 use serde::{Deserialize, Serialize};
 use statrs::statistics::Statistics;
 use tracing::{debug, info};
 
+use crate::config::{MLConfig, PluginConfig};
+use crate::plugins::{ForecastModel, Registry};
+
 #[derive(Debug, Clone)]
 pub struct LSTMModel {
     pub model_version: String,
@@ -169,6 +174,31 @@ impl LSTMModel {
     }
 }
 
+#[async_trait]
+impl ForecastModel for LSTMModel {
+    fn version(&self) -> &str {
+        &self.model_version
+    }
+    
+    fn predict(&self, input: &TimeSeriesData) -> Result<Vec<f64>> {
+        LSTMModel::predict(self, input)
+    }
+    
+    async fn retrain(&self, config: &MLConfig) -> Result<Box<dyn ForecastModel>> {
+        Ok(Box::new(LSTMModel::retrain(&config.model_path).await?))
+    }
+}
+
+pub fn register(registry: &mut Registry) {
+    registry.add_model("lstm", lstm_model);
+}
+
+fn lstm_model<'a>(_plugin: &'a PluginConfig, config: &'a MLConfig) -> BoxFuture<'a, Result<Box<dyn ForecastModel>>> {
+    Box::pin(async move {
+        Ok(Box::new(LSTMModel::load_from_file(&config.model_path).await?) as Box<dyn ForecastModel>)
+    })
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct TimeSeriesData {
     pub timestamps: Vec<chrono::DateTime<chrono::Utc>>,
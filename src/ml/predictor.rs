@@ -1,16 +1,32 @@
 use anyhow::Result;
+use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::RwLock;
 use tracing::debug;
 
-use super::models::{LSTMModel, TimeSeriesData};
+use crate::plugins::ForecastModel;
+use super::models::TimeSeriesData;
+
+// Shared with the engine, which swaps it on retraining
+pub type SharedModel = Arc<RwLock<Box<dyn ForecastModel>>>;
 
 pub struct LoadPredictor {
-    lstm_model: Arc<RwLock<LSTMModel>>,
+    model: SharedModel,
+    // By workload class; resources of other classes use model
+    workload_models: HashMap<String, SharedModel>,
+    workload_classes: RwLock<HashMap<String, String>>,
     historical_data: Arc<RwLock<HashMap<String, TimeSeriesData>>>,
 }
 
+// Multi-step forecast; values[i] is the load expected (i + 1) steps from now
+#[derive(Debug, Clone, Serialize)]
+pub struct LoadForecast {
+    pub values: Vec<f64>,
+    pub step_minutes: u32,
+    pub confidence: f64,
+}
+
 #[derive(Debug, Clone)]
 pub struct LoadPrediction {
     pub resource_id: String,
@@ -21,13 +37,38 @@ pub struct LoadPrediction {
 }
 
 impl LoadPredictor {
-    pub fn new(lstm_model: Arc<RwLock<LSTMModel>>) -> Self {
+    pub fn new(model: SharedModel, workload_models: HashMap<String, SharedModel>) -> Self {
         Self {
-            lstm_model,
+            model,
+            workload_models,
+            workload_classes: RwLock::new(HashMap::new()),
             historical_data: Arc::new(RwLock::new(HashMap::new())),
         }
     }
     
+    // The model forecasting the resource, by its workload class
+    async fn model_for(&self, resource_id: &str) -> &SharedModel {
+        self.workload_classes.read().await.get(resource_id)
+            .and_then(|class| self.workload_models.get(class))
+            .unwrap_or(&self.model)
+    }
+    
+    pub async fn set_workload_class(&self, resource_id: &str, workload_class: Option<&str>) {
+        if self.workload_models.is_empty() {
+            return;
+        }
+        let mut workload_classes = self.workload_classes.write().await;
+        match workload_class {
+            Some(class) if workload_classes.get(resource_id).map(String::as_str) != Some(class) => {
+                workload_classes.insert(resource_id.to_string(), class.to_string());
+            }
+            Some(_) => {}
+            None => {
+                workload_classes.remove(resource_id);
+            }
+        }
+    }
+    
     pub async fn predict_load_next_hour(&self) -> Result<Vec<LoadPrediction>> {
         debug!("Predicting load for next hour");
         
@@ -36,9 +77,9 @@ impl LoadPredictor {
         
         for (resource_id, time_series) in historical_data.iter() {
             if let Some(recent_data) = time_series.get_recent_window(24) {
-                let model = self.lstm_model.read().await;
+                let model = self.model_for(resource_id).await.read().await;
                 
-                // Create input data for LSTM
+                // Create input data for the model
                 let input_data = TimeSeriesData {
                     timestamps: vec![chrono::Utc::now()], // Simplified
                     values: recent_data.clone(),
@@ -69,7 +110,7 @@ impl LoadPredictor {
         
         if let Some(time_series) = historical_data.get(resource_id) {
             if let Some(recent_data) = time_series.get_recent_window(24) {
-                let model = self.lstm_model.read().await;
+                let model = self.model_for(resource_id).await.read().await;
                 
                 let input_data = TimeSeriesData {
                     timestamps: vec![chrono::Utc::now()],
@@ -86,14 +127,47 @@ impl LoadPredictor {
         Ok(0.0) // Default prediction if no data available
     }
     
-    pub async fn update_historical_data(&self, resource_id: String, value: f64) {
+    pub async fn forecast_resource_load(&self, resource_id: &str, step_minutes: u32) -> Result<Option<LoadForecast>> {
+        let historical_data = self.historical_data.read().await;
+        
+        let recent_data = match historical_data.get(resource_id).and_then(|ts| ts.get_recent_window(24)) {
+            Some(recent_data) => recent_data,
+            None => return Ok(None),
+        };
+        
+        let input_data = TimeSeriesData {
+            timestamps: vec![chrono::Utc::now()],
+            values: recent_data.clone(),
+            resource_id: resource_id.to_string(),
+            metric_type: "cpu_utilization".to_string(),
+        };
+        let values = self.model_for(resource_id).await.read().await.predict(&input_data)?;
+        
+        Ok(Some(LoadForecast {
+            values,
+            step_minutes,
+            confidence: self.calculate_confidence(&recent_data),
+        }))
+    }
+    
+    // Confidence the next prediction for the resource would carry; None
+    // without enough history
+    pub async fn prediction_confidence(&self, resource_id: &str) -> Option<f64> {
+        let historical_data = self.historical_data.read().await;
+        historical_data.get(resource_id)
+            .and_then(|ts| ts.get_recent_window(24))
+            .map(|recent_data| self.calculate_confidence(&recent_data))
+    }
+    
+    // Points are expected oldest first
+    pub async fn update_historical_data(&self, resource_id: String, timestamp: chrono::DateTime<chrono::Utc>, value: f64) {
         let mut historical_data = self.historical_data.write().await;
         
         let time_series = historical_data
             .entry(resource_id.clone())
             .or_insert_with(|| TimeSeriesData::new(resource_id, "cpu_utilization".to_string()));
         
-        time_series.add_point(chrono::Utc::now(), value);
+        time_series.add_point(timestamp, value);
     }
     
     fn calculate_confidence(&self, recent_data: &[f64]) -> f64 {
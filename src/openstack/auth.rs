@@ -1,11 +1,15 @@
 use anyhow::Result;
+use arc_swap::ArcSwapOption;
 use chrono::{DateTime, Utc, Duration};
 use reqwest::Client as HttpClient;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use std::sync::Arc;
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
 
-use crate::config::OpenStackConfig;
-use crate::error::OpenStackError;
+use crate::config::{AuthType, OpenStackConfig};
+use crate::error::{is_retryable_status, OpenStackError};
 
 #[derive(Debug, Clone)]
 pub struct AuthToken {
@@ -13,8 +17,34 @@ pub struct AuthToken {
     pub expires_at: DateTime<Utc>,
     pub project_id: String,
     pub user_id: String,
+    pub catalog: Vec<CatalogService>,
 }
 
+// Service catalog entry from the token, listing where each API lives
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogService {
+    #[serde(rename = "type")]
+    pub service_type: String,
+    #[serde(default)]
+    pub endpoints: Vec<CatalogEndpoint>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CatalogEndpoint {
+    pub interface: String,
+    #[serde(default)]
+    pub region_id: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    pub url: String,
+}
+
+// The renewal task replaces a token this long before it expires, ahead of
+// callers refreshing it themselves
+const RENEW_BEFORE_MINUTES: i64 = 10;
+// Wait after a failed renewal before trying again
+const RENEW_RETRY_SECONDS: u64 = 30;
+
 impl AuthToken {
     pub fn is_expired(&self) -> bool {
         Utc::now() + Duration::minutes(5) > self.expires_at
@@ -29,13 +59,21 @@ struct AuthRequest {
 #[derive(Serialize)]
 struct AuthPayload {
     identity: Identity,
-    scope: Scope,
+    // Left out for application credentials, which carry their own
+    #[serde(skip_serializing_if = "Option::is_none")]
+    scope: Option<Scope>,
 }
 
+// Exactly one method, with its matching credentials
 #[derive(Serialize)]
 struct Identity {
     methods: Vec<String>,
-    password: PasswordAuth,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    password: Option<PasswordAuth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    application_credential: Option<ApplicationCredentialAuth>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token: Option<TokenAuth>,
 }
 
 #[derive(Serialize)]
@@ -50,6 +88,29 @@ struct UserAuth {
     password: String,
 }
 
+// By id alone, or by name together with the owning user
+#[derive(Serialize)]
+struct ApplicationCredentialAuth {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    secret: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    user: Option<UserRef>,
+}
+
+#[derive(Serialize)]
+struct UserRef {
+    name: String,
+    domain: Domain,
+}
+
+#[derive(Serialize)]
+struct TokenAuth {
+    id: String,
+}
+
 #[derive(Serialize)]
 struct Domain {
     name: String,
@@ -76,6 +137,8 @@ struct TokenInfo {
     expires_at: String,
     project: ProjectInfo,
     user: UserInfo,
+    #[serde(default)]
+    catalog: Vec<CatalogService>,
 }
 
 #[derive(Deserialize)]
@@ -88,18 +151,23 @@ struct UserInfo {
     id: String,
 }
 
+// Holds the current token. Callers get a valid one without locking each
+// other out; when it has expired the first caller refreshes it and the rest
+// wait for that refresh rather than each asking Keystone
 pub struct AuthManager {
     config: OpenStackConfig,
     http_client: HttpClient,
-    current_token: Option<AuthToken>,
+    current_token: ArcSwapOption<AuthToken>,
+    refreshing: Mutex<()>,
 }
 
 impl AuthManager {
     pub async fn new(config: OpenStackConfig, http_client: HttpClient) -> Result<Self> {
-        let mut manager = Self {
+        let manager = Self {
             config,
             http_client,
-            current_token: None,
+            current_token: ArcSwapOption::empty(),
+            refreshing: Mutex::new(()),
         };
         
         // Get initial token
@@ -108,57 +176,122 @@ impl AuthManager {
         Ok(manager)
     }
     
-    pub async fn get_token(&self) -> Result<&AuthToken> {
-        if let Some(ref token) = self.current_token {
-            if !token.is_expired() {
-                return Ok(token);
-            }
+    pub async fn get_token(&self) -> Result<Arc<AuthToken>> {
+        if let Some(token) = self.valid_token() {
+            return Ok(token);
         }
         
-        // Token is expired or doesn't exist, need to refresh
-        // In a real implementation, this would need proper synchronization
-        Err(OpenStackError::AuthError("Token expired, refresh needed".to_string()).into())
+        let _refreshing = self.refreshing.lock().await;
+        // Someone else may have refreshed while this waited
+        if let Some(token) = self.valid_token() {
+            return Ok(token);
+        }
+        self.request_token().await
+    }
+    
+    // Drops token after an API rejected it, e.g. once revoked, so the next
+    // get_token authenticates again. A newer token is left alone
+    pub fn invalidate(&self, token: &str) {
+        let current = self.current_token.load();
+        if current.as_ref().is_some_and(|current| current.token == token) {
+            debug!("Discarding the rejected authentication token");
+            self.current_token.compare_and_swap(&*current, None);
+        }
     }
     
-    pub async fn refresh_token(&mut self) -> Result<()> {
-        debug!("Refreshing OpenStack authentication token");
+    // Replaces the token shortly before it expires, so requests rarely wait
+    // on Keystone. Failures are retried here; callers still refresh on demand
+    pub async fn run_renewal(&self, shutdown: CancellationToken) {
+        loop {
+            // Halfway through a short-lived token, rather than right away
+            let renew_in = self.current_token.load().as_ref()
+                .map(|token| {
+                    let remaining = token.expires_at - Utc::now();
+                    (remaining - Duration::minutes(RENEW_BEFORE_MINUTES)).max(remaining / 2)
+                })
+                .and_then(|delay| delay.to_std().ok())
+                .unwrap_or_default();
+            tokio::select! {
+                _ = tokio::time::sleep(renew_in) => {}
+                _ = shutdown.cancelled() => return,
+            }
+            
+            match self.refresh_token().await {
+                Ok(token) => info!("Renewed the OpenStack token, now valid until {}", token.expires_at),
+                Err(e) => {
+                    warn!("Failed to renew the OpenStack token: {}", e);
+                    tokio::select! {
+                        _ = tokio::time::sleep(std::time::Duration::from_secs(RENEW_RETRY_SECONDS)) => {}
+                        _ = shutdown.cancelled() => return,
+                    }
+                }
+            }
+        }
+    }
+    
+    fn valid_token(&self) -> Option<Arc<AuthToken>> {
+        self.current_token.load_full().filter(|token| !token.is_expired())
+    }
+    
+    // Base URL of service_type's API in the region and the configured
+    // interface, without a trailing slash
+    pub fn endpoint(&self, service_type: &str, region: &str) -> Result<String> {
+        let token = self.current_token.load_full()
+            .ok_or_else(|| OpenStackError::AuthError("Not authenticated".to_string()))?;
+        let interface = self.config.interface.as_str();
         
-        let auth_request = AuthRequest {
-            auth: AuthPayload {
-                identity: Identity {
-                    methods: vec!["password".to_string()],
-                    password: PasswordAuth {
-                        user: UserAuth {
-                            name: self.config.username.clone(),
-                            domain: Domain {
-                                name: self.config.user_domain.clone(),
-                            },
-                            password: self.config.password.clone(),
-                        },
-                    },
-                },
-                scope: Scope {
-                    project: Project {
-                        name: self.config.project_name.clone(),
-                        domain: Domain {
-                            name: self.config.project_domain.clone(),
-                        },
-                    },
-                },
-            },
-        };
+        token.catalog.iter()
+            .filter(|service| service.service_type == service_type)
+            .flat_map(|service| &service.endpoints)
+            .find(|endpoint| {
+                endpoint.interface == interface
+                    && (endpoint.region_id.as_deref() == Some(region) || endpoint.region.as_deref() == Some(region))
+            })
+            .map(|endpoint| endpoint.url.trim_end_matches('/').to_string())
+            .ok_or_else(|| {
+                OpenStackError::ServiceUnavailable(format!(
+                    "No {} {} endpoint in region {} in the service catalog",
+                    interface, service_type, region
+                )).into()
+            })
+    }
+    
+    // Asks Keystone for a new token whether or not the current one is valid
+    pub async fn refresh_token(&self) -> Result<Arc<AuthToken>> {
+        let _refreshing = self.refreshing.lock().await;
+        self.request_token().await
+    }
+    
+    // Only called with refreshing held
+    async fn request_token(&self) -> Result<Arc<AuthToken>> {
+        debug!("Refreshing OpenStack authentication token with {:?} auth", self.config.auth_type);
         
-        let response = self.http_client
-            .post(&format!("{}/v3/auth/tokens", self.config.auth_url))
-            .json(&auth_request)
-            .send()
-            .await?;
+        let auth_request = self.auth_request();
         
-        if !response.status().is_success() {
-            return Err(OpenStackError::AuthError(
-                format!("Authentication failed: {}", response.status())
-            ).into());
-        }
+        // Asking for a token again is harmless, so this retries like a read
+        let url = format!("{}/v3/auth/tokens", self.config.auth_url);
+        let response = self.config.retry.policy().run("Keystone authentication", || async {
+            let response = self.http_client
+                .post(&url)
+                .json(&auth_request)
+                .send()
+                .await?;
+            
+            let status = response.status();
+            if status.is_success() {
+                return Ok(response);
+            }
+            if is_retryable_status(status.as_u16()) {
+                return Err(OpenStackError::from_response(response).await.into());
+            }
+            let message = match self.config.auth_type {
+                AuthType::Token => format!("{}; the configured token may have expired", status),
+                _ => status.to_string(),
+            };
+            Err(anyhow::Error::from(OpenStackError::AuthError(
+                format!("Authentication failed: {}", message)
+            )))
+        }).await?;
         
         let token_header = response.headers()
             .get("X-Subject-Token")
@@ -171,14 +304,73 @@ impl AuthManager {
         let expires_at = DateTime::parse_from_rfc3339(&auth_response.token.expires_at)?
             .with_timezone(&Utc);
         
-        self.current_token = Some(AuthToken {
+        let token = Arc::new(AuthToken {
             token: token_header,
             expires_at,
             project_id: auth_response.token.project.id,
             user_id: auth_response.token.user.id,
+            catalog: auth_response.token.catalog,
         });
+        self.current_token.store(Some(token.clone()));
         
         debug!("Authentication token refreshed successfully");
-        Ok(())
+        Ok(token)
+    }
+    
+    fn auth_request(&self) -> AuthRequest {
+        let config = &self.config;
+        let user_domain = || Domain { name: config.user_domain.clone() };
+        let project_scope = || Some(Scope {
+            project: Project {
+                name: config.project_name.clone(),
+                domain: Domain { name: config.project_domain.clone() },
+            },
+        });
+        let method_only = |method: &str| Identity {
+            methods: vec![method.to_string()],
+            password: None,
+            application_credential: None,
+            token: None,
+        };
+        
+        let (identity, scope) = match config.auth_type {
+            AuthType::Password => (
+                Identity {
+                    password: Some(PasswordAuth {
+                        user: UserAuth {
+                            name: config.username.clone(),
+                            domain: user_domain(),
+                            password: config.password.clone(),
+                        },
+                    }),
+                    ..method_only("password")
+                },
+                project_scope(),
+            ),
+            AuthType::ApplicationCredential => (
+                Identity {
+                    application_credential: Some(ApplicationCredentialAuth {
+                        id: config.application_credential_id.clone(),
+                        name: config.application_credential_name.clone(),
+                        secret: config.application_credential_secret.clone(),
+                        // The id alone identifies the credential; a name only within its user
+                        user: config.application_credential_id.is_none().then(|| UserRef {
+                            name: config.username.clone(),
+                            domain: user_domain(),
+                        }),
+                    }),
+                    ..method_only("application_credential")
+                },
+                None,
+            ),
+            AuthType::Token => (
+                Identity {
+                    token: Some(TokenAuth { id: config.token.clone() }),
+                    ..method_only("token")
+                },
+                project_scope(),
+            ),
+        };
+        AuthRequest { auth: AuthPayload { identity, scope } }
     }
 }
@@ -0,0 +1,265 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::config::CircuitBreakerConfig;
+use crate::error::OpenStackError;
+
+// A breaker per API of one client, e.g. "compute", that opens after a run
+// of server errors. While open its calls fail without going out; once
+// open_seconds have passed a single call probes the API, closing the
+// breaker again if it succeeds
+pub struct CircuitBreakers {
+    config: CircuitBreakerConfig,
+    // Catalog base URL to service type, as endpoints are looked up
+    endpoints: Mutex<Vec<(String, String)>>,
+    breakers: Mutex<HashMap<String, Breaker>>,
+}
+
+#[derive(Debug, Default)]
+struct Breaker {
+    consecutive_failures: u32,
+    opened_at: Option<Instant>,
+    // When the call probing a half-open breaker went out; a probe that
+    // never finishes, e.g. because it was cancelled, lapses after open_seconds
+    probe_started: Option<Instant>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    Closed,
+    Open,
+    HalfOpen,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BreakerStatus {
+    pub cloud: Option<String>,
+    pub region: String,
+    pub service: String,
+    pub state: BreakerState,
+    pub consecutive_failures: u32,
+}
+
+impl CircuitBreakers {
+    pub fn new(config: &CircuitBreakerConfig) -> Self {
+        Self {
+            config: config.clone(),
+            endpoints: Mutex::new(Vec::new()),
+            breakers: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    pub fn register(&self, service_type: &str, endpoint: &str) {
+        let mut endpoints = self.endpoints.lock().unwrap();
+        if !endpoints.iter().any(|(known, _)| known == endpoint) {
+            endpoints.push((endpoint.to_string(), service_type.to_string()));
+        }
+    }
+    
+    // The service whose endpoint is the longest prefix of url
+    fn service_for(&self, url: &str) -> Option<String> {
+        self.endpoints.lock().unwrap().iter()
+            .filter(|(endpoint, _)| url.starts_with(endpoint.as_str()))
+            .max_by_key(|(endpoint, _)| endpoint.len())
+            .map(|(_, service)| service.clone())
+    }
+    
+    fn open_for(&self) -> Duration {
+        Duration::from_secs(self.config.open_seconds)
+    }
+    
+    // Fails while the breaker for url's service is open
+    pub fn check(&self, url: &str) -> Result<(), OpenStackError> {
+        if !self.config.enabled {
+            return Ok(());
+        }
+        let Some(service) = self.service_for(url) else {
+            return Ok(());
+        };
+        let mut breakers = self.breakers.lock().unwrap();
+        let Some(breaker) = breakers.get_mut(&service) else {
+            return Ok(());
+        };
+        
+        match breaker.state(self.open_for()) {
+            BreakerState::Closed => Ok(()),
+            BreakerState::HalfOpen if breaker.probe_started.is_none_or(|at| at.elapsed() >= self.open_for()) => {
+                breaker.probe_started = Some(Instant::now());
+                Ok(())
+            }
+            _ => Err(OpenStackError::CircuitOpen(service)),
+        }
+    }
+    
+    // Server errors and calls that got no response count as failures
+    pub fn record(&self, url: &str, failed: bool) {
+        if !self.config.enabled {
+            return;
+        }
+        let Some(service) = self.service_for(url) else {
+            return;
+        };
+        let mut breakers = self.breakers.lock().unwrap();
+        let breaker = breakers.entry(service.clone()).or_default();
+        
+        if !failed {
+            if breaker.opened_at.is_some() {
+                info!("The {} API answered again, closing its circuit breaker", service);
+            }
+            *breaker = Breaker::default();
+            return;
+        }
+        
+        breaker.consecutive_failures += 1;
+        let reopen = breaker.probe_started.take().is_some();
+        if reopen || (breaker.opened_at.is_none() && breaker.consecutive_failures >= self.config.failure_threshold) {
+            if !reopen {
+                warn!(
+                    "Opening the circuit breaker for the {} API after {} server errors in a row",
+                    service, breaker.consecutive_failures
+                );
+                ::metrics::counter!("openstack_circuit_breaker_opened_total", "service" => service).increment(1);
+            }
+            breaker.opened_at = Some(Instant::now());
+        }
+    }
+    
+    // Every service of the client that has been called at least once
+    pub fn status(&self, cloud: Option<&str>, region: &str) -> Vec<BreakerStatus> {
+        let mut status: Vec<_> = self.breakers.lock().unwrap().iter()
+            .map(|(service, breaker)| BreakerStatus {
+                cloud: cloud.map(str::to_string),
+                region: region.to_string(),
+                service: service.clone(),
+                state: breaker.state(self.open_for()),
+                consecutive_failures: breaker.consecutive_failures,
+            })
+            .collect();
+        status.sort_by(|a, b| a.service.cmp(&b.service));
+        status
+    }
+}
+
+impl Breaker {
+    fn state(&self, open_for: Duration) -> BreakerState {
+        match self.opened_at {
+            None => BreakerState::Closed,
+            Some(at) if at.elapsed() < open_for => BreakerState::Open,
+            Some(_) => BreakerState::HalfOpen,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const COMPUTE: &str = "https://nova.example.com/v2.1";
+    const SERVERS: &str = "https://nova.example.com/v2.1/servers/detail";
+    
+    fn breakers() -> CircuitBreakers {
+        let breakers = CircuitBreakers::new(&CircuitBreakerConfig {
+            enabled: true,
+            failure_threshold: 3,
+            open_seconds: 60,
+        });
+        breakers.register("compute", COMPUTE);
+        breakers
+    }
+    
+    fn state(breakers: &CircuitBreakers) -> BreakerState {
+        breakers.status(None, "RegionOne")[0].state
+    }
+    
+    // As if open_seconds had passed since the breaker opened
+    fn expire(breakers: &CircuitBreakers) {
+        let mut states = breakers.breakers.lock().unwrap();
+        let breaker = states.get_mut("compute").unwrap();
+        breaker.opened_at = breaker.opened_at.map(|at| at - Duration::from_secs(61));
+    }
+    
+    #[test]
+    fn opens_after_failure_threshold() {
+        let breakers = breakers();
+        breakers.record(SERVERS, true);
+        breakers.record(SERVERS, true);
+        assert!(breakers.check(SERVERS).is_ok());
+        
+        breakers.record(SERVERS, true);
+        assert_eq!(state(&breakers), BreakerState::Open);
+        assert!(matches!(breakers.check(SERVERS), Err(OpenStackError::CircuitOpen(service)) if service == "compute"));
+    }
+    
+    #[test]
+    fn success_resets_the_failure_count() {
+        let breakers = breakers();
+        breakers.record(SERVERS, true);
+        breakers.record(SERVERS, true);
+        breakers.record(SERVERS, false);
+        breakers.record(SERVERS, true);
+        breakers.record(SERVERS, true);
+        assert_eq!(state(&breakers), BreakerState::Closed);
+        assert_eq!(breakers.status(None, "RegionOne")[0].consecutive_failures, 2);
+    }
+    
+    #[test]
+    fn half_open_lets_one_probe_through() {
+        let breakers = breakers();
+        for _ in 0..3 {
+            breakers.record(SERVERS, true);
+        }
+        expire(&breakers);
+        assert_eq!(state(&breakers), BreakerState::HalfOpen);
+        
+        assert!(breakers.check(SERVERS).is_ok());
+        assert!(breakers.check(SERVERS).is_err());
+        breakers.record(SERVERS, false);
+        assert_eq!(state(&breakers), BreakerState::Closed);
+        assert!(breakers.check(SERVERS).is_ok());
+    }
+    
+    #[test]
+    fn failed_probe_reopens() {
+        let breakers = breakers();
+        for _ in 0..3 {
+            breakers.record(SERVERS, true);
+        }
+        expire(&breakers);
+        assert!(breakers.check(SERVERS).is_ok());
+        
+        breakers.record(SERVERS, true);
+        assert_eq!(state(&breakers), BreakerState::Open);
+        assert!(breakers.check(SERVERS).is_err());
+    }
+    
+    #[test]
+    fn ignores_unknown_endpoints_and_disabled_breakers() {
+        let breakers = breakers();
+        for _ in 0..3 {
+            breakers.record("https://cinder.example.com/v3/volumes", true);
+        }
+        assert!(breakers.status(None, "RegionOne").is_empty());
+        
+        let disabled = CircuitBreakers::new(&CircuitBreakerConfig {
+            enabled: false,
+            ..CircuitBreakerConfig::default()
+        });
+        disabled.register("compute", COMPUTE);
+        for _ in 0..10 {
+            disabled.record(SERVERS, true);
+        }
+        assert!(disabled.check(SERVERS).is_ok());
+    }
+    
+    #[test]
+    fn picks_the_longest_matching_endpoint() {
+        let breakers = breakers();
+        breakers.register("placement", "https://nova.example.com/v2.1/placement");
+        assert_eq!(breakers.service_for("https://nova.example.com/v2.1/placement/resource_providers").as_deref(), Some("placement"));
+        assert_eq!(breakers.service_for(SERVERS).as_deref(), Some("compute"));
+    }
+}
@@ -1,57 +1,220 @@
-use anyhow::Result;
-use reqwest::{Client as HttpClient, header::{HeaderMap, HeaderValue}};
+use anyhow::{Context, Result};
+use reqwest::{Client as HttpClient, StatusCode, header::{HeaderMap, HeaderValue}};
 use serde::Deserialize;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use tracing::info;
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info};
 
 use super::auth::AuthManager;
-use super::services::{NovaService, NeutronService, CinderService, TelemetryService};
+use super::breaker::{BreakerStatus, CircuitBreakers};
+use super::throttle::Throttle;
+use super::services::{Server, NovaService, PlacementService, GlanceService, NeutronService, CinderService, SwiftService, OctaviaService, TelemetryService, AodhService, IronicService, SenlinService, HeatService};
 use crate::config::OpenStackConfig;
-use crate::error::OpenStackError;
+use crate::error::{OpenStackError, RetryPolicy};
 
 #[derive(Clone)]
 pub struct Client {
-    http_client: HttpClient,
-    auth_manager: Arc<RwLock<AuthManager>>,
+    session: Session,
     pub nova: NovaService,
+    pub placement: PlacementService,
+    pub glance: GlanceService,
     pub neutron: NeutronService,
     pub cinder: CinderService,
+    pub swift: SwiftService,
+    pub octavia: OctaviaService,
     pub telemetry: TelemetryService,
+    pub aodh: AodhService,
+    pub ironic: IronicService,
+    pub senlin: SenlinService,
+    pub heat: HeatService,
+}
+
+// A Client per region of the main credentials and of every cloud under
+// [openstack.clouds], each set of credentials with a Keystone token of its
+// own. The first is region_name's, which serves whatever isn't collected
+// per region
+#[derive(Clone)]
+pub struct Clouds {
+    clients: Vec<Arc<Client>>,
+    // The region each of the main credentials' servers was last listed in
+    server_regions: Arc<RwLock<HashMap<String, String>>>,
+}
+
+// The Keystone token and HTTP client every API call goes out with, shared
+// by Client and the service clients, and the cloud and region whose
+// endpoints they call
+#[derive(Clone)]
+pub struct Session {
+    http_client: HttpClient,
+    auth_manager: Arc<AuthManager>,
+    retry: RetryPolicy,
+    throttle: Arc<Throttle>,
+    breakers: Arc<CircuitBreakers>,
+    // None for the main credentials
+    cloud: Option<String>,
+    region: String,
+}
+
+impl Clouds {
+    pub async fn connect(config: &OpenStackConfig) -> Result<Self> {
+        let mut clients = Self::connect_cloud(config, None).await?;
+        for (name, cloud) in &config.clouds {
+            let cloud_clients = Self::connect_cloud(&config.cloud(cloud), Some(name)).await
+                .with_context(|| format!("Cannot authenticate cloud {}", name))?;
+            clients.extend(cloud_clients);
+        }
+        info!(
+            "OpenStack clients initialized for {} region(s) of {} cloud(s)",
+            clients.len(),
+            config.clouds.len() + 1
+        );
+        Ok(Self {
+            clients,
+            server_regions: Arc::default(),
+        })
+    }
+    
+    // A cloud's regions share its HTTP client, with its TLS settings
+    async fn connect_cloud(config: &OpenStackConfig, cloud: Option<&str>) -> Result<Vec<Arc<Client>>> {
+        let http_client = http_client(config)?;
+        let auth_manager = Arc::new(AuthManager::new(config.clone(), http_client.clone()).await?);
+        Ok(config.region_names().into_iter()
+            .map(|region| Arc::new(Client::new(config, cloud, region, http_client.clone(), auth_manager.clone())))
+            .collect())
+    }
+    
+    pub fn primary(&self) -> Arc<Client> {
+        self.clients[0].clone()
+    }
+    
+    pub fn get(&self, cloud: Option<&str>, region: &str) -> Option<&Arc<Client>> {
+        self.clients.iter().find(|client| client.cloud() == cloud && client.region() == region)
+    }
+    
+    // The main credentials' client for the region, the primary one for no
+    // region or one we have no client for
+    pub fn region(&self, region: Option<&str>) -> Arc<Client> {
+        region.and_then(|region| self.get(None, region))
+            .cloned()
+            .unwrap_or_else(|| self.primary())
+    }
+    
+    // Remembers where the servers are for client_for; only needed with more
+    // than one region
+    pub fn locate(&self, servers: &[Server]) {
+        if self.is_multi_region() {
+            *self.server_regions.write().unwrap() = servers.iter()
+                .filter(|server| server.cloud.is_none())
+                .filter_map(|server| Some((server.id.clone(), server.region.clone()?)))
+                .collect();
+        }
+    }
+    
+    // The client of the region the server was last listed in
+    pub fn client_for(&self, server_id: &str) -> Arc<Client> {
+        let region = self.server_regions.read().unwrap().get(server_id).cloned();
+        self.region(region.as_deref())
+    }
+    
+    // The main credentials' clients, the only ones scheduled with
+    pub fn regions(&self) -> impl Iterator<Item = &Arc<Client>> {
+        self.clients.iter().filter(|client| client.cloud().is_none())
+    }
+    
+    // Every cloud's clients, for collection
+    pub fn all(&self) -> impl Iterator<Item = &Arc<Client>> {
+        self.clients.iter()
+    }
+    
+    pub fn is_multi_region(&self) -> bool {
+        self.regions().nth(1).is_some()
+    }
+    
+    // Circuit breakers of every cloud and region, for the health endpoint
+    pub fn breaker_status(&self) -> Vec<BreakerStatus> {
+        self.clients.iter()
+            .flat_map(|client| client.session.breakers.status(client.cloud(), client.region()))
+            .collect()
+    }
+    
+    // Keeps every cloud's token fresh until shutdown; see
+    // AuthManager::run_renewal
+    pub async fn renew_tokens(&self, shutdown: CancellationToken) {
+        let mut auth_managers: Vec<&Arc<AuthManager>> = Vec::new();
+        for client in &self.clients {
+            let auth_manager = &client.session.auth_manager;
+            if !auth_managers.iter().any(|known| Arc::ptr_eq(known, auth_manager)) {
+                auth_managers.push(auth_manager);
+            }
+        }
+        futures_util::future::join_all(
+            auth_managers.into_iter().map(|auth_manager| auth_manager.run_renewal(shutdown.clone()))
+        ).await;
+    }
 }
 
 impl Client {
-    pub async fn new(config: &OpenStackConfig) -> Result<Self> {
-        let http_client = HttpClient::builder()
-            .timeout(std::time::Duration::from_secs(30))
-            .build()?;
-        
-        let auth_manager = Arc::new(RwLock::new(
-            AuthManager::new(config.clone(), http_client.clone()).await?
-        ));
+    fn new(
+        config: &OpenStackConfig,
+        cloud: Option<&str>,
+        region: &str,
+        http_client: HttpClient,
+        auth_manager: Arc<AuthManager>,
+    ) -> Self {
+        let session = Session {
+            http_client: http_client.clone(),
+            auth_manager: auth_manager.clone(),
+            retry: config.retry.policy(),
+            throttle: Arc::new(Throttle::new(&config.throttle)),
+            breakers: Arc::new(CircuitBreakers::new(&config.circuit_breaker)),
+            cloud: cloud.map(str::to_string),
+            region: region.to_string(),
+        };
         
         // Initialize service clients
-        let nova = NovaService::new(http_client.clone(), auth_manager.clone());
-        let neutron = NeutronService::new(http_client.clone(), auth_manager.clone());
-        let cinder = CinderService::new(http_client.clone(), auth_manager.clone());
-        let telemetry = TelemetryService::new(http_client.clone(), auth_manager.clone());
+        let nova = NovaService::new(session.clone(), config.compute.clone(), &config.microversions);
+        let placement = PlacementService::new(session.clone(), &config.microversions);
+        let glance = GlanceService::new(session.clone(), config.glance.clone());
+        let neutron = NeutronService::new(session.clone());
+        let cinder = CinderService::new(session.clone(), &config.microversions);
+        let swift = SwiftService::new(session.clone());
+        let octavia = OctaviaService::new(session.clone());
+        let telemetry = TelemetryService::new(session.clone(), config.gnocchi.clone());
+        let aodh = AodhService::new(session.clone());
+        let ironic = IronicService::new(session.clone(), &config.microversions);
+        let senlin = SenlinService::new(http_client.clone(), auth_manager.clone());
+        let heat = HeatService::new(session.clone(), config.compute.all_projects);
         
-        info!("OpenStack client initialized successfully");
+        debug!("OpenStack client initialized for region {} of {}", region, cloud.unwrap_or("the main cloud"));
         
-        Ok(Self {
-            http_client,
-            auth_manager,
+        Self {
+            session,
             nova,
+            placement,
+            glance,
             neutron,
             cinder,
+            swift,
+            octavia,
             telemetry,
-        })
+            aodh,
+            ironic,
+            senlin,
+            heat,
+        }
+    }
+    
+    pub fn cloud(&self) -> Option<&str> {
+        self.session.cloud()
+    }
+    
+    pub fn region(&self) -> &str {
+        &self.session.region
     }
     
     pub async fn get_auth_token(&self) -> Result<String> {
-        let auth_manager = self.auth_manager.read().await;
-        let token = auth_manager.get_token().await?;
-        Ok(token.token.clone())
+        self.session.get_auth_token().await
     }
     
     pub async fn make_authenticated_request<T: for<'de> Deserialize<'de>>(
@@ -60,30 +223,147 @@ impl Client {
         url: &str,
         body: Option<serde_json::Value>,
     ) -> Result<T> {
-        let token = self.get_auth_token().await?;
+        self.session.request(method, url, body, HeaderMap::new()).await
+    }
+}
+
+impl Session {
+    pub async fn get_auth_token(&self) -> Result<String> {
+        let token = self.auth_manager.get_token().await?;
+        Ok(token.token.clone())
+    }
+    
+    // Base URL of an API from the service catalog, e.g. "compute"
+    pub fn endpoint(&self, service_type: &str) -> Result<String> {
+        let endpoint = self.auth_manager.endpoint(service_type, &self.region)?;
+        self.breakers.register(service_type, &endpoint);
+        Ok(endpoint)
+    }
+    
+    pub fn cloud(&self) -> Option<&str> {
+        self.cloud.as_deref()
+    }
+    
+    pub fn region(&self) -> &str {
+        &self.region
+    }
+    
+    // Sends with the token plus any extra headers, such as a microversion,
+    // and parses the JSON response
+    pub async fn request<T: for<'de> Deserialize<'de>>(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<serde_json::Value>,
+        extra_headers: HeaderMap,
+    ) -> Result<T> {
+        let response = self.response(method, url, body, extra_headers).await?;
+        Ok(response.json::<T>().await?)
+    }
+    
+    // For calls answered without a body, such as server actions that Nova
+    // accepts with a 202
+    pub async fn execute(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<serde_json::Value>,
+        extra_headers: HeaderMap,
+    ) -> Result<()> {
+        self.response(method, url, body, extra_headers).await?;
+        Ok(())
+    }
+    
+    // The response headers of a HEAD, where Swift reports its statistics
+    pub async fn head(&self, url: &str, extra_headers: HeaderMap) -> Result<HeaderMap> {
+        let response = self.response(reqwest::Method::HEAD, url, None, extra_headers).await?;
+        Ok(response.headers().clone())
+    }
+    
+    async fn response(
+        &self,
+        method: reqwest::Method,
+        url: &str,
+        body: Option<serde_json::Value>,
+        extra_headers: HeaderMap,
+    ) -> Result<reqwest::Response> {
+        // A POST or PATCH may have taken effect before failing, e.g. a server
+        // action that timed out, so only idempotent requests are retried
+        let retry = if method.is_idempotent() { self.retry.clone() } else { RetryPolicy::once() };
+        let what = format!("{} {}", method, url);
         
-        let mut headers = HeaderMap::new();
-        headers.insert("X-Auth-Token", HeaderValue::from_str(&token)?);
+        retry.run(&what, || async {
+            // An open breaker fails the call without retrying it
+            self.breakers.check(url)?;
+            // Each attempt waits its turn, so retries count against the limits too
+            let _permit = self.throttle.acquire().await;
+            let token = self.get_auth_token().await?;
+            let mut response = self.send(&method, url, body.as_ref(), &extra_headers, &token).await?;
+            
+            // Keystone can revoke a token before it expires. A 401 means the
+            // request was turned away unprocessed, so even a POST is safe to
+            // send once more with a new token
+            if response.status() == StatusCode::UNAUTHORIZED {
+                debug!("{} rejected the token, authenticating again", what);
+                self.auth_manager.invalidate(&token);
+                let token = self.get_auth_token().await?;
+                response = self.send(&method, url, body.as_ref(), &extra_headers, &token).await?;
+            }
+            
+            if !response.status().is_success() {
+                return Err(OpenStackError::from_response(response).await.into());
+            }
+            Ok::<_, anyhow::Error>(response)
+        }).await
+    }
+    
+    async fn send(
+        &self,
+        method: &reqwest::Method,
+        url: &str,
+        body: Option<&serde_json::Value>,
+        extra_headers: &HeaderMap,
+        token: &str,
+    ) -> Result<reqwest::Response> {
+        let mut headers = extra_headers.clone();
+        headers.insert("X-Auth-Token", HeaderValue::from_str(token)?);
         headers.insert("Content-Type", HeaderValue::from_static("application/json"));
         
         let mut request = self.http_client
-            .request(method, url)
+            .request(method.clone(), url)
             .headers(headers);
         
         if let Some(body) = body {
-            request = request.json(&body);
+            request = request.json(body);
         }
-        
-        let response = request.send().await?;
-        
-        if !response.status().is_success() {
-            return Err(OpenStackError::ApiError {
-                status: response.status().as_u16(),
-                message: response.text().await.unwrap_or_default(),
-            }.into());
+        let response = request.send().await;
+        let failed = match &response {
+            Ok(response) => response.status().is_server_error(),
+            Err(_) => true,
+        };
+        self.breakers.record(url, failed);
+        Ok(response?)
+    }
+}
+
+fn http_client(config: &OpenStackConfig) -> Result<HttpClient> {
+    let mut builder = HttpClient::builder()
+        .timeout(std::time::Duration::from_secs(30))
+        .danger_accept_invalid_certs(config.insecure);
+    if let Some(path) = &config.ca_bundle {
+        let pem = std::fs::read(path).with_context(|| format!("Cannot read CA bundle {}", path))?;
+        for certificate in reqwest::Certificate::from_pem_bundle(&pem)
+            .with_context(|| format!("Invalid CA bundle {}", path))?
+        {
+            builder = builder.add_root_certificate(certificate);
         }
-        
-        let result = response.json::<T>().await?;
-        Ok(result)
     }
+    if let (Some(cert), Some(key)) = (&config.client_cert, &config.client_key) {
+        let cert_pem = std::fs::read(cert).with_context(|| format!("Cannot read client certificate {}", cert))?;
+        let key_pem = std::fs::read(key).with_context(|| format!("Cannot read client key {}", key))?;
+        let identity = reqwest::Identity::from_pkcs8_pem(&cert_pem, &key_pem)
+            .with_context(|| format!("Invalid client certificate {} or key {}", cert, key))?;
+        builder = builder.identity(identity);
+    }
+    Ok(builder.build()?)
 }
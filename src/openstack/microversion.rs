@@ -0,0 +1,205 @@
+use anyhow::Result;
+use reqwest::header::{HeaderMap, HeaderValue};
+use reqwest::Method;
+use serde::Deserialize;
+use std::sync::Arc;
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use super::client::Session;
+
+// "major.minor" of an API microversion
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Microversion(pub u32, pub u32);
+
+impl Microversion {
+    pub fn parse(version: &str) -> Option<Self> {
+        let (major, minor) = version.trim().split_once('.')?;
+        Some(Self(major.parse().ok()?, minor.parse().ok()?))
+    }
+}
+
+impl std::fmt::Display for Microversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.0, self.1)
+    }
+}
+
+// A service's version document, either the one version it serves or all
+// of them; Placement calls the newest microversion max_version
+#[derive(Deserialize)]
+struct VersionDocument {
+    #[serde(default)]
+    version: Option<VersionInfo>,
+    #[serde(default)]
+    versions: Vec<VersionInfo>,
+}
+
+#[derive(Deserialize)]
+struct VersionInfo {
+    // Empty when the endpoint predates microversions
+    #[serde(default, alias = "max_version")]
+    version: String,
+    #[serde(default)]
+    min_version: String,
+}
+
+// The microversion one service's calls go out with: pinned in
+// [openstack.microversions], or else the newest both sides support up to
+// the one the calls were written against, asked of the version document once
+#[derive(Clone)]
+pub struct ApiMicroversion {
+    // As in OpenStack-API-Version, e.g. "compute"
+    service: &'static str,
+    // Older releases only know a service-specific header
+    legacy_header: Option<&'static str>,
+    preferred: Microversion,
+    pinned: Option<Microversion>,
+    negotiated: Arc<OnceCell<Option<Microversion>>>,
+}
+
+impl ApiMicroversion {
+    pub fn new(
+        service: &'static str,
+        legacy_header: Option<&'static str>,
+        preferred: Microversion,
+        pinned: Option<&str>,
+    ) -> Self {
+        Self {
+            service,
+            legacy_header,
+            preferred,
+            // Validated with the config
+            pinned: pinned.and_then(Microversion::parse),
+            negotiated: Arc::new(OnceCell::new()),
+        }
+    }
+    
+    // None when the endpoint has no microversions
+    pub async fn version(&self, session: &Session, endpoint: &str) -> Result<Option<Microversion>> {
+        if let Some(pinned) = self.pinned {
+            return Ok(Some(pinned));
+        }
+        let version = self.negotiated.get_or_try_init(|| self.negotiate(session, endpoint)).await?;
+        Ok(*version)
+    }
+    
+    pub async fn headers(&self, session: &Session, endpoint: &str) -> Result<HeaderMap> {
+        let mut headers = HeaderMap::new();
+        let Some(version) = self.version(session, endpoint).await? else {
+            return Ok(headers);
+        };
+        if let Ok(value) = HeaderValue::from_str(&format!("{} {}", self.service, version)) {
+            headers.insert("OpenStack-API-Version", value);
+        }
+        if let (Some(header), Ok(value)) = (self.legacy_header, HeaderValue::from_str(&version.to_string())) {
+            headers.insert(header, value);
+        }
+        Ok(headers)
+    }
+    
+    async fn negotiate(&self, session: &Session, endpoint: &str) -> Result<Option<Microversion>> {
+        let root = version_root(endpoint);
+        let document: VersionDocument = session
+            .request(Method::GET, &format!("{}/", root), None, HeaderMap::new())
+            .await?;
+        
+        let Some((version, min, max)) = choose(self.preferred, document) else {
+            warn!("The {} API at {} doesn't support microversions", self.service, root);
+            return Ok(None);
+        };
+        info!("Using {} API microversion {} ({} supports {} to {})", self.service, version, root, min, max);
+        Ok(Some(version))
+    }
+}
+
+// The microversion to use and the range it was picked from, out of the
+// document's version with preferred's major
+fn choose(preferred: Microversion, document: VersionDocument) -> Option<(Microversion, Microversion, Microversion)> {
+    let (max, min) = document.version.into_iter()
+        .chain(document.versions)
+        .filter_map(|info| Some((Microversion::parse(&info.version)?, Microversion::parse(&info.min_version)?)))
+        .find(|(max, _)| max.0 == preferred.0)?;
+    Some((max.min(preferred).max(min), min, max))
+}
+
+// The endpoint up to its version segment, e.g. ".../v3" of
+// ".../v3/<project id>"; the whole endpoint when it has none
+fn version_root(endpoint: &str) -> &str {
+    let endpoint = endpoint.trim_end_matches('/');
+    let mut end = 0;
+    for segment in endpoint.split('/') {
+        end += segment.len();
+        let is_version = segment.strip_prefix('v').is_some_and(|number| {
+            number.split('.').all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+        });
+        if is_version {
+            return &endpoint[..end];
+        }
+        end += 1;
+    }
+    endpoint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn document(json: &str) -> VersionDocument {
+        serde_json::from_str(json).unwrap()
+    }
+    
+    #[test]
+    fn parses_microversions() {
+        assert_eq!(Microversion::parse("2.79"), Some(Microversion(2, 79)));
+        assert_eq!(Microversion::parse(" 1.39 "), Some(Microversion(1, 39)));
+        assert_eq!(Microversion::parse(""), None);
+        assert_eq!(Microversion::parse("2"), None);
+        assert_eq!(Microversion::parse("2.x"), None);
+        assert!(Microversion(2, 100) > Microversion(2, 99));
+    }
+    
+    #[test]
+    fn finds_the_version_root() {
+        assert_eq!(version_root("https://nova.example.com/v2.1"), "https://nova.example.com/v2.1");
+        assert_eq!(version_root("https://nova.example.com/v2.1/"), "https://nova.example.com/v2.1");
+        assert_eq!(version_root("https://cinder.example.com/v3/0123abcd"), "https://cinder.example.com/v3");
+        assert_eq!(version_root("https://cloud.example.com/volume/v3/0123abcd"), "https://cloud.example.com/volume/v3");
+        assert_eq!(version_root("https://placement.example.com"), "https://placement.example.com");
+        // The first segment that looks like a version
+        assert_eq!(version_root("https://cloud.example.com/v1/v2"), "https://cloud.example.com/v1");
+        assert_eq!(version_root("https://cloud.example.com/vendor/api"), "https://cloud.example.com/vendor/api");
+    }
+    
+    #[test]
+    fn negotiates_within_the_supported_range() {
+        let nova = document(r#"{"version": {"id": "v2.1", "version": "2.95", "min_version": "2.1"}}"#);
+        assert_eq!(choose(Microversion(2, 60), nova), Some((Microversion(2, 60), Microversion(2, 1), Microversion(2, 95))));
+        
+        // An older cloud tops out below what the calls were written against
+        let old = document(r#"{"version": {"version": "2.53", "min_version": "2.1"}}"#);
+        assert_eq!(choose(Microversion(2, 60), old).map(|(version, ..)| version), Some(Microversion(2, 53)));
+        
+        let newer = document(r#"{"version": {"version": "2.95", "min_version": "2.70"}}"#);
+        assert_eq!(choose(Microversion(2, 60), newer).map(|(version, ..)| version), Some(Microversion(2, 70)));
+    }
+    
+    #[test]
+    fn negotiates_from_version_lists_and_placement_documents() {
+        let cinder = document(r#"{"versions": [
+            {"id": "v2.0", "version": "", "min_version": ""},
+            {"id": "v3.0", "version": "3.70", "min_version": "3.0"}
+        ]}"#);
+        assert_eq!(choose(Microversion(3, 60), cinder).map(|(version, ..)| version), Some(Microversion(3, 60)));
+        
+        let placement = document(r#"{"versions": [{"id": "v1.0", "max_version": "1.39", "min_version": "1.0"}]}"#);
+        assert_eq!(choose(Microversion(1, 36), placement).map(|(version, ..)| version), Some(Microversion(1, 36)));
+    }
+    
+    #[test]
+    fn no_microversions_without_a_matching_major() {
+        assert_eq!(choose(Microversion(2, 60), document(r#"{"versions": [{"version": "", "min_version": ""}]}"#)), None);
+        assert_eq!(choose(Microversion(2, 60), document(r#"{"version": {"version": "3.1", "min_version": "3.0"}}"#)), None);
+        assert_eq!(choose(Microversion(2, 60), document("{}")), None);
+    }
+}
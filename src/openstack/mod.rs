@@ -1,5 +1,8 @@
 pub mod client;
 pub mod auth;
 pub mod services;
+pub mod throttle;
+pub mod breaker;
+pub mod microversion;
 
-pub use client::Client;
+pub use client::{Client, Clouds};
@@ -1,18 +1,27 @@
 use anyhow::Result;
-use reqwest::Client as HttpClient;
+use futures_util::{stream, StreamExt};
+use reqwest::header::HeaderMap;
+use reqwest::{Client as HttpClient, Method};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use std::sync::Arc;
-use tokio::sync::RwLock;
-use uuid::Uuid;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, info, warn};
 
 use super::auth::AuthManager;
+use super::client::Session;
+use super::microversion::{ApiMicroversion, Microversion};
+use crate::config::{ComputeConfig, GlanceConfig, GnocchiConfig, MicroversionConfig};
+use crate::error::OpenStackError;
 
 // Nova Service for compute resources
 #[derive(Clone)]
 pub struct NovaService {
-    http_client: HttpClient,
-    auth_manager: Arc<RwLock<AuthManager>>,
+    session: Session,
+    config: ComputeConfig,
+    microversion: ApiMicroversion,
+    // Each server's last CPU time, to turn the next reading into utilisation
+    cpu_samples: Arc<Mutex<HashMap<String, CpuSample>>>,
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -20,17 +29,76 @@ pub struct Server {
     pub id: String,
     pub name: String,
     pub status: String,
+    #[serde(default)]
+    pub tenant_id: Option<String>,
     pub flavor: FlavorRef,
-    pub image: ImageRef,
+    // Empty for servers booted from a volume
+    #[serde(default, deserialize_with = "image_ref")]
+    pub image: Option<ImageRef>,
     pub created: String,
     pub updated: String,
     pub addresses: HashMap<String, Vec<Address>>,
     pub metadata: HashMap<String, String>,
+    #[serde(rename = "OS-EXT-SRV-ATTR:host", default)]
+    pub host: Option<String>,
+    #[serde(rename = "OS-EXT-AZ:availability_zone", default)]
+    pub availability_zone: Option<String>,
+    // Set while an action such as a resize is under way
+    #[serde(rename = "OS-EXT-STS:task_state", default)]
+    pub task_state: Option<String>,
+    // Why the server last went into ERROR
+    #[serde(default)]
+    pub fault: Option<ServerFault>,
+    // Filled in from Glance, see GlanceService::enrich
+    #[serde(skip_deserializing)]
+    pub image_metadata: Option<ImageMetadata>,
+    // The cloud and region of the client that listed it; no cloud for the
+    // main credentials
+    #[serde(skip_deserializing)]
+    pub cloud: Option<String>,
+    #[serde(skip_deserializing)]
+    pub region: Option<String>,
+}
+
+#[derive(Deserialize, Serialize, Debug)]
+pub struct ServerFault {
+    pub message: String,
 }
 
+// From microversion 2.47 Nova embeds the flavor without its id, so its
+// name stands in, along with the flavor's sizes and extra specs
 #[derive(Deserialize, Serialize, Debug)]
 pub struct FlavorRef {
+    #[serde(alias = "original_name")]
     pub id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vcpus: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ram: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub disk: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ephemeral: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub swap: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub extra_specs: Option<HashMap<String, String>>,
+}
+
+impl FlavorRef {
+    // The flavor as embedded in the server, so it needn't be looked up
+    pub fn embedded(&self) -> Option<Flavor> {
+        Some(Flavor {
+            id: self.id.clone(),
+            name: self.id.clone(),
+            vcpus: self.vcpus?,
+            ram: self.ram?,
+            disk: self.disk?,
+            ephemeral: self.ephemeral.unwrap_or(0),
+            swap: self.swap.unwrap_or(0),
+            extra_specs: Some(self.extra_specs.clone().unwrap_or_default()),
+        })
+    }
 }
 
 #[derive(Deserialize, Serialize, Debug)]
@@ -48,51 +116,837 @@ pub struct Address {
 #[derive(Deserialize, Debug)]
 pub struct ServersResponse {
     pub servers: Vec<Server>,
+    // A "next" link when there may be more pages
+    #[serde(default)]
+    pub servers_links: Vec<Link>,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Flavor {
+    pub id: String,
+    pub name: String,
+    pub vcpus: u32,
+    // MiB
+    pub ram: u64,
+    // GiB
+    pub disk: u64,
+    // GiB
+    #[serde(rename = "OS-FLV-EXT-DATA:ephemeral", default)]
+    pub ephemeral: u64,
+    // MiB; Nova sends "" for none before microversion 2.75
+    #[serde(default, deserialize_with = "swap_mb")]
+    pub swap: u64,
+    // Only part of the flavor from microversion 2.61, see get_flavor
+    #[serde(default)]
+    pub extra_specs: Option<HashMap<String, String>>,
+}
+
+#[derive(Deserialize)]
+struct FlavorResponse {
+    flavor: Flavor,
+}
+
+#[derive(Deserialize)]
+struct ExtraSpecsResponse {
+    extra_specs: HashMap<String, String>,
+}
+
+#[derive(Deserialize)]
+struct FlavorsResponse {
+    flavors: Vec<Flavor>,
+}
+
+#[derive(Deserialize)]
+struct ServerResponse {
+    server: Server,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct Link {
+    pub rel: String,
+    pub href: String,
+}
+
+fn image_ref<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Option<ImageRef>, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Image {
+        Ref(ImageRef),
+        Empty(String),
+    }
+    Ok(match Option::<Image>::deserialize(deserializer)? {
+        Some(Image::Ref(image)) => Some(image),
+        Some(Image::Empty(_)) | None => None,
+    })
+}
+
+// 2.48 standardised server diagnostics across hypervisor drivers
+const COMPUTE_MICROVERSION: Microversion = Microversion(2, 48);
+
+// CPU readings older than this are from servers that went away
+const CPU_SAMPLE_TTL: Duration = Duration::from_secs(3600);
+
+// How long a live migration gets to leave Nova's queue, and how often to look
+const MIGRATION_START_WAIT: Duration = Duration::from_secs(60);
+const MIGRATION_POLL_INTERVAL: Duration = Duration::from_secs(3);
+
+// /servers/{id}/diagnostics from 2.48; what a driver can't report is null
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct ServerDiagnostics {
+    uptime: Option<u64>,
+    num_cpus: Option<u32>,
+    cpu_details: Vec<CpuDetail>,
+    memory_details: MemoryDetail,
+    disk_details: Vec<DiskDetail>,
+    nic_details: Vec<NicDetail>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CpuDetail {
+    // Nanoseconds since boot
+    time: Option<u64>,
+    utilisation: Option<f64>,
+}
+
+// MiB
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct MemoryDetail {
+    maximum: Option<u64>,
+    used: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct DiskDetail {
+    read_bytes: Option<u64>,
+    write_bytes: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct NicDetail {
+    mac_address: Option<String>,
+    rx_octets: Option<u64>,
+    tx_octets: Option<u64>,
+    rx_packets: Option<u64>,
+    tx_packets: Option<u64>,
+    rx_drop: Option<u64>,
+    tx_drop: Option<u64>,
+}
+
+struct CpuSample {
+    time_ns: u64,
+    at: Instant,
 }
 
 impl NovaService {
-    pub fn new(http_client: HttpClient, auth_manager: Arc<RwLock<AuthManager>>) -> Self {
+    pub fn new(session: Session, config: ComputeConfig, microversions: &MicroversionConfig) -> Self {
         Self {
-            http_client,
-            auth_manager,
+            session,
+            config,
+            microversion: ApiMicroversion::new(
+                "compute",
+                Some("X-OpenStack-Nova-API-Version"),
+                COMPUTE_MICROVERSION,
+                microversions.compute.as_deref(),
+            ),
+            cpu_samples: Arc::new(Mutex::new(HashMap::new())),
         }
     }
     
+    // Every server, following Nova's pages by marker
     pub async fn list_servers(&self) -> Result<Vec<Server>> {
-        // In a real implementation, this would make the actual API call
-        // For now, return mock data
-        Ok(vec![
-            Server {
-                id: Uuid::new_v4().to_string(),
-                name: "web-server-1".to_string(),
-                status: "ACTIVE".to_string(),
-                flavor: FlavorRef { id: "m1.small".to_string() },
-                image: ImageRef { id: "ubuntu-20.04".to_string() },
-                created: chrono::Utc::now().to_rfc3339(),
-                updated: chrono::Utc::now().to_rfc3339(),
-                addresses: HashMap::new(),
-                metadata: HashMap::new(),
-            }
-        ])
+        let endpoint = self.session.endpoint("compute")?;
+        let headers = self.headers().await?;
+        let mut query = format!("limit={}", self.config.page_size);
+        if self.config.all_projects {
+            query.push_str("&all_tenants=True");
+        }
+        
+        let mut servers: Vec<Server> = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let url = match &marker {
+                Some(marker) => format!("{}/servers/detail?{}&marker={}", endpoint, query, marker),
+                None => format!("{}/servers/detail?{}", endpoint, query),
+            };
+            let page: ServersResponse = self.session.request(Method::GET, &url, None, headers.clone()).await?;
+            let more = page.servers_links.iter().any(|link| link.rel == "next");
+            servers.extend(page.servers.into_iter().map(|server| self.in_region(server)));
+            
+            match servers.last() {
+                Some(last) if more => marker = Some(last.id.clone()),
+                _ => break,
+            }
+        }
+        debug!("Listed {} servers from Nova", servers.len());
+        Ok(servers)
+    }
+    
+    pub async fn shelve_server(&self, server_id: &str) -> Result<()> {
+        // Mock implementation - would POST /servers/{server_id}/action with {"shelve": null}
+        info!("Shelving server {}", server_id);
+        Ok(())
+    }
+    
+    pub async fn get_server(&self, server_id: &str) -> Result<Server> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}", endpoint, server_id);
+        let response: ServerResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        Ok(self.in_region(response.server))
+    }
+    
+    fn in_region(&self, mut server: Server) -> Server {
+        server.cloud = self.session.cloud().map(str::to_string);
+        server.region = Some(self.session.region().to_string());
+        server
+    }
+    
+    // Hands the server to Nova to move onto target_host and waits until the
+    // migration is under way. Nova's pre-checks run after the request is
+    // accepted, so a rejection shows up as a failed migration record rather
+    // than an error response. Completion is left to list_server_migrations
+    pub async fn live_migrate(&self, server_id: &str, target_host: &str) -> Result<()> {
+        let server = self.get_server(server_id).await?;
+        if !matches!(server.status.as_str(), "ACTIVE" | "PAUSED") {
+            return Err(precheck_failed(format!(
+                "Server {} is {} and can't be live-migrated",
+                server_id, server.status
+            )).into());
+        }
+        if server.host.as_deref() == Some(target_host) {
+            return Err(precheck_failed(format!(
+                "Server {} is already on {}",
+                server_id, target_host
+            )).into());
+        }
+        self.check_compute_host(target_host).await?;
+        
+        // From 2.25 Nova picks block migration itself, depending on whether
+        // the hosts share storage. Before that only a volume-backed server
+        // can be moved without copying its disks
+        let block_migration = match self.microversion().await? {
+            Some(version) if version >= Microversion(2, 25) => serde_json::json!("auto"),
+            _ => serde_json::json!(server.image.is_some()),
+        };
+        let mut action = serde_json::json!({
+            "host": target_host,
+            "block_migration": block_migration,
+        });
+        if !block_migration.is_string() {
+            action["disk_over_commit"] = serde_json::json!(false);
+        }
+        
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/action", endpoint, server_id);
+        let requested_at = chrono::Utc::now();
+        self.session.execute(
+            Method::POST,
+            &url,
+            Some(serde_json::json!({ "os-migrateLive": action })),
+            self.headers().await?,
+        ).await?;
+        info!(
+            "Live-migrating server {} from {} to {}",
+            server_id, server.host.as_deref().unwrap_or("an unknown host"), target_host
+        );
+        
+        self.await_migration_start(server_id, requested_at).await
+    }
+    
+    // Every flavor, private ones included when we're an admin
+    pub async fn list_flavors(&self) -> Result<Vec<Flavor>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/flavors/detail?is_public=None", endpoint);
+        let response: FlavorsResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        Ok(response.flavors)
+    }
+    
+    // The flavor with its extra specs, asked for separately when the
+    // microversion doesn't include them
+    pub async fn get_flavor(&self, flavor_id: &str) -> Result<Flavor> {
+        let endpoint = self.session.endpoint("compute")?;
+        let headers = self.headers().await?;
+        let url = format!("{}/flavors/{}", endpoint, flavor_id);
+        let mut flavor = self.session.request::<FlavorResponse>(Method::GET, &url, None, headers.clone()).await?.flavor;
+        if flavor.extra_specs.is_none() {
+            let url = format!("{}/flavors/{}/os-extra-specs", endpoint, flavor_id);
+            let response: ExtraSpecsResponse = self.session.request(Method::GET, &url, None, headers).await?;
+            flavor.extra_specs = Some(response.extra_specs);
+        }
+        Ok(flavor)
+    }
+    
+    // Asks Nova to move the server onto flavor_id. Nova then has it wait in
+    // VERIFY_RESIZE for confirm_resize; follow it with resize_state
+    pub async fn resize_server(&self, server_id: &str, flavor_id: &str) -> Result<()> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/action", endpoint, server_id);
+        self.session.execute(
+            Method::POST,
+            &url,
+            Some(serde_json::json!({ "resize": { "flavorRef": flavor_id } })),
+            self.headers().await?,
+        ).await?;
+        info!("Resizing server {} to flavor {}", server_id, flavor_id);
+        Ok(())
     }
     
+    // Where a resize onto flavor (id or name, as Nova may report either)
+    // stands. Nova puts a server it couldn't resize back to ACTIVE on its
+    // old flavor, which is reported as failed
+    pub async fn resize_state(&self, server_id: &str, flavor: &[&str]) -> Result<ResizeState> {
+        let server = self.get_server(server_id).await?;
+        Ok(match (server.status.as_str(), server.task_state.as_deref()) {
+            ("VERIFY_RESIZE", _) => ResizeState::AwaitingConfirmation,
+            ("ERROR", _) => {
+                let reason = server.fault.map(|fault| fault.message).unwrap_or_else(|| "no fault recorded".to_string());
+                ResizeState::Failed(format!("Resize of {} left it in ERROR: {}", server_id, reason))
+            }
+            // Confirmed already, e.g. by an operator
+            (_, None) if flavor.contains(&server.flavor.id.as_str()) => ResizeState::Completed,
+            // Nova sets the task state before accepting the request, so
+            // none means the resize is over
+            (status, None) => ResizeState::Failed(format!(
+                "Nova gave up resizing {}, which is {} on its old flavor",
+                server_id, status
+            )),
+            (status, Some(task)) => {
+                debug!("Resize of {} is {} ({})", server_id, status, task);
+                ResizeState::Running
+            }
+        })
+    }
+    
+    // Keeps the new flavor and frees the resources held on the old host
+    pub async fn confirm_resize(&self, server_id: &str) -> Result<()> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/action", endpoint, server_id);
+        self.session.execute(
+            Method::POST,
+            &url,
+            Some(serde_json::json!({ "confirmResize": null })),
+            self.headers().await?,
+        ).await?;
+        debug!("Confirmed resize of server {}", server_id);
+        Ok(())
+    }
+    
+    // The target's nova-compute service has to be up and enabled for Nova's
+    // scheduler to accept it
+    async fn check_compute_host(&self, host: &str) -> Result<()> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/os-services?binary=nova-compute&host={}", endpoint, host);
+        let response: ComputeServicesResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        
+        let Some(service) = response.services.first() else {
+            return Err(precheck_failed(format!("No compute service on host {}", host)).into());
+        };
+        if !service.is_usable() {
+            return Err(precheck_failed(format!(
+                "Compute service on {} is {} and {}{}",
+                host,
+                service.state,
+                service.status,
+                service.disabled_reason.as_deref().map(|reason| format!(" ({})", reason)).unwrap_or_default(),
+            )).into());
+        }
+        Ok(())
+    }
+    
+    // Polls until the migration record is running or finished. One still
+    // queued when the wait runs out is left for the caller to follow up
+    async fn await_migration_start(&self, server_id: &str, requested_at: chrono::DateTime<chrono::Utc>) -> Result<()> {
+        // Nova and our clocks can disagree slightly
+        let since = requested_at - chrono::Duration::seconds(60);
+        let deadline = Instant::now() + MIGRATION_START_WAIT;
+        
+        loop {
+            tokio::time::sleep(MIGRATION_POLL_INTERVAL).await;
+            let migration = self.list_server_migrations(server_id).await?
+                .into_iter()
+                .filter(|m| m.created_at() >= since)
+                .max_by_key(|m| m.created_at());
+            
+            match migration {
+                Some(m) if m.state() == MigrationState::Failed => {
+                    return Err(OpenStackError::ApiError {
+                        status: 409,
+                        message: format!("Nova reports live migration {} of {} as {}", m.id, server_id, m.status),
+                        retry_after: None,
+                    }.into());
+                }
+                Some(m) if !matches!(m.status.as_str(), "queued" | "preparing" | "accepted") => {
+                    debug!("Live migration {} of {} is {}", m.id, server_id, m.status);
+                    return Ok(());
+                }
+                _ if Instant::now() >= deadline => {
+                    warn!("Live migration of {} hasn't started after {:?}", server_id, MIGRATION_START_WAIT);
+                    return Ok(());
+                }
+                _ => {}
+            }
+        }
+    }
+    
+    // The server's live migrations as Nova records them, oldest first
+    pub async fn list_server_migrations(&self, server_id: &str) -> Result<Vec<Migration>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/os-migrations?instance_uuid={}", endpoint, server_id);
+        let response: MigrationsResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        let mut migrations: Vec<Migration> = response.migrations.into_iter()
+            .filter(|m| m.instance_uuid == server_id)
+            .filter(|m| m.migration_type.as_deref().is_none_or(|kind| kind == "live-migration"))
+            .collect();
+        migrations.sort_by_key(|m| m.created_at);
+        Ok(migrations)
+    }
+    
+    // Asks Nova to stop a live migration and roll it back; its record then
+    // turns cancelled. Needs microversion 2.24, and Nova refuses once the
+    // migration is past the point it can be undone
+    pub async fn abort_live_migration(&self, server_id: &str, migration_id: u64) -> Result<()> {
+        if !self.microversion().await?.is_some_and(|version| version >= Microversion(2, 24)) {
+            return Err(OpenStackError::ConfigError(
+                "aborting a live migration needs compute microversion 2.24".to_string(),
+            ).into());
+        }
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/migrations/{}", endpoint, server_id, migration_id);
+        self.session.execute(Method::DELETE, &url, None, self.headers().await?).await?;
+        info!("Aborting live migration {} of server {}", migration_id, server_id);
+        Ok(())
+    }
+    
+    // What was done to the server, by anyone, newest first
+    pub async fn list_instance_actions(&self, server_id: &str) -> Result<Vec<InstanceAction>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/os-instance-actions", endpoint, server_id);
+        let response: InstanceActionsResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        let mut actions = response.instance_actions;
+        actions.sort_by(|a, b| b.start_time.cmp(&a.start_time));
+        Ok(actions)
+    }
+    
+    // Every compute node with its resource totals, following pages by marker
+    pub async fn list_hypervisors(&self) -> Result<Vec<Hypervisor>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let headers = self.headers().await?;
+        
+        let mut hypervisors: Vec<Hypervisor> = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let url = match &marker {
+                Some(marker) => format!("{}/os-hypervisors/detail?limit={}&marker={}", endpoint, self.config.page_size, marker),
+                None => format!("{}/os-hypervisors/detail?limit={}", endpoint, self.config.page_size),
+            };
+            let page: HypervisorsResponse = self.session.request(Method::GET, &url, None, headers.clone()).await?;
+            let more = page.hypervisors_links.iter().any(|link| link.rel == "next");
+            hypervisors.extend(page.hypervisors);
+            
+            match hypervisors.last() {
+                Some(last) if more => marker = Some(last.id.clone()),
+                _ => break,
+            }
+        }
+        Ok(hypervisors)
+    }
+    
+    // Server groups with their members, of every project when servers are
+    // listed across projects
+    pub async fn list_server_groups(&self) -> Result<Vec<ServerGroup>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = if self.config.all_projects {
+            format!("{}/os-server-groups?all_projects=True", endpoint)
+        } else {
+            format!("{}/os-server-groups", endpoint)
+        };
+        let response: ServerGroupsResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        Ok(response.server_groups)
+    }
+    
+    // Host aggregates with their members; those that define an availability
+    // zone are how Nova puts hosts in one
+    pub async fn list_aggregates(&self) -> Result<Vec<Aggregate>> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/os-aggregates", endpoint);
+        let response: AggregatesResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        Ok(response.aggregates)
+    }
+    
+    pub async fn get_hypervisor_details(&self, hypervisor_id: &str) -> Result<Hypervisor> {
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/os-hypervisors/{}", endpoint, hypervisor_id);
+        let response: HypervisorResponse = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        Ok(response.hypervisor)
+    }
+    
+    // From the hypervisor's diagnostics. Disk and network bytes are totals
+    // since boot; CPU is the utilisation since the previous reading, or
+    // since boot on the first
     pub async fn get_server_metrics(&self, server_id: &str) -> Result<ServerMetrics> {
-        // Mock implementation - would integrate with actual Nova API
+        let version = self.microversion().await?;
+        if version.is_none_or(|version| version < COMPUTE_MICROVERSION) {
+            return Err(OpenStackError::ServiceUnavailable(format!(
+                "Server diagnostics need compute API microversion {}",
+                COMPUTE_MICROVERSION
+            )).into());
+        }
+        
+        let endpoint = self.session.endpoint("compute")?;
+        let url = format!("{}/servers/{}/diagnostics", endpoint, server_id);
+        let diagnostics: ServerDiagnostics = self.session.request(Method::GET, &url, None, self.headers().await?).await?;
+        
         Ok(ServerMetrics {
             server_id: server_id.to_string(),
-            cpu_utilization: 45.2,
-            memory_usage: 2048,
-            memory_total: 4096,
-            disk_read_bytes: 1024000,
-            disk_write_bytes: 512000,
-            network_rx_bytes: 2048000,
-            network_tx_bytes: 1024000,
+            cpu_utilization: self.cpu_utilization(server_id, &diagnostics),
+            memory_usage: diagnostics.memory_details.used.unwrap_or(0),
+            memory_total: diagnostics.memory_details.maximum.unwrap_or(0),
+            disk_read_bytes: diagnostics.disk_details.iter().filter_map(|disk| disk.read_bytes).sum(),
+            disk_write_bytes: diagnostics.disk_details.iter().filter_map(|disk| disk.write_bytes).sum(),
+            network_rx_bytes: diagnostics.nic_details.iter().filter_map(|nic| nic.rx_octets).sum(),
+            network_tx_bytes: diagnostics.nic_details.iter().filter_map(|nic| nic.tx_octets).sum(),
+            interfaces: diagnostics.nic_details.iter()
+                .filter_map(|nic| Some(InterfaceTraffic {
+                    mac_address: nic.mac_address.clone()?,
+                    rx_bytes: nic.rx_octets.unwrap_or(0),
+                    tx_bytes: nic.tx_octets.unwrap_or(0),
+                    rx_packets: nic.rx_packets.unwrap_or(0),
+                    tx_packets: nic.tx_packets.unwrap_or(0),
+                    rx_dropped: nic.rx_drop.unwrap_or(0),
+                    tx_dropped: nic.tx_drop.unwrap_or(0),
+                }))
+                .collect(),
+            workload_class: None,
+            cloud: self.session.cloud().map(str::to_string),
+            region: Some(self.session.region().to_string()),
+            extra: BTreeMap::new(),
             timestamp: chrono::Utc::now(),
         })
     }
+    
+    // Percent of the server's vCPUs. Drivers that report utilisation are
+    // taken at their word; libvirt only reports CPU time
+    fn cpu_utilization(&self, server_id: &str, diagnostics: &ServerDiagnostics) -> f64 {
+        let reported: Vec<f64> = diagnostics.cpu_details.iter().filter_map(|cpu| cpu.utilisation).collect();
+        if !reported.is_empty() {
+            return reported.iter().sum::<f64>() / reported.len() as f64;
+        }
+        
+        let cpus = diagnostics.num_cpus.unwrap_or(diagnostics.cpu_details.len() as u32).max(1) as f64;
+        let time_ns: u64 = diagnostics.cpu_details.iter().filter_map(|cpu| cpu.time).sum();
+        let now = Instant::now();
+        
+        let mut samples = self.cpu_samples.lock().unwrap();
+        let previous = samples.insert(server_id.to_string(), CpuSample { time_ns, at: now });
+        let (busy_ns, elapsed) = match previous {
+            // A lower reading means the server rebooted
+            Some(previous) if time_ns >= previous.time_ns => {
+                (time_ns - previous.time_ns, now.duration_since(previous.at))
+            }
+            _ => {
+                samples.retain(|_, sample| now.duration_since(sample.at) < CPU_SAMPLE_TTL);
+                match diagnostics.uptime {
+                    Some(uptime) => (time_ns, Duration::from_secs(uptime)),
+                    None => return 0.0,
+                }
+            }
+        };
+        if elapsed.is_zero() {
+            return 0.0;
+        }
+        (busy_ns as f64 / elapsed.as_nanos() as f64 / cpus * 100.0).clamp(0.0, 100.0)
+    }
+    
+    async fn headers(&self) -> Result<HeaderMap> {
+        self.microversion.headers(&self.session, &self.session.endpoint("compute")?).await
+    }
+    
+    async fn microversion(&self) -> Result<Option<Microversion>> {
+        self.microversion.version(&self.session, &self.session.endpoint("compute")?).await
+    }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+// Compute node as /os-hypervisors reports it. Totals are physical; the
+// *_used figures count what is allocated to instances
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Hypervisor {
+    // A number before microversion 2.53, a uuid from then on
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub hypervisor_hostname: String,
+    // "up" or "down"
+    pub state: String,
+    // "enabled" or "disabled"
+    pub status: String,
+    pub vcpus: u32,
+    pub vcpus_used: u32,
+    pub memory_mb: u64,
+    pub memory_mb_used: u64,
+    #[serde(default)]
+    pub local_gb: u64,
+    #[serde(default)]
+    pub local_gb_used: u64,
+    pub running_vms: u32,
+    // The nova-compute service on the node, whose host servers report
+    #[serde(default)]
+    pub service: Option<HypervisorService>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct HypervisorService {
+    pub host: String,
+}
+
+impl Hypervisor {
+    pub fn is_usable(&self) -> bool {
+        self.state == "up" && self.status == "enabled"
+    }
+}
+
+// Entry of /os-server-groups. Before microversion 2.64 the policy comes
+// as a list of one
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ServerGroup {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub policy: Option<String>,
+    #[serde(default)]
+    pub policies: Vec<String>,
+    #[serde(default)]
+    pub members: Vec<String>,
+}
+
+impl ServerGroup {
+    pub fn group_ref(&self) -> Option<ServerGroupRef> {
+        let policy = self.policy.as_ref().or(self.policies.first())?;
+        Some(ServerGroupRef {
+            id: self.id.clone(),
+            name: self.name.clone(),
+            policy: policy.parse().ok()?,
+        })
+    }
+}
+
+// The server group a server was booted into, which Nova allows one of
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerGroupRef {
+    pub id: String,
+    pub name: String,
+    pub policy: ServerGroupPolicy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ServerGroupPolicy {
+    Affinity,
+    AntiAffinity,
+    // Preferences Nova gives way on when it has to
+    SoftAffinity,
+    SoftAntiAffinity,
+}
+
+impl std::str::FromStr for ServerGroupPolicy {
+    type Err = String;
+    
+    fn from_str(policy: &str) -> Result<Self, Self::Err> {
+        match policy {
+            "affinity" => Ok(Self::Affinity),
+            "anti-affinity" => Ok(Self::AntiAffinity),
+            "soft-affinity" => Ok(Self::SoftAffinity),
+            "soft-anti-affinity" => Ok(Self::SoftAntiAffinity),
+            other => Err(format!("unknown server group policy {}", other)),
+        }
+    }
+}
+
+impl std::fmt::Display for ServerGroupPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Self::Affinity => "affinity",
+            Self::AntiAffinity => "anti-affinity",
+            Self::SoftAffinity => "soft-affinity",
+            Self::SoftAntiAffinity => "soft-anti-affinity",
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct ServerGroupsResponse {
+    server_groups: Vec<ServerGroup>,
+}
+
+// Entry of /os-aggregates. Hosts are named as their compute service is
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Aggregate {
+    #[serde(deserialize_with = "string_or_number")]
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub availability_zone: Option<String>,
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+impl Aggregate {
+    // Projects allowed on the aggregate's hosts when it isolates tenants,
+    // as Nova's AggregateMultiTenancyIsolation reads filter_tenant_id,
+    // filter_tenant_id_2 and so on as comma-separated lists
+    pub fn tenants(&self) -> Option<Vec<String>> {
+        let tenants: Vec<String> = self.metadata.iter()
+            .filter(|(key, _)| key.starts_with("filter_tenant_id"))
+            .flat_map(|(_, value)| value.split(',').map(|tenant| tenant.trim().to_string()))
+            .filter(|tenant| !tenant.is_empty())
+            .collect();
+        (!tenants.is_empty()).then_some(tenants)
+    }
+}
+
+#[derive(Deserialize)]
+struct AggregatesResponse {
+    aggregates: Vec<Aggregate>,
+}
+
+// Entry of /os-services
+#[derive(Deserialize, Debug)]
+struct ComputeService {
+    // "up" or "down"
+    state: String,
+    // "enabled" or "disabled"
+    status: String,
+    #[serde(default)]
+    disabled_reason: Option<String>,
+}
+
+impl ComputeService {
+    fn is_usable(&self) -> bool {
+        self.state == "up" && self.status == "enabled"
+    }
+}
+
+#[derive(Deserialize)]
+struct ComputeServicesResponse {
+    services: Vec<ComputeService>,
+}
+
+#[derive(Deserialize)]
+struct HypervisorsResponse {
+    hypervisors: Vec<Hypervisor>,
+    #[serde(default)]
+    hypervisors_links: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+struct HypervisorResponse {
+    hypervisor: Hypervisor,
+}
+
+fn swap_mb<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<u64, D::Error> {
+    Ok(serde_json::Value::deserialize(deserializer)?.as_u64().unwrap_or(0))
+}
+
+fn string_or_number<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<String, D::Error> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Id {
+        String(String),
+        Number(u64),
+    }
+    Ok(match Id::deserialize(deserializer)? {
+        Id::String(id) => id,
+        Id::Number(id) => id.to_string(),
+    })
+}
+
+// Entry of /os-migrations; Nova reports timestamps without a zone, in UTC
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Migration {
+    pub id: u64,
+    pub instance_uuid: String,
+    pub status: String,
+    pub source_compute: Option<String>,
+    pub dest_compute: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    #[serde(default)]
+    pub updated_at: Option<chrono::NaiveDateTime>,
+    // From microversion 2.23; older releases list every kind of migration
+    // without saying which
+    #[serde(default)]
+    pub migration_type: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct MigrationsResponse {
+    migrations: Vec<Migration>,
+}
+
+// Entry of /servers/{id}/os-instance-actions, e.g. "live-migration" or
+// "reboot"; timestamps are UTC without a zone, like migrations'
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InstanceAction {
+    pub action: String,
+    pub request_id: String,
+    pub start_time: chrono::NaiveDateTime,
+    #[serde(default)]
+    pub user_id: Option<String>,
+    #[serde(default)]
+    pub message: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct InstanceActionsResponse {
+    #[serde(rename = "instanceActions")]
+    instance_actions: Vec<InstanceAction>,
+}
+
+impl InstanceAction {
+    pub fn started_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.start_time.and_utc()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResizeState {
+    Running,
+    // In VERIFY_RESIZE, for confirm_resize
+    AwaitingConfirmation,
+    Completed,
+    Failed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MigrationState {
+    InProgress,
+    Completed,
+    Failed,
+}
+
+impl Migration {
+    pub fn state(&self) -> MigrationState {
+        match self.status.to_ascii_lowercase().as_str() {
+            "completed" | "done" | "finished" | "confirmed" => MigrationState::Completed,
+            "error" | "failed" | "cancelled" => MigrationState::Failed,
+            _ => MigrationState::InProgress,
+        }
+    }
+    
+    pub fn created_at(&self) -> chrono::DateTime<chrono::Utc> {
+        self.created_at.and_utc()
+    }
+    
+    // For a finished migration, when it finished
+    pub fn updated_at(&self) -> Option<chrono::DateTime<chrono::Utc>> {
+        self.updated_at.map(|at| at.and_utc())
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ServerMetrics {
     pub server_id: String,
     pub cpu_utilization: f64,
@@ -102,126 +956,1787 @@ pub struct ServerMetrics {
     pub disk_write_bytes: u64,
     pub network_rx_bytes: u64,
     pub network_tx_bytes: u64,
+    // Per vNIC, for tying traffic to Neutron ports; empty from sources that
+    // only report totals
+    #[serde(default)]
+    pub interfaces: Vec<InterfaceTraffic>,
+    // From the server's image, for per-workload forecast models
+    #[serde(default)]
+    pub workload_class: Option<String>,
+    #[serde(default)]
+    pub cloud: Option<String>,
+    #[serde(default)]
+    pub region: Option<String>,
+    // Further measurements by name, such as cpu_steal_ns from a source that
+    // reports it, and those metric transforms derive
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra: BTreeMap<String, f64>,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
 
-// Neutron Service for networking
+// Counters since boot of one of a server's vNICs
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InterfaceTraffic {
+    pub mac_address: String,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    pub rx_dropped: u64,
+    pub tx_dropped: u64,
+}
+
+// Placement Service for compute node inventory and usage
 #[derive(Clone)]
-pub struct NeutronService {
-    http_client: HttpClient,
-    auth_manager: Arc<RwLock<AuthManager>>,
+pub struct PlacementService {
+    session: Session,
+    microversion: ApiMicroversion,
 }
 
-impl NeutronService {
-    pub fn new(http_client: HttpClient, auth_manager: Arc<RwLock<AuthManager>>) -> Self {
-        Self {
-            http_client,
-            auth_manager,
-        }
-    }
-    
-    pub async fn get_network_metrics(&self) -> Result<Vec<NetworkMetrics>> {
-        // Mock implementation
-        Ok(vec![
-            NetworkMetrics {
-                network_id: Uuid::new_v4().to_string(),
-                bandwidth_utilization: 23.5,
-                packet_loss: 0.01,
-                latency_ms: 2.3,
-                timestamp: chrono::Utc::now(),
-            }
-        ])
+#[derive(Debug, Clone, Deserialize)]
+pub struct ResourceProvider {
+    pub uuid: String,
+    // The compute node's hypervisor hostname
+    pub name: String,
+}
+
+// One resource class, e.g. VCPU, of a provider
+#[derive(Debug, Clone, Deserialize)]
+pub struct Inventory {
+    pub total: u64,
+    #[serde(default)]
+    pub reserved: u64,
+    #[serde(default = "default_allocation_ratio")]
+    pub allocation_ratio: f64,
+}
+
+impl Inventory {
+    // What may be allocated, overcommit included
+    pub fn capacity(&self) -> u64 {
+        (self.total.saturating_sub(self.reserved) as f64 * self.allocation_ratio) as u64
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct NetworkMetrics {
-    pub network_id: String,
-    pub bandwidth_utilization: f64,
-    pub packet_loss: f64,
-    pub latency_ms: f64,
-    pub timestamp: chrono::DateTime<chrono::Utc>,
+fn default_allocation_ratio() -> f64 {
+    1.0
 }
 
-// Cinder Service for block storage
-#[derive(Clone)]
-pub struct CinderService {
-    http_client: HttpClient,
-    auth_manager: Arc<RwLock<AuthManager>>,
+// A provider's inventory together with what is allocated from it
+#[derive(Debug, Clone)]
+pub struct ProviderUsage {
+    pub provider: ResourceProvider,
+    pub inventories: HashMap<String, Inventory>,
+    pub usages: HashMap<String, u64>,
+    // Instances and migrations holding allocations
+    pub consumers: u32,
 }
 
-impl CinderService {
-    pub fn new(http_client: HttpClient, auth_manager: Arc<RwLock<AuthManager>>) -> Self {
+#[derive(Deserialize)]
+struct ResourceProvidersResponse {
+    resource_providers: Vec<ResourceProvider>,
+}
+
+#[derive(Deserialize)]
+struct InventoriesResponse {
+    inventories: HashMap<String, Inventory>,
+}
+
+#[derive(Deserialize)]
+struct UsagesResponse {
+    usages: HashMap<String, u64>,
+}
+
+#[derive(Deserialize)]
+struct AllocationsResponse {
+    allocations: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct AllocationCandidatesResponse {
+    // Keyed by provider uuid
+    provider_summaries: HashMap<String, serde_json::Value>,
+}
+
+// 1.10 added allocation candidates
+const PLACEMENT_MICROVERSION: Microversion = Microversion(1, 10);
+
+impl PlacementService {
+    pub fn new(session: Session, microversions: &MicroversionConfig) -> Self {
         Self {
-            http_client,
-            auth_manager,
+            session,
+            microversion: ApiMicroversion::new("placement", None, PLACEMENT_MICROVERSION, microversions.placement.as_deref()),
         }
     }
     
-    pub async fn get_storage_metrics(&self) -> Result<Vec<StorageMetrics>> {
-        // Mock implementation
-        Ok(vec![
-            StorageMetrics {
-                volume_id: Uuid::new_v4().to_string(),
-                iops: 1500,
-                throughput_mbps: 125.0,
-                utilization_percent: 67.8,
-                timestamp: chrono::Utc::now(),
+    pub async fn list_resource_providers(&self) -> Result<Vec<ResourceProvider>> {
+        let response: ResourceProvidersResponse = self.get("/resource_providers").await?;
+        Ok(response.resource_providers)
+    }
+    
+    pub async fn get_inventories(&self, provider_uuid: &str) -> Result<HashMap<String, Inventory>> {
+        let response: InventoriesResponse = self.get(&format!("/resource_providers/{}/inventories", provider_uuid)).await?;
+        Ok(response.inventories)
+    }
+    
+    pub async fn get_usages(&self, provider_uuid: &str) -> Result<HashMap<String, u64>> {
+        let response: UsagesResponse = self.get(&format!("/resource_providers/{}/usages", provider_uuid)).await?;
+        Ok(response.usages)
+    }
+    
+    // Every provider with VCPU inventory, i.e. every compute node, and every
+    // one with a custom resource class, as bare-metal nodes have
+    pub async fn list_compute_usage(&self) -> Result<Vec<ProviderUsage>> {
+        let mut nodes = Vec::new();
+        for provider in self.list_resource_providers().await? {
+            let inventories = self.get_inventories(&provider.uuid).await?;
+            if !inventories.keys().any(|class| class == "VCPU" || class.starts_with("CUSTOM_")) {
+                continue;
             }
-        ])
+            let usages = self.get_usages(&provider.uuid).await?;
+            let allocations: AllocationsResponse = self
+                .get(&format!("/resource_providers/{}/allocations", provider.uuid))
+                .await?;
+            nodes.push(ProviderUsage {
+                provider,
+                inventories,
+                usages,
+                consumers: allocations.allocations.len() as u32,
+            });
+        }
+        Ok(nodes)
+    }
+    
+    // Uuids of the providers Placement would allocate these resources from,
+    // e.g. [("VCPU", 2), ("MEMORY_MB", 4096)]
+    pub async fn allocation_candidates(&self, resources: &[(&str, u64)]) -> Result<HashSet<String>> {
+        let resources = resources.iter()
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(class, amount)| format!("{}:{}", class, amount))
+            .collect::<Vec<_>>()
+            .join(",");
+        let response: AllocationCandidatesResponse = self
+            .get(&format!("/allocation_candidates?resources={}", resources))
+            .await?;
+        Ok(response.provider_summaries.into_keys().collect())
+    }
+    
+    async fn get<T: for<'de> Deserialize<'de>>(&self, path: &str) -> Result<T> {
+        let endpoint = self.session.endpoint("placement")?;
+        let headers = self.microversion.headers(&self.session, &endpoint).await?;
+        self.session.request(Method::GET, &format!("{}{}", endpoint, path), None, headers).await
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct StorageMetrics {
-    pub volume_id: String,
-    pub iops: u32,
-    pub throughput_mbps: f64,
-    pub utilization_percent: f64,
-    pub timestamp: chrono::DateTime<chrono::Utc>,
+// Neutron Service for networking
+#[derive(Clone)]
+pub struct NeutronService {
+    session: Session,
+    // Ports by MAC address with what they're attached to, rebuilt at most
+    // every PORT_INDEX_TTL
+    port_index: Arc<tokio::sync::RwLock<Option<(Instant, Arc<PortIndex>)>>>,
 }
 
-// Telemetry Service (Ceilometer/Gnocchi)
-#[derive(Clone)]
-pub struct TelemetryService {
-    http_client: HttpClient,
-    auth_manager: Arc<RwLock<AuthManager>>,
+// Collection pages and how long the port index is reused
+const NEUTRON_PAGE_SIZE: usize = 500;
+const PORT_INDEX_TTL: Duration = Duration::from_secs(60);
+
+#[derive(Default)]
+struct PortIndex {
+    ports: HashMap<String, Port>,
+    networks: HashMap<String, Network>,
+    // Router attached to each network, by network id
+    routers: HashMap<String, Router>,
+    floating_ips: HashMap<String, Vec<String>>,
 }
 
+impl NeutronService {
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            port_index: Arc::new(tokio::sync::RwLock::new(None)),
+        }
+    }
+    
+    pub async fn list_networks(&self) -> Result<Vec<Network>> {
+        self.list("networks").await
+    }
+    
+    pub async fn list_ports(&self) -> Result<Vec<Port>> {
+        self.list("ports").await
+    }
+    
+    pub async fn list_routers(&self) -> Result<Vec<Router>> {
+        self.list("routers").await
+    }
+    
+    pub async fn list_floating_ips(&self) -> Result<Vec<FloatingIp>> {
+        self.list("floatingips").await
+    }
+    
+    pub async fn get_flow_metrics(&self) -> Result<Vec<FlowMetric>> {
+        // Mock implementation - would read port-to-port flow records exported
+        // by the OVS sFlow/IPFIX collector since the previous poll
+        Ok(Vec::new())
+    }
+    
+    // Neutron keeps no traffic counters, so a server sample's vNIC counters
+    // are matched to its ports by MAC address, one record per port
+    pub async fn port_traffic(&self, metrics: &ServerMetrics) -> Result<Vec<NetworkMetrics>> {
+        if metrics.interfaces.is_empty() {
+            return Ok(Vec::new());
+        }
+        let index = self.port_index().await?;
+        
+        let mut records = Vec::new();
+        for interface in &metrics.interfaces {
+            let Some(port) = index.ports.get(&interface.mac_address.to_ascii_lowercase())
+                .filter(|port| port.device_id == metrics.server_id)
+            else {
+                debug!("No port of {} has MAC address {}", metrics.server_id, interface.mac_address);
+                continue;
+            };
+            let packets = interface.rx_packets + interface.tx_packets;
+            let dropped = interface.rx_dropped + interface.tx_dropped;
+            
+            records.push(NetworkMetrics {
+                network_id: port.network_id.clone(),
+                network_name: index.networks.get(&port.network_id).map(|network| network.name.clone()),
+                router_id: index.routers.get(&port.network_id).map(|router| router.id.clone()),
+                port_id: port.id.clone(),
+                server_id: metrics.server_id.clone(),
+                fixed_ips: port.fixed_ips.iter().map(|ip| ip.ip_address.clone()).collect(),
+                floating_ips: index.floating_ips.get(&port.id).cloned().unwrap_or_default(),
+                rx_bytes: interface.rx_bytes,
+                tx_bytes: interface.tx_bytes,
+                rx_packets: interface.rx_packets,
+                tx_packets: interface.tx_packets,
+                packet_loss: if packets + dropped > 0 { dropped as f64 / (packets + dropped) as f64 } else { 0.0 },
+                timestamp: metrics.timestamp,
+            });
+        }
+        Ok(records)
+    }
+    
+    async fn port_index(&self) -> Result<Arc<PortIndex>> {
+        if let Some((built, index)) = self.port_index.read().await.as_ref() {
+            if built.elapsed() < PORT_INDEX_TTL {
+                return Ok(index.clone());
+            }
+        }
+        
+        let mut cached = self.port_index.write().await;
+        if let Some((built, index)) = cached.as_ref() {
+            if built.elapsed() < PORT_INDEX_TTL {
+                return Ok(index.clone());
+            }
+        }
+        
+        let (ports, networks, routers, floating_ips) = tokio::try_join!(
+            self.list_ports(),
+            self.list_networks(),
+            self.list_routers(),
+            self.list_floating_ips(),
+        )?;
+        let routers: HashMap<String, Router> = routers.into_iter().map(|router| (router.id.clone(), router)).collect();
+        
+        let mut index = PortIndex::default();
+        for port in ports {
+            if port.device_owner == "network:router_interface" {
+                if let Some(router) = routers.get(&port.device_id) {
+                    index.routers.insert(port.network_id.clone(), router.clone());
+                }
+            }
+            index.ports.insert(port.mac_address.to_ascii_lowercase(), port);
+        }
+        index.networks = networks.into_iter().map(|network| (network.id.clone(), network)).collect();
+        for floating_ip in floating_ips {
+            if let Some(port_id) = floating_ip.port_id {
+                index.floating_ips.entry(port_id).or_default().push(floating_ip.floating_ip_address);
+            }
+        }
+        debug!("Indexed {} Neutron ports", index.ports.len());
+        
+        let index = Arc::new(index);
+        *cached = Some((Instant::now(), index.clone()));
+        Ok(index)
+    }
+    
+    // Every item of a collection, e.g. "ports", following Neutron's "next"
+    // links
+    async fn list<T: for<'de> Deserialize<'de>>(&self, collection: &str) -> Result<Vec<T>> {
+        let endpoint = self.session.endpoint("network")?;
+        let mut url = format!("{}/v2.0/{}?limit={}", endpoint.trim_end_matches('/'), collection, NEUTRON_PAGE_SIZE);
+        let links_key = format!("{}_links", collection);
+        
+        let mut items = Vec::new();
+        loop {
+            let mut page: serde_json::Value = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+            let page_items: Vec<T> = serde_json::from_value(page[collection].take())?;
+            let page_len = page_items.len();
+            items.extend(page_items);
+            
+            let links: Vec<Link> = serde_json::from_value(page[&links_key].take()).unwrap_or_default();
+            match links.into_iter().find(|link| link.rel == "next") {
+                Some(next) if page_len > 0 => url = next.href,
+                _ => break,
+            }
+        }
+        Ok(items)
+    }
+}
+
+// Neutron port; device_id is the owning server for compute ports
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Port {
+    pub id: String,
+    #[serde(default)]
+    pub network_id: String,
+    #[serde(default)]
+    pub device_id: String,
+    // e.g. "compute:nova" or "network:router_interface"
+    #[serde(default)]
+    pub device_owner: String,
+    #[serde(default)]
+    pub mac_address: String,
+    #[serde(default)]
+    pub fixed_ips: Vec<FixedIp>,
+}
+
+impl Port {
+    pub fn is_compute(&self) -> bool {
+        self.device_owner.starts_with("compute:")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FixedIp {
+    pub ip_address: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Network {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Router {
+    pub id: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FloatingIp {
+    pub id: String,
+    pub floating_ip_address: String,
+    // Unset while the address isn't associated
+    #[serde(default)]
+    pub port_id: Option<String>,
+}
+
+// Bytes sent from one port to another over the sampling period
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FlowMetric {
+    pub source_port_id: String,
+    pub destination_port_id: String,
+    pub bytes: u64,
+    pub duration_seconds: u64,
+}
+
+// Traffic through one port of a server, with counters since the server
+// booted as Nova reports them
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NetworkMetrics {
+    pub network_id: String,
+    pub network_name: Option<String>,
+    // Router the network is attached to
+    pub router_id: Option<String>,
+    pub port_id: String,
+    pub server_id: String,
+    pub fixed_ips: Vec<String>,
+    pub floating_ips: Vec<String>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_packets: u64,
+    // Share of packets dropped at the vNIC
+    pub packet_loss: f64,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+// Cinder Service for block storage
+#[derive(Clone)]
+pub struct CinderService {
+    session: Session,
+    microversion: ApiMicroversion,
+    // Every volume, reused for VOLUME_CACHE_TTL since each server's volumes
+    // are looked up from it
+    volumes: Arc<tokio::sync::RwLock<Option<(Instant, Arc<Vec<Volume>>)>>>,
+}
+
+const CINDER_PAGE_SIZE: usize = 500;
+const VOLUME_CACHE_TTL: Duration = Duration::from_secs(60);
+// The v3 API as first released, which the volume calls are written against
+const VOLUME_MICROVERSION: Microversion = Microversion(3, 0);
+
+impl CinderService {
+    pub fn new(session: Session, microversions: &MicroversionConfig) -> Self {
+        Self {
+            session,
+            microversion: ApiMicroversion::new("volume", None, VOLUME_MICROVERSION, microversions.volume.as_deref()),
+            volumes: Arc::new(tokio::sync::RwLock::new(None)),
+        }
+    }
+    
+    // Every project's volumes, following pages by marker
+    pub async fn list_volumes(&self) -> Result<Vec<Volume>> {
+        let endpoint = self.endpoint()?;
+        let headers = self.microversion.headers(&self.session, &endpoint).await?;
+        let mut volumes: Vec<Volume> = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let url = match &marker {
+                Some(marker) => format!("{}/volumes/detail?all_tenants=True&limit={}&marker={}", endpoint, CINDER_PAGE_SIZE, marker),
+                None => format!("{}/volumes/detail?all_tenants=True&limit={}", endpoint, CINDER_PAGE_SIZE),
+            };
+            let page: VolumesResponse = self.session.request(Method::GET, &url, None, headers.clone()).await?;
+            let more = page.volumes_links.iter().any(|link| link.rel == "next");
+            volumes.extend(page.volumes);
+            
+            match volumes.last() {
+                Some(last) if more => marker = Some(last.id.clone()),
+                _ => break,
+            }
+        }
+        debug!("Listed {} volumes from Cinder", volumes.len());
+        Ok(volumes)
+    }
+    
+    pub async fn get_volume(&self, volume_id: &str) -> Result<Volume> {
+        let endpoint = self.endpoint()?;
+        let url = format!("{}/volumes/{}", endpoint, volume_id);
+        let headers = self.microversion.headers(&self.session, &endpoint).await?;
+        let response: VolumeResponse = self.session.request(Method::GET, &url, None, headers).await?;
+        Ok(response.volume)
+    }
+    
+    // Volumes attached to the server, from the cached volume list
+    pub async fn list_server_volumes(&self, server_id: &str) -> Result<Vec<Volume>> {
+        let volumes = self.cached_volumes().await?;
+        Ok(volumes.iter()
+            .filter(|volume| volume.attachments.iter().any(|attachment| attachment.server_id == server_id))
+            .cloned()
+            .collect())
+    }
+    
+    // One record per volume attached to the server. Cinder keeps no I/O
+    // counters, so these describe the volume and where it's attached
+    pub async fn storage_metrics(&self, server_id: &str) -> Result<Vec<StorageMetrics>> {
+        let timestamp = chrono::Utc::now();
+        let volumes = self.list_server_volumes(server_id).await?;
+        Ok(volumes.iter()
+            .map(|volume| StorageMetrics {
+                volume_id: volume.id.clone(),
+                name: volume.name.clone(),
+                server_id: server_id.to_string(),
+                device: volume.attachments.iter()
+                    .find(|attachment| attachment.server_id == server_id)
+                    .and_then(|attachment| attachment.device.clone()),
+                size_gb: volume.size,
+                status: volume.status.clone(),
+                volume_type: volume.volume_type.clone(),
+                backend: volume.backend().map(str::to_string),
+                bootable: volume.is_bootable(),
+                timestamp,
+            })
+            .collect())
+    }
+    
+    // Older catalogs only list the versioned service type
+    fn endpoint(&self) -> Result<String> {
+        self.session.endpoint("block-storage").or_else(|_| self.session.endpoint("volumev3"))
+    }
+    
+    async fn cached_volumes(&self) -> Result<Arc<Vec<Volume>>> {
+        if let Some((listed, volumes)) = self.volumes.read().await.as_ref() {
+            if listed.elapsed() < VOLUME_CACHE_TTL {
+                return Ok(volumes.clone());
+            }
+        }
+        
+        let mut cached = self.volumes.write().await;
+        if let Some((listed, volumes)) = cached.as_ref() {
+            if listed.elapsed() < VOLUME_CACHE_TTL {
+                return Ok(volumes.clone());
+            }
+        }
+        let volumes = Arc::new(self.list_volumes().await?);
+        *cached = Some((Instant::now(), volumes.clone()));
+        Ok(volumes)
+    }
+}
+
+// Cinder volume as returned by /volumes/detail
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Volume {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub status: String,
+    pub size: u64,
+    #[serde(default)]
+    pub volume_type: Option<String>,
+    // "host@backend#pool"
+    #[serde(rename = "os-vol-host-attr:host", default)]
+    pub host: Option<String>,
+    // Cinder reports this as the string "true" / "false"
+    #[serde(default)]
+    pub bootable: String,
+    #[serde(default)]
+    pub attachments: Vec<VolumeAttachment>,
+}
+
+// One server a volume is attached to; multi-attach volumes have several
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct VolumeAttachment {
+    pub server_id: String,
+    // e.g. "/dev/vdb"
+    #[serde(default)]
+    pub device: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct VolumesResponse {
+    volumes: Vec<Volume>,
+    #[serde(default)]
+    volumes_links: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+struct VolumeResponse {
+    volume: Volume,
+}
+
+impl Volume {
+    pub fn backend(&self) -> Option<&str> {
+        let host = self.host.as_deref()?;
+        let backend = host.split_once('@')?.1;
+        Some(backend.split('#').next().unwrap_or(backend))
+    }
+    
+    pub fn is_bootable(&self) -> bool {
+        self.bootable.eq_ignore_ascii_case("true")
+    }
+}
+
+// A volume attached to a server
+#[derive(Debug, Serialize, Deserialize)]
+pub struct StorageMetrics {
+    pub volume_id: String,
+    pub name: Option<String>,
+    pub server_id: String,
+    pub device: Option<String>,
+    pub size_gb: u64,
+    pub status: String,
+    pub volume_type: Option<String>,
+    pub backend: Option<String>,
+    pub bootable: bool,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+// Glance Service for images
+#[derive(Clone)]
+pub struct GlanceService {
+    session: Session,
+    config: GlanceConfig,
+    // Images by id, rebuilt at most every IMAGE_CACHE_TTL
+    images: Arc<tokio::sync::RwLock<Option<(Instant, Arc<HashMap<String, Image>>)>>>,
+}
+
+const GLANCE_PAGE_SIZE: usize = 500;
+// Image properties are all but fixed once an image is uploaded
+const IMAGE_CACHE_TTL: Duration = Duration::from_secs(600);
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Image {
+    pub id: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // os_distro, os_type and whatever else the uploader set
+    #[serde(flatten)]
+    pub properties: HashMap<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct ImagesResponse {
+    images: Vec<Image>,
+    // Path of the next page, e.g. "/v2/images?marker=..."
+    #[serde(default)]
+    next: Option<String>,
+}
+
+// What a server's image says about what runs on it
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImageMetadata {
+    pub image_id: String,
+    pub image_name: Option<String>,
+    pub os_distro: Option<String>,
+    pub os_type: Option<String>,
+    pub os_version: Option<String>,
+    // e.g. "database" or "web", from the configured image property
+    pub workload_class: Option<String>,
+    pub tags: Vec<String>,
+}
+
+impl Image {
+    fn property(&self, key: &str) -> Option<String> {
+        match self.properties.get(key)? {
+            serde_json::Value::String(value) if !value.is_empty() => Some(value.clone()),
+            _ => None,
+        }
+    }
+}
+
+impl GlanceService {
+    pub fn new(session: Session, config: GlanceConfig) -> Self {
+        Self {
+            session,
+            config,
+            images: Arc::new(tokio::sync::RwLock::new(None)),
+        }
+    }
+    
+    // Every image, following Glance's "next" links
+    pub async fn list_images(&self) -> Result<Vec<Image>> {
+        let endpoint = self.session.endpoint("image")?;
+        let root = endpoint.trim_end_matches('/').trim_end_matches("/v2");
+        let mut url = format!("{}/v2/images?limit={}", root, GLANCE_PAGE_SIZE);
+        
+        let mut images = Vec::new();
+        loop {
+            let page: ImagesResponse = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+            let page_len = page.images.len();
+            images.extend(page.images);
+            match page.next {
+                Some(next) if page_len > 0 => url = format!("{}{}", root, next),
+                _ => break,
+            }
+        }
+        Ok(images)
+    }
+    
+    // Attaches each server's image metadata. Servers booted from a volume
+    // have no image and are left alone, as are servers without Glance in
+    // the catalog
+    pub async fn enrich(&self, servers: &mut [Server]) -> Result<()> {
+        if self.session.endpoint("image").is_err() || servers.iter().all(|server| server.image.is_none()) {
+            return Ok(());
+        }
+        let images = self.images().await?;
+        
+        for server in servers.iter_mut() {
+            let Some(image) = server.image.as_ref().and_then(|image| images.get(&image.id)) else {
+                continue;
+            };
+            server.image_metadata = Some(ImageMetadata {
+                image_id: image.id.clone(),
+                image_name: image.name.clone(),
+                os_distro: image.property("os_distro"),
+                os_type: image.property("os_type"),
+                os_version: image.property("os_version"),
+                workload_class: image.property(&self.config.workload_property),
+                tags: image.tags.clone(),
+            });
+        }
+        Ok(())
+    }
+    
+    async fn images(&self) -> Result<Arc<HashMap<String, Image>>> {
+        if let Some((built, images)) = self.images.read().await.as_ref() {
+            if built.elapsed() < IMAGE_CACHE_TTL {
+                return Ok(images.clone());
+            }
+        }
+        
+        let mut cached = self.images.write().await;
+        if let Some((built, images)) = cached.as_ref() {
+            if built.elapsed() < IMAGE_CACHE_TTL {
+                return Ok(images.clone());
+            }
+        }
+        
+        let images: HashMap<String, Image> = self.list_images().await?
+            .into_iter()
+            .map(|image| (image.id.clone(), image))
+            .collect();
+        debug!("Loaded {} Glance images", images.len());
+        
+        let images = Arc::new(images);
+        *cached = Some((Instant::now(), images.clone()));
+        Ok(images)
+    }
+}
+
+// Swift Service for object storage
+#[derive(Clone)]
+pub struct SwiftService {
+    session: Session,
+}
+
+// Swift's own cap on a container listing page
+const SWIFT_PAGE_SIZE: usize = 10000;
+// Containers whose quotas are read at once
+const SWIFT_CONCURRENCY: usize = 8;
+
+// Usage of the project's Swift account, for capacity planning
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ObjectStorageMetrics {
+    // e.g. "AUTH_<project id>"
+    pub account: String,
+    pub bytes_used: u64,
+    pub object_count: u64,
+    pub container_count: u64,
+    pub quota_bytes: Option<u64>,
+    pub containers: Vec<ContainerMetrics>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContainerMetrics {
+    pub name: String,
+    pub bytes_used: u64,
+    pub object_count: u64,
+    // Set through the container_quotas middleware
+    pub quota_bytes: Option<u64>,
+    pub quota_count: Option<u64>,
+}
+
+// Entry of an account listing with ?format=json
+#[derive(Deserialize, Debug)]
+struct ContainerListing {
+    name: String,
+    count: u64,
+    bytes: u64,
+}
+
+impl SwiftService {
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+    
+    // Whether the catalog has Swift at all
+    pub fn is_available(&self) -> bool {
+        self.session.endpoint("object-store").is_ok()
+    }
+    
+    // The account's totals with every container and its quotas
+    pub async fn object_storage_metrics(&self) -> Result<ObjectStorageMetrics> {
+        let endpoint = self.session.endpoint("object-store")?;
+        let account = endpoint.trim_end_matches('/').rsplit('/').next().unwrap_or_default().to_string();
+        let headers = self.session.head(&endpoint, HeaderMap::new()).await?;
+        
+        // Swift may answer the listing of an empty account with no body at all
+        let listing = match header_u64(&headers, "X-Account-Container-Count") {
+            Some(0) => Vec::new(),
+            _ => self.list_containers(&endpoint).await?,
+        };
+        let endpoint = endpoint.as_str();
+        let containers: Vec<ContainerMetrics> = stream::iter(listing)
+            .map(|container| async move {
+                let quotas = self.container_headers(endpoint, &container.name).await;
+                if let Err(e) = &quotas {
+                    debug!("Failed to read the quotas of container {}: {}", container.name, e);
+                }
+                let quotas = quotas.ok();
+                ContainerMetrics {
+                    quota_bytes: quotas.as_ref().and_then(|h| header_u64(h, "X-Container-Meta-Quota-Bytes")),
+                    quota_count: quotas.as_ref().and_then(|h| header_u64(h, "X-Container-Meta-Quota-Count")),
+                    name: container.name,
+                    bytes_used: container.bytes,
+                    object_count: container.count,
+                }
+            })
+            .buffer_unordered(SWIFT_CONCURRENCY)
+            .collect()
+            .await;
+        
+        Ok(ObjectStorageMetrics {
+            account,
+            bytes_used: header_u64(&headers, "X-Account-Bytes-Used").unwrap_or(0),
+            object_count: header_u64(&headers, "X-Account-Object-Count").unwrap_or(0),
+            container_count: header_u64(&headers, "X-Account-Container-Count").unwrap_or(containers.len() as u64),
+            quota_bytes: header_u64(&headers, "X-Account-Meta-Quota-Bytes"),
+            containers,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+    
+    // Every container in the account, following pages by marker
+    async fn list_containers(&self, endpoint: &str) -> Result<Vec<ContainerListing>> {
+        let mut containers: Vec<ContainerListing> = Vec::new();
+        loop {
+            let mut url = reqwest::Url::parse(endpoint)?;
+            url.query_pairs_mut()
+                .append_pair("format", "json")
+                .append_pair("limit", &SWIFT_PAGE_SIZE.to_string());
+            if let Some(last) = containers.last() {
+                url.query_pairs_mut().append_pair("marker", &last.name);
+            }
+            
+            let page: Vec<ContainerListing> = self.session.request(Method::GET, url.as_str(), None, HeaderMap::new()).await?;
+            let done = page.len() < SWIFT_PAGE_SIZE;
+            containers.extend(page);
+            if done {
+                break;
+            }
+        }
+        Ok(containers)
+    }
+    
+    async fn container_headers(&self, endpoint: &str, container: &str) -> Result<HeaderMap> {
+        let mut url = reqwest::Url::parse(endpoint)?;
+        url.path_segments_mut()
+            .map_err(|_| OpenStackError::ConfigError(format!("Swift endpoint {} can't take a path", endpoint)))?
+            .pop_if_empty()
+            .push(container);
+        self.session.head(url.as_str(), HeaderMap::new()).await
+    }
+}
+
+// A live migration Nova would refuse, reported as the conflict it would
+// answer with so that it isn't retried
+fn precheck_failed(message: String) -> OpenStackError {
+    OpenStackError::ApiError {
+        status: 409,
+        message,
+        retry_after: None,
+    }
+}
+
+fn header_u64(headers: &HeaderMap, name: &str) -> Option<u64> {
+    headers.get(name)?.to_str().ok()?.parse().ok()
+}
+
+// Octavia Service for load balancing
+#[derive(Clone)]
+pub struct OctaviaService {
+    session: Session,
+}
+
+const OCTAVIA_PAGE_SIZE: usize = 500;
+// Listeners whose stats are read at once
+const OCTAVIA_CONCURRENCY: usize = 8;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct LoadBalancer {
+    pub id: String,
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub project_id: Option<String>,
+    pub provisioning_status: String,
+    pub operating_status: String,
+}
+
+// Counters since the amphora or listener was created
+#[derive(Deserialize, Serialize, Debug, Clone, Default)]
+pub struct LoadBalancerStats {
+    pub active_connections: u64,
+    pub total_connections: u64,
+    pub bytes_in: u64,
+    pub bytes_out: u64,
+    #[serde(default)]
+    pub request_errors: u64,
+}
+
+#[derive(Deserialize)]
+struct StatsResponse {
+    stats: LoadBalancerStats,
+}
+
+// The status tree of a load balancer down to its members
+#[derive(Deserialize)]
+struct StatusResponse {
+    statuses: StatusTree,
+}
+
+#[derive(Deserialize)]
+struct StatusTree {
+    loadbalancer: LoadBalancerStatus,
+}
+
+#[derive(Deserialize)]
+struct LoadBalancerStatus {
+    #[serde(default)]
+    listeners: Vec<ListenerStatus>,
+}
+
+#[derive(Deserialize)]
+struct ListenerStatus {
+    id: String,
+    #[serde(default)]
+    name: String,
+    operating_status: String,
+    #[serde(default)]
+    pools: Vec<PoolStatus>,
+}
+
+#[derive(Deserialize)]
+struct PoolStatus {
+    id: String,
+    #[serde(default)]
+    name: String,
+    operating_status: String,
+    #[serde(default)]
+    members: Vec<MemberHealth>,
+}
+
+#[derive(Deserialize, Serialize, Debug, Clone)]
+pub struct MemberHealth {
+    pub id: String,
+    pub address: String,
+    pub protocol_port: u16,
+    pub operating_status: String,
+}
+
+impl MemberHealth {
+    // NO_MONITOR members have no health check and are assumed to serve
+    pub fn is_healthy(&self) -> bool {
+        matches!(self.operating_status.as_str(), "ONLINE" | "NO_MONITOR")
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadBalancerMetrics {
+    pub loadbalancer_id: String,
+    pub name: String,
+    pub project_id: Option<String>,
+    pub operating_status: String,
+    pub stats: LoadBalancerStats,
+    pub listeners: Vec<ListenerMetrics>,
+    pub pools: Vec<PoolMetrics>,
+    // Share of pools with a healthy member, zero while the load balancer
+    // itself is down; None without any pools
+    pub availability_percent: Option<f64>,
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ListenerMetrics {
+    pub listener_id: String,
+    pub name: String,
+    pub operating_status: String,
+    pub stats: LoadBalancerStats,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PoolMetrics {
+    pub pool_id: String,
+    pub name: String,
+    pub operating_status: String,
+    pub members_total: usize,
+    pub members_online: usize,
+    pub members: Vec<MemberHealth>,
+}
+
+impl OctaviaService {
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+    
+    // Whether the catalog has Octavia at all
+    pub fn is_available(&self) -> bool {
+        self.session.endpoint("load-balancer").is_ok()
+    }
+    
+    // Every load balancer, following Octavia's "next" links
+    pub async fn list_load_balancers(&self) -> Result<Vec<LoadBalancer>> {
+        let mut url = format!("{}/loadbalancers?limit={}", self.endpoint()?, OCTAVIA_PAGE_SIZE);
+        
+        let mut load_balancers = Vec::new();
+        loop {
+            let mut page: serde_json::Value = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+            let items: Vec<LoadBalancer> = serde_json::from_value(page["loadbalancers"].take())?;
+            let page_len = items.len();
+            load_balancers.extend(items);
+            
+            let links: Vec<Link> = serde_json::from_value(page["loadbalancers_links"].take()).unwrap_or_default();
+            match links.into_iter().find(|link| link.rel == "next") {
+                Some(next) if page_len > 0 => url = next.href,
+                _ => break,
+            }
+        }
+        Ok(load_balancers)
+    }
+    
+    pub async fn load_balancer_stats(&self, loadbalancer_id: &str) -> Result<LoadBalancerStats> {
+        let url = format!("{}/loadbalancers/{}/stats", self.endpoint()?, loadbalancer_id);
+        let response: StatsResponse = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+        Ok(response.stats)
+    }
+    
+    pub async fn listener_stats(&self, listener_id: &str) -> Result<LoadBalancerStats> {
+        let url = format!("{}/listeners/{}/stats", self.endpoint()?, listener_id);
+        let response: StatsResponse = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+        Ok(response.stats)
+    }
+    
+    // Traffic of the load balancer and each listener, with the health of
+    // every pool member
+    pub async fn load_balancer_metrics(&self, load_balancer: &LoadBalancer) -> Result<LoadBalancerMetrics> {
+        let url = format!("{}/loadbalancers/{}/status", self.endpoint()?, load_balancer.id);
+        let (stats, status) = tokio::try_join!(
+            self.load_balancer_stats(&load_balancer.id),
+            self.session.request::<StatusResponse>(Method::GET, &url, None, HeaderMap::new()),
+        )?;
+        let listener_statuses = status.statuses.loadbalancer.listeners;
+        
+        let listeners: Vec<ListenerMetrics> = stream::iter(&listener_statuses)
+            .map(|listener| async move {
+                let stats = self.listener_stats(&listener.id).await;
+                if let Err(e) = &stats {
+                    debug!("Failed to read the stats of listener {}: {}", listener.id, e);
+                }
+                ListenerMetrics {
+                    listener_id: listener.id.clone(),
+                    name: listener.name.clone(),
+                    operating_status: listener.operating_status.clone(),
+                    stats: stats.unwrap_or_default(),
+                }
+            })
+            .buffer_unordered(OCTAVIA_CONCURRENCY)
+            .collect()
+            .await;
+        
+        // A pool serving several listeners appears under each of them
+        let mut seen = HashSet::new();
+        let pools: Vec<PoolMetrics> = listener_statuses.into_iter()
+            .flat_map(|listener| listener.pools)
+            .filter(|pool| seen.insert(pool.id.clone()))
+            .map(|pool| PoolMetrics {
+                members_total: pool.members.len(),
+                members_online: pool.members.iter().filter(|member| member.is_healthy()).count(),
+                pool_id: pool.id,
+                name: pool.name,
+                operating_status: pool.operating_status,
+                members: pool.members,
+            })
+            .collect();
+        
+        let down = matches!(load_balancer.operating_status.as_str(), "ERROR" | "OFFLINE")
+            || load_balancer.provisioning_status == "ERROR";
+        let availability_percent = if down {
+            Some(0.0)
+        } else if pools.is_empty() {
+            None
+        } else {
+            let serving = pools.iter().filter(|pool| pool.members_online > 0).count();
+            Some(serving as f64 / pools.len() as f64 * 100.0)
+        };
+        
+        Ok(LoadBalancerMetrics {
+            loadbalancer_id: load_balancer.id.clone(),
+            name: load_balancer.name.clone(),
+            project_id: load_balancer.project_id.clone(),
+            operating_status: load_balancer.operating_status.clone(),
+            stats,
+            listeners,
+            pools,
+            availability_percent,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+    
+    fn endpoint(&self) -> Result<String> {
+        let endpoint = self.session.endpoint("load-balancer")?;
+        Ok(format!("{}/v2/lbaas", endpoint.trim_end_matches('/')))
+    }
+}
+
+// Telemetry Service (Ceilometer/Gnocchi)
+#[derive(Clone)]
+pub struct TelemetryService {
+    session: Session,
+    config: GnocchiConfig,
+}
+
+// Gnocchi resource; for instances the id is the server's
+#[derive(Debug, Clone, Deserialize)]
+pub struct GnocchiResource {
+    pub id: String,
+    #[serde(rename = "type", default)]
+    pub resource_type: String,
+    // Metric name to metric id
+    #[serde(default)]
+    pub metrics: HashMap<String, String>,
+    #[serde(default)]
+    pub ended_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GnocchiMetric {
+    pub id: String,
+    pub name: String,
+    #[serde(default)]
+    pub unit: Option<String>,
+}
+
+// One aggregated point: timestamp, granularity in seconds, value
+#[derive(Debug, Clone, Deserialize)]
+pub struct Measure(pub chrono::DateTime<chrono::Utc>, pub f64, pub f64);
+
+// Which measures to fetch; Gnocchi only has granularities its archive
+// policy keeps
+#[derive(Debug, Clone)]
+pub struct MeasuresQuery {
+    pub start: chrono::DateTime<chrono::Utc>,
+    pub granularity: Option<u64>,
+    pub aggregation: String,
+}
+
+// Resources per search request
+const GNOCCHI_PAGE_SIZE: usize = 500;
+// Resources whose history is fetched at once when seeding
+const GNOCCHI_CONCURRENCY: usize = 8;
+
 impl TelemetryService {
-    pub fn new(http_client: HttpClient, auth_manager: Arc<RwLock<AuthManager>>) -> Self {
+    pub fn new(session: Session, config: GnocchiConfig) -> Self {
         Self {
-            http_client,
-            auth_manager,
+            session,
+            config,
         }
     }
     
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Every resource of resource_type matching a Gnocchi search filter, e.g.
+    // {"=": {"ended_at": null}}, following pages by marker
+    pub async fn search_resources(&self, resource_type: &str, filter: serde_json::Value) -> Result<Vec<GnocchiResource>> {
+        let endpoint = self.session.endpoint("metric")?;
+        let mut resources: Vec<GnocchiResource> = Vec::new();
+        let mut marker: Option<String> = None;
+        loop {
+            let mut url = format!(
+                "{}/v1/search/resource/{}?limit={}&sort=id:asc",
+                endpoint, resource_type, GNOCCHI_PAGE_SIZE
+            );
+            if let Some(marker) = &marker {
+                url.push_str(&format!("&marker={}", marker));
+            }
+            let page: Vec<GnocchiResource> = self.session
+                .request(Method::POST, &url, Some(filter.clone()), HeaderMap::new())
+                .await?;
+            let full = page.len() == GNOCCHI_PAGE_SIZE;
+            resources.extend(page);
+            
+            match resources.last() {
+                Some(last) if full => marker = Some(last.id.clone()),
+                _ => break,
+            }
+        }
+        Ok(resources)
+    }
+    
+    pub async fn list_metrics(&self, resource_id: &str) -> Result<Vec<GnocchiMetric>> {
+        let endpoint = self.session.endpoint("metric")?;
+        let url = format!("{}/v1/resource/generic/{}/metric", endpoint, resource_id);
+        self.session.request(Method::GET, &url, None, HeaderMap::new()).await
+    }
+    
+    pub async fn get_measures(&self, metric_id: &str, query: &MeasuresQuery) -> Result<Vec<Measure>> {
+        let endpoint = self.session.endpoint("metric")?;
+        let mut url = format!(
+            "{}/v1/metric/{}/measures?start={}&aggregation={}",
+            endpoint,
+            metric_id,
+            query.start.format("%Y-%m-%dT%H:%M:%SZ"),
+            query.aggregation,
+        );
+        if let Some(granularity) = query.granularity {
+            url.push_str(&format!("&granularity={}", granularity));
+        }
+        self.session.request(Method::GET, &url, None, HeaderMap::new()).await
+    }
+    
+    // The latest mean of each of the resource's metrics over the last hour
     pub async fn get_resource_metrics(&self, resource_id: &str) -> Result<Vec<TelemetryMetric>> {
-        // Mock implementation - would integrate with Gnocchi API
-        Ok(vec![
-            TelemetryMetric {
-                resource_id: resource_id.to_string(),
-                metric_name: "cpu_util".to_string(),
-                value: 45.2,
-                unit: "percent".to_string(),
-                timestamp: chrono::Utc::now(),
-            },
-            TelemetryMetric {
-                resource_id: resource_id.to_string(),
-                metric_name: "memory.usage".to_string(),
-                value: 2048.0,
-                unit: "MB".to_string(),
-                timestamp: chrono::Utc::now(),
-            },
-        ])
+        let query = MeasuresQuery {
+            start: chrono::Utc::now() - chrono::Duration::hours(1),
+            granularity: None,
+            aggregation: "mean".to_string(),
+        };
+        
+        let mut metrics = Vec::new();
+        for metric in self.list_metrics(resource_id).await? {
+            let measures = self.get_measures(&metric.id, &query).await?;
+            if let Some(Measure(timestamp, _, value)) = measures.into_iter().max_by_key(|measure| measure.0) {
+                metrics.push(TelemetryMetric {
+                    resource_id: resource_id.to_string(),
+                    metric_name: metric.name,
+                    value,
+                    unit: metric.unit.unwrap_or_default(),
+                    timestamp,
+                });
+            }
+        }
+        Ok(metrics)
+    }
+    
+    // CPU utilisation history of every live instance since start, as the
+    // percent of its vCPUs in use over each granularity period
+    pub async fn cpu_history(
+        &self,
+        start: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<(String, Vec<(chrono::DateTime<chrono::Utc>, f64)>)>> {
+        let instances = self.search_resources("instance", serde_json::json!({"=": {"ended_at": null}})).await?;
+        debug!("Fetching CPU history for {} Gnocchi instances", instances.len());
+        
+        let histories: Vec<_> = stream::iter(instances)
+            .map(|instance| async move {
+                let history = self.instance_cpu_history(&instance, start).await;
+                (instance.id, history)
+            })
+            .buffer_unordered(GNOCCHI_CONCURRENCY)
+            .collect()
+            .await;
+        
+        let mut result = Vec::new();
+        for (id, history) in histories {
+            match history {
+                Ok(points) if !points.is_empty() => result.push((id, points)),
+                Ok(_) => {}
+                Err(e) => warn!("Failed to fetch CPU history of {} from Gnocchi: {}", id, e),
+            }
+        }
+        Ok(result)
+    }
+    
+    // The CPU metric is cumulative nanoseconds, so its rate over a period
+    // divided by the period and the vCPU count is the utilisation
+    async fn instance_cpu_history(
+        &self,
+        instance: &GnocchiResource,
+        start: chrono::DateTime<chrono::Utc>,
+    ) -> Result<Vec<(chrono::DateTime<chrono::Utc>, f64)>> {
+        let Some(cpu_metric) = instance.metrics.get(&self.config.cpu_metric) else {
+            return Ok(Vec::new());
+        };
+        let granularity = self.config.granularity_seconds;
+        
+        let vcpus = match instance.metrics.get("vcpus") {
+            Some(metric) => {
+                let query = MeasuresQuery { start, granularity: None, aggregation: "mean".to_string() };
+                self.get_measures(metric, &query).await?
+                    .into_iter()
+                    .max_by_key(|measure| measure.0)
+                    .map_or(1.0, |Measure(_, _, value)| value.max(1.0))
+            }
+            None => 1.0,
+        };
+        
+        let query = MeasuresQuery {
+            start,
+            granularity: Some(granularity),
+            aggregation: "rate:mean".to_string(),
+        };
+        let period_ns = granularity as f64 * 1e9;
+        Ok(self.get_measures(cpu_metric, &query).await?
+            .into_iter()
+            .map(|Measure(timestamp, _, value)| (timestamp, (value / period_ns / vcpus * 100.0).clamp(0.0, 100.0)))
+            .collect())
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
-pub struct TelemetryMetric {
+// Aodh Service for alarming
+#[derive(Clone)]
+pub struct AodhService {
+    session: Session,
+}
+
+const AODH_PAGE_SIZE: usize = 500;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Alarm {
+    pub alarm_id: String,
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    pub enabled: bool,
+    // "ok", "alarm" or "insufficient data"
+    pub state: String,
+    // "low", "moderate" or "critical"
+    #[serde(default)]
+    pub severity: String,
+    #[serde(default)]
+    pub state_timestamp: Option<String>,
+    #[serde(default)]
+    pub gnocchi_resources_threshold_rule: Option<ThresholdRule>,
+}
+
+// Compares a Gnocchi metric of one resource with a threshold
+#[derive(Deserialize, Serialize, Debug, Clone, PartialEq)]
+pub struct ThresholdRule {
+    pub metric: String,
+    pub resource_type: String,
     pub resource_id: String,
-    pub metric_name: String,
-    pub value: f64,
-    pub unit: String,
+    pub aggregation_method: String,
+    pub granularity: u64,
+    pub evaluation_periods: u32,
+    pub threshold: f64,
+    // "gt", "ge", "lt", ...
+    pub comparison_operator: String,
+}
+
+// What we create or update an alarm with
+#[derive(Serialize, Debug, Clone)]
+pub struct AlarmDefinition {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub alarm_type: &'static str,
+    pub description: String,
+    pub severity: String,
+    pub enabled: bool,
+    pub gnocchi_resources_threshold_rule: ThresholdRule,
+}
+
+impl Alarm {
+    pub fn is_firing(&self) -> bool {
+        self.enabled && self.state == "alarm"
+    }
+    
+    pub fn resource_id(&self) -> Option<&str> {
+        self.gnocchi_resources_threshold_rule.as_ref().map(|rule| rule.resource_id.as_str())
+    }
+    
+    // Whether the alarm already matches the definition
+    pub fn matches(&self, definition: &AlarmDefinition) -> bool {
+        self.description == definition.description
+            && self.severity == definition.severity
+            && self.enabled == definition.enabled
+            && self.gnocchi_resources_threshold_rule.as_ref() == Some(&definition.gnocchi_resources_threshold_rule)
+    }
+}
+
+impl AodhService {
+    pub fn new(session: Session) -> Self {
+        Self { session }
+    }
+    
+    // Whether the catalog has Aodh at all
+    pub fn is_available(&self) -> bool {
+        self.session.endpoint("alarming").is_ok()
+    }
+    
+    // Every alarm the project can see, following pages by marker
+    pub async fn list_alarms(&self) -> Result<Vec<Alarm>> {
+        let endpoint = self.endpoint()?;
+        let mut alarms: Vec<Alarm> = Vec::new();
+        loop {
+            let mut url = format!("{}/alarms?limit={}&sort=alarm_id:asc", endpoint, AODH_PAGE_SIZE);
+            if let Some(last) = alarms.last() {
+                url.push_str(&format!("&marker={}", last.alarm_id));
+            }
+            let page: Vec<Alarm> = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+            let done = page.len() < AODH_PAGE_SIZE;
+            alarms.extend(page);
+            if done {
+                break;
+            }
+        }
+        Ok(alarms)
+    }
+    
+    pub async fn create_alarm(&self, definition: &AlarmDefinition) -> Result<Alarm> {
+        let url = format!("{}/alarms", self.endpoint()?);
+        self.session.request(Method::POST, &url, Some(serde_json::to_value(definition)?), HeaderMap::new()).await
+    }
+    
+    // Replaces the alarm's definition as a whole
+    pub async fn update_alarm(&self, alarm_id: &str, definition: &AlarmDefinition) -> Result<Alarm> {
+        let url = format!("{}/alarms/{}", self.endpoint()?, alarm_id);
+        self.session.request(Method::PUT, &url, Some(serde_json::to_value(definition)?), HeaderMap::new()).await
+    }
+    
+    pub async fn delete_alarm(&self, alarm_id: &str) -> Result<()> {
+        let url = format!("{}/alarms/{}", self.endpoint()?, alarm_id);
+        self.session.execute(Method::DELETE, &url, None, HeaderMap::new()).await
+    }
+    
+    // Regions may share one Aodh
+    pub fn endpoint(&self) -> Result<String> {
+        let endpoint = self.session.endpoint("alarming")?;
+        Ok(format!("{}/v2", endpoint.trim_end_matches('/')))
+    }
+}
+
+// Ironic Service for bare-metal nodes and their power
+#[derive(Clone)]
+pub struct IronicService {
+    session: Session,
+    microversion: ApiMicroversion,
+}
+
+const IRONIC_PAGE_SIZE: usize = 500;
+// The first that reports a node's resource_class
+const IRONIC_MICROVERSION: Microversion = Microversion(1, 21);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PowerTarget {
+    #[serde(rename = "power on")]
+    PowerOn,
+    #[serde(rename = "power off")]
+    PowerOff,
+    #[serde(rename = "soft power off")]
+    SoftPowerOff,
+}
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct BareMetalNode {
+    pub uuid: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    // "power on" or "power off"; None until Ironic has read it
+    #[serde(default)]
+    pub power_state: Option<String>,
+    pub provision_state: String,
+    #[serde(default)]
+    pub maintenance: bool,
+    // Nova schedules the node by this, as CUSTOM_<class> in Placement
+    #[serde(default)]
+    pub resource_class: Option<String>,
+    // The server deployed on the node
+    #[serde(default)]
+    pub instance_uuid: Option<String>,
+    #[serde(default)]
+    pub properties: serde_json::Map<String, serde_json::Value>,
+}
+
+#[derive(Deserialize)]
+struct NodesResponse {
+    nodes: Vec<BareMetalNode>,
+    // Url of the next page, when there is one
+    #[serde(default)]
+    next: Option<String>,
+}
+
+impl BareMetalNode {
+    // Available to deploy on, or already running an instance, with a BMC
+    // Ironic can reach
+    pub fn is_schedulable(&self) -> bool {
+        !self.maintenance
+            && self.power_state.is_some()
+            && matches!(self.provision_state.as_str(), "available" | "active")
+    }
+    
+    // The resource class as Placement names it
+    pub fn placement_class(&self) -> Option<String> {
+        let class = self.resource_class.as_deref()?;
+        let normalized: String = class.chars()
+            .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_uppercase() } else { '_' })
+            .collect();
+        Some(format!("CUSTOM_{}", normalized))
+    }
+    
+    // Introspection fills these in, as numbers or as strings of them
+    pub fn property(&self, key: &str) -> u64 {
+        match self.properties.get(key) {
+            Some(serde_json::Value::Number(n)) => n.as_u64().unwrap_or(0),
+            Some(serde_json::Value::String(s)) => s.parse().unwrap_or(0),
+            _ => 0,
+        }
+    }
+}
+
+impl IronicService {
+    pub fn new(session: Session, microversions: &MicroversionConfig) -> Self {
+        Self {
+            session,
+            microversion: ApiMicroversion::new(
+                "baremetal",
+                Some("X-OpenStack-Ironic-API-Version"),
+                IRONIC_MICROVERSION,
+                microversions.baremetal.as_deref(),
+            ),
+        }
+    }
+    
+    // Whether the catalog has Ironic at all
+    pub fn is_available(&self) -> bool {
+        self.session.endpoint("baremetal").is_ok()
+    }
+    
+    // Every node with its details, following Ironic's "next" links
+    pub async fn list_nodes(&self) -> Result<Vec<BareMetalNode>> {
+        let endpoint = self.session.endpoint("baremetal")?;
+        let endpoint = endpoint.trim_end_matches('/').trim_end_matches("/v1");
+        let mut url = format!("{}/v1/nodes/detail?limit={}", endpoint, IRONIC_PAGE_SIZE);
+        // The root document lists the microversions of v1
+        let headers = self.microversion.headers(&self.session, endpoint).await?;
+        
+        let mut nodes = Vec::new();
+        loop {
+            let page: NodesResponse = self.session.request(Method::GET, &url, None, headers.clone()).await?;
+            let page_len = page.nodes.len();
+            nodes.extend(page.nodes);
+            match page.next {
+                Some(next) if page_len > 0 => url = next,
+                _ => break,
+            }
+        }
+        Ok(nodes)
+    }
+    
+    pub async fn set_power_state(&self, node_id: &str, target: PowerTarget) -> Result<()> {
+        // Mock implementation - would PUT /v1/nodes/{node_id}/states/power
+        // with {"target": target}; Ironic falls back to IPMI for the actual switch
+        info!("Requesting power state {:?} for bare-metal node {}", target, node_id);
+        Ok(())
+    }
+    
+    pub async fn get_power_reading(&self, node_id: &str) -> Result<PowerReading> {
+        // Mock implementation - would GET /v1/nodes/{node_id}/management/indicators
+        // power sensor data, which Ironic reads over Redfish or IPMI DCMI
+        Ok(PowerReading {
+            node_id: node_id.to_string(),
+            watts: 245.0,
+            timestamp: chrono::Utc::now(),
+        })
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PowerReading {
+    pub node_id: String,
+    pub watts: f64,
     pub timestamp: chrono::DateTime<chrono::Utc>,
 }
+
+// Autoscaling group as exposed by Senlin clusters or Heat scaling groups
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScalingGroup {
+    pub id: String,
+    pub name: String,
+    pub desired_capacity: u32,
+    pub min_size: u32,
+    pub max_size: u32,
+}
+
+// Senlin Service for clustering
+#[derive(Clone)]
+pub struct SenlinService {
+    http_client: HttpClient,
+    auth_manager: Arc<AuthManager>,
+}
+
+impl SenlinService {
+    pub fn new(http_client: HttpClient, auth_manager: Arc<AuthManager>) -> Self {
+        Self {
+            http_client,
+            auth_manager,
+        }
+    }
+    
+    pub async fn get_cluster(&self, cluster_id: &str) -> Result<ScalingGroup> {
+        // Mock implementation - would GET /v1/clusters/{cluster_id}
+        Ok(ScalingGroup {
+            id: cluster_id.to_string(),
+            name: format!("cluster-{}", cluster_id),
+            desired_capacity: 2,
+            min_size: 1,
+            max_size: 10,
+        })
+    }
+    
+    pub async fn resize_cluster(&self, cluster_id: &str, desired_capacity: u32) -> Result<()> {
+        // Mock implementation - would POST /v1/clusters/{cluster_id}/actions
+        // with {"resize": {"adjustment_type": "EXACT_CAPACITY", "number": desired_capacity}}
+        info!("Resizing Senlin cluster {} to {} nodes", cluster_id, desired_capacity);
+        Ok(())
+    }
+}
+
+// Heat Service for orchestration
+#[derive(Clone)]
+pub struct HeatService {
+    session: Session,
+    // Lists every project's stacks rather than just our own
+    all_projects: bool,
+    // Stack of each server, rebuilt at most every STACK_CACHE_TTL
+    membership: Arc<tokio::sync::RwLock<Option<(Instant, Arc<HashMap<String, StackRef>>)>>>,
+    // Held for a rebuild, which walks every stack, so there is one at a time
+    rebuilding: Arc<tokio::sync::Mutex<()>>,
+}
+
+const HEAT_PAGE_SIZE: usize = 500;
+// Stacks change rarely and walking their resources is expensive
+const STACK_CACHE_TTL: Duration = Duration::from_secs(300);
+// Deep enough for servers inside scaling groups inside nested templates
+const STACK_NESTED_DEPTH: u32 = 3;
+const HEAT_CONCURRENCY: usize = 8;
+
+#[derive(Deserialize, Debug, Clone)]
+pub struct Stack {
+    pub id: String,
+    pub stack_name: String,
+}
+
+#[derive(Deserialize)]
+struct StacksResponse {
+    stacks: Vec<Stack>,
+}
+
+// Entry of a stack's resource list
+#[derive(Deserialize, Debug, Clone)]
+pub struct StackResource {
+    pub resource_type: String,
+    // The server id for OS::Nova::Server; empty until it's created
+    #[serde(default)]
+    pub physical_resource_id: String,
+}
+
+#[derive(Deserialize)]
+struct StackResourcesResponse {
+    resources: Vec<StackResource>,
+}
+
+// The top-level stack a server was created by
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StackRef {
+    pub id: String,
+    pub name: String,
+}
+
+impl HeatService {
+    pub fn new(session: Session, all_projects: bool) -> Self {
+        Self {
+            session,
+            all_projects,
+            membership: Arc::new(tokio::sync::RwLock::new(None)),
+            rebuilding: Arc::new(tokio::sync::Mutex::new(())),
+        }
+    }
+    
+    // Top-level stacks, following pages by marker
+    pub async fn list_stacks(&self) -> Result<Vec<Stack>> {
+        let endpoint = self.session.endpoint("orchestration")?;
+        let mut query = format!("limit={}", HEAT_PAGE_SIZE);
+        if self.all_projects {
+            query.push_str("&global_tenant=True");
+        }
+        
+        let mut stacks: Vec<Stack> = Vec::new();
+        loop {
+            let url = match stacks.last() {
+                Some(last) => format!("{}/stacks?{}&marker={}", endpoint, query, last.id),
+                None => format!("{}/stacks?{}", endpoint, query),
+            };
+            let page: StacksResponse = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+            let done = page.stacks.len() < HEAT_PAGE_SIZE;
+            stacks.extend(page.stacks);
+            if done {
+                break;
+            }
+        }
+        Ok(stacks)
+    }
+    
+    // The stack's resources, those of its nested stacks included
+    pub async fn list_stack_resources(&self, stack: &Stack) -> Result<Vec<StackResource>> {
+        let endpoint = self.session.endpoint("orchestration")?;
+        let url = format!(
+            "{}/stacks/{}/{}/resources?nested_depth={}",
+            endpoint, stack.stack_name, stack.id, STACK_NESTED_DEPTH
+        );
+        let response: StackResourcesResponse = self.session.request(Method::GET, &url, None, HeaderMap::new()).await?;
+        Ok(response.resources)
+    }
+    
+    // Server id to the stack it belongs to; empty without Heat in the catalog.
+    // While a rebuild is under way others get the stale membership, if any
+    pub async fn stack_membership(&self) -> Result<Arc<HashMap<String, StackRef>>> {
+        let cached = self.membership.read().await.clone();
+        if let Some((built, membership)) = &cached {
+            if built.elapsed() < STACK_CACHE_TTL {
+                return Ok(membership.clone());
+            }
+        }
+        
+        let _rebuilding = match self.rebuilding.try_lock() {
+            Ok(guard) => guard,
+            Err(_) => match cached {
+                Some((_, membership)) => return Ok(membership),
+                None => self.rebuilding.lock().await,
+            },
+        };
+        // Rebuilt by whoever held the lock before
+        if let Some((built, membership)) = self.membership.read().await.as_ref() {
+            if built.elapsed() < STACK_CACHE_TTL {
+                return Ok(membership.clone());
+            }
+        }
+        
+        let membership = Arc::new(self.walk_stacks().await?);
+        *self.membership.write().await = Some((Instant::now(), membership.clone()));
+        Ok(membership)
+    }
+    
+    async fn walk_stacks(&self) -> Result<HashMap<String, StackRef>> {
+        let mut membership = HashMap::new();
+        if self.session.endpoint("orchestration").is_ok() {
+            let stacks = self.list_stacks().await?;
+            let resources: Vec<(Stack, Result<Vec<StackResource>>)> = stream::iter(stacks)
+                .map(|stack| async move {
+                    let resources = self.list_stack_resources(&stack).await;
+                    (stack, resources)
+                })
+                .buffer_unordered(HEAT_CONCURRENCY)
+                .collect()
+                .await;
+            
+            for (stack, resources) in resources {
+                let resources = match resources {
+                    Ok(resources) => resources,
+                    Err(e) => {
+                        warn!("Failed to list the resources of stack {}: {}", stack.stack_name, e);
+                        continue;
+                    }
+                };
+                let stack_ref = StackRef {
+                    id: stack.id.clone(),
+                    name: stack.stack_name.clone(),
+                };
+                for resource in resources {
+                    if resource.resource_type == "OS::Nova::Server" && !resource.physical_resource_id.is_empty() {
+                        membership.insert(resource.physical_resource_id, stack_ref.clone());
+                    }
+                }
+            }
+            debug!("{} servers belong to Heat stacks", membership.len());
+        }
+        Ok(membership)
+    }
+    
+    pub async fn get_scaling_group(&self, group_stack_id: &str) -> Result<ScalingGroup> {
+        // Mock implementation - would read the OS::Heat::AutoScalingGroup
+        // nested stack and its min_size/max_size/desired_capacity properties
+        Ok(ScalingGroup {
+            id: group_stack_id.to_string(),
+            name: format!("asg-{}", group_stack_id),
+            desired_capacity: 2,
+            min_size: 1,
+            max_size: 10,
+        })
+    }
+    
+    pub async fn resize_scaling_group(&self, group_stack_id: &str, desired_capacity: u32) -> Result<()> {
+        // Mock implementation - would PATCH the parent stack with the new
+        // desired_capacity parameter
+        info!("Resizing Heat scaling group {} to {} members", group_stack_id, desired_capacity);
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{Semaphore, SemaphorePermit};
+
+use crate::config::ThrottleConfig;
+
+// Paces one client's API calls: a token bucket bounds the rate and a
+// semaphore the calls in flight
+pub struct Throttle {
+    // None without a rate limit
+    bucket: Option<Mutex<Bucket>>,
+    refill_per_second: f64,
+    capacity: f64,
+    in_flight: Semaphore,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+impl Throttle {
+    pub fn new(config: &ThrottleConfig) -> Self {
+        let capacity = config.burst.max(1) as f64;
+        Self {
+            bucket: (config.requests_per_second > 0.0).then(|| Mutex::new(Bucket {
+                tokens: capacity,
+                updated: Instant::now(),
+            })),
+            refill_per_second: config.requests_per_second,
+            capacity,
+            in_flight: Semaphore::new(config.max_concurrent_requests.max(1) as usize),
+        }
+    }
+    
+    // Waits for a token, then for a free slot, which is held until the
+    // permit is dropped
+    pub async fn acquire(&self) -> SemaphorePermit<'_> {
+        while let Some(wait) = self.take_token() {
+            ::metrics::counter!("openstack_requests_throttled_total").increment(1);
+            tokio::time::sleep(wait).await;
+        }
+        self.in_flight.acquire().await.expect("the semaphore is never closed")
+    }
+    
+    // None once a token is taken, otherwise how long until one is due
+    fn take_token(&self) -> Option<Duration> {
+        let mut bucket = self.bucket.as_ref()?.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        bucket.updated = now;
+        
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            None
+        } else {
+            Some(Duration::from_secs_f64((1.0 - bucket.tokens) / self.refill_per_second))
+        }
+    }
+}
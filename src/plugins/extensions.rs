@@ -0,0 +1,7 @@
+use super::Registry;
+
+// Integrations kept out of the core build. Each lives in its own module here
+// behind a cargo feature of the same name, and its register function is
+// called below under the same cfg, so a build without the feature doesn't
+// compile it at all.
+pub fn register(_registry: &mut Registry) {}
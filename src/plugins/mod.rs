@@ -0,0 +1,245 @@
+pub mod extensions;
+
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use async_trait::async_trait;
+use futures_util::future::BoxFuture;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use crate::config::{MLConfig, MetricsConfig, PlacementConfig, PluginConfig};
+use crate::metrics::lifecycle::LifecycleEvent;
+use crate::ml::models::TimeSeriesData;
+use crate::openstack::services::{LoadBalancerMetrics, NetworkMetrics, ObjectStorageMetrics, ServerMetrics, StorageMetrics};
+use crate::scheduler::filters::{HostFilter, HostWeigher};
+use crate::scheduler::scoring::ScoringStrategy;
+
+// Collects server samples from somewhere other than Nova, polled on the
+// compute interval alongside the built-in collection
+#[async_trait]
+pub trait MetricSource: Send + Sync {
+    fn name(&self) -> &str;
+    
+    async fn collect(&self) -> Result<Vec<ServerMetrics>>;
+}
+
+// Receives every collected sample
+#[async_trait]
+pub trait MetricSink: Send + Sync {
+    fn name(&self) -> &str;
+    
+    async fn publish_server(&self, metrics: &ServerMetrics) -> Result<()>;
+    
+    async fn publish_network(&self, _metrics: &NetworkMetrics) -> Result<()> {
+        Ok(())
+    }
+    
+    async fn publish_storage(&self, _metrics: &StorageMetrics) -> Result<()> {
+        Ok(())
+    }
+    
+    async fn publish_object_storage(&self, _metrics: &ObjectStorageMetrics) -> Result<()> {
+        Ok(())
+    }
+    
+    async fn publish_load_balancer(&self, _metrics: &LoadBalancerMetrics) -> Result<()> {
+        Ok(())
+    }
+    
+    async fn publish_lifecycle(&self, _event: &LifecycleEvent) -> Result<()> {
+        Ok(())
+    }
+    
+    // Fails while the sink can't take samples, e.g. with its broker down
+    async fn check(&self) -> Result<()> {
+        Ok(())
+    }
+    
+    // Waits for buffered samples to go out, up to the timeout
+    async fn flush(&self, _timeout: Duration) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Rewrites or enriches server samples after collection, before the caches
+// and sinks see them, in the order [[metrics.transforms]] lists them
+pub trait MetricTransform: Send + Sync {
+    fn name(&self) -> &str;
+    
+    fn apply(&self, metrics: &mut ServerMetrics);
+}
+
+// Predicts a resource's load from its recent history
+#[async_trait]
+pub trait ForecastModel: Send + Sync {
+    fn version(&self) -> &str;
+    
+    // values[i] is the load expected (i + 1) steps after the last input point
+    fn predict(&self, input: &TimeSeriesData) -> Result<Vec<f64>>;
+    
+    // A replacement trained on fresh data, which the engine swaps in
+    async fn retrain(&self, config: &MLConfig) -> Result<Box<dyn ForecastModel>>;
+}
+
+pub type SourceFactory = for<'a> fn(&'a PluginConfig, &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSource>>>;
+pub type SinkFactory = for<'a> fn(&'a PluginConfig, &'a MetricsConfig) -> BoxFuture<'a, Result<Arc<dyn MetricSink>>>;
+pub type TransformFactory = fn(&PluginConfig) -> Result<Box<dyn MetricTransform>>;
+pub type ModelFactory = for<'a> fn(&'a PluginConfig, &'a MLConfig) -> BoxFuture<'a, Result<Box<dyn ForecastModel>>>;
+pub type FilterFactory = fn(&PlacementConfig) -> Result<Box<dyn HostFilter>>;
+pub type WeigherFactory = fn(&PlacementConfig, &Arc<ArcSwap<ScoringStrategy>>) -> Result<Box<dyn HostWeigher>>;
+
+#[derive(Debug, Clone, Copy)]
+pub enum PluginKind {
+    Source,
+    Sink,
+    Transform,
+    Model,
+    Filter,
+    Weigher,
+}
+
+// Every integration selectable by name in config. Built-ins and enabled
+// extensions register at startup; nothing is loaded at runtime.
+#[derive(Default)]
+pub struct Registry {
+    sources: BTreeMap<&'static str, SourceFactory>,
+    sinks: BTreeMap<&'static str, SinkFactory>,
+    transforms: BTreeMap<&'static str, TransformFactory>,
+    models: BTreeMap<&'static str, ModelFactory>,
+    filters: BTreeMap<&'static str, FilterFactory>,
+    weighers: BTreeMap<&'static str, WeigherFactory>,
+}
+
+static REGISTRY: OnceLock<Registry> = OnceLock::new();
+
+pub fn registry() -> &'static Registry {
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        crate::metrics::http_source::register(&mut registry);
+        crate::metrics::influxdb::register(&mut registry);
+        crate::metrics::kafka_producer::register(&mut registry);
+        crate::metrics::processor::register(&mut registry);
+        crate::metrics::timescale::register(&mut registry);
+        crate::ml::models::register(&mut registry);
+        crate::scheduler::filters::register(&mut registry);
+        extensions::register(&mut registry);
+        registry
+    })
+}
+
+impl Registry {
+    // Registering a name twice is a build mistake, so these panic rather
+    // than let one integration silently shadow another
+    pub fn add_source(&mut self, name: &'static str, factory: SourceFactory) -> &mut Self {
+        assert!(self.sources.insert(name, factory).is_none(), "metric source {} registered twice", name);
+        self
+    }
+    
+    pub fn add_sink(&mut self, name: &'static str, factory: SinkFactory) -> &mut Self {
+        assert!(self.sinks.insert(name, factory).is_none(), "metric sink {} registered twice", name);
+        self
+    }
+    
+    pub fn add_transform(&mut self, name: &'static str, factory: TransformFactory) -> &mut Self {
+        assert!(self.transforms.insert(name, factory).is_none(), "metric transform {} registered twice", name);
+        self
+    }
+    
+    pub fn add_model(&mut self, name: &'static str, factory: ModelFactory) -> &mut Self {
+        assert!(self.models.insert(name, factory).is_none(), "forecast model {} registered twice", name);
+        self
+    }
+    
+    pub fn add_filter(&mut self, name: &'static str, factory: FilterFactory) -> &mut Self {
+        assert!(self.filters.insert(name, factory).is_none(), "placement filter {} registered twice", name);
+        self
+    }
+    
+    pub fn add_weigher(&mut self, name: &'static str, factory: WeigherFactory) -> &mut Self {
+        assert!(self.weighers.insert(name, factory).is_none(), "placement weigher {} registered twice", name);
+        self
+    }
+    
+    pub async fn source(&self, plugin: &PluginConfig, config: &MetricsConfig) -> Result<Arc<dyn MetricSource>> {
+        let factory = self.sources.get(plugin.name.as_str())
+            .ok_or_else(|| self.unknown(PluginKind::Source, &plugin.name))?;
+        factory(plugin, config).await
+    }
+    
+    pub async fn sink(&self, plugin: &PluginConfig, config: &MetricsConfig) -> Result<Arc<dyn MetricSink>> {
+        let factory = self.sinks.get(plugin.name.as_str())
+            .ok_or_else(|| self.unknown(PluginKind::Sink, &plugin.name))?;
+        factory(plugin, config).await
+    }
+    
+    pub fn transform(&self, plugin: &PluginConfig) -> Result<Box<dyn MetricTransform>> {
+        let factory = self.transforms.get(plugin.name.as_str())
+            .ok_or_else(|| self.unknown(PluginKind::Transform, &plugin.name))?;
+        factory(plugin)
+    }
+    
+    pub async fn model(&self, plugin: &PluginConfig, config: &MLConfig) -> Result<Box<dyn ForecastModel>> {
+        let factory = self.models.get(plugin.name.as_str())
+            .ok_or_else(|| self.unknown(PluginKind::Model, &plugin.name))?;
+        factory(plugin, config).await
+    }
+    
+    pub fn filter(&self, name: &str, config: &PlacementConfig) -> Result<Box<dyn HostFilter>> {
+        let factory = self.filters.get(name)
+            .ok_or_else(|| self.unknown(PluginKind::Filter, name))?;
+        factory(config)
+    }
+    
+    pub fn weigher(
+        &self,
+        name: &str,
+        config: &PlacementConfig,
+        scoring: &Arc<ArcSwap<ScoringStrategy>>,
+    ) -> Result<Box<dyn HostWeigher>> {
+        let factory = self.weighers.get(name)
+            .ok_or_else(|| self.unknown(PluginKind::Weigher, name))?;
+        factory(config, scoring)
+    }
+    
+    // Err names the ones that are, for config validation
+    pub fn check(&self, kind: PluginKind, name: &str) -> Result<(), String> {
+        if self.names(kind).contains(&name) {
+            return Ok(());
+        }
+        Err(self.unknown(kind, name).to_string())
+    }
+    
+    fn names(&self, kind: PluginKind) -> Vec<&'static str> {
+        match kind {
+            PluginKind::Source => self.sources.keys().copied().collect(),
+            PluginKind::Sink => self.sinks.keys().copied().collect(),
+            PluginKind::Transform => self.transforms.keys().copied().collect(),
+            PluginKind::Model => self.models.keys().copied().collect(),
+            PluginKind::Filter => self.filters.keys().copied().collect(),
+            PluginKind::Weigher => self.weighers.keys().copied().collect(),
+        }
+    }
+    
+    fn unknown(&self, kind: PluginKind, name: &str) -> anyhow::Error {
+        let known = self.names(kind);
+        if known.is_empty() {
+            return anyhow::anyhow!("unknown {} '{}', none are registered", kind, name);
+        }
+        anyhow::anyhow!("unknown {} '{}', expected one of: {}", kind, name, known.join(", "))
+    }
+}
+
+impl fmt::Display for PluginKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            PluginKind::Source => "metric source",
+            PluginKind::Sink => "metric sink",
+            PluginKind::Transform => "metric transform",
+            PluginKind::Model => "forecast model",
+            PluginKind::Filter => "placement filter",
+            PluginKind::Weigher => "placement weigher",
+        })
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::collections::BTreeSet;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use tokio::signal::unix::{signal, SignalKind};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::Config;
+use crate::scheduler::ResourceScheduler;
+use crate::web::audit::{AuditEntry, AuditInterface, AuditLog, AuditOutcome};
+use crate::web::DashboardServer;
+
+// Settings applied at runtime, as paths into the config; a change anywhere
+// else needs a restart and gets the whole reload rejected
+const RELOADABLE: &[&str] = &[
+    "scheduler.scheduling_interval_seconds",
+    "scheduler.high_load_threshold",
+    "scheduler.low_load_threshold",
+    "scheduler.max_migrations_per_cycle",
+    "scheduler.migration_timeout_seconds",
+    "scheduler.max_migration_attempts",
+    "scheduler.optimizer.interval_seconds",
+    "scheduler.rebalance.interval_seconds",
+    "scheduler.scoring",
+    "alerting",
+];
+
+// Re-reads the config file on SIGHUP or when it changes on disk, and hands
+// the settings that can change at runtime to the components using them
+pub struct ConfigReloader {
+    path: String,
+    current: Mutex<Config>,
+    scheduler: Arc<ResourceScheduler>,
+    dashboard: DashboardServer,
+    audit: Arc<AuditLog>,
+}
+
+impl ConfigReloader {
+    pub fn new(path: &str, config: Config, scheduler: Arc<ResourceScheduler>, dashboard: DashboardServer) -> Self {
+        Self {
+            path: path.to_string(),
+            audit: dashboard.audit_log(),
+            current: Mutex::new(config),
+            scheduler,
+            dashboard,
+        }
+    }
+    
+    pub async fn run(&self, shutdown: CancellationToken) -> Result<()> {
+        let mut hangup = signal(SignalKind::hangup())?;
+        let reload = self.current.lock().await.reload.clone();
+        let mut poll = tokio::time::interval(Duration::from_secs(reload.poll_interval_seconds));
+        let mut modified = self.modified();
+        info!("Watching {} for changes (SIGHUP reloads it too)", self.path);
+        
+        loop {
+            tokio::select! {
+                _ = hangup.recv() => {
+                    info!("SIGHUP received, reloading {}", self.path);
+                }
+                _ = poll.tick(), if reload.watch_file => {
+                    let latest = self.modified();
+                    if latest == modified {
+                        continue;
+                    }
+                    info!("{} changed on disk, reloading", self.path);
+                }
+                _ = shutdown.cancelled() => return Ok(()),
+            }
+            modified = self.modified();
+            self.reload().await;
+        }
+    }
+    
+    fn modified(&self) -> Option<SystemTime> {
+        std::fs::metadata(&self.path).and_then(|m| m.modified()).ok()
+    }
+    
+    // A file needing a restart changes nothing; either way the attempt is audited
+    pub async fn reload(&self) {
+        let mut current = self.current.lock().await;
+        let mut entry = AuditEntry::new(AuditInterface::Config, "reload".to_string(), self.path.clone(), None);
+        
+        match self.apply(&current).await {
+            Ok((config, changed)) if changed.is_empty() => {
+                info!("Config reload found no changes");
+                entry.status = "unchanged".to_string();
+                *current = config;
+            }
+            Ok((config, changed)) => {
+                info!("Config reloaded; changed: {}", changed.join(", "));
+                entry.request = Some(json!({ "changed": changed }));
+                entry.status = "applied".to_string();
+                *current = config;
+            }
+            Err(e) => {
+                warn!("Config reload rejected, keeping the running config: {}", e);
+                entry.outcome = AuditOutcome::Failed;
+                entry.status = "rejected".to_string();
+                entry.error = Some(e.to_string());
+            }
+        }
+        ::metrics::counter!("config_reloads_total", "status" => entry.status.clone()).increment(1);
+        self.audit.record(entry).await;
+    }
+    
+    async fn apply(&self, current: &Config) -> Result<(Config, Vec<String>)> {
+        let config = Config::from_file(&self.path)?;
+        let mut changed = Vec::new();
+        changed_paths(&serde_json::to_value(current)?, &serde_json::to_value(&config)?, "", &mut changed);
+        
+        let needs_restart: BTreeSet<&str> = changed.iter()
+            .filter(|path| !is_reloadable(path))
+            .map(String::as_str)
+            .collect();
+        if !needs_restart.is_empty() {
+            anyhow::bail!(
+                "changes to {} need a restart",
+                needs_restart.into_iter().collect::<Vec<_>>().join(", ")
+            );
+        }
+        
+        if changed.iter().any(|path| path.starts_with("scheduler.")) {
+            self.scheduler.apply_config(config.scheduler.clone())?;
+        }
+        if changed.iter().any(|path| path.starts_with("alerting")) {
+            self.dashboard.apply_alerting(config.alerting.clone())?;
+        }
+        Ok((config, changed))
+    }
+}
+
+fn is_reloadable(path: &str) -> bool {
+    RELOADABLE.iter().any(|reloadable| {
+        path == *reloadable || path.strip_prefix(reloadable).is_some_and(|rest| rest.starts_with('.'))
+    })
+}
+
+// Dotted paths of every leaf that differs; arrays compare as a whole
+fn changed_paths(old: &Value, new: &Value, prefix: &str, changed: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old), Value::Object(new)) => {
+            let keys: BTreeSet<&String> = old.keys().chain(new.keys()).collect();
+            for key in keys {
+                let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+                changed_paths(old.get(key).unwrap_or(&Value::Null), new.get(key).unwrap_or(&Value::Null), &path, changed);
+            }
+        }
+        (old, new) if old != new => changed.push(prefix.to_string()),
+        _ => {}
+    }
+}
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::config::AlarmSyncConfig;
+use crate::openstack::services::{Alarm, AlarmDefinition, ThresholdRule};
+use crate::openstack::{Client, Clouds};
+use super::sla_manager::{SLAPolicy, SLAPriority};
+
+// Keeps a CPU threshold alarm in Aodh for every SLA policy and follows the
+// state of every alarm Aodh has, so the dashboard can show them
+pub struct AlarmSync {
+    config: AlarmSyncConfig,
+    clouds: Clouds,
+    // As of the last sync, by alarm id
+    alarms: RwLock<HashMap<String, Alarm>>,
+}
+
+impl AlarmSync {
+    pub fn new(config: AlarmSyncConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+            alarms: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.clouds.regions().any(|client| client.aodh.is_available())
+    }
+    
+    // Reads every alarm's state in every region with Aodh; with manage set it
+    // also brings our alarms in line with the policies, which only the
+    // leader should do
+    pub async fn sync(&self, policies: &[SLAPolicy], manage: bool) -> Result<()> {
+        // A policy's alarm goes to the Aodh of the region its server is in.
+        // Regions sharing an Aodh are synced once, with all their policies,
+        // or each would delete the others' alarms as left over
+        let mut services: Vec<(String, &Client, Vec<&SLAPolicy>)> = Vec::new();
+        for client in self.clouds.regions().filter(|client| client.aodh.is_available()) {
+            let endpoint = client.aodh.endpoint()?;
+            let region_policies = policies.iter()
+                .filter(|policy| self.clouds.client_for(&policy.resource_id).region() == client.region());
+            match services.iter_mut().find(|(known, ..)| *known == endpoint) {
+                Some((_, _, shared)) => shared.extend(region_policies),
+                None => services.push((endpoint, client.as_ref(), region_policies.collect())),
+            }
+        }
+        
+        let mut alarms = Vec::new();
+        for (_, client, service_policies) in services {
+            let service_alarms = client.aodh.list_alarms().await?;
+            if manage {
+                self.reconcile(client, &service_policies, &service_alarms).await;
+            }
+            alarms.extend(service_alarms);
+        }
+        
+        let mut known = self.alarms.write().await;
+        for alarm in &alarms {
+            let previous = known.get(&alarm.alarm_id).map(|previous| previous.state.as_str());
+            if previous.is_some_and(|state| state != alarm.state) {
+                info!(
+                    "Aodh alarm {} went from {} to {}{}",
+                    alarm.name,
+                    previous.unwrap_or_default(),
+                    alarm.state,
+                    alarm.state_timestamp.as_deref().map(|at| format!(" at {}", at)).unwrap_or_default()
+                );
+                ::metrics::counter!("aodh_alarm_transitions_total", "state" => alarm.state.clone()).increment(1);
+            }
+        }
+        *known = alarms.into_iter().map(|alarm| (alarm.alarm_id.clone(), alarm)).collect();
+        Ok(())
+    }
+    
+    // Alarms currently in the alarm state
+    pub async fn firing(&self) -> Vec<Alarm> {
+        self.alarms.read().await.values()
+            .filter(|alarm| alarm.is_firing())
+            .cloned()
+            .collect()
+    }
+    
+    // Whether the alarm is one of ours, mirroring an SLA policy
+    pub fn mirrors_policy(&self, alarm: &Alarm) -> bool {
+        alarm.name.starts_with(&format!("{}-", self.config.name_prefix))
+    }
+    
+    // Failures are logged and retried on the next sync
+    async fn reconcile(&self, client: &Client, policies: &[&SLAPolicy], alarms: &[Alarm]) {
+        let aodh = &client.aodh;
+        let mut ours: HashMap<&str, &Alarm> = alarms.iter()
+            .filter(|alarm| self.mirrors_policy(alarm))
+            .map(|alarm| (alarm.name.as_str(), alarm))
+            .collect();
+        
+        for policy in policies {
+            let definition = self.definition(policy);
+            let result = match ours.remove(definition.name.as_str()) {
+                Some(alarm) if alarm.matches(&definition) => continue,
+                Some(alarm) => aodh.update_alarm(&alarm.alarm_id, &definition).await,
+                None => aodh.create_alarm(&definition).await,
+            };
+            match result {
+                Ok(alarm) => debug!("Synced Aodh alarm {} for {}", alarm.name, policy.resource_id),
+                Err(e) => warn!("Failed to sync the Aodh alarm for {}: {}", policy.resource_id, e),
+            }
+        }
+        
+        // Left over from policies that have since been deleted
+        for alarm in ours.into_values() {
+            match aodh.delete_alarm(&alarm.alarm_id).await {
+                Ok(()) => info!("Deleted Aodh alarm {}, its SLA policy is gone", alarm.name),
+                Err(e) => warn!("Failed to delete Aodh alarm {}: {}", alarm.name, e),
+            }
+        }
+    }
+    
+    // Policies on resources Gnocchi has no instance for, like load balancers,
+    // get an alarm that stays in insufficient data
+    fn definition(&self, policy: &SLAPolicy) -> AlarmDefinition {
+        let severity = match policy.priority {
+            SLAPriority::Critical | SLAPriority::High => "critical",
+            SLAPriority::Medium => "moderate",
+            SLAPriority::Low => "low",
+        };
+        AlarmDefinition {
+            name: format!("{}-{}-cpu", self.config.name_prefix, policy.resource_id),
+            alarm_type: "gnocchi_resources_threshold",
+            description: format!(
+                "CPU utilisation of {} above its SLA limit of {:.1}%",
+                policy.resource_id, policy.max_cpu_utilization
+            ),
+            severity: severity.to_string(),
+            enabled: true,
+            gnocchi_resources_threshold_rule: ThresholdRule {
+                metric: self.config.cpu_metric.clone(),
+                resource_type: "instance".to_string(),
+                resource_id: policy.resource_id.clone(),
+                aggregation_method: self.config.aggregation_method.clone(),
+                granularity: self.config.granularity_seconds,
+                evaluation_periods: self.config.evaluation_periods,
+                threshold: policy.max_cpu_utilization,
+                comparison_operator: "gt".to_string(),
+            },
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tokio::sync::RwLock;
+use tracing::{debug, info};
+
+use crate::config::AutoscalingConfig;
+use crate::openstack::services::ScalingGroup;
+use crate::openstack::{Client, Clouds};
+use super::cluster::{ClusterSnapshot, ResourceContext};
+use super::prescaling::PeakForecast;
+
+// Sizes Senlin clusters and Heat autoscaling groups from the forecasted load
+// of their members instead of moving individual instances around
+pub struct AutoScaler {
+    config: AutoscalingConfig,
+    clouds: Clouds,
+    last_scaled: RwLock<HashMap<GroupRef, DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum ScalingBackend {
+    Senlin,
+    Heat,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct GroupRef {
+    pub backend: ScalingBackend,
+    pub group_id: String,
+    // Its members' region, whose Senlin or Heat has it
+    pub region: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ScalingProposal {
+    pub group: GroupRef,
+    // Member the decision is attributed to for policy matching
+    pub representative: String,
+    pub current_capacity: u32,
+    pub desired_capacity: u32,
+    // Set when a forecast peak, not the current forecast, sets the size
+    pub ahead_of_peak: Option<DateTime<Utc>>,
+}
+
+impl ScalingProposal {
+    pub fn is_scale_out(&self) -> bool {
+        self.desired_capacity > self.current_capacity
+    }
+}
+
+impl AutoScaler {
+    pub fn new(config: AutoscalingConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+            last_scaled: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    pub fn group_of(&self, context: &ResourceContext) -> Option<GroupRef> {
+        if let Some(group_id) = context.metadata.get(&self.config.senlin_metadata_key) {
+            return Some(GroupRef {
+                backend: ScalingBackend::Senlin,
+                group_id: group_id.clone(),
+                region: context.region.clone(),
+            });
+        }
+        
+        context.metadata.get(&self.config.heat_metadata_key).map(|group_id| GroupRef {
+            backend: ScalingBackend::Heat,
+            group_id: group_id.clone(),
+            region: context.region.clone(),
+        })
+    }
+    
+    // Peaks, when given, size groups for the highest load forecast within
+    // the pre-scaling lead time and keep them from scaling in ahead of it
+    pub async fn plan(
+        &self,
+        snapshot: &ClusterSnapshot,
+        peaks: &HashMap<String, PeakForecast>,
+    ) -> Result<Vec<ScalingProposal>> {
+        let mut members: HashMap<GroupRef, Vec<&str>> = HashMap::new();
+        for (resource_id, context) in &snapshot.resources {
+            if let Some(group) = self.group_of(context) {
+                members.entry(group).or_default().push(resource_id.as_str());
+            }
+        }
+        
+        let mut proposals = Vec::new();
+        for (group_ref, resource_ids) in members {
+            if self.in_cooldown(&group_ref).await {
+                debug!("Scaling group {} is cooling down", group_ref.group_id);
+                continue;
+            }
+            
+            let group = self.fetch_group(&group_ref).await?;
+            let members: Vec<_> = snapshot.instances.iter()
+                .filter(|i| resource_ids.contains(&i.resource_id.as_str()))
+                .collect();
+            let total_load: f64 = members.iter().map(|i| i.predicted_load).sum();
+            let peak_load: f64 = members.iter()
+                .map(|i| peaks.get(&i.resource_id).map(|p| p.load.max(i.predicted_load)).unwrap_or(i.predicted_load))
+                .sum();
+            
+            // Enough replicas to bring the mean member load down to the target
+            let replicas_for = |load: f64| {
+                ((load / self.config.target_utilization).ceil() as u32).clamp(group.min_size, group.max_size)
+            };
+            let reactive = replicas_for(total_load);
+            let desired = replicas_for(peak_load);
+            let ahead_of_peak = (desired > reactive)
+                .then(|| members.iter().filter_map(|i| peaks.get(&i.resource_id)).map(|p| p.at).min())
+                .flatten();
+            
+            if desired != group.desired_capacity {
+                let mut resource_ids = resource_ids;
+                resource_ids.sort();
+                proposals.push(ScalingProposal {
+                    group: group_ref,
+                    representative: resource_ids[0].to_string(),
+                    current_capacity: group.desired_capacity,
+                    desired_capacity: desired,
+                    ahead_of_peak,
+                });
+            }
+        }
+        
+        Ok(proposals)
+    }
+    
+    // False when the group is still cooling down and was left alone
+    pub async fn apply(&self, group_ref: &GroupRef, desired_capacity: u32) -> Result<bool> {
+        if self.in_cooldown(group_ref).await {
+            debug!("Skipping resize of {}: still cooling down", group_ref.group_id);
+            return Ok(false);
+        }
+        
+        info!(
+            "Scaling {:?} group {} to {} replicas",
+            group_ref.backend, group_ref.group_id, desired_capacity
+        );
+        
+        let client = self.client_for(group_ref);
+        match group_ref.backend {
+            ScalingBackend::Senlin => {
+                client.senlin.resize_cluster(&group_ref.group_id, desired_capacity).await?
+            }
+            ScalingBackend::Heat => {
+                client.heat.resize_scaling_group(&group_ref.group_id, desired_capacity).await?
+            }
+        }
+        
+        self.last_scaled.write().await.insert(group_ref.clone(), Utc::now());
+        Ok(true)
+    }
+    
+    async fn fetch_group(&self, group_ref: &GroupRef) -> Result<ScalingGroup> {
+        let client = self.client_for(group_ref);
+        match group_ref.backend {
+            ScalingBackend::Senlin => client.senlin.get_cluster(&group_ref.group_id).await,
+            ScalingBackend::Heat => client.heat.get_scaling_group(&group_ref.group_id).await,
+        }
+    }
+    
+    fn client_for(&self, group_ref: &GroupRef) -> Arc<Client> {
+        self.clouds.region(group_ref.region.as_deref())
+    }
+    
+    async fn in_cooldown(&self, group_ref: &GroupRef) -> bool {
+        self.last_scaled.read().await
+            .get(group_ref)
+            .map(|at| Utc::now() - *at < Duration::seconds(self.config.cooldown_seconds))
+            .unwrap_or(false)
+    }
+}
@@ -0,0 +1,395 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, DurationRound, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tracing::{debug, info, warn};
+
+use crate::config::CapacityConfig;
+use crate::storage::Storage;
+use super::cluster::ClusterSnapshot;
+use super::placement::HostMetrics;
+
+const CAPACITY_COLLECTION: &str = "capacity_history";
+
+// Hosts and zones not seen in a snapshot for this long have left the cluster
+const STALE_AFTER_HOURS: i64 = 1;
+
+// Hourly usage of every host and availability zone, sampled from the
+// scheduler's snapshots and persisted so trends can span weeks. A linear
+// trend over the lookback window projects usage forward and dates when each
+// resource crosses the exhaustion threshold.
+pub struct CapacityPlanner {
+    config: CapacityConfig,
+    storage: Storage,
+    series: DashMap<(CapacityScope, String), UsageSeries>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CapacityScope {
+    Host,
+    AvailabilityZone,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+struct Usage {
+    hosts: u32,
+    vcpus_total: u64,
+    vcpus_used: u64,
+    memory_total_mb: u64,
+    memory_used_mb: u64,
+    // Measured, weighted by each host's vCPUs
+    cpu_utilization: f64,
+}
+
+// Averages of the samples taken within one hour, as percentages
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct HourBucket {
+    start: DateTime<Utc>,
+    vcpus_sum: f64,
+    memory_sum: f64,
+    cpu_sum: f64,
+    samples: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct UsageSeries {
+    scope: CapacityScope,
+    id: String,
+    availability_zone: Option<String>,
+    current: Usage,
+    updated_at: DateTime<Utc>,
+    // Oldest first; the last one is still filling
+    buckets: VecDeque<HourBucket>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacityReport {
+    pub generated_at: DateTime<Utc>,
+    pub exhaustion_threshold_percent: f64,
+    pub hosts: Vec<CapacityEntry>,
+    pub availability_zones: Vec<CapacityEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CapacityEntry {
+    pub id: String,
+    pub scope: CapacityScope,
+    // The host's zone; None for zones themselves
+    pub availability_zone: Option<String>,
+    pub hosts: u32,
+    pub vcpus_total: u64,
+    pub vcpus_used: u64,
+    pub memory_total_mb: u64,
+    pub memory_used_mb: u64,
+    // Allocated vCPUs and memory, and measured CPU utilization
+    pub vcpus: UsageForecast,
+    pub memory: UsageForecast,
+    pub cpu: UsageForecast,
+    // Earliest of the three
+    pub exhaustion_at: Option<DateTime<Utc>>,
+    pub updated_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageForecast {
+    pub current_percent: f64,
+    // None until there is enough history to fit
+    pub trend: Option<UsageTrend>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UsageTrend {
+    // Percentage points per day
+    pub growth_per_day: f64,
+    pub in_1_day: f64,
+    pub in_7_days: f64,
+    pub in_30_days: f64,
+    // When usage reaches the threshold; now if it already has, None if it
+    // won't within the horizon
+    pub exhaustion_at: Option<DateTime<Utc>>,
+    pub history_hours: usize,
+}
+
+impl Usage {
+    fn add(&mut self, host: &HostMetrics) {
+        let vcpus = self.vcpus_total + host.total_vcpus as u64;
+        if vcpus > 0 {
+            self.cpu_utilization = (self.cpu_utilization * self.vcpus_total as f64
+                + host.cpu_utilization * host.total_vcpus as f64) / vcpus as f64;
+        }
+        self.hosts += 1;
+        self.vcpus_total = vcpus;
+        self.vcpus_used += host.total_vcpus.saturating_sub(host.available_vcpus) as u64;
+        self.memory_total_mb += host.total_memory_mb;
+        self.memory_used_mb += host.total_memory_mb.saturating_sub(host.available_memory_mb);
+    }
+    
+    fn vcpus_percent(&self) -> f64 {
+        percent(self.vcpus_used, self.vcpus_total)
+    }
+    
+    fn memory_percent(&self) -> f64 {
+        percent(self.memory_used_mb, self.memory_total_mb)
+    }
+}
+
+impl UsageSeries {
+    // Returns whether the sample started a new hour, closing the previous one
+    fn add(&mut self, at: DateTime<Utc>) -> bool {
+        let start = at.duration_trunc(Duration::hours(1)).unwrap_or(at);
+        let vcpus = self.current.vcpus_percent();
+        let memory = self.current.memory_percent();
+        let cpu = self.current.cpu_utilization;
+        
+        match self.buckets.back_mut() {
+            Some(bucket) if bucket.start == start => {
+                bucket.vcpus_sum += vcpus;
+                bucket.memory_sum += memory;
+                bucket.cpu_sum += cpu;
+                bucket.samples += 1;
+                false
+            }
+            Some(bucket) if bucket.start > start => false,
+            last => {
+                let closed = last.is_some();
+                self.buckets.push_back(HourBucket {
+                    start,
+                    vcpus_sum: vcpus,
+                    memory_sum: memory,
+                    cpu_sum: cpu,
+                    samples: 1,
+                });
+                closed
+            }
+        }
+    }
+    
+    fn storage_key(&self) -> String {
+        let prefix = match self.scope {
+            CapacityScope::Host => "host",
+            CapacityScope::AvailabilityZone => "az",
+        };
+        let id: String = self.id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect();
+        format!("{}-{}", prefix, id)
+    }
+}
+
+impl CapacityPlanner {
+    pub async fn load(config: CapacityConfig, storage: Storage) -> Result<Self> {
+        let stored: Vec<UsageSeries> = storage.list(CAPACITY_COLLECTION).await?;
+        info!("Restored capacity history for {} hosts and zones", stored.len());
+        
+        Ok(Self {
+            config,
+            storage,
+            series: stored.into_iter().map(|s| ((s.scope, s.id.clone()), s)).collect(),
+        })
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Adds the snapshot to the current hour; series whose hour just ended are
+    // written out, so a restart loses at most the hour in progress
+    pub async fn sample(&self, snapshot: &ClusterSnapshot) {
+        let now = snapshot.taken_at;
+        let mut usage: HashMap<(CapacityScope, String), (Usage, Option<String>)> = HashMap::new();
+        for host in &snapshot.hosts {
+            let (host_usage, _) = usage.entry((CapacityScope::Host, host.host_id.clone()))
+                .or_insert_with(|| (Usage::default(), host.availability_zone.clone()));
+            host_usage.add(host);
+            if let Some(zone) = &host.availability_zone {
+                usage.entry((CapacityScope::AvailabilityZone, zone.clone())).or_default().0.add(host);
+            }
+        }
+        
+        let mut closed = Vec::new();
+        for ((scope, id), (current, availability_zone)) in usage {
+            if current.vcpus_total == 0 && current.memory_total_mb == 0 {
+                continue;
+            }
+            let mut series = self.series.entry((scope, id.clone())).or_insert_with(|| UsageSeries {
+                scope,
+                id: id.clone(),
+                availability_zone: None,
+                current: Usage::default(),
+                updated_at: now,
+                buckets: VecDeque::new(),
+            });
+            series.availability_zone = availability_zone;
+            series.current = current;
+            series.updated_at = now;
+            if series.add(now) {
+                closed.push((scope, id));
+            }
+        }
+        
+        if !closed.is_empty() {
+            self.prune(now).await;
+            for key in closed {
+                self.persist(&key).await;
+            }
+        }
+    }
+    
+    async fn persist(&self, key: &(CapacityScope, String)) {
+        let series = match self.series.get(key) {
+            Some(series) => series.clone(),
+            None => return,
+        };
+        if let Err(e) = self.storage.put(CAPACITY_COLLECTION, &series.storage_key(), &series).await {
+            warn!("Failed to persist capacity history of {}: {}", series.id, e);
+        }
+    }
+    
+    // Drops hours past retention, and hosts and zones with nothing left
+    async fn prune(&self, now: DateTime<Utc>) {
+        let cutoff = now - Duration::days(self.config.retention_days);
+        let mut emptied = Vec::new();
+        for mut series in self.series.iter_mut() {
+            while series.buckets.front().is_some_and(|b| b.start < cutoff) {
+                series.buckets.pop_front();
+            }
+            if series.buckets.is_empty() {
+                emptied.push(series.key().clone());
+            }
+        }
+        
+        for key in emptied {
+            if let Some((_, series)) = self.series.remove(&key) {
+                if let Err(e) = self.storage.delete(CAPACITY_COLLECTION, &series.storage_key()).await {
+                    warn!("Failed to delete capacity history of {}: {}", series.id, e);
+                }
+            }
+        }
+    }
+    
+    pub fn report(&self) -> CapacityReport {
+        let now = Utc::now();
+        let mut hosts = Vec::new();
+        let mut availability_zones = Vec::new();
+        
+        for series in self.series.iter() {
+            if now - series.updated_at > Duration::hours(STALE_AFTER_HOURS) {
+                continue;
+            }
+            let entry = self.entry(&series, now);
+            match series.scope {
+                CapacityScope::Host => hosts.push(entry),
+                CapacityScope::AvailabilityZone => availability_zones.push(entry),
+            }
+        }
+        hosts.sort_by(|a, b| a.id.cmp(&b.id));
+        availability_zones.sort_by(|a, b| a.id.cmp(&b.id));
+        debug!("Capacity report covers {} hosts in {} zones", hosts.len(), availability_zones.len());
+        
+        CapacityReport {
+            generated_at: now,
+            exhaustion_threshold_percent: self.config.exhaustion_threshold_percent,
+            hosts,
+            availability_zones,
+        }
+    }
+    
+    fn entry(&self, series: &UsageSeries, now: DateTime<Utc>) -> CapacityEntry {
+        let current = &series.current;
+        let vcpus = self.forecast(series, now, current.vcpus_percent(), |b| b.vcpus_sum);
+        let memory = self.forecast(series, now, current.memory_percent(), |b| b.memory_sum);
+        let cpu = self.forecast(series, now, current.cpu_utilization, |b| b.cpu_sum);
+        let exhaustion_at = [&vcpus, &memory, &cpu].iter()
+            .filter_map(|f| f.trend.as_ref().and_then(|t| t.exhaustion_at))
+            .min();
+        
+        CapacityEntry {
+            id: series.id.clone(),
+            scope: series.scope,
+            availability_zone: series.availability_zone.clone(),
+            hosts: current.hosts,
+            vcpus_total: current.vcpus_total,
+            vcpus_used: current.vcpus_used,
+            memory_total_mb: current.memory_total_mb,
+            memory_used_mb: current.memory_used_mb,
+            vcpus,
+            memory,
+            cpu,
+            exhaustion_at,
+            updated_at: series.updated_at,
+        }
+    }
+    
+    // Least-squares line through the hourly averages in the lookback window,
+    // with time in days from now
+    fn forecast(
+        &self,
+        series: &UsageSeries,
+        now: DateTime<Utc>,
+        current_percent: f64,
+        sum: impl Fn(&HourBucket) -> f64,
+    ) -> UsageForecast {
+        let since = now - Duration::days(self.config.lookback_days);
+        let points: Vec<(f64, f64)> = series.buckets.iter()
+            .filter(|b| b.start >= since && b.samples > 0)
+            .map(|b| ((b.start - now).num_seconds() as f64 / 86_400.0, sum(b) / b.samples as f64))
+            .collect();
+        
+        let trend = (points.len() >= self.config.min_history_hours.max(2))
+            .then(|| fit(&points))
+            .flatten()
+            .map(|(at_now, slope)| {
+                let project = |days: f64| (at_now + slope * days).max(0.0);
+                UsageTrend {
+                    growth_per_day: slope,
+                    in_1_day: project(1.0),
+                    in_7_days: project(7.0),
+                    in_30_days: project(30.0),
+                    exhaustion_at: self.exhaustion(now, current_percent.max(at_now), slope),
+                    history_hours: points.len(),
+                }
+            });
+        
+        UsageForecast {
+            current_percent,
+            trend,
+        }
+    }
+    
+    fn exhaustion(&self, now: DateTime<Utc>, at_now: f64, slope: f64) -> Option<DateTime<Utc>> {
+        let threshold = self.config.exhaustion_threshold_percent;
+        if at_now >= threshold {
+            return Some(now);
+        }
+        if slope <= 0.0 {
+            return None;
+        }
+        let days = (threshold - at_now) / slope;
+        (days <= self.config.max_horizon_days as f64)
+            .then(|| now + Duration::seconds((days * 86_400.0) as i64))
+    }
+}
+
+// Value at x = 0 and slope; None when every point is at the same time
+fn fit(points: &[(f64, f64)]) -> Option<(f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let variance = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    if variance <= f64::EPSILON {
+        return None;
+    }
+    let covariance = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let slope = covariance / variance;
+    Some((mean_y - slope * mean_x, slope))
+}
+
+fn percent(used: u64, total: u64) -> f64 {
+    if total == 0 {
+        0.0
+    } else {
+        used as f64 / total as f64 * 100.0
+    }
+}
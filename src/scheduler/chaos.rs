@@ -0,0 +1,100 @@
+use dashmap::DashMap;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::Serialize;
+use std::sync::Mutex;
+use tracing::warn;
+
+use crate::config::ChaosConfig;
+
+// Randomly injected failures for exercising retry, rollback and alerting
+// paths before the scheduler is trusted with production. Never enable in
+// production: injected migration errors are indistinguishable from real ones
+pub struct FaultInjector {
+    config: ChaosConfig,
+    rng: Mutex<StdRng>,
+    // Prediction from the previous cycle, replayed as a stale one
+    previous_predictions: DashMap<String, f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationFault {
+    // Nova rejects the request
+    ApiError,
+    // The request never completes, so the migration timeout fires
+    Timeout,
+}
+
+impl MigrationFault {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MigrationFault::ApiError => "migration_api_error",
+            MigrationFault::Timeout => "migration_timeout",
+        }
+    }
+}
+
+impl FaultInjector {
+    pub fn new(config: ChaosConfig) -> Self {
+        if config.enabled {
+            warn!(
+                "Chaos mode enabled: migration errors {:.0}%, timeouts {:.0}%, stale predictions {:.0}%",
+                config.migration_error_rate * 100.0,
+                config.migration_timeout_rate * 100.0,
+                config.stale_prediction_rate * 100.0
+            );
+        }
+        let rng = match config.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        
+        Self {
+            config,
+            rng: Mutex::new(rng),
+            previous_predictions: DashMap::new(),
+        }
+    }
+    
+    pub fn migration_fault(&self) -> Option<MigrationFault> {
+        if !self.config.enabled {
+            return None;
+        }
+        
+        let fault = if self.roll(self.config.migration_error_rate) {
+            MigrationFault::ApiError
+        } else if self.roll(self.config.migration_timeout_rate) {
+            MigrationFault::Timeout
+        } else {
+            return None;
+        };
+        
+        Self::record(fault.as_str());
+        Some(fault)
+    }
+    
+    // Swaps in the resource's previous prediction at the configured rate
+    pub fn prediction(&self, resource_id: &str, fresh: f64) -> f64 {
+        if !self.config.enabled {
+            return fresh;
+        }
+        
+        let previous = self.previous_predictions.insert(resource_id.to_string(), fresh);
+        match previous {
+            Some(stale) if self.roll(self.config.stale_prediction_rate) => {
+                Self::record("stale_prediction");
+                stale
+            }
+            _ => fresh,
+        }
+    }
+    
+    fn roll(&self, rate: f64) -> bool {
+        rate > 0.0 && self.rng.lock().unwrap().gen_bool(rate.min(1.0))
+    }
+    
+    fn record(fault: &'static str) {
+        ::metrics::counter!("scheduler_chaos_faults_total", "fault" => fault).increment(1);
+    }
+}
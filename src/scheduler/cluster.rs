@@ -0,0 +1,243 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::openstack::services::{Flavor, Server, ServerGroupPolicy, ServerGroupRef, StackRef};
+use super::placement::HostMetrics;
+use super::sla_manager::PenaltyModel;
+use super::storage_locality::{InstanceStorage, StorageMigration, StorageTopology};
+use super::traffic::TrafficHints;
+
+// Point-in-time view of hosts and the instances placed on them, built once per
+// scheduling cycle so planners work against a consistent picture
+#[derive(Debug, Clone)]
+pub struct ClusterSnapshot {
+    pub hosts: Vec<HostMetrics>,
+    pub instances: Vec<InstancePlacement>,
+    pub resources: HashMap<String, ResourceContext>,
+    pub storage_topology: Arc<StorageTopology>,
+    pub traffic: Arc<TrafficHints>,
+    pub taken_at: DateTime<Utc>,
+}
+
+// Ownership and labelling of a resource, used by policy and override matching
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ResourceContext {
+    pub resource_id: String,
+    pub name: String,
+    pub project_id: Option<String>,
+    pub flavor_id: String,
+    // Embedded in the server from compute microversion 2.47, otherwise
+    // looked up by the placement engine
+    #[serde(skip)]
+    pub flavor: Option<Flavor>,
+    pub host: Option<String>,
+    pub availability_zone: Option<String>,
+    pub region: Option<String>,
+    pub metadata: HashMap<String, String>,
+    // The Heat stack that created the server, if any
+    pub stack: Option<StackRef>,
+    // The Nova server group it was booted into, if any
+    pub server_group: Option<ServerGroupRef>,
+}
+
+impl ResourceContext {
+    pub fn from_server(server: &Server) -> Self {
+        Self {
+            resource_id: server.id.clone(),
+            name: server.name.clone(),
+            project_id: server.tenant_id.clone(),
+            flavor_id: server.flavor.id.clone(),
+            flavor: server.flavor.embedded(),
+            host: server.host.clone(),
+            availability_zone: server.availability_zone.clone(),
+            region: server.region.clone(),
+            metadata: server.metadata.clone(),
+            stack: None,
+            server_group: None,
+        }
+    }
+    
+    // Context for resources we only know by id
+    pub fn unknown(resource_id: &str) -> Self {
+        Self {
+            resource_id: resource_id.to_string(),
+            ..Default::default()
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstancePlacement {
+    pub resource_id: String,
+    pub host_id: String,
+    pub vcpus: u32,
+    pub memory_mb: u64,
+    // Predicted utilization of the instance's own vCPUs (0-100)
+    pub predicted_load: f64,
+    // Excluded from consolidation and optimizer plans by a policy override
+    pub pinned: bool,
+    // A policy override lets plans move it to another availability zone
+    pub cross_az: bool,
+    // SLA penalty paid when the instance's host runs too hot
+    pub penalty: Option<PenaltyModel>,
+    pub storage: InstanceStorage,
+}
+
+impl InstancePlacement {
+    pub fn predicted_vcpu_demand(&self) -> f64 {
+        self.vcpus as f64 * self.predicted_load / 100.0
+    }
+}
+
+impl ClusterSnapshot {
+    pub fn new(
+        hosts: Vec<HostMetrics>,
+        instances: Vec<InstancePlacement>,
+        resources: HashMap<String, ResourceContext>,
+        storage_topology: Arc<StorageTopology>,
+        traffic: Arc<TrafficHints>,
+    ) -> Self {
+        Self {
+            hosts,
+            instances,
+            resources,
+            storage_topology,
+            traffic,
+            taken_at: Utc::now(),
+        }
+    }
+    
+    pub fn resource_context(&self, resource_id: &str) -> ResourceContext {
+        self.resources
+            .get(resource_id)
+            .cloned()
+            .unwrap_or_else(|| ResourceContext::unknown(resource_id))
+    }
+    
+    pub fn host(&self, host_id: &str) -> Option<&HostMetrics> {
+        self.hosts.iter().find(|h| h.host_id == host_id)
+    }
+    
+    pub fn instances_on<'a>(&'a self, host_id: &'a str) -> impl Iterator<Item = &'a InstancePlacement> {
+        self.instances.iter().filter(move |i| i.host_id == host_id)
+    }
+    
+    pub fn is_host_empty(&self, host_id: &str) -> bool {
+        self.instances_on(host_id).next().is_none()
+    }
+    
+    pub fn predicted_vcpu_demand(&self) -> f64 {
+        self.instances.iter().map(|i| i.predicted_vcpu_demand()).sum()
+    }
+    
+    // (vCPU, memory MB) free on the host under the forecast
+    pub fn predicted_headroom(&self, host: &HostMetrics) -> (f64, f64) {
+        let (mut vcpus, mut memory) = self.unmanaged_load(host);
+        for instance in self.instances_on(&host.host_id) {
+            vcpus += instance.predicted_vcpu_demand();
+            memory += instance.memory_mb as f64;
+        }
+        (host.total_vcpus as f64 - vcpus, host.total_memory_mb as f64 - memory)
+    }
+    
+    pub fn instance(&self, resource_id: &str) -> Option<&InstancePlacement> {
+        self.instances.iter().find(|i| i.resource_id == resource_id)
+    }
+    
+    // What moving the instance to the target host costs in storage terms
+    pub fn storage_migration(&self, instance: &InstancePlacement, target_host: &str) -> StorageMigration {
+        self.storage_topology.classify(&instance.storage, &instance.host_id, target_host)
+    }
+    
+    // Whether the instance may move to the target host at all: servers stay
+    // in their region, and their zone unless policy lets them leave it, and
+    // their storage has to be able to follow
+    pub fn can_migrate(&self, instance: &InstancePlacement, target_host: &str) -> bool {
+        let context = self.resources.get(&instance.resource_id);
+        let target = self.host(target_host);
+        let region = context.and_then(|context| context.region.as_ref());
+        let target_region = target.and_then(|host| host.region.as_ref());
+        if region.is_some() && target_region.is_some() && region != target_region {
+            return false;
+        }
+        if !instance.cross_az {
+            let zone = context.and_then(|context| context.availability_zone.as_ref());
+            if zone.is_some() && zone != target.and_then(|host| host.availability_zone.as_ref()) {
+                return false;
+            }
+        }
+        if self.breaks_server_group(instance, target_host) {
+            return false;
+        }
+        // Hosts reserved for other projects by their aggregates
+        if let Some(allowed) = target.and_then(|host| host.allowed_projects.as_ref()) {
+            if !context.and_then(|context| context.project_id.as_ref()).is_some_and(|project| allowed.contains(project)) {
+                return false;
+            }
+        }
+        self.storage_migration(instance, target_host).feasible
+    }
+    
+    // Hosts running the other members of the resource's server group
+    pub fn server_group_hosts(&self, resource_id: &str) -> HashSet<&str> {
+        let Some(group) = self.resources.get(resource_id).and_then(|context| context.server_group.as_ref()) else {
+            return HashSet::new();
+        };
+        self.instances.iter()
+            .filter(|i| i.resource_id != resource_id)
+            .filter(|i| {
+                self.resources.get(&i.resource_id)
+                    .and_then(|context| context.server_group.as_ref())
+                    .is_some_and(|g| g.id == group.id)
+            })
+            .map(|i| i.host_id.as_str())
+            .collect()
+    }
+    
+    // Whether the move breaks a hard server group policy, which Nova would
+    // refuse anyway: an affinity member can't leave its peers' host and an
+    // anti-affinity member can't join one of its peers
+    pub fn breaks_server_group(&self, instance: &InstancePlacement, target_host: &str) -> bool {
+        let Some(group) = self.resources.get(&instance.resource_id).and_then(|context| context.server_group.as_ref()) else {
+            return false;
+        };
+        let hosts = self.server_group_hosts(&instance.resource_id);
+        match group.policy {
+            ServerGroupPolicy::Affinity => !hosts.is_empty() && !hosts.contains(target_host),
+            ServerGroupPolicy::AntiAffinity => hosts.contains(target_host),
+            ServerGroupPolicy::SoftAffinity | ServerGroupPolicy::SoftAntiAffinity => false,
+        }
+    }
+    
+    // Traffic to peers the instance would keep local on the host
+    pub fn traffic_affinity(&self, resource_id: &str, host_id: &str) -> f64 {
+        self.traffic.affinity(resource_id, host_id, |peer| self.instance(peer).map(|i| i.host_id.clone()))
+    }
+    
+    // Whether the host runs instances that planners may not move
+    pub fn has_pinned_instances(&self, host_id: &str) -> bool {
+        self.instances_on(host_id).any(|i| i.pinned)
+    }
+    
+    // Whether the host runs instances that are not part of this snapshot
+    pub fn has_unmanaged_instances(&self, host: &HostMetrics) -> bool {
+        host.vm_count as usize > self.instances_on(&host.host_id).count()
+    }
+    
+    // (vCPU, memory MB) used on the host by instances outside the snapshot,
+    // estimated from current usage since there is no forecast for them
+    pub fn unmanaged_load(&self, host: &HostMetrics) -> (f64, f64) {
+        if !self.has_unmanaged_instances(host) {
+            return (0.0, 0.0);
+        }
+        
+        let known_vcpus: f64 = self.instances_on(&host.host_id).map(|i| i.predicted_vcpu_demand()).sum();
+        let known_memory: f64 = self.instances_on(&host.host_id).map(|i| i.memory_mb as f64).sum();
+        let current_vcpus = host.total_vcpus as f64 * host.cpu_utilization / 100.0;
+        let current_memory = host.total_memory_mb.saturating_sub(host.available_memory_mb) as f64;
+        
+        ((current_vcpus - known_vcpus).max(0.0), (current_memory - known_memory).max(0.0))
+    }
+}
@@ -0,0 +1,459 @@
+use std::collections::{HashMap, HashSet};
+use tracing::debug;
+
+use crate::config::ConsolidationConfig;
+use super::cluster::{ClusterSnapshot, InstancePlacement};
+use super::disruption::DisruptionAllowance;
+use super::energy::PowerModels;
+use super::placement::HostMetrics;
+
+pub struct ConsolidationPlanner {
+    config: ConsolidationConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct MigrationStep {
+    pub resource_id: String,
+    pub source_host: String,
+    pub target_host: String,
+    pub predicted_vcpus: f64,
+    pub memory_mb: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ConsolidationPlan {
+    // Ordered so each source host is drained completely before the next one
+    pub steps: Vec<MigrationStep>,
+    pub hosts_freed: Vec<String>,
+    // Only set when planning against power models
+    pub energy: Option<EnergyEstimate>,
+    // Steps held back by disruption budgets, left for later cycles
+    pub deferred_by_budget: usize,
+}
+
+impl ConsolidationPlan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+// Predicted draw of the hosts involved in a plan, before and after it runs
+#[derive(Debug, Clone, Copy)]
+pub struct EnergyEstimate {
+    pub watts_before: f64,
+    pub watts_after: f64,
+}
+
+impl EnergyEstimate {
+    pub fn watts_saved(&self) -> f64 {
+        self.watts_before - self.watts_after
+    }
+    
+    pub fn kwh_saved(&self, hours: f64) -> f64 {
+        self.watts_saved() * hours / 1000.0
+    }
+}
+
+// Packing state of a single host while the plan is being built
+#[derive(Debug, Clone)]
+struct Bin {
+    host_id: String,
+    total_vcpus: f64,
+    vcpu_capacity: f64,
+    memory_capacity: f64,
+    vcpu_used: f64,
+    memory_used: f64,
+}
+
+impl Bin {
+    fn fits(&self, instance: &InstancePlacement) -> bool {
+        self.vcpu_used + instance.predicted_vcpu_demand() <= self.vcpu_capacity
+            && self.memory_used + instance.memory_mb as f64 <= self.memory_capacity
+    }
+    
+    fn add(&mut self, instance: &InstancePlacement) {
+        self.vcpu_used += instance.predicted_vcpu_demand();
+        self.memory_used += instance.memory_mb as f64;
+    }
+    
+    fn utilization(&self) -> f64 {
+        if self.total_vcpus > 0.0 {
+            self.vcpu_used / self.total_vcpus * 100.0
+        } else {
+            0.0
+        }
+    }
+}
+
+impl ConsolidationPlanner {
+    pub fn new(config: ConsolidationConfig) -> Self {
+        Self { config }
+    }
+    
+    // With power models the plan predicted to draw the least wins; without,
+    // the one freeing the most hosts. Either is cut down to what disruption
+    // budgets allow right now
+    pub fn plan(
+        &self,
+        snapshot: &ClusterSnapshot,
+        excluded_hosts: &HashSet<String>,
+        power: Option<&PowerModels>,
+        allowance: &DisruptionAllowance,
+    ) -> ConsolidationPlan {
+        let plan = self.best_plan(snapshot, excluded_hosts, power);
+        Self::within_budgets(plan, snapshot, allowance.clone())
+    }
+    
+    // Keeps each host's steps in order when the budgets admit all of them,
+    // and defers the host's whole drain otherwise, so no host is left half
+    // emptied; deferred hosts are drained by a later plan
+    fn within_budgets(
+        mut plan: ConsolidationPlan,
+        snapshot: &ClusterSnapshot,
+        mut allowance: DisruptionAllowance,
+    ) -> ConsolidationPlan {
+        let total = plan.steps.len();
+        let mut admitted = Vec::with_capacity(total);
+        for host_steps in plan.steps.chunk_by(|a, b| a.source_host == b.source_host) {
+            let mut trial = allowance.clone();
+            if host_steps.iter().all(|step| trial.try_take(&snapshot.resource_context(&step.resource_id))) {
+                allowance = trial;
+                admitted.extend_from_slice(host_steps);
+            }
+        }
+        plan.steps = admitted;
+        plan.deferred_by_budget = total - plan.steps.len();
+        
+        if plan.deferred_by_budget > 0 {
+            let steps = &plan.steps;
+            plan.hosts_freed.retain(|host_id| {
+                snapshot.instances_on(host_id).all(|i| steps.iter().any(|s| s.resource_id == i.resource_id))
+            });
+            debug!("Disruption budgets defer {} consolidation steps", plan.deferred_by_budget);
+        }
+        plan
+    }
+    
+    fn best_plan(
+        &self,
+        snapshot: &ClusterSnapshot,
+        excluded_hosts: &HashSet<String>,
+        power: Option<&PowerModels>,
+    ) -> ConsolidationPlan {
+        let hosts: Vec<_> = snapshot.hosts.iter()
+            .filter(|h| !excluded_hosts.contains(&h.host_id))
+            .collect();
+        
+        // Hosts running instances we don't know about or may not move can't
+        // be drained safely
+        let mut pinned = HashSet::new();
+        let mut candidates = Vec::new();
+        for host in &hosts {
+            if host.vm_count == 0 && snapshot.is_host_empty(&host.host_id) {
+                continue;
+            }
+            if snapshot.has_unmanaged_instances(host) || snapshot.has_pinned_instances(&host.host_id) {
+                pinned.insert(host.host_id.clone());
+            }
+            candidates.push(*host);
+        }
+        
+        // Keep the most loaded hosts so the fewest instances have to move
+        let predicted_load = |host_id: &str| -> f64 {
+            snapshot.instances_on(host_id).map(|i| i.predicted_vcpu_demand()).sum()
+        };
+        candidates.sort_by(|a, b| {
+            pinned.contains(&b.host_id).cmp(&pinned.contains(&a.host_id))
+                .then(predicted_load(&b.host_id).total_cmp(&predicted_load(&a.host_id)))
+        });
+        
+        let min_kept = pinned.len().max(1);
+        let power = match power {
+            Some(power) => power,
+            None => return self.fewest_hosts(snapshot, &candidates, min_kept),
+        };
+        
+        // Also try keeping the hosts that run the target load most cheaply,
+        // which can beat keeping the busiest ones on mixed hardware
+        let target = self.config.target_utilization;
+        let watts_per_vcpu = |host: &HostMetrics| -> f64 {
+            power.predict(&host.host_id, target * 100.0) / (host.total_vcpus as f64 * target).max(1.0)
+        };
+        let mut efficient = candidates.clone();
+        efficient.sort_by(|a, b| {
+            pinned.contains(&b.host_id).cmp(&pinned.contains(&a.host_id))
+                .then(watts_per_vcpu(a).total_cmp(&watts_per_vcpu(b)))
+        });
+        
+        let mut best: Option<ConsolidationPlan> = None;
+        for ordering in [&candidates, &efficient] {
+            for kept in min_kept..ordering.len() {
+                let plan = match self.try_pack(snapshot, &ordering[..kept], &ordering[kept..], Some(power)) {
+                    Some(plan) => plan,
+                    None => continue,
+                };
+                // Keeping more hosts only frees fewer
+                if plan.hosts_freed.len() < self.config.min_hosts_freed {
+                    break;
+                }
+                
+                let watts = |p: &ConsolidationPlan| p.energy.map(|e| e.watts_after).unwrap_or(f64::MAX);
+                if best.as_ref().map(|b| watts(&plan) < watts(b)).unwrap_or(true) {
+                    best = Some(plan);
+                }
+            }
+        }
+        
+        match best {
+            Some(plan) if plan.energy.map(|e| e.watts_saved() > 0.0).unwrap_or(false) => {
+                debug!(
+                    "Consolidation plan frees {} hosts with {} migrations, saving {:.0} W",
+                    plan.hosts_freed.len(),
+                    plan.steps.len(),
+                    plan.energy.map(|e| e.watts_saved()).unwrap_or(0.0)
+                );
+                plan
+            }
+            _ => ConsolidationPlan::default(),
+        }
+    }
+    
+    fn fewest_hosts(
+        &self,
+        snapshot: &ClusterSnapshot,
+        candidates: &[&HostMetrics],
+        min_kept: usize,
+    ) -> ConsolidationPlan {
+        for kept in min_kept..candidates.len() {
+            if let Some(plan) = self.try_pack(snapshot, &candidates[..kept], &candidates[kept..], None) {
+                if plan.hosts_freed.len() >= self.config.min_hosts_freed {
+                    debug!(
+                        "Consolidation plan frees {} hosts with {} migrations",
+                        plan.hosts_freed.len(),
+                        plan.steps.len()
+                    );
+                    return plan;
+                }
+                break;
+            }
+        }
+        
+        ConsolidationPlan::default()
+    }
+    
+    fn try_pack(
+        &self,
+        snapshot: &ClusterSnapshot,
+        kept: &[&HostMetrics],
+        drained: &[&HostMetrics],
+        power: Option<&PowerModels>,
+    ) -> Option<ConsolidationPlan> {
+        let target = self.config.target_utilization;
+        
+        // Instances already on kept hosts stay where they are
+        let mut bins: Vec<Bin> = kept.iter()
+            .map(|host| {
+                let (unmanaged_vcpus, unmanaged_memory) = snapshot.unmanaged_load(host);
+                let mut bin = Bin {
+                    host_id: host.host_id.clone(),
+                    total_vcpus: host.total_vcpus as f64,
+                    vcpu_capacity: host.total_vcpus as f64 * target,
+                    memory_capacity: host.total_memory_mb as f64 * target,
+                    vcpu_used: unmanaged_vcpus,
+                    memory_used: unmanaged_memory,
+                };
+                for instance in snapshot.instances_on(&host.host_id) {
+                    bin.add(instance);
+                }
+                bin
+            })
+            .collect();
+        
+        // First-fit-decreasing over predicted demand for everything being drained
+        let mut moving: Vec<&InstancePlacement> = drained.iter()
+            .flat_map(|host| snapshot.instances_on(&host.host_id))
+            .collect();
+        moving.sort_by(|a, b| b.predicted_vcpu_demand().total_cmp(&a.predicted_vcpu_demand()));
+        
+        // Among the bins that fit, prefer the one closest to the instance's
+        // traffic peers, counting peers already assigned by this plan
+        let mut assignments: HashMap<String, String> = HashMap::new();
+        for instance in &moving {
+            let locate = |peer: &str| {
+                assignments.get(peer).cloned()
+                    .or_else(|| snapshot.instance(peer).map(|i| i.host_id.clone()))
+            };
+            let mut best: Option<(usize, f64)> = None;
+            for (idx, bin) in bins.iter().enumerate() {
+                if !bin.fits(instance) || !snapshot.can_migrate(instance, &bin.host_id) {
+                    continue;
+                }
+                let affinity = snapshot.traffic.affinity(&instance.resource_id, &bin.host_id, locate);
+                if best.map(|(_, best_affinity)| affinity > best_affinity).unwrap_or(true) {
+                    best = Some((idx, affinity));
+                }
+                if snapshot.traffic.is_empty() {
+                    break;
+                }
+            }
+            
+            let bin = &mut bins[best?.0];
+            bin.add(instance);
+            assignments.insert(instance.resource_id.clone(), bin.host_id.clone());
+        }
+        
+        // Drain the emptiest hosts first so capacity is released early in the plan
+        let mut drain_order: Vec<_> = drained.iter().map(|h| h.host_id.clone()).collect();
+        drain_order.sort_by_key(|host_id| snapshot.instances_on(host_id).count());
+        
+        let mut steps = Vec::new();
+        for host_id in &drain_order {
+            let mut host_instances: Vec<_> = snapshot.instances_on(host_id).collect();
+            host_instances.sort_by(|a, b| b.predicted_vcpu_demand().total_cmp(&a.predicted_vcpu_demand()));
+            
+            for instance in host_instances {
+                steps.push(MigrationStep {
+                    resource_id: instance.resource_id.clone(),
+                    source_host: host_id.clone(),
+                    target_host: assignments[&instance.resource_id].clone(),
+                    predicted_vcpus: instance.predicted_vcpu_demand(),
+                    memory_mb: instance.memory_mb,
+                });
+            }
+        }
+        
+        let energy = power.map(|power| {
+            let watts_before: f64 = kept.iter().chain(drained)
+                .map(|host| {
+                    let (free_vcpus, _) = snapshot.predicted_headroom(host);
+                    let total = host.total_vcpus.max(1) as f64;
+                    power.predict(&host.host_id, (total - free_vcpus) / total * 100.0)
+                })
+                .sum();
+            let watts_after = bins.iter().map(|bin| power.predict(&bin.host_id, bin.utilization())).sum::<f64>()
+                + drain_order.iter().map(|host_id| power.freed_host_watts(host_id)).sum::<f64>();
+            EnergyEstimate { watts_before, watts_after }
+        });
+        
+        Some(ConsolidationPlan {
+            steps,
+            hosts_freed: drain_order,
+            energy,
+            deferred_by_budget: 0,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use std::sync::Arc;
+    use crate::scheduler::cluster::ResourceContext;
+    use crate::scheduler::disruption::DisruptionBudget;
+    use crate::scheduler::policy::RuleSelector;
+    
+    fn host(host_id: &str, vm_count: u32) -> HostMetrics {
+        HostMetrics {
+            host_id: host_id.to_string(),
+            cpu_utilization: 0.0,
+            memory_utilization: 0.0,
+            disk_utilization: 0.0,
+            network_utilization: 0.0,
+            vm_count,
+            total_vcpus: 16,
+            total_memory_mb: 65536,
+            available_vcpus: 16,
+            available_memory_mb: 65536,
+            availability_zone: None,
+            aggregates: Vec::new(),
+            allowed_projects: None,
+            region: None,
+            resource_class: None,
+            last_updated: Utc::now(),
+        }
+    }
+    
+    // Fully loaded, so its predicted demand is its vCPU count
+    fn instance(resource_id: &str, host_id: &str, vcpus: u32) -> InstancePlacement {
+        InstancePlacement {
+            resource_id: resource_id.to_string(),
+            host_id: host_id.to_string(),
+            vcpus,
+            memory_mb: 2048,
+            predicted_load: 100.0,
+            pinned: false,
+            cross_az: false,
+            penalty: None,
+            storage: Default::default(),
+        }
+    }
+    
+    // "a" keeps compute-1 busiest; "b" belongs to project p
+    fn snapshot() -> ClusterSnapshot {
+        let mut resources = HashMap::new();
+        resources.insert("b".to_string(), ResourceContext {
+            resource_id: "b".to_string(),
+            project_id: Some("p".to_string()),
+            ..Default::default()
+        });
+        ClusterSnapshot::new(
+            vec![host("compute-1", 1), host("compute-2", 1), host("compute-3", 1)],
+            vec![
+                instance("a", "compute-1", 6),
+                instance("b", "compute-2", 4),
+                instance("c", "compute-3", 2),
+            ],
+            resources,
+            Arc::new(Default::default()),
+            Arc::new(Default::default()),
+        )
+    }
+    
+    fn moves(plan: &ConsolidationPlan) -> Vec<(&str, &str, &str)> {
+        plan.steps.iter()
+            .map(|s| (s.resource_id.as_str(), s.source_host.as_str(), s.target_host.as_str()))
+            .collect()
+    }
+    
+    #[test]
+    fn packs_drained_hosts_onto_the_busiest() {
+        let planner = ConsolidationPlanner::new(ConsolidationConfig::default());
+        let plan = planner.plan(&snapshot(), &HashSet::new(), None, &DisruptionAllowance::default());
+        
+        assert_eq!(moves(&plan), [("b", "compute-2", "compute-1"), ("c", "compute-3", "compute-1")]);
+        assert_eq!(plan.hosts_freed, ["compute-2", "compute-3"]);
+        assert_eq!(plan.deferred_by_budget, 0);
+    }
+    
+    #[test]
+    fn keeps_more_hosts_when_the_target_utilization_is_lower() {
+        // 16 vCPUs at 0.7 leave room for 11.2, so only compute-3 fits alongside "a"
+        let planner = ConsolidationPlanner::new(ConsolidationConfig {
+            target_utilization: 0.7,
+            ..ConsolidationConfig::default()
+        });
+        let plan = planner.plan(&snapshot(), &HashSet::new(), None, &DisruptionAllowance::default());
+        
+        assert_eq!(moves(&plan), [("c", "compute-3", "compute-1")]);
+        assert_eq!(plan.hosts_freed, ["compute-3"]);
+    }
+    
+    #[test]
+    fn defers_whole_host_drains_an_exhausted_budget_covers() {
+        let allowance = DisruptionAllowance::new(vec![DisruptionBudget {
+            name: "project-p".to_string(),
+            selector: RuleSelector {
+                project: Some("p".to_string()),
+                ..Default::default()
+            },
+            max_disrupted: 0,
+        }]);
+        let planner = ConsolidationPlanner::new(ConsolidationConfig::default());
+        let plan = planner.plan(&snapshot(), &HashSet::new(), None, &allowance);
+        
+        assert_eq!(moves(&plan), [("c", "compute-3", "compute-1")]);
+        assert_eq!(plan.hosts_freed, ["compute-3"]);
+        assert_eq!(plan.deferred_by_budget, 1);
+    }
+}
@@ -0,0 +1,159 @@
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::storage::Storage;
+use super::edf::InFlightAction;
+use super::policy::Aggressiveness;
+use super::resource_scheduler::SchedulingDecision;
+
+const CONTROL_COLLECTION: &str = "scheduler_control";
+const CONTROL_KEY: &str = "state";
+
+// Runtime switches operators flip during incidents. They're persisted so a
+// restart doesn't silently resume a paused scheduler, and re-read every
+// cycle so they reach a new leader after failover
+pub struct SchedulerControl {
+    storage: Storage,
+    state: ArcSwap<ControlState>,
+    // Held across an update's read, change and write
+    updating: Mutex<()>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    // Decisions are carried out
+    #[default]
+    Enforce,
+    // Automatic decisions are only recorded as recommendations; operator
+    // requests still run
+    Recommend,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ControlState {
+    pub paused: bool,
+    pub pause_reason: Option<String>,
+    pub mode: ExecutionMode,
+    // Applied to every resource unless a policy override sets its own
+    pub aggressiveness: Aggressiveness,
+    pub updated_by: Option<String>,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+// What the scheduler is doing right now, for operators deciding whether to step in
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerStatus {
+    pub control: ControlState,
+    pub is_leader: bool,
+    // Earliest deadline first
+    pub queued: Vec<SchedulingDecision>,
+    pub in_flight: Vec<InFlightAction>,
+}
+
+impl SchedulerControl {
+    pub async fn load(storage: Storage) -> Result<Self> {
+        let state: ControlState = storage.get(CONTROL_COLLECTION, CONTROL_KEY).await?.unwrap_or_default();
+        if state.paused {
+            warn!(
+                "Scheduler starts paused (by {}: {})",
+                state.updated_by.as_deref().unwrap_or("unknown"),
+                state.pause_reason.as_deref().unwrap_or("no reason given")
+            );
+        }
+        ::metrics::gauge!("scheduler_paused").set(if state.paused { 1.0 } else { 0.0 });
+        
+        Ok(Self {
+            storage,
+            state: ArcSwap::from_pointee(state),
+            updating: Mutex::new(()),
+        })
+    }
+    
+    // Takes in what another instance stored; keeps what it had when the
+    // storage can't be read
+    pub async fn refresh(&self) {
+        let state: ControlState = match self.storage.get(CONTROL_COLLECTION, CONTROL_KEY).await {
+            Ok(state) => state.unwrap_or_default(),
+            Err(e) => {
+                warn!("Failed to re-read the scheduler control state: {}", e);
+                return;
+            }
+        };
+        if state.paused != self.is_paused() {
+            info!(
+                "Scheduler {} by {}",
+                if state.paused { "paused" } else { "resumed" },
+                state.updated_by.as_deref().unwrap_or("unknown")
+            );
+        }
+        ::metrics::gauge!("scheduler_paused").set(if state.paused { 1.0 } else { 0.0 });
+        self.state.store(state.into());
+    }
+    
+    pub fn state(&self) -> ControlState {
+        self.state.load().as_ref().clone()
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.state.load().paused
+    }
+    
+    pub fn mode(&self) -> ExecutionMode {
+        self.state.load().mode
+    }
+    
+    pub fn aggressiveness(&self) -> Aggressiveness {
+        self.state.load().aggressiveness
+    }
+    
+    pub async fn pause(&self, operator: &str, reason: Option<String>) -> Result<ControlState> {
+        info!(
+            "Scheduler paused by {} ({})",
+            operator,
+            reason.as_deref().unwrap_or("no reason given")
+        );
+        self.update(operator, |state| {
+            state.paused = true;
+            state.pause_reason = reason;
+        }).await
+    }
+    
+    pub async fn resume(&self, operator: &str) -> Result<ControlState> {
+        info!("Scheduler resumed by {}", operator);
+        self.update(operator, |state| {
+            state.paused = false;
+            state.pause_reason = None;
+        }).await
+    }
+    
+    pub async fn set_mode(&self, operator: &str, mode: ExecutionMode) -> Result<ControlState> {
+        info!("Scheduler switched to {:?} mode by {}", mode, operator);
+        self.update(operator, |state| state.mode = mode).await
+    }
+    
+    pub async fn set_aggressiveness(&self, operator: &str, aggressiveness: Aggressiveness) -> Result<ControlState> {
+        info!("Scheduler aggressiveness set to {:?} by {}", aggressiveness, operator);
+        self.update(operator, |state| state.aggressiveness = aggressiveness).await
+    }
+    
+    // Stored before it takes effect, so a failed write changes nothing. The
+    // change applies to what is stored, so it doesn't undo one another
+    // instance made since our last refresh
+    async fn update(&self, operator: &str, change: impl FnOnce(&mut ControlState)) -> Result<ControlState> {
+        let _updating = self.updating.lock().await;
+        let mut state: ControlState = self.storage.get(CONTROL_COLLECTION, CONTROL_KEY).await?.unwrap_or_default();
+        change(&mut state);
+        state.updated_by = Some(operator.to_string());
+        state.updated_at = Some(Utc::now());
+        
+        self.storage.put(CONTROL_COLLECTION, CONTROL_KEY, &state).await?;
+        ::metrics::gauge!("scheduler_paused").set(if state.paused { 1.0 } else { 0.0 });
+        self.state.store(state.clone().into());
+        Ok(state)
+    }
+}
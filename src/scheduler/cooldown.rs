@@ -0,0 +1,86 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tracing::warn;
+
+use crate::config::CooldownConfig;
+use crate::openstack::Client;
+
+// Keeps the scheduler off servers that were just migrated, resized or
+// rebooted, going by Nova's instance actions so changes made by operators
+// or other tools count as well as our own
+pub struct ActionCooldown {
+    config: CooldownConfig,
+    // Only servers something was decided for are looked up
+    recent: RwLock<HashMap<String, RecentAction>>,
+}
+
+struct RecentAction {
+    fetched_at: Instant,
+    // The newest action of a configured kind, and when it started
+    last: Option<(String, DateTime<Utc>)>,
+}
+
+impl ActionCooldown {
+    pub fn new(config: CooldownConfig) -> Self {
+        Self {
+            config,
+            recent: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    // Why the server is still cooling down; None once it may be acted on.
+    // Instance actions that can't be read hold nothing off
+    pub async fn active(&self, client: &Client, server_id: &str) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        
+        let last = match self.cached(server_id).await {
+            Some(last) => last,
+            None => match client.nova.list_instance_actions(server_id).await {
+                Ok(actions) => {
+                    let last = actions.into_iter()
+                        .find(|action| self.config.actions.contains(&action.action))
+                        .map(|action| (action.action.clone(), action.started_at()));
+                    self.remember(server_id, last.clone()).await;
+                    last
+                }
+                Err(e) => {
+                    warn!("Failed to read instance actions of {}: {}", server_id, e);
+                    return None;
+                }
+            },
+        };
+        
+        let (action, at) = last?;
+        let until = at + ChronoDuration::seconds(self.config.cooldown_seconds as i64);
+        (Utc::now() < until).then(|| format!("{} at {}, cooling down until {}", action, at, until))
+    }
+    
+    // Starts the cooldown for an action we took without waiting for Nova's
+    // record of it to be read
+    pub async fn record(&self, server_id: &str, action: &str) {
+        if self.config.enabled {
+            self.remember(server_id, Some((action.to_string(), Utc::now()))).await;
+        }
+    }
+    
+    async fn cached(&self, server_id: &str) -> Option<Option<(String, DateTime<Utc>)>> {
+        let refresh = Duration::from_secs(self.config.refresh_seconds);
+        self.recent.read().await.get(server_id)
+            .filter(|recent| recent.fetched_at.elapsed() < refresh)
+            .map(|recent| recent.last.clone())
+    }
+    
+    async fn remember(&self, server_id: &str, last: Option<(String, DateTime<Utc>)>) {
+        let refresh = Duration::from_secs(self.config.refresh_seconds);
+        let mut recent = self.recent.write().await;
+        recent.retain(|_, recent| recent.fetched_at.elapsed() < refresh);
+        recent.insert(server_id.to_string(), RecentAction {
+            fetched_at: Instant::now(),
+            last,
+        });
+    }
+}
@@ -0,0 +1,141 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use tokio::sync::RwLock;
+use tracing::info;
+
+use crate::error::SchedulerError;
+use crate::storage::Storage;
+use super::cluster::{ClusterSnapshot, ResourceContext};
+use super::policy::RuleSelector;
+
+const BUDGET_COLLECTION: &str = "disruption_budgets";
+
+// Tenant-defined cap on how many matching instances may be migrated or
+// restarted at the same time, in the spirit of Kubernetes PodDisruptionBudgets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DisruptionBudget {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub selector: RuleSelector,
+    // Zero forbids voluntary disruptions entirely
+    pub max_disrupted: u32,
+}
+
+impl DisruptionBudget {
+    pub fn validate(&self) -> Result<()> {
+        if self.name.is_empty() {
+            return Err(SchedulerError::PolicyError("Disruption budget needs a name".to_string()).into());
+        }
+        if self.selector.project.is_none() && self.selector.flavor.is_none() && self.selector.metadata.is_empty() {
+            return Err(SchedulerError::PolicyError(format!(
+                "Disruption budget '{}' must match on project, flavor or metadata",
+                self.name
+            )).into());
+        }
+        Ok(())
+    }
+}
+
+pub struct DisruptionBudgets {
+    storage: Storage,
+    budgets: RwLock<HashMap<String, DisruptionBudget>>,
+}
+
+// Disruptions each budget still admits, taken down as actions are planned
+// or started
+#[derive(Debug, Clone, Default)]
+pub struct DisruptionAllowance {
+    budgets: Vec<DisruptionBudget>,
+    remaining: HashMap<String, u32>,
+}
+
+impl DisruptionAllowance {
+    // Every budget untouched
+    pub fn new(budgets: Vec<DisruptionBudget>) -> Self {
+        Self {
+            remaining: budgets.iter().map(|b| (b.name.clone(), b.max_disrupted)).collect(),
+            budgets,
+        }
+    }
+    
+    // First budget covering the resource that has nothing left
+    pub fn exhausted_budget(&self, context: &ResourceContext) -> Option<String> {
+        self.budgets.iter()
+            .filter(|b| b.selector.matches(context))
+            .find(|b| self.remaining.get(&b.name).copied().unwrap_or(0) == 0)
+            .map(|b| b.name.clone())
+    }
+    
+    pub fn take(&mut self, context: &ResourceContext) {
+        for budget in self.budgets.iter().filter(|b| b.selector.matches(context)) {
+            if let Some(remaining) = self.remaining.get_mut(&budget.name) {
+                *remaining = remaining.saturating_sub(1);
+            }
+        }
+    }
+    
+    // Takes from every covering budget if all of them still admit it
+    pub fn try_take(&mut self, context: &ResourceContext) -> bool {
+        if self.exhausted_budget(context).is_some() {
+            return false;
+        }
+        self.take(context);
+        true
+    }
+}
+
+impl DisruptionBudgets {
+    pub async fn load(storage: Storage) -> Result<Self> {
+        let budgets: Vec<DisruptionBudget> = storage.list(BUDGET_COLLECTION).await?;
+        info!("Loaded {} disruption budgets from storage", budgets.len());
+        
+        Ok(Self {
+            storage,
+            budgets: RwLock::new(budgets.into_iter().map(|b| (b.name.clone(), b)).collect()),
+        })
+    }
+    
+    pub async fn list(&self) -> Vec<DisruptionBudget> {
+        let mut budgets: Vec<_> = self.budgets.read().await.values().cloned().collect();
+        budgets.sort_by(|a, b| a.name.cmp(&b.name));
+        budgets
+    }
+    
+    pub async fn get(&self, name: &str) -> Option<DisruptionBudget> {
+        self.budgets.read().await.get(name).cloned()
+    }
+    
+    pub async fn put(&self, budget: DisruptionBudget) -> Result<DisruptionBudget> {
+        budget.validate()?;
+        
+        let mut budgets = self.budgets.write().await;
+        self.storage.put(BUDGET_COLLECTION, &budget.name, &budget).await?;
+        budgets.insert(budget.name.clone(), budget.clone());
+        
+        info!("Stored disruption budget {}", budget.name);
+        Ok(budget)
+    }
+    
+    pub async fn delete(&self, name: &str) -> Result<bool> {
+        let mut budgets = self.budgets.write().await;
+        let stored = self.storage.delete(BUDGET_COLLECTION, name).await?;
+        let removed = budgets.remove(name).is_some();
+        Ok(stored || removed)
+    }
+    
+    // What's left of each budget given the resources currently disrupted
+    pub async fn allowance<'a>(
+        &self,
+        snapshot: &ClusterSnapshot,
+        disrupted: impl IntoIterator<Item = &'a str>,
+    ) -> DisruptionAllowance {
+        let budgets: Vec<DisruptionBudget> = self.budgets.read().await.values().cloned().collect();
+        let mut allowance = DisruptionAllowance::new(budgets);
+        
+        for resource_id in disrupted {
+            allowance.take(&snapshot.resource_context(resource_id));
+        }
+        allowance
+    }
+}
@@ -0,0 +1,368 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::storage::Storage;
+use super::resize::ResizeTarget;
+use super::resource_scheduler::SchedulingDecision;
+
+const MAX_RECORDED_MISSES: usize = 200;
+const QUEUE_COLLECTION: &str = "decision_queue";
+const IN_FLIGHT_COLLECTION: &str = "in_flight_actions";
+
+// Earliest-deadline-first queue of scheduling decisions; decisions that
+// can't run in one cycle stay queued for the next. Pending decisions and
+// started migrations and resizes are persisted so a restart picks them back up
+pub struct DecisionQueue {
+    storage: Storage,
+    pending: RwLock<Vec<SchedulingDecision>>,
+    // Drained this cycle and not yet settled or deferred
+    draining: RwLock<HashMap<String, SchedulingDecision>>,
+    in_flight: RwLock<HashMap<String, InFlightAction>>,
+    stats: RwLock<DeadlineStats>,
+}
+
+// Migration or resize handed to Nova whose completion hasn't been
+// confirmed yet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct InFlightAction {
+    pub decision: SchedulingDecision,
+    pub source_host: Option<String>,
+    // The source host for a resize, which Nova may move elsewhere
+    pub target_host: String,
+    pub started_at: DateTime<Utc>,
+    // Set for a resize
+    #[serde(default)]
+    pub resize: Option<ResizeTarget>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DeadlineMiss {
+    pub resource_id: String,
+    pub action: String,
+    pub deadline: DateTime<Utc>,
+    pub executed_at: DateTime<Utc>,
+    pub lateness_seconds: i64,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DeadlineStats {
+    pub executed: u64,
+    pub missed: u64,
+    pub queued: usize,
+    pub in_flight: usize,
+    pub recent_misses: VecDeque<DeadlineMiss>,
+}
+
+impl DecisionQueue {
+    pub async fn load(storage: Storage) -> Result<Self> {
+        let pending: Vec<SchedulingDecision> = storage.list(QUEUE_COLLECTION).await?;
+        let in_flight: Vec<InFlightAction> = storage.list(IN_FLIGHT_COLLECTION).await?;
+        info!(
+            "Restored {} queued decisions and {} in-flight actions from storage",
+            pending.len(),
+            in_flight.len()
+        );
+        ::metrics::gauge!("scheduler_queue_depth").set(pending.len() as f64);
+        
+        Ok(Self {
+            storage,
+            pending: RwLock::new(pending),
+            draining: RwLock::new(HashMap::new()),
+            in_flight: RwLock::new(in_flight.into_iter().map(|a| (a.decision.id.clone(), a)).collect()),
+            stats: RwLock::new(DeadlineStats::default()),
+        })
+    }
+    
+    // A fresh decision replaces whatever was still queued for the same
+    // resource, except that automatic ones don't displace operator requests.
+    // It takes over the queued one's id, attempts and, when earlier, its
+    // deadline, so a resource that keeps being decided on still comes due
+    pub async fn enqueue(&self, decisions: Vec<SchedulingDecision>) {
+        let mut pending = self.pending.write().await;
+        for mut decision in decisions {
+            if decision.rationale.requested_by.is_none()
+                && pending.iter().any(|queued| {
+                    queued.resource_id == decision.resource_id && queued.rationale.requested_by.is_some()
+                })
+            {
+                debug!(
+                    "Dropping {} of {}: an operator request is queued",
+                    decision.action.as_str(),
+                    decision.resource_id
+                );
+                continue;
+            }
+            if let Some(queued) = pending.iter().find(|queued| queued.resource_id == decision.resource_id) {
+                decision.id = queued.id.clone();
+                decision.deadline = decision.deadline.min(queued.deadline);
+                decision.attempts = decision.attempts.max(queued.attempts);
+            }
+            let replaced: Vec<String> = pending.iter()
+                .filter(|queued| queued.resource_id == decision.resource_id && queued.id != decision.id)
+                .map(|queued| queued.id.clone())
+                .collect();
+            for id in replaced {
+                self.forget(QUEUE_COLLECTION, &id).await;
+            }
+            pending.retain(|queued| queued.resource_id != decision.resource_id);
+            self.persist(QUEUE_COLLECTION, &decision.id, &decision).await;
+            pending.push(decision);
+        }
+        ::metrics::gauge!("scheduler_queue_depth").set(pending.len() as f64);
+    }
+    
+    // Everything queued, earliest deadline first; priority breaks ties. The
+    // stored copies stay until each decision is settled, so a crash or an
+    // error mid-cycle loses nothing
+    pub async fn drain(&self) -> Vec<SchedulingDecision> {
+        let mut decisions = std::mem::take(&mut *self.pending.write().await);
+        ::metrics::gauge!("scheduler_queue_depth").set(0.0);
+        decisions.sort_by(|a, b| a.deadline.cmp(&b.deadline).then(a.priority.cmp(&b.priority)));
+        self.draining.write().await.extend(decisions.iter().map(|d| (d.id.clone(), d.clone())));
+        decisions
+    }
+    
+    // A drained decision reached a terminal outcome; its stored copy can go.
+    // Ids that weren't drained are left alone
+    pub async fn settle(&self, decision_id: &str) {
+        if self.draining.write().await.remove(decision_id).is_some() {
+            self.forget(QUEUE_COLLECTION, decision_id).await;
+        }
+    }
+    
+    pub async fn defer(&self, decision: SchedulingDecision) {
+        debug!(
+            "Deferring {} of {} (deadline {})",
+            decision.action.as_str(),
+            decision.resource_id,
+            decision.deadline
+        );
+        self.draining.write().await.remove(&decision.id);
+        let mut pending = self.pending.write().await;
+        self.persist(QUEUE_COLLECTION, &decision.id, &decision).await;
+        pending.push(decision);
+        ::metrics::gauge!("scheduler_queue_depth").set(pending.len() as f64);
+    }
+    
+    // Ends a cycle: drained decisions that were neither settled nor deferred
+    // go back on the queue, and keep their stored copies
+    pub async fn checkpoint(&self) {
+        let unsettled: Vec<SchedulingDecision> = self.draining.write().await.drain().map(|(_, d)| d).collect();
+        if unsettled.is_empty() {
+            return;
+        }
+        warn!("Requeueing {} decisions left unsettled by the last cycle", unsettled.len());
+        let mut pending = self.pending.write().await;
+        pending.extend(unsettled);
+        ::metrics::gauge!("scheduler_queue_depth").set(pending.len() as f64);
+    }
+    
+    pub async fn start_action(&self, decision: &SchedulingDecision, source_host: Option<String>, target_host: &str) {
+        self.track(InFlightAction {
+            decision: decision.clone(),
+            source_host,
+            target_host: target_host.to_string(),
+            started_at: Utc::now(),
+            resize: None,
+        }).await;
+    }
+    
+    pub async fn start_resize(&self, decision: &SchedulingDecision, host: Option<String>, target: ResizeTarget) {
+        self.track(InFlightAction {
+            decision: decision.clone(),
+            target_host: host.clone().unwrap_or_default(),
+            source_host: host,
+            started_at: Utc::now(),
+            resize: Some(target),
+        }).await;
+    }
+    
+    async fn track(&self, action: InFlightAction) {
+        let id = action.decision.id.clone();
+        self.persist(IN_FLIGHT_COLLECTION, &id, &action).await;
+        self.forget(QUEUE_COLLECTION, &id).await;
+        self.draining.write().await.remove(&id);
+        self.in_flight.write().await.insert(id, action);
+    }
+    
+    pub async fn finish_action(&self, decision_id: &str) {
+        self.in_flight.write().await.remove(decision_id);
+        self.forget(IN_FLIGHT_COLLECTION, decision_id).await;
+    }
+    
+    // Queued decisions in the order they'd run
+    pub async fn pending(&self) -> Vec<SchedulingDecision> {
+        let mut decisions = self.pending.read().await.clone();
+        decisions.sort_by(|a, b| a.deadline.cmp(&b.deadline).then(a.priority.cmp(&b.priority)));
+        decisions
+    }
+    
+    pub async fn in_flight(&self) -> Vec<InFlightAction> {
+        self.in_flight.read().await.values().cloned().collect()
+    }
+    
+    // Storage failures are logged; the in-memory queue stays authoritative
+    async fn persist<T: Serialize>(&self, collection: &str, id: &str, value: &T) {
+        if let Err(e) = self.storage.put(collection, id, value).await {
+            warn!("Failed to persist {} entry {}: {}", collection, id, e);
+        }
+    }
+    
+    async fn forget(&self, collection: &str, id: &str) {
+        if let Err(e) = self.storage.delete(collection, id).await {
+            warn!("Failed to remove {} entry {}: {}", collection, id, e);
+        }
+    }
+    
+    pub async fn record_execution(&self, decision: &SchedulingDecision) {
+        let now = Utc::now();
+        let mut stats = self.stats.write().await;
+        stats.executed += 1;
+        ::metrics::counter!("scheduler_decisions_executed_total").increment(1);
+        
+        if now <= decision.deadline {
+            return;
+        }
+        
+        let lateness_seconds = (now - decision.deadline).num_seconds();
+        warn!(
+            "Deadline missed: {} of {} ran {}s late",
+            decision.action.as_str(),
+            decision.resource_id,
+            lateness_seconds
+        );
+        
+        stats.missed += 1;
+        ::metrics::counter!("scheduler_deadline_misses_total").increment(1);
+        stats.recent_misses.push_back(DeadlineMiss {
+            resource_id: decision.resource_id.clone(),
+            action: decision.action.as_str().to_string(),
+            deadline: decision.deadline,
+            executed_at: now,
+            lateness_seconds,
+        });
+        while stats.recent_misses.len() > MAX_RECORDED_MISSES {
+            stats.recent_misses.pop_front();
+        }
+    }
+    
+    pub async fn stats(&self) -> DeadlineStats {
+        let mut stats = self.stats.read().await.clone();
+        stats.queued = self.pending.read().await.len();
+        stats.in_flight = self.in_flight.read().await.len();
+        stats
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration as ChronoDuration;
+    use crate::config::StorageConfig;
+    use crate::scheduler::resource_scheduler::SchedulingAction;
+    
+    async fn queue() -> DecisionQueue {
+        let path = std::env::temp_dir().join(format!("decision-queue-{}", uuid::Uuid::new_v4()));
+        let storage = Storage::from_config(&StorageConfig {
+            path: path.to_string_lossy().into_owned(),
+            ..StorageConfig::default()
+        }).await.unwrap();
+        DecisionQueue::load(storage).await.unwrap()
+    }
+    
+    fn decision(resource_id: &str, deadline_seconds: i64) -> SchedulingDecision {
+        let now = Utc::now();
+        SchedulingDecision {
+            id: uuid::Uuid::new_v4().to_string(),
+            resource_id: resource_id.to_string(),
+            action: SchedulingAction::Migrate,
+            target_host: None,
+            priority: 5,
+            sla_impact: 0.0,
+            deadline: now + ChronoDuration::seconds(deadline_seconds),
+            rationale: Default::default(),
+            created_at: now,
+            attempts: 0,
+        }
+    }
+    
+    async fn stored(queue: &DecisionQueue) -> Vec<String> {
+        let mut ids: Vec<String> = queue.storage.list::<SchedulingDecision>(QUEUE_COLLECTION).await.unwrap()
+            .into_iter()
+            .map(|d| d.resource_id)
+            .collect();
+        ids.sort();
+        ids
+    }
+    
+    fn resource_ids(decisions: &[SchedulingDecision]) -> Vec<&str> {
+        decisions.iter().map(|d| d.resource_id.as_str()).collect()
+    }
+    
+    #[tokio::test]
+    async fn drains_earliest_deadline_first_then_by_priority() {
+        let queue = queue().await;
+        let mut urgent = decision("urgent", 60);
+        urgent.priority = 1;
+        let mut routine = decision("routine", 60);
+        routine.deadline = urgent.deadline;
+        queue.enqueue(vec![decision("late", 600), routine, urgent]).await;
+        
+        let drained = queue.drain().await;
+        assert_eq!(resource_ids(&drained), ["urgent", "routine", "late"]);
+        assert!(queue.pending().await.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn deferred_decisions_stay_queued_and_stored() {
+        let queue = queue().await;
+        queue.enqueue(vec![decision("a", 60), decision("b", 120)]).await;
+        
+        let mut drained = queue.drain().await.into_iter();
+        let first = drained.next().unwrap();
+        queue.settle(&first.id).await;
+        for decision in drained {
+            queue.defer(decision).await;
+        }
+        queue.checkpoint().await;
+        
+        assert_eq!(stored(&queue).await, ["b"]);
+        assert_eq!(resource_ids(&queue.pending().await), ["b"]);
+    }
+    
+    #[tokio::test]
+    async fn keeps_unsettled_decisions_when_a_cycle_fails_part_way() {
+        let queue = queue().await;
+        queue.enqueue(vec![decision("a", 60), decision("b", 120), decision("c", 180)]).await;
+        
+        let mut drained = queue.drain().await.into_iter();
+        let first = drained.next().unwrap();
+        queue.settle(&first.id).await;
+        // The cycle bails out here, leaving "b" and "c" unhandled
+        queue.checkpoint().await;
+        
+        assert_eq!(stored(&queue).await, ["b", "c"]);
+        let pending: Vec<String> = queue.pending().await.into_iter().map(|d| d.resource_id).collect();
+        assert_eq!(pending, ["b", "c"]);
+    }
+    
+    #[tokio::test]
+    async fn settling_forgets_only_drained_decisions() {
+        let queue = queue().await;
+        let queued = decision("a", 60);
+        queue.enqueue(vec![queued.clone()]).await;
+        
+        queue.settle(&queued.id).await;
+        assert_eq!(stored(&queue).await, ["a"]);
+        
+        queue.drain().await;
+        queue.settle(&queued.id).await;
+        queue.checkpoint().await;
+        assert!(stored(&queue).await.is_empty());
+        assert!(queue.pending().await.is_empty());
+    }
+}
@@ -0,0 +1,166 @@
+use dashmap::DashMap;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use tracing::{debug, warn};
+
+use crate::config::{EnergyConfig, PowerManagementConfig};
+use crate::openstack::Clouds;
+use super::cluster::ClusterSnapshot;
+
+// Below this spread in observed utilization a slope can't be fitted reliably
+const MIN_UTILIZATION_SPREAD: f64 = 5.0;
+
+// Per-host linear power models fitted from BMC readings (Redfish or IPMI DCMI,
+// surfaced through Ironic) against the host's CPU utilization at the time
+pub struct EnergyModel {
+    config: EnergyConfig,
+    host_nodes: HashMap<String, String>,
+    powers_off_hosts: bool,
+    clouds: Clouds,
+    samples: DashMap<String, VecDeque<PowerSample>>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PowerSample {
+    utilization: f64,
+    watts: f64,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+pub struct HostPowerModel {
+    pub idle_watts: f64,
+    // Additional watts per percentage point of CPU utilization
+    pub watts_per_percent: f64,
+    // Zero when the configured defaults are used
+    pub samples: usize,
+}
+
+impl HostPowerModel {
+    pub fn predict(&self, utilization: f64) -> f64 {
+        self.idle_watts + self.watts_per_percent * utilization.clamp(0.0, 100.0)
+    }
+}
+
+// Models for every host in a snapshot, handed to the planners
+#[derive(Debug, Clone)]
+pub struct PowerModels {
+    models: HashMap<String, HostPowerModel>,
+    default_model: HostPowerModel,
+    // Draw of a host emptied by a plan: standby if it gets powered down
+    standby_watts: Option<f64>,
+}
+
+impl PowerModels {
+    pub fn model(&self, host_id: &str) -> &HostPowerModel {
+        self.models.get(host_id).unwrap_or(&self.default_model)
+    }
+    
+    pub fn predict(&self, host_id: &str, utilization: f64) -> f64 {
+        self.model(host_id).predict(utilization)
+    }
+    
+    pub fn freed_host_watts(&self, host_id: &str) -> f64 {
+        self.standby_watts.unwrap_or_else(|| self.model(host_id).idle_watts)
+    }
+}
+
+impl EnergyModel {
+    pub fn new(config: EnergyConfig, power_management: &PowerManagementConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            host_nodes: power_management.host_nodes.clone(),
+            powers_off_hosts: power_management.enabled,
+            clouds,
+            samples: DashMap::new(),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Pairs a power reading per host with its current utilization
+    pub async fn sample(&self, snapshot: &ClusterSnapshot) {
+        for host in &snapshot.hosts {
+            let node_id = match self.host_nodes.get(&host.host_id) {
+                Some(node_id) => node_id,
+                None => continue,
+            };
+            
+            let reading = match self.clouds.region(host.region.as_deref()).ironic.get_power_reading(node_id).await {
+                Ok(reading) => reading,
+                Err(e) => {
+                    warn!("Failed to read power draw of host {}: {}", host.host_id, e);
+                    continue;
+                }
+            };
+            
+            let mut samples = self.samples.entry(host.host_id.clone()).or_default();
+            samples.push_back(PowerSample {
+                utilization: host.cpu_utilization,
+                watts: reading.watts,
+            });
+            while samples.len() > self.config.max_samples {
+                samples.pop_front();
+            }
+        }
+    }
+    
+    pub fn models(&self, snapshot: &ClusterSnapshot) -> PowerModels {
+        let models = snapshot.hosts.iter()
+            .map(|host| (host.host_id.clone(), self.host_model(&host.host_id)))
+            .collect();
+        
+        PowerModels {
+            models,
+            default_model: self.default_model(),
+            standby_watts: self.powers_off_hosts.then_some(self.config.standby_watts),
+        }
+    }
+    
+    fn default_model(&self) -> HostPowerModel {
+        HostPowerModel {
+            idle_watts: self.config.default_idle_watts,
+            watts_per_percent: (self.config.default_peak_watts - self.config.default_idle_watts).max(0.0) / 100.0,
+            samples: 0,
+        }
+    }
+    
+    // Least-squares fit of watts against utilization
+    fn host_model(&self, host_id: &str) -> HostPowerModel {
+        let samples = match self.samples.get(host_id) {
+            Some(samples) if samples.len() >= self.config.min_samples => samples,
+            _ => return self.default_model(),
+        };
+        
+        let n = samples.len() as f64;
+        let mean_util = samples.iter().map(|s| s.utilization).sum::<f64>() / n;
+        let mean_watts = samples.iter().map(|s| s.watts).sum::<f64>() / n;
+        let variance = samples.iter().map(|s| (s.utilization - mean_util).powi(2)).sum::<f64>() / n;
+        
+        // A host that always runs at the same load only tells us one point;
+        // keep the default slope through it
+        let slope = if variance.sqrt() < MIN_UTILIZATION_SPREAD {
+            self.default_model().watts_per_percent
+        } else {
+            let covariance = samples.iter()
+                .map(|s| (s.utilization - mean_util) * (s.watts - mean_watts))
+                .sum::<f64>() / n;
+            (covariance / variance).max(0.0)
+        };
+        
+        let model = HostPowerModel {
+            idle_watts: (mean_watts - slope * mean_util).max(0.0),
+            watts_per_percent: slope,
+            samples: samples.len(),
+        };
+        debug!(
+            "Power model for {}: {:.0} W idle, {:.2} W per % CPU from {} samples",
+            host_id,
+            model.idle_watts,
+            model.watts_per_percent,
+            model.samples
+        );
+        model
+    }
+}
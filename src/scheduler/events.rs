@@ -0,0 +1,164 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::time::Duration;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::config::KafkaConfig;
+use crate::metrics::kafka_producer::KafkaProducer;
+use super::resource_scheduler::SchedulingDecision;
+use super::sla_manager::SLAViolation;
+
+// Bumped on any breaking change to the event layout; fields may be added
+// without a bump
+const EVENT_SCHEMA_VERSION: u32 = 1;
+
+// Publishes scheduler activity to the Kafka events topic for external
+// automation. Sends happen in the background and never hold up a cycle
+pub struct EventPublisher {
+    producer: Option<KafkaProducer>,
+    source: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SchedulerEvent {
+    pub schema_version: u32,
+    pub event_id: String,
+    pub emitted_at: DateTime<Utc>,
+    // Scheduler instance that emitted the event
+    pub source: String,
+    #[serde(flatten)]
+    pub body: EventBody,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event_type", content = "data", rename_all = "snake_case")]
+pub enum EventBody {
+    DecisionMade {
+        decision_id: String,
+        resource_id: String,
+        action: String,
+        target_host: Option<String>,
+        priority: u8,
+        deadline: DateTime<Utc>,
+        summary: String,
+        requested_by: Option<String>,
+    },
+    ExecutionStarted {
+        decision_id: String,
+        resource_id: String,
+        action: String,
+        target_host: Option<String>,
+    },
+    ExecutionCompleted {
+        decision_id: String,
+        resource_id: String,
+        action: String,
+        target_host: Option<String>,
+        succeeded: bool,
+        error: Option<String>,
+    },
+    SlaViolation {
+        resource_id: String,
+        violation_type: String,
+        severity: f64,
+        detected_at: DateTime<Utc>,
+    },
+}
+
+impl EventBody {
+    fn key(&self) -> &str {
+        match self {
+            EventBody::DecisionMade { resource_id, .. }
+            | EventBody::ExecutionStarted { resource_id, .. }
+            | EventBody::ExecutionCompleted { resource_id, .. }
+            | EventBody::SlaViolation { resource_id, .. } => resource_id,
+        }
+    }
+}
+
+impl EventPublisher {
+    pub async fn new(kafka_config: &KafkaConfig, source: String) -> Result<Self> {
+        let producer = match kafka_config.events_topic {
+            Some(_) => Some(KafkaProducer::new(kafka_config).await?),
+            None => None,
+        };
+        Ok(Self { producer, source })
+    }
+    
+    pub fn decision_made(&self, decision: &SchedulingDecision) {
+        self.publish(EventBody::DecisionMade {
+            decision_id: decision.id.clone(),
+            resource_id: decision.resource_id.clone(),
+            action: decision.action.as_str().to_string(),
+            target_host: decision.target_host.clone(),
+            priority: decision.priority,
+            deadline: decision.deadline,
+            summary: decision.rationale.summary.clone(),
+            requested_by: decision.rationale.requested_by.clone(),
+        });
+    }
+    
+    pub fn execution_started(&self, decision: &SchedulingDecision, target_host: Option<&str>) {
+        self.publish(EventBody::ExecutionStarted {
+            decision_id: decision.id.clone(),
+            resource_id: decision.resource_id.clone(),
+            action: decision.action.as_str().to_string(),
+            target_host: target_host.map(str::to_string),
+        });
+    }
+    
+    pub fn execution_completed(&self, decision: &SchedulingDecision, target_host: Option<&str>, error: Option<String>) {
+        self.publish(EventBody::ExecutionCompleted {
+            decision_id: decision.id.clone(),
+            resource_id: decision.resource_id.clone(),
+            action: decision.action.as_str().to_string(),
+            target_host: target_host.map(str::to_string),
+            succeeded: error.is_none(),
+            error,
+        });
+    }
+    
+    pub fn sla_violation(&self, violation: &SLAViolation) {
+        self.publish(EventBody::SlaViolation {
+            resource_id: violation.resource_id.clone(),
+            violation_type: format!("{:?}", violation.violation_type),
+            severity: violation.severity,
+            detected_at: violation.timestamp,
+        });
+    }
+    
+    pub async fn flush(&self, timeout: Duration) {
+        if let Some(producer) = &self.producer {
+            if let Err(e) = producer.flush(timeout).await {
+                warn!("Failed to flush scheduler events: {}", e);
+            }
+        }
+    }
+    
+    fn publish(&self, body: EventBody) {
+        let producer = match &self.producer {
+            Some(producer) => producer.clone(),
+            None => return,
+        };
+        let event = SchedulerEvent {
+            schema_version: EVENT_SCHEMA_VERSION,
+            event_id: Uuid::new_v4().to_string(),
+            emitted_at: Utc::now(),
+            source: self.source.clone(),
+            body,
+        };
+        
+        tokio::spawn(async move {
+            let outcome = match producer.send_event(event.body.key(), &event).await {
+                Ok(()) => "sent",
+                Err(e) => {
+                    warn!("Failed to publish scheduler event {}: {}", event.event_id, e);
+                    "failed"
+                }
+            };
+            ::metrics::counter!("scheduler_events_published_total", "outcome" => outcome).increment(1);
+        });
+    }
+}
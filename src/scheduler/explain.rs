@@ -0,0 +1,147 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tokio::sync::Mutex;
+use tracing::{info, warn};
+
+use crate::storage::Storage;
+use super::filters::PlacementOutcome;
+use super::risk::RiskAssessment;
+
+const EXPLANATION_COLLECTION: &str = "decision_explanations";
+const MAX_EXPLANATIONS: usize = 5000;
+// Most explanations one listing reads back, whatever limit the REST or
+// GraphQL client asked for
+const MAX_RECENT: usize = 500;
+
+// Inputs that led to the choice of action
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionRationale {
+    pub summary: String,
+    pub predicted_load: Option<f64>,
+    pub high_load_threshold: Option<f64>,
+    pub low_load_threshold: Option<f64>,
+    pub sla_critical: bool,
+    pub sla_violations: Vec<String>,
+    pub expected_penalty: Option<f64>,
+    pub applied_overrides: Vec<String>,
+    // Operator who asked for the action through the API
+    pub requested_by: Option<String>,
+    // Set once the decision has been scored against the risk guardrails
+    pub risk: Option<RiskAssessment>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum DecisionOutcome {
+    Executed { target_host: Option<String> },
+    Deferred { reason: String },
+    Blocked { rule: String, reason: String },
+    Skipped { reason: String },
+    // Left for an operator to carry out, e.g. because it's too risky to automate
+    Recommended { reason: String },
+    Failed { reason: String, will_retry: bool },
+    NoCapacity,
+}
+
+impl DecisionOutcome {
+    // Same names as the serialized "status" tag
+    pub fn status(&self) -> &'static str {
+        match self {
+            DecisionOutcome::Executed { .. } => "executed",
+            DecisionOutcome::Deferred { .. } => "deferred",
+            DecisionOutcome::Blocked { .. } => "blocked",
+            DecisionOutcome::Skipped { .. } => "skipped",
+            DecisionOutcome::Recommended { .. } => "recommended",
+            DecisionOutcome::Failed { .. } => "failed",
+            DecisionOutcome::NoCapacity => "no_capacity",
+        }
+    }
+    
+    // Whether the decision is done with, rather than queued for another try
+    pub fn is_terminal(&self) -> bool {
+        !matches!(
+            self,
+            DecisionOutcome::Deferred { .. } | DecisionOutcome::Failed { will_retry: true, .. }
+        )
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DecisionExplanation {
+    pub decision_id: String,
+    pub resource_id: String,
+    pub action: String,
+    pub rationale: ActionRationale,
+    // Every host considered, with filter rejections and weigher scores
+    pub placement: Option<PlacementOutcome>,
+    pub outcome: DecisionOutcome,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+// Persisted explanations of executed, deferred and rejected decisions,
+// pruned oldest first
+pub struct DecisionJournal {
+    storage: Storage,
+    recent: Mutex<VecDeque<String>>,
+}
+
+impl DecisionJournal {
+    pub async fn load(storage: Storage) -> Result<Self> {
+        let mut stored: Vec<DecisionExplanation> = storage.list(EXPLANATION_COLLECTION).await?;
+        stored.sort_by_key(|e| e.created_at);
+        info!("Loaded {} decision explanations from storage", stored.len());
+        
+        Ok(Self {
+            storage,
+            recent: Mutex::new(stored.into_iter().map(|e| e.decision_id).collect()),
+        })
+    }
+    
+    // Failures are logged rather than failing the scheduling cycle
+    pub async fn record(&self, explanation: &DecisionExplanation) {
+        if let Err(e) = self.storage
+            .put(EXPLANATION_COLLECTION, &explanation.decision_id, explanation)
+            .await
+        {
+            warn!("Failed to store explanation for decision {}: {}", explanation.decision_id, e);
+            return;
+        }
+        
+        let mut recent = self.recent.lock().await;
+        if !recent.contains(&explanation.decision_id) {
+            recent.push_back(explanation.decision_id.clone());
+        }
+        while recent.len() > MAX_EXPLANATIONS {
+            if let Some(expired) = recent.pop_front() {
+                if let Err(e) = self.storage.delete(EXPLANATION_COLLECTION, &expired).await {
+                    warn!("Failed to prune explanation {}: {}", expired, e);
+                }
+            }
+        }
+    }
+    
+    pub async fn get(&self, decision_id: &str) -> Result<Option<DecisionExplanation>> {
+        self.storage.get(EXPLANATION_COLLECTION, decision_id).await
+    }
+    
+    // Every retained decision, oldest first
+    pub async fn ids(&self) -> Vec<String> {
+        self.recent.lock().await.iter().cloned().collect()
+    }
+    
+    // Newest first
+    pub async fn recent(&self, limit: usize) -> Result<Vec<DecisionExplanation>> {
+        let limit = limit.min(MAX_RECENT);
+        let ids: Vec<String> = self.recent.lock().await.iter().rev().take(limit).cloned().collect();
+        let mut explanations = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(explanation) = self.get(&id).await? {
+                explanations.push(explanation);
+            }
+        }
+        Ok(explanations)
+    }
+}
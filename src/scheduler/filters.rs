@@ -0,0 +1,570 @@
+use anyhow::Result;
+use arc_swap::ArcSwap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+
+use crate::config::PlacementConfig;
+use crate::error::SchedulerError;
+use crate::openstack::services::{ServerGroupPolicy, ServerGroupRef};
+use crate::plugins::{self, Registry};
+use super::cluster::{ClusterSnapshot, ResourceContext};
+use super::placement::{HostMetrics, ResourceRequirements};
+use super::scoring::ScoringStrategy;
+
+// Everything filters and weighers get to look at for one placement
+pub struct PlacementRequest<'a> {
+    pub resource: &'a ResourceContext,
+    pub requirements: &'a ResourceRequirements,
+    pub snapshot: &'a ClusterSnapshot,
+    // Hosts powered off by power management
+    pub offline_hosts: &'a HashSet<String>,
+    // Hosts the policy engine denies for this action
+    pub denied_hosts: &'a HashSet<String>,
+    // Set by a policy override; otherwise the instance stays in its zone
+    pub allow_cross_az: bool,
+}
+
+// Hard constraint: a host failing any filter is not a candidate
+pub trait HostFilter: Send + Sync {
+    fn name(&self) -> &str;
+    
+    // Err carries the reason the host was rejected
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String>;
+}
+
+// Soft preference: raw weights are normalized across the surviving hosts
+// and scaled by the configured multiplier, Nova style
+pub trait HostWeigher: Send + Sync {
+    fn name(&self) -> &str;
+    
+    fn weigh(&self, host: &HostMetrics, request: &PlacementRequest) -> f64;
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FilterRejection {
+    pub filter: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WeigherScore {
+    pub weigher: String,
+    pub raw: f64,
+    pub normalized: f64,
+    pub weighted: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HostEvaluation {
+    pub host_id: String,
+    pub rejected_by: Option<FilterRejection>,
+    pub weights: Vec<WeigherScore>,
+    pub total_weight: f64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlacementOutcome {
+    pub selected: Option<String>,
+    pub candidates: Vec<HostEvaluation>,
+    // The server group policy placement had to honour, if any
+    #[serde(default)]
+    pub server_group: Option<ServerGroupConstraint>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ServerGroupConstraint {
+    #[serde(flatten)]
+    pub group: ServerGroupRef,
+    // Where the group's other members ran at the time
+    pub member_hosts: Vec<String>,
+}
+
+pub struct FilterPipeline {
+    filters: Vec<Box<dyn HostFilter>>,
+    weighers: Vec<(Box<dyn HostWeigher>, f64)>,
+}
+
+impl FilterPipeline {
+    pub fn from_config(config: &PlacementConfig, scoring: Arc<ArcSwap<ScoringStrategy>>) -> Result<Self> {
+        let registry = plugins::registry();
+        let filters = config.filters.iter()
+            .map(|name| registry.filter(name, config))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| SchedulerError::PlacementError(e.to_string()))?;
+        let weighers = config.weighers.iter()
+            .map(|weigher| Ok((registry.weigher(&weigher.name, config, &scoring)?, weigher.multiplier)))
+            .collect::<Result<Vec<_>>>()
+            .map_err(|e| SchedulerError::PlacementError(e.to_string()))?;
+        
+        Ok(Self { filters, weighers })
+    }
+    
+    pub fn run(&self, hosts: &[HostMetrics], request: &PlacementRequest) -> PlacementOutcome {
+        let mut candidates: Vec<HostEvaluation> = hosts.iter()
+            .map(|host| HostEvaluation {
+                host_id: host.host_id.clone(),
+                rejected_by: self.first_rejection(host, request),
+                weights: Vec::new(),
+                total_weight: 0.0,
+            })
+            .collect();
+        
+        let passed: Vec<usize> = candidates.iter()
+            .enumerate()
+            .filter(|(_, c)| c.rejected_by.is_none())
+            .map(|(idx, _)| idx)
+            .collect();
+        
+        for (weigher, multiplier) in &self.weighers {
+            let raw: Vec<f64> = passed.iter().map(|&idx| weigher.weigh(&hosts[idx], request)).collect();
+            let min = raw.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = raw.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            
+            for (&idx, raw) in passed.iter().zip(raw) {
+                let normalized = if max > min { (raw - min) / (max - min) } else { 0.0 };
+                let weighted = normalized * multiplier;
+                let candidate = &mut candidates[idx];
+                candidate.total_weight += weighted;
+                candidate.weights.push(WeigherScore {
+                    weigher: weigher.name().to_string(),
+                    raw,
+                    normalized,
+                    weighted,
+                });
+            }
+        }
+        
+        // Ties keep host order so the choice is stable between cycles
+        let selected = passed.iter()
+            .map(|&idx| &candidates[idx])
+            .fold(None::<&HostEvaluation>, |best, c| match best {
+                Some(b) if b.total_weight >= c.total_weight => Some(b),
+                _ => Some(c),
+            })
+            .map(|c| c.host_id.clone());
+        
+        let server_group = request.resource.server_group.as_ref().map(|group| {
+            let mut member_hosts: Vec<String> = request.snapshot.server_group_hosts(&request.resource.resource_id)
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+            member_hosts.sort();
+            ServerGroupConstraint {
+                group: group.clone(),
+                member_hosts,
+            }
+        });
+        
+        PlacementOutcome { selected, candidates, server_group }
+    }
+    
+    fn first_rejection(&self, host: &HostMetrics, request: &PlacementRequest) -> Option<FilterRejection> {
+        self.filters.iter().find_map(|filter| {
+            filter.filter(host, request).err().map(|reason| FilterRejection {
+                filter: filter.name().to_string(),
+                reason,
+            })
+        })
+    }
+}
+
+// The built-ins; other filters and weighers register through
+// plugins::extensions and are selected by name the same way
+pub fn register(registry: &mut Registry) {
+    registry
+        .add_filter("capacity", |config| Ok(Box::new(CapacityFilter {
+            max_cpu_utilization: config.max_cpu_utilization,
+            max_memory_utilization: config.max_memory_utilization,
+        })))
+        .add_filter("availability_zone", |_| Ok(Box::new(AvailabilityZoneFilter)))
+        .add_filter("aggregate_isolation", |_| Ok(Box::new(AggregateIsolationFilter)))
+        .add_filter("region", |_| Ok(Box::new(RegionFilter)))
+        .add_filter("affinity", |config| Ok(Box::new(AffinityFilter {
+            affinity_key: config.affinity_metadata_key.clone(),
+            anti_affinity_key: config.anti_affinity_metadata_key.clone(),
+        })))
+        .add_filter("maintenance", |config| Ok(Box::new(MaintenanceFilter {
+            maintenance_hosts: config.maintenance_hosts.iter().cloned().collect(),
+        })))
+        .add_filter("server_group", |_| Ok(Box::new(ServerGroupFilter)))
+        .add_filter("policy", |_| Ok(Box::new(PolicyFilter)))
+        .add_filter("storage_locality", |_| Ok(Box::new(StorageLocalityFilter)))
+        .add_filter("stack_spread", |config| Ok(Box::new(StackSpreadFilter {
+            domains: config.failure_domains.iter()
+                .flat_map(|(domain, hosts)| hosts.iter().map(move |host| (host.clone(), domain.clone())))
+                .collect(),
+        })));
+    
+    registry
+        .add_weigher("scoring", |_, scoring| Ok(Box::new(ScoringWeigher { scoring: scoring.clone() })))
+        .add_weigher("ram", |_, _| Ok(Box::new(RamWeigher)))
+        .add_weigher("cpu", |_, _| Ok(Box::new(CpuWeigher)))
+        .add_weigher("storage_locality", |_, _| Ok(Box::new(StorageLocalityWeigher)))
+        .add_weigher("traffic_affinity", |_, _| Ok(Box::new(TrafficAffinityWeigher)))
+        .add_weigher("server_group", |_, _| Ok(Box::new(ServerGroupWeigher)));
+}
+
+struct CapacityFilter {
+    max_cpu_utilization: f64,
+    max_memory_utilization: f64,
+}
+
+impl HostFilter for CapacityFilter {
+    fn name(&self) -> &str {
+        "capacity"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let requirements = request.requirements;
+        match (&requirements.resource_class, &host.resource_class) {
+            (None, None) => {}
+            (Some(needed), Some(class)) if needed == class => {
+                return match host.vm_count {
+                    0 => Ok(()),
+                    _ => Err("bare-metal node already in use".to_string()),
+                };
+            }
+            (Some(needed), Some(class)) => return Err(format!("bare-metal node of class {}, {} needed", class, needed)),
+            (Some(needed), None) => return Err(format!("not a bare-metal node, {} needed", needed)),
+            (None, Some(_)) => return Err("bare-metal node".to_string()),
+        }
+        if host.available_vcpus < requirements.vcpus {
+            return Err(format!("{} vCPUs free, {} needed", host.available_vcpus, requirements.vcpus));
+        }
+        if host.available_memory_mb < requirements.memory_mb {
+            return Err(format!("{} MB free, {} MB needed", host.available_memory_mb, requirements.memory_mb));
+        }
+        if host.cpu_utilization >= self.max_cpu_utilization {
+            return Err(format!("CPU at {:.1}%", host.cpu_utilization));
+        }
+        if host.memory_utilization >= self.max_memory_utilization {
+            return Err(format!("memory at {:.1}%", host.memory_utilization));
+        }
+        Ok(())
+    }
+}
+
+struct AvailabilityZoneFilter;
+
+impl HostFilter for AvailabilityZoneFilter {
+    fn name(&self) -> &str {
+        "availability_zone"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let zone = match &request.resource.availability_zone {
+            Some(zone) if !request.allow_cross_az => zone,
+            _ => return Ok(()),
+        };
+        match &host.availability_zone {
+            Some(host_zone) if host_zone == zone => Ok(()),
+            Some(host_zone) => Err(format!("host is in {}, instance is in {}", host_zone, zone)),
+            None => Err(format!("host has no availability zone, instance is in {}", zone)),
+        }
+    }
+}
+
+// Hosts in aggregates that isolate tenants only take those projects'
+// instances, as Nova's AggregateMultiTenancyIsolation would insist
+struct AggregateIsolationFilter;
+
+impl HostFilter for AggregateIsolationFilter {
+    fn name(&self) -> &str {
+        "aggregate_isolation"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let Some(allowed) = &host.allowed_projects else {
+            return Ok(());
+        };
+        match &request.resource.project_id {
+            Some(project) if allowed.contains(project) => Ok(()),
+            _ => Err(format!("aggregates {} are reserved for other projects", host.aggregates.join(", "))),
+        }
+    }
+}
+
+// Nova can't move a server between regions
+struct RegionFilter;
+
+impl HostFilter for RegionFilter {
+    fn name(&self) -> &str {
+        "region"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        match (&request.resource.region, &host.region) {
+            (Some(region), Some(host_region)) if host_region != region => {
+                Err(format!("host is in region {}, instance is in {}", host_region, region))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// Server-group style placement via instance metadata: members of an affinity
+// group share a host, members of an anti-affinity group never do
+struct AffinityFilter {
+    affinity_key: String,
+    anti_affinity_key: String,
+}
+
+impl AffinityFilter {
+    fn group_hosts<'a>(&self, key: &str, request: &PlacementRequest<'a>) -> Option<(String, HashSet<&'a str>)> {
+        let group = request.resource.metadata.get(key)?;
+        let hosts = request.snapshot.instances.iter()
+            .filter(|i| i.resource_id != request.resource.resource_id)
+            .filter(|i| {
+                request.snapshot.resources.get(&i.resource_id)
+                    .and_then(|context| context.metadata.get(key))
+                    .map(|g| g == group)
+                    .unwrap_or(false)
+            })
+            .map(|i| i.host_id.as_str())
+            .collect();
+        Some((group.clone(), hosts))
+    }
+}
+
+impl HostFilter for AffinityFilter {
+    fn name(&self) -> &str {
+        "affinity"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        if let Some((group, hosts)) = self.group_hosts(&self.affinity_key, request) {
+            if !hosts.is_empty() && !hosts.contains(host.host_id.as_str()) {
+                return Err(format!("affinity group {} lives elsewhere", group));
+            }
+        }
+        if let Some((group, hosts)) = self.group_hosts(&self.anti_affinity_key, request) {
+            if hosts.contains(host.host_id.as_str()) {
+                return Err(format!("already runs a member of anti-affinity group {}", group));
+            }
+        }
+        Ok(())
+    }
+}
+
+struct MaintenanceFilter {
+    maintenance_hosts: HashSet<String>,
+}
+
+impl HostFilter for MaintenanceFilter {
+    fn name(&self) -> &str {
+        "maintenance"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        if self.maintenance_hosts.contains(&host.host_id) {
+            return Err("in maintenance".to_string());
+        }
+        if request.offline_hosts.contains(&host.host_id) {
+            return Err("powered off".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Nova server groups with a hard policy: affinity members stay with their
+// peers, anti-affinity members away from them
+struct ServerGroupFilter;
+
+impl HostFilter for ServerGroupFilter {
+    fn name(&self) -> &str {
+        "server_group"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let Some(group) = &request.resource.server_group else {
+            return Ok(());
+        };
+        let hosts = request.snapshot.server_group_hosts(&request.resource.resource_id);
+        match group.policy {
+            ServerGroupPolicy::Affinity if !hosts.is_empty() && !hosts.contains(host.host_id.as_str()) => {
+                Err(format!("affinity server group {} runs elsewhere", group.name))
+            }
+            ServerGroupPolicy::AntiAffinity if hosts.contains(host.host_id.as_str()) => {
+                Err(format!("already runs a member of anti-affinity server group {}", group.name))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+struct PolicyFilter;
+
+impl HostFilter for PolicyFilter {
+    fn name(&self) -> &str {
+        "policy"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        if request.denied_hosts.contains(&host.host_id) {
+            return Err("denied by scheduling policy".to_string());
+        }
+        Ok(())
+    }
+}
+
+// Rejects targets that can't reach the instance's Cinder backends
+struct StorageLocalityFilter;
+
+impl HostFilter for StorageLocalityFilter {
+    fn name(&self) -> &str {
+        "storage_locality"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let instance = match request.snapshot.instance(&request.resource.resource_id) {
+            Some(instance) => instance,
+            None => return Ok(()),
+        };
+        let migration = request.snapshot.storage_migration(instance, &host.host_id);
+        if !migration.feasible {
+            return Err(format!(
+                "volumes {} are on backends the host can't reach",
+                migration.cross_backend_volumes.join(", ")
+            ));
+        }
+        Ok(())
+    }
+}
+
+// Keeps members of one Heat stack out of a failure domain another member
+// already runs in, so losing a domain takes down as few of them as possible
+struct StackSpreadFilter {
+    // Host to failure domain; unlisted hosts are a domain of their own
+    domains: HashMap<String, String>,
+}
+
+impl StackSpreadFilter {
+    fn domain<'a>(&'a self, host_id: &'a str) -> &'a str {
+        self.domains.get(host_id).map(String::as_str).unwrap_or(host_id)
+    }
+}
+
+impl HostFilter for StackSpreadFilter {
+    fn name(&self) -> &str {
+        "stack_spread"
+    }
+    
+    fn filter(&self, host: &HostMetrics, request: &PlacementRequest) -> Result<(), String> {
+        let Some(stack) = &request.resource.stack else {
+            return Ok(());
+        };
+        let domain = self.domain(&host.host_id);
+        let sibling = request.snapshot.instances.iter()
+            .filter(|i| i.resource_id != request.resource.resource_id)
+            .filter(|i| self.domain(&i.host_id) == domain)
+            .find(|i| {
+                request.snapshot.resources.get(&i.resource_id)
+                    .and_then(|context| context.stack.as_ref())
+                    .is_some_and(|s| s.id == stack.id)
+            });
+        match sibling {
+            Some(sibling) => Err(format!(
+                "{} of stack {} already runs in failure domain {}",
+                sibling.resource_id, stack.name, domain
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+// The multi-objective scoring strategy, switchable at runtime
+struct ScoringWeigher {
+    scoring: Arc<ArcSwap<ScoringStrategy>>,
+}
+
+impl HostWeigher for ScoringWeigher {
+    fn name(&self) -> &str {
+        "scoring"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, request: &PlacementRequest) -> f64 {
+        self.scoring.load().score(host, request.requirements).score
+    }
+}
+
+struct RamWeigher;
+
+impl HostWeigher for RamWeigher {
+    fn name(&self) -> &str {
+        "ram"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, _request: &PlacementRequest) -> f64 {
+        host.available_memory_mb as f64
+    }
+}
+
+struct CpuWeigher;
+
+impl HostWeigher for CpuWeigher {
+    fn name(&self) -> &str {
+        "cpu"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, _request: &PlacementRequest) -> f64 {
+        host.available_vcpus as f64
+    }
+}
+
+// Prefers targets that need the least disk data copied
+struct StorageLocalityWeigher;
+
+impl HostWeigher for StorageLocalityWeigher {
+    fn name(&self) -> &str {
+        "storage_locality"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, request: &PlacementRequest) -> f64 {
+        request.snapshot.instance(&request.resource.resource_id)
+            .map(|instance| -request.snapshot.storage_migration(instance, &host.host_id).transfer_gb)
+            .unwrap_or(0.0)
+    }
+}
+
+// Prefers hosts close to the instances it exchanges the most traffic with
+struct TrafficAffinityWeigher;
+
+impl HostWeigher for TrafficAffinityWeigher {
+    fn name(&self) -> &str {
+        "traffic_affinity"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, request: &PlacementRequest) -> f64 {
+        request.snapshot.traffic_affinity(&request.resource.resource_id, &host.host_id)
+    }
+}
+
+// Soft server group policies as a preference: members of the group already
+// on the host count for soft-affinity and against soft-anti-affinity
+struct ServerGroupWeigher;
+
+impl HostWeigher for ServerGroupWeigher {
+    fn name(&self) -> &str {
+        "server_group"
+    }
+    
+    fn weigh(&self, host: &HostMetrics, request: &PlacementRequest) -> f64 {
+        let Some(group) = &request.resource.server_group else {
+            return 0.0;
+        };
+        let on_host = request.snapshot.instances_on(&host.host_id)
+            .filter(|i| i.resource_id != request.resource.resource_id)
+            .filter(|i| {
+                request.snapshot.resources.get(&i.resource_id)
+                    .and_then(|context| context.server_group.as_ref())
+                    .is_some_and(|g| g.id == group.id)
+            })
+            .count() as f64;
+        match group.policy {
+            ServerGroupPolicy::SoftAffinity => on_host,
+            ServerGroupPolicy::SoftAntiAffinity => -on_host,
+            ServerGroupPolicy::Affinity | ServerGroupPolicy::AntiAffinity => 0.0,
+        }
+    }
+}
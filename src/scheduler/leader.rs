@@ -0,0 +1,95 @@
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+use tokio::time::interval;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, warn};
+
+use crate::config::HighAvailabilityConfig;
+use crate::coordination::Coordinator;
+
+// Lease deciding which scheduler instance executes decisions. Standbys keep
+// running cycles for warm state but act on nothing
+pub struct LeaderElector {
+    config: HighAvailabilityConfig,
+    coordinator: Coordinator,
+    is_leader: AtomicBool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LeadershipStatus {
+    pub enabled: bool,
+    pub instance_id: String,
+    pub is_leader: bool,
+}
+
+impl LeaderElector {
+    pub fn new(config: HighAvailabilityConfig, coordinator: Coordinator) -> Self {
+        Self {
+            // Without HA there is nobody to compete with
+            is_leader: AtomicBool::new(!config.enabled),
+            config,
+            coordinator,
+        }
+    }
+    
+    pub fn is_leader(&self) -> bool {
+        self.is_leader.load(Ordering::SeqCst)
+    }
+    
+    pub fn status(&self) -> LeadershipStatus {
+        LeadershipStatus {
+            enabled: self.config.enabled,
+            instance_id: self.coordinator.instance_id().to_string(),
+            is_leader: self.is_leader(),
+        }
+    }
+    
+    // Campaigns for and renews the lease until shutdown is cancelled
+    pub async fn run(&self, shutdown: CancellationToken) {
+        if !self.config.enabled {
+            return;
+        }
+        
+        info!("Leader election enabled as {}", self.coordinator.instance_id());
+        let lease = Duration::from_secs(self.config.lease_seconds);
+        let mut ticker = interval(Duration::from_secs(self.config.renew_interval_seconds));
+        loop {
+            tokio::select! {
+                _ = ticker.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
+            let held = match self.coordinator.acquire_lease(&self.config.lock_key, lease).await {
+                Ok(held) => held,
+                Err(e) => {
+                    // Can't prove we still hold the lease, so stop acting on it
+                    warn!("Leader election failed: {}", e);
+                    false
+                }
+            };
+            self.set_leader(held);
+        }
+    }
+    
+    // Gives the lease up so a standby can take over without waiting for expiry
+    pub async fn resign(&self) {
+        if !self.config.enabled || !self.is_leader() {
+            return;
+        }
+        
+        self.set_leader(false);
+        if let Err(e) = self.coordinator.release_lease(&self.config.lock_key).await {
+            warn!("Failed to release leadership: {}", e);
+        }
+    }
+    
+    fn set_leader(&self, held: bool) {
+        let was_leader = self.is_leader.swap(held, Ordering::SeqCst);
+        match (was_leader, held) {
+            (false, true) => info!("{} became scheduler leader", self.coordinator.instance_id()),
+            (true, false) => warn!("{} lost scheduler leadership", self.coordinator.instance_id()),
+            _ => {}
+        }
+        ::metrics::gauge!("scheduler_is_leader").set(if held { 1.0 } else { 0.0 });
+    }
+}
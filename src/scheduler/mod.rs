@@ -1,5 +1,34 @@
 pub mod resource_scheduler;
 pub mod placement;
 pub mod sla_manager;
+pub mod sla_notifier;
+pub mod alarms;
+pub mod autoscaling;
+pub mod capacity;
+pub mod chaos;
+pub mod cluster;
+pub mod consolidation;
+pub mod control;
+pub mod cooldown;
+pub mod disruption;
+pub mod edf;
+pub mod energy;
+pub mod events;
+pub mod explain;
+pub mod filters;
+pub mod leader;
+pub mod policy;
+pub mod power;
+pub mod preemption;
+pub mod prescaling;
+pub mod rebalance;
+pub mod resize;
+pub mod risk;
+pub mod scoring;
+pub mod shard_feed;
+pub mod simulation;
+pub mod stats;
+pub mod storage_locality;
+pub mod traffic;
 
 pub use resource_scheduler::ResourceScheduler;
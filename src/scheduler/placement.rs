@@ -1,13 +1,41 @@
 use anyhow::Result;
-use std::collections::HashMap;
+use arc_swap::ArcSwap;
+use rand::Rng;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tracing::{debug, info};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, RwLock};
+use tracing::{debug, info, warn};
 
-use crate::openstack::Client;
+use crate::config::{OptimizerConfig, PlacementConfig};
+use crate::error::SchedulerError;
+use crate::openstack::services::{Aggregate, BareMetalNode, Flavor, Hypervisor, ProviderUsage};
+use crate::openstack::{Client, Clouds};
+use super::cluster::{ClusterSnapshot, ResourceContext};
+use super::consolidation::MigrationStep;
+use super::filters::{FilterPipeline, PlacementOutcome, PlacementRequest};
+use super::scoring::ScoringStrategy;
+
+// Hosts are reused for this long, so one scheduling pass asks Placement
+// once rather than for every resource it places
+const HOST_CACHE_TTL: Duration = Duration::from_secs(30);
+
+// Flavors can't be resized, only their extra specs changed
+const FLAVOR_CACHE_TTL: Duration = Duration::from_secs(600);
 
 pub struct PlacementEngine {
-    openstack_client: Arc<Client>,
-    host_metrics: HashMap<String, HostMetrics>,
+    clouds: Clouds,
+    // Keyed by resource provider uuid
+    host_metrics: RwLock<HashMap<String, HostMetrics>>,
+    // Held while refreshing, so concurrent callers wait for one refresh
+    hosts_refreshed: Mutex<Option<Instant>>,
+    // Flavors looked up for servers that don't embed theirs, by region
+    // and flavor id
+    flavors: RwLock<HashMap<(String, String), (Instant, Flavor)>>,
+    scoring: Arc<ArcSwap<ScoringStrategy>>,
+    pipeline: FilterPipeline,
+    // Nova's zone for hosts no aggregate puts in one
+    default_availability_zone: String,
 }
 
 #[derive(Debug, Clone)]
@@ -18,8 +46,19 @@ pub struct HostMetrics {
     pub disk_utilization: f64,
     pub network_utilization: f64,
     pub vm_count: u32,
+    pub total_vcpus: u32,
+    pub total_memory_mb: u64,
     pub available_vcpus: u32,
     pub available_memory_mb: u64,
+    pub availability_zone: Option<String>,
+    // Names of the Nova host aggregates the host is in
+    pub aggregates: Vec<String>,
+    // Projects the host is reserved for by tenant-isolating aggregates;
+    // None when any project may use it
+    pub allowed_projects: Option<HashSet<String>>,
+    pub region: Option<String>,
+    // Placement resource class of a bare-metal node, None for hypervisors
+    pub resource_class: Option<String>,
     pub last_updated: chrono::DateTime<chrono::Utc>,
 }
 
@@ -34,132 +73,565 @@ pub struct PlacementScore {
 }
 
 impl PlacementEngine {
-    pub fn new(openstack_client: Arc<Client>) -> Self {
-        Self {
-            openstack_client,
-            host_metrics: HashMap::new(),
-        }
+    pub fn new(clouds: Clouds, scoring: ScoringStrategy, config: &PlacementConfig) -> Result<Self> {
+        let scoring = Arc::new(ArcSwap::from_pointee(scoring));
+        let pipeline = FilterPipeline::from_config(config, scoring.clone())?;
+        
+        Ok(Self {
+            clouds,
+            host_metrics: RwLock::new(HashMap::new()),
+            hosts_refreshed: Mutex::new(None),
+            flavors: RwLock::new(HashMap::new()),
+            scoring,
+            pipeline,
+            default_availability_zone: config.default_availability_zone.clone(),
+        })
     }
     
-    pub async fn find_optimal_host(&self, resource_id: &str) -> Result<Option<String>> {
-        debug!("Finding optimal host for resource {}", resource_id);
+    pub fn scoring_strategy(&self) -> Arc<ScoringStrategy> {
+        self.scoring.load_full()
+    }
+    
+    pub fn set_scoring_strategy(&self, scoring: ScoringStrategy) {
+        info!("Switching placement scoring to {:?}", scoring.preset);
+        self.scoring.store(Arc::new(scoring));
+    }
+    
+    pub async fn find_optimal_host(
+        &self,
+        resource: &ResourceContext,
+        snapshot: &ClusterSnapshot,
+        offline_hosts: &HashSet<String>,
+        denied_hosts: &HashSet<String>,
+        allow_cross_az: bool,
+    ) -> Result<PlacementOutcome> {
+        debug!("Finding optimal host for resource {}", resource.resource_id);
         
-        // Get current resource requirements
-        let resource_requirements = self.get_resource_requirements(resource_id).await?;
+        let requirements = self.get_resource_requirements(resource).await?;
         
-        // Get available hosts
-        let available_hosts = self.get_available_hosts().await?;
+        // Get available hosts, narrowed to those the resource's region's
+        // Placement could allocate from. A bare-metal node is claimed whole,
+        // through its resource class, and dedicated CPUs come out of PCPU
+        let mut available_hosts = self.get_available_hosts().await?;
+        let client = self.client_for(resource);
+        let cpu_class = if requirements.dedicated_cpus { "PCPU" } else { "VCPU" };
+        let candidates = match &requirements.resource_class {
+            Some(class) => client.placement.allocation_candidates(&[(class.as_str(), 1)]).await?,
+            None => client.placement.allocation_candidates(&[
+                (cpu_class, requirements.vcpus as u64),
+                ("MEMORY_MB", requirements.memory_mb),
+                ("DISK_GB", requirements.disk_gb as u64),
+            ]).await?,
+        };
+        let candidate_hosts: HashSet<String> = self.host_metrics.read().await.iter()
+            .filter(|(uuid, _)| candidates.contains(*uuid))
+            .map(|(_, host)| host.host_id.clone())
+            .collect();
+        available_hosts.retain(|host| candidate_hosts.contains(&host.host_id));
         
-        // Score each host
-        let mut host_scores: Vec<PlacementScore> = Vec::new();
+        // Filter, then weigh the survivors
+        let request = PlacementRequest {
+            resource,
+            requirements: &requirements,
+            snapshot,
+            offline_hosts,
+            denied_hosts,
+            allow_cross_az,
+        };
+        let outcome = self.pipeline.run(&available_hosts, &request);
         
-        for host in available_hosts {
-            if self.can_host_resource(&host, &resource_requirements) {
-                let score = self.calculate_placement_score(&host, &resource_requirements);
-                host_scores.push(score);
+        for candidate in &outcome.candidates {
+            if let Some(rejection) = &candidate.rejected_by {
+                debug!(
+                    "Host {} rejected by {} filter: {}",
+                    candidate.host_id, rejection.filter, rejection.reason
+                );
             }
         }
         
-        // Sort by score (higher is better)
-        host_scores.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        if let Some(selected) = &outcome.selected {
+            let weight = outcome.candidates.iter()
+                .find(|c| &c.host_id == selected)
+                .map(|c| c.total_weight)
+                .unwrap_or(0.0);
+            info!("Selected host {} with weight {:.2}", selected, weight);
+        }
+        
+        Ok(outcome)
+    }
+    
+    // What the resource's flavor asks for
+    pub async fn get_resource_requirements(&self, resource: &ResourceContext) -> Result<ResourceRequirements> {
+        if let Some(flavor) = &resource.flavor {
+            return Ok(ResourceRequirements::from_flavor(flavor));
+        }
+        if resource.flavor_id.is_empty() {
+            return Err(SchedulerError::PlacementError(format!(
+                "The flavor of {} isn't known",
+                resource.resource_id
+            )).into());
+        }
+        
+        let client = self.client_for(resource);
+        let key = (client.region().to_string(), resource.flavor_id.clone());
+        if let Some((fetched_at, flavor)) = self.flavors.read().await.get(&key) {
+            if fetched_at.elapsed() < FLAVOR_CACHE_TTL {
+                return Ok(ResourceRequirements::from_flavor(flavor));
+            }
+        }
         
-        if let Some(best_host) = host_scores.first() {
-            info!("Selected host {} with score {:.2}", best_host.host_id, best_host.score);
-            Ok(Some(best_host.host_id.clone()))
-        } else {
-            Ok(None)
+        let flavor = client.nova.get_flavor(&resource.flavor_id).await?;
+        let requirements = ResourceRequirements::from_flavor(&flavor);
+        self.flavors.write().await.insert(key, (Instant::now(), flavor));
+        Ok(requirements)
+    }
+    
+    // The client for the resource's region, the main one when it has none
+    fn client_for(&self, resource: &ResourceContext) -> Arc<Client> {
+        self.clouds.region(resource.region.as_deref())
+    }
+    
+    // Compute nodes from Placement, with Nova's hypervisor statistics where
+    // it has them, and Ironic's bare-metal nodes, across every region.
+    // Utilisation here is the share of each resource allocated, not the load
+    // measured on the host
+    pub async fn get_available_hosts(&self) -> Result<Vec<HostMetrics>> {
+        let mut refreshed = self.hosts_refreshed.lock().await;
+        if refreshed.is_none_or(|at| at.elapsed() > HOST_CACHE_TTL) {
+            let mut hosts = HashMap::new();
+            for client in self.clouds.regions() {
+                hosts.extend(self.region_hosts(client).await?);
+            }
+            debug!("Loaded {} compute hosts from Placement and Nova", hosts.len());
+            *self.host_metrics.write().await = hosts;
+            *refreshed = Some(Instant::now());
         }
+        
+        let mut hosts: Vec<HostMetrics> = self.host_metrics.read().await.values().cloned().collect();
+        hosts.sort_by(|a, b| a.host_id.cmp(&b.host_id));
+        Ok(hosts)
     }
     
-    async fn get_resource_requirements(&self, _resource_id: &str) -> Result<ResourceRequirements> {
-        // Mock implementation - would query OpenStack for actual requirements
-        Ok(ResourceRequirements {
-            vcpus: 2,
-            memory_mb: 4096,
-            disk_gb: 20,
-            network_bandwidth_mbps: 100,
-        })
+    // The client's region's hosts, by resource provider uuid
+    async fn region_hosts(&self, client: &Client) -> Result<HashMap<String, HostMetrics>> {
+        let nodes = client.placement.list_compute_usage().await?;
+        let hypervisors: HashMap<String, Hypervisor> = client.nova.list_hypervisors().await?
+            .into_iter()
+            .map(|hypervisor| (hypervisor.hypervisor_hostname.clone(), hypervisor))
+            .collect();
+        let bare_metal = self.bare_metal_nodes(client).await;
+        let aggregates = self.aggregates(client).await;
+        
+        // Nova names each compute node's provider after its hypervisor,
+        // and each bare-metal node's after the node's uuid
+        let hosts = nodes.iter()
+            .filter_map(|node| {
+                let mut host = HostMetrics::from_provider(node);
+                host.region = Some(client.region().to_string());
+                if let Some(bare_metal_node) = bare_metal.get(&node.provider.name) {
+                    if !bare_metal_node.is_schedulable() {
+                        debug!(
+                            "Skipping bare-metal node {}, which is {}{}",
+                            node.provider.name,
+                            bare_metal_node.provision_state,
+                            if bare_metal_node.maintenance { " in maintenance" } else { "" }
+                        );
+                        return None;
+                    }
+                    host.apply_bare_metal_node(bare_metal_node);
+                } else if let Some(hypervisor) = hypervisors.get(&node.provider.name) {
+                    if !hypervisor.is_usable() {
+                        debug!("Skipping compute node {}, which is {}/{}", node.provider.name, hypervisor.state, hypervisor.status);
+                        return None;
+                    }
+                    host.apply_hypervisor(hypervisor);
+                }
+                host.apply_aggregates(&aggregates, &self.default_availability_zone);
+                Some((node.provider.uuid.clone(), host))
+            })
+            .collect();
+        Ok(hosts)
+    }
+    
+    // Without the aggregates, e.g. when policy keeps us from listing them,
+    // every host counts as being in the default zone
+    async fn aggregates(&self, client: &Client) -> Vec<Aggregate> {
+        match client.nova.list_aggregates().await {
+            Ok(aggregates) => aggregates,
+            Err(e) => {
+                warn!("Failed to list host aggregates: {}", e);
+                Vec::new()
+            }
+        }
+    }
+    
+    // Ironic's nodes by uuid; none without Ironic, or when it can't be
+    // reached, so virtual hosts are still placed on
+    async fn bare_metal_nodes(&self, client: &Client) -> HashMap<String, BareMetalNode> {
+        let ironic = &client.ironic;
+        if !ironic.is_available() {
+            return HashMap::new();
+        }
+        match ironic.list_nodes().await {
+            Ok(nodes) => nodes.into_iter().map(|node| (node.uuid.clone(), node)).collect(),
+            Err(e) => {
+                warn!("Failed to list bare-metal nodes: {}", e);
+                HashMap::new()
+            }
+        }
+    }
+}
+
+impl HostMetrics {
+    fn from_provider(node: &ProviderUsage) -> Self {
+        let capacity = |class: &str| node.inventories.get(class).map_or(0, |inventory| inventory.capacity());
+        let used = |class: &str| node.usages.get(class).copied().unwrap_or(0);
+        let percent = |class: &str| match capacity(class) {
+            0 => 0.0,
+            capacity => used(class) as f64 / capacity as f64 * 100.0,
+        };
+        
+        Self {
+            host_id: node.provider.name.clone(),
+            cpu_utilization: percent("VCPU"),
+            memory_utilization: percent("MEMORY_MB"),
+            disk_utilization: percent("DISK_GB"),
+            network_utilization: 0.0,
+            vm_count: node.consumers,
+            total_vcpus: capacity("VCPU") as u32,
+            total_memory_mb: capacity("MEMORY_MB"),
+            available_vcpus: capacity("VCPU").saturating_sub(used("VCPU")) as u32,
+            available_memory_mb: capacity("MEMORY_MB").saturating_sub(used("MEMORY_MB")),
+            // Placement has no notion of zones or aggregates
+            availability_zone: None,
+            aggregates: Vec::new(),
+            allowed_projects: None,
+            region: None,
+            resource_class: None,
+            last_updated: chrono::Utc::now(),
+        }
+    }
+    
+    // Physical totals and running instances as the hypervisor counts them,
+    // under the host name servers report. What is still available stays as
+    // Placement has it, overcommit included
+    fn apply_hypervisor(&mut self, hypervisor: &Hypervisor) {
+        if let Some(service) = &hypervisor.service {
+            self.host_id = service.host.clone();
+        }
+        self.vm_count = hypervisor.running_vms;
+        self.total_vcpus = hypervisor.vcpus;
+        self.total_memory_mb = hypervisor.memory_mb;
+        if hypervisor.memory_mb > 0 {
+            self.memory_utilization = hypervisor.memory_mb_used as f64 / hypervisor.memory_mb as f64 * 100.0;
+        }
     }
     
-    async fn get_available_hosts(&self) -> Result<Vec<HostMetrics>> {
-        // Mock implementation - would query Nova for actual host data
-        Ok(vec![
-            HostMetrics {
-                host_id: "compute-1".to_string(),
-                cpu_utilization: 45.0,
-                memory_utilization: 60.0,
-                disk_utilization: 30.0,
-                network_utilization: 25.0,
-                vm_count: 12,
-                available_vcpus: 16,
-                available_memory_mb: 32768,
-                last_updated: chrono::Utc::now(),
-            },
-            HostMetrics {
-                host_id: "compute-2".to_string(),
-                cpu_utilization: 70.0,
-                memory_utilization: 80.0,
-                disk_utilization: 45.0,
-                network_utilization: 40.0,
-                vm_count: 18,
-                available_vcpus: 8,
-                available_memory_mb: 16384,
-                last_updated: chrono::Utc::now(),
-            },
-        ])
-    }
-    
-    fn can_host_resource(&self, host: &HostMetrics, requirements: &ResourceRequirements) -> bool {
-        host.available_vcpus >= requirements.vcpus &&
-        host.available_memory_mb >= requirements.memory_mb &&
-        host.cpu_utilization < 90.0 &&
-        host.memory_utilization < 90.0
-    }
-    
-    fn calculate_placement_score(&self, host: &HostMetrics, _requirements: &ResourceRequirements) -> PlacementScore {
-        // Multi-criteria scoring algorithm
-        
-        // CPU score (prefer hosts with moderate utilization)
-        let cpu_score = self.calculate_utilization_score(host.cpu_utilization);
-        
-        // Memory score
-        let memory_score = self.calculate_utilization_score(host.memory_utilization);
-        
-        // Network score
-        let network_score = self.calculate_utilization_score(host.network_utilization);
-        
-        // Consolidation score (prefer hosts with more VMs for better consolidation)
-        let consolidation_score = (host.vm_count as f64 / 20.0).min(1.0);
-        
-        // Weighted total score
-        let total_score = 
-            cpu_score * 0.3 +
-            memory_score * 0.3 +
-            network_score * 0.2 +
-            consolidation_score * 0.2;
-        
-        PlacementScore {
-            host_id: host.host_id.clone(),
-            score: total_score,
-            cpu_score,
-            memory_score,
-            network_score,
-            consolidation_score,
-        }
-    }
-    
-    fn calculate_utilization_score(&self, utilization: f64) -> f64 {
-        // Prefer moderate utilization (around 60-70%)
-        let optimal_utilization = 65.0;
-        let distance = (utilization - optimal_utilization).abs();
-        (100.0 - distance) / 100.0
+    // Zone and aggregates by the host's compute service name, which the
+    // host goes by once its hypervisor has been applied
+    fn apply_aggregates(&mut self, aggregates: &[Aggregate], default_zone: &str) {
+        let member_of: Vec<&Aggregate> = aggregates.iter()
+            .filter(|aggregate| aggregate.hosts.contains(&self.host_id))
+            .collect();
+        self.availability_zone = Some(
+            member_of.iter()
+                .find_map(|aggregate| aggregate.availability_zone.clone())
+                .unwrap_or_else(|| default_zone.to_string()),
+        );
+        self.aggregates = member_of.iter().map(|aggregate| aggregate.name.clone()).collect();
+        // A host in several isolating aggregates takes the projects of any
+        self.allowed_projects = member_of.iter()
+            .filter_map(|aggregate| aggregate.tenants())
+            .reduce(|mut all, tenants| {
+                all.extend(tenants);
+                all
+            })
+            .map(|tenants| tenants.into_iter().collect());
+    }
+    
+    // A bare-metal node runs at most one instance, which takes all of it
+    fn apply_bare_metal_node(&mut self, node: &BareMetalNode) {
+        let occupied = node.instance_uuid.is_some();
+        self.host_id = node.name.clone().unwrap_or_else(|| node.uuid.clone());
+        self.resource_class = node.placement_class();
+        self.vm_count = occupied as u32;
+        self.total_vcpus = node.property("cpus") as u32;
+        self.total_memory_mb = node.property("memory_mb");
+        let used = if occupied { 100.0 } else { 0.0 };
+        self.cpu_utilization = used;
+        self.memory_utilization = used;
+        self.disk_utilization = used;
+        self.available_vcpus = if occupied { 0 } else { self.total_vcpus };
+        self.available_memory_mb = if occupied { 0 } else { self.total_memory_mb };
+    }
+    
+    pub fn is_bare_metal(&self) -> bool {
+        self.resource_class.is_some()
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct ResourceRequirements {
     pub vcpus: u32,
     pub memory_mb: u64,
+    // Root and ephemeral disk plus swap
     pub disk_gb: u32,
+    // 0 when the flavor doesn't cap the instance's bandwidth
     pub network_bandwidth_mbps: u32,
+    // Set for instances that need a whole bare-metal node of this class
+    pub resource_class: Option<String>,
+    // hw:cpu_policy=dedicated, vCPUs pinned to host cores
+    pub dedicated_cpus: bool,
+    // hw:numa_nodes, the NUMA nodes the guest is spread over
+    pub numa_nodes: Option<u32>,
+    // hw:mem_page_size, e.g. "large" or "1GB" for huge pages
+    pub mem_page_size: Option<String>,
+}
+
+impl ResourceRequirements {
+    pub fn from_flavor(flavor: &Flavor) -> Self {
+        let no_specs = HashMap::new();
+        let specs = flavor.extra_specs.as_ref().unwrap_or(&no_specs);
+        // Bare-metal flavors ask for one unit of the node's custom class
+        // and zero of everything else
+        let resource_class = specs.iter()
+            .filter_map(|(key, value)| Some((key.strip_prefix("resources:")?, value)))
+            .find(|(class, value)| class.starts_with("CUSTOM_") && value.trim() != "0")
+            .map(|(class, _)| class.to_string());
+        // KB/s, as libvirt takes it
+        let bandwidth_kbps = specs.get("quota:vif_outbound_average")
+            .and_then(|average| average.parse::<u64>().ok())
+            .unwrap_or(0);
+        
+        Self {
+            vcpus: flavor.vcpus,
+            memory_mb: flavor.ram,
+            disk_gb: (flavor.disk + flavor.ephemeral + flavor.swap.div_ceil(1024)) as u32,
+            network_bandwidth_mbps: (bandwidth_kbps * 8 / 1000) as u32,
+            resource_class,
+            dedicated_cpus: specs.get("hw:cpu_policy").is_some_and(|policy| policy == "dedicated"),
+            numa_nodes: specs.get("hw:numa_nodes").and_then(|nodes| nodes.parse().ok()),
+            mem_page_size: specs.get("hw:mem_page_size").cloned(),
+        }
+    }
+}
+
+// Simulated-annealing search over VM-to-host assignments for periodic global
+// rebalancing, minimizing imbalance + migration count + predicted SLA risk
+pub struct PlacementOptimizer {
+    config: OptimizerConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct OptimizationResult {
+    pub steps: Vec<MigrationStep>,
+    pub initial_cost: f64,
+    pub final_cost: f64,
+}
+
+struct SearchSpace<'a> {
+    snapshot: &'a ClusterSnapshot,
+    host_ids: Vec<String>,
+    vcpu_capacity: Vec<f64>,
+    memory_capacity: Vec<f64>,
+    base_vcpus: Vec<f64>,
+    base_memory: Vec<f64>,
+    // Indices into snapshot.instances that the search may move
+    movable: Vec<usize>,
+    original: Vec<usize>,
+}
+
+impl PlacementOptimizer {
+    pub fn new(config: OptimizerConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn optimize(&self, snapshot: &ClusterSnapshot, excluded_hosts: &HashSet<String>) -> OptimizationResult {
+        let space = match self.build_search_space(snapshot, excluded_hosts) {
+            Some(space) => space,
+            None => {
+                return OptimizationResult {
+                    steps: Vec::new(),
+                    initial_cost: 0.0,
+                    final_cost: 0.0,
+                };
+            }
+        };
+        
+        let mut rng = rand::thread_rng();
+        let mut current = space.original.clone();
+        let mut vcpus = space.base_vcpus.clone();
+        let mut memory = space.base_memory.clone();
+        for (slot, &instance_idx) in space.movable.iter().enumerate() {
+            let instance = &snapshot.instances[instance_idx];
+            vcpus[current[slot]] += instance.predicted_vcpu_demand();
+            memory[current[slot]] += instance.memory_mb as f64;
+        }
+        
+        let initial_cost = self.objective(&space, &current, &vcpus);
+        let mut current_cost = initial_cost;
+        let mut best = current.clone();
+        let mut best_cost = current_cost;
+        let mut temperature = self.config.initial_temperature;
+        
+        for _ in 0..self.config.iterations {
+            let slot = rng.gen_range(0..space.movable.len());
+            let to = rng.gen_range(0..space.host_ids.len());
+            let from = current[slot];
+            if to == from {
+                continue;
+            }
+            
+            let instance = &snapshot.instances[space.movable[slot]];
+            let demand = instance.predicted_vcpu_demand();
+            let mem = instance.memory_mb as f64;
+            if vcpus[to] + demand > space.vcpu_capacity[to] || memory[to] + mem > space.memory_capacity[to] {
+                continue;
+            }
+            if !snapshot.can_migrate(instance, &space.host_ids[to]) {
+                continue;
+            }
+            
+            vcpus[from] -= demand;
+            memory[from] -= mem;
+            vcpus[to] += demand;
+            memory[to] += mem;
+            current[slot] = to;
+            
+            let candidate_cost = self.objective(&space, &current, &vcpus);
+            let delta = candidate_cost - current_cost;
+            
+            // Metropolis acceptance: always take improvements, sometimes take regressions
+            if delta <= 0.0 || rng.gen::<f64>() < (-delta / temperature.max(f64::EPSILON)).exp() {
+                current_cost = candidate_cost;
+                if current_cost < best_cost {
+                    best_cost = current_cost;
+                    best = current.clone();
+                }
+            } else {
+                vcpus[to] -= demand;
+                memory[to] -= mem;
+                vcpus[from] += demand;
+                memory[from] += mem;
+                current[slot] = from;
+            }
+            
+            temperature *= self.config.cooling_rate;
+        }
+        
+        let improvement = if initial_cost > 0.0 { (initial_cost - best_cost) / initial_cost } else { 0.0 };
+        if improvement < self.config.min_improvement {
+            return OptimizationResult {
+                steps: Vec::new(),
+                initial_cost,
+                final_cost: initial_cost,
+            };
+        }
+        
+        OptimizationResult {
+            steps: self.migration_steps(&space, &best),
+            initial_cost,
+            final_cost: best_cost,
+        }
+    }
+    
+    fn build_search_space<'a>(
+        &self,
+        snapshot: &'a ClusterSnapshot,
+        excluded_hosts: &HashSet<String>,
+    ) -> Option<SearchSpace<'a>> {
+        let hosts: Vec<_> = snapshot.hosts.iter()
+            .filter(|h| !excluded_hosts.contains(&h.host_id) && h.total_vcpus > 0)
+            .collect();
+        if hosts.len() < 2 {
+            return None;
+        }
+        
+        let host_index: HashMap<&str, usize> = hosts.iter()
+            .enumerate()
+            .map(|(idx, h)| (h.host_id.as_str(), idx))
+            .collect();
+        
+        let unmanaged: Vec<_> = hosts.iter().map(|h| snapshot.unmanaged_load(h)).collect();
+        let mut base_vcpus: Vec<f64> = unmanaged.iter().map(|(v, _)| *v).collect();
+        let mut base_memory: Vec<f64> = unmanaged.iter().map(|(_, m)| *m).collect();
+        
+        // Pinned instances stay put and count as fixed load on their host
+        let mut movable = Vec::new();
+        let mut original = Vec::new();
+        for (idx, instance) in snapshot.instances.iter().enumerate() {
+            if let Some(&host_idx) = host_index.get(instance.host_id.as_str()) {
+                if instance.pinned {
+                    base_vcpus[host_idx] += instance.predicted_vcpu_demand();
+                    base_memory[host_idx] += instance.memory_mb as f64;
+                } else {
+                    movable.push(idx);
+                    original.push(host_idx);
+                }
+            }
+        }
+        if movable.is_empty() {
+            return None;
+        }
+        
+        Some(SearchSpace {
+            snapshot,
+            host_ids: hosts.iter().map(|h| h.host_id.clone()).collect(),
+            vcpu_capacity: hosts.iter().map(|h| h.total_vcpus as f64).collect(),
+            memory_capacity: hosts.iter().map(|h| h.total_memory_mb as f64).collect(),
+            base_vcpus,
+            base_memory,
+            movable,
+            original,
+        })
+    }
+    
+    fn objective(&self, space: &SearchSpace, assignment: &[usize], vcpus: &[f64]) -> f64 {
+        let utilization: Vec<f64> = vcpus.iter()
+            .zip(space.vcpu_capacity.iter())
+            .map(|(used, capacity)| used / capacity)
+            .collect();
+        
+        let mean = utilization.iter().sum::<f64>() / utilization.len() as f64;
+        let imbalance = (utilization.iter().map(|u| (u - mean).powi(2)).sum::<f64>()
+            / utilization.len() as f64).sqrt();
+        
+        let moves = assignment.iter()
+            .zip(space.original.iter())
+            .filter(|(a, b)| a != b)
+            .count() as f64 / assignment.len() as f64;
+        
+        let sla_risk: f64 = utilization.iter()
+            .map(|u| (u - self.config.risk_utilization).max(0.0))
+            .sum();
+        
+        self.config.imbalance_weight * imbalance
+            + self.config.migration_weight * moves
+            + self.config.sla_risk_weight * sla_risk
+    }
+    
+    fn migration_steps(&self, space: &SearchSpace, assignment: &[usize]) -> Vec<MigrationStep> {
+        let mut steps: Vec<(f64, MigrationStep)> = assignment.iter()
+            .enumerate()
+            .filter(|(slot, host_idx)| space.original[*slot] != **host_idx)
+            .map(|(slot, &host_idx)| {
+                let instance = &space.snapshot.instances[space.movable[slot]];
+                let source = space.original[slot];
+                let source_utilization = space.snapshot.host(&space.host_ids[source])
+                    .map(|h| h.cpu_utilization)
+                    .unwrap_or(0.0);
+                (source_utilization, MigrationStep {
+                    resource_id: instance.resource_id.clone(),
+                    source_host: space.host_ids[source].clone(),
+                    target_host: space.host_ids[host_idx].clone(),
+                    predicted_vcpus: instance.predicted_vcpu_demand(),
+                    memory_mb: instance.memory_mb,
+                })
+            })
+            .collect();
+        
+        // Relieve the hottest hosts first
+        steps.sort_by(|a, b| b.0.total_cmp(&a.0));
+        steps.into_iter().map(|(_, step)| step).collect()
+    }
 }
@@ -0,0 +1,660 @@
+use anyhow::Result;
+use chrono::{DateTime, Datelike, Duration, NaiveTime, Utc, Weekday};
+use cron::Schedule;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fs;
+use std::str::FromStr;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::{BlackoutMode, BlackoutWindow};
+use crate::error::SchedulerError;
+use super::cluster::ResourceContext;
+use super::resource_scheduler::SchedulingAction;
+
+const MAX_RECORDED_BLOCKS: usize = 500;
+
+const ACTION_NAMES: [&str; 5] = ["migrate", "scale", "scale_out", "scale_in", "consolidate"];
+
+// Declarative scheduling rules loaded from a TOML policy file, e.g.
+//
+// [[rules]]
+// name = "protect-databases"
+// actions = ["migrate"]
+// match = { metadata = { tier = "database" } }
+// during = { start = "08:00", end = "20:00" }
+//
+// [[rules]]
+// name = "project-x-off-aggregate-y"
+// match = { project = "x" }
+// deny_aggregates = ["y"]
+//
+// [aggregates]
+// y = ["compute-3", "compute-4"]
+//
+// [[overrides]]
+// name = "batch-tenants"
+// match = { project = "batch" }
+// low_load_threshold = 40.0
+// aggressiveness = "aggressive"
+// allow_cross_az = true
+//
+// [[blackouts]]
+// name = "billing-close"
+// match = { project = "billing" }
+// schedule = "0 0 0 L * *"
+// duration_minutes = 1440
+// on_blackout = "expire"
+//
+// A rule without deny_hosts/deny_aggregates blocks the action outright;
+// otherwise it only removes those hosts from the placement candidates.
+// Overrides replace the scheduler defaults for matching resources, later
+// entries taking precedence field by field.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct PolicyFile {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+    #[serde(default)]
+    pub aggregates: HashMap<String, Vec<String>>,
+    #[serde(default)]
+    pub overrides: Vec<PolicyOverride>,
+    #[serde(default)]
+    pub blackouts: Vec<PolicyBlackout>,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PolicyBlackout {
+    #[serde(flatten)]
+    pub window: BlackoutWindow,
+    #[serde(default, rename = "match")]
+    pub selector: RuleSelector,
+}
+
+// A blackout currently holding back an action
+#[derive(Debug, Clone, Serialize)]
+pub struct ActiveBlackout {
+    pub name: String,
+    pub ends_at: DateTime<Utc>,
+    pub mode: BlackoutMode,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BlackoutStatus {
+    #[serde(flatten)]
+    pub window: BlackoutWindow,
+    // None for global windows from the scheduler config
+    pub selector: Option<RuleSelector>,
+    pub active_until: Option<DateTime<Utc>>,
+    pub next_start: Option<DateTime<Utc>>,
+}
+
+struct CompiledBlackout {
+    window: BlackoutWindow,
+    selector: Option<RuleSelector>,
+    schedule: Schedule,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PolicyRule {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    // Empty means every action
+    #[serde(default)]
+    pub actions: Vec<String>,
+    #[serde(default, rename = "match")]
+    pub selector: RuleSelector,
+    #[serde(default)]
+    pub during: Option<TimeWindow>,
+    #[serde(default)]
+    pub deny_hosts: Vec<String>,
+    #[serde(default)]
+    pub deny_aggregates: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RuleSelector {
+    pub project: Option<String>,
+    pub flavor: Option<String>,
+    #[serde(default)]
+    pub metadata: HashMap<String, String>,
+}
+
+// Daily UTC window, may wrap past midnight; empty days means every day
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct TimeWindow {
+    pub start: String,
+    pub end: String,
+    #[serde(default)]
+    pub days: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Aggressiveness {
+    // Only act on SLA-critical load
+    Conservative,
+    #[default]
+    Normal,
+    // Gets migration slots ahead of normal resources
+    Aggressive,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PolicyOverride {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub selector: RuleSelector,
+    #[serde(default)]
+    pub high_load_threshold: Option<f64>,
+    #[serde(default)]
+    pub low_load_threshold: Option<f64>,
+    #[serde(default)]
+    pub allowed_actions: Option<Vec<String>>,
+    #[serde(default)]
+    pub aggressiveness: Option<Aggressiveness>,
+    // Lets migrations take the resource out of its availability zone
+    #[serde(default)]
+    pub allow_cross_az: Option<bool>,
+}
+
+// Scheduling parameters resolved for a single resource
+#[derive(Debug, Clone, Serialize)]
+pub struct EffectivePolicy {
+    pub high_load_threshold: f64,
+    pub low_load_threshold: f64,
+    // None means every action
+    pub allowed_actions: Option<Vec<String>>,
+    pub aggressiveness: Aggressiveness,
+    pub allow_cross_az: bool,
+    pub applied_overrides: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PolicyBlock {
+    pub rule: String,
+    pub resource_id: String,
+    pub action: String,
+    pub target_host: Option<String>,
+    pub reason: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+pub struct PolicyEngine {
+    policy: PolicyFile,
+    blackouts: Vec<CompiledBlackout>,
+    recent_blocks: RwLock<VecDeque<PolicyBlock>>,
+}
+
+impl RuleSelector {
+    pub fn matches(&self, context: &ResourceContext) -> bool {
+        if let Some(project) = &self.project {
+            if context.project_id.as_ref() != Some(project) {
+                return false;
+            }
+        }
+        if let Some(flavor) = &self.flavor {
+            if &context.flavor_id != flavor {
+                return false;
+            }
+        }
+        self.metadata.iter().all(|(key, value)| context.metadata.get(key) == Some(value))
+    }
+}
+
+impl EffectivePolicy {
+    pub fn allows(&self, action: &SchedulingAction) -> bool {
+        self.allowed_actions
+            .as_ref()
+            .map(|actions| actions.iter().any(|a| a.eq_ignore_ascii_case(action.as_str())))
+            .unwrap_or(true)
+    }
+    
+    // Planners must leave the resource where it is
+    pub fn is_pinned(&self) -> bool {
+        !self.allows(&SchedulingAction::Migrate)
+    }
+}
+
+impl TimeWindow {
+    fn validate(&self) -> Result<()> {
+        Self::parse_time(&self.start)?;
+        Self::parse_time(&self.end)?;
+        for day in &self.days {
+            day.parse::<Weekday>().map_err(|_| {
+                SchedulerError::PolicyError(format!("Invalid weekday '{}'", day))
+            })?;
+        }
+        Ok(())
+    }
+    
+    fn parse_time(value: &str) -> Result<NaiveTime> {
+        NaiveTime::parse_from_str(value, "%H:%M")
+            .map_err(|_| SchedulerError::PolicyError(format!("Invalid time '{}', expected HH:MM", value)).into())
+    }
+    
+    fn contains(&self, now: DateTime<Utc>) -> bool {
+        let (Ok(start), Ok(end)) = (Self::parse_time(&self.start), Self::parse_time(&self.end)) else {
+            return false;
+        };
+        
+        if !self.days.is_empty() && !self.days.iter().any(|d| d.parse::<Weekday>().ok() == Some(now.weekday())) {
+            return false;
+        }
+        
+        let time = now.time();
+        if start <= end {
+            time >= start && time < end
+        } else {
+            time >= start || time < end
+        }
+    }
+}
+
+impl CompiledBlackout {
+    fn compile(window: BlackoutWindow, selector: Option<RuleSelector>) -> Result<Self> {
+        let schedule = Schedule::from_str(&window.schedule).map_err(|e| {
+            SchedulerError::PolicyError(format!("Blackout '{}': invalid schedule: {}", window.name, e))
+        })?;
+        if window.duration_minutes <= 0 {
+            return Err(SchedulerError::PolicyError(format!(
+                "Blackout '{}': duration_minutes must be positive",
+                window.name
+            )).into());
+        }
+        for action in &window.actions {
+            if !ACTION_NAMES.contains(&action.to_ascii_lowercase().as_str()) {
+                return Err(SchedulerError::PolicyError(format!(
+                    "Blackout '{}': unknown action '{}'",
+                    window.name, action
+                )).into());
+            }
+        }
+        
+        Ok(Self { window, selector, schedule })
+    }
+    
+    fn covers(&self, action: &SchedulingAction, context: &ResourceContext) -> bool {
+        let action_matches = if self.window.actions.is_empty() {
+            !matches!(action, SchedulingAction::ScaleOut { .. } | SchedulingAction::NoAction)
+        } else {
+            self.window.actions.iter().any(|a| a.eq_ignore_ascii_case(action.as_str()))
+        };
+        action_matches && self.selector.as_ref().map(|s| s.matches(context)).unwrap_or(true)
+    }
+    
+    // End of the window containing now, if any
+    fn active_until(&self, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let duration = Duration::minutes(self.window.duration_minutes);
+        let start = self.schedule.after(&(now - duration)).next()?;
+        (start <= now).then_some(start + duration)
+    }
+    
+    fn status(&self, now: DateTime<Utc>) -> BlackoutStatus {
+        BlackoutStatus {
+            window: self.window.clone(),
+            selector: self.selector.clone(),
+            active_until: self.active_until(now),
+            next_start: self.schedule.after(&now).next(),
+        }
+    }
+}
+
+impl PolicyRule {
+    fn applies_to(&self, action: &SchedulingAction, context: &ResourceContext, now: DateTime<Utc>) -> bool {
+        let action_matches = self.actions.is_empty()
+            || self.actions.iter().any(|a| a.eq_ignore_ascii_case(action.as_str()));
+        
+        action_matches
+            && self.selector.matches(context)
+            && self.during.as_ref().map(|w| w.contains(now)).unwrap_or(true)
+    }
+    
+    fn constrains_hosts(&self) -> bool {
+        !self.deny_hosts.is_empty() || !self.deny_aggregates.is_empty()
+    }
+}
+
+impl PolicyEngine {
+    pub fn new(policy: PolicyFile, global_blackouts: &[BlackoutWindow]) -> Result<Self> {
+        for rule in &policy.rules {
+            if let Some(window) = &rule.during {
+                window.validate().map_err(|e| {
+                    SchedulerError::PolicyError(format!("Rule '{}': {}", rule.name, e))
+                })?;
+            }
+            for action in &rule.actions {
+                if !ACTION_NAMES.contains(&action.to_ascii_lowercase().as_str()) {
+                    return Err(SchedulerError::PolicyError(format!(
+                        "Rule '{}': unknown action '{}'",
+                        rule.name, action
+                    )).into());
+                }
+            }
+            for aggregate in &rule.deny_aggregates {
+                if !policy.aggregates.contains_key(aggregate) {
+                    return Err(SchedulerError::PolicyError(format!(
+                        "Rule '{}' references unknown aggregate '{}'",
+                        rule.name, aggregate
+                    )).into());
+                }
+            }
+        }
+        
+        for policy_override in &policy.overrides {
+            Self::validate_override(policy_override)?;
+        }
+        
+        let mut blackouts = global_blackouts.iter()
+            .map(|window| CompiledBlackout::compile(window.clone(), None))
+            .collect::<Result<Vec<_>>>()?;
+        for blackout in &policy.blackouts {
+            blackouts.push(CompiledBlackout::compile(blackout.window.clone(), Some(blackout.selector.clone()))?);
+        }
+        
+        Ok(Self {
+            policy,
+            blackouts,
+            recent_blocks: RwLock::new(VecDeque::new()),
+        })
+    }
+    
+    pub fn from_file(path: Option<&str>, global_blackouts: &[BlackoutWindow]) -> Result<Self> {
+        let policy = match path {
+            Some(path) => {
+                let content = fs::read_to_string(path)?;
+                let policy: PolicyFile = toml::from_str(&content)
+                    .map_err(|e| SchedulerError::PolicyError(format!("{}: {}", path, e)))?;
+                info!("Loaded {} scheduling policy rules from {}", policy.rules.len(), path);
+                policy
+            }
+            None => PolicyFile::default(),
+        };
+        
+        Self::new(policy, global_blackouts)
+    }
+    
+    fn validate_override(policy_override: &PolicyOverride) -> Result<()> {
+        let invalid = |reason: String| -> anyhow::Error {
+            SchedulerError::PolicyError(format!("Override '{}': {}", policy_override.name, reason)).into()
+        };
+        
+        for threshold in [policy_override.high_load_threshold, policy_override.low_load_threshold].into_iter().flatten() {
+            if !(0.0..=100.0).contains(&threshold) {
+                return Err(invalid(format!("threshold {} must be between 0 and 100", threshold)));
+            }
+        }
+        if let (Some(high), Some(low)) = (policy_override.high_load_threshold, policy_override.low_load_threshold) {
+            if low >= high {
+                return Err(invalid("low_load_threshold must be below high_load_threshold".to_string()));
+            }
+        }
+        for action in policy_override.allowed_actions.iter().flatten() {
+            if !ACTION_NAMES.contains(&action.to_ascii_lowercase().as_str()) {
+                return Err(invalid(format!("unknown action '{}'", action)));
+            }
+        }
+        Ok(())
+    }
+    
+    // An override setting one threshold keeps the scheduler's other one, so
+    // it's checked against the defaults, at startup and on every reload
+    pub fn validate_thresholds(&self, high_load_threshold: f64, low_load_threshold: f64) -> Result<()> {
+        for policy_override in &self.policy.overrides {
+            let high = policy_override.high_load_threshold.unwrap_or(high_load_threshold);
+            let low = policy_override.low_load_threshold.unwrap_or(low_load_threshold);
+            if low >= high {
+                return Err(SchedulerError::PolicyError(format!(
+                    "Override '{}': low_load_threshold {} must be below high_load_threshold {}",
+                    policy_override.name, low, high
+                )).into());
+            }
+        }
+        Ok(())
+    }
+    
+    pub fn rules(&self) -> &[PolicyRule] {
+        &self.policy.rules
+    }
+    
+    pub fn overrides(&self) -> &[PolicyOverride] {
+        &self.policy.overrides
+    }
+    
+    // Blackout holding the action back right now; the one ending last wins
+    pub fn active_blackout(
+        &self,
+        action: &SchedulingAction,
+        context: &ResourceContext,
+        now: DateTime<Utc>,
+    ) -> Option<ActiveBlackout> {
+        self.blackouts.iter()
+            .filter(|b| b.covers(action, context))
+            .filter_map(|b| b.active_until(now).map(|ends_at| (b, ends_at)))
+            .max_by_key(|(_, ends_at)| *ends_at)
+            .map(|(b, ends_at)| ActiveBlackout {
+                name: b.window.name.clone(),
+                ends_at,
+                mode: b.window.on_blackout,
+            })
+    }
+    
+    pub fn blackouts(&self, now: DateTime<Utc>) -> Vec<BlackoutStatus> {
+        self.blackouts.iter().map(|b| b.status(now)).collect()
+    }
+    
+    // Scheduler defaults with every matching override applied in file order
+    pub fn effective_policy(
+        &self,
+        context: &ResourceContext,
+        high_load_threshold: f64,
+        low_load_threshold: f64,
+        aggressiveness: Aggressiveness,
+    ) -> EffectivePolicy {
+        let mut effective = EffectivePolicy {
+            high_load_threshold,
+            low_load_threshold,
+            allowed_actions: None,
+            aggressiveness,
+            allow_cross_az: false,
+            applied_overrides: Vec::new(),
+        };
+        
+        for policy_override in self.policy.overrides.iter().filter(|o| o.selector.matches(context)) {
+            if let Some(high) = policy_override.high_load_threshold {
+                effective.high_load_threshold = high;
+            }
+            if let Some(low) = policy_override.low_load_threshold {
+                effective.low_load_threshold = low;
+            }
+            if let Some(actions) = &policy_override.allowed_actions {
+                effective.allowed_actions = Some(actions.clone());
+            }
+            if let Some(aggressiveness) = policy_override.aggressiveness {
+                effective.aggressiveness = aggressiveness;
+            }
+            if let Some(allow_cross_az) = policy_override.allow_cross_az {
+                effective.allow_cross_az = allow_cross_az;
+            }
+            effective.applied_overrides.push(policy_override.name.clone());
+        }
+        // Overrides that are each valid can still cross when several match
+        if effective.low_load_threshold >= effective.high_load_threshold {
+            warn!(
+                "Overrides {} leave {} with low_load_threshold {} above high_load_threshold {}, using the defaults",
+                effective.applied_overrides.join(", "),
+                context.resource_id,
+                effective.low_load_threshold,
+                effective.high_load_threshold
+            );
+            effective.high_load_threshold = high_load_threshold;
+            effective.low_load_threshold = low_load_threshold;
+        }
+        
+        effective
+    }
+    
+    // Hosts the resource must not be placed on for this action right now
+    pub fn denied_hosts(
+        &self,
+        action: &SchedulingAction,
+        context: &ResourceContext,
+        now: DateTime<Utc>,
+    ) -> HashSet<String> {
+        let mut hosts = HashSet::new();
+        
+        for rule in self.policy.rules.iter().filter(|r| r.constrains_hosts()) {
+            if !rule.applies_to(action, context, now) {
+                continue;
+            }
+            hosts.extend(rule.deny_hosts.iter().cloned());
+            for aggregate in &rule.deny_aggregates {
+                if let Some(members) = self.policy.aggregates.get(aggregate) {
+                    hosts.extend(members.iter().cloned());
+                }
+            }
+        }
+        
+        hosts
+    }
+    
+    // Returns the first rule that blocks the decision, if any
+    pub fn evaluate(
+        &self,
+        action: &SchedulingAction,
+        target_host: Option<&str>,
+        context: &ResourceContext,
+        now: DateTime<Utc>,
+    ) -> Option<PolicyBlock> {
+        for rule in &self.policy.rules {
+            if !rule.applies_to(action, context, now) {
+                continue;
+            }
+            
+            let reason = if !rule.constrains_hosts() {
+                Some(format!("{} is not allowed for this resource", action.as_str()))
+            } else {
+                target_host
+                    .filter(|host| self.denied_hosts(action, context, now).contains(*host))
+                    .map(|host| format!("host {} is excluded for this resource", host))
+            };
+            
+            if let Some(reason) = reason {
+                return Some(PolicyBlock {
+                    rule: rule.name.clone(),
+                    resource_id: context.resource_id.clone(),
+                    action: action.as_str().to_string(),
+                    target_host: target_host.map(str::to_string),
+                    reason,
+                    timestamp: now,
+                });
+            }
+        }
+        
+        None
+    }
+    
+    pub async fn record_block(&self, block: PolicyBlock) {
+        warn!(
+            "Policy rule '{}' blocked {} of {}: {}",
+            block.rule, block.action, block.resource_id, block.reason
+        );
+        
+        let mut blocks = self.recent_blocks.write().await;
+        blocks.push_back(block);
+        while blocks.len() > MAX_RECORDED_BLOCKS {
+            blocks.pop_front();
+        }
+    }
+    
+    pub async fn recent_blocks(&self) -> Vec<PolicyBlock> {
+        self.recent_blocks.read().await.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    
+    // 2024-01-01 is a Monday
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, day, hour, minute, 0).unwrap()
+    }
+    
+    fn window(start: &str, end: &str, days: &[&str]) -> TimeWindow {
+        TimeWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+            days: days.iter().map(|d| d.to_string()).collect(),
+        }
+    }
+    
+    fn blackout(schedule: &str, duration_minutes: i64, actions: &[&str]) -> Result<CompiledBlackout> {
+        CompiledBlackout::compile(
+            BlackoutWindow {
+                name: "nightly".to_string(),
+                schedule: schedule.to_string(),
+                duration_minutes,
+                actions: actions.iter().map(|a| a.to_string()).collect(),
+                on_blackout: BlackoutMode::Queue,
+            },
+            None,
+        )
+    }
+    
+    #[test]
+    fn time_window_wraps_past_midnight() {
+        let night = window("22:00", "06:00", &[]);
+        assert!(night.contains(at(1, 23, 0)));
+        assert!(night.contains(at(2, 5, 59)));
+        assert!(!night.contains(at(2, 6, 0)));
+        assert!(!night.contains(at(1, 12, 0)));
+        
+        let day = window("08:00", "20:00", &[]);
+        assert!(day.contains(at(1, 8, 0)));
+        assert!(!day.contains(at(1, 20, 0)));
+    }
+    
+    #[test]
+    fn time_window_only_applies_on_its_days() {
+        let mondays = window("00:00", "23:59", &["Mon"]);
+        assert!(mondays.contains(at(1, 12, 0)));
+        assert!(!mondays.contains(at(2, 12, 0)));
+    }
+    
+    #[test]
+    fn blackout_is_active_from_each_start_for_its_duration() {
+        let blackout = blackout("0 0 2 * * *", 60, &[]).unwrap();
+        assert_eq!(blackout.active_until(at(1, 2, 0)), Some(at(1, 3, 0)));
+        assert_eq!(blackout.active_until(at(1, 2, 30)), Some(at(1, 3, 0)));
+        assert_eq!(blackout.active_until(at(1, 3, 0)), None);
+        assert_eq!(blackout.active_until(at(1, 1, 59)), None);
+    }
+    
+    #[test]
+    fn rejects_invalid_blackouts() {
+        assert!(blackout("not a schedule", 60, &[]).is_err());
+        assert!(blackout("0 0 2 * * *", 0, &[]).is_err());
+        assert!(blackout("0 0 2 * * *", 60, &["reboot"]).is_err());
+    }
+    
+    #[test]
+    fn rejects_rules_with_unknown_actions() {
+        let rule = |action: &str| PolicyFile {
+            rules: vec![PolicyRule {
+                name: "protect".to_string(),
+                description: None,
+                actions: vec![action.to_string()],
+                selector: RuleSelector::default(),
+                during: None,
+                deny_hosts: Vec::new(),
+                deny_aggregates: Vec::new(),
+            }],
+            ..PolicyFile::default()
+        };
+        assert!(PolicyEngine::new(rule("Migrate"), &[]).is_ok());
+        assert!(PolicyEngine::new(rule("evacuate"), &[]).is_err());
+    }
+}
@@ -0,0 +1,220 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::RwLock;
+use tracing::{debug, info, warn};
+
+use crate::config::PowerManagementConfig;
+use crate::error::SchedulerError;
+use crate::openstack::services::PowerTarget;
+use crate::openstack::Clouds;
+use super::cluster::ClusterSnapshot;
+
+pub struct PowerManager {
+    config: PowerManagementConfig,
+    clouds: Clouds,
+    host_states: RwLock<HashMap<String, HostPowerRecord>>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PowerState {
+    On,
+    Off,
+}
+
+#[derive(Debug, Clone)]
+struct HostPowerRecord {
+    state: PowerState,
+    total_vcpus: u32,
+    empty_since: Option<DateTime<Utc>>,
+    // Whose Ironic the host's node is in
+    region: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PowerAction {
+    PowerOn(String),
+    PowerOff(String),
+}
+
+impl PowerManager {
+    pub fn new(config: PowerManagementConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+            host_states: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Hosts that are powered down and must not receive placements
+    pub async fn powered_off_hosts(&self) -> HashSet<String> {
+        self.host_states.read().await
+            .iter()
+            .filter(|(_, record)| record.state == PowerState::Off)
+            .map(|(host_id, _)| host_id.clone())
+            .collect()
+    }
+    
+    // Returns the hosts it powered off. A failed action doesn't hold up the rest
+    pub async fn run_power_cycle(&self, snapshot: &ClusterSnapshot) -> Result<Vec<String>> {
+        let actions = {
+            let mut states = self.host_states.write().await;
+            self.observe_hosts(&mut states, snapshot);
+            self.plan_power_actions(&states, snapshot.predicted_vcpu_demand())
+        };
+        
+        let mut powered_off = Vec::new();
+        for action in actions {
+            let host_id = match &action {
+                PowerAction::PowerOff(host_id) => Some(host_id.clone()),
+                PowerAction::PowerOn(_) => None,
+            };
+            match self.apply(action).await {
+                Ok(true) => powered_off.extend(host_id),
+                Ok(false) => {}
+                Err(e) => warn!("{}", e),
+            }
+        }
+        
+        Ok(powered_off)
+    }
+    
+    // Power on the largest sleeping host, used when placement finds no
+    // headroom. Hosts without an Ironic node can't be woken, so aren't picked
+    pub async fn wake_on_demand(&self) -> Result<Option<String>> {
+        let candidate = {
+            let states = self.host_states.read().await;
+            states.iter()
+                .filter(|(host_id, record)| {
+                    record.state == PowerState::Off && self.config.host_nodes.contains_key(*host_id)
+                })
+                .max_by_key(|(_, record)| record.total_vcpus)
+                .map(|(host_id, _)| host_id.clone())
+        };
+        
+        let Some(host_id) = candidate else {
+            return Ok(None);
+        };
+        info!("No placement headroom, waking host {}", host_id);
+        if self.apply(PowerAction::PowerOn(host_id.clone())).await? {
+            Ok(Some(host_id))
+        } else {
+            Ok(None)
+        }
+    }
+    
+    fn observe_hosts(&self, states: &mut HashMap<String, HostPowerRecord>, snapshot: &ClusterSnapshot) {
+        let now = Utc::now();
+        
+        for host in &snapshot.hosts {
+            let record = states.entry(host.host_id.clone()).or_insert_with(|| HostPowerRecord {
+                state: PowerState::On,
+                total_vcpus: host.total_vcpus,
+                empty_since: None,
+                region: None,
+            });
+            
+            record.total_vcpus = host.total_vcpus;
+            record.region = host.region.clone();
+            
+            let is_empty = host.vm_count == 0 && snapshot.is_host_empty(&host.host_id);
+            record.empty_since = match (is_empty, record.empty_since) {
+                (true, Some(since)) => Some(since),
+                (true, None) => Some(now),
+                (false, _) => None,
+            };
+        }
+    }
+    
+    fn plan_power_actions(
+        &self,
+        states: &HashMap<String, HostPowerRecord>,
+        predicted_vcpu_demand: f64,
+    ) -> Vec<PowerAction> {
+        let demand = predicted_vcpu_demand * (1.0 + self.config.safety_margin);
+        
+        let mut online: Vec<(&String, &HostPowerRecord)> = states.iter()
+            .filter(|(_, r)| r.state == PowerState::On)
+            .collect();
+        let mut offline: Vec<(&String, &HostPowerRecord)> = states.iter()
+            .filter(|(_, r)| r.state == PowerState::Off)
+            .collect();
+        
+        let mut online_capacity: f64 = online.iter().map(|(_, r)| r.total_vcpus as f64).sum();
+        let mut actions = Vec::new();
+        
+        // Wake hosts until forecasted demand fits under the wake threshold again
+        if demand > online_capacity * self.config.wake_threshold {
+            offline.sort_by_key(|(_, r)| std::cmp::Reverse(r.total_vcpus));
+            
+            for (host_id, record) in offline {
+                if demand <= online_capacity * self.config.wake_threshold {
+                    break;
+                }
+                online_capacity += record.total_vcpus as f64;
+                actions.push(PowerAction::PowerOn(host_id.clone()));
+            }
+            
+            return actions;
+        }
+        
+        // Power down at most one emptied host per cycle, smallest first
+        if online.len() <= self.config.min_online_hosts {
+            return actions;
+        }
+        
+        let cutoff = Utc::now() - Duration::minutes(self.config.min_empty_minutes);
+        online.sort_by_key(|(_, r)| r.total_vcpus);
+        
+        for (host_id, record) in online {
+            let empty_long_enough = record.empty_since.map(|since| since <= cutoff).unwrap_or(false);
+            let remaining_capacity = online_capacity - record.total_vcpus as f64;
+            
+            if empty_long_enough && demand <= remaining_capacity * self.config.wake_threshold {
+                actions.push(PowerAction::PowerOff(host_id.clone()));
+                break;
+            }
+        }
+        
+        actions
+    }
+    
+    // False when the host has no node to act on
+    async fn apply(&self, action: PowerAction) -> Result<bool> {
+        let (host_id, target, new_state) = match &action {
+            PowerAction::PowerOn(host_id) => (host_id, PowerTarget::PowerOn, PowerState::On),
+            PowerAction::PowerOff(host_id) => (host_id, PowerTarget::SoftPowerOff, PowerState::Off),
+        };
+        
+        let node_id = match self.config.host_nodes.get(host_id) {
+            Some(node_id) => node_id,
+            None => {
+                warn!("No Ironic node mapped for host {}, skipping {:?}", host_id, action);
+                return Ok(false);
+            }
+        };
+        
+        debug!("Applying power action {:?} via node {}", action, node_id);
+        
+        let region = self.host_states.read().await.get(host_id).and_then(|record| record.region.clone());
+        self.clouds.region(region.as_deref()).ironic
+            .set_power_state(node_id, target)
+            .await
+            .map_err(|e| SchedulerError::DecisionError(
+                format!("Power action {:?} failed: {}", action, e)
+            ))?;
+        
+        let mut states = self.host_states.write().await;
+        if let Some(record) = states.get_mut(host_id) {
+            record.state = new_state;
+            record.empty_since = None;
+        }
+        
+        info!("Host {} is now {:?}", host_id, new_state);
+        Ok(true)
+    }
+}
@@ -0,0 +1,176 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::config::PreemptionConfig;
+use crate::openstack::Clouds;
+use super::cluster::{ClusterSnapshot, InstancePlacement};
+
+const MAX_AUDIT_RECORDS: usize = 1000;
+
+// Evicts instances tagged preemptible to make room for SLA-critical instances
+// when no host has headroom left
+pub struct PreemptionManager {
+    config: PreemptionConfig,
+    clouds: Clouds,
+    audit_log: RwLock<VecDeque<PreemptionRecord>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PreemptionAction {
+    Shelve,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PreemptionRecord {
+    pub victim: String,
+    pub victim_priority: i64,
+    pub beneficiary: String,
+    pub host: String,
+    pub action: PreemptionAction,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+struct Victim<'a> {
+    instance: &'a InstancePlacement,
+    priority: i64,
+}
+
+impl PreemptionManager {
+    pub fn new(config: PreemptionConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+            audit_log: RwLock::new(VecDeque::new()),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Frees room for the beneficiary on a single host and returns that host
+    pub async fn preempt_for(
+        &self,
+        beneficiary: &str,
+        snapshot: &ClusterSnapshot,
+        excluded_hosts: &HashSet<String>,
+    ) -> Result<Option<String>> {
+        let needed = match snapshot.instance(beneficiary) {
+            Some(instance) => instance,
+            None => return Ok(None),
+        };
+        let need_vcpus = needed.vcpus as f64;
+        let need_memory = needed.memory_mb as f64;
+        
+        // Pick the host whose most valuable victim is the least valuable overall,
+        // then the one needing the fewest evictions
+        let mut best: Option<(&str, Vec<Victim>)> = None;
+        let hosts = snapshot.hosts.iter()
+            .filter(|h| !excluded_hosts.contains(&h.host_id) && h.host_id != needed.host_id);
+        for host in hosts {
+            let (mut free_vcpus, mut free_memory) = snapshot.predicted_headroom(host);
+            let mut candidates = self.victims_on(snapshot, &host.host_id);
+            candidates.sort_by(|a, b| {
+                a.priority.cmp(&b.priority).then(
+                    b.instance.predicted_vcpu_demand().total_cmp(&a.instance.predicted_vcpu_demand())
+                )
+            });
+            
+            let mut chosen = Vec::new();
+            for victim in candidates {
+                if (free_vcpus >= need_vcpus && free_memory >= need_memory)
+                    || chosen.len() >= self.config.max_victims_per_decision
+                {
+                    break;
+                }
+                free_vcpus += victim.instance.predicted_vcpu_demand();
+                free_memory += victim.instance.memory_mb as f64;
+                chosen.push(victim);
+            }
+            
+            if free_vcpus < need_vcpus || free_memory < need_memory {
+                continue;
+            }
+            
+            let key = |victims: &[Victim]| {
+                (victims.iter().map(|v| v.priority).max().unwrap_or(i64::MIN), victims.len())
+            };
+            if best.as_ref().map(|(_, current)| key(&chosen) < key(current)).unwrap_or(true) {
+                best = Some((host.host_id.as_str(), chosen));
+            }
+        }
+        
+        let (host_id, victims) = match best {
+            Some(best) => best,
+            None => {
+                warn!("No preemptible capacity can be freed for {}", beneficiary);
+                return Ok(None);
+            }
+        };
+        
+        for victim in victims {
+            let action = self.evict(&victim).await?;
+            self.record(PreemptionRecord {
+                victim: victim.instance.resource_id.clone(),
+                victim_priority: victim.priority,
+                beneficiary: beneficiary.to_string(),
+                host: host_id.to_string(),
+                action,
+                timestamp: Utc::now(),
+            }).await;
+        }
+        
+        Ok(Some(host_id.to_string()))
+    }
+    
+    pub async fn audit_log(&self) -> Vec<PreemptionRecord> {
+        self.audit_log.read().await.iter().cloned().collect()
+    }
+    
+    fn victims_on<'a>(&self, snapshot: &'a ClusterSnapshot, host_id: &'a str) -> Vec<Victim<'a>> {
+        snapshot.instances_on(host_id)
+            .filter_map(|instance| {
+                let context = snapshot.resources.get(&instance.resource_id)?;
+                let preemptible = context.metadata
+                    .get(&self.config.preemptible_metadata_key)
+                    .map(|v| v.eq_ignore_ascii_case("true"))
+                    .unwrap_or(false);
+                if !preemptible {
+                    return None;
+                }
+                let priority = context.metadata
+                    .get(&self.config.priority_metadata_key)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(0);
+                Some(Victim { instance, priority })
+            })
+            .collect()
+    }
+    
+    // Victims are shelved rather than moved: a migration would leave the
+    // host full until it finished, after the beneficiary has been placed
+    async fn evict(&self, victim: &Victim<'_>) -> Result<PreemptionAction> {
+        info!("Preempting {}: shelving", victim.instance.resource_id);
+        self.clouds.client_for(&victim.instance.resource_id).nova.shelve_server(&victim.instance.resource_id).await?;
+        Ok(PreemptionAction::Shelve)
+    }
+    
+    async fn record(&self, record: PreemptionRecord) {
+        info!(
+            "Preemption: {} (priority {}) evicted from {} for {} via {:?}",
+            record.victim, record.victim_priority, record.host, record.beneficiary, record.action
+        );
+        
+        let mut log = self.audit_log.write().await;
+        log.push_back(record);
+        while log.len() > MAX_AUDIT_RECORDS {
+            log.pop_front();
+        }
+    }
+}
@@ -0,0 +1,105 @@
+use chrono::{DateTime, Duration, Utc};
+use std::collections::{HashMap, HashSet};
+
+use crate::config::PrescalingConfig;
+use crate::ml::predictor::LoadForecast;
+use super::cluster::{ClusterSnapshot, InstancePlacement};
+
+// Looks ahead in the load forecasts so capacity is in place before a peak
+// arrives rather than after it has been observed
+pub struct PreScaler {
+    config: PrescalingConfig,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PeakForecast {
+    pub load: f64,
+    pub at: DateTime<Utc>,
+    pub confidence: f64,
+}
+
+#[derive(Debug, Clone)]
+pub struct PreMigration {
+    pub resource_id: String,
+    pub source_host: String,
+    // Host utilization (0-100) forecast at its peak before anything moves
+    pub host_peak_utilization: f64,
+    pub peak: PeakForecast,
+}
+
+impl PreScaler {
+    pub fn new(config: PrescalingConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Highest forecast load within the lead time, if the forecast is trusted
+    pub fn peak(&self, forecast: &LoadForecast, now: DateTime<Utc>) -> Option<PeakForecast> {
+        if forecast.confidence < self.config.min_confidence || forecast.step_minutes == 0 {
+            return None;
+        }
+        
+        // At least the first step, even when the lead time is shorter
+        let steps = (self.config.lead_time_minutes / forecast.step_minutes).max(1) as usize;
+        forecast.values.iter()
+            .take(steps)
+            .enumerate()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(step, &load)| PeakForecast {
+                load,
+                at: now + Duration::minutes(((step + 1) as u32 * forecast.step_minutes) as i64),
+                confidence: forecast.confidence,
+            })
+    }
+    
+    // Instances to move off hosts whose forecast peak exceeds the high load
+    // threshold, largest peak demand first, until the rest fits under it
+    pub fn premigrations(
+        &self,
+        snapshot: &ClusterSnapshot,
+        peaks: &HashMap<String, PeakForecast>,
+        high_load_threshold: f64,
+        skipped: &HashSet<String>,
+    ) -> Vec<PreMigration> {
+        let peak_demand = |instance: &InstancePlacement| -> f64 {
+            peaks.get(&instance.resource_id)
+                .map(|p| instance.vcpus as f64 * p.load.max(instance.predicted_load) / 100.0)
+                .unwrap_or_else(|| instance.predicted_vcpu_demand())
+        };
+        
+        let mut premigrations = Vec::new();
+        for host in &snapshot.hosts {
+            let total = host.total_vcpus.max(1) as f64;
+            let (unmanaged_vcpus, _) = snapshot.unmanaged_load(host);
+            let mut demand = unmanaged_vcpus + snapshot.instances_on(&host.host_id).map(peak_demand).sum::<f64>();
+            let host_peak_utilization = demand / total * 100.0;
+            if host_peak_utilization <= high_load_threshold {
+                continue;
+            }
+            
+            let mut movable: Vec<(&InstancePlacement, PeakForecast)> = snapshot.instances_on(&host.host_id)
+                .filter(|i| !i.pinned && !skipped.contains(&i.resource_id))
+                .filter_map(|i| peaks.get(&i.resource_id).map(|p| (i, *p)))
+                .collect();
+            movable.sort_by(|(a, _), (b, _)| peak_demand(b).total_cmp(&peak_demand(a)));
+            
+            for (instance, peak) in movable {
+                if demand / total * 100.0 <= high_load_threshold {
+                    break;
+                }
+                demand -= peak_demand(instance);
+                premigrations.push(PreMigration {
+                    resource_id: instance.resource_id.clone(),
+                    source_host: host.host_id.clone(),
+                    host_peak_utilization,
+                    peak,
+                });
+            }
+        }
+        
+        premigrations
+    }
+}
@@ -0,0 +1,310 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::sync::Mutex;
+use tracing::debug;
+
+use crate::config::RebalanceConfig;
+use super::cluster::{ClusterSnapshot, InstancePlacement};
+use super::consolidation::MigrationStep;
+use super::disruption::DisruptionAllowance;
+
+// Brings host load imbalance under a target coefficient of variation with as
+// few migrations as it can, never spending more than the budget per window
+pub struct RebalancePlanner {
+    config: RebalanceConfig,
+    // When each planned rebalancing migration was handed to the executor
+    spent: Mutex<VecDeque<DateTime<Utc>>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RebalancePlan {
+    pub steps: Vec<MigrationStep>,
+    // Coefficient of variation of predicted host utilization
+    pub cv_before: f64,
+    pub cv_after: f64,
+}
+
+impl RebalancePlan {
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RebalanceReport {
+    pub cv_before: f64,
+    pub cv_after: f64,
+    pub target_cv: f64,
+    pub migrations: usize,
+    pub budget_remaining: usize,
+    pub planned_at: DateTime<Utc>,
+}
+
+struct HostLoad {
+    host_id: String,
+    total_vcpus: f64,
+    total_memory_mb: f64,
+    vcpus: f64,
+    memory_mb: f64,
+}
+
+impl HostLoad {
+    fn utilization(&self) -> f64 {
+        self.vcpus / self.total_vcpus
+    }
+}
+
+impl RebalancePlanner {
+    pub fn new(config: RebalanceConfig) -> Self {
+        Self {
+            config,
+            spent: Mutex::new(VecDeque::new()),
+        }
+    }
+    
+    pub fn target_cv(&self) -> f64 {
+        self.config.target_cv
+    }
+    
+    pub fn budget_remaining(&self, now: DateTime<Utc>) -> usize {
+        let mut spent = self.spent.lock().unwrap();
+        let cutoff = now - Duration::minutes(self.config.budget_window_minutes);
+        while spent.front().is_some_and(|at| *at < cutoff) {
+            spent.pop_front();
+        }
+        self.config.migration_budget.saturating_sub(spent.len())
+    }
+    
+    pub fn record_spent(&self, migrations: usize, now: DateTime<Utc>) {
+        self.spent.lock().unwrap().extend(std::iter::repeat_n(now, migrations));
+    }
+    
+    // Greedy: each step is the single move off an above-average host that
+    // lowers the imbalance most, until the target or the budget is reached
+    pub fn plan(
+        &self,
+        snapshot: &ClusterSnapshot,
+        excluded_hosts: &HashSet<String>,
+        allowance: &DisruptionAllowance,
+        budget: usize,
+    ) -> RebalancePlan {
+        let mut hosts: Vec<HostLoad> = snapshot.hosts.iter()
+            .filter(|h| !excluded_hosts.contains(&h.host_id) && h.total_vcpus > 0)
+            .map(|h| {
+                let (unmanaged_vcpus, unmanaged_memory) = snapshot.unmanaged_load(h);
+                let instances: Vec<_> = snapshot.instances_on(&h.host_id).collect();
+                HostLoad {
+                    host_id: h.host_id.clone(),
+                    total_vcpus: h.total_vcpus as f64,
+                    total_memory_mb: h.total_memory_mb as f64,
+                    vcpus: unmanaged_vcpus + instances.iter().map(|i| i.predicted_vcpu_demand()).sum::<f64>(),
+                    memory_mb: unmanaged_memory + instances.iter().map(|i| i.memory_mb as f64).sum::<f64>(),
+                }
+            })
+            .collect();
+        
+        let cv_before = coefficient_of_variation(&hosts);
+        let mut plan = RebalancePlan {
+            steps: Vec::new(),
+            cv_before,
+            cv_after: cv_before,
+        };
+        if hosts.len() < 2 {
+            return plan;
+        }
+        
+        let mut allowance = allowance.clone();
+        let mut moved: HashSet<String> = HashSet::new();
+        while plan.cv_after > self.config.target_cv && plan.steps.len() < budget {
+            let mean = hosts.iter().map(|h| h.utilization()).sum::<f64>() / hosts.len() as f64;
+            let mut best: Option<(f64, &InstancePlacement, usize, usize)> = None;
+            
+            for (from, source) in hosts.iter().enumerate().filter(|(_, h)| h.utilization() > mean) {
+                for instance in snapshot.instances.iter().filter(|i| i.host_id == source.host_id) {
+                    if instance.pinned
+                        || moved.contains(&instance.resource_id)
+                        || allowance.exhausted_budget(&snapshot.resource_context(&instance.resource_id)).is_some()
+                    {
+                        continue;
+                    }
+                    
+                    let demand = instance.predicted_vcpu_demand();
+                    let memory = instance.memory_mb as f64;
+                    for (to, target) in hosts.iter().enumerate() {
+                        if to == from
+                            || (target.vcpus + demand) / target.total_vcpus > self.config.max_target_utilization
+                            || target.memory_mb + memory > target.total_memory_mb
+                            || !snapshot.can_migrate(instance, &target.host_id)
+                        {
+                            continue;
+                        }
+                        
+                        let cv = cv_after_move(&hosts, from, to, demand);
+                        if best.map(|(best_cv, ..)| cv < best_cv).unwrap_or(true) {
+                            best = Some((cv, instance, from, to));
+                        }
+                    }
+                }
+            }
+            
+            // Stop once no single move helps any more
+            let (cv, instance, from, to) = match best {
+                Some(best) if best.0 < plan.cv_after => best,
+                _ => break,
+            };
+            
+            let demand = instance.predicted_vcpu_demand();
+            let memory = instance.memory_mb as f64;
+            hosts[from].vcpus -= demand;
+            hosts[from].memory_mb -= memory;
+            hosts[to].vcpus += demand;
+            hosts[to].memory_mb += memory;
+            allowance.take(&snapshot.resource_context(&instance.resource_id));
+            moved.insert(instance.resource_id.clone());
+            plan.cv_after = cv;
+            plan.steps.push(MigrationStep {
+                resource_id: instance.resource_id.clone(),
+                source_host: hosts[from].host_id.clone(),
+                target_host: hosts[to].host_id.clone(),
+                predicted_vcpus: demand,
+                memory_mb: instance.memory_mb,
+            });
+        }
+        
+        debug!(
+            "Rebalance plan: CV {:.3} -> {:.3} (target {:.3}) with {} migrations",
+            plan.cv_before,
+            plan.cv_after,
+            self.config.target_cv,
+            plan.steps.len()
+        );
+        plan
+    }
+}
+
+fn coefficient_of_variation(hosts: &[HostLoad]) -> f64 {
+    let utilization: Vec<f64> = hosts.iter().map(|h| h.utilization()).collect();
+    cv_of(&utilization)
+}
+
+fn cv_after_move(hosts: &[HostLoad], from: usize, to: usize, demand: f64) -> f64 {
+    let utilization: Vec<f64> = hosts.iter()
+        .enumerate()
+        .map(|(idx, h)| {
+            let vcpus = match idx {
+                idx if idx == from => h.vcpus - demand,
+                idx if idx == to => h.vcpus + demand,
+                _ => h.vcpus,
+            };
+            vcpus / h.total_vcpus
+        })
+        .collect();
+    cv_of(&utilization)
+}
+
+fn cv_of(values: &[f64]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    let mean = values.iter().sum::<f64>() / values.len() as f64;
+    if mean <= 0.0 {
+        return 0.0;
+    }
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / values.len() as f64;
+    variance.sqrt() / mean
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use crate::scheduler::placement::HostMetrics;
+    
+    fn host(host_id: &str, vm_count: u32) -> HostMetrics {
+        HostMetrics {
+            host_id: host_id.to_string(),
+            cpu_utilization: 0.0,
+            memory_utilization: 0.0,
+            disk_utilization: 0.0,
+            network_utilization: 0.0,
+            vm_count,
+            total_vcpus: 16,
+            total_memory_mb: 65536,
+            available_vcpus: 16,
+            available_memory_mb: 65536,
+            availability_zone: None,
+            aggregates: Vec::new(),
+            allowed_projects: None,
+            region: None,
+            resource_class: None,
+            last_updated: Utc::now(),
+        }
+    }
+    
+    fn instance(resource_id: &str, host_id: &str, vcpus: u32) -> InstancePlacement {
+        InstancePlacement {
+            resource_id: resource_id.to_string(),
+            host_id: host_id.to_string(),
+            vcpus,
+            memory_mb: 2048,
+            predicted_load: 100.0,
+            pinned: false,
+            cross_az: false,
+            penalty: None,
+            storage: Default::default(),
+        }
+    }
+    
+    // compute-1 predicted at 75%, the other two at 12.5%
+    fn snapshot() -> ClusterSnapshot {
+        ClusterSnapshot::new(
+            vec![host("compute-1", 3), host("compute-2", 1), host("compute-3", 1)],
+            vec![
+                instance("a", "compute-1", 4),
+                instance("b", "compute-1", 4),
+                instance("c", "compute-1", 4),
+                instance("d", "compute-2", 2),
+                instance("e", "compute-3", 2),
+            ],
+            HashMap::new(),
+            Arc::new(Default::default()),
+            Arc::new(Default::default()),
+        )
+    }
+    
+    fn plan(budget: usize) -> RebalancePlan {
+        RebalancePlanner::new(RebalanceConfig::default())
+            .plan(&snapshot(), &HashSet::new(), &DisruptionAllowance::default(), budget)
+    }
+    
+    #[test]
+    fn lowers_imbalance_within_the_budget() {
+        let one = plan(1);
+        assert_eq!(one.steps.len(), 1);
+        assert_eq!(one.steps[0].source_host, "compute-1");
+        assert!(one.cv_after < one.cv_before);
+        
+        let more = plan(10);
+        assert!(more.steps.len() > 1 && more.steps.len() <= 10);
+        assert!(more.cv_after < one.cv_after);
+        assert!(more.steps.iter().all(|s| s.source_host == "compute-1"));
+    }
+    
+    #[test]
+    fn plans_nothing_without_budget() {
+        let none = plan(0);
+        assert!(none.is_empty());
+        assert_eq!(none.cv_after, none.cv_before);
+    }
+    
+    #[test]
+    fn spent_budget_comes_back_after_the_window() {
+        let planner = RebalancePlanner::new(RebalanceConfig::default());
+        let now = Utc::now();
+        planner.record_spent(3, now);
+        assert_eq!(planner.budget_remaining(now), 7);
+        assert_eq!(planner.budget_remaining(now + Duration::minutes(61)), 10);
+    }
+}
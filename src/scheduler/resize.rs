@@ -0,0 +1,144 @@
+use anyhow::Result;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+use crate::config::ResizeConfig;
+use crate::error::SchedulerError;
+use crate::openstack::services::ResizeState;
+use crate::openstack::Clouds;
+use super::cluster::ResourceContext;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResizeDirection {
+    Up,
+    Down,
+}
+
+impl ResizeDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResizeDirection::Up => "up",
+            ResizeDirection::Down => "down",
+        }
+    }
+}
+
+// The flavor a resize in flight is going to
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResizeTarget {
+    pub flavor_id: String,
+    pub flavor_name: String,
+}
+
+pub enum ResizeProgress {
+    Running,
+    Done,
+}
+
+// Scales a server vertically by moving it one step along the configured
+// flavor ladder
+pub struct Resizer {
+    config: ResizeConfig,
+    clouds: Clouds,
+}
+
+impl Resizer {
+    pub fn new(config: ResizeConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // The neighbouring rung; None at either end of the ladder or for a
+    // flavor that isn't on it
+    fn next_flavor(&self, current: &str, direction: ResizeDirection) -> Option<&str> {
+        let position = self.config.flavor_ladder.iter().position(|flavor| flavor == current)?;
+        let next = match direction {
+            ResizeDirection::Up => position + 1,
+            ResizeDirection::Down => position.checked_sub(1)?,
+        };
+        self.config.flavor_ladder.get(next).map(String::as_str)
+    }
+    
+    // Asks Nova to resize the server and returns the flavor it's going to,
+    // or None when there's no flavor to go to. progress() follows it up
+    pub async fn start(&self, context: &ResourceContext, direction: ResizeDirection) -> Result<Option<ResizeTarget>> {
+        let client = self.clouds.client_for(&context.resource_id);
+        let nova = &client.nova;
+        let flavors = nova.list_flavors().await?;
+        
+        // Depending on the microversion Nova identifies a server's flavor by
+        // id or by name, and the ladder may use either
+        let current = flavors.iter()
+            .find(|flavor| flavor.id == context.flavor_id || flavor.name == context.flavor_id)
+            .ok_or_else(|| SchedulerError::DecisionError(format!(
+                "{} has flavor {}, which Nova doesn't list",
+                context.resource_id, context.flavor_id
+            )))?;
+        let Some(next) = self.next_flavor(&current.name, direction)
+            .or_else(|| self.next_flavor(&current.id, direction))
+        else {
+            return Ok(None);
+        };
+        let target = flavors.iter()
+            .find(|flavor| flavor.name == next || flavor.id == next)
+            .ok_or_else(|| SchedulerError::DecisionError(format!("Flavor {} from the ladder doesn't exist", next)))?;
+        
+        nova.resize_server(&context.resource_id, &target.id).await.map_err(|e| SchedulerError::DecisionError(format!(
+            "Resize of {} to {} failed: {}",
+            context.resource_id, target.name, e
+        )))?;
+        info!(
+            "Resizing {} {} from {} to {}",
+            context.resource_id,
+            direction.as_str(),
+            current.name,
+            target.name
+        );
+        Ok(Some(ResizeTarget {
+            flavor_id: target.id.clone(),
+            flavor_name: target.name.clone(),
+        }))
+    }
+    
+    // Confirms the resize once Nova has it waiting, unless that's left to an
+    // operator. Fails when Nova gave up or it ran past timeout_seconds
+    pub async fn progress(&self, resource_id: &str, target: &ResizeTarget, started_at: DateTime<Utc>) -> Result<ResizeProgress> {
+        let client = self.clouds.client_for(resource_id);
+        let nova = &client.nova;
+        let state = match nova.resize_state(resource_id, &[&target.flavor_id, &target.flavor_name]).await {
+            Ok(state) => state,
+            // Looked at again next cycle
+            Err(e) => {
+                warn!("Failed to check on the resize of {}: {}", resource_id, e);
+                ResizeState::Running
+            }
+        };
+        match state {
+            ResizeState::Failed(reason) => Err(SchedulerError::DecisionError(reason).into()),
+            ResizeState::Completed => Ok(ResizeProgress::Done),
+            ResizeState::AwaitingConfirmation if self.config.auto_confirm => {
+                nova.confirm_resize(resource_id).await?;
+                info!("Resized {} to {}", resource_id, target.flavor_name);
+                Ok(ResizeProgress::Done)
+            }
+            ResizeState::AwaitingConfirmation => {
+                info!("Resized {} to {}, awaiting confirmation", resource_id, target.flavor_name);
+                Ok(ResizeProgress::Done)
+            }
+            ResizeState::Running if Utc::now() - started_at > ChronoDuration::seconds(self.config.timeout_seconds as i64) => {
+                Err(SchedulerError::DecisionError(format!(
+                    "Resize of {} to {} timed out after {}s",
+                    resource_id, target.flavor_name, self.config.timeout_seconds
+                )).into())
+            }
+            ResizeState::Running => Ok(ResizeProgress::Running),
+        }
+    }
+}
@@ -1,173 +1,1992 @@
 use anyhow::Result;
+use arc_swap::ArcSwap;
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use std::time::Duration;
-use tokio::time::interval;
-use tracing::{debug, error, info};
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+use tokio::time::{interval, interval_at, Interval};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, info_span, warn, Instrument, Span};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
 
-use crate::config::SchedulerConfig;
-use crate::openstack::Client;
+use crate::config::{BlackoutMode, KafkaConfig, SchedulerConfig, ScoringPreset, ScoringWeights};
+use crate::coordination::Coordinator;
+use crate::error::SchedulerError;
+use crate::openstack::{Client, Clouds};
+use crate::openstack::services::{Alarm, MigrationState, Server, ServerGroupRef, StackRef};
+use crate::metrics::sharding::ShardCoordinator;
+use crate::metrics::LatestMetrics;
 use crate::ml::MLEngine;
-use super::placement::PlacementEngine;
-use super::sla_manager::SLAManager;
+use crate::storage::Storage;
+use super::alarms::AlarmSync;
+use super::autoscaling::AutoScaler;
+use super::capacity::{CapacityPlanner, CapacityReport};
+use super::chaos::{FaultInjector, MigrationFault};
+use super::cluster::{ClusterSnapshot, InstancePlacement, ResourceContext};
+use super::consolidation::{ConsolidationPlanner, MigrationStep};
+use super::cooldown::ActionCooldown;
+use super::control::{ControlState, ExecutionMode, SchedulerControl, SchedulerStatus};
+use super::disruption::{DisruptionAllowance, DisruptionBudget, DisruptionBudgets};
+use super::edf::{DeadlineStats, DecisionQueue, InFlightAction};
+use super::energy::EnergyModel;
+use super::events::EventPublisher;
+use super::explain::{ActionRationale, DecisionExplanation, DecisionJournal, DecisionOutcome};
+use super::filters::PlacementOutcome;
+use super::leader::{LeaderElector, LeadershipStatus};
+use super::placement::{PlacementEngine, PlacementOptimizer};
+use super::policy::{
+    Aggressiveness, BlackoutStatus, EffectivePolicy, PolicyBlock, PolicyEngine, PolicyOverride, PolicyRule,
+};
+use super::power::PowerManager;
+use super::preemption::{PreemptionManager, PreemptionRecord};
+use super::prescaling::{PeakForecast, PreScaler};
+use super::rebalance::{RebalancePlanner, RebalanceReport};
+use super::resize::{ResizeDirection, ResizeProgress, Resizer};
+use super::risk::{RiskAssessor, RiskInputs, RiskVerdict};
+use super::scoring::ScoringStrategy;
+use super::shard_feed::ShardFeed;
+use super::simulation::{PlanSimulator, SimulationReport};
+use super::sla_manager::{ErrorBudgetStatus, SLAManager, SLAPolicy, SLAViolation, ViolationType};
+use super::sla_notifier::SLANotifier;
+use super::stats::{SchedulerPerformance, SchedulerStats};
+use super::storage_locality::{InstanceStorage, StorageTopology};
+use super::traffic::{TrafficHints, TrafficMatrix};
 
 pub struct ResourceScheduler {
-    config: SchedulerConfig,
+    // Swapped wholesale on reload; components built from it keep their copy
+    config: ArcSwap<SchedulerConfig>,
+    // The primary region's, for everything that isn't per region
     openstack_client: Arc<Client>,
+    clouds: Clouds,
     ml_engine: Arc<MLEngine>,
+    latest_metrics: Arc<LatestMetrics>,
     placement_engine: PlacementEngine,
-    sla_manager: SLAManager,
+    sla_manager: RwLock<SLAManager>,
+    sla_notifier: SLANotifier,
+    storage: Storage,
+    consolidation_planner: ConsolidationPlanner,
+    placement_optimizer: PlacementOptimizer,
+    plan_simulator: PlanSimulator,
+    last_simulation: RwLock<Option<SimulationReport>>,
+    rebalance_planner: RebalancePlanner,
+    last_rebalance: RwLock<Option<RebalanceReport>>,
+    policy_engine: PolicyEngine,
+    power_manager: PowerManager,
+    energy_model: EnergyModel,
+    capacity_planner: CapacityPlanner,
+    autoscaler: AutoScaler,
+    resizer: Resizer,
+    cooldown: ActionCooldown,
+    alarm_sync: AlarmSync,
+    prescaler: PreScaler,
+    preemption_manager: PreemptionManager,
+    decision_queue: DecisionQueue,
+    disruption_budgets: DisruptionBudgets,
+    storage_topology: Arc<StorageTopology>,
+    traffic_matrix: TrafficMatrix,
+    decision_journal: DecisionJournal,
+    leader_elector: Arc<LeaderElector>,
+    stats: SchedulerStats,
+    fault_injector: FaultInjector,
+    risk_assessor: RiskAssessor,
+    events: EventPublisher,
+    control: SchedulerControl,
+    // Set when collection is sharded; only the leader then runs cycles
+    shard_feed: Option<Arc<ShardFeed>>,
 }
 
-#[derive(Debug, Clone)]
+// Longest wait for Kafka to take queued events on shutdown
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Priority given to decisions for resources whose SLA is critical
+const CRITICAL_PRIORITY: u8 = 1;
+
+// The collector reads collection intervals from it too
+pub const SLA_POLICY_COLLECTION: &str = "sla_policies";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SchedulingDecision {
+    pub id: String,
     pub resource_id: String,
     pub action: SchedulingAction,
     pub target_host: Option<String>,
     pub priority: u8,
     pub sla_impact: f64,
+    // Absolute deadline derived from the resource's SLA
+    pub deadline: DateTime<Utc>,
+    pub rationale: ActionRationale,
+    pub created_at: DateTime<Utc>,
+    // Failed execution attempts so far
+    #[serde(default)]
+    pub attempts: u32,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum SchedulingAction {
     Migrate,
     Scale,
+    // Resize the autoscaling group the resource belongs to
+    ScaleOut { replicas: u32 },
+    ScaleIn { replicas: u32 },
     Consolidate,
     NoAction,
 }
 
+#[derive(Debug, Clone, Serialize)]
+pub struct DryRunReport {
+    pub decisions: Vec<SchedulingDecision>,
+    // Set when the cycle vetted a plan
+    pub simulation: Option<SimulationReport>,
+}
+
+// Action an operator asks for through the API
+#[derive(Debug, Clone, Deserialize)]
+pub struct ManualActionRequest {
+    pub action: ManualAction,
+    // Required for migrate and scale; consolidation plans the whole cluster
+    pub resource_id: Option<String>,
+    // Left to the placement engine when omitted
+    pub target_host: Option<String>,
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ManualAction {
+    Migrate,
+    Scale,
+    Consolidate,
+}
+
+impl ManualAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ManualAction::Migrate => "migrate",
+            ManualAction::Scale => "scale",
+            ManualAction::Consolidate => "consolidate",
+        }
+    }
+}
+
+impl SchedulingDecision {
+    pub fn new(
+        resource_id: String,
+        action: SchedulingAction,
+        target_host: Option<String>,
+        priority: u8,
+        sla_impact: f64,
+        deadline: DateTime<Utc>,
+        rationale: ActionRationale,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            resource_id,
+            action,
+            target_host,
+            priority,
+            sla_impact,
+            deadline,
+            rationale,
+            created_at: Utc::now(),
+            attempts: 0,
+        }
+    }
+}
+
+impl SchedulingAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SchedulingAction::Migrate => "migrate",
+            SchedulingAction::Scale => "scale",
+            SchedulingAction::ScaleOut { .. } => "scale_out",
+            SchedulingAction::ScaleIn { .. } => "scale_in",
+            SchedulingAction::Consolidate => "consolidate",
+            SchedulingAction::NoAction => "no_action",
+        }
+    }
+}
+
 impl ResourceScheduler {
     pub async fn new(
         config: &SchedulerConfig,
-        openstack_client: Arc<Client>,
+        clouds: Clouds,
         ml_engine: Arc<MLEngine>,
+        storage: Storage,
+        latest_metrics: Arc<LatestMetrics>,
+        shards: Arc<ShardCoordinator>,
+        coordinator: Coordinator,
+        kafka_config: &KafkaConfig,
     ) -> Result<Self> {
-        let placement_engine = PlacementEngine::new(openstack_client.clone());
-        let sla_manager = SLAManager::new();
+        let openstack_client = clouds.primary();
+        let scoring = ScoringStrategy::from_config(&config.scoring)?;
+        let placement_engine = PlacementEngine::new(clouds.clone(), scoring, &config.placement)?;
+        let mut sla_manager = SLAManager::new(config.error_budget.clone(), latest_metrics.clone());
+        let sla_policies: Vec<SLAPolicy> = storage.list(SLA_POLICY_COLLECTION).await?;
+        info!("Loaded {} SLA policies from storage", sla_policies.len());
+        for policy in sla_policies {
+            sla_manager.add_sla_policy(policy);
+        }
+        let consolidation_planner = ConsolidationPlanner::new(config.consolidation.clone());
+        let sla_notifier = SLANotifier::new(config.sla_webhooks.clone())?;
+        let placement_optimizer = PlacementOptimizer::new(config.optimizer.clone());
+        let plan_simulator = PlanSimulator::new(config.simulation.clone());
+        let policy_engine = PolicyEngine::from_file(config.policy_file.as_deref(), &config.blackouts)?;
+        policy_engine.validate_thresholds(config.high_load_threshold, config.low_load_threshold)?;
+        let power_manager = PowerManager::new(
+            config.power_management.clone(),
+            clouds.clone(),
+        );
+        let energy_model = EnergyModel::new(
+            config.energy.clone(),
+            &config.power_management,
+            clouds.clone(),
+        );
+        let capacity_planner = CapacityPlanner::load(config.capacity.clone(), storage.clone()).await?;
+        let autoscaler = AutoScaler::new(config.autoscaling.clone(), clouds.clone());
+        let resizer = Resizer::new(config.resize.clone(), clouds.clone());
+        let alarm_sync = AlarmSync::new(config.alarms.clone(), clouds.clone());
+        let preemption_manager = PreemptionManager::new(config.preemption.clone(), clouds.clone());
+        let decision_journal = DecisionJournal::load(storage.clone()).await?;
+        let decision_queue = DecisionQueue::load(storage.clone()).await?;
+        let disruption_budgets = DisruptionBudgets::load(storage.clone()).await?;
+        let traffic_matrix = TrafficMatrix::new(config.traffic_affinity.clone(), clouds.clone());
+        let leader_elector = Arc::new(LeaderElector::new(config.high_availability.clone(), coordinator));
+        let events = EventPublisher::new(kafka_config, leader_elector.status().instance_id).await?;
+        let control = SchedulerControl::load(storage.clone()).await?;
+        let shard_feed = match shards.is_enabled() {
+            true => Some(Arc::new(ShardFeed::new(
+                kafka_config,
+                &leader_elector.status().instance_id,
+                shards,
+                latest_metrics.clone(),
+                ml_engine.clone(),
+            )?)),
+            false => None,
+        };
         
         info!("Resource scheduler initialized");
         
         Ok(Self {
-            config: config.clone(),
+            config: ArcSwap::from_pointee(config.clone()),
             openstack_client,
+            clouds,
             ml_engine,
+            latest_metrics,
             placement_engine,
-            sla_manager,
+            sla_manager: RwLock::new(sla_manager),
+            sla_notifier,
+            storage,
+            consolidation_planner,
+            placement_optimizer,
+            plan_simulator,
+            last_simulation: RwLock::new(None),
+            rebalance_planner: RebalancePlanner::new(config.rebalance.clone()),
+            last_rebalance: RwLock::new(None),
+            policy_engine,
+            power_manager,
+            energy_model,
+            capacity_planner,
+            autoscaler,
+            resizer,
+            cooldown: ActionCooldown::new(config.cooldown.clone()),
+            alarm_sync,
+            prescaler: PreScaler::new(config.prescaling.clone()),
+            preemption_manager,
+            decision_queue,
+            disruption_budgets,
+            storage_topology: Arc::new(StorageTopology::from_config(&config.storage_locality)),
+            traffic_matrix,
+            decision_journal,
+            leader_elector,
+            stats: SchedulerStats::new(),
+            fault_injector: FaultInjector::new(config.chaos.clone()),
+            risk_assessor: RiskAssessor::new(config.risk.clone()),
+            events,
+            control,
+            shard_feed,
         })
     }
     
-    pub async fn start_scheduling_loop(&self) -> Result<()> {
+    // Runs until shutdown is cancelled. A cycle in progress stops taking
+    // decisions, leaving the rest queued; migrations already started are
+    // persisted and reconciled by whoever leads next
+    pub async fn start_scheduling_loop(&self, shutdown: CancellationToken) -> Result<()> {
         info!("Starting resource scheduling loop");
         
-        let mut interval = interval(Duration::from_secs(self.config.scheduling_interval_seconds));
+        // Keeps renewing the lease until the last cycle is done
+        let elector_shutdown = CancellationToken::new();
+        let elector_handle = tokio::spawn({
+            let elector = self.leader_elector.clone();
+            let shutdown = elector_shutdown.clone();
+            async move { elector.run(shutdown).await }
+        });
+        
+        let feed_handle = self.shard_feed.clone().map(|feed| tokio::spawn({
+            let elector = self.leader_elector.clone();
+            let shutdown = shutdown.clone();
+            async move { feed.run(elector, shutdown).await }
+        }));
+        
+        let config = self.config.load_full();
+        let mut optimizer_interval = interval(Duration::from_secs(config.optimizer.interval_seconds));
+        let mut rebalance_interval = interval(Duration::from_secs(config.rebalance.interval_seconds));
+        let mut alarm_interval = interval(Duration::from_secs(config.alarms.sync_interval_seconds));
+        let mut interval = interval(Duration::from_secs(config.scheduling_interval_seconds));
         
         loop {
-            interval.tick().await;
+            // Intervals changed by a reload take over from the next tick
+            let config = self.config.load_full();
+            retune(&mut interval, config.scheduling_interval_seconds);
+            retune(&mut optimizer_interval, config.optimizer.interval_seconds);
+            retune(&mut rebalance_interval, config.rebalance.interval_seconds);
+            retune(&mut alarm_interval, config.alarms.sync_interval_seconds);
             
-            if let Err(e) = self.run_scheduling_cycle().await {
-                error!("Scheduling cycle failed: {}", e);
+            tokio::select! {
+                _ = interval.tick() => {
+                    if self.is_sharded_standby() {
+                        continue;
+                    }
+                    let started = Instant::now();
+                    let result = self.run_scheduling_cycle(&shutdown).await;
+                    if let Err(e) = &result {
+                        error!("Scheduling cycle failed: {}", e);
+                    }
+                    let decisions = result.as_ref().copied().unwrap_or(0);
+                    self.stats.record_cycle(started.elapsed(), decisions, result.is_ok()).await;
+                }
+                _ = optimizer_interval.tick(), if config.optimizer.enabled => {
+                    if self.is_sharded_standby() {
+                        continue;
+                    }
+                    if let Err(e) = self.run_optimization_cycle(&shutdown).await {
+                        error!("Placement optimization cycle failed: {}", e);
+                    }
+                }
+                _ = rebalance_interval.tick(), if config.rebalance.enabled => {
+                    if self.is_sharded_standby() {
+                        continue;
+                    }
+                    if let Err(e) = self.run_rebalance_cycle(&shutdown).await {
+                        error!("Rebalance cycle failed: {}", e);
+                    }
+                }
+                // Every instance follows alarm states for its own dashboard
+                _ = alarm_interval.tick(), if self.alarm_sync.is_enabled() => {
+                    let policies = self.list_sla_policies().await;
+                    if let Err(e) = self.alarm_sync.sync(&policies, self.leader_elector.is_leader()).await {
+                        warn!("Aodh alarm sync failed: {}", e);
+                    }
+                }
+                _ = shutdown.cancelled() => break,
             }
         }
+        
+        if let Some(handle) = feed_handle {
+            let _ = handle.await;
+        }
+        elector_shutdown.cancel();
+        let _ = elector_handle.await;
+        self.decision_queue.checkpoint().await;
+        self.events.flush(FLUSH_TIMEOUT).await;
+        // Hand leadership over only once nothing more will be started
+        self.resign_leadership().await;
+        info!("Resource scheduling loop stopped");
+        Ok(())
+    }
+    
+    // With sharded collection a standby holds only its own shard's metrics,
+    // too little to plan with, so it leaves cycles to the leader
+    fn is_sharded_standby(&self) -> bool {
+        self.shard_feed.is_some() && !self.leader_elector.is_leader()
+    }
+    
+    pub fn leadership(&self) -> LeadershipStatus {
+        self.leader_elector.status()
     }
     
-    async fn run_scheduling_cycle(&self) -> Result<()> {
+    pub async fn resign_leadership(&self) {
+        self.leader_elector.resign().await;
+    }
+    
+    // Takes the settings a config reload may change; the reloader has
+    // already rejected changes to anything else
+    pub fn apply_config(&self, config: SchedulerConfig) -> Result<()> {
+        let scoring = ScoringStrategy::from_config(&config.scoring)?;
+        self.policy_engine.validate_thresholds(config.high_load_threshold, config.low_load_threshold)?;
+        self.placement_engine.set_scoring_strategy(scoring);
+        info!(
+            "Scheduler config reloaded: thresholds {:.1}/{:.1}, cycle every {}s",
+            config.high_load_threshold,
+            config.low_load_threshold,
+            config.scheduling_interval_seconds
+        );
+        self.config.store(Arc::new(config));
+        Ok(())
+    }
+    
+    pub fn scoring_strategy(&self) -> ScoringStrategy {
+        self.placement_engine.scoring_strategy().as_ref().clone()
+    }
+    
+    pub fn set_scoring_strategy(
+        &self,
+        preset: ScoringPreset,
+        weights: Option<ScoringWeights>,
+    ) -> Result<ScoringStrategy> {
+        let strategy = ScoringStrategy::new(preset, weights)?;
+        self.placement_engine.set_scoring_strategy(strategy.clone());
+        Ok(strategy)
+    }
+    
+    pub async fn pause(&self, operator: &str, reason: Option<String>) -> Result<ControlState> {
+        self.control.pause(operator, reason).await
+    }
+    
+    pub async fn resume(&self, operator: &str) -> Result<ControlState> {
+        self.control.resume(operator).await
+    }
+    
+    pub async fn set_execution_mode(&self, operator: &str, mode: ExecutionMode) -> Result<ControlState> {
+        self.control.set_mode(operator, mode).await
+    }
+    
+    pub async fn set_aggressiveness(&self, operator: &str, aggressiveness: Aggressiveness) -> Result<ControlState> {
+        self.control.set_aggressiveness(operator, aggressiveness).await
+    }
+    
+    pub async fn status(&self) -> SchedulerStatus {
+        SchedulerStatus {
+            control: self.control.state(),
+            is_leader: self.leader_elector.is_leader(),
+            queued: self.decision_queue.pending().await,
+            in_flight: self.decision_queue.in_flight().await,
+        }
+    }
+    
+    // Returns the number of decisions the cycle produced
+    async fn run_scheduling_cycle(&self, shutdown: &CancellationToken) -> Result<usize> {
         debug!("Running scheduling cycle");
+        self.control.refresh().await;
         
+        let (scheduling_decisions, snapshot) = self.plan_cycle(false).await?;
+        
+        // Execute scheduling decisions
+        let decision_count = scheduling_decisions.len();
+        self.execute_scheduling_decisions(scheduling_decisions, &snapshot, shutdown).await?;
+        
+        // Power hosts down or back up against the forecasted demand
+        if self.power_manager.is_enabled()
+            && !shutdown.is_cancelled()
+            && self.leader_elector.is_leader()
+            && !self.control.is_paused()
+            && self.control.mode() == ExecutionMode::Enforce
+        {
+            let powered_off = self.power_manager.run_power_cycle(&snapshot).await?;
+            self.record_energy_savings(&snapshot, &powered_off).await;
+        }
+        
+        Ok(decision_count)
+    }
+    
+    // Savings are only credited once hosts are actually off, at what each
+    // would have drawn idle over the horizon
+    async fn record_energy_savings(&self, snapshot: &ClusterSnapshot, powered_off: &[String]) {
+        if powered_off.is_empty() || !self.energy_model.is_enabled() {
+            return;
+        }
+        let models = self.energy_model.models(snapshot);
+        let watts: f64 = powered_off.iter()
+            .map(|host_id| (models.model(host_id).idle_watts - models.freed_host_watts(host_id)).max(0.0))
+            .sum();
+        let kwh = watts * self.config.load().energy.savings_horizon_hours / 1000.0;
+        info!("Powered off {:?}, about {:.1} kWh saved", powered_off, kwh);
+        self.stats.record_energy_savings(kwh).await;
+    }
+    
+    // What a scheduling cycle would hand to execution right now; nothing is
+    // executed and no samples or violations are recorded
+    pub async fn dry_run(&self) -> Result<DryRunReport> {
+        let (decisions, _) = self.plan_cycle(true).await?;
+        Ok(DryRunReport {
+            decisions,
+            simulation: self.last_simulation().await,
+        })
+    }
+    
+    // Decisions for the current cluster state. A dry run leaves out the
+    // bookkeeping a real cycle does along the way
+    async fn plan_cycle(&self, dry_run: bool) -> Result<(Vec<SchedulingDecision>, ClusterSnapshot)> {
         // Get current resource state
-        let servers = self.openstack_client.nova.list_servers().await?;
+        let servers = self.list_servers().await?;
+        let predictions = self.collect_predictions(&servers).await;
+        if self.traffic_matrix.is_enabled() {
+            if let Err(e) = self.traffic_matrix.refresh().await {
+                warn!("Failed to refresh traffic matrix: {}", e);
+            }
+        }
+        let snapshot = self.build_cluster_snapshot(&servers, &predictions).await?;
+        if self.energy_model.is_enabled() && !dry_run {
+            self.energy_model.sample(&snapshot).await;
+        }
+        if self.capacity_planner.is_enabled() && !dry_run {
+            self.capacity_planner.sample(&snapshot).await;
+        }
+        let peaks = self.forecast_peaks(&servers).await;
         
         let mut scheduling_decisions = Vec::new();
         
-        for server in servers {
-            // Get ML prediction for this resource
-            let predicted_load = self.ml_engine
-                .get_resource_prediction(&server.id)
-                .await
-                .unwrap_or(0.0);
+        for server in &servers {
+            let context = snapshot.resource_context(&server.id);
+            
+            // Autoscaling group members are handled per group below
+            if self.autoscaler.is_enabled() && self.autoscaler.group_of(&context).is_some() {
+                continue;
+            }
+            
+            let predicted_load = predictions.get(&server.id).copied().unwrap_or(0.0);
+            let policy = self.apply_error_budget(self.effective_policy(&context), &server.id).await;
             
             // Check SLA requirements
-            let sla_status = self.sla_manager.check_sla_compliance(&server.id).await;
+            let sla_status = self.sla_manager.read().await.check_sla_compliance(&server.id).await;
+            if !dry_run {
+                self.track_sla_violations(&server.id, &sla_status).await;
+            }
             
             // Make scheduling decision based on hybrid algorithm
+            let span = info_span!(
+                "scheduler.decision",
+                resource_id = %server.id,
+                decision_id = tracing::field::Empty,
+                action = tracing::field::Empty,
+            );
+            self.join_sample_trace(&span, &server.id);
             let decision = self.make_scheduling_decision(
                 &server.id,
                 predicted_load,
                 &sla_status,
-            ).await?;
+                &policy,
+            ).instrument(span.clone()).await?;
+            span.record("decision_id", decision.id.as_str());
+            span.record("action", decision.action.as_str());
             
             if !matches!(decision.action, SchedulingAction::NoAction) {
                 scheduling_decisions.push(decision);
             }
         }
         
-        // Execute scheduling decisions
-        self.execute_scheduling_decisions(scheduling_decisions).await?;
+        // Policies can also cover load balancers, which are only tracked as
+        // there's nothing to schedule for them
+        if !dry_run {
+            let server_ids: HashSet<&str> = servers.iter().map(|server| server.id.as_str()).collect();
+            let others: Vec<String> = self.sla_manager.read().await.list_sla_policies().into_iter()
+                .map(|policy| policy.resource_id)
+                .filter(|resource_id| !server_ids.contains(resource_id.as_str()))
+                .collect();
+            for resource_id in others {
+                let sla_status = self.sla_manager.read().await.check_sla_compliance(&resource_id).await;
+                self.track_sla_violations(&resource_id, &sla_status).await;
+            }
+        }
+        
+        if self.autoscaler.is_enabled() {
+            for proposal in self.autoscaler.plan(&snapshot, &peaks).await? {
+                let sla_status = self.sla_manager.read().await.check_sla_compliance(&proposal.representative).await;
+                info!(
+                    "Autoscaling group {} forecast needs {} replicas (currently {})",
+                    proposal.group.group_id,
+                    proposal.desired_capacity,
+                    proposal.current_capacity
+                );
+                let (action, priority) = if proposal.is_scale_out() {
+                    (SchedulingAction::ScaleOut { replicas: proposal.desired_capacity }, 3)
+                } else {
+                    (SchedulingAction::ScaleIn { replicas: proposal.desired_capacity }, 7)
+                };
+                let peak = proposal.ahead_of_peak
+                    .map(|at| format!(" ahead of the peak at {}", at))
+                    .unwrap_or_default();
+                let rationale = ActionRationale {
+                    summary: format!(
+                        "{:?} group {} forecast needs {} replicas{}, currently {}",
+                        proposal.group.backend,
+                        proposal.group.group_id,
+                        proposal.desired_capacity,
+                        peak,
+                        proposal.current_capacity
+                    ),
+                    sla_critical: sla_status.is_critical,
+                    sla_violations: sla_status.violations.iter().map(|v| format!("{:?}", v)).collect(),
+                    ..Default::default()
+                };
+                scheduling_decisions.push(SchedulingDecision::new(
+                    proposal.representative,
+                    action,
+                    None,
+                    priority,
+                    sla_status.impact_score,
+                    Self::deadline_for(&sla_status),
+                    rationale,
+                ));
+            }
+        }
+        
+        // Move instances off hosts before a forecast peak lands on them
+        if self.prescaler.is_enabled() {
+            let mut skipped: HashSet<String> = scheduling_decisions.iter().map(|d| d.resource_id.clone()).collect();
+            if self.autoscaler.is_enabled() {
+                skipped.extend(snapshot.resources.iter()
+                    .filter(|(_, context)| self.autoscaler.group_of(context).is_some())
+                    .map(|(resource_id, _)| resource_id.clone()));
+            }
+            
+            for premigration in self.prescaler.premigrations(
+                &snapshot,
+                &peaks,
+                self.config.load().high_load_threshold,
+                &skipped,
+            ) {
+                let context = snapshot.resource_context(&premigration.resource_id);
+                if !self.effective_policy(&context).allows(&SchedulingAction::Migrate) {
+                    continue;
+                }
+                
+                let rationale = ActionRationale {
+                    summary: format!(
+                        "Host {} forecast to reach {:.1}% at {} (confidence {:.2}); moving ahead of the peak",
+                        premigration.source_host,
+                        premigration.host_peak_utilization,
+                        premigration.peak.at,
+                        premigration.peak.confidence
+                    ),
+                    predicted_load: Some(premigration.peak.load),
+                    high_load_threshold: Some(self.config.load().high_load_threshold),
+                    ..Default::default()
+                };
+                scheduling_decisions.push(SchedulingDecision::new(
+                    premigration.resource_id,
+                    SchedulingAction::Migrate,
+                    None,
+                    5,
+                    0.0,
+                    // The move has to be done before the peak arrives
+                    premigration.peak.at,
+                    rationale,
+                ));
+            }
+        }
+        
+        // Low-load resources signal a consolidation opportunity; replace them
+        // with the migrations of a cluster-wide packing plan
+        if scheduling_decisions.iter().any(|d| matches!(d.action, SchedulingAction::Consolidate)) {
+            let excluded_hosts = self.power_manager.powered_off_hosts().await;
+            let power_models = self.energy_model.is_enabled().then(|| self.energy_model.models(&snapshot));
+            let allowance = self.disruption_allowance(&snapshot).await;
+            let plan = self.consolidation_planner.plan(
+                &snapshot,
+                &excluded_hosts,
+                power_models.as_ref(),
+                &allowance,
+            );
+            if !plan.is_empty() {
+                info!(
+                    "Consolidation plan: {} migrations freeing hosts {:?} ({} held back by disruption budgets)",
+                    plan.steps.len(),
+                    plan.hosts_freed,
+                    plan.deferred_by_budget
+                );
+            }
+            scheduling_decisions.retain(|d| !matches!(d.action, SchedulingAction::Consolidate));
+            if !plan.is_empty() && self.vet_plan("consolidation", &snapshot, &plan.steps).await {
+                if let Some(energy) = &plan.energy {
+                    let kwh = energy.kwh_saved(self.config.load().energy.savings_horizon_hours);
+                    info!(
+                        "Consolidation plan cuts predicted draw {:.0} W -> {:.0} W, about {:.1} kWh over {} h",
+                        energy.watts_before,
+                        energy.watts_after,
+                        kwh,
+                        self.config.load().energy.savings_horizon_hours
+                    );
+                }
+                scheduling_decisions.extend(self.decisions_from_steps("consolidation", &plan.steps).await);
+            }
+        }
+        
+        Ok((scheduling_decisions, snapshot))
+    }
+    
+    // Servers in every region; one region failing fails the cycle rather
+    // than leave planners with part of the picture
+    async fn list_servers(&self) -> Result<Vec<Server>> {
+        let mut servers = Vec::new();
+        for client in self.clouds.regions() {
+            servers.extend(client.nova.list_servers().await?);
+        }
+        self.clouds.locate(&servers);
+        Ok(servers)
+    }
+    
+    // Periodic global rebalancing, slower than the reactive cycle
+    async fn run_optimization_cycle(&self, shutdown: &CancellationToken) -> Result<()> {
+        self.control.refresh().await;
+        if self.control.is_paused() {
+            return Ok(());
+        }
+        debug!("Running placement optimization cycle");
+        
+        let servers = self.list_servers().await?;
+        let predictions = self.collect_predictions(&servers).await;
+        let snapshot = self.build_cluster_snapshot(&servers, &predictions).await?;
+        let excluded_hosts = self.power_manager.powered_off_hosts().await;
+        
+        let result = self.placement_optimizer.optimize(&snapshot, &excluded_hosts);
+        if result.steps.is_empty() {
+            debug!("Placement optimizer found no worthwhile improvement");
+            return Ok(());
+        }
+        
+        info!(
+            "Placement optimizer: objective {:.4} -> {:.4} with {} migrations",
+            result.initial_cost,
+            result.final_cost,
+            result.steps.len()
+        );
         
+        if !self.vet_plan("optimizer", &snapshot, &result.steps).await {
+            return Ok(());
+        }
+        
+        let decisions = self.decisions_from_steps("optimizer", &result.steps).await;
+        self.execute_scheduling_decisions(decisions, &snapshot, shutdown).await?;
+        Ok(())
+    }
+    
+    // Batch rebalancing toward a target imbalance, bounded by the migration
+    // budget of the current window
+    async fn run_rebalance_cycle(&self, shutdown: &CancellationToken) -> Result<()> {
+        // Only the leader executes, so only it spends the budget
+        self.control.refresh().await;
+        if !self.leader_elector.is_leader() || self.control.is_paused() {
+            return Ok(());
+        }
+        
+        let now = Utc::now();
+        let budget = self.rebalance_planner.budget_remaining(now);
+        if budget == 0 {
+            debug!("Rebalance migration budget exhausted for this window");
+            return Ok(());
+        }
+        
+        let servers = self.list_servers().await?;
+        let predictions = self.collect_predictions(&servers).await;
+        let snapshot = self.build_cluster_snapshot(&servers, &predictions).await?;
+        let excluded_hosts = self.power_manager.powered_off_hosts().await;
+        let allowance = self.disruption_allowance(&snapshot).await;
+        
+        let plan = self.rebalance_planner.plan(&snapshot, &excluded_hosts, &allowance, budget);
+        *self.last_rebalance.write().await = Some(RebalanceReport {
+            cv_before: plan.cv_before,
+            cv_after: plan.cv_after,
+            target_cv: self.rebalance_planner.target_cv(),
+            migrations: plan.steps.len(),
+            budget_remaining: budget - plan.steps.len(),
+            planned_at: now,
+        });
+        ::metrics::gauge!("scheduler_host_load_cv").set(plan.cv_before);
+        if plan.is_empty() {
+            debug!("Host load imbalance {:.3} needs no rebalancing", plan.cv_before);
+            return Ok(());
+        }
+        
+        info!(
+            "Rebalance plan: imbalance {:.3} -> {:.3} with {} migrations ({} left in budget)",
+            plan.cv_before,
+            plan.cv_after,
+            plan.steps.len(),
+            budget - plan.steps.len()
+        );
+        if !self.vet_plan("rebalance", &snapshot, &plan.steps).await {
+            return Ok(());
+        }
+        
+        // Only what actually ran counts against the budget, not what
+        // recommend mode or the guardrails held back
+        let decisions = self.decisions_from_steps("rebalance", &plan.steps).await;
+        let started = self.execute_scheduling_decisions(decisions, &snapshot, shutdown).await?;
+        self.rebalance_planner.record_spent(started, now);
         Ok(())
     }
     
+    pub async fn last_rebalance(&self) -> Option<RebalanceReport> {
+        self.last_rebalance.read().await.clone()
+    }
+    
+    // Confident peaks within the pre-scaling lead time; empty when disabled
+    async fn forecast_peaks(&self, servers: &[Server]) -> HashMap<String, PeakForecast> {
+        let mut peaks = HashMap::new();
+        if !self.prescaler.is_enabled() {
+            return peaks;
+        }
+        
+        let now = Utc::now();
+        for server in servers {
+            match self.ml_engine.get_resource_forecast(&server.id).await {
+                Ok(Some(forecast)) => {
+                    if let Some(peak) = self.prescaler.peak(&forecast, now) {
+                        peaks.insert(server.id.clone(), peak);
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => warn!("Forecast for {} failed: {}", server.id, e),
+            }
+        }
+        
+        peaks
+    }
+    
+    async fn collect_predictions(&self, servers: &[Server]) -> HashMap<String, f64> {
+        let mut predictions = HashMap::new();
+        
+        for server in servers {
+            // Get ML prediction for this resource
+            let span = info_span!("ml.inference", resource_id = %server.id);
+            self.join_sample_trace(&span, &server.id);
+            let predicted_load = self.ml_engine
+                .get_resource_prediction(&server.id)
+                .instrument(span)
+                .await
+                .unwrap_or(0.0);
+            let predicted_load = self.fault_injector.prediction(&server.id, predicted_load);
+            predictions.insert(server.id.clone(), predicted_load);
+        }
+        
+        predictions
+    }
+    
+    // Parents the span on the trace of the resource's latest sample, so its
+    // collection, publish, inference and decision show up as one trace
+    fn join_sample_trace(&self, span: &Span, resource_id: &str) {
+        if let Some(context) = self.latest_metrics.trace_context(resource_id) {
+            span.set_parent(context);
+        }
+    }
+    
+    async fn build_cluster_snapshot(
+        &self,
+        servers: &[Server],
+        predictions: &HashMap<String, f64>,
+    ) -> Result<ClusterSnapshot> {
+        let mut instances = Vec::new();
+        let mut resources = HashMap::new();
+        let stacks = self.stack_membership().await;
+        let server_groups = self.server_group_membership().await;
+        
+        for server in servers {
+            let mut context = ResourceContext::from_server(server);
+            context.stack = stacks.get(&server.id).cloned();
+            context.server_group = server_groups.get(&server.id).cloned();
+            let policy = self.effective_policy(&context);
+            
+            if let Some(host_id) = &server.host {
+                let requirements = self.placement_engine
+                    .get_resource_requirements(&context)
+                    .await?;
+                let volumes = self.clouds.client_for(&server.id).cinder.list_server_volumes(&server.id).await?;
+                instances.push(InstancePlacement {
+                    resource_id: server.id.clone(),
+                    host_id: host_id.clone(),
+                    vcpus: requirements.vcpus,
+                    memory_mb: requirements.memory_mb,
+                    predicted_load: predictions.get(&server.id).copied().unwrap_or(0.0),
+                    pinned: policy.is_pinned(),
+                    cross_az: policy.allow_cross_az,
+                    penalty: self.sla_manager.read().await
+                        .get_sla_policy(&server.id)
+                        .and_then(|policy| policy.penalty.clone()),
+                    storage: InstanceStorage::new(requirements.disk_gb as u64, &volumes),
+                });
+            }
+            resources.insert(server.id.clone(), context);
+        }
+        
+        // Bare-metal nodes are only placed on directly; nothing migrates
+        // onto or off them
+        let mut hosts = self.placement_engine.get_available_hosts().await?;
+        hosts.retain(|host| !host.is_bare_metal());
+        let traffic = if self.traffic_matrix.is_enabled() {
+            self.traffic_matrix.hints()
+        } else {
+            TrafficHints::default()
+        };
+        Ok(ClusterSnapshot::new(
+            hosts,
+            instances,
+            resources,
+            self.storage_topology.clone(),
+            Arc::new(traffic),
+        ))
+    }
+    
     async fn make_scheduling_decision(
         &self,
         resource_id: &str,
         predicted_load: f64,
         sla_status: &SLAStatus,
+        policy: &EffectivePolicy,
     ) -> Result<SchedulingDecision> {
         // Hybrid algorithm combining load-based triggers and ML predictions
         
-        let action = if predicted_load > self.config.high_load_threshold {
-            // High predicted load - consider migration or scaling
+        let conservative = policy.aggressiveness == Aggressiveness::Conservative;
+        let mut expected_penalty = None;
+        let (mut action, mut summary) = if predicted_load > policy.high_load_threshold {
+            // High predicted load - consider migration or scaling. A migration
+            // is also worth it when the penalty it avoids exceeds its disruption
+            let penalty = self.sla_manager.read().await.expected_penalty(
+                resource_id,
+                predicted_load,
+                self.config.load().simulation.penalty_horizon_minutes,
+            );
+            expected_penalty = Some(penalty);
+            let disruption = self.config.load().simulation.migration_disruption_cost;
             if sla_status.is_critical {
-                SchedulingAction::Migrate
+                (SchedulingAction::Migrate, "High predicted load on an SLA-critical resource".to_string())
+            } else if conservative {
+                (SchedulingAction::NoAction, "High predicted load, but policy is conservative".to_string())
+            } else if penalty > disruption {
+                debug!(
+                    "Migrating {} justified by expected penalty {:.2} (disruption {:.2})",
+                    resource_id,
+                    penalty,
+                    disruption
+                );
+                (
+                    SchedulingAction::Migrate,
+                    format!("Expected penalty {:.2} exceeds migration disruption {:.2}", penalty, disruption),
+                )
             } else {
-                SchedulingAction::Scale
+                (
+                    SchedulingAction::Scale,
+                    format!("Expected penalty {:.2} doesn't justify migration disruption {:.2}", penalty, disruption),
+                )
             }
-        } else if predicted_load < self.config.low_load_threshold {
+        } else if predicted_load < policy.low_load_threshold && !conservative {
             // Low predicted load - consider consolidation
-            SchedulingAction::Consolidate
+            (SchedulingAction::Consolidate, "Low predicted load".to_string())
         } else {
-            SchedulingAction::NoAction
+            (SchedulingAction::NoAction, "Predicted load within thresholds".to_string())
+        };
+        
+        if !matches!(action, SchedulingAction::NoAction) && !policy.allows(&action) {
+            debug!(
+                "{} of {} not allowed by overrides {:?}",
+                action.as_str(),
+                resource_id,
+                policy.applied_overrides
+            );
+            summary = format!("{} not allowed by policy overrides", action.as_str());
+            action = SchedulingAction::NoAction;
+        }
+        
+        let priority = match (sla_status.is_critical, policy.aggressiveness) {
+            (true, _) => CRITICAL_PRIORITY,
+            (false, Aggressiveness::Aggressive) => 3,
+            (false, _) => 5,
         };
         
-        let priority = if sla_status.is_critical { 1 } else { 5 };
+        let rationale = ActionRationale {
+            summary,
+            predicted_load: Some(predicted_load),
+            high_load_threshold: Some(policy.high_load_threshold),
+            low_load_threshold: Some(policy.low_load_threshold),
+            sla_critical: sla_status.is_critical,
+            sla_violations: sla_status.violations.iter().map(|v| format!("{:?}", v)).collect(),
+            expected_penalty,
+            applied_overrides: policy.applied_overrides.clone(),
+            requested_by: None,
+            risk: None,
+        };
         
-        Ok(SchedulingDecision {
-            resource_id: resource_id.to_string(),
+        Ok(SchedulingDecision::new(
+            resource_id.to_string(),
             action,
-            target_host: None, // Would be determined by placement engine
+            None, // Determined by the placement engine at execution
             priority,
-            sla_impact: sla_status.impact_score,
-        })
+            sla_status.impact_score,
+            Self::deadline_for(sla_status),
+            rationale,
+        ))
+    }
+    
+    // Simulate a plan against forecasted loads; false means it must be discarded
+    async fn vet_plan(&self, plan_source: &str, snapshot: &ClusterSnapshot, steps: &[MigrationStep]) -> bool {
+        if !self.config.load().simulation.enabled {
+            return true;
+        }
+        
+        let report = self.plan_simulator.simulate(plan_source, snapshot, steps);
+        let accepted = report.accepted;
+        *self.last_simulation.write().await = Some(report);
+        accepted
+    }
+    
+    pub async fn last_simulation(&self) -> Option<SimulationReport> {
+        self.last_simulation.read().await.clone()
+    }
+    
+    pub fn policy_rules(&self) -> Vec<PolicyRule> {
+        self.policy_engine.rules().to_vec()
+    }
+    
+    pub fn blackouts(&self) -> Vec<BlackoutStatus> {
+        self.policy_engine.blackouts(Utc::now())
+    }
+    
+    pub async fn policy_blocks(&self) -> Vec<PolicyBlock> {
+        self.policy_engine.recent_blocks().await
+    }
+    
+    pub async fn list_disruption_budgets(&self) -> Vec<DisruptionBudget> {
+        self.disruption_budgets.list().await
     }
     
+    pub async fn get_disruption_budget(&self, name: &str) -> Option<DisruptionBudget> {
+        self.disruption_budgets.get(name).await
+    }
+    
+    pub async fn put_disruption_budget(&self, budget: DisruptionBudget) -> Result<DisruptionBudget> {
+        self.disruption_budgets.put(budget).await
+    }
+    
+    pub async fn delete_disruption_budget(&self, name: &str) -> Result<bool> {
+        self.disruption_budgets.delete(name).await
+    }
+    
+    // Migrations still running count against their budgets
+    async fn disruption_allowance(&self, snapshot: &ClusterSnapshot) -> DisruptionAllowance {
+        let in_flight = self.decision_queue.in_flight().await;
+        self.disruption_budgets
+            .allowance(snapshot, in_flight.iter().map(|a| a.decision.resource_id.as_str()))
+            .await
+    }
+    
+    pub async fn list_sla_policies(&self) -> Vec<SLAPolicy> {
+        self.sla_manager.read().await.list_sla_policies()
+    }
+    
+    // Alarms Aodh currently has in the alarm state
+    pub async fn firing_alarms(&self) -> Vec<Alarm> {
+        self.alarm_sync.firing().await
+    }
+    
+    pub fn is_sla_alarm(&self, alarm: &Alarm) -> bool {
+        self.alarm_sync.mirrors_policy(alarm)
+    }
+    
+    pub async fn get_sla_policy(&self, resource_id: &str) -> Option<SLAPolicy> {
+        self.sla_manager.read().await.get_sla_policy(resource_id).cloned()
+    }
+    
+    // Validates and persists the policy before it takes effect
+    pub async fn put_sla_policy(&self, policy: SLAPolicy) -> Result<SLAPolicy> {
+        policy.validate()?;
+        
+        let mut sla_manager = self.sla_manager.write().await;
+        self.storage.put(SLA_POLICY_COLLECTION, &policy.resource_id, &policy).await?;
+        sla_manager.add_sla_policy(policy.clone());
+        
+        info!("Stored SLA policy for {}", policy.resource_id);
+        Ok(policy)
+    }
+    
+    pub async fn delete_sla_policy(&self, resource_id: &str) -> Result<bool> {
+        let mut sla_manager = self.sla_manager.write().await;
+        let stored = self.storage.delete(SLA_POLICY_COLLECTION, resource_id).await?;
+        let removed = sla_manager.remove_sla_policy(resource_id).is_some();
+        Ok(stored || removed)
+    }
+    
+    pub async fn preemption_audit_log(&self) -> Vec<PreemptionRecord> {
+        self.preemption_manager.audit_log().await
+    }
+    
+    pub fn policy_overrides(&self) -> Vec<PolicyOverride> {
+        self.policy_engine.overrides().to_vec()
+    }
+    
+    pub async fn error_budgets(&self) -> Vec<ErrorBudgetStatus> {
+        self.sla_manager.read().await.error_budgets(Utc::now())
+    }
+    
+    pub async fn sla_violations(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<SLAViolation> {
+        self.sla_manager.read().await.violations_between(from, to)
+    }
+    
+    // Act earlier for resources burning their error budget fast and later for
+    // those with plenty left
+    async fn apply_error_budget(&self, mut policy: EffectivePolicy, resource_id: &str) -> EffectivePolicy {
+        let budget = match self.sla_manager.read().await.error_budget(resource_id, Utc::now()) {
+            Some(budget) => budget,
+            None => return policy,
+        };
+        let adjustment = self.config.load().error_budget.threshold_adjustment;
+        
+        if budget.burning_fast || budget.budget_remaining <= 0.0 {
+            policy.high_load_threshold = (policy.high_load_threshold - adjustment).max(policy.low_load_threshold);
+            if policy.aggressiveness == Aggressiveness::Normal {
+                policy.aggressiveness = Aggressiveness::Aggressive;
+            }
+        } else if budget.budget_remaining >= self.config.load().error_budget.ample_budget_fraction {
+            policy.high_load_threshold = (policy.high_load_threshold + adjustment).min(100.0);
+        }
+        
+        policy
+    }
+    
+    fn effective_policy(&self, context: &ResourceContext) -> EffectivePolicy {
+        // Both thresholds from the same config, even across a reload
+        let config = self.config.load();
+        self.policy_engine.effective_policy(
+            context,
+            config.high_load_threshold,
+            config.low_load_threshold,
+            self.control.aggressiveness(),
+        )
+    }
+    
+    async fn decisions_from_steps(&self, plan_source: &str, steps: &[MigrationStep]) -> Vec<SchedulingDecision> {
+        let mut decisions = Vec::new();
+        
+        for step in steps {
+            let sla_status = self.sla_manager.read().await.check_sla_compliance(&step.resource_id).await;
+            let rationale = ActionRationale {
+                summary: format!(
+                    "Step of the simulated {} plan moving {} from {} to {}",
+                    plan_source, step.resource_id, step.source_host, step.target_host
+                ),
+                sla_critical: sla_status.is_critical,
+                sla_violations: sla_status.violations.iter().map(|v| format!("{:?}", v)).collect(),
+                ..Default::default()
+            };
+            decisions.push(SchedulingDecision::new(
+                step.resource_id.clone(),
+                SchedulingAction::Migrate,
+                Some(step.target_host.clone()),
+                9,
+                0.0,
+                Self::deadline_for(&sla_status),
+                rationale,
+            ));
+        }
+        
+        decisions
+    }
+    
+    // Record violations and SLO samples, notifying webhooks once per newly
+    // opened violation or fast budget burn
+    async fn track_sla_violations(&self, resource_id: &str, sla_status: &SLAStatus) {
+        let mut sla_manager = self.sla_manager.write().await;
+        sla_manager.resolve_violations(resource_id, &sla_status.violations);
+        
+        if let Some(slo_met) = sla_status.slo_met {
+            if let Some(burn) = sla_manager.record_slo_sample(resource_id, slo_met, Utc::now()) {
+                if self.leader_elector.is_leader() {
+                    self.sla_notifier.notify_budget_burn(&burn, sla_manager.get_sla_policy(resource_id));
+                }
+            }
+        }
+        
+        for violation_type in &sla_status.violations {
+            let violation = SLAViolation {
+                resource_id: resource_id.to_string(),
+                violation_type: violation_type.clone(),
+                severity: sla_status.impact_score,
+                timestamp: Utc::now(),
+                resolved: false,
+            };
+            
+            if sla_manager.record_violation(violation.clone()) && self.leader_elector.is_leader() {
+                self.sla_notifier.notify(&violation, sla_manager.get_sla_policy(resource_id));
+                self.events.sla_violation(&violation);
+            }
+        }
+    }
+    
+    fn deadline_for(sla_status: &SLAStatus) -> DateTime<Utc> {
+        Utc::now() + ChronoDuration::minutes(sla_status.deadline_minutes as i64)
+    }
+    
+    pub fn capacity_report(&self) -> CapacityReport {
+        self.capacity_planner.report()
+    }
+    
+    pub async fn deadline_stats(&self) -> DeadlineStats {
+        self.decision_queue.stats().await
+    }
+    
+    pub async fn performance(&self) -> SchedulerPerformance {
+        let deadlines = self.decision_queue.stats().await;
+        self.stats.snapshot(deadlines.queued, deadlines.missed).await
+    }
+    
+    async fn explain(
+        &self,
+        decision: &SchedulingDecision,
+        placement: Option<PlacementOutcome>,
+        outcome: DecisionOutcome,
+    ) {
+        if outcome.is_terminal() {
+            self.decision_queue.settle(&decision.id).await;
+        }
+        self.decision_journal.record(&DecisionExplanation {
+            decision_id: decision.id.clone(),
+            resource_id: decision.resource_id.clone(),
+            action: decision.action.as_str().to_string(),
+            rationale: decision.rationale.clone(),
+            placement,
+            outcome,
+            created_at: decision.created_at,
+            updated_at: Utc::now(),
+        }).await;
+    }
+    
+    // Validates and simulates an operator's request, then queues the resulting
+    // decisions so policy, blackouts, budgets and throttling apply on the
+    // next cycle exactly as they do for automatic ones
+    pub async fn request_manual_action(
+        &self,
+        request: ManualActionRequest,
+        operator: &str,
+    ) -> Result<Vec<SchedulingDecision>> {
+        if !self.leader_elector.is_leader() {
+            return Err(SchedulerError::DecisionError(
+                "This instance is a standby; send manual actions to the leader".to_string(),
+            ).into());
+        }
+        
+        let servers = self.list_servers().await?;
+        let predictions = self.collect_predictions(&servers).await;
+        let snapshot = self.build_cluster_snapshot(&servers, &predictions).await?;
+        let reason = request.reason.as_deref().unwrap_or("no reason given");
+        info!(
+            "Operator {} requested {} of {} ({})",
+            operator,
+            request.action.as_str(),
+            request.resource_id.as_deref().unwrap_or("the cluster"),
+            reason
+        );
+        
+        let mut decisions = match request.action {
+            ManualAction::Consolidate => {
+                let excluded_hosts = self.power_manager.powered_off_hosts().await;
+                let power_models = self.energy_model.is_enabled().then(|| self.energy_model.models(&snapshot));
+                let allowance = self.disruption_allowance(&snapshot).await;
+                let plan = self.consolidation_planner.plan(
+                    &snapshot,
+                    &excluded_hosts,
+                    power_models.as_ref(),
+                    &allowance,
+                );
+                if plan.is_empty() {
+                    return Err(SchedulerError::DecisionError(
+                        "Consolidation found nothing worth moving".to_string(),
+                    ).into());
+                }
+                if !self.vet_plan("manual consolidation", &snapshot, &plan.steps).await {
+                    return Err(SchedulerError::DecisionError(
+                        "Consolidation plan rejected by simulation; see /api/v1/scheduler/simulation".to_string(),
+                    ).into());
+                }
+                self.decisions_from_steps("manual consolidation", &plan.steps).await
+            }
+            ManualAction::Migrate | ManualAction::Scale => {
+                let resource_id = request.resource_id.as_deref().ok_or_else(|| {
+                    SchedulerError::DecisionError(format!("{} needs a resource_id", request.action.as_str()))
+                })?;
+                vec![self.manual_decision(&snapshot, request.action, resource_id, request.target_host.as_deref()).await?]
+            }
+        };
+        
+        for decision in &mut decisions {
+            decision.rationale.summary = format!("Requested by {}: {}. {}", operator, reason, decision.rationale.summary);
+            decision.rationale.requested_by = Some(operator.to_string());
+            self.explain(decision, None, DecisionOutcome::Deferred {
+                reason: "Queued for the next scheduling cycle".to_string(),
+            }).await;
+            self.events.decision_made(decision);
+        }
+        ::metrics::counter!("scheduler_manual_actions_total", "action" => request.action.as_str()).increment(1);
+        self.decision_queue.enqueue(decisions.clone()).await;
+        
+        Ok(decisions)
+    }
+    
+    async fn manual_decision(
+        &self,
+        snapshot: &ClusterSnapshot,
+        action: ManualAction,
+        resource_id: &str,
+        target_host: Option<&str>,
+    ) -> Result<SchedulingDecision> {
+        let context = snapshot.resources.get(resource_id).cloned().ok_or_else(|| {
+            SchedulerError::DecisionError(format!("Unknown resource {}", resource_id))
+        })?;
+        let action = match action {
+            ManualAction::Migrate => SchedulingAction::Migrate,
+            _ if target_host.is_some() => {
+                return Err(SchedulerError::DecisionError("target_host only applies to migrate".to_string()).into());
+            }
+            _ => SchedulingAction::Scale,
+        };
+        let policy = self.effective_policy(&context);
+        if !policy.allows(&action) {
+            return Err(SchedulerError::PolicyError(format!(
+                "{} of {} not allowed by policy overrides {:?}",
+                action.as_str(),
+                resource_id,
+                policy.applied_overrides
+            )).into());
+        }
+        
+        if let (SchedulingAction::Migrate, Some(target_host)) = (&action, target_host) {
+            let instance = snapshot.instance(resource_id).ok_or_else(|| {
+                SchedulerError::DecisionError(format!("{} isn't running on any host", resource_id))
+            })?;
+            if snapshot.host(target_host).is_none() {
+                return Err(SchedulerError::PlacementError(format!("Unknown host {}", target_host)).into());
+            }
+            if instance.host_id == target_host {
+                return Err(SchedulerError::PlacementError(format!(
+                    "{} already runs on {}",
+                    resource_id,
+                    target_host
+                )).into());
+            }
+            
+            let step = MigrationStep {
+                resource_id: resource_id.to_string(),
+                source_host: instance.host_id.clone(),
+                target_host: target_host.to_string(),
+                predicted_vcpus: instance.predicted_vcpu_demand(),
+                memory_mb: instance.memory_mb,
+            };
+            if !self.vet_plan("manual", snapshot, &[step]).await {
+                return Err(SchedulerError::PlacementError(format!(
+                    "Moving {} to {} rejected by simulation; see /api/v1/scheduler/simulation",
+                    resource_id,
+                    target_host
+                )).into());
+            }
+        }
+        
+        let sla_status = self.sla_manager.read().await.check_sla_compliance(resource_id).await;
+        let rationale = ActionRationale {
+            summary: format!("Manual {} of {}", action.as_str(), resource_id),
+            sla_critical: sla_status.is_critical,
+            sla_violations: sla_status.violations.iter().map(|v| format!("{:?}", v)).collect(),
+            applied_overrides: policy.applied_overrides.clone(),
+            ..Default::default()
+        };
+        Ok(SchedulingDecision::new(
+            resource_id.to_string(),
+            action,
+            target_host.map(str::to_string),
+            // Ahead of routine decisions, short of triggering preemption
+            CRITICAL_PRIORITY + 1,
+            sla_status.impact_score,
+            Self::deadline_for(&sla_status),
+            rationale,
+        ))
+    }
+    
+    // Server id to its Heat stack. Stacks only refine placement, so without
+    // them scheduling carries on as if no server had one
+    pub async fn stack_membership(&self) -> Arc<HashMap<String, StackRef>> {
+        match self.openstack_client.heat.stack_membership().await {
+            Ok(membership) => membership,
+            Err(e) => {
+                warn!("Failed to read Heat stack membership: {}", e);
+                Arc::default()
+            }
+        }
+    }
+    
+    // Server id to its Nova server group. Like stacks, groups that can't be
+    // read leave placement to the other filters
+    async fn server_group_membership(&self) -> HashMap<String, ServerGroupRef> {
+        let mut membership = HashMap::new();
+        for client in self.clouds.regions() {
+            match client.nova.list_server_groups().await {
+                Ok(groups) => {
+                    for group in groups {
+                        let Some(group_ref) = group.group_ref() else {
+                            continue;
+                        };
+                        for member in group.members {
+                            membership.insert(member, group_ref.clone());
+                        }
+                    }
+                }
+                Err(e) => warn!("Failed to read server groups in {}: {}", client.region(), e),
+            }
+        }
+        membership
+    }
+    
+    pub async fn decision_explanation(&self, decision_id: &str) -> Result<Option<DecisionExplanation>> {
+        self.decision_journal.get(decision_id).await
+    }
+    
+    pub async fn recent_decisions(&self, limit: usize) -> Result<Vec<DecisionExplanation>> {
+        self.decision_journal.recent(limit).await
+    }
+    
+    pub async fn decision_ids(&self) -> Vec<String> {
+        self.decision_journal.ids().await
+    }
+    
+    async fn live_migrate(&self, resource_id: &str, target_host: &str) -> Result<()> {
+        let timeout = Duration::from_secs(self.config.load().migration_timeout_seconds);
+        let request = async {
+            match self.fault_injector.migration_fault() {
+                Some(MigrationFault::ApiError) => Err(anyhow::anyhow!("Injected fault: Nova rejected the migration")),
+                Some(MigrationFault::Timeout) => std::future::pending().await,
+                None => self.clouds.client_for(resource_id).nova.live_migrate(resource_id, target_host).await,
+            }
+        };
+        
+        // Nova may have taken a request that timed out, so it's followed up
+        // like a started one: retrying it straight away could start a second
+        // migration of a server that is already moving
+        match tokio::time::timeout(timeout, request).await {
+            Ok(Ok(())) => Ok(()),
+            Ok(Err(e)) => Err(SchedulerError::MigrationFailed(format!(
+                "{} to {}: {}",
+                resource_id, target_host, e
+            )).into()),
+            Err(_) => {
+                warn!(
+                    "Migration request for {} to {} timed out after {}s, following it up through Nova's migration records",
+                    resource_id, target_host, timeout.as_secs()
+                );
+                Ok(())
+            }
+        }
+    }
+    
+    // Scores the decision and records the score in its rationale; Some outcome
+    // means a guardrail stops it from running
+    async fn risk_guardrail(
+        &self,
+        decision: &mut SchedulingDecision,
+        snapshot: &ClusterSnapshot,
+        target_host: Option<&str>,
+    ) -> Option<DecisionOutcome> {
+        if !self.risk_assessor.is_enabled() {
+            return None;
+        }
+        
+        let prediction_confidence = self.ml_engine.get_prediction_confidence(&decision.resource_id).await;
+        let sla_priority = self.sla_manager.read().await
+            .get_sla_policy(&decision.resource_id)
+            .map(|policy| policy.priority.clone());
+        let instance = snapshot.instance(&decision.resource_id);
+        let assessment = self.risk_assessor.assess(&RiskInputs {
+            prediction_confidence,
+            vcpus: instance.map(|i| i.vcpus).unwrap_or(0),
+            memory_mb: instance.map(|i| i.memory_mb).unwrap_or(0),
+            sla_priority: sla_priority.as_ref(),
+            sla_critical: decision.rationale.sla_critical,
+            target_host,
+        }, Utc::now());
+        let verdict = self.risk_assessor.verdict(&assessment);
+        let reason = format!(
+            "Risk {:.2} driven mostly by {}",
+            assessment.score,
+            assessment.dominant_factor()
+        );
+        decision.rationale.risk = Some(assessment);
+        
+        match verdict {
+            RiskVerdict::Allow => None,
+            RiskVerdict::Recommend => {
+                info!(
+                    "Recommending {} of {} instead of executing it: {}",
+                    decision.action.as_str(),
+                    decision.resource_id,
+                    reason
+                );
+                Some(DecisionOutcome::Recommended { reason })
+            }
+            RiskVerdict::Block => {
+                warn!("Blocked {} of {}: {}", decision.action.as_str(), decision.resource_id, reason);
+                Some(DecisionOutcome::Blocked {
+                    rule: "risk_guardrail".to_string(),
+                    reason,
+                })
+            }
+        }
+    }
+    
+    // The instance stays on its source host, so nothing has to be undone
+    // locally; the decision is requeued until its attempts run out
+    async fn handle_failed_migration(
+        &self,
+        mut decision: SchedulingDecision,
+        placement: Option<PlacementOutcome>,
+        target_host: &str,
+        error: anyhow::Error,
+    ) {
+        self.risk_assessor.record_failure(target_host, Utc::now());
+        decision.attempts += 1;
+        let will_retry = decision.attempts < self.config.load().max_migration_attempts;
+        let reason = error.to_string();
+        self.events.execution_completed(&decision, Some(target_host), Some(reason.clone()));
+        if will_retry {
+            warn!(
+                "Migration of {} failed (attempt {}/{}), retrying next cycle: {}",
+                decision.resource_id, decision.attempts, self.config.load().max_migration_attempts, reason
+            );
+        } else {
+            error!(
+                "Migration of {} failed after {} attempts, giving up: {}",
+                decision.resource_id, decision.attempts, reason
+            );
+            ::metrics::counter!("scheduler_migrations_abandoned_total").increment(1);
+        }
+        
+        self.stats.record_action(decision.action.as_str(), false).await;
+        self.explain(&decision, placement, DecisionOutcome::Failed { reason, will_retry }).await;
+        if will_retry {
+            self.decision_queue.defer(decision).await;
+        }
+    }
+    
+    // A failure that says nothing about the decision itself, e.g. Placement
+    // being unreachable; it stays queued rather than fail the whole cycle
+    async fn defer_after_error(&self, decision: SchedulingDecision, step: &str, error: anyhow::Error) {
+        warn!("{} for {} failed, deferring it: {}", step, decision.resource_id, error);
+        self.explain(&decision, None, DecisionOutcome::Deferred {
+            reason: format!("{} failed: {}", step, error),
+        }).await;
+        self.decision_queue.defer(decision).await;
+    }
+    
+    // Returns the number of migrations started. Errors are handled per
+    // decision, so whatever wasn't settled is always deferred and checkpointed
     async fn execute_scheduling_decisions(
         &self,
-        mut decisions: Vec<SchedulingDecision>,
-    ) -> Result<()> {
-        // Sort by priority (EDF-style scheduling)
-        decisions.sort_by_key(|d| d.priority);
+        decisions: Vec<SchedulingDecision>,
+        snapshot: &ClusterSnapshot,
+        shutdown: &CancellationToken,
+    ) -> Result<usize> {
+        // Standbys compute decisions but leave executing them to the leader
+        if !self.leader_elector.is_leader() {
+            debug!("Standby: not executing {} decisions", decisions.len());
+            return Ok(0);
+        }
         
-        for decision in decisions {
+        self.reconcile_in_flight().await;
+        
+        for decision in &decisions {
+            self.events.decision_made(decision);
+        }
+        
+        // Earliest deadline first, including decisions deferred by earlier cycles
+        self.decision_queue.enqueue(decisions).await;
+        
+        // Paused: everything stays queued until an operator resumes
+        if self.control.is_paused() {
+            debug!("Scheduler paused, leaving decisions queued");
+            return Ok(0);
+        }
+        let mode = self.control.mode();
+        let mut decisions = self.decision_queue.drain().await.into_iter();
+        
+        let powered_off_hosts = self.power_manager.powered_off_hosts().await;
+        let mut allowance = self.disruption_allowance(snapshot).await;
+        let mut migrations_started = 0;
+        // Servers still migrating or resizing aren't acted on again until
+        // that settles
+        let mut moving: HashSet<String> = self.decision_queue.in_flight().await
+            .into_iter()
+            .map(|action| action.decision.resource_id)
+            .collect();
+        
+        for mut decision in decisions.by_ref() {
+            // Leadership can be lost mid-cycle; keep the rest queued for whoever leads next
+            if !self.leader_elector.is_leader() {
+                warn!("Lost leadership, leaving remaining decisions queued");
+                self.decision_queue.defer(decision).await;
+                break;
+            }
+            if shutdown.is_cancelled() {
+                info!("Shutting down, leaving remaining decisions queued");
+                self.decision_queue.defer(decision).await;
+                break;
+            }
+            
+            let context = snapshot.resource_context(&decision.resource_id);
+            let now = Utc::now();
+            
+            if !self.effective_policy(&context).allows(&decision.action) {
+                debug!(
+                    "Skipping {} of {}: not allowed by policy overrides",
+                    decision.action.as_str(),
+                    decision.resource_id
+                );
+                self.explain(&decision, None, DecisionOutcome::Skipped {
+                    reason: "Not allowed by policy overrides".to_string(),
+                }).await;
+                continue;
+            }
+            
+            if let Some(block) = self.policy_engine.evaluate(
+                &decision.action,
+                decision.target_host.as_deref(),
+                &context,
+                now,
+            ) {
+                self.explain(&decision, None, DecisionOutcome::Blocked {
+                    rule: block.rule.clone(),
+                    reason: block.reason.clone(),
+                }).await;
+                self.policy_engine.record_block(block).await;
+                continue;
+            }
+            
+            if let Some(blackout) = self.policy_engine.active_blackout(&decision.action, &context, now) {
+                let reason = format!("Blackout '{}' until {}", blackout.name, blackout.ends_at);
+                debug!("Holding {} of {}: {}", decision.action.as_str(), decision.resource_id, reason);
+                match blackout.mode {
+                    BlackoutMode::Queue => {
+                        self.explain(&decision, None, DecisionOutcome::Deferred { reason }).await;
+                        self.decision_queue.defer(decision).await;
+                    }
+                    BlackoutMode::Expire => {
+                        self.explain(&decision, None, DecisionOutcome::Skipped { reason }).await;
+                    }
+                }
+                continue;
+            }
+            
+            // Operator requests still run in recommend mode
+            if mode == ExecutionMode::Recommend && decision.rationale.requested_by.is_none() {
+                self.explain(&decision, None, DecisionOutcome::Recommended {
+                    reason: "Scheduler is in recommend mode".to_string(),
+                }).await;
+                continue;
+            }
+            
+            let disruptive = matches!(decision.action, SchedulingAction::Migrate | SchedulingAction::Scale);
+            if disruptive && moving.contains(&decision.resource_id) {
+                debug!("Skipping {} of {}: an earlier action on it is in flight", decision.action.as_str(), decision.resource_id);
+                self.explain(&decision, None, DecisionOutcome::Skipped {
+                    reason: "An earlier migration or resize of it is still in flight".to_string(),
+                }).await;
+                continue;
+            }
+            // Operator requests aren't held off
+            if disruptive && decision.rationale.requested_by.is_none() {
+                let client = self.clouds.client_for(&decision.resource_id);
+                if let Some(reason) = self.cooldown.active(&client, &decision.resource_id).await {
+                    debug!("Skipping {} of {}: {}", decision.action.as_str(), decision.resource_id, reason);
+                    ::metrics::counter!("scheduler_cooldown_skips_total", "action" => decision.action.as_str()).increment(1);
+                    self.explain(&decision, None, DecisionOutcome::Skipped {
+                        reason: format!("Recently acted on: {}", reason),
+                    }).await;
+                    continue;
+                }
+            }
+            if disruptive {
+                if let Some(budget) = allowance.exhausted_budget(&context) {
+                    debug!("Disruption budget {} exhausted, deferring {}", budget, decision.resource_id);
+                    self.explain(&decision, None, DecisionOutcome::Deferred {
+                        reason: format!("Disruption budget '{}' exhausted", budget),
+                    }).await;
+                    self.decision_queue.defer(decision).await;
+                    continue;
+                }
+            }
+            
             match decision.action {
                 SchedulingAction::Migrate => {
-                    if let Some(target_host) = self.placement_engine
-                        .find_optimal_host(&decision.resource_id)
-                        .await? {
+                    if migrations_started >= self.config.load().max_migrations_per_cycle {
+                        debug!("Migration budget exhausted, deferring {}", decision.resource_id);
+                        self.explain(&decision, None, DecisionOutcome::Deferred {
+                            reason: "Migration budget for this cycle exhausted".to_string(),
+                        }).await;
+                        self.decision_queue.defer(decision).await;
+                        continue;
+                    }
+                    
+                    let mut placement = None;
+                    let target_host = match decision.target_host.clone() {
+                        Some(host) => Some(host),
+                        None => {
+                            let denied_hosts = self.policy_engine.denied_hosts(&decision.action, &context, now);
+                            let allow_cross_az = self.effective_policy(&context).allow_cross_az;
+                            let outcome = match self.placement_engine
+                                .find_optimal_host(&context, snapshot, &powered_off_hosts, &denied_hosts, allow_cross_az)
+                                .await
+                            {
+                                Ok(outcome) => outcome,
+                                Err(e) => {
+                                    self.defer_after_error(decision, "Placement", e).await;
+                                    continue;
+                                }
+                            };
+                            let target = outcome.selected.clone();
+                            placement = Some(outcome);
+                            let mut excluded_hosts = powered_off_hosts.clone();
+                            excluded_hosts.extend(denied_hosts);
+                            
+                            // SLA-critical resources may evict preemptible ones
+                            // when nothing has headroom
+                            if target.is_none()
+                                && decision.priority == CRITICAL_PRIORITY
+                                && self.preemption_manager.is_enabled()
+                            {
+                                match self.preemption_manager
+                                    .preempt_for(&decision.resource_id, snapshot, &excluded_hosts)
+                                    .await
+                                {
+                                    Ok(target) => target,
+                                    Err(e) => {
+                                        self.defer_after_error(decision, "Preemption", e).await;
+                                        continue;
+                                    }
+                                }
+                            } else {
+                                target
+                            }
+                        }
+                    };
+                    
+                    if let Some(target_host) = target_host {
+                        if let Some(outcome) = self.risk_guardrail(&mut decision, snapshot, Some(&target_host)).await {
+                            self.explain(&decision, placement, outcome).await;
+                            continue;
+                        }
+                        
                         info!("Migrating {} to {}", decision.resource_id, target_host);
-                        // Execute migration via OpenStack API
+                        self.events.execution_started(&decision, Some(&target_host));
+                        if let Err(e) = self.live_migrate(&decision.resource_id, &target_host).await {
+                            self.handle_failed_migration(decision, placement, &target_host, e).await;
+                            continue;
+                        }
+                        self.decision_queue.start_action(&decision, context.host.clone(), &target_host).await;
+                        moving.insert(decision.resource_id.clone());
+                        self.cooldown.record(&decision.resource_id, "live-migration").await;
+                        allowance.take(&context);
+                        migrations_started += 1;
+                        self.decision_queue.record_execution(&decision).await;
+                        self.stats.record_action(decision.action.as_str(), true).await;
+                        self.explain(&decision, placement, DecisionOutcome::Executed {
+                            target_host: Some(target_host),
+                        }).await;
+                    } else {
+                        self.explain(&decision, placement, DecisionOutcome::NoCapacity).await;
+                        self.stats.record_action(decision.action.as_str(), false).await;
+                        if self.power_manager.is_enabled() {
+                            if let Err(e) = self.power_manager.wake_on_demand().await {
+                                warn!("Failed to wake a host for {}: {}", decision.resource_id, e);
+                            }
+                        }
                     }
                 },
                 SchedulingAction::Scale => {
-                    info!("Scaling resource {}", decision.resource_id);
-                    // Execute scaling operation
+                    if let Some(outcome) = self.risk_guardrail(&mut decision, snapshot, None).await {
+                        self.explain(&decision, None, outcome).await;
+                        continue;
+                    }
+                    
+                    if !self.resizer.is_enabled() {
+                        self.explain(&decision, None, DecisionOutcome::Recommended {
+                            reason: "Resizing is disabled".to_string(),
+                        }).await;
+                        continue;
+                    }
+                    
+                    // Manual scales carry no prediction and go up
+                    let direction = match (decision.rationale.predicted_load, decision.rationale.low_load_threshold) {
+                        (Some(load), Some(threshold)) if load < threshold => ResizeDirection::Down,
+                        _ => ResizeDirection::Up,
+                    };
+                    info!("Scaling resource {} {}", decision.resource_id, direction.as_str());
+                    self.events.execution_started(&decision, None);
+                    // Followed up by reconcile_in_flight, as a resize can take
+                    // far longer than a cycle
+                    match self.resizer.start(&context, direction).await {
+                        Ok(Some(target)) => {
+                            self.decision_queue.start_resize(&decision, context.host.clone(), target).await;
+                            moving.insert(decision.resource_id.clone());
+                            self.cooldown.record(&decision.resource_id, "resize").await;
+                            allowance.take(&context);
+                            self.decision_queue.record_execution(&decision).await;
+                            self.stats.record_action(decision.action.as_str(), true).await;
+                            self.explain(&decision, None, DecisionOutcome::Executed { target_host: None }).await;
+                        }
+                        Ok(None) => {
+                            let reason = format!(
+                                "Flavor {} has no step {} on the flavor ladder",
+                                context.flavor_id,
+                                direction.as_str()
+                            );
+                            self.events.execution_completed(&decision, None, Some(reason.clone()));
+                            self.explain(&decision, None, DecisionOutcome::Skipped { reason }).await;
+                        }
+                        Err(e) => {
+                            warn!("Scaling {} failed: {}", decision.resource_id, e);
+                            let reason = e.to_string();
+                            self.events.execution_completed(&decision, None, Some(reason.clone()));
+                            self.stats.record_action(decision.action.as_str(), false).await;
+                            self.explain(&decision, None, DecisionOutcome::Failed { reason, will_retry: false }).await;
+                        }
+                    }
+                },
+                SchedulingAction::ScaleOut { replicas } | SchedulingAction::ScaleIn { replicas } => {
+                    let Some(group) = self.autoscaler.group_of(&context) else {
+                        self.explain(&decision, None, DecisionOutcome::Skipped {
+                            reason: "Resource is no longer in an autoscaling group".to_string(),
+                        }).await;
+                        continue;
+                    };
+                    self.events.execution_started(&decision, None);
+                    let result = self.autoscaler.apply(&group, replicas).await;
+                    self.events.execution_completed(&decision, None, result.as_ref().err().map(|e| e.to_string()));
+                    match result {
+                        Ok(true) => {
+                            self.stats.record_action(decision.action.as_str(), true).await;
+                            self.decision_queue.record_execution(&decision).await;
+                            self.explain(&decision, None, DecisionOutcome::Executed { target_host: None }).await;
+                        }
+                        Ok(false) => {
+                            self.explain(&decision, None, DecisionOutcome::Skipped {
+                                reason: format!("Scaling group {} is still cooling down", group.group_id),
+                            }).await;
+                        }
+                        Err(e) => {
+                            warn!("Scaling the group of {} failed: {}", decision.resource_id, e);
+                            self.stats.record_action(decision.action.as_str(), false).await;
+                            self.explain(&decision, None, DecisionOutcome::Failed {
+                                reason: e.to_string(),
+                                will_retry: false,
+                            }).await;
+                        }
+                    }
                 },
                 SchedulingAction::Consolidate => {
                     info!("Consolidating resource {}", decision.resource_id);
+                    self.events.execution_started(&decision, None);
                     // Execute consolidation
+                    self.decision_queue.record_execution(&decision).await;
+                    self.stats.record_action(decision.action.as_str(), true).await;
+                    self.explain(&decision, None, DecisionOutcome::Executed { target_host: None }).await;
+                    self.events.execution_completed(&decision, None, None);
                 },
                 SchedulingAction::NoAction => {},
             }
         }
         
-        Ok(())
+        for decision in decisions {
+            self.decision_queue.defer(decision).await;
+        }
+        self.decision_queue.checkpoint().await;
+        
+        Ok(migrations_started)
+    }
+    
+    // Settles migrations started by this or a previous process against
+    // Nova's migration records. One still running past the timeout is
+    // aborted, and retried only once Nova reports it cancelled or failed
+    async fn reconcile_in_flight(&self) {
+        let timeout = ChronoDuration::seconds(self.config.load().migration_timeout_seconds as i64);
+        
+        for action in self.decision_queue.in_flight().await {
+            if action.resize.is_some() {
+                self.reconcile_resize(action).await;
+                continue;
+            }
+            let resource_id = &action.decision.resource_id;
+            let client = self.clouds.client_for(resource_id);
+            let migrations = match client.nova.list_server_migrations(resource_id).await {
+                Ok(migrations) => migrations,
+                Err(e) => {
+                    warn!("Failed to fetch migrations of {}: {}", resource_id, e);
+                    continue;
+                }
+            };
+            
+            // Nova and our clocks can disagree slightly
+            let since = action.started_at - ChronoDuration::seconds(60);
+            let migration = migrations.iter()
+                .filter(|m| m.created_at() >= since)
+                .max_by_key(|m| m.created_at());
+            
+            let failure = match migration.map(|m| (m, m.state())) {
+                // Nova wouldn't abort it; it's no longer tracked, and isn't
+                // retried, as Nova won't migrate a server that is still moving
+                Some((m, MigrationState::InProgress)) if Utc::now() - action.started_at > timeout * 2 => {
+                    warn!(
+                        "Migration {} of {} to {} is still {} after {}s, no longer following it",
+                        m.id, resource_id, action.target_host, m.status, (timeout * 2).num_seconds()
+                    );
+                    self.decision_queue.finish_action(&action.decision.id).await;
+                    let reason = format!("Migration {} still {} past the timeout", m.id, m.status);
+                    self.events.execution_completed(&action.decision, Some(&action.target_host), Some(reason));
+                    ::metrics::counter!("scheduler_migrations_abandoned_total").increment(1);
+                    continue;
+                }
+                Some((m, MigrationState::InProgress)) if Utc::now() - action.started_at > timeout => {
+                    if let Err(e) = client.nova.abort_live_migration(resource_id, m.id).await {
+                        warn!("Failed to abort migration {} of {}, still waiting for it: {}", m.id, resource_id, e);
+                    }
+                    continue;
+                }
+                Some((_, MigrationState::InProgress)) => continue,
+                Some((m, MigrationState::Completed)) => {
+                    debug!("Migration {} of {} to {} completed", m.id, resource_id, action.target_host);
+                    // By Nova's clock, as this only notices on the next cycle
+                    let finished_at = m.updated_at().unwrap_or_else(Utc::now);
+                    self.stats.record_migration((finished_at - m.created_at()).to_std().unwrap_or_default()).await;
+                    self.decision_queue.finish_action(&action.decision.id).await;
+                    self.events.execution_completed(&action.decision, Some(&action.target_host), None);
+                    continue;
+                }
+                Some((m, MigrationState::Failed)) => format!("Nova reports migration {} as {}", m.id, m.status),
+                None if Utc::now() - action.started_at > timeout => {
+                    "Nova has no record of the migration".to_string()
+                }
+                None => continue,
+            };
+            
+            self.decision_queue.finish_action(&action.decision.id).await;
+            let error = SchedulerError::MigrationFailed(format!("{} to {}: {}", resource_id, action.target_host, failure));
+            self.handle_failed_migration(action.decision, None, &action.target_host, error.into()).await;
+        }
+    }
+    
+    // Resizes aren't retried: the next cycle decides afresh on the new flavor
+    async fn reconcile_resize(&self, action: InFlightAction) {
+        let Some(target) = &action.resize else {
+            return;
+        };
+        let resource_id = &action.decision.resource_id;
+        let failure = match self.resizer.progress(resource_id, target, action.started_at).await {
+            Ok(ResizeProgress::Running) => return,
+            Ok(ResizeProgress::Done) => None,
+            Err(e) => {
+                warn!("Scaling {} failed: {}", resource_id, e);
+                Some(e.to_string())
+            }
+        };
+        self.decision_queue.finish_action(&action.decision.id).await;
+        if failure.is_some() {
+            self.stats.record_action(action.decision.action.as_str(), false).await;
+        }
+        self.events.execution_completed(&action.decision, None, failure);
     }
 }
 
@@ -176,4 +1995,15 @@ pub struct SLAStatus {
     pub is_critical: bool,
     pub impact_score: f64,
     pub deadline_minutes: u32,
+    pub violations: Vec<ViolationType>,
+    // Whether this check met the availability/latency SLO; None without a policy
+    pub slo_met: Option<bool>,
+}
+
+// The interval restarts from now when its period changed
+fn retune(interval: &mut Interval, seconds: u64) {
+    let period = Duration::from_secs(seconds);
+    if interval.period() != period {
+        *interval = interval_at(tokio::time::Instant::now() + period, period);
+    }
 }
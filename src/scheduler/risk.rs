@@ -0,0 +1,236 @@
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use tracing::debug;
+
+use crate::config::RiskConfig;
+use super::sla_manager::SLAPriority;
+
+// Scores how much could go wrong if a decision runs, so the riskiest ones
+// can be held for an operator instead of executed automatically
+pub struct RiskAssessor {
+    config: RiskConfig,
+    // Recent failed migrations into each host
+    host_failures: DashMap<String, VecDeque<DateTime<Utc>>>,
+}
+
+pub struct RiskInputs<'a> {
+    // None when there's no history to predict from
+    pub prediction_confidence: Option<f64>,
+    pub vcpus: u32,
+    pub memory_mb: u64,
+    pub sla_priority: Option<&'a SLAPriority>,
+    pub sla_critical: bool,
+    pub target_host: Option<&'a str>,
+}
+
+// Each factor and the weighted score are in 0-1
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RiskAssessment {
+    pub score: f64,
+    pub prediction_uncertainty: f64,
+    pub size: f64,
+    pub sla_priority: f64,
+    pub host_failures: f64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RiskVerdict {
+    Allow,
+    Recommend,
+    Block,
+}
+
+impl RiskVerdict {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RiskVerdict::Allow => "allow",
+            RiskVerdict::Recommend => "recommend",
+            RiskVerdict::Block => "block",
+        }
+    }
+}
+
+impl RiskAssessment {
+    // Name of the factor contributing most, for explanations
+    pub fn dominant_factor(&self) -> &'static str {
+        [
+            ("prediction_uncertainty", self.prediction_uncertainty),
+            ("size", self.size),
+            ("sla_priority", self.sla_priority),
+            ("host_failures", self.host_failures),
+        ]
+        .into_iter()
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(name, _)| name)
+        .unwrap_or("none")
+    }
+}
+
+impl RiskAssessor {
+    pub fn new(config: RiskConfig) -> Self {
+        Self {
+            config,
+            host_failures: DashMap::new(),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    pub fn record_failure(&self, target_host: &str, now: DateTime<Utc>) {
+        let mut failures = self.host_failures.entry(target_host.to_string()).or_default();
+        failures.push_back(now);
+        self.prune(&mut failures, now);
+    }
+    
+    pub fn assess(&self, inputs: &RiskInputs, now: DateTime<Utc>) -> RiskAssessment {
+        let config = &self.config;
+        
+        // Acting on a placeholder, or NaN, prediction is as uncertain as it gets
+        let prediction_uncertainty = 1.0 - inputs.prediction_confidence
+            .filter(|confidence| !confidence.is_nan())
+            .unwrap_or(0.0)
+            .clamp(0.0, 1.0);
+        let size = (inputs.vcpus as f64 / config.large_vcpus.max(1) as f64)
+            .max(inputs.memory_mb as f64 / config.large_memory_mb.max(1) as f64)
+            .min(1.0);
+        let sla_priority = if inputs.sla_critical {
+            1.0
+        } else {
+            match inputs.sla_priority {
+                Some(SLAPriority::Critical) => 1.0,
+                Some(SLAPriority::High) => 0.7,
+                Some(SLAPriority::Medium) => 0.4,
+                Some(SLAPriority::Low) => 0.1,
+                None => 0.0,
+            }
+        };
+        let host_failures = inputs.target_host
+            .map(|host| self.recent_failures(host, now) as f64 / config.host_failures_for_max_risk.max(1) as f64)
+            .unwrap_or(0.0)
+            .min(1.0);
+        
+        let weights = &config.weights;
+        let total_weight = weights.prediction_uncertainty + weights.size + weights.sla_priority + weights.host_failures;
+        let score = if total_weight > 0.0 {
+            (weights.prediction_uncertainty * prediction_uncertainty
+                + weights.size * size
+                + weights.sla_priority * sla_priority
+                + weights.host_failures * host_failures)
+                / total_weight
+        } else {
+            0.0
+        };
+        
+        ::metrics::histogram!("scheduler_decision_risk").record(score);
+        RiskAssessment {
+            score,
+            prediction_uncertainty,
+            size,
+            sla_priority,
+            host_failures,
+        }
+    }
+    
+    pub fn verdict(&self, assessment: &RiskAssessment) -> RiskVerdict {
+        let verdict = if assessment.score > self.config.block_above {
+            RiskVerdict::Block
+        } else if assessment.score > self.config.recommend_above {
+            RiskVerdict::Recommend
+        } else {
+            RiskVerdict::Allow
+        };
+        
+        if verdict != RiskVerdict::Allow {
+            debug!(
+                "Risk {:.2} (mostly {}) exceeds guardrail: {}",
+                assessment.score,
+                assessment.dominant_factor(),
+                verdict.as_str()
+            );
+            ::metrics::counter!("scheduler_risk_guardrail_total", "verdict" => verdict.as_str()).increment(1);
+        }
+        verdict
+    }
+    
+    fn recent_failures(&self, host: &str, now: DateTime<Utc>) -> usize {
+        match self.host_failures.get_mut(host) {
+            Some(mut failures) => {
+                self.prune(&mut failures, now);
+                failures.len()
+            }
+            None => 0,
+        }
+    }
+    
+    fn prune(&self, failures: &mut VecDeque<DateTime<Utc>>, now: DateTime<Utc>) {
+        let cutoff = now - Duration::minutes(self.config.host_failure_window_minutes);
+        while failures.front().is_some_and(|at| *at < cutoff) {
+            failures.pop_front();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn assessment(score: f64) -> RiskAssessment {
+        RiskAssessment {
+            score,
+            prediction_uncertainty: score,
+            size: 0.0,
+            sla_priority: 0.0,
+            host_failures: 0.0,
+        }
+    }
+    
+    fn inputs(prediction_confidence: Option<f64>, target_host: Option<&str>) -> RiskInputs<'_> {
+        RiskInputs {
+            prediction_confidence,
+            vcpus: 2,
+            memory_mb: 4096,
+            sla_priority: None,
+            sla_critical: false,
+            target_host,
+        }
+    }
+    
+    #[test]
+    fn verdict_follows_the_thresholds() {
+        let assessor = RiskAssessor::new(RiskConfig::default());
+        assert_eq!(assessor.verdict(&assessment(0.0)), RiskVerdict::Allow);
+        assert_eq!(assessor.verdict(&assessment(0.6)), RiskVerdict::Allow);
+        assert_eq!(assessor.verdict(&assessment(0.61)), RiskVerdict::Recommend);
+        assert_eq!(assessor.verdict(&assessment(0.85)), RiskVerdict::Recommend);
+        assert_eq!(assessor.verdict(&assessment(0.86)), RiskVerdict::Block);
+    }
+    
+    #[test]
+    fn missing_or_nan_confidence_is_fully_uncertain() {
+        let assessor = RiskAssessor::new(RiskConfig::default());
+        let now = Utc::now();
+        assert_eq!(assessor.assess(&inputs(None, None), now).prediction_uncertainty, 1.0);
+        assert_eq!(assessor.assess(&inputs(Some(f64::NAN), None), now).prediction_uncertainty, 1.0);
+        assert_eq!(assessor.assess(&inputs(Some(1.0), None), now).prediction_uncertainty, 0.0);
+    }
+    
+    #[test]
+    fn recent_failures_make_a_host_risky_until_they_age_out() {
+        let assessor = RiskAssessor::new(RiskConfig::default());
+        let now = Utc::now();
+        for _ in 0..3 {
+            assessor.record_failure("compute-1", now - Duration::minutes(30));
+        }
+        
+        let assessed = assessor.assess(&inputs(Some(1.0), Some("compute-1")), now);
+        assert_eq!(assessed.host_failures, 1.0);
+        assert_eq!(assessed.dominant_factor(), "host_failures");
+        
+        let later = now + Duration::minutes(31);
+        assert_eq!(assessor.assess(&inputs(Some(1.0), Some("compute-1")), later).host_failures, 0.0);
+    }
+}
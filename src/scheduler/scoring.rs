@@ -0,0 +1,99 @@
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ScoringConfig, ScoringPreset, ScoringWeights};
+use crate::error::SchedulerError;
+use super::placement::{HostMetrics, PlacementScore, ResourceRequirements};
+
+// Weighted multi-objective host scoring, selected by named preset or custom weights
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoringStrategy {
+    pub preset: ScoringPreset,
+    pub weights: ScoringWeights,
+}
+
+impl ScoringPreset {
+    pub fn weights(&self) -> Option<ScoringWeights> {
+        let (cpu, memory, network, consolidation, optimal_utilization) = match self {
+            // Spread load evenly around moderate utilization
+            ScoringPreset::Balance => (0.3, 0.3, 0.2, 0.2, 65.0),
+            // Fill busy hosts so others can be drained
+            ScoringPreset::Consolidate => (0.2, 0.2, 0.1, 0.5, 85.0),
+            // Pack tightly to keep as few hosts powered as possible
+            ScoringPreset::Energy => (0.25, 0.25, 0.05, 0.45, 80.0),
+            // Keep headroom and favour quiet networks
+            ScoringPreset::Latency => (0.3, 0.2, 0.4, 0.1, 40.0),
+            ScoringPreset::Custom => return None,
+        };
+        
+        Some(ScoringWeights {
+            cpu,
+            memory,
+            network,
+            consolidation,
+            optimal_utilization,
+        })
+    }
+}
+
+impl ScoringStrategy {
+    pub fn from_config(config: &ScoringConfig) -> Result<Self> {
+        Self::new(config.preset, Some(config.weights))
+    }
+    
+    pub fn new(preset: ScoringPreset, custom_weights: Option<ScoringWeights>) -> Result<Self> {
+        let weights = match preset.weights() {
+            Some(weights) => weights,
+            None => custom_weights.ok_or_else(|| SchedulerError::DecisionError(
+                "Custom scoring preset requires weights".to_string()
+            ))?,
+        };
+        
+        let total = weights.cpu + weights.memory + weights.network + weights.consolidation;
+        if [weights.cpu, weights.memory, weights.network, weights.consolidation].iter().any(|w| *w < 0.0)
+            || total <= 0.0
+        {
+            return Err(SchedulerError::DecisionError(
+                "Scoring weights must be non-negative and not all zero".to_string()
+            ).into());
+        }
+        
+        if !(0.0..=100.0).contains(&weights.optimal_utilization) {
+            return Err(SchedulerError::DecisionError(
+                "optimal_utilization must be between 0 and 100".to_string()
+            ).into());
+        }
+        
+        Ok(Self { preset, weights })
+    }
+    
+    pub fn score(&self, host: &HostMetrics, _requirements: &ResourceRequirements) -> PlacementScore {
+        let cpu_score = self.utilization_score(host.cpu_utilization);
+        let memory_score = self.utilization_score(host.memory_utilization);
+        let network_score = self.utilization_score(host.network_utilization);
+        
+        // Prefer hosts with more VMs for better consolidation
+        let consolidation_score = (host.vm_count as f64 / 20.0).min(1.0);
+        
+        let w = &self.weights;
+        let total_weight = w.cpu + w.memory + w.network + w.consolidation;
+        let total_score = (cpu_score * w.cpu
+            + memory_score * w.memory
+            + network_score * w.network
+            + consolidation_score * w.consolidation) / total_weight;
+        
+        PlacementScore {
+            host_id: host.host_id.clone(),
+            score: total_score,
+            cpu_score,
+            memory_score,
+            network_score,
+            consolidation_score,
+        }
+    }
+    
+    fn utilization_score(&self, utilization: f64) -> f64 {
+        let distance = (utilization - self.weights.optimal_utilization).abs();
+        (100.0 - distance) / 100.0
+    }
+}
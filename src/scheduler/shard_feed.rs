@@ -0,0 +1,95 @@
+use anyhow::Result;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{Consumer, StreamConsumer};
+use rdkafka::Message;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio_util::sync::CancellationToken;
+use tracing::{info, info_span, warn};
+
+use crate::config::KafkaConfig;
+use crate::metrics::avro;
+use crate::metrics::kafka_producer::trace_carrier;
+use crate::metrics::sharding::ShardCoordinator;
+use crate::metrics::LatestMetrics;
+use crate::ml::MLEngine;
+use crate::telemetry;
+use super::leader::LeaderElector;
+
+const RETRY_DELAY: Duration = Duration::from_secs(1);
+
+// With sharding each member collects only its share, but the leader plans
+// for the whole cluster. It reads the other members' samples back off the
+// compute topic into its metrics cache and predictor
+pub struct ShardFeed {
+    consumer: StreamConsumer,
+    shards: Arc<ShardCoordinator>,
+    latest_metrics: Arc<LatestMetrics>,
+    ml_engine: Arc<MLEngine>,
+}
+
+impl ShardFeed {
+    pub fn new(
+        kafka_config: &KafkaConfig,
+        instance_id: &str,
+        shards: Arc<ShardCoordinator>,
+        latest_metrics: Arc<LatestMetrics>,
+        ml_engine: Arc<MLEngine>,
+    ) -> Result<Self> {
+        // A group of its own, so this instance sees every partition
+        let consumer: StreamConsumer = ClientConfig::new()
+            .set("bootstrap.servers", &kafka_config.brokers)
+            .set("group.id", format!("scheduler-feed-{}", instance_id))
+            .set("enable.auto.commit", "false")
+            .set("auto.offset.reset", "latest")
+            .create()?;
+        consumer.subscribe(&[&kafka_config.compute_topic])?;
+        
+        Ok(Self {
+            consumer,
+            shards,
+            latest_metrics,
+            ml_engine,
+        })
+    }
+    
+    // Standbys keep reading so a new leader starts from current offsets, but
+    // only the leader records what it reads
+    pub async fn run(&self, elector: Arc<LeaderElector>, shutdown: CancellationToken) {
+        info!("Reading other shards' samples from Kafka");
+        
+        loop {
+            let received = tokio::select! {
+                received = self.consumer.recv() => received,
+                _ = shutdown.cancelled() => return,
+            };
+            let (metrics, carrier) = match received {
+                Ok(message) => match message.payload().map(avro::parse_server_metrics) {
+                    Some(Ok(metrics)) => (metrics, trace_carrier(&message)),
+                    Some(Err(e)) => {
+                        warn!("Skipping unreadable sample from partition {}: {}", message.partition(), e);
+                        continue;
+                    }
+                    None => continue,
+                },
+                Err(e) => {
+                    warn!("Shard feed receive failed: {}", e);
+                    tokio::time::sleep(RETRY_DELAY).await;
+                    continue;
+                }
+            };
+            
+            // Our own share was recorded when it was collected
+            if !elector.is_leader() || self.shards.owns(&metrics.server_id) {
+                continue;
+            }
+            
+            // Carries on the collecting member's trace for this resource
+            let span = info_span!("shard_feed.record", resource_id = %metrics.server_id);
+            telemetry::set_remote_parent(&span, &carrier);
+            span.in_scope(|| self.latest_metrics.record_server_metrics(&metrics));
+            self.ml_engine.record_sample(metrics).await;
+            ::metrics::counter!("shard_feed_samples_total").increment(1);
+        }
+    }
+}
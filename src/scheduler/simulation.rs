@@ -0,0 +1,199 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::collections::HashMap;
+use tracing::{debug, info};
+
+use crate::config::SimulationConfig;
+use super::cluster::ClusterSnapshot;
+use super::consolidation::MigrationStep;
+use super::sla_manager::PenaltyModel;
+
+// Dry-runs a migration plan against forecasted host loads and compares the
+// projected outcome to leaving everything where it is
+pub struct PlanSimulator {
+    config: SimulationConfig,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct HostProjection {
+    pub host_id: String,
+    pub vcpu_utilization: f64,
+    pub memory_utilization: f64,
+    pub instance_count: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationOutcome {
+    pub hosts: Vec<HostProjection>,
+    pub imbalance: f64,
+    pub sla_risk: f64,
+    pub active_hosts: usize,
+    // GB of instance memory that live migration has to copy
+    pub migration_cost_gb: f64,
+    // GB of local disk and cross-backend volume data copied on top of memory
+    pub storage_transfer_gb: f64,
+    // Forecast SLA penalties over the penalty horizon
+    pub expected_penalty: f64,
+    pub disruption_cost: f64,
+    pub score: f64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    pub plan_source: String,
+    pub migrations: usize,
+    pub baseline: SimulationOutcome,
+    pub projected: SimulationOutcome,
+    pub penalty_avoided: f64,
+    pub accepted: bool,
+    pub simulated_at: DateTime<Utc>,
+}
+
+impl PlanSimulator {
+    pub fn new(config: SimulationConfig) -> Self {
+        Self { config }
+    }
+    
+    pub fn simulate(&self, plan_source: &str, snapshot: &ClusterSnapshot, steps: &[MigrationStep]) -> SimulationReport {
+        let baseline_placement: HashMap<&str, &str> = snapshot.instances.iter()
+            .map(|i| (i.resource_id.as_str(), i.host_id.as_str()))
+            .collect();
+        
+        let mut projected_placement = baseline_placement.clone();
+        let mut migration_cost_gb = 0.0;
+        let mut storage_transfer_gb = 0.0;
+        for step in steps {
+            if let Some(host) = projected_placement.get_mut(step.resource_id.as_str()) {
+                *host = step.target_host.as_str();
+                migration_cost_gb += step.memory_mb as f64 / 1024.0;
+            }
+            if let Some(instance) = snapshot.instance(&step.resource_id) {
+                storage_transfer_gb += snapshot.storage_migration(instance, &step.target_host).transfer_gb;
+            }
+        }
+        
+        let disruption_cost = steps.len() as f64 * self.config.migration_disruption_cost;
+        let baseline = self.evaluate(snapshot, &baseline_placement, 0.0, 0.0, 0.0);
+        let projected = self.evaluate(
+            snapshot,
+            &projected_placement,
+            migration_cost_gb,
+            storage_transfer_gb,
+            disruption_cost,
+        );
+        let accepted = !steps.is_empty() && projected.score < baseline.score;
+        
+        let report = SimulationReport {
+            plan_source: plan_source.to_string(),
+            migrations: steps.len(),
+            penalty_avoided: baseline.expected_penalty - projected.expected_penalty,
+            baseline,
+            projected,
+            accepted,
+            simulated_at: Utc::now(),
+        };
+        
+        if report.accepted {
+            debug!(
+                "Simulated {} plan improves score {:.4} -> {:.4}",
+                plan_source, report.baseline.score, report.projected.score
+            );
+        } else {
+            info!(
+                "Discarding {} plan: simulated score {:.4} is not better than status quo {:.4}",
+                plan_source, report.projected.score, report.baseline.score
+            );
+        }
+        
+        report
+    }
+    
+    fn evaluate(
+        &self,
+        snapshot: &ClusterSnapshot,
+        placement: &HashMap<&str, &str>,
+        migration_cost_gb: f64,
+        storage_transfer_gb: f64,
+        disruption_cost: f64,
+    ) -> SimulationOutcome {
+        let mut load: HashMap<&str, (f64, f64, usize)> = snapshot.hosts.iter()
+            .map(|h| {
+                let (vcpus, memory) = snapshot.unmanaged_load(h);
+                let unmanaged_count = (h.vm_count as usize).saturating_sub(snapshot.instances_on(&h.host_id).count());
+                (h.host_id.as_str(), (vcpus, memory, unmanaged_count))
+            })
+            .collect();
+        
+        for instance in &snapshot.instances {
+            let host_id = placement.get(instance.resource_id.as_str()).copied().unwrap_or(instance.host_id.as_str());
+            if let Some(entry) = load.get_mut(host_id) {
+                entry.0 += instance.predicted_vcpu_demand();
+                entry.1 += instance.memory_mb as f64;
+                entry.2 += 1;
+            }
+        }
+        
+        let hosts: Vec<HostProjection> = snapshot.hosts.iter()
+            .filter(|h| h.total_vcpus > 0 && h.total_memory_mb > 0)
+            .map(|h| {
+                let (vcpus, memory, count) = load[h.host_id.as_str()];
+                HostProjection {
+                    host_id: h.host_id.clone(),
+                    vcpu_utilization: vcpus / h.total_vcpus as f64,
+                    memory_utilization: memory / h.total_memory_mb as f64,
+                    instance_count: count,
+                }
+            })
+            .collect();
+        
+        let active: Vec<_> = hosts.iter().filter(|h| h.instance_count > 0).collect();
+        let imbalance = if active.is_empty() {
+            0.0
+        } else {
+            let mean = active.iter().map(|h| h.vcpu_utilization).sum::<f64>() / active.len() as f64;
+            (active.iter().map(|h| (h.vcpu_utilization - mean).powi(2)).sum::<f64>() / active.len() as f64).sqrt()
+        };
+        
+        // Risk grows with predicted utilization above the threshold; memory
+        // overcommit is treated as a hard failure
+        let sla_risk: f64 = hosts.iter()
+            .map(|h| {
+                let cpu_risk = (h.vcpu_utilization - self.config.risk_utilization).max(0.0);
+                let memory_risk = if h.memory_utilization > 1.0 { 1.0 } else { 0.0 };
+                cpu_risk + memory_risk
+            })
+            .sum();
+        
+        let active_fraction = if hosts.is_empty() { 0.0 } else { active.len() as f64 / hosts.len() as f64 };
+        
+        // Instances with a penalty model pay for running on hosts above the risk threshold
+        let risk_percent = self.config.risk_utilization * 100.0;
+        let expected_penalty: f64 = snapshot.instances.iter()
+            .filter_map(|instance| {
+                let penalty = instance.penalty.as_ref()?;
+                let host_id = placement.get(instance.resource_id.as_str()).copied().unwrap_or(instance.host_id.as_str());
+                let host = hosts.iter().find(|h| h.host_id == host_id)?;
+                let (probability, severity) = PenaltyModel::exposure(host.vcpu_utilization * 100.0, risk_percent);
+                Some(penalty.expected_cost(probability, severity, self.config.penalty_horizon_minutes))
+            })
+            .sum();
+        
+        let score = self.config.sla_risk_weight * sla_risk
+            + self.config.imbalance_weight * imbalance
+            + self.config.migration_cost_weight * (migration_cost_gb + storage_transfer_gb)
+            + self.config.active_host_weight * active_fraction
+            + self.config.penalty_weight * (expected_penalty + disruption_cost);
+        
+        SimulationOutcome {
+            active_hosts: active.len(),
+            hosts,
+            imbalance,
+            sla_risk,
+            migration_cost_gb,
+            storage_transfer_gb,
+            expected_penalty,
+            disruption_cost,
+            score,
+        }
+    }
+}
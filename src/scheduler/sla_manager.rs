@@ -1,15 +1,49 @@
+use anyhow::Result;
 use chrono::{DateTime, Utc, Duration};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
 use tracing::{debug, warn};
 
+use crate::config::ErrorBudgetConfig;
+use crate::error::SchedulerError;
+use crate::metrics::latest::MetricsView;
+use crate::metrics::LatestMetrics;
 use super::resource_scheduler::SLAStatus;
 
+// Width of the buckets SLO samples are aggregated into
+const SLO_BUCKET_MINUTES: i64 = 5;
+
 pub struct SLAManager {
     sla_policies: HashMap<String, SLAPolicy>,
     violation_history: HashMap<String, Vec<SLAViolation>>,
+    error_budget_config: ErrorBudgetConfig,
+    slo_samples: HashMap<String, VecDeque<SLOBucket>>,
+    burn_alerts: HashSet<String>,
+    latest_metrics: Arc<LatestMetrics>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct SLOBucket {
+    start: DateTime<Utc>,
+    good: u64,
+    bad: u64,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorBudgetStatus {
+    pub resource_id: String,
+    pub availability_target: f64,
+    pub samples: u64,
+    pub bad_samples: u64,
+    // Fraction of the window's budget left; negative once overspent
+    pub budget_remaining: f64,
+    // Multiples of the rate that would exactly exhaust the budget
+    pub burn_rate: f64,
+    pub burning_fast: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SLAPolicy {
     pub resource_id: String,
     pub max_cpu_utilization: f64,
@@ -18,9 +52,28 @@ pub struct SLAPolicy {
     pub min_availability_percent: f64,
     pub priority: SLAPriority,
     pub deadline_minutes: u32,
+    #[serde(default)]
+    pub penalty: Option<PenaltyModel>,
+    // Collects the resource's metrics this often instead of on the
+    // collector's intervals; picked up at the next discovery
+    #[serde(default)]
+    pub collection_interval_seconds: Option<u64>,
 }
 
-#[derive(Debug, Clone)]
+// Monetary cost of violating the SLA, as a step function of severity
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PenaltyModel {
+    pub tiers: Vec<PenaltyTier>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PenaltyTier {
+    pub min_severity: f64,
+    pub cost_per_minute: f64,
+}
+
+// Ordered most urgent first
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
 pub enum SLAPriority {
     Critical,
     High,
@@ -28,7 +81,7 @@ pub enum SLAPriority {
     Low,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct SLAViolation {
     pub resource_id: String,
     pub violation_type: ViolationType,
@@ -37,7 +90,7 @@ pub struct SLAViolation {
     pub resolved: bool,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub enum ViolationType {
     CpuUtilization,
     MemoryUtilization,
@@ -45,11 +98,71 @@ pub enum ViolationType {
     Availability,
 }
 
+impl PenaltyModel {
+    pub fn cost_per_minute(&self, severity: f64) -> f64 {
+        self.tiers.iter()
+            .filter(|tier| severity >= tier.min_severity)
+            .map(|tier| tier.cost_per_minute)
+            .fold(0.0, f64::max)
+    }
+    
+    // Expected cost over the horizon given the chance of a violation and its severity
+    pub fn expected_cost(&self, probability: f64, severity: f64, horizon_minutes: f64) -> f64 {
+        probability.clamp(0.0, 1.0) * self.cost_per_minute(severity) * horizon_minutes
+    }
+    
+    // Violation likelihood and severity when a utilization limit is overshot
+    pub fn exposure(utilization: f64, limit: f64) -> (f64, f64) {
+        if utilization <= limit || limit <= 0.0 {
+            return (0.0, 0.0);
+        }
+        let probability = ((utilization - limit) / (100.0 - limit).max(f64::EPSILON)).min(1.0);
+        (probability, (utilization - limit) / limit)
+    }
+}
+
+impl SLAPolicy {
+    pub fn validate(&self) -> Result<()> {
+        let invalid = |reason: &str| -> anyhow::Error {
+            SchedulerError::SLAViolation(format!("Invalid SLA policy for {}: {}", self.resource_id, reason)).into()
+        };
+        
+        if self.resource_id.trim().is_empty() {
+            return Err(invalid("resource_id must not be empty"));
+        }
+        if !(0.0..=100.0).contains(&self.max_cpu_utilization) {
+            return Err(invalid("max_cpu_utilization must be between 0 and 100"));
+        }
+        if !(0.0..=100.0).contains(&self.max_memory_utilization) {
+            return Err(invalid("max_memory_utilization must be between 0 and 100"));
+        }
+        if !(0.0..=100.0).contains(&self.min_availability_percent) {
+            return Err(invalid("min_availability_percent must be between 0 and 100"));
+        }
+        if self.deadline_minutes == 0 {
+            return Err(invalid("deadline_minutes must be positive"));
+        }
+        if self.collection_interval_seconds == Some(0) {
+            return Err(invalid("collection_interval_seconds must be positive"));
+        }
+        if let Some(penalty) = &self.penalty {
+            if penalty.tiers.iter().any(|t| t.cost_per_minute < 0.0 || t.min_severity < 0.0) {
+                return Err(invalid("penalty tiers must be non-negative"));
+            }
+        }
+        Ok(())
+    }
+}
+
 impl SLAManager {
-    pub fn new() -> Self {
+    pub fn new(error_budget_config: ErrorBudgetConfig, latest_metrics: Arc<LatestMetrics>) -> Self {
         Self {
             sla_policies: HashMap::new(),
             violation_history: HashMap::new(),
+            error_budget_config,
+            slo_samples: HashMap::new(),
+            burn_alerts: HashSet::new(),
+            latest_metrics,
         }
     }
     
@@ -57,45 +170,76 @@ impl SLAManager {
         debug!("Checking SLA compliance for resource {}", resource_id);
         
         if let Some(policy) = self.sla_policies.get(resource_id) {
-            // Get current metrics for the resource
-            let current_metrics = self.get_current_metrics(resource_id).await;
+            // Get current metrics for the resource; missing or stale values
+            // are skipped rather than assumed healthy or violated
+            let current_metrics = self.get_current_metrics(resource_id);
+            if current_metrics.is_empty() {
+                debug!("No fresh metrics for {}, skipping SLA evaluation", resource_id);
+            }
             
             let mut violations = Vec::new();
             let mut impact_score = 0.0;
             
             // Check CPU utilization
-            if current_metrics.cpu_utilization > policy.max_cpu_utilization {
-                violations.push(ViolationType::CpuUtilization);
-                impact_score += self.calculate_impact_score(
-                    current_metrics.cpu_utilization,
-                    policy.max_cpu_utilization,
-                    &policy.priority
-                );
+            if let Some(cpu_utilization) = current_metrics.cpu_utilization {
+                if cpu_utilization > policy.max_cpu_utilization {
+                    violations.push(ViolationType::CpuUtilization);
+                    impact_score += self.calculate_impact_score(
+                        cpu_utilization,
+                        policy.max_cpu_utilization,
+                        &policy.priority
+                    );
+                }
             }
             
             // Check memory utilization
-            if current_metrics.memory_utilization > policy.max_memory_utilization {
-                violations.push(ViolationType::MemoryUtilization);
-                impact_score += self.calculate_impact_score(
-                    current_metrics.memory_utilization,
-                    policy.max_memory_utilization,
-                    &policy.priority
-                );
+            if let Some(memory_utilization) = current_metrics.memory_utilization {
+                if memory_utilization > policy.max_memory_utilization {
+                    violations.push(ViolationType::MemoryUtilization);
+                    impact_score += self.calculate_impact_score(
+                        memory_utilization,
+                        policy.max_memory_utilization,
+                        &policy.priority
+                    );
+                }
             }
             
             // Check response time
-            if current_metrics.response_time_ms > policy.max_response_time_ms {
-                violations.push(ViolationType::ResponseTime);
-                impact_score += 0.3; // Fixed impact for response time violations
+            if let Some(response_time_ms) = current_metrics.response_time_ms {
+                if response_time_ms > policy.max_response_time_ms {
+                    violations.push(ViolationType::ResponseTime);
+                    impact_score += 0.3; // Fixed impact for response time violations
+                }
+            }
+            
+            // Check availability
+            if let Some(availability_percent) = current_metrics.availability_percent {
+                if availability_percent < policy.min_availability_percent {
+                    violations.push(ViolationType::Availability);
+                    impact_score += 0.5; // Fixed impact for outages
+                }
             }
             
             // Determine if critical based on priority and violations
             let is_critical = matches!(policy.priority, SLAPriority::Critical) && !violations.is_empty();
             
+            // SLO sample: available and responsive enough, only counted when
+            // at least one of the two was actually observed
+            let slo_met = if current_metrics.availability_percent.is_none() && current_metrics.response_time_ms.is_none() {
+                None
+            } else {
+                Some(
+                    current_metrics.availability_percent.map(|a| a >= policy.min_availability_percent).unwrap_or(true)
+                        && current_metrics.response_time_ms.map(|r| r <= policy.max_response_time_ms).unwrap_or(true)
+                )
+            };
+            
             SLAStatus {
                 is_critical,
                 impact_score,
                 deadline_minutes: policy.deadline_minutes,
+                violations,
+                slo_met,
             }
         } else {
             // No SLA policy defined - use default
@@ -103,6 +247,8 @@ impl SLAManager {
                 is_critical: false,
                 impact_score: 0.0,
                 deadline_minutes: 60,
+                violations: Vec::new(),
+                slo_met: None,
             }
         }
     }
@@ -111,13 +257,160 @@ impl SLAManager {
         self.sla_policies.insert(policy.resource_id.clone(), policy);
     }
     
-    pub fn record_violation(&mut self, violation: SLAViolation) {
-        warn!("SLA violation recorded: {:?}", violation);
+    pub fn remove_sla_policy(&mut self, resource_id: &str) -> Option<SLAPolicy> {
+        self.slo_samples.remove(resource_id);
+        self.burn_alerts.remove(resource_id);
+        self.sla_policies.remove(resource_id)
+    }
+    
+    // Returns the budget status when this sample starts a fast burn, so the
+    // caller alerts once per episode
+    pub fn record_slo_sample(&mut self, resource_id: &str, good: bool, now: DateTime<Utc>) -> Option<ErrorBudgetStatus> {
+        let bucket_seconds = SLO_BUCKET_MINUTES * 60;
+        let bucket_start = DateTime::from_timestamp(now.timestamp() - now.timestamp().rem_euclid(bucket_seconds), 0)
+            .unwrap_or(now);
+        let cutoff = now - Duration::hours(self.error_budget_config.window_hours);
         
-        self.violation_history
+        let buckets = self.slo_samples.entry(resource_id.to_string()).or_default();
+        match buckets.back_mut() {
+            Some(bucket) if bucket.start == bucket_start => {
+                if good { bucket.good += 1 } else { bucket.bad += 1 }
+            }
+            _ => buckets.push_back(SLOBucket {
+                start: bucket_start,
+                good: good as u64,
+                bad: !good as u64,
+            }),
+        }
+        while buckets.front().map(|b| b.start < cutoff).unwrap_or(false) {
+            buckets.pop_front();
+        }
+        
+        let status = self.error_budget(resource_id, now)?;
+        if status.burning_fast {
+            if self.burn_alerts.insert(resource_id.to_string()) {
+                warn!(
+                    "Error budget for {} burning at {:.1}x, {:.1}% left",
+                    resource_id,
+                    status.burn_rate,
+                    status.budget_remaining * 100.0
+                );
+                return Some(status);
+            }
+        } else {
+            self.burn_alerts.remove(resource_id);
+        }
+        None
+    }
+    
+    pub fn error_budget(&self, resource_id: &str, now: DateTime<Utc>) -> Option<ErrorBudgetStatus> {
+        let policy = self.sla_policies.get(resource_id)?;
+        let buckets = self.slo_samples.get(resource_id)?;
+        
+        // Allowed fraction of bad samples
+        let allowed = (1.0 - policy.min_availability_percent / 100.0).max(1e-6);
+        
+        let window_start = now - Duration::hours(self.error_budget_config.window_hours);
+        let burn_start = now - Duration::minutes(self.error_budget_config.burn_window_minutes);
+        let (mut good, mut bad, mut recent_good, mut recent_bad) = (0, 0, 0, 0);
+        for bucket in buckets.iter().filter(|b| b.start >= window_start) {
+            good += bucket.good;
+            bad += bucket.bad;
+            if bucket.start >= burn_start {
+                recent_good += bucket.good;
+                recent_bad += bucket.bad;
+            }
+        }
+        
+        let samples = good + bad;
+        if samples == 0 {
+            return None;
+        }
+        
+        let budget_remaining = 1.0 - (bad as f64 / samples as f64) / allowed;
+        let recent = recent_good + recent_bad;
+        let burn_rate = if recent == 0 { 0.0 } else { (recent_bad as f64 / recent as f64) / allowed };
+        
+        Some(ErrorBudgetStatus {
+            resource_id: resource_id.to_string(),
+            availability_target: policy.min_availability_percent,
+            samples,
+            bad_samples: bad,
+            budget_remaining,
+            burn_rate,
+            burning_fast: burn_rate >= self.error_budget_config.fast_burn_rate,
+        })
+    }
+    
+    pub fn error_budgets(&self, now: DateTime<Utc>) -> Vec<ErrorBudgetStatus> {
+        let mut budgets: Vec<_> = self.sla_policies.keys()
+            .filter_map(|resource_id| self.error_budget(resource_id, now))
+            .collect();
+        budgets.sort_by(|a, b| a.budget_remaining.partial_cmp(&b.budget_remaining).unwrap());
+        budgets
+    }
+    
+    pub fn get_sla_policy(&self, resource_id: &str) -> Option<&SLAPolicy> {
+        self.sla_policies.get(resource_id)
+    }
+    
+    // Expected penalty if the resource runs at the predicted CPU utilization
+    pub fn expected_penalty(&self, resource_id: &str, predicted_cpu: f64, horizon_minutes: f64) -> f64 {
+        let policy = match self.sla_policies.get(resource_id) {
+            Some(policy) => policy,
+            None => return 0.0,
+        };
+        let penalty = match &policy.penalty {
+            Some(penalty) => penalty,
+            None => return 0.0,
+        };
+        
+        let (probability, severity) = PenaltyModel::exposure(predicted_cpu, policy.max_cpu_utilization);
+        penalty.expected_cost(probability, severity, horizon_minutes)
+    }
+    
+    pub fn list_sla_policies(&self) -> Vec<SLAPolicy> {
+        let mut policies: Vec<_> = self.sla_policies.values().cloned().collect();
+        policies.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+        policies
+    }
+    
+    // Returns false if the same violation is already open for the resource
+    pub fn record_violation(&mut self, violation: SLAViolation) -> bool {
+        let history = self.violation_history
             .entry(violation.resource_id.clone())
-            .or_insert_with(Vec::new)
-            .push(violation);
+            .or_insert_with(Vec::new);
+        
+        if history.iter().any(|v| !v.resolved && v.violation_type == violation.violation_type) {
+            return false;
+        }
+        
+        warn!("SLA violation recorded: {:?}", violation);
+        history.push(violation);
+        true
+    }
+    
+    // Close open violations that are no longer observed
+    pub fn resolve_violations(&mut self, resource_id: &str, active: &[ViolationType]) {
+        if let Some(history) = self.violation_history.get_mut(resource_id) {
+            for violation in history.iter_mut().filter(|v| !v.resolved) {
+                if !active.contains(&violation.violation_type) {
+                    debug!("SLA violation {:?} resolved for {}", violation.violation_type, resource_id);
+                    violation.resolved = true;
+                }
+            }
+        }
+    }
+    
+    // Violations opened in [from, to) across all resources, oldest first
+    pub fn violations_between(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<SLAViolation> {
+        let mut violations: Vec<SLAViolation> = self.violation_history.values()
+            .flatten()
+            .filter(|v| v.timestamp >= from && v.timestamp < to)
+            .cloned()
+            .collect();
+        violations.sort_by_key(|v| v.timestamp);
+        violations
     }
     
     pub fn get_violation_history(&self, resource_id: &str) -> Vec<&SLAViolation> {
@@ -147,14 +440,8 @@ impl SLAManager {
         }
     }
     
-    async fn get_current_metrics(&self, _resource_id: &str) -> ResourceMetrics {
-        // Mock implementation - would get actual metrics from monitoring system
-        ResourceMetrics {
-            cpu_utilization: 45.0,
-            memory_utilization: 60.0,
-            response_time_ms: 150,
-            availability_percent: 99.5,
-        }
+    fn get_current_metrics(&self, resource_id: &str) -> MetricsView {
+        self.latest_metrics.view(resource_id, Utc::now())
     }
     
     fn calculate_impact_score(&self, current: f64, threshold: f64, priority: &SLAPriority) -> f64 {
@@ -170,16 +457,8 @@ impl SLAManager {
     }
 }
 
-#[derive(Debug)]
-struct ResourceMetrics {
-    cpu_utilization: f64,
-    memory_utilization: f64,
-    response_time_ms: u64,
-    availability_percent: f64,
-}
-
 impl Default for SLAManager {
     fn default() -> Self {
-        Self::new()
+        Self::new(ErrorBudgetConfig::default(), Arc::new(LatestMetrics::new(120)))
     }
 }
@@ -0,0 +1,150 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use sha2::Sha256;
+use std::time::Duration;
+use tracing::{debug, error};
+
+use crate::config::SLAWebhookConfig;
+use crate::error::{DeliveryError, RetryPolicy};
+use super::sla_manager::{ErrorBudgetStatus, SLAPolicy, SLAViolation, ViolationType};
+
+// Pushes newly opened SLA violations and error budget burn alerts to external
+// webhooks. Payloads are signed
+// with HMAC-SHA256 over "<timestamp>.<body>" so receivers can verify origin
+// and reject replays.
+pub struct SLANotifier {
+    webhooks: Vec<SLAWebhookConfig>,
+    http_client: HttpClient,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ViolationPayload {
+    pub event: &'static str,
+    pub resource_id: String,
+    pub violation_type: ViolationType,
+    pub severity: f64,
+    pub priority: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BudgetBurnPayload {
+    pub event: &'static str,
+    #[serde(flatten)]
+    pub status: ErrorBudgetStatus,
+    pub priority: Option<String>,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl SLANotifier {
+    pub fn new(webhooks: Vec<SLAWebhookConfig>) -> Result<Self> {
+        let http_client = HttpClient::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        
+        Ok(Self {
+            webhooks,
+            http_client,
+        })
+    }
+    
+    pub fn notify(&self, violation: &SLAViolation, policy: Option<&SLAPolicy>) {
+        let priority = policy.map(|p| format!("{:?}", p.priority));
+        let payload = ViolationPayload {
+            event: "sla_violation",
+            resource_id: violation.resource_id.clone(),
+            violation_type: violation.violation_type.clone(),
+            severity: violation.severity,
+            priority: priority.clone(),
+            occurred_at: violation.timestamp,
+        };
+        
+        self.dispatch(&violation.resource_id, violation.severity, priority.as_deref(), &payload);
+    }
+    
+    pub fn notify_budget_burn(&self, status: &ErrorBudgetStatus, policy: Option<&SLAPolicy>) {
+        let priority = policy.map(|p| format!("{:?}", p.priority));
+        let payload = BudgetBurnPayload {
+            event: "error_budget_burn",
+            status: status.clone(),
+            priority: priority.clone(),
+            occurred_at: Utc::now(),
+        };
+        
+        // Burn alerts always pass severity filters
+        self.dispatch(&status.resource_id, f64::INFINITY, priority.as_deref(), &payload);
+    }
+    
+    // Fire-and-forget: each matching hook is delivered on its own task
+    fn dispatch<T: Serialize>(&self, resource_id: &str, severity: f64, priority: Option<&str>, payload: &T) {
+        let body = match serde_json::to_string(payload) {
+            Ok(body) => body,
+            Err(e) => {
+                error!("Failed to serialize SLA webhook payload: {}", e);
+                return;
+            }
+        };
+        
+        for webhook in self.webhooks.iter().filter(|w| Self::matches(w, resource_id, severity, priority)) {
+            let webhook = webhook.clone();
+            let http_client = self.http_client.clone();
+            let body = body.clone();
+            tokio::spawn(async move {
+                if let Err(e) = Self::deliver(&http_client, &webhook, &body).await {
+                    error!("Giving up on SLA webhook {}: {}", webhook.url, e);
+                }
+            });
+        }
+    }
+    
+    fn matches(webhook: &SLAWebhookConfig, resource_id: &str, severity: f64, priority: Option<&str>) -> bool {
+        let priority_matches = webhook.priorities.is_empty()
+            || priority.map(|p| webhook.priorities.iter().any(|w| w.eq_ignore_ascii_case(p))).unwrap_or(false);
+        let resource_matches = webhook.resource_ids.is_empty()
+            || webhook.resource_ids.iter().any(|id| id == resource_id);
+        
+        priority_matches && resource_matches && severity >= webhook.min_severity
+    }
+    
+    async fn deliver(http_client: &HttpClient, webhook: &SLAWebhookConfig, body: &str) -> Result<()> {
+        let retry = RetryPolicy::new(webhook.max_retries + 1, Duration::from_millis(webhook.initial_backoff_ms));
+        
+        retry.run(&format!("SLA webhook {}", webhook.url), || async {
+            // Signed per attempt, so receivers checking the timestamp for
+            // replays don't reject a late retry
+            let timestamp = Utc::now().timestamp().to_string();
+            let signature = Self::sign(&webhook.secret, &timestamp, body)
+                .map_err(|e| DeliveryError::Permanent(e.to_string()))?;
+            
+            Self::post(http_client
+                .post(&webhook.url)
+                .header("Content-Type", "application/json")
+                .header("X-Signature-Timestamp", &timestamp)
+                .header("X-Signature-256", format!("sha256={}", signature))
+                .body(body.to_string())
+            ).await
+        }).await?;
+        
+        debug!("Delivered SLA violation to {}", webhook.url);
+        Ok(())
+    }
+    
+    pub async fn post(request: reqwest::RequestBuilder) -> Result<(), DeliveryError> {
+        let response = request.send().await?;
+        if response.status().is_success() {
+            return Ok(());
+        }
+        Err(DeliveryError::from_status(response.status().as_u16(), response.headers()))
+    }
+    
+    pub fn sign(secret: &str, timestamp: &str, body: &str) -> Result<String> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes())?;
+        mac.update(timestamp.as_bytes());
+        mac.update(b".");
+        mac.update(body.as_bytes());
+        Ok(hex::encode(mac.finalize().into_bytes()))
+    }
+}
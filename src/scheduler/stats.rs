@@ -0,0 +1,119 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+use tokio::sync::RwLock;
+
+// Weight of the newest sample in the running averages
+const SMOOTHING: f64 = 0.2;
+
+// Operational counters about the scheduler itself, mirrored to Prometheus
+pub struct SchedulerStats {
+    inner: RwLock<SchedulerPerformance>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionCounts {
+    pub succeeded: u64,
+    pub failed: u64,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SchedulerPerformance {
+    pub cycles: u64,
+    pub failed_cycles: u64,
+    pub last_cycle_decisions: usize,
+    pub avg_decisions_per_cycle: f64,
+    pub last_cycle_duration_ms: f64,
+    pub avg_cycle_duration_ms: f64,
+    pub actions: HashMap<String, ActionCounts>,
+    pub migrations: u64,
+    pub avg_migration_duration_ms: f64,
+    pub queue_depth: usize,
+    pub deadline_misses: u64,
+    pub energy_plans: u64,
+    pub estimated_kwh_saved: f64,
+    pub last_plan_kwh_saved: Option<f64>,
+}
+
+impl SchedulerStats {
+    pub fn new() -> Self {
+        Self {
+            inner: RwLock::new(SchedulerPerformance::default()),
+        }
+    }
+    
+    pub async fn record_cycle(&self, duration: Duration, decisions: usize, succeeded: bool) {
+        let mut stats = self.inner.write().await;
+        let duration_ms = duration.as_secs_f64() * 1000.0;
+        let first = stats.cycles == 0;
+        stats.cycles += 1;
+        if !succeeded {
+            stats.failed_cycles += 1;
+            ::metrics::counter!("scheduler_cycle_failures_total").increment(1);
+        }
+        stats.last_cycle_decisions = decisions;
+        stats.last_cycle_duration_ms = duration_ms;
+        stats.avg_decisions_per_cycle = smooth(stats.avg_decisions_per_cycle, decisions as f64, first);
+        stats.avg_cycle_duration_ms = smooth(stats.avg_cycle_duration_ms, duration_ms, first);
+        
+        ::metrics::counter!("scheduler_cycles_total").increment(1);
+        ::metrics::histogram!("scheduler_cycle_duration_seconds").record(duration.as_secs_f64());
+        ::metrics::histogram!("scheduler_decisions_per_cycle").record(decisions as f64);
+    }
+    
+    pub async fn record_action(&self, action: &'static str, succeeded: bool) {
+        let mut stats = self.inner.write().await;
+        let counts = stats.actions.entry(action.to_string()).or_default();
+        if succeeded {
+            counts.succeeded += 1;
+        } else {
+            counts.failed += 1;
+        }
+        
+        let result = if succeeded { "success" } else { "failure" };
+        ::metrics::counter!("scheduler_actions_total", "action" => action, "result" => result).increment(1);
+    }
+    
+    pub async fn record_migration(&self, duration: Duration) {
+        let mut stats = self.inner.write().await;
+        let first = stats.migrations == 0;
+        stats.migrations += 1;
+        stats.avg_migration_duration_ms = smooth(
+            stats.avg_migration_duration_ms,
+            duration.as_secs_f64() * 1000.0,
+            first,
+        );
+        ::metrics::histogram!("scheduler_migration_duration_seconds").record(duration.as_secs_f64());
+    }
+    
+    // Estimated saving of the hosts a power cycle turned off
+    pub async fn record_energy_savings(&self, kwh: f64) {
+        let mut stats = self.inner.write().await;
+        stats.energy_plans += 1;
+        stats.estimated_kwh_saved += kwh;
+        stats.last_plan_kwh_saved = Some(kwh);
+        ::metrics::gauge!("scheduler_estimated_energy_saved_kwh").increment(kwh);
+    }
+    
+    // Queue depth and deadline misses live in the decision queue
+    pub async fn snapshot(&self, queue_depth: usize, deadline_misses: u64) -> SchedulerPerformance {
+        let mut stats = self.inner.read().await.clone();
+        stats.queue_depth = queue_depth;
+        stats.deadline_misses = deadline_misses;
+        stats
+    }
+}
+
+impl Default for SchedulerStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn smooth(average: f64, sample: f64, first: bool) -> f64 {
+    if first {
+        sample
+    } else {
+        average + SMOOTHING * (sample - average)
+    }
+}
@@ -0,0 +1,121 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::config::StorageLocalityConfig;
+use crate::openstack::services::Volume;
+
+// Which compute hosts can reach which Cinder backends, and which hosts share
+// instance storage, so migrations can be priced by the data they move
+#[derive(Debug, Clone, Default)]
+pub struct StorageTopology {
+    backend_hosts: HashMap<String, HashSet<String>>,
+    shared_ephemeral_hosts: HashSet<String>,
+    allow_cross_backend: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct AttachedVolume {
+    pub volume_id: String,
+    // None when Cinder doesn't expose the backend to us
+    pub backend: Option<String>,
+    pub size_gb: u64,
+    pub bootable: bool,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct InstanceStorage {
+    // Ephemeral disk from the flavor, only relevant when not booted from a volume
+    pub local_disk_gb: u64,
+    pub volumes: Vec<AttachedVolume>,
+}
+
+impl InstanceStorage {
+    pub fn new(local_disk_gb: u64, volumes: &[Volume]) -> Self {
+        Self {
+            local_disk_gb,
+            volumes: volumes.iter()
+                .map(|v| AttachedVolume {
+                    volume_id: v.id.clone(),
+                    backend: v.backend().map(str::to_string),
+                    size_gb: v.size,
+                    bootable: v.is_bootable(),
+                })
+                .collect(),
+        }
+    }
+    
+    pub fn is_volume_backed(&self) -> bool {
+        self.volumes.iter().any(|v| v.bootable)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MigrationKind {
+    // Only memory is copied
+    SharedStorage,
+    // Local disks are copied along with memory
+    BlockMigration,
+    // Volumes would have to be migrated to another Cinder backend first
+    CrossBackend,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StorageMigration {
+    pub kind: MigrationKind,
+    pub transfer_gb: f64,
+    // Volumes that would need a Cinder migration to follow the instance
+    pub cross_backend_volumes: Vec<String>,
+    pub feasible: bool,
+}
+
+impl StorageTopology {
+    pub fn from_config(config: &StorageLocalityConfig) -> Self {
+        Self {
+            backend_hosts: config.backend_hosts.iter()
+                .map(|(backend, hosts)| (backend.clone(), hosts.iter().cloned().collect()))
+                .collect(),
+            shared_ephemeral_hosts: config.shared_ephemeral_hosts.iter().cloned().collect(),
+            allow_cross_backend: config.allow_cross_backend,
+        }
+    }
+    
+    pub fn classify(&self, storage: &InstanceStorage, source_host: &str, target_host: &str) -> StorageMigration {
+        let local_gb = if storage.is_volume_backed()
+            || (self.shared_ephemeral_hosts.contains(source_host) && self.shared_ephemeral_hosts.contains(target_host))
+        {
+            0.0
+        } else {
+            storage.local_disk_gb as f64
+        };
+        
+        let unreachable: Vec<_> = storage.volumes.iter()
+            .filter(|v| !self.reachable(v.backend.as_deref(), target_host))
+            .collect();
+        let unreachable_gb: f64 = unreachable.iter().map(|v| v.size_gb as f64).sum();
+        let crosses_backend = !unreachable.is_empty();
+        
+        let kind = if crosses_backend {
+            MigrationKind::CrossBackend
+        } else if local_gb > 0.0 {
+            MigrationKind::BlockMigration
+        } else {
+            MigrationKind::SharedStorage
+        };
+        
+        StorageMigration {
+            kind,
+            transfer_gb: local_gb + unreachable_gb,
+            cross_backend_volumes: unreachable.iter().map(|v| v.volume_id.clone()).collect(),
+            feasible: !crosses_backend || self.allow_cross_backend,
+        }
+    }
+    
+    // Backends without a host list are assumed to be reachable everywhere
+    fn reachable(&self, backend: Option<&str>, host: &str) -> bool {
+        backend
+            .and_then(|b| self.backend_hosts.get(b))
+            .map(|hosts| hosts.contains(host))
+            .unwrap_or(true)
+    }
+}
@@ -0,0 +1,137 @@
+use anyhow::Result;
+use dashmap::DashMap;
+use std::collections::HashMap;
+use tracing::debug;
+
+use crate::config::TrafficAffinityConfig;
+use crate::openstack::Clouds;
+
+// Communication intensity between instance pairs, learned from port-level
+// flow records, so chatty instances can be kept on the same host or rack
+pub struct TrafficMatrix {
+    config: TrafficAffinityConfig,
+    clouds: Clouds,
+    // Smoothed bytes/s per unordered instance pair
+    pairs: DashMap<(String, String), f64>,
+}
+
+// Read-only copy of the matrix handed to placement and planners
+#[derive(Debug, Clone, Default)]
+pub struct TrafficHints {
+    peers: HashMap<String, Vec<(String, f64)>>,
+    host_racks: HashMap<String, String>,
+    same_rack_weight: f64,
+}
+
+impl TrafficHints {
+    pub fn is_empty(&self) -> bool {
+        self.peers.is_empty()
+    }
+    
+    // Traffic the instance would keep off the network by running on the host:
+    // full weight for peers on the same host, discounted for the same rack.
+    // `locate` gives each peer's host, which may differ from the snapshot
+    // while a plan is being built
+    pub fn affinity<F>(&self, resource_id: &str, host_id: &str, locate: F) -> f64
+    where
+        F: Fn(&str) -> Option<String>,
+    {
+        let rack = self.host_racks.get(host_id);
+        self.peers.get(resource_id)
+            .map(|peers| {
+                peers.iter()
+                    .filter_map(|(peer, rate)| locate(peer).map(|peer_host| (peer_host, rate)))
+                    .map(|(peer_host, rate)| {
+                        if peer_host == host_id {
+                            *rate
+                        } else if rack.is_some() && self.host_racks.get(&peer_host) == rack {
+                            rate * self.same_rack_weight
+                        } else {
+                            0.0
+                        }
+                    })
+                    .sum()
+            })
+            .unwrap_or(0.0)
+    }
+}
+
+impl TrafficMatrix {
+    pub fn new(config: TrafficAffinityConfig, clouds: Clouds) -> Self {
+        Self {
+            config,
+            clouds,
+            pairs: DashMap::new(),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Folds the latest flow records of every region into the smoothed pair
+    // rates; pairs that stopped talking decay away
+    pub async fn refresh(&self) -> Result<()> {
+        let mut ports = Vec::new();
+        let mut flows = Vec::new();
+        for client in self.clouds.regions() {
+            ports.extend(client.neutron.list_ports().await?);
+            flows.extend(client.neutron.get_flow_metrics().await?);
+        }
+        let owners: HashMap<&str, &str> = ports.iter()
+            .filter(|p| p.is_compute() && !p.device_id.is_empty())
+            .map(|p| (p.id.as_str(), p.device_id.as_str()))
+            .collect();
+        
+        let mut observed: HashMap<(String, String), f64> = HashMap::new();
+        for flow in &flows {
+            let (source, destination) = match (
+                owners.get(flow.source_port_id.as_str()),
+                owners.get(flow.destination_port_id.as_str()),
+            ) {
+                (Some(source), Some(destination)) if source != destination => (*source, *destination),
+                _ => continue,
+            };
+            let rate = flow.bytes as f64 / flow.duration_seconds.max(1) as f64;
+            *observed.entry(pair_key(source, destination)).or_default() += rate;
+        }
+        
+        let alpha = self.config.smoothing;
+        for mut entry in self.pairs.iter_mut() {
+            let sample = observed.remove(entry.key()).unwrap_or(0.0);
+            *entry.value_mut() += alpha * (sample - *entry.value());
+        }
+        for (pair, rate) in observed {
+            self.pairs.insert(pair, rate);
+        }
+        self.pairs.retain(|_, rate| *rate >= self.config.min_bytes_per_second);
+        
+        debug!("Traffic matrix tracks {} communicating instance pairs", self.pairs.len());
+        Ok(())
+    }
+    
+    pub fn hints(&self) -> TrafficHints {
+        let mut peers: HashMap<String, Vec<(String, f64)>> = HashMap::new();
+        for entry in self.pairs.iter() {
+            let ((a, b), rate) = (entry.key(), *entry.value());
+            peers.entry(a.clone()).or_default().push((b.clone(), rate));
+            peers.entry(b.clone()).or_default().push((a.clone(), rate));
+        }
+        
+        TrafficHints {
+            peers,
+            host_racks: self.config.racks.iter()
+                .flat_map(|(rack, hosts)| hosts.iter().map(move |host| (host.clone(), rack.clone())))
+                .collect(),
+            same_rack_weight: self.config.same_rack_weight,
+        }
+    }
+}
+
+fn pair_key(a: &str, b: &str) -> (String, String) {
+    if a <= b {
+        (a.to_string(), b.to_string())
+    } else {
+        (b.to_string(), a.to_string())
+    }
+}
@@ -0,0 +1,114 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use serde_json::Value;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use tokio::fs;
+use tracing::{info, warn};
+
+use crate::error::StorageError;
+use super::DocumentStore;
+
+// One directory per collection, one JSON file per document
+pub struct FileStore {
+    root: PathBuf,
+}
+
+impl FileStore {
+    pub async fn new(root: &str) -> Result<Self> {
+        fs::create_dir_all(root).await.map_err(|e| {
+            StorageError::BackendError(format!("Cannot create storage directory {}: {}", root, e))
+        })?;
+        
+        info!("File storage backend rooted at {}", root);
+        
+        Ok(Self {
+            root: PathBuf::from(root),
+        })
+    }
+    
+    fn validate(name: &str) -> Result<()> {
+        let valid = !name.is_empty()
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+            && !name.starts_with('.');
+        
+        if !valid {
+            return Err(StorageError::InvalidKey(name.to_string()).into());
+        }
+        Ok(())
+    }
+    
+    fn collection_dir(&self, collection: &str) -> Result<PathBuf> {
+        Self::validate(collection)?;
+        Ok(self.root.join(collection))
+    }
+    
+    fn document_path(&self, collection: &str, key: &str) -> Result<PathBuf> {
+        Self::validate(key)?;
+        Ok(self.collection_dir(collection)?.join(format!("{}.json", key)))
+    }
+    
+    async fn read_document(path: &Path) -> Result<Option<Value>> {
+        match fs::read(path).await {
+            Ok(bytes) => Ok(Some(serde_json::from_slice(&bytes)?)),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[async_trait]
+impl DocumentStore for FileStore {
+    async fn get(&self, collection: &str, key: &str) -> Result<Option<Value>> {
+        Self::read_document(&self.document_path(collection, key)?).await
+    }
+    
+    async fn put(&self, collection: &str, key: &str, value: Value) -> Result<()> {
+        let path = self.document_path(collection, key)?;
+        fs::create_dir_all(self.collection_dir(collection)?).await?;
+        
+        // Write to a temporary file first so readers never see a partial document
+        let tmp_path = path.with_extension("json.tmp");
+        fs::write(&tmp_path, serde_json::to_vec_pretty(&value)?).await?;
+        fs::rename(&tmp_path, &path).await?;
+        Ok(())
+    }
+    
+    async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        match fs::remove_file(self.document_path(collection, key)?).await {
+            Ok(()) => Ok(true),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+            Err(e) => Err(e.into()),
+        }
+    }
+    
+    async fn list(&self, collection: &str) -> Result<Vec<(String, Value)>> {
+        let dir = self.collection_dir(collection)?;
+        let mut entries = match fs::read_dir(&dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        
+        let mut documents = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path.extension().and_then(|e| e.to_str()) != Some("json") {
+                continue;
+            }
+            let key = match path.file_stem().and_then(|s| s.to_str()) {
+                Some(key) => key.to_string(),
+                None => continue,
+            };
+            
+            match Self::read_document(&path).await {
+                Ok(Some(value)) => documents.push((key, value)),
+                Ok(None) => {}
+                Err(e) => warn!("Skipping unreadable document {}: {}", path.display(), e),
+            }
+        }
+        
+        documents.sort_by(|a, b| a.0.cmp(&b.0));
+        Ok(documents)
+    }
+}
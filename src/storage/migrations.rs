@@ -0,0 +1,70 @@
+use anyhow::Result;
+use chrono::Utc;
+use sqlx::{AnyPool, Row};
+use tracing::info;
+
+// Schema changes for the SQL backends, applied in order on connect. Append
+// only: an applied migration must never change. Statements stay within what
+// sqlite and Postgres both accept
+pub struct Migration {
+    pub version: i64,
+    pub name: &'static str,
+    pub statements: &'static [&'static str],
+}
+
+pub const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        name: "documents",
+        statements: &[
+            "CREATE TABLE IF NOT EXISTS documents (
+                collection TEXT NOT NULL,
+                id TEXT NOT NULL,
+                body TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                PRIMARY KEY (collection, id)
+            )",
+        ],
+    },
+];
+
+// Instances starting together may race through the same migration; every
+// statement tolerates having already run
+pub async fn run(pool: &AnyPool) -> Result<()> {
+    sqlx::query(
+        "CREATE TABLE IF NOT EXISTS schema_migrations (
+            version BIGINT PRIMARY KEY,
+            name TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+    )
+    .execute(pool)
+    .await?;
+    
+    let applied: Vec<i64> = sqlx::query("SELECT version FROM schema_migrations")
+        .fetch_all(pool)
+        .await?
+        .iter()
+        .map(|row| row.try_get("version"))
+        .collect::<Result<_, _>>()?;
+    
+    for migration in MIGRATIONS.iter().filter(|m| !applied.contains(&m.version)) {
+        info!("Applying storage migration {} ({})", migration.version, migration.name);
+        let mut tx = pool.begin().await?;
+        for statement in migration.statements {
+            sqlx::query(statement).execute(&mut *tx).await?;
+        }
+        sqlx::query(
+            "INSERT INTO schema_migrations (version, name, applied_at) VALUES ($1, $2, $3)
+             ON CONFLICT (version) DO NOTHING",
+        )
+        .bind(migration.version)
+        .bind(migration.name)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,62 @@
+pub mod file;
+pub mod migrations;
+pub mod sql;
+
+use anyhow::Result;
+use async_trait::async_trait;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::Value;
+use std::sync::Arc;
+
+use crate::config::{StorageBackend, StorageConfig};
+use self::file::FileStore;
+use self::sql::SqlStore;
+
+// Backend-agnostic store of JSON documents grouped into named collections
+#[async_trait]
+pub trait DocumentStore: Send + Sync {
+    async fn get(&self, collection: &str, key: &str) -> Result<Option<Value>>;
+    async fn put(&self, collection: &str, key: &str, value: Value) -> Result<()>;
+    // Returns whether a document was removed
+    async fn delete(&self, collection: &str, key: &str) -> Result<bool>;
+    async fn list(&self, collection: &str) -> Result<Vec<(String, Value)>>;
+}
+
+// Typed access to the configured backend, shared by every subsystem
+#[derive(Clone)]
+pub struct Storage {
+    backend: Arc<dyn DocumentStore>,
+}
+
+impl Storage {
+    pub async fn from_config(config: &StorageConfig) -> Result<Self> {
+        let backend: Arc<dyn DocumentStore> = match config.backend {
+            StorageBackend::File => Arc::new(FileStore::new(&config.path).await?),
+            StorageBackend::Sqlite | StorageBackend::Postgres => Arc::new(SqlStore::connect(config).await?),
+        };
+        
+        Ok(Self { backend })
+    }
+    
+    pub async fn get<T: DeserializeOwned>(&self, collection: &str, key: &str) -> Result<Option<T>> {
+        match self.backend.get(collection, key).await? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+    
+    pub async fn put<T: Serialize>(&self, collection: &str, key: &str, value: &T) -> Result<()> {
+        self.backend.put(collection, key, serde_json::to_value(value)?).await
+    }
+    
+    pub async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        self.backend.delete(collection, key).await
+    }
+    
+    pub async fn list<T: DeserializeOwned>(&self, collection: &str) -> Result<Vec<T>> {
+        self.backend.list(collection).await?
+            .into_iter()
+            .map(|(_, value)| Ok(serde_json::from_value(value)?))
+            .collect()
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+use async_trait::async_trait;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::any::AnyPoolOptions;
+use sqlx::{AnyPool, Row};
+use tracing::info;
+
+use crate::config::StorageConfig;
+use crate::error::StorageError;
+use super::migrations;
+use super::DocumentStore;
+
+// Every collection in one table of a SQL database: sqlite for development,
+// Postgres for production. Documents are stored as JSON text so both
+// databases share the schema
+pub struct SqlStore {
+    pool: AnyPool,
+}
+
+impl SqlStore {
+    pub async fn connect(config: &StorageConfig) -> Result<Self> {
+        let url = config.url.as_deref().ok_or_else(|| {
+            StorageError::BackendError("storage.url is required for SQL backends".to_string())
+        })?;
+        
+        sqlx::any::install_default_drivers();
+        let pool = AnyPoolOptions::new()
+            .max_connections(config.max_connections)
+            .connect(url)
+            .await
+            .map_err(|e| StorageError::BackendError(format!("Cannot connect to {:?} storage: {}", config.backend, e)))?;
+        migrations::run(&pool).await?;
+        
+        info!("{:?} storage backend connected", config.backend);
+        Ok(Self { pool })
+    }
+}
+
+#[async_trait]
+impl DocumentStore for SqlStore {
+    async fn get(&self, collection: &str, key: &str) -> Result<Option<Value>> {
+        let row = sqlx::query("SELECT body FROM documents WHERE collection = $1 AND id = $2")
+            .bind(collection)
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await?;
+        
+        match row {
+            Some(row) => Ok(Some(serde_json::from_str(&row.try_get::<String, _>("body")?)?)),
+            None => Ok(None),
+        }
+    }
+    
+    async fn put(&self, collection: &str, key: &str, value: Value) -> Result<()> {
+        sqlx::query(
+            "INSERT INTO documents (collection, id, body, updated_at) VALUES ($1, $2, $3, $4)
+             ON CONFLICT (collection, id) DO UPDATE SET body = excluded.body, updated_at = excluded.updated_at",
+        )
+        .bind(collection)
+        .bind(key)
+        .bind(serde_json::to_string(&value)?)
+        .bind(Utc::now().to_rfc3339())
+        .execute(&self.pool)
+        .await?;
+        Ok(())
+    }
+    
+    async fn delete(&self, collection: &str, key: &str) -> Result<bool> {
+        let result = sqlx::query("DELETE FROM documents WHERE collection = $1 AND id = $2")
+            .bind(collection)
+            .bind(key)
+            .execute(&self.pool)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+    
+    async fn list(&self, collection: &str) -> Result<Vec<(String, Value)>> {
+        let rows = sqlx::query("SELECT id, body FROM documents WHERE collection = $1 ORDER BY id")
+            .bind(collection)
+            .fetch_all(&self.pool)
+            .await?;
+        
+        rows.iter()
+            .map(|row| {
+                let body: String = row.try_get("body")?;
+                Ok((row.try_get("id")?, serde_json::from_str::<Value>(&body)?))
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,88 @@
+use anyhow::{Context, Result};
+use std::fs;
+use std::os::linux::net::SocketAddrExt;
+use std::os::unix::net::{SocketAddr, UnixDatagram};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tokio::time::interval;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+// Sends sd_notify state, e.g. "READY=1", to systemd. A no-op unless systemd
+// started the service with Type=notify and set $NOTIFY_SOCKET
+pub fn notify(state: &str) {
+    let Some(socket) = std::env::var_os("NOTIFY_SOCKET") else {
+        return;
+    };
+    if let Err(e) = send(&socket.to_string_lossy(), state) {
+        warn!("Failed to notify systemd of {}: {}", state, e);
+    }
+}
+
+fn send(socket: &str, state: &str) -> Result<()> {
+    // A leading @ names a socket in the abstract namespace
+    let addr = match socket.strip_prefix('@') {
+        Some(name) => SocketAddr::from_abstract_name(name)?,
+        None => SocketAddr::from_pathname(socket)?,
+    };
+    UnixDatagram::unbound()?.send_to_addr(state.as_bytes(), &addr)?;
+    Ok(())
+}
+
+// How often systemd expects a ping, from WatchdogSec= in the unit; None
+// without a watchdog or when it's meant for another process
+fn watchdog_interval() -> Option<Duration> {
+    if let Ok(pid) = std::env::var("WATCHDOG_PID") {
+        if pid.parse::<u32>().ok() != Some(std::process::id()) {
+            return None;
+        }
+    }
+    let usec: u64 = std::env::var("WATCHDOG_USEC").ok()?.parse().ok()?;
+    (usec > 0).then(|| Duration::from_micros(usec))
+}
+
+// Pings the watchdog at half its interval while every service task is still
+// running. Once one has died the pings stop, so systemd restarts the process
+// rather than leave it running without that service.
+pub async fn run_watchdog(tasks: Vec<(&'static str, AbortHandle)>, shutdown: CancellationToken) {
+    let Some(timeout) = watchdog_interval() else {
+        return;
+    };
+    info!("Pinging the systemd watchdog every {:?}", timeout / 2);
+    
+    let mut ticker = interval(timeout / 2);
+    loop {
+        tokio::select! {
+            _ = ticker.tick() => {}
+            _ = shutdown.cancelled() => return,
+        }
+        if let Some((name, _)) = tasks.iter().find(|(_, task)| task.is_finished()) {
+            error!("The {} task has stopped, no longer pinging the systemd watchdog", name);
+            notify(&format!("STATUS=The {} task has stopped", name));
+            return;
+        }
+        notify("WATCHDOG=1");
+    }
+}
+
+// Holds the process ID for PIDFile= in the unit; removed again when dropped
+pub struct PidFile {
+    path: String,
+}
+
+impl PidFile {
+    pub fn create(path: &str) -> Result<Self> {
+        fs::write(path, format!("{}\n", std::process::id()))
+            .with_context(|| format!("Cannot write PID file {}", path))?;
+        debug!("Wrote PID file {}", path);
+        Ok(Self { path: path.to_string() })
+    }
+}
+
+impl Drop for PidFile {
+    fn drop(&mut self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            warn!("Failed to remove PID file {}: {}", self.path, e);
+        }
+    }
+}
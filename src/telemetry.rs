@@ -0,0 +1,109 @@
+use anyhow::Result;
+use opentelemetry::global;
+use opentelemetry::KeyValue;
+use opentelemetry_otlp::WithExportConfig;
+use opentelemetry_sdk::propagation::TraceContextPropagator;
+use opentelemetry_sdk::trace::{self as sdktrace, Sampler};
+use opentelemetry_sdk::Resource;
+use std::collections::HashMap;
+use tracing::Span;
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::config::{DiagnosticsConfig, LogFormat, TelemetryConfig};
+
+// Logs go to stderr, as text or one JSON object per line, and spans to an
+// OTLP collector when enabled. Trace context crosses Kafka in W3C
+// traceparent headers. tokio-console, when on, sees every span and event
+// regardless of the log filter
+pub struct Telemetry {
+    exporting: bool,
+}
+
+pub fn init(config: &TelemetryConfig, diagnostics: &DiagnosticsConfig) -> Result<Telemetry> {
+    // RUST_LOG wins over the configured filter
+    let filter = match EnvFilter::try_from_default_env() {
+        Ok(filter) => filter,
+        Err(_) => EnvFilter::try_new(&config.log_filter)?,
+    };
+    
+    let logs = match config.log_format {
+        LogFormat::Text => tracing_subscriber::fmt::layer()
+            .with_writer(std::io::stderr)
+            .boxed(),
+        LogFormat::Json => tracing_subscriber::fmt::layer()
+            .json()
+            .flatten_event(true)
+            .with_current_span(true)
+            .with_span_list(false)
+            .with_writer(std::io::stderr)
+            .boxed(),
+    };
+    
+    let traces = if config.otlp.enabled {
+        global::set_text_map_propagator(TraceContextPropagator::new());
+        let otlp = &config.otlp;
+        let tracer = opentelemetry_otlp::new_pipeline()
+            .tracing()
+            .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(&otlp.endpoint))
+            .with_trace_config(
+                sdktrace::config()
+                    .with_sampler(Sampler::ParentBased(Box::new(Sampler::TraceIdRatioBased(otlp.sample_ratio))))
+                    .with_resource(Resource::new(vec![KeyValue::new("service.name", otlp.service_name.clone())])),
+            )
+            .install_batch(opentelemetry_sdk::runtime::Tokio)?;
+        Some(tracing_opentelemetry::layer().with_tracer(tracer))
+    } else {
+        None
+    };
+    
+    tracing_subscriber::registry()
+        .with(logs.and_then(traces).with_filter(filter))
+        .with(console_layer(diagnostics)?)
+        .try_init()?;
+    
+    Ok(Telemetry { exporting: config.otlp.enabled })
+}
+
+#[cfg(feature = "diagnostics")]
+fn console_layer(diagnostics: &DiagnosticsConfig) -> Result<Option<console_subscriber::ConsoleLayer>> {
+    diagnostics.console.then(|| crate::diagnostics::console_layer(diagnostics)).transpose()
+}
+
+// Config validation rejects console = true without the feature
+#[cfg(not(feature = "diagnostics"))]
+fn console_layer(_diagnostics: &DiagnosticsConfig) -> Result<Option<tracing_subscriber::layer::Identity>> {
+    Ok(None)
+}
+
+impl Telemetry {
+    // Exports spans still queued; the exporter blocks, so off the runtime
+    pub async fn shutdown(self) {
+        if self.exporting {
+            let _ = tokio::task::spawn_blocking(global::shutdown_tracer_provider).await;
+        }
+    }
+}
+
+// The current span's trace context as W3C headers; empty when traces
+// aren't exported
+pub fn inject_context() -> HashMap<String, String> {
+    let mut carrier = HashMap::new();
+    global::get_text_map_propagator(|propagator| {
+        propagator.inject_context(&Span::current().context(), &mut carrier)
+    });
+    carrier
+}
+
+// Continues the trace carried in headers from another instance
+pub fn set_remote_parent(span: &Span, carrier: &HashMap<String, String>) {
+    let context = global::get_text_map_propagator(|propagator| propagator.extract(carrier));
+    span.set_parent(context);
+}
+
+// The current span's context, to parent later work on the same resource
+pub fn current_context() -> opentelemetry::Context {
+    Span::current().context()
+}
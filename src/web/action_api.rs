@@ -0,0 +1,25 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::scheduler::resource_scheduler::ManualActionRequest;
+use super::auth::Operator;
+use super::dashboard::DashboardServer;
+
+pub async fn request_action(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+    Json(request): Json<ManualActionRequest>,
+) -> Response {
+    if !server.scheduler.leadership().is_leader {
+        return (StatusCode::CONFLICT, "This instance is a standby; send manual actions to the leader").into_response();
+    }
+    
+    match server.scheduler.request_manual_action(request, &operator.name).await {
+        Ok(decisions) => (StatusCode::ACCEPTED, Json(decisions)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,27 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::metrics::agent::AgentReport;
+use super::auth::Operator;
+use super::dashboard::DashboardServer;
+
+// Libvirt domain stats from a per-hypervisor agent. The samples drive
+// migrations, so agents need an operator key even with auth disabled
+pub async fn ingest(
+    State(server): State<DashboardServer>,
+    Operator(_): Operator,
+    Json(report): Json<AgentReport>,
+) -> Response {
+    let collector = &server.metrics_collector;
+    if !collector.accepts_agent_reports() {
+        return (StatusCode::NOT_FOUND, "Agent ingestion is disabled").into_response();
+    }
+    match collector.ingest_agent_report(report).await {
+        Ok(result) => Json(result).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,287 @@
+use anyhow::Result;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, Query, Request, State},
+    http::{Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::VecDeque;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+use crate::config::{ApiRole, AuditConfig};
+use crate::storage::Storage;
+use super::auth::{Operator, Principal};
+use super::dashboard::DashboardServer;
+use super::proxy::ClientInfo;
+
+const AUDIT_COLLECTION: &str = "audit";
+
+// Request fields whose values never reach the audit log
+const REDACTED_FIELDS: &[&str] = &["password", "secret", "token", "api_key"];
+
+// Who changed what through the HTTP and gRPC APIs and how it went. Entries
+// are persisted as they are recorded and pruned by age and count.
+pub struct AuditLog {
+    config: AuditConfig,
+    storage: Storage,
+    // Oldest first
+    entries: RwLock<VecDeque<AuditEntry>>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub id: String,
+    pub timestamp: DateTime<Utc>,
+    // None when the caller didn't authenticate
+    pub principal: Option<String>,
+    pub role: Option<ApiRole>,
+    pub client_address: Option<String>,
+    pub interface: AuditInterface,
+    // "POST /alerts/:id/acknowledge", or the gRPC method and operation
+    pub action: String,
+    // The path or resource acted on
+    pub target: String,
+    // What was sent, with credentials redacted
+    pub request: Option<Value>,
+    pub outcome: AuditOutcome,
+    // HTTP status, or the gRPC status code
+    pub status: String,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditInterface {
+    Http,
+    Grpc,
+    // Reloads of the config file, by SIGHUP or a change on disk
+    Config,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOutcome {
+    Success,
+    // Refused for missing credentials or role
+    Denied,
+    Failed,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct AuditQuery {
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    principal: Option<String>,
+    // Matches any part of the action, e.g. "acknowledge" or "/sla"
+    action: Option<String>,
+    outcome: Option<AuditOutcome>,
+}
+
+impl AuditEntry {
+    pub fn new(interface: AuditInterface, action: String, target: String, principal: Option<&Principal>) -> Self {
+        let timestamp = Utc::now();
+        Self {
+            // Sorts by time, and is unique within the same microsecond
+            id: format!("{}-{}", timestamp.format("%Y%m%dT%H%M%S%.6fZ"), &Uuid::new_v4().simple().to_string()[..8]),
+            timestamp,
+            principal: principal.map(|p| p.name.clone()),
+            role: principal.map(|p| p.role),
+            client_address: None,
+            interface,
+            action,
+            target,
+            request: None,
+            outcome: AuditOutcome::Success,
+            status: String::new(),
+            error: None,
+        }
+    }
+}
+
+impl AuditLog {
+    pub async fn load(config: AuditConfig, storage: Storage) -> Result<Self> {
+        let mut stored: Vec<AuditEntry> = storage.list(AUDIT_COLLECTION).await?;
+        stored.sort_by_key(|e| e.timestamp);
+        info!("Loaded {} audit entries from storage", stored.len());
+        
+        let log = Self {
+            config,
+            storage,
+            entries: RwLock::new(stored.into()),
+        };
+        log.prune().await;
+        Ok(log)
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled
+    }
+    
+    // Failures are logged rather than failing the call being audited
+    pub async fn record(&self, entry: AuditEntry) {
+        if !self.config.enabled {
+            return;
+        }
+        
+        info!(
+            "Audit: {} {} by {} -> {}",
+            entry.action,
+            entry.target,
+            entry.principal.as_deref().unwrap_or("anonymous"),
+            entry.status
+        );
+        if let Err(e) = self.storage.put(AUDIT_COLLECTION, &entry.id, &entry).await {
+            warn!("Failed to store audit entry {}: {}", entry.id, e);
+        }
+        self.entries.write().await.push_back(entry);
+        self.prune().await;
+    }
+    
+    // Newest first
+    pub async fn query(&self, query: &AuditQuery) -> Vec<AuditEntry> {
+        let action = query.action.as_ref().map(|a| a.to_lowercase());
+        self.entries.read().await.iter().rev()
+            .filter(|e| query.from.is_none_or(|from| e.timestamp >= from))
+            .filter(|e| query.to.is_none_or(|to| e.timestamp < to))
+            .filter(|e| query.principal.as_ref().is_none_or(|p| e.principal.as_ref() == Some(p)))
+            .filter(|e| action.as_ref().is_none_or(|a| e.action.to_lowercase().contains(a.as_str())))
+            .filter(|e| query.outcome.is_none_or(|outcome| e.outcome == outcome))
+            .cloned()
+            .collect()
+    }
+    
+    async fn prune(&self) {
+        let cutoff = Utc::now() - Duration::days(self.config.retention_days);
+        let mut expired = Vec::new();
+        {
+            let mut entries = self.entries.write().await;
+            while entries.front().is_some_and(|e| e.timestamp < cutoff || entries.len() > self.config.max_entries) {
+                if let Some(entry) = entries.pop_front() {
+                    expired.push(entry.id);
+                }
+            }
+        }
+        
+        for id in expired {
+            if let Err(e) = self.storage.delete(AUDIT_COLLECTION, &id).await {
+                warn!("Failed to prune audit entry {}: {}", id, e);
+            }
+        }
+    }
+}
+
+// Reading the audit trail always takes an operator
+pub async fn list_audit(
+    State(server): State<DashboardServer>,
+    Operator(_): Operator,
+    Query(query): Query<AuditQuery>,
+) -> impl IntoResponse {
+    Json(server.audit.query(&query).await)
+}
+
+// Records every request that isn't a read, including refused ones, once
+// the handler has answered it
+pub async fn record_writes(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    request: Request,
+    next: Next,
+) -> Response {
+    if !server.audit.is_enabled() || matches!(*request.method(), Method::GET | Method::HEAD | Method::OPTIONS) {
+        return next.run(request).await;
+    }
+    
+    let principal = server.auth.authenticate(request.headers());
+    let path = request.uri().path().to_string();
+    let route = request.extensions().get::<MatchedPath>()
+        .map(|matched| route_template(matched.as_str(), &path))
+        .unwrap_or_else(|| path.clone());
+    let target = match request.uri().query() {
+        Some(query) => format!("{}?{}", path, query),
+        None => path,
+    };
+    let mut entry = AuditEntry::new(
+        AuditInterface::Http,
+        format!("{} {}", request.method(), route),
+        target,
+        principal.as_ref(),
+    );
+    entry.client_address = Some(client.address.to_string());
+    
+    // The body is read here to be recorded, so it has to be held to the
+    // same limit the handlers' extractors apply
+    let (parts, body) = request.into_parts();
+    let bytes = match to_bytes(body, server.max_body_bytes).await {
+        Ok(bytes) => bytes,
+        Err(_) => return (StatusCode::PAYLOAD_TOO_LARGE, "Request body too large").into_response(),
+    };
+    entry.request = request_payload(&bytes);
+    
+    let response = next.run(Request::from_parts(parts, Body::from(bytes))).await;
+    let status = response.status();
+    entry.status = status.as_u16().to_string();
+    entry.outcome = match status {
+        status if status.is_success() => AuditOutcome::Success,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => AuditOutcome::Denied,
+        _ => AuditOutcome::Failed,
+    };
+    
+    // Error bodies are short messages worth keeping
+    let response = if status.is_success() {
+        response
+    } else {
+        let (parts, body) = response.into_parts();
+        let bytes = to_bytes(body, usize::MAX).await.unwrap_or_default();
+        entry.error = Some(String::from_utf8_lossy(&bytes).trim().to_string()).filter(|e| !e.is_empty());
+        Response::from_parts(parts, Body::from(bytes))
+    };
+    
+    server.audit.record(entry).await;
+    response
+}
+
+// The route without the base path and version prefix it was reached under,
+// e.g. "/alerts/:id/acknowledge" for "/api/v1/alerts/:id/acknowledge"
+fn route_template(matched: &str, path: &str) -> String {
+    let depth = path.trim_start_matches('/').split('/').count();
+    let mut segments: Vec<&str> = matched.rsplit('/').take(depth).collect();
+    segments.reverse();
+    format!("/{}", segments.join("/"))
+}
+
+fn request_payload(bytes: &[u8]) -> Option<Value> {
+    if bytes.is_empty() {
+        return None;
+    }
+    match serde_json::from_slice::<Value>(bytes) {
+        Ok(mut value) => {
+            redact(&mut value);
+            Some(value)
+        }
+        Err(_) => Some(Value::String(String::from_utf8_lossy(bytes).into_owned())),
+    }
+}
+
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(fields) => {
+            for (name, field) in fields.iter_mut() {
+                let name = name.to_lowercase();
+                if REDACTED_FIELDS.iter().any(|redacted| name.contains(redacted)) {
+                    *field = Value::String("[redacted]".to_string());
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
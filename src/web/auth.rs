@@ -0,0 +1,309 @@
+use anyhow::Result;
+use argon2::{Argon2, PasswordHash, PasswordVerifier};
+use axum::{
+    async_trait,
+    extract::{FromRequestParts, Request, State},
+    http::{
+        header::{AUTHORIZATION, COOKIE, SET_COOKIE},
+        request::Parts,
+        HeaderMap, Method, StatusCode,
+    },
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::{info, warn};
+
+use crate::config::{ApiConfig, ApiRole, ApiUserConfig};
+use super::dashboard::DashboardServer;
+use super::proxy::ClientInfo;
+
+const SESSION_COOKIE: &str = "scheduler_session";
+const API_KEY_HEADER: &str = "x-api-key";
+// Verified against for unknown users, so they take as long to reject as a
+// wrong password. It has the default argon2 cost user hashes are made with
+const DUMMY_PASSWORD_HASH: &str =
+    "$argon2id$v=19$m=19456,t=2,p=1$aCtKyZqMe7ZEhThtEJtbCA$6bdhcPtEx1dSPwqwyFnFzjFmhNNFqT6MD2bamVo0UJQ";
+
+// Resolves API keys and session tokens to principals
+pub struct Authenticator {
+    enabled: bool,
+    sso_enabled: bool,
+    // Hex SHA-256 of each key -> (name, role)
+    api_keys: HashMap<String, (String, ApiRole)>,
+    users: HashMap<String, ApiUserConfig>,
+    encoding_key: EncodingKey,
+    decoding_key: DecodingKey,
+    session_ttl: Duration,
+}
+
+// Who a request is acting as; attached to the request by require_auth
+#[derive(Debug, Clone)]
+pub struct Principal {
+    pub name: String,
+    pub role: ApiRole,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct SessionClaims {
+    sub: String,
+    role: ApiRole,
+    // Signed in through single sign-on rather than as a configured user
+    #[serde(default)]
+    sso: bool,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+pub struct LoginRequest {
+    username: String,
+    password: String,
+}
+
+#[derive(Serialize)]
+pub struct LoginResponse {
+    token: String,
+    role: ApiRole,
+    expires_at: DateTime<Utc>,
+}
+
+impl Authenticator {
+    pub fn new(config: &ApiConfig) -> Self {
+        let secret = match &config.jwt_secret {
+            Some(secret) => secret.as_bytes().to_vec(),
+            None => {
+                if !config.users.is_empty() {
+                    warn!("No jwt_secret configured; dashboard sessions won't survive a restart");
+                }
+                rand::thread_rng().gen::<[u8; 32]>().to_vec()
+            }
+        };
+        if config.auth_enabled {
+            info!(
+                "API authentication enabled with {} API keys and {} users",
+                config.api_keys.len(),
+                config.users.len()
+            );
+        }
+        
+        Self {
+            enabled: config.auth_enabled,
+            sso_enabled: config.sso.enabled,
+            api_keys: config.api_keys.iter()
+                .map(|(name, key)| (key.sha256.to_ascii_lowercase(), (name.clone(), key.role)))
+                .collect(),
+            users: config.users.clone(),
+            encoding_key: EncodingKey::from_secret(&secret),
+            decoding_key: DecodingKey::from_secret(&secret),
+            session_ttl: Duration::minutes(config.session_ttl_minutes),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+    
+    // Bearer API key or session token, X-API-Key header, or session cookie
+    pub fn authenticate(&self, headers: &HeaderMap) -> Option<Principal> {
+        if let Some(token) = header_value(headers, AUTHORIZATION.as_str()).and_then(|v| v.strip_prefix("Bearer ")) {
+            return self.authenticate_bearer(token);
+        }
+        if let Some(key) = header_value(headers, API_KEY_HEADER) {
+            return self.api_key(key.trim());
+        }
+        session_cookie(headers).and_then(|token| self.session(&token))
+    }
+    
+    // Argon2 takes tens of milliseconds of CPU by design, so it runs off the
+    // async workers
+    pub async fn login(&self, username: &str, password: &str) -> Result<(String, Principal, DateTime<Utc>)> {
+        let rejected = || anyhow::anyhow!("Invalid username or password");
+        let user = self.users.get(username);
+        let password_hash = user.map_or(DUMMY_PASSWORD_HASH, |user| user.password_hash.as_str()).to_string();
+        let password = password.to_string();
+        let verified = tokio::task::spawn_blocking(move || {
+            let hash = PasswordHash::new(&password_hash)?;
+            Argon2::default().verify_password(password.as_bytes(), &hash)
+        }).await?;
+        let Some(user) = user else {
+            return Err(rejected());
+        };
+        match verified {
+            Ok(()) => {}
+            Err(argon2::password_hash::Error::Password) => return Err(rejected()),
+            Err(e) => {
+                warn!("Password hash of dashboard user {} is malformed: {}", username, e);
+                return Err(rejected());
+            }
+        }
+        
+        let principal = Principal { name: username.to_string(), role: user.role };
+        let (token, expires_at) = self.issue_session(&principal, false)?;
+        Ok((token, principal, expires_at))
+    }
+    
+    // Signs a session for a principal single sign-on has vouched for
+    pub fn issue_sso_session(&self, principal: &Principal) -> Result<(String, DateTime<Utc>)> {
+        self.issue_session(principal, true)
+    }
+    
+    fn issue_session(&self, principal: &Principal, sso: bool) -> Result<(String, DateTime<Utc>)> {
+        let now = Utc::now();
+        let expires_at = now + self.session_ttl;
+        let claims = SessionClaims {
+            sub: principal.name.clone(),
+            role: principal.role,
+            sso,
+            iat: now.timestamp(),
+            exp: expires_at.timestamp(),
+        };
+        let token = encode(&Header::new(Algorithm::HS256), &claims, &self.encoding_key)?;
+        Ok((token, expires_at))
+    }
+    
+    // An API key or session token, for transports without HTTP headers
+    pub fn authenticate_bearer(&self, token: &str) -> Option<Principal> {
+        let token = token.trim();
+        self.api_key(token).or_else(|| self.session(token))
+    }
+    
+    pub fn api_key(&self, key: &str) -> Option<Principal> {
+        // Only digests are stored, so comparing them leaks nothing about the key
+        let digest = format!("{:x}", Sha256::digest(key.as_bytes()));
+        self.api_keys.get(&digest).map(|(name, role)| Principal { name: name.clone(), role: *role })
+    }
+    
+    // A configured user's role is looked up again, so removing the user or
+    // changing the role takes effect before the session expires. Single
+    // sign-on sessions keep the role the provider granted for their
+    // lifetime, as long as single sign-on stays on
+    fn session(&self, token: &str) -> Option<Principal> {
+        let claims = decode::<SessionClaims>(token, &self.decoding_key, &Validation::new(Algorithm::HS256))
+            .ok()?
+            .claims;
+        let role = match claims.sso {
+            true => self.sso_enabled.then_some(claims.role)?,
+            false => self.users.get(&claims.sub)?.role,
+        };
+        Some(Principal { name: claims.sub, role })
+    }
+}
+
+fn header_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name).and_then(|value| value.to_str().ok())
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, token)| token.to_string())
+}
+
+// Middleware on /api, /graphql and /ws. With auth on, reads need any
+// principal and changes need an operator; with it off, reads are open but
+// changes still need an operator
+pub async fn require_auth(
+    State(server): State<DashboardServer>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let principal = server.auth.authenticate(request.headers());
+    
+    // The GraphQL schema has no mutations, so POSTing a query only reads
+    let read_only = matches!(*request.method(), Method::GET | Method::HEAD)
+        || request.uri().path() == "/graphql";
+    match &principal {
+        Some(principal) if !read_only && principal.role != ApiRole::Operator => {
+            return (StatusCode::FORBIDDEN, "Operator role required").into_response();
+        }
+        None if !read_only || server.auth.is_enabled() => {
+            return (StatusCode::UNAUTHORIZED, "Authentication required").into_response();
+        }
+        _ => {}
+    }
+    
+    if let Some(principal) = principal {
+        request.extensions_mut().insert(principal);
+    }
+    next.run(request).await
+}
+
+// Extractor for endpoints that always need an authenticated operator
+pub struct Operator(pub Principal);
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Operator {
+    type Rejection = Response;
+    
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        match parts.extensions.get::<Principal>() {
+            Some(principal) if principal.role == ApiRole::Operator => Ok(Operator(principal.clone())),
+            Some(_) => Err((StatusCode::FORBIDDEN, "Operator role required").into_response()),
+            None => Err((StatusCode::UNAUTHORIZED, "Authentication required").into_response()),
+        }
+    }
+}
+
+pub async fn login(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    Json(request): Json<LoginRequest>,
+) -> Response {
+    match server.auth.login(&request.username, &request.password).await {
+        Ok((token, principal, expires_at)) => {
+            info!("Dashboard user {} logged in", principal.name);
+            session_response(token, &principal, expires_at, &client)
+        }
+        Err(e) => {
+            warn!("Failed dashboard login for {}", request.username);
+            (StatusCode::UNAUTHORIZED, e.to_string()).into_response()
+        }
+    }
+}
+
+// Sets the session cookie and returns the token for non-browser clients
+pub fn session_response(token: String, principal: &Principal, expires_at: DateTime<Utc>, client: &ClientInfo) -> Response {
+    (
+        [(SET_COOKIE, session_set_cookie(&token, expires_at, client))],
+        Json(LoginResponse { token, role: principal.role, expires_at }),
+    ).into_response()
+}
+
+pub fn session_set_cookie(token: &str, expires_at: DateTime<Utc>, client: &ClientInfo) -> String {
+    format!(
+        "{}={}; {}; Max-Age={}",
+        SESSION_COOKIE,
+        token,
+        cookie_attributes(client, "/", "Strict"),
+        (expires_at - Utc::now()).num_seconds()
+    )
+}
+
+// Scoped to where the app is mounted, and HTTPS-only when the browser uses it
+pub fn cookie_attributes(client: &ClientInfo, path: &str, same_site: &str) -> String {
+    format!(
+        "Path={}; HttpOnly; SameSite={}{}",
+        client.path(path),
+        same_site,
+        if client.secure { "; Secure" } else { "" }
+    )
+}
+
+// Sessions are stateless tokens; logging out just drops the cookie
+pub async fn logout(Extension(client): Extension<ClientInfo>) -> impl IntoResponse {
+    (
+        [(SET_COOKIE, format!("{}=; {}; Max-Age=0", SESSION_COOKIE, cookie_attributes(&client, "/", "Strict")))],
+        StatusCode::NO_CONTENT,
+    )
+}
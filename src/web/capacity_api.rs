@@ -0,0 +1,27 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+use serde::Deserialize;
+
+use super::dashboard::DashboardServer;
+
+#[derive(Deserialize)]
+pub struct CapacityQuery {
+    // Only this zone and its hosts
+    availability_zone: Option<String>,
+}
+
+// Current usage, projections and exhaustion dates per host and zone
+pub async fn get_capacity(
+    State(server): State<DashboardServer>,
+    Query(query): Query<CapacityQuery>,
+) -> impl IntoResponse {
+    let mut report = server.scheduler.capacity_report();
+    if let Some(zone) = &query.availability_zone {
+        report.hosts.retain(|host| host.availability_zone.as_ref() == Some(zone));
+        report.availability_zones.retain(|az| az.id == *zone);
+    }
+    Json(report)
+}
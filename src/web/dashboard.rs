@@ -1,30 +1,81 @@
 use anyhow::Result;
+use arc_swap::ArcSwap;
+use async_graphql::{Enum, SimpleObject};
 use axum::{
-    extract::{Query, State, WebSocketUpgrade},
+    extract::{DefaultBodyLimit, Query, State, WebSocketUpgrade},
     http::StatusCode,
-    response::{Html, IntoResponse},
-    routing::{get, post},
-    Json, Router,
+    response::{Html, IntoResponse, Response},
+    middleware,
+    routing::{get, post, put},
+    Extension, Json, Router,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use metrics_exporter_prometheus::PrometheusHandle;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use std::time::{Duration, Instant};
+use tokio::sync::{broadcast, RwLock};
+use tokio_util::sync::CancellationToken;
 use tower_http::services::ServeDir;
 use tracing::{info, warn};
 
+use crate::config::{AlertGroupBy, AlertingConfig, ApiConfig, ApiVersioningConfig, CorsConfig, DiagnosticsConfig};
+use crate::metrics::edf::CollectionQueueStats;
+use crate::metrics::history::HistoryMetric;
 use crate::ml::MLEngine;
 use crate::metrics::MetricsCollector;
+use crate::openstack::breaker::{BreakerState, BreakerStatus};
+use crate::scheduler::stats::SchedulerPerformance;
 use crate::scheduler::ResourceScheduler;
+use crate::storage::Storage;
+use super::action_api;
+use super::agent_api;
+use super::audit::{self, AuditLog};
+use super::capacity_api;
+use super::resource_api;
+#[cfg(feature = "diagnostics")]
+use super::debug_api;
+use super::auth::{self, Authenticator, Principal};
+use super::decision_api;
+use super::disruption_api;
+use super::export_api;
+use super::graphql;
+use super::history_api;
+use super::notifier::AlertNotifier;
+use super::proxy::{self, ClientInfo, ProxySettings};
+use super::rate_limit::{self, RateLimiter};
+use super::scheduler_api;
+use super::sla_api;
+use super::sso::{self, SsoLogin};
+use super::versioning;
 use super::websocket::WebSocketHandler;
 
+const ALERT_COLLECTION: &str = "alerts";
+
 #[derive(Clone)]
 pub struct DashboardServer {
     ml_engine: Arc<MLEngine>,
-    metrics_collector: Arc<MetricsCollector>,
-    scheduler: Arc<ResourceScheduler>,
-    websocket_handler: Arc<WebSocketHandler>,
-    dashboard_state: Arc<RwLock<DashboardState>>,
+    pub(super) metrics_collector: Arc<MetricsCollector>,
+    pub(super) scheduler: Arc<ResourceScheduler>,
+    pub(super) websocket_handler: Arc<WebSocketHandler>,
+    pub(super) dashboard_state: Arc<RwLock<DashboardState>>,
+    prometheus: PrometheusHandle,
+    pub(super) auth: Arc<Authenticator>,
+    pub(super) sso: Arc<SsoLogin>,
+    notifier: Arc<ArcSwap<AlertNotifier>>,
+    pub(super) rate_limiter: Arc<RateLimiter>,
+    pub(super) max_body_bytes: usize,
+    pub(super) proxy: Arc<ProxySettings>,
+    cors: CorsConfig,
+    pub(super) versioning: ApiVersioningConfig,
+    pub(super) audit: Arc<AuditLog>,
+    alerting: Arc<ArcSwap<AlertingConfig>>,
+    storage: Storage,
+    #[cfg_attr(not(feature = "diagnostics"), allow(dead_code))]
+    pub(super) diagnostics: DiagnosticsConfig,
+    // Every state refresh, for GraphQL subscriptions
+    pub(super) state_updates: broadcast::Sender<Arc<DashboardState>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -35,10 +86,17 @@ pub struct DashboardState {
     pub performance_stats: PerformanceStats,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
 pub struct PredictionData {
     pub resource_id: String,
     pub resource_type: String,
+    pub project_id: Option<String>,
+    // None for the main credentials
+    pub cloud: Option<String>,
+    pub region: Option<String>,
+    // The Heat stack the server belongs to
+    pub stack_id: Option<String>,
+    pub stack_name: Option<String>,
     pub current_value: f64,
     pub predicted_values: Vec<f64>,
     pub confidence: f64,
@@ -47,27 +105,55 @@ pub struct PredictionData {
     pub model_version: String,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
 pub struct SystemMetrics {
     pub total_resources: u32,
     pub active_predictions: u32,
-    pub model_accuracy: f64,
+    // Mean forecast confidence (0-1) of the active predictions
+    pub mean_confidence: f64,
+    // Mean time to forecast one resource in the last refresh
     pub inference_latency_ms: f64,
-    pub memory_usage_mb: f64,
-    pub cpu_usage_percent: f64,
+    // Due collections waiting for a slot, and those running
+    pub collection_queue_depth: u32,
+    pub collections_in_flight: u32,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, SimpleObject)]
 pub struct Alert {
+    // Unique per firing episode
     pub id: String,
+    // Rule plus resource, shared by every episode of the same problem
+    pub fingerprint: String,
+    pub rule: String,
+    pub group: String,
     pub severity: AlertSeverity,
+    pub status: AlertStatus,
     pub message: String,
     pub resource_id: Option<String>,
+    // When the alert started firing
     pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub last_seen: chrono::DateTime<chrono::Utc>,
+    pub resolved_at: Option<chrono::DateTime<chrono::Utc>>,
+    pub last_notified: Option<chrono::DateTime<chrono::Utc>>,
     pub acknowledged: bool,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+impl Alert {
+    // Ids carry the fingerprint's ':', which storage keys can't
+    fn storage_key(&self) -> String {
+        self.id.chars()
+            .map(|c| if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') { c } else { '_' })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
+pub enum AlertStatus {
+    Firing,
+    Resolved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Enum)]
 pub enum AlertSeverity {
     Critical,
     Warning,
@@ -78,9 +164,13 @@ pub enum AlertSeverity {
 pub struct PerformanceStats {
     pub predictions_per_second: f64,
     pub model_inference_time_ms: f64,
+    // The whole prediction refresh, forecasting included
     pub data_processing_time_ms: f64,
     pub total_predictions_today: u64,
-    pub accuracy_trend: Vec<f64>,
+    // mean_confidence at each refresh, the last 100 of them
+    pub confidence_trend: Vec<f64>,
+    pub scheduler: SchedulerPerformance,
+    pub collection: CollectionQueueStats,
 }
 
 impl Default for DashboardState {
@@ -90,10 +180,10 @@ impl Default for DashboardState {
             system_metrics: SystemMetrics {
                 total_resources: 0,
                 active_predictions: 0,
-                model_accuracy: 0.0,
+                mean_confidence: 0.0,
                 inference_latency_ms: 0.0,
-                memory_usage_mb: 0.0,
-                cpu_usage_percent: 0.0,
+                collection_queue_depth: 0,
+                collections_in_flight: 0,
             },
             alerts: Vec::new(),
             performance_stats: PerformanceStats {
@@ -101,62 +191,231 @@ impl Default for DashboardState {
                 model_inference_time_ms: 0.0,
                 data_processing_time_ms: 0.0,
                 total_predictions_today: 0,
-                accuracy_trend: Vec::new(),
+                confidence_trend: Vec::new(),
+                scheduler: SchedulerPerformance::default(),
+                collection: CollectionQueueStats::default(),
             },
         }
     }
 }
 
 impl DashboardServer {
-    pub fn new(
+    pub async fn new(
         ml_engine: Arc<MLEngine>,
         metrics_collector: Arc<MetricsCollector>,
         scheduler: Arc<ResourceScheduler>,
-    ) -> Self {
-        let websocket_handler = Arc::new(WebSocketHandler::new());
+        storage: Storage,
+        prometheus: PrometheusHandle,
+        api: ApiConfig,
+        alerting: AlertingConfig,
+        diagnostics: DiagnosticsConfig,
+    ) -> Result<Self> {
+        let websocket_handler = Arc::new(WebSocketHandler::new(api.websocket.clone()));
+        let proxy = ProxySettings::new(&api)?;
+        let audit = AuditLog::load(api.audit.clone(), storage.clone()).await?;
+        // Open and recently resolved alerts, with acknowledgements, survive a restart
+        let alerts: Vec<Alert> = storage.list(ALERT_COLLECTION).await?;
+        info!("Loaded {} alerts from storage", alerts.len());
         
-        Self {
+        Ok(Self {
             ml_engine,
             metrics_collector,
             scheduler,
             websocket_handler,
-            dashboard_state: Arc::new(RwLock::new(DashboardState::default())),
-        }
+            dashboard_state: Arc::new(RwLock::new(DashboardState { alerts, ..DashboardState::default() })),
+            prometheus,
+            auth: Arc::new(Authenticator::new(&api)),
+            sso: Arc::new(SsoLogin::new(api.sso)),
+            rate_limiter: Arc::new(RateLimiter::new(api.rate_limit)),
+            max_body_bytes: api.max_body_bytes,
+            proxy: Arc::new(proxy),
+            cors: api.cors,
+            versioning: api.versioning,
+            audit: Arc::new(audit),
+            notifier: Arc::new(ArcSwap::from_pointee(AlertNotifier::new(&alerting)?)),
+            alerting: Arc::new(ArcSwap::from_pointee(alerting)),
+            storage,
+            diagnostics,
+            state_updates: broadcast::channel(16).0,
+        })
+    }
+    
+    pub fn authenticator(&self) -> Arc<Authenticator> {
+        self.auth.clone()
+    }
+    
+    pub fn audit_log(&self) -> Arc<AuditLog> {
+        self.audit.clone()
+    }
+    
+    // Channels are rebuilt from scratch, so their send rate limits restart
+    pub fn apply_alerting(&self, alerting: AlertingConfig) -> Result<()> {
+        let notifier = AlertNotifier::new(&alerting)?;
+        info!("Alerting config reloaded with {} channels", alerting.channels.len());
+        self.notifier.store(Arc::new(notifier));
+        self.alerting.store(Arc::new(alerting));
+        Ok(())
     }
     
-    pub async fn start(&self, port: u16) -> Result<()> {
+    // Serves until shutdown is cancelled, then lets open requests finish
+    pub async fn start(&self, port: u16, shutdown: CancellationToken) -> Result<()> {
         info!("Starting ML monitoring dashboard on port {}", port);
         
         // Start background tasks
         let state_updater = self.clone();
+        let updater_shutdown = shutdown.clone();
         tokio::spawn(async move {
-            state_updater.update_dashboard_state_loop().await;
+            state_updater.update_dashboard_state_loop(updater_shutdown).await;
         });
         
-        // Create router
+        let rate_limiter = self.rate_limiter.clone();
+        let pruner_shutdown = shutdown.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(60));
+            loop {
+                tokio::select! {
+                    _ = interval.tick() => rate_limiter.prune(),
+                    _ = pruner_shutdown.cancelled() => return,
+                }
+            }
+        });
+        
+        // The API as served under /api/v1 and, deprecated, under /api. Apart
+        // from logging in, it all goes through authentication, as do
+        // /graphql, /ws, /ingest/agent and, in diagnostics builds, /debug.
+        let protected_api = Router::new()
+            .route("/predictions", get(get_predictions))
+            .route("/predictions/stacks", get(get_stack_predictions))
+            .route("/metrics", get(get_system_metrics))
+            .route("/alerts", get(get_alerts))
+            .route("/alerts/:id/acknowledge", post(acknowledge_alert))
+            .route("/performance", get(get_performance_stats))
+            .route("/history", get(history_api::get_history))
+            .route("/capacity", get(capacity_api::get_capacity))
+            .route("/resources/changelog", get(resource_api::get_changelog))
+            .route("/export/predictions", get(export_api::export_predictions))
+            .route("/export/violations", get(export_api::export_violations))
+            .route("/export/decisions", get(export_api::export_decisions))
+            .route("/scheduler/scoring", get(scheduler_api::get_scoring).put(scheduler_api::update_scoring))
+            .route("/scheduler/simulation", get(scheduler_api::get_last_simulation))
+            .route("/scheduler/rebalance", get(scheduler_api::get_last_rebalance))
+            .route("/scheduler/policy", get(scheduler_api::get_policy_rules))
+            .route("/scheduler/policy/blocks", get(scheduler_api::get_policy_blocks))
+            .route("/scheduler/policy/overrides", get(scheduler_api::get_policy_overrides))
+            .route("/scheduler/policy/blackouts", get(scheduler_api::get_blackouts))
+            .route("/scheduler/preemptions", get(scheduler_api::get_preemptions))
+            .route("/scheduler/deadlines", get(scheduler_api::get_deadline_stats))
+            .route("/scheduler/leader", get(scheduler_api::get_leadership))
+            .route("/scheduler/status", get(scheduler_api::get_status))
+            .route("/scheduler/pause", post(scheduler_api::pause))
+            .route("/scheduler/resume", post(scheduler_api::resume))
+            .route("/scheduler/mode", put(scheduler_api::update_mode))
+            .route("/scheduler/aggressiveness", put(scheduler_api::update_aggressiveness))
+            .route("/sla", get(sla_api::list_policies).post(sla_api::create_policy))
+            .route("/sla/budgets", get(sla_api::list_error_budgets))
+            .route(
+                "/sla/:resource_id",
+                get(sla_api::get_policy).put(sla_api::update_policy).delete(sla_api::delete_policy),
+            )
+            .route(
+                "/disruption-budgets",
+                get(disruption_api::list_budgets).post(disruption_api::create_budget),
+            )
+            .route(
+                "/disruption-budgets/:name",
+                get(disruption_api::get_budget).put(disruption_api::update_budget).delete(disruption_api::delete_budget),
+            )
+            .route("/actions", post(action_api::request_action))
+            .route("/decisions", get(decision_api::list_decisions))
+            .route("/decisions/:id/explain", get(decision_api::explain_decision))
+            .route("/audit", get(audit::list_audit))
+            .route_layer(middleware::from_fn_with_state(self.clone(), auth::require_auth));
+        let api = Router::new()
+            .route("/login", post(auth::login))
+            .route("/logout", post(auth::logout))
+            .route("/login/sso", get(sso::get_status))
+            .route("/login/keystone", post(sso::keystone_login))
+            .merge(protected_api)
+            .layer(middleware::from_fn_with_state(self.clone(), audit::record_writes));
+        
+        // Agents push too often for their reports to be audited
+        let protected = Router::new()
+            .route("/graphql", get(graphql::graphiql).post(graphql::graphql_query))
+            .route("/graphql/ws", get(graphql::graphql_ws))
+            .route("/ws", get(websocket_handler))
+            .route("/ingest/agent", post(agent_api::ingest));
+        #[cfg(feature = "diagnostics")]
+        let protected = if self.diagnostics.profiling {
+            protected.nest("/debug", debug_api::routes())
+        } else {
+            protected
+        };
+        let protected = protected
+            .route_layer(middleware::from_fn_with_state(self.clone(), auth::require_auth));
+        
         let app = Router::new()
             .route("/", get(serve_dashboard))
-            .route("/api/predictions", get(get_predictions))
-            .route("/api/metrics", get(get_system_metrics))
-            .route("/api/alerts", get(get_alerts))
-            .route("/api/alerts/:id/acknowledge", post(acknowledge_alert))
-            .route("/api/performance", get(get_performance_stats))
-            .route("/ws", get(websocket_handler))
-            .nest_service("/static", ServeDir::new("static"))
-            .with_state(self.clone());
+            .route("/metrics", get(get_prometheus_metrics))
+            .route("/health", get(get_health))
+            .route("/api/versions", get(versioning::list_versions))
+            .nest(
+                &format!("/api/v{}", versioning::CURRENT_VERSION),
+                api.clone().layer(middleware::from_fn(versioning::v1)),
+            )
+            .route("/auth/sso/login", get(sso::oidc_login))
+            .route("/auth/sso/callback", get(sso::oidc_callback))
+            .merge(protected)
+            .nest_service("/static", ServeDir::new("static"));
+        let app = if self.versioning.legacy_routes {
+            app.nest("/api", api.layer(middleware::from_fn_with_state(self.clone(), versioning::deprecated)))
+        } else {
+            app
+        };
+        
+        // Nesting matches the bare prefix but not the prefix with a slash
+        let base_path = self.proxy.base_path();
+        let app = if base_path.is_empty() {
+            app
+        } else {
+            Router::new()
+                .nest(base_path, app)
+                .route(&format!("{}/", base_path), get(serve_dashboard))
+        };
+        
+        // Layers run bottom-up: CORS answers preflights before anything else,
+        // and client details are resolved before rate limiting uses them
+        let app = app
+            .layer(Extension(graphql::build_schema(self.clone())))
+            // Graceful shutdown would otherwise wait on open WebSockets
+            .layer(Extension(shutdown.clone()))
+            .layer(DefaultBodyLimit::max(self.max_body_bytes))
+            .layer(middleware::from_fn_with_state(self.clone(), rate_limit::limit_requests))
+            .layer(middleware::from_fn_with_state(self.clone(), proxy::resolve_client));
+        let app = match proxy::cors_layer(&self.cors) {
+            Some(cors) => app.layer(cors),
+            None => app,
+        };
+        let app = app.with_state(self.clone());
         
         let listener = tokio::net::TcpListener::bind(format!("0.0.0.0:{}", port)).await?;
-        info!("Dashboard server listening on http://0.0.0.0:{}", port);
+        info!("Dashboard server listening on http://0.0.0.0:{}{}/", port, base_path);
         
-        axum::serve(listener, app).await?;
+        // Peer addresses identify clients not behind a trusted proxy
+        axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
+            .with_graceful_shutdown(shutdown.cancelled_owned())
+            .await?;
+        info!("Dashboard server stopped");
         Ok(())
     }
     
-    async fn update_dashboard_state_loop(&self) {
+    async fn update_dashboard_state_loop(&self, shutdown: CancellationToken) {
         let mut interval = tokio::time::interval(tokio::time::Duration::from_secs(1));
         
         loop {
-            interval.tick().await;
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown.cancelled() => return,
+            }
             
             if let Err(e) = self.update_dashboard_state().await {
                 warn!("Failed to update dashboard state: {}", e);
@@ -179,145 +438,317 @@ impl DashboardServer {
         // Update performance stats
         self.update_performance_stats(&mut state).await?;
         
-        // Broadcast updates via WebSocket
-        let state_json = serde_json::to_string(&*state)?;
-        self.websocket_handler.broadcast(state_json).await;
+        // Each WebSocket and GraphQL subscriber picks out what it asked for
+        let snapshot = Arc::new(state.clone());
+        self.websocket_handler.broadcast(snapshot.clone()).await;
+        // Fails only when nobody is subscribed
+        let _ = self.state_updates.send(snapshot);
         
         Ok(())
     }
     
+    // Every discovered resource with a fresh sample and enough history to
+    // forecast from; the rest drop off until they have both
     async fn update_predictions(&self, state: &mut DashboardState) -> Result<()> {
-        // Mock implementation - in reality would get from ML engine
-        let resource_ids = vec!["vm-001", "vm-002", "vm-003", "host-001", "host-002"];
-        
-        for resource_id in resource_ids {
-            let predicted_load = self.ml_engine
-                .get_resource_prediction(resource_id)
-                .await
-                .unwrap_or(0.0);
-            
-            let prediction_data = PredictionData {
-                resource_id: resource_id.to_string(),
-                resource_type: if resource_id.starts_with("vm") { "VM" } else { "Host" }.to_string(),
-                current_value: 45.0 + rand::random::<f64>() * 30.0,
-                predicted_values: self.generate_prediction_series(predicted_load).await,
-                confidence: 0.85 + rand::random::<f64>() * 0.1,
-                trend: self.determine_trend(predicted_load),
-                last_updated: chrono::Utc::now(),
-                model_version: "v1.0.1".to_string(),
+        let started = Instant::now();
+        let mut inference_time = Duration::ZERO;
+        let now = chrono::Utc::now();
+        let latest = self.metrics_collector.latest_metrics();
+        let history = self.metrics_collector.history();
+        let model_version = self.ml_engine.model_version().await;
+        let stacks = self.scheduler.stack_membership().await;
+        let mut predictions = HashMap::new();
+        
+        for (resource_id, info) in self.metrics_collector.resources() {
+            let current_value = match latest.view(&resource_id, now).cpu_utilization {
+                Some(value) => value,
+                None => continue,
+            };
+            let inference_started = Instant::now();
+            let forecast = self.ml_engine.get_resource_forecast(&resource_id).await;
+            inference_time += inference_started.elapsed();
+            let forecast = match forecast {
+                Ok(Some(forecast)) if !forecast.values.is_empty() => forecast,
+                Ok(_) => continue,
+                Err(e) => {
+                    warn!("Failed to forecast {}: {}", resource_id, e);
+                    continue;
+                }
             };
+            let predicted_load = forecast.values[0];
             
-            state.active_predictions.insert(resource_id.to_string(), prediction_data);
+            // Kept as history so predictions can be charted and exported later
+            history.record(&resource_id, HistoryMetric::PredictedLoad, predicted_load, now);
+            history.record(&resource_id, HistoryMetric::PredictionConfidence, forecast.confidence, now);
+            
+            let stack = stacks.get(&resource_id);
+            predictions.insert(resource_id.clone(), PredictionData {
+                stack_id: stack.map(|stack| stack.id.clone()),
+                stack_name: stack.map(|stack| stack.name.clone()),
+                resource_id,
+                resource_type: info.resource_type,
+                project_id: info.project_id,
+                cloud: info.cloud,
+                region: info.region,
+                current_value,
+                trend: determine_trend(current_value, &forecast.values),
+                predicted_values: forecast.values,
+                confidence: forecast.confidence,
+                last_updated: now,
+                model_version: model_version.clone(),
+            });
         }
         
-        Ok(())
-    }
-    
-    async fn generate_prediction_series(&self, base_value: f64) -> Vec<f64> {
-        (0..24).map(|i| {
-            let trend = 0.1 * i as f64;
-            let seasonal = 5.0 * (2.0 * std::f64::consts::PI * i as f64 / 24.0).sin();
-            let noise = (rand::random::<f64>() - 0.5) * 2.0;
-            (base_value + trend + seasonal + noise).max(0.0).min(100.0)
-        }).collect()
-    }
-    
-    fn determine_trend(&self, value: f64) -> String {
-        if value > 70.0 {
-            "Increasing".to_string()
-        } else if value < 30.0 {
-            "Decreasing".to_string()
+        let stats = &mut state.performance_stats;
+        stats.model_inference_time_ms = if predictions.is_empty() {
+            0.0
         } else {
-            "Stable".to_string()
-        }
+            inference_time.as_secs_f64() * 1000.0 / predictions.len() as f64
+        };
+        stats.data_processing_time_ms = started.elapsed().as_secs_f64() * 1000.0;
+        state.active_predictions = predictions;
+        Ok(())
     }
     
     async fn update_system_metrics(&self, state: &mut DashboardState) -> Result<()> {
+        let collection = self.metrics_collector.collection_stats();
         state.system_metrics = SystemMetrics {
             total_resources: state.active_predictions.len() as u32,
             active_predictions: state.active_predictions.len() as u32,
-            model_accuracy: 0.87 + rand::random::<f64>() * 0.1,
-            inference_latency_ms: 15.0 + rand::random::<f64>() * 10.0,
-            memory_usage_mb: 512.0 + rand::random::<f64>() * 100.0,
-            cpu_usage_percent: 25.0 + rand::random::<f64>() * 20.0,
+            mean_confidence: mean_confidence(&state.active_predictions).unwrap_or(0.0),
+            inference_latency_ms: state.performance_stats.model_inference_time_ms,
+            collection_queue_depth: collection.queued as u32,
+            collections_in_flight: collection.running as u32,
         };
         
         Ok(())
     }
     
     async fn update_alerts(&self, state: &mut DashboardState) -> Result<()> {
-        // Generate sample alerts based on predictions
-        for (resource_id, prediction) in &state.active_predictions {
-            if prediction.current_value > 90.0 {
-                let alert = Alert {
-                    id: format!("alert-{}-{}", resource_id, chrono::Utc::now().timestamp()),
-                    severity: AlertSeverity::Critical,
-                    message: format!("High resource utilization detected on {}: {:.1}%", 
-                                   resource_id, prediction.current_value),
+        let now = chrono::Utc::now();
+        let DashboardState { active_predictions, alerts, .. } = state;
+        let known: HashMap<String, String> = alerts.iter().map(|a| (a.id.clone(), a.storage_key())).collect();
+        // Alerts that fired, are due a reminder or resolved this round
+        let mut notify = Vec::new();
+        let mut firing = HashSet::new();
+        
+        let mut conditions = Vec::new();
+        for (resource_id, prediction) in active_predictions.iter() {
+            let group = self.alert_group(resource_id).await;
+            for (rule, severity, message) in firing_rules(resource_id, prediction) {
+                conditions.push(AlertCondition {
+                    fingerprint: format!("{}:{}", rule, resource_id),
+                    rule: rule.to_string(),
+                    group: group.clone(),
+                    severity,
+                    message,
                     resource_id: Some(resource_id.clone()),
-                    timestamp: chrono::Utc::now(),
-                    acknowledged: false,
-                };
-                
-                // Only add if not already present
-                if !state.alerts.iter().any(|a| a.resource_id.as_ref() == Some(resource_id) && 
-                                           matches!(a.severity, AlertSeverity::Critical)) {
-                    state.alerts.push(alert);
-                }
+                });
+            }
+        }
+        
+        // Alarms firing in Aodh, those mirroring our SLA policies and the
+        // operators' own alike. One of ours on a resource already alerting
+        // on its utilization would say the same thing twice
+        let utilization_alerts: HashSet<String> = conditions.iter()
+            .filter(|condition| condition.rule == "high_utilization")
+            .filter_map(|condition| condition.resource_id.clone())
+            .collect();
+        for alarm in self.scheduler.firing_alarms().await {
+            let resource_id = alarm.resource_id().map(str::to_string);
+            if self.scheduler.is_sla_alarm(&alarm) && resource_id.as_ref().is_some_and(|id| utilization_alerts.contains(id)) {
+                continue;
             }
+            let group = match &resource_id {
+                Some(resource_id) => self.alert_group(resource_id).await,
+                None => "aodh".to_string(),
+            };
+            conditions.push(AlertCondition {
+                fingerprint: format!("aodh_alarm:{}", alarm.alarm_id),
+                rule: "aodh_alarm".to_string(),
+                group,
+                severity: match alarm.severity.as_str() {
+                    "critical" => AlertSeverity::Critical,
+                    "moderate" => AlertSeverity::Warning,
+                    _ => AlertSeverity::Info,
+                },
+                message: match alarm.description.as_str() {
+                    "" => format!("Aodh alarm {} is firing", alarm.name),
+                    description => format!("Aodh alarm {} is firing: {}", alarm.name, description),
+                },
+                resource_id,
+            });
+        }
+        
+        for AlertCondition { fingerprint, rule, group, severity, message, resource_id } in conditions {
+            firing.insert(fingerprint.clone());
             
-            if prediction.confidence < 0.7 {
-                let alert = Alert {
-                    id: format!("alert-conf-{}-{}", resource_id, chrono::Utc::now().timestamp()),
-                    severity: AlertSeverity::Warning,
-                    message: format!("Low prediction confidence for {}: {:.1}%", 
-                                   resource_id, prediction.confidence * 100.0),
-                    resource_id: Some(resource_id.clone()),
-                    timestamp: chrono::Utc::now(),
-                    acknowledged: false,
-                };
-                
-                if !state.alerts.iter().any(|a| a.resource_id.as_ref() == Some(resource_id) && 
-                                           matches!(a.severity, AlertSeverity::Warning)) {
-                    state.alerts.push(alert);
+            match alerts.iter_mut().find(|a| a.fingerprint == fingerprint && a.status == AlertStatus::Firing) {
+                Some(alert) => {
+                    alert.message = message;
+                    alert.severity = severity;
+                    alert.group = group;
+                    alert.last_seen = now;
+                    if !alert.acknowledged && self.renotify_due(alert, now) {
+                        alert.last_notified = Some(now);
+                        notify.push(alert.clone());
+                    }
+                }
+                None => {
+                    // A resolved alert firing again starts a new episode
+                    alerts.retain(|a| a.fingerprint != fingerprint);
+                    let alert = Alert {
+                        id: format!("alert-{}-{}", fingerprint, now.timestamp()),
+                        fingerprint,
+                        rule,
+                        group,
+                        severity,
+                        status: AlertStatus::Firing,
+                        message,
+                        resource_id,
+                        timestamp: now,
+                        last_seen: now,
+                        resolved_at: None,
+                        last_notified: Some(now),
+                        acknowledged: false,
+                    };
+                    notify.push(alert.clone());
+                    alerts.push(alert);
                 }
             }
         }
         
-        // Remove old alerts (older than 1 hour)
-        let cutoff = chrono::Utc::now() - chrono::Duration::hours(1);
-        state.alerts.retain(|alert| alert.timestamp > cutoff);
+        // Conditions that cleared, including resources no longer predicted
+        for alert in alerts.iter_mut().filter(|a| a.status == AlertStatus::Firing && !firing.contains(&a.fingerprint)) {
+            alert.status = AlertStatus::Resolved;
+            alert.resolved_at = Some(now);
+            notify.push(alert.clone());
+        }
+        
+        let cutoff = now - chrono::Duration::minutes(self.alerting.load().resolved_retention_minutes);
+        alerts.retain(|alert| alert.resolved_at.is_none_or(|resolved_at| resolved_at > cutoff));
+        
+        // Stored on every change of status; last_seen alone isn't worth a write
+        let kept: HashSet<&str> = alerts.iter().map(|a| a.id.as_str()).collect();
+        for (id, key) in known.iter().filter(|(id, _)| !kept.contains(id.as_str())) {
+            if let Err(e) = self.storage.delete(ALERT_COLLECTION, key).await {
+                warn!("Failed to delete alert {}: {}", id, e);
+            }
+        }
+        for alert in notify.iter().filter(|a| kept.contains(a.id.as_str())) {
+            self.store_alert(alert).await;
+        }
+        
+        if !notify.is_empty() {
+            self.notifier.load().notify(&notify, active_predictions);
+        }
         
         Ok(())
     }
     
+    async fn store_alert(&self, alert: &Alert) {
+        if let Err(e) = self.storage.put(ALERT_COLLECTION, &alert.storage_key(), alert).await {
+            warn!("Failed to store alert {}: {}", alert.id, e);
+        }
+    }
+    
+    async fn alert_group(&self, resource_id: &str) -> String {
+        let group_by = self.alerting.load().group_by;
+        match group_by {
+            AlertGroupBy::Resource => format!("resource:{}", resource_id),
+            AlertGroupBy::Policy => match self.scheduler.get_sla_policy(resource_id).await {
+                Some(policy) => format!("policy:{:?}", policy.priority),
+                None => "policy:none".to_string(),
+            },
+        }
+    }
+    
+    fn renotify_due(&self, alert: &Alert, now: chrono::DateTime<chrono::Utc>) -> bool {
+        let interval = self.alerting.load().renotify_interval_minutes;
+        interval > 0 && alert.last_notified.is_none_or(|at| now - at >= chrono::Duration::minutes(interval))
+    }
+    
     async fn update_performance_stats(&self, state: &mut DashboardState) -> Result<()> {
         state.performance_stats.predictions_per_second = 
             state.active_predictions.len() as f64 / 60.0; // Assuming 1-minute intervals
         
-        state.performance_stats.model_inference_time_ms = 
-            12.0 + rand::random::<f64>() * 8.0;
-        
-        state.performance_stats.data_processing_time_ms = 
-            5.0 + rand::random::<f64>() * 3.0;
-        
         state.performance_stats.total_predictions_today += 
             state.active_predictions.len() as u64;
         
-        // Update accuracy trend (keep last 100 points)
-        let new_accuracy = 0.85 + rand::random::<f64>() * 0.1;
-        state.performance_stats.accuracy_trend.push(new_accuracy);
-        if state.performance_stats.accuracy_trend.len() > 100 {
-            state.performance_stats.accuracy_trend.remove(0);
+        if let Some(confidence) = mean_confidence(&state.active_predictions) {
+            state.performance_stats.confidence_trend.push(confidence);
+            if state.performance_stats.confidence_trend.len() > 100 {
+                state.performance_stats.confidence_trend.remove(0);
+            }
         }
         
+        state.performance_stats.scheduler = self.scheduler.performance().await;
+        state.performance_stats.collection = self.metrics_collector.collection_stats();
+        
         Ok(())
     }
 }
 
+fn mean_confidence(predictions: &HashMap<String, PredictionData>) -> Option<f64> {
+    if predictions.is_empty() {
+        return None;
+    }
+    Some(predictions.values().map(|p| p.confidence).sum::<f64>() / predictions.len() as f64)
+}
+
+// Where the forecast ends up relative to now, within a band of noise
+fn determine_trend(current_value: f64, forecast: &[f64]) -> String {
+    let change = forecast.last().copied().unwrap_or(current_value) - current_value;
+    if change > 5.0 {
+        "Increasing".to_string()
+    } else if change < -5.0 {
+        "Decreasing".to_string()
+    } else {
+        "Stable".to_string()
+    }
+}
+
+// A condition found firing this round, by the alert it belongs to
+struct AlertCondition {
+    fingerprint: String,
+    rule: String,
+    group: String,
+    severity: AlertSeverity,
+    message: String,
+    resource_id: Option<String>,
+}
+
+// (rule, severity, message) for every alert rule the prediction trips
+fn firing_rules(resource_id: &str, prediction: &PredictionData) -> Vec<(&'static str, AlertSeverity, String)> {
+    let mut rules = Vec::new();
+    if prediction.current_value > 90.0 {
+        rules.push((
+            "high_utilization",
+            AlertSeverity::Critical,
+            format!("High resource utilization detected on {}: {:.1}%", resource_id, prediction.current_value),
+        ));
+    }
+    if prediction.confidence < 0.7 {
+        rules.push((
+            "low_confidence",
+            AlertSeverity::Warning,
+            format!("Low prediction confidence for {}: {:.1}%", resource_id, prediction.confidence * 100.0),
+        ));
+    }
+    rules
+}
+
 // API Handlers
-async fn serve_dashboard() -> Html<&'static str> {
-    Html(include_str!("../../static/dashboard.html"))
+// The page builds its API and WebSocket URLs from the base path it is given
+async fn serve_dashboard(Extension(client): Extension<ClientInfo>) -> Html<String> {
+    let base_path = client.base_path
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;");
+    Html(include_str!("../../static/dashboard.html").replace(
+        "<meta name=\"base-path\" content=\"\">",
+        &format!("<meta name=\"base-path\" content=\"{}\">", base_path),
+    ))
 }
 
 async fn get_predictions(State(server): State<DashboardServer>) -> impl IntoResponse {
@@ -325,14 +756,71 @@ async fn get_predictions(State(server): State<DashboardServer>) -> impl IntoResp
     Json(state.active_predictions.clone())
 }
 
+#[derive(Debug, Serialize)]
+struct StackPredictions {
+    stack_id: String,
+    stack_name: String,
+    mean_current_value: f64,
+    // Mean of the members' next predicted value
+    mean_predicted_value: f64,
+    predictions: Vec<PredictionData>,
+}
+
+// Predictions of servers created by Heat, one group per stack
+async fn get_stack_predictions(State(server): State<DashboardServer>) -> impl IntoResponse {
+    let state = server.dashboard_state.read().await;
+    let mut stacks: HashMap<&str, StackPredictions> = HashMap::new();
+    for prediction in state.active_predictions.values() {
+        let (Some(stack_id), Some(stack_name)) = (&prediction.stack_id, &prediction.stack_name) else {
+            continue;
+        };
+        stacks.entry(stack_id.as_str())
+            .or_insert_with(|| StackPredictions {
+                stack_id: stack_id.clone(),
+                stack_name: stack_name.clone(),
+                mean_current_value: 0.0,
+                mean_predicted_value: 0.0,
+                predictions: Vec::new(),
+            })
+            .predictions
+            .push(prediction.clone());
+    }
+    
+    let mut stacks: Vec<StackPredictions> = stacks.into_values().collect();
+    for stack in &mut stacks {
+        let members = stack.predictions.len() as f64;
+        stack.predictions.sort_by(|a, b| a.resource_id.cmp(&b.resource_id));
+        stack.mean_current_value = stack.predictions.iter().map(|p| p.current_value).sum::<f64>() / members;
+        stack.mean_predicted_value = stack.predictions.iter()
+            .filter_map(|p| p.predicted_values.first())
+            .sum::<f64>() / members;
+    }
+    stacks.sort_by(|a, b| a.stack_name.cmp(&b.stack_name));
+    Json(stacks)
+}
+
 async fn get_system_metrics(State(server): State<DashboardServer>) -> impl IntoResponse {
     let state = server.dashboard_state.read().await;
     Json(state.system_metrics.clone())
 }
 
-async fn get_alerts(State(server): State<DashboardServer>) -> impl IntoResponse {
+#[derive(Deserialize)]
+struct AlertQuery {
+    status: Option<AlertStatus>,
+    group: Option<String>,
+}
+
+async fn get_alerts(
+    State(server): State<DashboardServer>,
+    Query(query): Query<AlertQuery>,
+) -> impl IntoResponse {
     let state = server.dashboard_state.read().await;
-    Json(state.alerts.clone())
+    let alerts: Vec<Alert> = state.alerts.iter()
+        .filter(|a| query.status.is_none_or(|status| a.status == status))
+        .filter(|a| query.group.as_ref().is_none_or(|group| a.group == *group))
+        .cloned()
+        .collect();
+    Json(alerts)
 }
 
 async fn get_performance_stats(State(server): State<DashboardServer>) -> impl IntoResponse {
@@ -340,6 +828,28 @@ async fn get_performance_stats(State(server): State<DashboardServer>) -> impl In
     Json(state.performance_stats.clone())
 }
 
+async fn get_prometheus_metrics(State(server): State<DashboardServer>) -> impl IntoResponse {
+    server.prometheus.render()
+}
+
+#[derive(Serialize)]
+struct Health {
+    // "degraded" while any OpenStack API's circuit breaker isn't closed
+    status: &'static str,
+    circuit_breakers: Vec<BreakerStatus>,
+}
+
+// Unauthenticated, like /metrics, for load balancers and probes. It answers
+// 200 even when degraded: the service is up, only some calls fail fast
+async fn get_health(State(server): State<DashboardServer>) -> Json<Health> {
+    let circuit_breakers = server.metrics_collector.clouds().breaker_status();
+    let degraded = circuit_breakers.iter().any(|breaker| breaker.state != BreakerState::Closed);
+    Json(Health {
+        status: if degraded { "degraded" } else { "ok" },
+        circuit_breakers,
+    })
+}
+
 #[derive(Deserialize)]
 struct AcknowledgeParams {
     id: String,
@@ -353,17 +863,27 @@ async fn acknowledge_alert(
     
     if let Some(alert) = state.alerts.iter_mut().find(|a| a.id == params.id) {
         alert.acknowledged = true;
+        server.store_alert(alert).await;
         (StatusCode::OK, "Alert acknowledged")
     } else {
         (StatusCode::NOT_FOUND, "Alert not found")
     }
 }
 
+// require_auth has already vetted the upgrade request like any API call
 async fn websocket_handler(
     ws: WebSocketUpgrade,
     State(server): State<DashboardServer>,
-) -> impl IntoResponse {
+    Extension(shutdown): Extension<CancellationToken>,
+    principal: Option<Extension<Principal>>,
+) -> Response {
+    let slot = match server.websocket_handler.reserve_slot() {
+        Some(slot) => slot,
+        None => return (StatusCode::SERVICE_UNAVAILABLE, "Too many WebSocket connections").into_response(),
+    };
+    let principal = principal.map(|Extension(principal)| principal.name);
+    
     ws.on_upgrade(move |socket| async move {
-        server.websocket_handler.handle_connection(socket).await;
+        server.websocket_handler.handle_connection(socket, principal, slot, shutdown).await;
     })
 }
@@ -0,0 +1,71 @@
+use axum::{
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use serde::Deserialize;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::diagnostics::{self, ProfileFormat};
+use super::auth::Operator;
+use super::dashboard::DashboardServer;
+
+const DEFAULT_PROFILE_SECONDS: u64 = 30;
+
+#[derive(Deserialize)]
+pub struct ProfileQuery {
+    // Capped at diagnostics.max_profile_seconds
+    seconds: Option<u64>,
+    #[serde(default)]
+    format: ProfileFormat,
+}
+
+// Mounted under /debug when diagnostics.profiling is on
+pub fn routes() -> Router<DashboardServer> {
+    Router::new()
+        .route("/pprof/profile", get(cpu_profile))
+        .route("/pprof/heap", get(heap_profile))
+}
+
+// Blocks for the whole sampling period, then returns the profile
+async fn cpu_profile(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+    Query(query): Query<ProfileQuery>,
+) -> Response {
+    let seconds = query.seconds
+        .unwrap_or(DEFAULT_PROFILE_SECONDS)
+        .clamp(1, server.diagnostics.max_profile_seconds);
+    info!("{} started a {}s CPU profile", operator.name, seconds);
+    
+    match diagnostics::cpu_profile(Duration::from_secs(seconds), query.format).await {
+        Ok(Some(profile)) => profile_response("cpu", query.format, profile),
+        Ok(None) => (StatusCode::CONFLICT, "A CPU profile is already running").into_response(),
+        Err(e) => {
+            warn!("CPU profile failed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+async fn heap_profile(Operator(operator): Operator) -> Response {
+    info!("{} dumped a heap profile", operator.name);
+    match diagnostics::heap_profile().await {
+        Ok(profile) => profile_response("heap", ProfileFormat::Pprof, profile),
+        Err(e) => {
+            warn!("Heap profile failed: {}", e);
+            (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response()
+        }
+    }
+}
+
+fn profile_response(kind: &str, format: ProfileFormat, profile: Vec<u8>) -> Response {
+    let disposition = format!("attachment; filename=\"{}.{}\"", kind, format.extension());
+    (
+        [(header::CONTENT_TYPE, format.content_type().to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        profile,
+    ).into_response()
+}
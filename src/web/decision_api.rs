@@ -0,0 +1,44 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::error::StorageError;
+use super::dashboard::DashboardServer;
+
+#[derive(Deserialize)]
+pub struct RecentQuery {
+    #[serde(default = "default_limit")]
+    limit: usize,
+}
+
+fn default_limit() -> usize {
+    50
+}
+
+pub async fn list_decisions(
+    State(server): State<DashboardServer>,
+    Query(query): Query<RecentQuery>,
+) -> Response {
+    match server.scheduler.recent_decisions(query.limit).await {
+        Ok(decisions) => Json(decisions).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+pub async fn explain_decision(
+    State(server): State<DashboardServer>,
+    Path(decision_id): Path<String>,
+) -> Response {
+    let not_found = || (StatusCode::NOT_FOUND, format!("No explanation for decision {}", decision_id)).into_response();
+    match server.scheduler.decision_explanation(&decision_id).await {
+        Ok(Some(explanation)) => Json(explanation).into_response(),
+        Ok(None) => not_found(),
+        // No decision has an id the storage key check rejects
+        Err(e) if matches!(e.downcast_ref::<StorageError>(), Some(StorageError::InvalidKey(_))) => not_found(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
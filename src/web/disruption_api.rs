@@ -0,0 +1,65 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::scheduler::disruption::DisruptionBudget;
+use super::dashboard::DashboardServer;
+
+pub async fn list_budgets(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.list_disruption_budgets().await)
+}
+
+pub async fn get_budget(
+    State(server): State<DashboardServer>,
+    Path(name): Path<String>,
+) -> Response {
+    match server.scheduler.get_disruption_budget(&name).await {
+        Some(budget) => Json(budget).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No disruption budget {}", name)).into_response(),
+    }
+}
+
+pub async fn create_budget(
+    State(server): State<DashboardServer>,
+    Json(budget): Json<DisruptionBudget>,
+) -> Response {
+    if server.scheduler.get_disruption_budget(&budget.name).await.is_some() {
+        return (
+            StatusCode::CONFLICT,
+            format!("Disruption budget {} already exists", budget.name),
+        ).into_response();
+    }
+    
+    match server.scheduler.put_disruption_budget(budget).await {
+        Ok(budget) => (StatusCode::CREATED, Json(budget)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+pub async fn update_budget(
+    State(server): State<DashboardServer>,
+    Path(name): Path<String>,
+    Json(mut budget): Json<DisruptionBudget>,
+) -> Response {
+    // The path identifies the budget; a mismatching body name is ignored
+    budget.name = name;
+    
+    match server.scheduler.put_disruption_budget(budget).await {
+        Ok(budget) => Json(budget).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+pub async fn delete_budget(
+    State(server): State<DashboardServer>,
+    Path(name): Path<String>,
+) -> Response {
+    match server.scheduler.delete_disruption_budget(&name).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, format!("No disruption budget {}", name)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
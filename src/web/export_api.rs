@@ -0,0 +1,265 @@
+use axum::{
+    body::Body,
+    extract::{Query, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use tracing::{error, warn};
+
+use crate::metrics::history::HistoryMetric;
+use crate::scheduler::explain::DecisionExplanation;
+use crate::scheduler::sla_manager::SLAViolation;
+use super::dashboard::DashboardServer;
+
+#[derive(Deserialize)]
+pub struct ExportQuery {
+    // RFC 3339; the last day when omitted
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    resource: Option<String>,
+    #[serde(default)]
+    format: ExportFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ExportFormat {
+    Csv,
+    // One JSON object per line
+    #[default]
+    Ndjson,
+}
+
+// A record that can be written as an NDJSON line or a CSV row
+trait ExportRow: Serialize {
+    const COLUMNS: &'static [&'static str];
+    
+    fn csv_values(&self) -> Vec<String>;
+}
+
+#[derive(Debug, Serialize)]
+struct PredictionRow {
+    timestamp: DateTime<Utc>,
+    resource_id: String,
+    predicted_load: f64,
+    predicted_load_min: f64,
+    predicted_load_max: f64,
+    confidence: Option<f64>,
+    samples: u64,
+}
+
+impl ExportRow for PredictionRow {
+    const COLUMNS: &'static [&'static str] = &[
+        "timestamp", "resource_id", "predicted_load", "predicted_load_min", "predicted_load_max", "confidence", "samples",
+    ];
+    
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.timestamp.to_rfc3339(),
+            self.resource_id.clone(),
+            self.predicted_load.to_string(),
+            self.predicted_load_min.to_string(),
+            self.predicted_load_max.to_string(),
+            self.confidence.map(|c| c.to_string()).unwrap_or_default(),
+            self.samples.to_string(),
+        ]
+    }
+}
+
+impl ExportRow for SLAViolation {
+    const COLUMNS: &'static [&'static str] = &["timestamp", "resource_id", "violation_type", "severity", "resolved"];
+    
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.timestamp.to_rfc3339(),
+            self.resource_id.clone(),
+            format!("{:?}", self.violation_type),
+            self.severity.to_string(),
+            self.resolved.to_string(),
+        ]
+    }
+}
+
+// NDJSON carries the full explanation; CSV only the flat summary
+impl ExportRow for DecisionExplanation {
+    const COLUMNS: &'static [&'static str] = &[
+        "decision_id", "resource_id", "action", "status", "requested_by", "summary", "created_at", "updated_at",
+    ];
+    
+    fn csv_values(&self) -> Vec<String> {
+        vec![
+            self.decision_id.clone(),
+            self.resource_id.clone(),
+            self.action.clone(),
+            self.outcome.status().to_string(),
+            self.rationale.requested_by.clone().unwrap_or_default(),
+            self.rationale.summary.clone(),
+            self.created_at.to_rfc3339(),
+            self.updated_at.to_rfc3339(),
+        ]
+    }
+}
+
+// Predicted load per history interval, joined with the confidence recorded
+// alongside it
+pub async fn export_predictions(
+    State(server): State<DashboardServer>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    
+    let history = server.metrics_collector.history();
+    let resource_ids = match &query.resource {
+        Some(resource_id) => vec![resource_id.clone()],
+        None => history.resource_ids().await,
+    };
+    // One resource at a time so the whole export is never held in memory
+    let rows = stream::iter(resource_ids).then(move |resource_id| {
+        let history = history.clone();
+        async move {
+            let confidence: HashMap<DateTime<Utc>, f64> = history
+                .points(&resource_id, HistoryMetric::PredictionConfidence, from, to)
+                .await
+                .into_iter()
+                .map(|point| (point.timestamp, point.avg))
+                .collect();
+            let rows: Vec<PredictionRow> = history
+                .points(&resource_id, HistoryMetric::PredictedLoad, from, to)
+                .await
+                .into_iter()
+                .map(|point| PredictionRow {
+                    timestamp: point.timestamp,
+                    resource_id: resource_id.clone(),
+                    predicted_load: point.avg,
+                    predicted_load_min: point.min,
+                    predicted_load_max: point.max,
+                    confidence: confidence.get(&point.timestamp).copied(),
+                    samples: point.samples,
+                })
+                .collect();
+            stream::iter(rows)
+        }
+    }).flatten();
+    
+    export("predictions", query.format, rows)
+}
+
+pub async fn export_violations(
+    State(server): State<DashboardServer>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    
+    let violations: Vec<SLAViolation> = server.scheduler.sla_violations(from, to).await
+        .into_iter()
+        .filter(|v| query.resource.as_ref().is_none_or(|id| *id == v.resource_id))
+        .collect();
+    export("violations", query.format, stream::iter(violations))
+}
+
+// Decisions are loaded from storage as the response is written
+pub async fn export_decisions(
+    State(server): State<DashboardServer>,
+    Query(query): Query<ExportQuery>,
+) -> Response {
+    let (from, to) = match time_range(&query) {
+        Ok(range) => range,
+        Err(message) => return (StatusCode::BAD_REQUEST, message).into_response(),
+    };
+    
+    let scheduler = server.scheduler.clone();
+    let resource = query.resource.clone();
+    let ids = scheduler.decision_ids().await;
+    let rows = stream::iter(ids)
+        .then(move |id| {
+            let scheduler = scheduler.clone();
+            async move {
+                match scheduler.decision_explanation(&id).await {
+                    Ok(explanation) => explanation,
+                    Err(e) => {
+                        warn!("Skipping decision {} in export: {}", id, e);
+                        None
+                    }
+                }
+            }
+        })
+        .filter_map(move |explanation| {
+            let keep = explanation.filter(|e| {
+                e.created_at >= from && e.created_at < to
+                    && resource.as_ref().is_none_or(|id| *id == e.resource_id)
+            });
+            async move { keep }
+        });
+    
+    export("decisions", query.format, rows)
+}
+
+fn time_range(query: &ExportQuery) -> Result<(DateTime<Utc>, DateTime<Utc>), &'static str> {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::days(1));
+    if from >= to {
+        return Err("from must be before to");
+    }
+    Ok((from, to))
+}
+
+fn export<R, S>(name: &str, format: ExportFormat, rows: S) -> Response
+where
+    R: ExportRow,
+    S: Stream<Item = R> + Send + 'static,
+{
+    let (content_type, extension) = match format {
+        ExportFormat::Csv => ("text/csv; charset=utf-8", "csv"),
+        ExportFormat::Ndjson => ("application/x-ndjson", "ndjson"),
+    };
+    let header_line = match format {
+        ExportFormat::Csv => Some(csv_line(R::COLUMNS.iter().map(|c| c.to_string()).collect())),
+        ExportFormat::Ndjson => None,
+    };
+    
+    let lines = rows.filter_map(move |row| {
+        let line = match format {
+            ExportFormat::Csv => Some(csv_line(row.csv_values())),
+            ExportFormat::Ndjson => match serde_json::to_string(&row) {
+                Ok(json) => Some(json + "\n"),
+                Err(e) => {
+                    error!("Failed to serialize export row: {}", e);
+                    None
+                }
+            },
+        };
+        async move { line }
+    });
+    let body = stream::iter(header_line).chain(lines).map(Ok::<_, Infallible>);
+    
+    let disposition = format!("attachment; filename=\"{}.{}\"", name, extension);
+    (
+        [(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)],
+        Body::from_stream(body),
+    ).into_response()
+}
+
+// Fields with separators, quotes or line breaks are quoted per RFC 4180
+fn csv_line(values: Vec<String>) -> String {
+    let fields: Vec<String> = values.into_iter()
+        .map(|value| {
+            if value.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", value.replace('"', "\"\""))
+            } else {
+                value
+            }
+        })
+        .collect();
+    fields.join(",") + "\r\n"
+}
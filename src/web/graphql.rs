@@ -0,0 +1,440 @@
+use async_graphql::{
+    http::{GraphiQLSource, WebSocket as GraphQLWebSocket, WebSocketProtocols, WsMessage, ALL_WEBSOCKET_PROTOCOLS},
+    Context, EmptyMutation, Enum, Json as GraphQLJson, Object, Result as GraphQLResult, Schema, Subscription,
+};
+use axum::{
+    extract::{
+        ws::{CloseFrame, Message, WebSocketUpgrade},
+        State,
+    },
+    http::{header::SEC_WEBSOCKET_PROTOCOL, HeaderMap, StatusCode},
+    response::{Html, IntoResponse, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{future, stream, SinkExt, Stream, StreamExt};
+use std::collections::{BTreeSet, HashSet};
+use std::str::FromStr;
+use std::sync::Arc;
+use tokio::sync::broadcast::error::RecvError;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::metrics::history::{HistoryMetric, HistoryPoint, HistorySeries};
+use crate::scheduler::explain::DecisionExplanation;
+use crate::scheduler::sla_manager::{PenaltyModel, SLAPolicy};
+use super::dashboard::{Alert, AlertStatus, DashboardServer, DashboardState, PredictionData, SystemMetrics};
+use super::proxy::ClientInfo;
+use super::websocket::going_away;
+
+// How far back per-resource decision lookups search the journal
+const RESOURCE_DECISION_SCAN: usize = 500;
+
+// Read-only; changes still go through the REST endpoints
+pub type DashboardSchema = Schema<QueryRoot, EmptyMutation, SubscriptionRoot>;
+
+pub fn build_schema(server: DashboardServer) -> DashboardSchema {
+    Schema::build(QueryRoot, EmptyMutation, SubscriptionRoot)
+        .data(server)
+        .limit_depth(8)
+        .finish()
+}
+
+fn server<'a>(ctx: &Context<'a>) -> &'a DashboardServer {
+    ctx.data_unchecked::<DashboardServer>()
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    // Everything the dashboard knows about: predicted resources and
+    // resources with an SLA
+    async fn resources(&self, ctx: &Context<'_>) -> Vec<Resource> {
+        let server = server(ctx);
+        let mut ids: BTreeSet<String> = server.dashboard_state.read().await.active_predictions.keys().cloned().collect();
+        ids.extend(server.scheduler.list_sla_policies().await.into_iter().map(|p| p.resource_id));
+        ids.into_iter().map(|id| Resource { id }).collect()
+    }
+    
+    async fn resource(&self, id: String) -> Resource {
+        Resource { id }
+    }
+    
+    async fn predictions(&self, ctx: &Context<'_>) -> Vec<PredictionData> {
+        server(ctx).dashboard_state.read().await.active_predictions.values().cloned().collect()
+    }
+    
+    async fn system_metrics(&self, ctx: &Context<'_>) -> SystemMetrics {
+        server(ctx).dashboard_state.read().await.system_metrics.clone()
+    }
+    
+    async fn alerts(&self, ctx: &Context<'_>, acknowledged: Option<bool>, status: Option<AlertStatus>) -> Vec<Alert> {
+        server(ctx).dashboard_state.read().await.alerts.iter()
+            .filter(|a| acknowledged.is_none_or(|acknowledged| a.acknowledged == acknowledged))
+            .filter(|a| status.is_none_or(|status| a.status == status))
+            .cloned()
+            .collect()
+    }
+    
+    // Newest first
+    async fn decisions(&self, ctx: &Context<'_>, #[graphql(default = 50)] limit: usize) -> GraphQLResult<Vec<Decision>> {
+        let decisions = server(ctx).scheduler.recent_decisions(limit).await?;
+        Ok(decisions.into_iter().map(Decision).collect())
+    }
+    
+    async fn decision(&self, ctx: &Context<'_>, id: String) -> GraphQLResult<Option<Decision>> {
+        Ok(server(ctx).scheduler.decision_explanation(&id).await?.map(Decision))
+    }
+    
+    async fn sla_policies(&self, ctx: &Context<'_>) -> Vec<SlaPolicy> {
+        server(ctx).scheduler.list_sla_policies().await.into_iter().map(SlaPolicy).collect()
+    }
+}
+
+// Entry point for nested queries about one resource
+pub struct Resource {
+    id: String,
+}
+
+#[Object]
+impl Resource {
+    async fn id(&self) -> &str {
+        &self.id
+    }
+    
+    async fn prediction(&self, ctx: &Context<'_>) -> Option<PredictionData> {
+        server(ctx).dashboard_state.read().await.active_predictions.get(&self.id).cloned()
+    }
+    
+    async fn alerts(&self, ctx: &Context<'_>) -> Vec<Alert> {
+        server(ctx).dashboard_state.read().await.alerts.iter()
+            .filter(|a| a.resource_id.as_deref() == Some(self.id.as_str()))
+            .cloned()
+            .collect()
+    }
+    
+    async fn sla_policy(&self, ctx: &Context<'_>) -> Option<SlaPolicy> {
+        server(ctx).scheduler.get_sla_policy(&self.id).await.map(SlaPolicy)
+    }
+    
+    async fn decisions(&self, ctx: &Context<'_>, #[graphql(default = 20)] limit: usize) -> GraphQLResult<Vec<Decision>> {
+        let decisions = server(ctx).scheduler.recent_decisions(RESOURCE_DECISION_SCAN).await?;
+        Ok(decisions.into_iter()
+            .filter(|d| d.resource_id == self.id)
+            .take(limit)
+            .map(Decision)
+            .collect())
+    }
+    
+    // Defaults to the last hour; see /api/v1/history
+    async fn history(
+        &self,
+        ctx: &Context<'_>,
+        metric: Metric,
+        from: Option<DateTime<Utc>>,
+        to: Option<DateTime<Utc>>,
+        step_seconds: Option<i64>,
+    ) -> GraphQLResult<History> {
+        let to = to.unwrap_or_else(Utc::now);
+        let from = from.unwrap_or(to - Duration::hours(1));
+        if from >= to {
+            return Err("from must be before to".into());
+        }
+        let history = server(ctx).metrics_collector.history();
+        Ok(History(history.query(&self.id, metric.into(), from, to, step_seconds.filter(|s| *s > 0)).await))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Enum)]
+pub enum Metric {
+    CpuUtilization,
+    MemoryUtilization,
+    DiskReadBytes,
+    DiskWriteBytes,
+    NetworkRxBytes,
+    NetworkTxBytes,
+    PredictedLoad,
+    PredictionConfidence,
+}
+
+impl From<Metric> for HistoryMetric {
+    fn from(metric: Metric) -> Self {
+        match metric {
+            Metric::CpuUtilization => HistoryMetric::CpuUtilization,
+            Metric::MemoryUtilization => HistoryMetric::MemoryUtilization,
+            Metric::DiskReadBytes => HistoryMetric::DiskReadBytes,
+            Metric::DiskWriteBytes => HistoryMetric::DiskWriteBytes,
+            Metric::NetworkRxBytes => HistoryMetric::NetworkRxBytes,
+            Metric::NetworkTxBytes => HistoryMetric::NetworkTxBytes,
+            Metric::PredictedLoad => HistoryMetric::PredictedLoad,
+            Metric::PredictionConfidence => HistoryMetric::PredictionConfidence,
+        }
+    }
+}
+
+pub struct History(HistorySeries);
+
+#[Object]
+impl History {
+    async fn step_seconds(&self) -> i64 {
+        self.0.step_seconds
+    }
+    
+    async fn points(&self) -> Vec<Point> {
+        self.0.points.iter().cloned().map(Point).collect()
+    }
+}
+
+pub struct Point(HistoryPoint);
+
+#[Object]
+impl Point {
+    async fn timestamp(&self) -> DateTime<Utc> {
+        self.0.timestamp
+    }
+    
+    async fn avg(&self) -> f64 {
+        self.0.avg
+    }
+    
+    async fn min(&self) -> f64 {
+        self.0.min
+    }
+    
+    async fn max(&self) -> f64 {
+        self.0.max
+    }
+    
+    async fn samples(&self) -> u64 {
+        self.0.samples
+    }
+}
+
+pub struct Decision(DecisionExplanation);
+
+#[Object]
+impl Decision {
+    async fn id(&self) -> &str {
+        &self.0.decision_id
+    }
+    
+    async fn action(&self) -> &str {
+        &self.0.action
+    }
+    
+    async fn summary(&self) -> &str {
+        &self.0.rationale.summary
+    }
+    
+    async fn requested_by(&self) -> Option<&str> {
+        self.0.rationale.requested_by.as_deref()
+    }
+    
+    async fn risk_score(&self) -> Option<f64> {
+        self.0.rationale.risk.as_ref().map(|risk| risk.score)
+    }
+    
+    // executed, deferred, blocked, ...; details are in the explanation
+    async fn status(&self) -> &'static str {
+        self.0.outcome.status()
+    }
+    
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+    
+    async fn updated_at(&self) -> DateTime<Utc> {
+        self.0.updated_at
+    }
+    
+    // Same document as /api/v1/decisions/:id/explain
+    async fn explanation(&self) -> GraphQLJson<&DecisionExplanation> {
+        GraphQLJson(&self.0)
+    }
+    
+    async fn resource(&self) -> Resource {
+        Resource { id: self.0.resource_id.clone() }
+    }
+}
+
+pub struct SlaPolicy(SLAPolicy);
+
+#[Object]
+impl SlaPolicy {
+    async fn max_cpu_utilization(&self) -> f64 {
+        self.0.max_cpu_utilization
+    }
+    
+    async fn max_memory_utilization(&self) -> f64 {
+        self.0.max_memory_utilization
+    }
+    
+    async fn max_response_time_ms(&self) -> u64 {
+        self.0.max_response_time_ms
+    }
+    
+    async fn min_availability_percent(&self) -> f64 {
+        self.0.min_availability_percent
+    }
+    
+    async fn priority(&self) -> String {
+        format!("{:?}", self.0.priority)
+    }
+    
+    async fn deadline_minutes(&self) -> u32 {
+        self.0.deadline_minutes
+    }
+    
+    async fn penalty(&self) -> Option<GraphQLJson<&PenaltyModel>> {
+        self.0.penalty.as_ref().map(GraphQLJson)
+    }
+    
+    async fn collection_interval_seconds(&self) -> Option<u64> {
+        self.0.collection_interval_seconds
+    }
+    
+    async fn resource(&self) -> Resource {
+        Resource { id: self.0.resource_id.clone() }
+    }
+}
+
+// Snapshot pushed to subscribers on every dashboard refresh
+pub struct DashboardUpdate(Arc<DashboardState>);
+
+#[Object]
+impl DashboardUpdate {
+    async fn system_metrics(&self) -> &SystemMetrics {
+        &self.0.system_metrics
+    }
+    
+    async fn predictions(&self) -> Vec<PredictionData> {
+        self.0.active_predictions.values().cloned().collect()
+    }
+    
+    async fn alerts(&self) -> &[Alert] {
+        &self.0.alerts
+    }
+}
+
+pub struct SubscriptionRoot;
+
+#[Subscription]
+impl SubscriptionRoot {
+    async fn dashboard(&self, ctx: &Context<'_>) -> impl Stream<Item = DashboardUpdate> {
+        state_updates(server(ctx)).map(DashboardUpdate)
+    }
+    
+    async fn prediction(&self, ctx: &Context<'_>, resource_id: String) -> impl Stream<Item = PredictionData> {
+        state_updates(server(ctx))
+            .filter_map(move |state| future::ready(state.active_predictions.get(&resource_id).cloned()))
+    }
+    
+    // Each alert once, when it first appears
+    async fn alert_raised(&self, ctx: &Context<'_>) -> impl Stream<Item = Alert> {
+        let mut seen: HashSet<String> = server(ctx).dashboard_state.read().await.alerts.iter()
+            .map(|a| a.id.clone())
+            .collect();
+        state_updates(server(ctx)).flat_map(move |state| {
+            let raised: Vec<Alert> = state.alerts.iter()
+                .filter(|a| seen.insert(a.id.clone()))
+                .cloned()
+                .collect();
+            stream::iter(raised)
+        })
+    }
+}
+
+// Lagging subscribers skip to the latest state rather than erroring
+fn state_updates(server: &DashboardServer) -> impl Stream<Item = Arc<DashboardState>> {
+    stream::unfold(server.state_updates.subscribe(), |mut receiver| async move {
+        loop {
+            match receiver.recv().await {
+                Ok(state) => return Some((state, receiver)),
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
+
+pub async fn graphql_query(
+    Extension(schema): Extension<DashboardSchema>,
+    Json(request): Json<async_graphql::Request>,
+) -> Json<async_graphql::Response> {
+    Json(schema.execute(request).await)
+}
+
+pub async fn graphiql(Extension(client): Extension<ClientInfo>) -> Html<String> {
+    Html(GraphiQLSource::build()
+        .endpoint(&client.path("/graphql"))
+        .subscription_endpoint(&client.path("/graphql/ws"))
+        .finish())
+}
+
+// Subscriptions over graphql-transport-ws or the older graphql-ws protocol.
+// They share the dashboard WebSocket's connection slots and message budget
+pub async fn graphql_ws(
+    State(server): State<DashboardServer>,
+    Extension(schema): Extension<DashboardSchema>,
+    Extension(shutdown): Extension<CancellationToken>,
+    headers: HeaderMap,
+    ws: WebSocketUpgrade,
+) -> Response {
+    let slot = match server.websocket_handler.reserve_slot() {
+        Some(slot) => slot,
+        None => return (StatusCode::SERVICE_UNAVAILABLE, "Too many WebSocket connections").into_response(),
+    };
+    let mut budget = server.websocket_handler.message_budget();
+    let protocol = headers.get(SEC_WEBSOCKET_PROTOCOL)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').find_map(|p| WebSocketProtocols::from_str(p.trim()).ok()))
+        .unwrap_or(WebSocketProtocols::GraphQLWS);
+    
+    ws.protocols(ALL_WEBSOCKET_PROTOCOLS)
+        .on_upgrade(move |socket| async move {
+            let _slot = slot;
+            let (mut sink, stream) = socket.split();
+            let input = stream
+                .take_while(|message| future::ready(message.is_ok()))
+                .filter_map(move |message| {
+                    let payload = match message {
+                        Ok(Message::Text(text)) => Some(text.into_bytes()),
+                        Ok(Message::Binary(bytes)) => Some(bytes),
+                        _ => None,
+                    };
+                    // Over the budget a message is dropped, as on the dashboard socket
+                    let payload = payload.filter(|_| {
+                        let allowed = budget.take();
+                        if !allowed {
+                            debug!("GraphQL WebSocket client is sending too fast");
+                            ::metrics::counter!("websocket_messages_limited_total").increment(1);
+                        }
+                        allowed
+                    });
+                    future::ready(payload)
+                });
+            
+            let mut output = GraphQLWebSocket::new(schema, input, protocol);
+            loop {
+                let message = tokio::select! {
+                    message = output.next() => match message {
+                        Some(message) => message,
+                        None => break,
+                    },
+                    _ = shutdown.cancelled() => {
+                        let _ = sink.send(Message::Close(Some(going_away()))).await;
+                        break;
+                    }
+                };
+                let message = match message {
+                    WsMessage::Text(text) => Message::Text(text),
+                    WsMessage::Close(code, reason) => Message::Close(Some(CloseFrame { code, reason: reason.into() })),
+                };
+                if sink.send(message).await.is_err() {
+                    break;
+                }
+            }
+        })
+        .into_response()
+}
@@ -0,0 +1,39 @@
+use axum::{
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use serde::Deserialize;
+
+use crate::metrics::history::HistoryMetric;
+use super::dashboard::DashboardServer;
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    resource: String,
+    metric: HistoryMetric,
+    // RFC 3339; the last hour when omitted
+    from: Option<DateTime<Utc>>,
+    to: Option<DateTime<Utc>>,
+    // Seconds between points; coarsened as needed to bound the response
+    step: Option<i64>,
+}
+
+pub async fn get_history(
+    State(server): State<DashboardServer>,
+    Query(query): Query<HistoryQuery>,
+) -> Response {
+    let to = query.to.unwrap_or_else(Utc::now);
+    let from = query.from.unwrap_or(to - Duration::hours(1));
+    if from >= to {
+        return (StatusCode::BAD_REQUEST, "from must be before to").into_response();
+    }
+    if query.step.is_some_and(|step| step <= 0) {
+        return (StatusCode::BAD_REQUEST, "step must be a positive number of seconds").into_response();
+    }
+    
+    let history = server.metrics_collector.history();
+    Json(history.query(&query.resource, query.metric, from, to, query.step).await).into_response()
+}
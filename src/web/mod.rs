@@ -1,4 +1,24 @@
+pub mod auth;
 pub mod dashboard;
 pub mod websocket;
+pub mod scheduler_api;
+pub mod sla_api;
+pub mod decision_api;
+pub mod disruption_api;
+pub mod action_api;
+pub mod agent_api;
+pub mod sso;
+pub mod history_api;
+pub mod graphql;
+pub mod notifier;
+pub mod export_api;
+pub mod rate_limit;
+pub mod proxy;
+pub mod versioning;
+pub mod audit;
+pub mod capacity_api;
+pub mod resource_api;
+#[cfg(feature = "diagnostics")]
+pub mod debug_api;
 
 pub use dashboard::DashboardServer;
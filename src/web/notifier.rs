@@ -0,0 +1,329 @@
+use anyhow::Result;
+use chrono::Utc;
+use lettre::message::{header::ContentType, Mailbox, Message};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Tokio1Executor};
+use reqwest::Client as HttpClient;
+use serde::Serialize;
+use serde_json::json;
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{debug, error, info};
+
+use crate::config::{AlertChannelConfig, AlertChannelKind, AlertingConfig};
+use crate::error::{DeliveryError, RetryPolicy};
+use crate::scheduler::sla_notifier::SLANotifier;
+use super::dashboard::{Alert, AlertSeverity, AlertStatus, PredictionData};
+
+// Routes dashboard alerts as they fire and resolve to Slack, email, PagerDuty
+// and plain webhooks. Each channel picks alerts by severity and label,
+// renders its own template, is rate limited, and retries failed deliveries
+// with backoff.
+pub struct AlertNotifier {
+    channels: Vec<Arc<Channel>>,
+    http_client: HttpClient,
+}
+
+struct Channel {
+    config: AlertChannelConfig,
+    mailer: Option<AsyncSmtpTransport<Tokio1Executor>>,
+    // When alerts were sent within the last minute
+    recent: Mutex<VecDeque<Instant>>,
+}
+
+// An alert with the labels channels match on and templates use
+#[derive(Debug, Clone, Serialize)]
+struct LabeledAlert {
+    #[serde(flatten)]
+    alert: Alert,
+    labels: HashMap<String, String>,
+}
+
+impl AlertNotifier {
+    pub fn new(config: &AlertingConfig) -> Result<Self> {
+        let http_client = HttpClient::builder()
+            .timeout(Duration::from_secs(10))
+            .build()?;
+        
+        let mut channels = Vec::new();
+        for channel in &config.channels {
+            let mailer = match &channel.kind {
+                AlertChannelKind::Email { smtp_host, smtp_port, username, password, .. } => {
+                    let mut builder = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(smtp_host)?
+                        .port(*smtp_port);
+                    if let Some(username) = username {
+                        builder = builder.credentials(Credentials::new(
+                            username.clone(),
+                            password.clone().unwrap_or_default(),
+                        ));
+                    }
+                    Some(builder.build())
+                }
+                _ => None,
+            };
+            channels.push(Arc::new(Channel {
+                config: channel.clone(),
+                mailer,
+                recent: Mutex::new(VecDeque::new()),
+            }));
+        }
+        if !channels.is_empty() {
+            info!("Alert notifications go to {} channels", channels.len());
+        }
+        
+        Ok(Self {
+            channels,
+            http_client,
+        })
+    }
+    
+    // Fire-and-forget: each channel gets one notification per alert group,
+    // delivered on its own task. PagerDuty groups incidents itself, so it
+    // gets one event per alert instead.
+    pub fn notify(&self, alerts: &[Alert], predictions: &HashMap<String, PredictionData>) {
+        let mut groups: BTreeMap<&str, Vec<LabeledAlert>> = BTreeMap::new();
+        for alert in alerts {
+            let prediction = alert.resource_id.as_ref().and_then(|id| predictions.get(id));
+            groups.entry(alert.group.as_str()).or_default().push(LabeledAlert {
+                alert: alert.clone(),
+                labels: alert_labels(alert, prediction),
+            });
+        }
+        
+        for channel in &self.channels {
+            for members in groups.values() {
+                let matching: Vec<LabeledAlert> = members.iter()
+                    .filter(|a| channel.matches(a))
+                    .cloned()
+                    .collect();
+                if matching.is_empty() {
+                    continue;
+                }
+                let batches = match channel.config.kind {
+                    AlertChannelKind::PagerDuty { .. } => matching.into_iter().map(|a| vec![a]).collect(),
+                    _ => vec![matching],
+                };
+                for batch in batches {
+                    self.dispatch(channel, batch);
+                }
+            }
+        }
+    }
+    
+    // Resolutions are exempt from the rate limit, so a busy channel never
+    // leaves an incident open that has already cleared
+    fn dispatch(&self, channel: &Arc<Channel>, mut batch: Vec<LabeledAlert>) {
+        let is_resolved = |a: &LabeledAlert| a.alert.status == AlertStatus::Resolved;
+        if !batch.iter().all(is_resolved) && !channel.admit() {
+            let total = batch.len();
+            batch.retain(is_resolved);
+            debug!(
+                "Alert channel {} is over its rate limit, dropping {} firing alerts",
+                channel.config.name,
+                total - batch.len()
+            );
+            ::metrics::counter!("alert_notifications_total", "channel" => channel.config.name.clone(), "outcome" => "rate_limited").increment(1);
+            if batch.is_empty() {
+                return;
+            }
+        }
+        
+        let channel = channel.clone();
+        let http_client = self.http_client.clone();
+        tokio::spawn(async move {
+            let outcome = match channel.deliver(&http_client, &batch).await {
+                Ok(()) => "sent",
+                Err(e) => {
+                    error!("Giving up on alert channel {}: {}", channel.config.name, e);
+                    "failed"
+                }
+            };
+            ::metrics::counter!("alert_notifications_total", "channel" => channel.config.name.clone(), "outcome" => outcome).increment(1);
+        });
+    }
+}
+
+impl Channel {
+    fn matches(&self, alert: &LabeledAlert) -> bool {
+        let severity = format!("{:?}", alert.alert.severity);
+        let severity_matches = self.config.severities.is_empty()
+            || self.config.severities.iter().any(|s| s.eq_ignore_ascii_case(&severity));
+        let labels_match = self.config.matchers.iter().all(|(label, pattern)| {
+            alert.labels.get(label).is_some_and(|value| match pattern.strip_suffix('*') {
+                Some(prefix) => value.starts_with(prefix),
+                None => value == pattern,
+            })
+        });
+        
+        severity_matches && labels_match
+    }
+    
+    // Counts a notification against the per-minute limit, refusing it once full
+    fn admit(&self) -> bool {
+        if self.config.max_per_minute == 0 {
+            return true;
+        }
+        let now = Instant::now();
+        let mut recent = self.recent.lock().unwrap();
+        while recent.front().is_some_and(|sent| now.duration_since(*sent) >= Duration::from_secs(60)) {
+            recent.pop_front();
+        }
+        if recent.len() >= self.config.max_per_minute as usize {
+            return false;
+        }
+        recent.push_back(now);
+        true
+    }
+    
+    async fn deliver(&self, http_client: &HttpClient, batch: &[LabeledAlert]) -> Result<()> {
+        let retry = RetryPolicy::new(self.config.max_retries + 1, Duration::from_millis(self.config.initial_backoff_ms));
+        retry.run(&format!("Alert channel {}", self.config.name), || self.send(http_client, batch)).await?;
+        
+        debug!("Delivered {} alerts to {}", batch.len(), self.config.name);
+        Ok(())
+    }
+    
+    // One message for the whole batch, a line per alert
+    async fn send(&self, http_client: &HttpClient, batch: &[LabeledAlert]) -> Result<(), DeliveryError> {
+        let first = &batch[0];
+        let text = batch.iter()
+            .map(|a| render(&self.config.template, &a.alert, &a.labels))
+            .collect::<Vec<_>>()
+            .join("\n");
+        
+        match &self.config.kind {
+            AlertChannelKind::Slack { webhook_url, channel } => {
+                let mut body = json!({ "text": text });
+                if let Some(channel) = channel {
+                    body["channel"] = json!(channel);
+                }
+                SLANotifier::post(http_client.post(webhook_url).json(&body)).await
+            }
+            AlertChannelKind::PagerDuty { routing_key, events_url } => {
+                let alert = &first.alert;
+                let severity = match alert.severity {
+                    AlertSeverity::Critical => "critical",
+                    AlertSeverity::Warning => "warning",
+                    AlertSeverity::Info => "info",
+                };
+                let event_action = match alert.status {
+                    AlertStatus::Firing => "trigger",
+                    AlertStatus::Resolved => "resolve",
+                };
+                let body = json!({
+                    "routing_key": routing_key,
+                    "event_action": event_action,
+                    // Every episode of the same problem maps to one incident
+                    "dedup_key": alert.fingerprint,
+                    "payload": {
+                        "summary": text,
+                        "source": alert.resource_id.as_deref().unwrap_or("openstack-scheduler"),
+                        "severity": severity,
+                        "timestamp": alert.timestamp,
+                        "custom_details": first.labels,
+                    },
+                });
+                SLANotifier::post(http_client.post(events_url).json(&body)).await
+            }
+            AlertChannelKind::Webhook { url, secret, headers } => {
+                let body = json!({
+                    "event": "alerts",
+                    "group": first.alert.group,
+                    "text": text,
+                    "alerts": batch,
+                });
+                let body = body.to_string();
+                let mut request = http_client.post(url)
+                    .header("Content-Type", "application/json")
+                    .body(body.clone());
+                for (name, value) in headers {
+                    request = request.header(name, value);
+                }
+                if let Some(secret) = secret {
+                    let timestamp = Utc::now().timestamp().to_string();
+                    let signature = SLANotifier::sign(secret, &timestamp, &body)
+                        .map_err(|e| DeliveryError::Permanent(e.to_string()))?;
+                    request = request
+                        .header("X-Signature-Timestamp", timestamp)
+                        .header("X-Signature-256", format!("sha256={}", signature));
+                }
+                SLANotifier::post(request).await
+            }
+            AlertChannelKind::Email { from, to, subject, .. } => {
+                let mailer = match &self.mailer {
+                    Some(mailer) => mailer,
+                    None => return Err(DeliveryError::Permanent("SMTP transport not configured".to_string())),
+                };
+                let mut subject = render(subject, &first.alert, &first.labels);
+                if batch.len() > 1 {
+                    subject.push_str(&format!(" (+{} more)", batch.len() - 1));
+                }
+                let mut message = Message::builder()
+                    .from(mailbox(from)?)
+                    .subject(subject)
+                    .header(ContentType::TEXT_PLAIN);
+                for recipient in to {
+                    message = message.to(mailbox(recipient)?);
+                }
+                let message = message.body(text)
+                    .map_err(|e| DeliveryError::Permanent(e.to_string()))?;
+                
+                match mailer.send(message).await {
+                    Ok(_) => Ok(()),
+                    Err(e) if e.is_permanent() => Err(DeliveryError::Permanent(e.to_string())),
+                    Err(e) => Err(DeliveryError::Transient { message: e.to_string(), retry_after: None }),
+                }
+            }
+        }
+    }
+}
+
+fn mailbox(address: &str) -> Result<Mailbox, DeliveryError> {
+    address.parse()
+        .map_err(|e| DeliveryError::Permanent(format!("invalid address {}: {}", address, e)))
+}
+
+// Labels channels can match on and templates can use
+fn alert_labels(alert: &Alert, prediction: Option<&PredictionData>) -> HashMap<String, String> {
+    let mut labels = HashMap::new();
+    labels.insert("severity".to_string(), format!("{:?}", alert.severity));
+    labels.insert("status".to_string(), format!("{:?}", alert.status));
+    labels.insert("rule".to_string(), alert.rule.clone());
+    labels.insert("group".to_string(), alert.group.clone());
+    if let Some(resource_id) = &alert.resource_id {
+        labels.insert("resource_id".to_string(), resource_id.clone());
+    }
+    if let Some(prediction) = prediction {
+        labels.insert("resource_type".to_string(), prediction.resource_type.clone());
+        if let Some(project_id) = &prediction.project_id {
+            labels.insert("project_id".to_string(), project_id.clone());
+        }
+    }
+    labels
+}
+
+// Replaces {{name}} with the label or alert field of that name; unknown
+// names render empty
+fn render(template: &str, alert: &Alert, labels: &HashMap<String, String>) -> String {
+    let mut rendered = String::with_capacity(template.len());
+    let mut rest = template;
+    
+    while let Some(start) = rest.find("{{") {
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        rendered.push_str(&rest[..start]);
+        let name = rest[start + 2..end].trim();
+        match name {
+            "message" => rendered.push_str(&alert.message),
+            "id" => rendered.push_str(&alert.id),
+            "timestamp" => rendered.push_str(&alert.timestamp.to_rfc3339()),
+            _ => rendered.push_str(labels.get(name).map(String::as_str).unwrap_or("")),
+        }
+        rest = &rest[end + 2..];
+    }
+    rendered.push_str(rest);
+    rendered
+}
@@ -0,0 +1,186 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{ApiConfig, CorsConfig};
+use super::dashboard::DashboardServer;
+
+// Where the app is mounted and which proxies in front of it are trusted
+pub struct ProxySettings {
+    base_path: String,
+    trusted: Vec<(IpAddr, u8)>,
+}
+
+// How the browser reached us once trusted proxies are accounted for; every
+// request carries one as an extension
+#[derive(Debug, Clone)]
+pub struct ClientInfo {
+    pub address: IpAddr,
+    // HTTPS as far as the browser is concerned, even if the proxy isn't
+    pub secure: bool,
+    // Path the app is served under as the browser sees it; empty at the root
+    pub base_path: String,
+}
+
+impl ClientInfo {
+    // Browser-facing URL path for a path of ours
+    pub fn path(&self, path: &str) -> String {
+        format!("{}{}", self.base_path, path)
+    }
+}
+
+impl ProxySettings {
+    pub fn new(config: &ApiConfig) -> Result<Self> {
+        let trusted = config.trusted_proxies.iter()
+            .map(|entry| parse_network(entry).with_context(|| format!("Invalid trusted proxy {}", entry)))
+            .collect::<Result<_>>()?;
+        
+        Ok(Self {
+            base_path: normalize_base_path(&config.base_path),
+            trusted,
+        })
+    }
+    
+    pub fn base_path(&self) -> &str {
+        &self.base_path
+    }
+    
+    fn is_trusted(&self, address: IpAddr) -> bool {
+        self.trusted.iter().any(|(network, prefix)| in_network(address, *network, *prefix))
+    }
+    
+    fn client_info(&self, headers: &HeaderMap, peer: IpAddr) -> ClientInfo {
+        if !self.is_trusted(peer) {
+            return ClientInfo {
+                address: peer,
+                secure: false,
+                base_path: self.base_path.clone(),
+            };
+        }
+        
+        // Each proxy appends the address it got the request from, so the
+        // client is the last one not added by a proxy of ours
+        let hops: Vec<IpAddr> = headers.get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|hop| hop.trim().parse().ok())
+            .collect();
+        let address = hops.iter().rev()
+            .find(|hop| !self.is_trusted(**hop))
+            .or(hops.first())
+            .copied()
+            .unwrap_or(peer);
+        
+        let secure = first_value(headers, "x-forwarded-proto").is_some_and(|proto| proto.eq_ignore_ascii_case("https"));
+        let prefix = first_value(headers, "x-forwarded-prefix").map(normalize_base_path).unwrap_or_default();
+        
+        ClientInfo {
+            address,
+            secure,
+            base_path: format!("{}{}", prefix, self.base_path),
+        }
+    }
+}
+
+pub async fn resolve_client(
+    State(server): State<DashboardServer>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    mut request: Request,
+    next: Next,
+) -> Response {
+    let client = server.proxy.client_info(request.headers(), peer.ip());
+    request.extensions_mut().insert(client);
+    next.run(request).await
+}
+
+// None when no origins are configured
+pub fn cors_layer(config: &CorsConfig) -> Option<CorsLayer> {
+    if config.allowed_origins.is_empty() {
+        return None;
+    }
+    
+    let any_origin = config.allowed_origins.iter().any(|origin| origin == "*");
+    let allow_origin = if any_origin && config.allow_credentials {
+        // A literal * can't be combined with credentials
+        AllowOrigin::mirror_request()
+    } else if any_origin {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.allowed_origins.iter().filter_map(|origin| HeaderValue::from_str(origin).ok()))
+    };
+    
+    Some(CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE, Method::OPTIONS])
+        .allow_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            HeaderName::from_static("x-api-key"),
+            HeaderName::from_static("api-version"),
+        ])
+        .expose_headers([
+            header::RETRY_AFTER,
+            header::LINK,
+            HeaderName::from_static("api-version"),
+            HeaderName::from_static("deprecation"),
+            HeaderName::from_static("sunset"),
+            HeaderName::from_static("x-ratelimit-limit"),
+            HeaderName::from_static("x-ratelimit-remaining"),
+        ])
+        .allow_credentials(config.allow_credentials)
+        .max_age(Duration::from_secs(config.max_age_seconds)))
+}
+
+fn first_value<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.split(',').next())
+        .map(str::trim)
+        .filter(|value| !value.is_empty())
+}
+
+// "/metrics-service/" and "metrics-service" both become "/metrics-service";
+// "/" and "" mean the root
+fn normalize_base_path(path: &str) -> String {
+    let path = path.trim().trim_matches('/');
+    if path.is_empty() {
+        String::new()
+    } else {
+        format!("/{}", path)
+    }
+}
+
+// A bare address is a network of one
+fn parse_network(entry: &str) -> Result<(IpAddr, u8)> {
+    let (address, prefix) = match entry.split_once('/') {
+        Some((address, prefix)) => (address.parse::<IpAddr>()?, Some(prefix.parse::<u8>()?)),
+        None => (entry.parse::<IpAddr>()?, None),
+    };
+    let max = if address.is_ipv4() { 32 } else { 128 };
+    let prefix = prefix.unwrap_or(max);
+    if prefix > max {
+        anyhow::bail!("prefix length {} is longer than {}", prefix, max);
+    }
+    Ok((address, prefix))
+}
+
+fn in_network(address: IpAddr, network: IpAddr, prefix: u8) -> bool {
+    let (address, network, bits) = match (address, network) {
+        (IpAddr::V4(a), IpAddr::V4(n)) => (u32::from(a) as u128, u32::from(n) as u128, 32),
+        (IpAddr::V6(a), IpAddr::V6(n)) => (u128::from(a), u128::from(n), 128),
+        _ => return false,
+    };
+    if prefix == 0 {
+        return true;
+    }
+    let shift = bits - prefix as u32;
+    address >> shift == network >> shift
+}
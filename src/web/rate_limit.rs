@@ -0,0 +1,173 @@
+use axum::{
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension,
+};
+use dashmap::DashMap;
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+use crate::config::RateLimitConfig;
+use super::dashboard::DashboardServer;
+use super::proxy::ClientInfo;
+
+// Buckets idle this long are full again and can be forgotten
+const IDLE_BUCKET_TTL: Duration = Duration::from_secs(600);
+
+pub struct RateLimiter {
+    config: RateLimitConfig,
+    buckets: DashMap<String, Bucket>,
+}
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant,
+}
+
+enum Verdict {
+    Allowed { remaining: u32 },
+    Limited { retry_after: Duration },
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig) -> Self {
+        Self {
+            config,
+            buckets: DashMap::new(),
+        }
+    }
+    
+    pub fn is_enabled(&self) -> bool {
+        self.config.enabled && self.config.requests_per_minute > 0
+    }
+    
+    fn capacity(&self) -> f64 {
+        self.config.burst.max(1) as f64
+    }
+    
+    fn check(&self, client: &str) -> Verdict {
+        self.check_at(client, Instant::now())
+    }
+    
+    fn check_at(&self, client: &str, now: Instant) -> Verdict {
+        let refill_per_second = self.config.requests_per_minute as f64 / 60.0;
+        let capacity = self.capacity();
+        let mut bucket = self.buckets.entry(client.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            updated: now,
+        });
+        
+        let elapsed = now.duration_since(bucket.updated).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * refill_per_second).min(capacity);
+        bucket.updated = now;
+        
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Verdict::Allowed { remaining: bucket.tokens as u32 }
+        } else {
+            let retry_after = Duration::from_secs_f64((1.0 - bucket.tokens) / refill_per_second);
+            Verdict::Limited { retry_after }
+        }
+    }
+    
+    pub fn prune(&self) {
+        let now = Instant::now();
+        self.buckets.retain(|_, bucket| now.duration_since(bucket.updated) < IDLE_BUCKET_TTL);
+        debug!("Rate limiter tracks {} clients", self.buckets.len());
+    }
+    
+    // Authenticated clients are limited per key or user wherever they
+    // connect from; everyone else per address
+    fn client_key(&self, server: &DashboardServer, headers: &HeaderMap, client: &ClientInfo) -> String {
+        match server.auth.authenticate(headers) {
+            Some(principal) => format!("principal:{}", principal.name),
+            None => format!("ip:{}", client.address),
+        }
+    }
+}
+
+pub async fn limit_requests(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let limiter = server.rate_limiter.clone();
+    if !limiter.is_enabled() {
+        return next.run(request).await;
+    }
+    
+    let client = limiter.client_key(&server, request.headers(), &client);
+    let limit = HeaderValue::from(limiter.capacity() as u32);
+    match limiter.check(&client) {
+        Verdict::Allowed { remaining } => {
+            let mut response = next.run(request).await;
+            let headers = response.headers_mut();
+            headers.insert("x-ratelimit-limit", limit);
+            headers.insert("x-ratelimit-remaining", HeaderValue::from(remaining));
+            response
+        }
+        Verdict::Limited { retry_after } => {
+            debug!("Rate limited {}", client);
+            ::metrics::counter!("api_rate_limited_total").increment(1);
+            let retry_after = retry_after.as_secs_f64().ceil().max(1.0) as u64;
+            let mut response = (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded").into_response();
+            let headers = response.headers_mut();
+            headers.insert("retry-after", HeaderValue::from(retry_after));
+            headers.insert("x-ratelimit-limit", limit);
+            headers.insert("x-ratelimit-remaining", HeaderValue::from(0u32));
+            response
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn limiter(requests_per_minute: u32, burst: u32) -> RateLimiter {
+        RateLimiter::new(RateLimitConfig {
+            enabled: true,
+            requests_per_minute,
+            burst,
+        })
+    }
+    
+    fn allowed(verdict: Verdict) -> bool {
+        matches!(verdict, Verdict::Allowed { .. })
+    }
+    
+    #[test]
+    fn allows_a_burst_then_limits() {
+        let limiter = limiter(60, 2);
+        let now = Instant::now();
+        
+        assert!(allowed(limiter.check_at("a", now)));
+        assert!(allowed(limiter.check_at("a", now)));
+        match limiter.check_at("a", now) {
+            Verdict::Limited { retry_after } => assert_eq!(retry_after, Duration::from_secs(1)),
+            Verdict::Allowed { .. } => panic!("third request in the same instant was allowed"),
+        }
+        // Other clients have their own bucket
+        assert!(allowed(limiter.check_at("b", now)));
+    }
+    
+    #[test]
+    fn refills_at_the_sustained_rate_up_to_the_burst() {
+        let limiter = limiter(60, 2);
+        let now = Instant::now();
+        limiter.check_at("a", now);
+        limiter.check_at("a", now);
+        
+        assert!(!allowed(limiter.check_at("a", now + Duration::from_millis(500))));
+        assert!(allowed(limiter.check_at("a", now + Duration::from_secs(1))));
+        
+        // Idle for much longer than the burst takes to refill
+        let later = now + Duration::from_secs(60);
+        assert!(allowed(limiter.check_at("a", later)));
+        assert!(allowed(limiter.check_at("a", later)));
+        assert!(!allowed(limiter.check_at("a", later)));
+    }
+}
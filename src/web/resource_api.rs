@@ -0,0 +1,16 @@
+use axum::{
+    extract::{Query, State},
+    response::IntoResponse,
+    Json,
+};
+
+use crate::metrics::lifecycle::ChangelogQuery;
+use super::dashboard::DashboardServer;
+
+// Resources this member started or stopped collecting, newest first
+pub async fn get_changelog(
+    State(server): State<DashboardServer>,
+    Query(query): Query<ChangelogQuery>,
+) -> impl IntoResponse {
+    Json(server.metrics_collector.changelog().query(&query))
+}
@@ -0,0 +1,135 @@
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Deserialize;
+
+use crate::config::{ScoringPreset, ScoringWeights};
+use crate::scheduler::control::{ControlState, ExecutionMode};
+use crate::scheduler::policy::Aggressiveness;
+use super::auth::Operator;
+use super::dashboard::DashboardServer;
+
+#[derive(Deserialize)]
+pub struct ScoringUpdate {
+    preset: ScoringPreset,
+    weights: Option<ScoringWeights>,
+}
+
+#[derive(Deserialize)]
+pub struct PauseRequest {
+    reason: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct ModeUpdate {
+    mode: ExecutionMode,
+}
+
+#[derive(Deserialize)]
+pub struct AggressivenessUpdate {
+    aggressiveness: Aggressiveness,
+}
+
+pub async fn get_scoring(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.scoring_strategy())
+}
+
+pub async fn update_scoring(
+    State(server): State<DashboardServer>,
+    Json(update): Json<ScoringUpdate>,
+) -> Response {
+    match server.scheduler.set_scoring_strategy(update.preset, update.weights) {
+        Ok(strategy) => Json(strategy).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+pub async fn get_last_simulation(State(server): State<DashboardServer>) -> Response {
+    match server.scheduler.last_simulation().await {
+        Some(report) => Json(report).into_response(),
+        None => (StatusCode::NOT_FOUND, "No plan has been simulated yet").into_response(),
+    }
+}
+
+pub async fn get_last_rebalance(State(server): State<DashboardServer>) -> Response {
+    match server.scheduler.last_rebalance().await {
+        Some(report) => Json(report).into_response(),
+        None => (StatusCode::NOT_FOUND, "No rebalance has been planned yet").into_response(),
+    }
+}
+
+pub async fn get_policy_rules(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.policy_rules())
+}
+
+pub async fn get_policy_blocks(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.policy_blocks().await)
+}
+
+pub async fn get_policy_overrides(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.policy_overrides())
+}
+
+pub async fn get_blackouts(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.blackouts())
+}
+
+pub async fn get_preemptions(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.preemption_audit_log().await)
+}
+
+pub async fn get_deadline_stats(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.deadline_stats().await)
+}
+
+pub async fn get_leadership(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.leadership())
+}
+
+// Control switches together with the queue and in-flight migrations
+pub async fn get_status(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.status().await)
+}
+
+// The body is optional; an empty POST pauses without a reason
+pub async fn pause(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+    request: Option<Json<PauseRequest>>,
+) -> Response {
+    let reason = request.and_then(|Json(request)| request.reason);
+    control_response(server.scheduler.pause(&operator.name, reason).await)
+}
+
+pub async fn resume(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+) -> Response {
+    control_response(server.scheduler.resume(&operator.name).await)
+}
+
+pub async fn update_mode(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+    Json(update): Json<ModeUpdate>,
+) -> Response {
+    control_response(server.scheduler.set_execution_mode(&operator.name, update.mode).await)
+}
+
+pub async fn update_aggressiveness(
+    State(server): State<DashboardServer>,
+    Operator(operator): Operator,
+    Json(update): Json<AggressivenessUpdate>,
+) -> Response {
+    control_response(server.scheduler.set_aggressiveness(&operator.name, update.aggressiveness).await)
+}
+
+fn control_response(result: anyhow::Result<ControlState>) -> Response {
+    match result {
+        Ok(state) => Json(state).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
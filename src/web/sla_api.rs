@@ -0,0 +1,69 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::scheduler::sla_manager::SLAPolicy;
+use super::dashboard::DashboardServer;
+
+pub async fn list_policies(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.list_sla_policies().await)
+}
+
+pub async fn list_error_budgets(State(server): State<DashboardServer>) -> impl IntoResponse {
+    Json(server.scheduler.error_budgets().await)
+}
+
+pub async fn get_policy(
+    State(server): State<DashboardServer>,
+    Path(resource_id): Path<String>,
+) -> Response {
+    match server.scheduler.get_sla_policy(&resource_id).await {
+        Some(policy) => Json(policy).into_response(),
+        None => (StatusCode::NOT_FOUND, format!("No SLA policy for {}", resource_id)).into_response(),
+    }
+}
+
+pub async fn create_policy(
+    State(server): State<DashboardServer>,
+    Json(policy): Json<SLAPolicy>,
+) -> Response {
+    if server.scheduler.get_sla_policy(&policy.resource_id).await.is_some() {
+        return (
+            StatusCode::CONFLICT,
+            format!("SLA policy for {} already exists", policy.resource_id),
+        ).into_response();
+    }
+    
+    match server.scheduler.put_sla_policy(policy).await {
+        Ok(policy) => (StatusCode::CREATED, Json(policy)).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+pub async fn update_policy(
+    State(server): State<DashboardServer>,
+    Path(resource_id): Path<String>,
+    Json(mut policy): Json<SLAPolicy>,
+) -> Response {
+    // The path identifies the policy; a mismatching body id is ignored
+    policy.resource_id = resource_id;
+    
+    match server.scheduler.put_sla_policy(policy).await {
+        Ok(policy) => Json(policy).into_response(),
+        Err(e) => (StatusCode::BAD_REQUEST, e.to_string()).into_response(),
+    }
+}
+
+pub async fn delete_policy(
+    State(server): State<DashboardServer>,
+    Path(resource_id): Path<String>,
+) -> Response {
+    match server.scheduler.delete_sla_policy(&resource_id).await {
+        Ok(true) => StatusCode::NO_CONTENT.into_response(),
+        Ok(false) => (StatusCode::NOT_FOUND, format!("No SLA policy for {}", resource_id)).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
@@ -0,0 +1,419 @@
+use anyhow::{Context, Result};
+use axum::{
+    extract::{Query, State},
+    http::{
+        header::{COOKIE, SET_COOKIE},
+        HeaderMap, StatusCode,
+    },
+    response::{IntoResponse, Redirect, Response},
+    Extension, Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use dashmap::DashMap;
+use rand::Rng;
+use reqwest::{Client as HttpClient, Url};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::sync::OnceCell;
+use tracing::{info, warn};
+
+use crate::config::{ApiRole, SsoConfig, SsoProvider};
+use super::auth::{self, Principal};
+use super::dashboard::DashboardServer;
+use super::proxy::ClientInfo;
+use super::versioning;
+
+const STATE_COOKIE: &str = "scheduler_sso_state";
+// How long a user has to finish signing in at the OIDC provider
+const STATE_TTL_MINUTES: i64 = 10;
+
+// Delegates dashboard sign-in to Keystone or an OIDC provider and maps the
+// roles it reports onto dashboard roles
+pub struct SsoLogin {
+    config: SsoConfig,
+    http_client: HttpClient,
+    discovery: OnceCell<OidcDiscovery>,
+    // Outstanding OIDC states -> when the redirect was issued
+    pending: DashMap<String, DateTime<Utc>>,
+}
+
+// Who the provider says the user is
+struct Identity {
+    name: String,
+    roles: Vec<String>,
+    project: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct OidcDiscovery {
+    authorization_endpoint: String,
+    token_endpoint: String,
+    userinfo_endpoint: String,
+}
+
+#[derive(Deserialize)]
+struct OidcTokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct KeystoneTokenResponse {
+    token: KeystoneToken,
+}
+
+#[derive(Deserialize)]
+struct KeystoneToken {
+    user: KeystoneNamed,
+    project: Option<KeystoneNamed>,
+    #[serde(default)]
+    roles: Vec<KeystoneNamed>,
+}
+
+#[derive(Deserialize)]
+struct KeystoneNamed {
+    name: String,
+}
+
+// Either credentials or an existing Keystone token, e.g. from the CLI
+#[derive(Deserialize)]
+pub struct KeystoneLoginRequest {
+    username: Option<String>,
+    password: Option<String>,
+    domain: Option<String>,
+    project: Option<String>,
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct OidcCallback {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct SsoStatus {
+    enabled: bool,
+    provider: Option<SsoProvider>,
+    // Behind the proxy's base path, if any
+    login_url: Option<String>,
+}
+
+impl SsoLogin {
+    pub fn new(config: SsoConfig) -> Self {
+        if config.enabled {
+            info!(
+                "Dashboard single sign-on through {:?} with {} role mappings",
+                config.provider,
+                config.role_mappings.len()
+            );
+        }
+        Self {
+            config,
+            http_client: HttpClient::builder()
+                .timeout(std::time::Duration::from_secs(10))
+                .build()
+                .unwrap_or_default(),
+            discovery: OnceCell::new(),
+            pending: DashMap::new(),
+        }
+    }
+    
+    fn enabled_for(&self, provider: SsoProvider) -> bool {
+        self.config.enabled && self.config.provider == provider
+    }
+    
+    pub async fn keystone_login(&self, request: &KeystoneLoginRequest) -> Result<Principal> {
+        let keystone_url = self.config.keystone_url.as_deref()
+            .context("No keystone_url configured for single sign-on")?
+            .trim_end_matches('/');
+        let tokens_url = format!("{}/v3/auth/tokens", keystone_url);
+        
+        let response = match (&request.token, &request.username, &request.password) {
+            // A token may validate itself
+            (Some(token), _, _) => self.http_client
+                .get(&tokens_url)
+                .header("X-Auth-Token", token)
+                .header("X-Subject-Token", token)
+                .send()
+                .await?,
+            (None, Some(username), Some(password)) => {
+                let domain = request.domain.as_deref().unwrap_or(&self.config.default_domain);
+                let mut auth = json!({
+                    "identity": {
+                        "methods": ["password"],
+                        "password": {
+                            "user": { "name": username, "domain": { "name": domain }, "password": password }
+                        }
+                    }
+                });
+                // Without a scope Keystone falls back to the user's default project
+                if let Some(project) = &request.project {
+                    auth["scope"] = json!({ "project": { "name": project, "domain": { "name": domain } } });
+                }
+                self.http_client
+                    .post(&tokens_url)
+                    .json(&json!({ "auth": auth }))
+                    .send()
+                    .await?
+            }
+            _ => anyhow::bail!("Either a token or a username and password is required"),
+        };
+        
+        if !response.status().is_success() {
+            anyhow::bail!("Keystone rejected the login: {}", response.status());
+        }
+        let token = response.json::<KeystoneTokenResponse>().await?.token;
+        self.authorize(Identity {
+            name: token.user.name,
+            roles: token.roles.into_iter().map(|role| role.name).collect(),
+            project: token.project.map(|project| project.name),
+        })
+    }
+    
+    // Starts an authorization code flow; returns the provider URL and the state
+    // the callback must come back with
+    pub async fn oidc_authorize_url(&self) -> Result<(String, String)> {
+        let discovery = self.discovery().await?;
+        let client_id = self.config.client_id.as_deref().context("No client_id configured for single sign-on")?;
+        let redirect_url = self.config.redirect_url.as_deref().context("No redirect_url configured for single sign-on")?;
+        
+        let now = Utc::now();
+        self.pending.retain(|_, issued| now - *issued < Duration::minutes(STATE_TTL_MINUTES));
+        let state = hex::encode(rand::thread_rng().gen::<[u8; 16]>());
+        self.pending.insert(state.clone(), now);
+        
+        let url = Url::parse_with_params(
+            &discovery.authorization_endpoint,
+            &[
+                ("response_type", "code"),
+                ("client_id", client_id),
+                ("redirect_uri", redirect_url),
+                ("scope", &self.config.scopes.join(" ")),
+                ("state", &state),
+            ],
+        )?;
+        Ok((url.to_string(), state))
+    }
+    
+    pub async fn oidc_callback(&self, code: &str, state: &str, cookie_state: Option<&str>) -> Result<Principal> {
+        // The state must be one we issued, to this browser, recently
+        let issued = self.pending.remove(state).map(|(_, issued)| issued);
+        if cookie_state != Some(state)
+            || issued.is_none_or(|issued| Utc::now() - issued >= Duration::minutes(STATE_TTL_MINUTES))
+        {
+            anyhow::bail!("Unknown or expired sign-in attempt");
+        }
+        
+        let discovery = self.discovery().await?;
+        let response = self.http_client
+            .post(&discovery.token_endpoint)
+            .form(&[
+                ("grant_type", "authorization_code"),
+                ("code", code),
+                ("redirect_uri", self.config.redirect_url.as_deref().unwrap_or_default()),
+                ("client_id", self.config.client_id.as_deref().unwrap_or_default()),
+                ("client_secret", self.config.client_secret.as_deref().unwrap_or_default()),
+            ])
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Code exchange failed: {}", response.status());
+        }
+        let access_token = response.json::<OidcTokenResponse>().await?.access_token;
+        
+        // Userinfo comes straight from the provider over TLS, so its claims can
+        // be trusted without verifying an ID token signature
+        let claims: Value = self.http_client
+            .get(&discovery.userinfo_endpoint)
+            .bearer_auth(access_token)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        
+        let name = ["preferred_username", "email", "sub"].iter()
+            .find_map(|claim| claims.get(claim).and_then(Value::as_str))
+            .context("Userinfo has no subject")?
+            .to_string();
+        let roles = match claim(&claims, &self.config.roles_claim) {
+            Some(Value::Array(values)) => values.iter().filter_map(Value::as_str).map(str::to_string).collect(),
+            Some(Value::String(value)) => vec![value.clone()],
+            _ => Vec::new(),
+        };
+        let project = claim(&claims, &self.config.project_claim)
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        self.authorize(Identity { name, roles, project })
+    }
+    
+    async fn discovery(&self) -> Result<&OidcDiscovery> {
+        self.discovery.get_or_try_init(|| async {
+            let issuer = self.config.issuer_url.as_deref()
+                .context("No issuer_url configured for single sign-on")?
+                .trim_end_matches('/');
+            let discovery = self.http_client
+                .get(format!("{}/.well-known/openid-configuration", issuer))
+                .send()
+                .await?
+                .error_for_status()?
+                .json::<OidcDiscovery>()
+                .await?;
+            Ok(discovery)
+        })
+        .await
+    }
+    
+    // Highest role any mapping grants; users no mapping covers get no access
+    fn authorize(&self, identity: Identity) -> Result<Principal> {
+        let granted: Vec<ApiRole> = self.config.role_mappings.iter()
+            .filter(|mapping| identity.roles.contains(&mapping.role))
+            .filter(|mapping| mapping.project.is_none() || mapping.project == identity.project)
+            .map(|mapping| mapping.grants)
+            .collect();
+        
+        let role = if granted.contains(&ApiRole::Operator) {
+            ApiRole::Operator
+        } else if granted.contains(&ApiRole::Viewer) {
+            ApiRole::Viewer
+        } else {
+            anyhow::bail!(
+                "{} has no dashboard access with roles [{}] in project {}",
+                identity.name,
+                identity.roles.join(", "),
+                identity.project.as_deref().unwrap_or("none")
+            );
+        };
+        Ok(Principal { name: identity.name, role })
+    }
+}
+
+// Dotted paths reach nested claims, e.g. realm_access.roles
+fn claim<'a>(claims: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.').try_fold(claims, |value, key| value.get(key))
+}
+
+fn state_cookie(headers: &HeaderMap) -> Option<String> {
+    headers.get_all(COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == STATE_COOKIE)
+        .map(|(_, state)| state.to_string())
+}
+
+fn start_session(server: &DashboardServer, principal: Principal) -> Result<(String, Principal, DateTime<Utc>)> {
+    let (token, expires_at) = server.auth.issue_sso_session(&principal)?;
+    info!("Dashboard user {} signed in with {:?} role through single sign-on", principal.name, principal.role);
+    ::metrics::counter!("dashboard_sso_logins_total", "outcome" => "success").increment(1);
+    Ok((token, principal, expires_at))
+}
+
+fn rejected(error: anyhow::Error) -> Response {
+    warn!("Single sign-on failed: {}", error);
+    ::metrics::counter!("dashboard_sso_logins_total", "outcome" => "rejected").increment(1);
+    (StatusCode::UNAUTHORIZED, error.to_string()).into_response()
+}
+
+// Lets the dashboard offer the right sign-in option
+pub async fn get_status(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+) -> Json<SsoStatus> {
+    let config = &server.sso.config;
+    Json(SsoStatus {
+        enabled: config.enabled,
+        provider: config.enabled.then_some(config.provider),
+        login_url: config.enabled.then(|| match config.provider {
+            SsoProvider::Keystone => client.path(&format!("/api/v{}/login/keystone", versioning::CURRENT_VERSION)),
+            SsoProvider::Oidc => client.path("/auth/sso/login"),
+        }),
+    })
+}
+
+pub async fn keystone_login(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    Json(request): Json<KeystoneLoginRequest>,
+) -> Response {
+    if !server.sso.enabled_for(SsoProvider::Keystone) {
+        return (StatusCode::NOT_FOUND, "Keystone sign-on is not enabled").into_response();
+    }
+    let session = match server.sso.keystone_login(&request).await {
+        Ok(principal) => start_session(&server, principal),
+        Err(e) => return rejected(e),
+    };
+    match session {
+        Ok((token, principal, expires_at)) => auth::session_response(token, &principal, expires_at, &client),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
+
+pub async fn oidc_login(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+) -> Response {
+    if !server.sso.enabled_for(SsoProvider::Oidc) {
+        return (StatusCode::NOT_FOUND, "OIDC sign-on is not enabled").into_response();
+    }
+    match server.sso.oidc_authorize_url().await {
+        Ok((url, state)) => {
+            // Lax so the browser sends it back on the provider's redirect
+            let cookie = format!(
+                "{}={}; {}; Max-Age={}",
+                STATE_COOKIE,
+                state,
+                auth::cookie_attributes(&client, "/auth/sso", "Lax"),
+                STATE_TTL_MINUTES * 60
+            );
+            ([(SET_COOKIE, cookie)], Redirect::to(&url)).into_response()
+        }
+        Err(e) => {
+            warn!("Could not start OIDC sign-on: {}", e);
+            (StatusCode::BAD_GATEWAY, e.to_string()).into_response()
+        }
+    }
+}
+
+pub async fn oidc_callback(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    headers: HeaderMap,
+    Query(callback): Query<OidcCallback>,
+) -> Response {
+    if !server.sso.enabled_for(SsoProvider::Oidc) {
+        return (StatusCode::NOT_FOUND, "OIDC sign-on is not enabled").into_response();
+    }
+    let (code, state) = match (callback.code, callback.state) {
+        (Some(code), Some(state)) => (code, state),
+        _ => {
+            let reason = callback.error.unwrap_or_else(|| "missing code".to_string());
+            return rejected(anyhow::anyhow!("Provider returned no authorization code: {}", reason));
+        }
+    };
+    
+    let cookie_state = state_cookie(&headers);
+    let session = match server.sso.oidc_callback(&code, &state, cookie_state.as_deref()).await {
+        Ok(principal) => start_session(&server, principal),
+        Err(e) => return rejected(e),
+    };
+    match session {
+        Ok((token, _, expires_at)) => {
+            let clear_state = format!(
+                "{}=; {}; Max-Age=0",
+                STATE_COOKIE,
+                auth::cookie_attributes(&client, "/auth/sso", "Lax")
+            );
+            (
+                [
+                    (SET_COOKIE, auth::session_set_cookie(&token, expires_at, &client)),
+                    (SET_COOKIE, clear_state),
+                ],
+                Redirect::to(&client.path("/")),
+            ).into_response()
+        }
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    }
+}
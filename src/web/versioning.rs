@@ -0,0 +1,249 @@
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Query, Request, State},
+    http::{header, uri::PathAndQuery, HeaderMap, HeaderValue, StatusCode, Uri},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Extension, Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tracing::error;
+
+use super::dashboard::DashboardServer;
+use super::proxy::ClientInfo;
+
+// Within a version, responses only ever gain fields and endpoints only get
+// added. Anything that would break an existing client goes into a new
+// version, and the previous one is served alongside it until its sunset.
+pub const CURRENT_VERSION: &str = "1";
+const SUPPORTED_VERSIONS: &[&str] = &["1"];
+
+// Clients may send the versions they understand, e.g. "API-Version: 1";
+// every versioned response says which one it speaks
+const VERSION_HEADER: &str = "api-version";
+
+// Every v1 error body
+#[derive(Debug, Serialize)]
+struct ErrorBody {
+    error: ErrorObject,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorObject {
+    // Stable machine-readable name, e.g. "not_found"
+    code: String,
+    message: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct PageQuery {
+    limit: Option<usize>,
+    // Opaque; the next_cursor of the previous page
+    cursor: Option<String>,
+}
+
+struct Page {
+    offset: usize,
+    limit: Option<usize>,
+}
+
+fn error_response(status: StatusCode, code: &str, message: impl Into<String>) -> Response {
+    let body = ErrorBody {
+        error: ErrorObject {
+            code: code.to_string(),
+            message: message.into(),
+        },
+    };
+    (status, [(VERSION_HEADER, CURRENT_VERSION)], Json(body)).into_response()
+}
+
+// Lists come back as {"items": [...], "next_cursor": ...}, paged with
+// ?limit and ?cursor, and failures as {"error": {"code", "message"}}.
+// Objects and streamed exports pass through untouched.
+pub async fn v1(mut request: Request, next: Next) -> Response {
+    if let Err(requested) = negotiate(request.headers()) {
+        return error_response(
+            StatusCode::NOT_ACCEPTABLE,
+            "unsupported_version",
+            format!("API version {} is not supported; this server speaks {}", requested, SUPPORTED_VERSIONS.join(", ")),
+        );
+    }
+    
+    let page = match page(request.uri()) {
+        Ok(page) => page,
+        Err(message) => return error_response(StatusCode::BAD_REQUEST, "invalid_cursor", message),
+    };
+    widen_limit(&mut request, &page);
+    
+    let mut response = envelope(next.run(request).await, &page).await;
+    response.headers_mut().insert(VERSION_HEADER, HeaderValue::from_static(CURRENT_VERSION));
+    response
+}
+
+// The unversioned routes answer as they always have, pointing at their
+// replacement
+pub async fn deprecated(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let successor = client.path(&format!("/api/v{}{}", CURRENT_VERSION, request.uri().path()));
+    ::metrics::counter!("api_legacy_requests_total").increment(1);
+    
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&format!("<{}>; rel=\"successor-version\"", successor)) {
+        headers.insert(header::LINK, link);
+    }
+    if let Some(sunset) = server.versioning.legacy_sunset {
+        let sunset = sunset.format("%a, %d %b %Y %H:%M:%S GMT").to_string();
+        if let Ok(sunset) = HeaderValue::from_str(&sunset) {
+            headers.insert("sunset", sunset);
+        }
+    }
+    response
+}
+
+// Which versions exist and where they live
+pub async fn list_versions(
+    State(server): State<DashboardServer>,
+    Extension(client): Extension<ClientInfo>,
+) -> impl IntoResponse {
+    let versions: Vec<Value> = SUPPORTED_VERSIONS.iter()
+        .map(|version| json!({
+            "version": version,
+            "path": client.path(&format!("/api/v{}", version)),
+            "status": if *version == CURRENT_VERSION { "current" } else { "supported" },
+        }))
+        .collect();
+    let legacy = server.versioning.legacy_routes.then(|| json!({
+        "path": client.path("/api"),
+        "status": "deprecated",
+        "successor": CURRENT_VERSION,
+        "sunset": server.versioning.legacy_sunset,
+    }));
+    
+    Json(json!({
+        "current": CURRENT_VERSION,
+        "versions": versions,
+        "legacy": legacy,
+    }))
+}
+
+// The newest supported version the client asked for, the current one when
+// it didn't ask; otherwise what it asked for
+fn negotiate(headers: &HeaderMap) -> Result<&'static str, String> {
+    let requested = match headers.get(VERSION_HEADER).and_then(|value| value.to_str().ok()) {
+        Some(requested) => requested,
+        None => return Ok(CURRENT_VERSION),
+    };
+    
+    requested.split(',')
+        .map(|version| version.trim().trim_start_matches(['v', 'V']))
+        .filter_map(|version| SUPPORTED_VERSIONS.iter().find(|supported| **supported == version))
+        .max_by_key(|version| version.parse::<u32>().unwrap_or(0))
+        .copied()
+        .ok_or_else(|| requested.to_string())
+}
+
+fn page(uri: &Uri) -> Result<Page, &'static str> {
+    let query = Query::<PageQuery>::try_from_uri(uri).map_err(|_| "limit must be a non-negative number")?;
+    let offset = match &query.cursor {
+        Some(cursor) => decode_cursor(cursor).ok_or("cursor is not one this server handed out")?,
+        None => 0,
+    };
+    Ok(Page {
+        offset,
+        limit: query.limit,
+    })
+}
+
+// Handlers that cap their own lists (recent decisions) have to return
+// everything up to the end of the requested page
+fn widen_limit(request: &mut Request, page: &Page) {
+    let limit = match page.limit {
+        Some(limit) => limit,
+        None => return,
+    };
+    let uri = request.uri();
+    let mut pairs: Vec<String> = uri.query().unwrap_or("")
+        .split('&')
+        .filter(|pair| !pair.is_empty() && !pair.starts_with("limit=") && !pair.starts_with("cursor="))
+        .map(str::to_string)
+        .collect();
+    pairs.push(format!("limit={}", page.offset + limit));
+    
+    let path_and_query = format!("{}?{}", uri.path(), pairs.join("&"));
+    let mut parts = uri.clone().into_parts();
+    parts.path_and_query = PathAndQuery::try_from(path_and_query).ok();
+    if let Ok(uri) = Uri::from_parts(parts) {
+        *request.uri_mut() = uri;
+    }
+}
+
+async fn envelope(response: Response, page: &Page) -> Response {
+    let status = response.status();
+    let is_json = response.headers().get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let is_error = status.is_client_error() || status.is_server_error();
+    if !(is_error || (status.is_success() && is_json)) {
+        return response;
+    }
+    
+    let (mut parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, usize::MAX).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            error!("Failed to read response body: {}", e);
+            return error_response(StatusCode::INTERNAL_SERVER_ERROR, "internal_server_error", "Failed to read response");
+        }
+    };
+    
+    let body = if is_error {
+        if is_json {
+            // Already structured
+            return Response::from_parts(parts, Body::from(bytes));
+        }
+        let message = String::from_utf8_lossy(&bytes).trim().to_string();
+        let code = status.canonical_reason().unwrap_or("error").to_lowercase().replace([' ', '-'], "_");
+        json!(ErrorBody { error: ErrorObject { code, message } })
+    } else {
+        match serde_json::from_slice::<Value>(&bytes) {
+            Ok(Value::Array(items)) => page.apply(items),
+            _ => return Response::from_parts(parts, Body::from(bytes)),
+        }
+    };
+    
+    parts.headers.remove(header::CONTENT_LENGTH);
+    parts.headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    Response::from_parts(parts, Body::from(body.to_string()))
+}
+
+impl Page {
+    // Without a limit the rest of the list is one page
+    fn apply(&self, items: Vec<Value>) -> Value {
+        let total = items.len();
+        let end = match self.limit {
+            Some(limit) => (self.offset + limit).min(total),
+            None => total,
+        };
+        let next_cursor = (end < total).then(|| encode_cursor(end));
+        let items: Vec<Value> = items.into_iter().skip(self.offset).take(end.saturating_sub(self.offset)).collect();
+        
+        json!({ "items": items, "next_cursor": next_cursor })
+    }
+}
+
+// Cursors are opaque so their encoding can change without breaking clients
+fn encode_cursor(offset: usize) -> String {
+    hex::encode(format!("o:{}", offset))
+}
+
+fn decode_cursor(cursor: &str) -> Option<usize> {
+    let decoded = String::from_utf8(hex::decode(cursor).ok()?).ok()?;
+    decoded.strip_prefix("o:")?.parse().ok()
+}
@@ -1,57 +1,184 @@
-use axum::extract::ws::{Message, WebSocket};
+use axum::extract::ws::{close_code, CloseFrame, Message, WebSocket};
 use futures_util::{SinkExt, StreamExt};
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
-use tokio::sync::{broadcast, RwLock};
-use tracing::{debug, error, info};
+use std::time::Instant;
+use tokio::sync::{broadcast, mpsc, OwnedSemaphorePermit, RwLock, Semaphore};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
+use crate::config::WebSocketConfig;
+use super::dashboard::{AlertSeverity, DashboardState};
+
+// Replies queued for a client that isn't reading are dropped past this
+const DIRECT_QUEUE_SIZE: usize = 32;
+
 pub struct WebSocketHandler {
-    connections: Arc<RwLock<HashMap<String, broadcast::Sender<String>>>>,
-    broadcast_tx: broadcast::Sender<String>,
+    config: WebSocketConfig,
+    connections: Arc<RwLock<HashMap<String, mpsc::Sender<String>>>>,
+    // One permit per open connection
+    slots: Arc<Semaphore>,
+    broadcast_tx: broadcast::Sender<Arc<DashboardState>>,
+}
+
+// Token bucket for the messages a single client sends
+pub(super) struct MessageBudget {
+    tokens: f64,
+    capacity: f64,
+    refill_per_second: f64,
+    updated: Instant,
+}
+
+impl MessageBudget {
+    fn new(config: &WebSocketConfig) -> Self {
+        let capacity = config.message_burst.max(1) as f64;
+        Self {
+            tokens: capacity,
+            capacity,
+            refill_per_second: config.messages_per_minute as f64 / 60.0,
+            updated: Instant::now(),
+        }
+    }
+    
+    pub(super) fn take(&mut self) -> bool {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.updated).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.refill_per_second).min(self.capacity);
+        self.updated = now;
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+// Parts of the dashboard state a client can subscribe to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Topic {
+    Predictions,
+    Metrics,
+    Alerts,
+    Performance,
+}
+
+const ALL_TOPICS: [Topic; 4] = [Topic::Predictions, Topic::Metrics, Topic::Alerts, Topic::Performance];
+
+// Narrows predictions and alerts; empty fields match everything
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateFilter {
+    pub resource_ids: Vec<String>,
+    pub project_id: Option<String>,
+    pub min_severity: Option<AlertSeverity>,
+}
+
+#[derive(Debug, Clone)]
+struct Subscription {
+    // Clients that never subscribe get every topic, as before topics existed
+    topics: HashSet<Topic>,
+    filter: StateFilter,
+}
+
+impl Default for Subscription {
+    fn default() -> Self {
+        Self {
+            topics: ALL_TOPICS.into_iter().collect(),
+            filter: StateFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+    // Replaces the topic set
+    Subscribe { topics: Vec<Topic> },
+    Unsubscribe { topics: Vec<Topic> },
+    Filter { filter: StateFilter },
 }
 
 impl WebSocketHandler {
-    pub fn new() -> Self {
-        let (broadcast_tx, _) = broadcast::channel(1000);
+    pub fn new(config: WebSocketConfig) -> Self {
+        let (broadcast_tx, _) = broadcast::channel(16);
         
         Self {
+            slots: Arc::new(Semaphore::new(config.max_connections)),
+            config,
             connections: Arc::new(RwLock::new(HashMap::new())),
             broadcast_tx,
         }
     }
     
-    pub async fn handle_connection(&self, socket: WebSocket) {
+    // Fresh per connection, GraphQL subscriptions included
+    pub(super) fn message_budget(&self) -> MessageBudget {
+        MessageBudget::new(&self.config)
+    }
+    
+    // Held for the life of a connection; None when every slot is taken
+    pub fn reserve_slot(&self) -> Option<OwnedSemaphorePermit> {
+        let permit = self.slots.clone().try_acquire_owned().ok();
+        if permit.is_none() {
+            warn!("Rejecting WebSocket connection: {} already open", self.config.max_connections);
+            ::metrics::counter!("websocket_connections_rejected_total").increment(1);
+        }
+        permit
+    }
+    
+    pub async fn handle_connection(
+        &self,
+        socket: WebSocket,
+        principal: Option<String>,
+        _slot: OwnedSemaphorePermit,
+        shutdown: CancellationToken,
+    ) {
         let connection_id = Uuid::new_v4().to_string();
-        info!("New WebSocket connection: {}", connection_id);
+        info!(
+            "New WebSocket connection {} from {}",
+            connection_id,
+            principal.as_deref().unwrap_or("anonymous client")
+        );
         
-        let (tx, mut rx) = broadcast::channel(100);
+        let (tx, mut rx) = mpsc::channel(DIRECT_QUEUE_SIZE);
         
         // Add connection to the map
         {
             let mut connections = self.connections.write().await;
             connections.insert(connection_id.clone(), tx.clone());
+            ::metrics::gauge!("websocket_connections").set(connections.len() as f64);
         }
         
         // Subscribe to broadcasts
         let mut broadcast_rx = self.broadcast_tx.subscribe();
+        let subscription = Arc::new(RwLock::new(Subscription::default()));
         
         // Split the socket into sender and receiver
         let (mut sender, mut receiver) = socket.split();
         
         // Handle incoming messages
-        let connections_clone = self.connections.clone();
         let connection_id_clone = connection_id.clone();
+        let client_subscription = subscription.clone();
+        let mut budget = self.message_budget();
         
-        let recv_task = tokio::spawn(async move {
+        let mut recv_task = tokio::spawn(async move {
             while let Some(msg) = receiver.next().await {
                 match msg {
                     Ok(Message::Text(text)) => {
-                        debug!("Received message from {}: {}", connection_id_clone, text);
-                        // Handle client messages (e.g., subscription requests)
-                        if let Err(e) = handle_client_message(&text, &tx).await {
-                            error!("Error handling client message: {}", e);
-                        }
+                        let reply = if budget.take() {
+                            debug!("Received message from {}: {}", connection_id_clone, text);
+                            handle_client_message(&text, &client_subscription).await
+                        } else {
+                            debug!("WebSocket client {} is sending too fast", connection_id_clone);
+                            ::metrics::counter!("websocket_messages_limited_total").increment(1);
+                            json!({ "type": "error", "message": "Rate limit exceeded; message ignored" })
+                        };
+                        // A client that doesn't read its replies just loses them
+                        let _ = tx.try_send(reply.to_string());
                     }
                     Ok(Message::Close(_)) => {
                         info!("WebSocket connection closed: {}", connection_id_clone);
@@ -64,75 +191,163 @@ impl WebSocketHandler {
                     _ => {}
                 }
             }
-            
-            // Remove connection from map
-            let mut connections = connections_clone.write().await;
-            connections.remove(&connection_id_clone);
         });
         
         // Handle outgoing messages
-        let send_task = tokio::spawn(async move {
+        let mut send_task = tokio::spawn(async move {
             loop {
                 tokio::select! {
-                    // Broadcast messages
-                    Ok(msg) = broadcast_rx.recv() => {
+                    // State updates, cut down to what this client asked for
+                    state = broadcast_rx.recv() => {
+                        let state = match state {
+                            Ok(state) => state,
+                            // A slow client skips the updates it missed
+                            Err(broadcast::error::RecvError::Lagged(_)) => continue,
+                            Err(broadcast::error::RecvError::Closed) => break,
+                        };
+                        let msg = match render(&state, &*subscription.read().await) {
+                            Some(msg) => msg,
+                            None => continue,
+                        };
                         if sender.send(Message::Text(msg)).await.is_err() {
                             break;
                         }
                     }
                     // Direct messages to this connection
-                    Ok(msg) = rx.recv() => {
+                    Some(msg) = rx.recv() => {
                         if sender.send(Message::Text(msg)).await.is_err() {
                             break;
                         }
                     }
+                    _ = shutdown.cancelled() => {
+                        let _ = sender.send(Message::Close(Some(going_away()))).await;
+                        break;
+                    }
                 }
             }
         });
         
-        // Wait for either task to complete
+        // Whichever side finishes first takes the other down with it
         tokio::select! {
-            _ = recv_task => {},
-            _ = send_task => {},
+            _ = &mut recv_task => send_task.abort(),
+            _ = &mut send_task => recv_task.abort(),
         }
+        
+        let mut connections = self.connections.write().await;
+        connections.remove(&connection_id);
+        ::metrics::gauge!("websocket_connections").set(connections.len() as f64);
     }
     
-    pub async fn broadcast(&self, message: String) {
-        if let Err(e) = self.broadcast_tx.send(message) {
-            error!("Failed to broadcast message: {}", e);
-        }
+    pub async fn broadcast(&self, state: Arc<DashboardState>) {
+        // Fails only when no client is connected
+        let _ = self.broadcast_tx.send(state);
+        
+        // Connections whose tasks died without cleaning up
+        let mut connections = self.connections.write().await;
+        connections.retain(|_, tx| !tx.is_closed());
+        ::metrics::gauge!("websocket_connections").set(connections.len() as f64);
     }
     
     pub async fn send_to_connection(&self, connection_id: &str, message: String) {
         let connections = self.connections.read().await;
         if let Some(tx) = connections.get(connection_id) {
-            if let Err(e) = tx.send(message) {
+            if let Err(e) = tx.try_send(message) {
                 error!("Failed to send message to connection {}: {}", connection_id, e);
             }
         }
     }
 }
 
-async fn handle_client_message(
-    message: &str,
-    _tx: &broadcast::Sender<String>,
-) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
-    // Parse client messages (e.g., subscription requests, filters)
-    if let Ok(request) = serde_json::from_str::<serde_json::Value>(message) {
-        match request.get("type").and_then(|t| t.as_str()) {
-            Some("subscribe") => {
-                debug!("Client subscribed to updates");
-                // Handle subscription logic
+// Tells clients to reconnect elsewhere when the server shuts down
+pub fn going_away() -> CloseFrame<'static> {
+    CloseFrame {
+        code: close_code::AWAY,
+        reason: "Server shutting down".into(),
+    }
+}
+
+// Applies a subscribe, unsubscribe or filter message and returns the reply
+async fn handle_client_message(message: &str, subscription: &RwLock<Subscription>) -> Value {
+    let request = match serde_json::from_str::<ClientMessage>(message) {
+        Ok(request) => request,
+        Err(e) => {
+            debug!("Unknown message: {}", message);
+            return json!({ "type": "error", "message": format!("Invalid message: {}", e) });
+        }
+    };
+    
+    let mut subscription = subscription.write().await;
+    match request {
+        ClientMessage::Subscribe { topics } => subscription.topics = topics.into_iter().collect(),
+        ClientMessage::Unsubscribe { topics } => {
+            for topic in topics {
+                subscription.topics.remove(&topic);
+            }
+        }
+        ClientMessage::Filter { filter } => subscription.filter = filter,
+    }
+    debug!("Client subscription is now {:?}", subscription);
+    
+    let mut topics: Vec<Topic> = subscription.topics.iter().copied().collect();
+    topics.sort_by_key(|topic| ALL_TOPICS.iter().position(|t| t == topic));
+    json!({ "type": "subscribed", "topics": topics, "filter": subscription.filter })
+}
+
+// Subscribed parts of the state under the same keys as the full state, or
+// None when the client wants nothing
+fn render(state: &DashboardState, subscription: &Subscription) -> Option<String> {
+    if subscription.topics.is_empty() {
+        return None;
+    }
+    let filter = &subscription.filter;
+    let resource_matches = |resource_id: &str| -> bool {
+        if !filter.resource_ids.is_empty() && !filter.resource_ids.iter().any(|id| id == resource_id) {
+            return false;
+        }
+        match &filter.project_id {
+            Some(project) => state.active_predictions.get(resource_id)
+                .is_some_and(|p| p.project_id.as_ref() == Some(project)),
+            None => true,
+        }
+    };
+    
+    let mut message = Map::new();
+    for topic in &subscription.topics {
+        let (key, value) = match topic {
+            Topic::Predictions => {
+                let predictions: HashMap<_, _> = state.active_predictions.iter()
+                    .filter(|(resource_id, _)| resource_matches(resource_id))
+                    .collect();
+                ("active_predictions", serde_json::to_value(predictions))
             }
-            Some("filter") => {
-                debug!("Client requested filter: {:?}", request.get("filter"));
-                // Handle filtering logic
+            Topic::Metrics => ("system_metrics", serde_json::to_value(&state.system_metrics)),
+            Topic::Alerts => {
+                let alerts: Vec<_> = state.alerts.iter()
+                    .filter(|a| filter.min_severity.is_none_or(|min| severity_rank(a.severity) >= severity_rank(min)))
+                    .filter(|a| match &a.resource_id {
+                        Some(resource_id) => resource_matches(resource_id),
+                        // Cluster-wide alerts only when not narrowed to resources
+                        None => filter.resource_ids.is_empty() && filter.project_id.is_none(),
+                    })
+                    .collect();
+                ("alerts", serde_json::to_value(alerts))
             }
-            _ => {
-                debug!("Unknown message type: {}", message);
+            Topic::Performance => ("performance_stats", serde_json::to_value(&state.performance_stats)),
+        };
+        match value {
+            Ok(value) => {
+                message.insert(key.to_string(), value);
             }
+            Err(e) => error!("Failed to serialize {} for WebSocket client: {}", key, e),
         }
     }
-    
-    Ok(())
+    Some(Value::Object(message).to_string())
+}
+
+fn severity_rank(severity: AlertSeverity) -> u8 {
+    match severity {
+        AlertSeverity::Info => 0,
+        AlertSeverity::Warning => 1,
+        AlertSeverity::Critical => 2,
+    }
 }